@@ -1,15 +1,28 @@
 use std::{collections::HashMap, path::Path, sync::Arc};
 
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use futures::future::join_all;
-use log::{debug, warn};
+use log::{debug, info, warn};
 use tokio::sync::RwLock;
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{
-    job_client, load_var_source, new_var_source, project::FeathrProjectImpl,
-    registry_client::api_models, Error, FeathrApiClient, FeathrProject, FeatureRegistry, JobClient,
-    JobId, JobStatus, SubmitJobRequest, VarSource,
+    connectivity, job_client,
+    job_cost::summarize_costs,
+    job_queue::{JobQueue, QUEUE_POLL_INTERVAL},
+    job_retry::{RetryState, ATTEMPT_TAG, RETRY_OF_TAG},
+    observability::{init_tracing, ObservabilityConfig},
+    open_lineage::{LineageRun, OpenLineageClient},
+    presign,
+    project::FeathrProjectImpl,
+    registry_client::api_models,
+    schema, try_load_var_source, try_load_var_source_with_overrides,
+    try_load_var_source_with_profile, try_new_var_source, try_new_var_source_with_overrides,
+    ConnectionTestResult, ConsumptionInfo, CostSummary, DataLocation, Error, FeathrApiClient,
+    FeathrProject, FeatureRegistry, JobClient, JobCost, JobHandle, JobId, JobManifest, JobStatus,
+    MaterializationInfo, PriceTable, QueueStatus, RetryPolicy, Source, SourceSchema,
+    SubmitJobRequest, TemplateSnippet, UrlPermissions, VarSource, DEFAULT_MAX_FEATURE_CONFIG_SIZE,
 };
 
 #[derive(Clone, Debug)]
@@ -27,15 +40,131 @@ impl FeathrClient {
         })
     }
 
+    /**
+     * Load the client with the config file, values in `overrides` take precedence over the
+     * environment variables and the config file, see `dump_config` for the precedence order
+     */
+    pub async fn load_with_overrides<T>(
+        conf_file: T,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, Error>
+    where
+        T: AsRef<Path>,
+    {
+        FeathrClientImpl::load_with_overrides(conf_file, overrides)
+            .await
+            .map(|inner| Self {
+                inner: Arc::new(inner),
+            })
+    }
+
+    /**
+     * Load the client with the settings of the named profile in the config file merged over the
+     * common settings, the profile takes precedence over `FEATHR_PROFILE`
+     */
+    pub async fn load_profile<T>(conf_file: T, profile: &str) -> Result<Self, Error>
+    where
+        T: AsRef<Path>,
+    {
+        Self::load_profile_with_overrides(conf_file, Some(profile), Default::default()).await
+    }
+
+    /**
+     * Same as `load_profile`, the profile is selected by `FEATHR_PROFILE` if `profile` is `None`,
+     * values in `overrides` take precedence over all settings
+     */
+    pub async fn load_profile_with_overrides<T>(
+        conf_file: T,
+        profile: Option<&str>,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, Error>
+    where
+        T: AsRef<Path>,
+    {
+        FeathrClientImpl::load_profile_with_overrides(conf_file, profile, overrides)
+            .await
+            .map(|inner| Self {
+                inner: Arc::new(inner),
+            })
+    }
+
     pub async fn from_str(content: &str) -> Result<Self, Error> {
         FeathrClientImpl::from_str(content).await.map(|inner| Self {
             inner: Arc::new(inner),
         })
     }
 
+    /**
+     * Same as `from_str`, values in `overrides` take precedence over the environment variables and
+     * the config content
+     */
+    pub async fn from_str_with_overrides(
+        content: &str,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        FeathrClientImpl::from_str_with_overrides(content, overrides)
+            .await
+            .map(|inner| Self {
+                inner: Arc::new(inner),
+            })
+    }
+
+    /**
+     * The Databricks job client if `spark_config.spark_cluster` is `databricks`, e.g. to replace its
+     * token provider
+     */
+    pub fn get_databricks_job_client(&self) -> Option<Arc<crate::DatabricksClient>> {
+        match &self.inner.job_client {
+            job_client::Client::Databricks(c) => Some(c.clone()),
+            _ => None,
+        }
+    }
+
+    /**
+     * The mock job client if `spark_config.spark_cluster` is `mock`, to script job statuses and
+     * inspect submitted jobs
+     */
+    #[cfg(feature = "testing")]
+    pub fn get_mock_job_client(&self) -> Option<Arc<crate::MockJobClient>> {
+        match &self.inner.job_client {
+            job_client::Client::Mock(c) => Some(c.clone()),
+            _ => None,
+        }
+    }
+
+    /**
+     * The effective config in YAML with secrets masked, a key is resolved from the first layer having it:
+     * 1. Overrides passed to `load_with_overrides`
+     * 2. Environment variables, `FEATHR__SECTION__KEY` first, then `SECTION__KEY`
+     * 3. Azure KeyVault if `KEY_VAULT_NAME` is set, these values are not included in the dump
+     * 4. The config file, with the settings of the selected profile merged over the common ones
+     * 5. Built-in defaults
+     */
+    pub fn dump_config(&self) -> Result<String, Error> {
+        self.inner.dump_config()
+    }
+
+    #[instrument(skip_all, fields(project = name))]
     pub async fn load_project(&self, name: &str) -> Result<FeathrProject, Error> {
         if let Some(r) = self.inner.get_registry_client() {
-            let lineage = r.load_project(name).await?;
+            let lineage = r.load_project(name, None).await?;
+            self.load_project_from_lineage(lineage)
+        } else {
+            Err(Error::DetachedClient)
+        }
+    }
+
+    /**
+     * Load the entity versions pinned by the release instead of the latest ones
+     */
+    #[instrument(skip_all, fields(project = name, release = release))]
+    pub async fn load_project_release(
+        &self,
+        name: &str,
+        release: &str,
+    ) -> Result<FeathrProject, Error> {
+        if let Some(r) = self.inner.get_registry_client() {
+            let lineage = r.load_project(name, Some(release)).await?;
             self.load_project_from_lineage(lineage)
         } else {
             Err(Error::DetachedClient)
@@ -67,28 +196,71 @@ impl FeathrClient {
         &self,
         name: &str,
         tags: HashMap<String, String>,
+    ) -> Result<FeathrProject, Error> {
+        self.new_project_with_default_tags(name, tags, Default::default())
+            .await
+    }
+
+    /**
+     * Create a project whose sources, anchor groups and features inherit `default_tags`, tags
+     * added to an entity override the inherited ones.
+     * The registry keeps the default tags of the project when it's first created
+     */
+    #[instrument(skip_all, fields(project = name))]
+    pub async fn new_project_with_default_tags(
+        &self,
+        name: &str,
+        tags: HashMap<String, String>,
+        default_tags: HashMap<String, String>,
     ) -> Result<FeathrProject, Error> {
         let (id, version) = if let Some(r) = self.inner.get_registry_client() {
             let def = api_models::ProjectDef {
                 name: name.to_string(),
                 tags,
+                ownership: Default::default(),
+                default_tags: default_tags.clone(),
             };
             r.new_project(def).await?
         } else {
             warn!("The project {} is created in detached mode, all changes will not be able to be stored to the registry", name);
             (Uuid::new_v4(), 1)
         };
-        Ok(FeathrProject::new(self.inner.clone(), name, id, version).await)
+        let project = FeathrProject::new(self.inner.clone(), name, id, version).await;
+        project.inner.write().await.default_tags = default_tags;
+        Ok(project)
     }
 
-    pub async fn submit_job(&self, request: SubmitJobRequest) -> Result<JobId, Error> {
+    pub async fn submit_job(&self, request: SubmitJobRequest) -> Result<JobHandle, Error> {
         self.inner.submit_job(request).await
     }
 
-    pub async fn submit_jobs(&self, requests: Vec<SubmitJobRequest>) -> Result<Vec<JobId>, Error> {
+    /**
+     * Same as `submit_job` but failed jobs are resubmitted by `wait_for_job` with the given policy
+     * instead of `spark_config.retry`, `None` disables retries for this job
+     */
+    pub async fn submit_job_with_retry(
+        &self,
+        request: SubmitJobRequest,
+        policy: Option<RetryPolicy>,
+    ) -> Result<JobHandle, Error> {
+        self.inner.submit_job_with_retry(request, policy).await
+    }
+
+    /**
+     * Submit all requests, the result of each request is returned in the same order, so the
+     * handles of submitted jobs are kept even if some submissions failed
+     */
+    pub async fn submit_jobs(
+        &self,
+        requests: Vec<SubmitJobRequest>,
+    ) -> Result<Vec<Result<JobHandle, Error>>, Error> {
         self.inner.submit_jobs(requests).await
     }
 
+    /**
+     * Wait for the job and return its driver log, a failed job is resubmitted if it's submitted
+     * with a retry policy, the timeout applies to each attempt and the log is from the last attempt
+     */
     pub async fn wait_for_job(
         &self,
         job_id: JobId,
@@ -97,6 +269,43 @@ impl FeathrClient {
         self.inner.wait_for_job(job_id, timeout).await
     }
 
+    /**
+     * All attempts of a job submitted with a retry policy, `job_id` is the id of the first attempt
+     */
+    pub async fn get_job_attempts(&self, job_id: JobId) -> Vec<JobHandle> {
+        self.inner.get_job_attempts(job_id).await
+    }
+
+    /**
+     * Manifest of a job submitted by this client
+     */
+    pub async fn get_manifest(&self, job_id: JobId) -> Option<JobManifest> {
+        self.inner.get_manifest(job_id).await
+    }
+
+    /**
+     * Save the manifest of a job submitted by this client next to the job output, or in the
+     * workspace if the job has no output on the storage, returns the URL of the manifest
+     */
+    pub async fn save_manifest(&self, job_id: JobId) -> Result<String, Error> {
+        self.inner.save_manifest(job_id).await
+    }
+
+    /**
+     * Load a manifest saved by `save_manifest`, `path` can be a local path or a URL on the storage
+     */
+    pub async fn load_manifest(&self, path: &str) -> Result<JobManifest, Error> {
+        self.inner.load_manifest(path).await
+    }
+
+    /**
+     * Submit the job in the manifest again with the same configs, the job runs with the
+     * credentials in the config of this client
+     */
+    pub async fn resubmit(&self, manifest: JobManifest) -> Result<JobHandle, Error> {
+        self.inner.submit_job(manifest.request).await
+    }
+
     pub async fn wait_for_jobs(
         &self,
         job_ids: Vec<JobId>,
@@ -105,6 +314,22 @@ impl FeathrClient {
         self.inner.wait_for_jobs(job_ids, timeout).await
     }
 
+    /**
+     * Resources and estimated cost of a job submitted by this client, priced by
+     * `spark_config.cost`. `None` if the job hasn't been observed as ended by `wait_for_job` or
+     * `get_job_status` yet
+     */
+    pub async fn get_job_cost(&self, job_id: JobId) -> Option<JobCost> {
+        self.inner.get_job_cost(job_id).await
+    }
+
+    /**
+     * Costs of the ended jobs submitted by this client, aggregated per project and month
+     */
+    pub async fn get_monthly_costs(&self) -> Vec<CostSummary> {
+        self.inner.get_monthly_costs().await
+    }
+
     pub fn get_remote_url(&self, path: &str) -> String {
         self.inner.get_remote_url(path)
     }
@@ -116,6 +341,61 @@ impl FeathrClient {
     pub async fn get_job_output_url(&self, job_id: JobId) -> Result<Option<String>, crate::Error> {
         self.inner.get_job_output_url(job_id).await
     }
+
+    pub async fn get_job_metadata(&self, job_id: JobId) -> Result<HashMap<String, String>, Error> {
+        self.inner.get_job_metadata(job_id).await
+    }
+
+    pub async fn get_job_url(&self, job_id: JobId) -> Result<Option<String>, Error> {
+        self.inner.get_job_url(job_id).await
+    }
+
+    /**
+     * Generate a read-only URL of the job output that expires after `ttl`, the output can be
+     * downloaded without storage account keys.
+     * Returns `None` if the job has no output.
+     */
+    pub async fn get_output_download_url(
+        &self,
+        job_id: JobId,
+        ttl: Duration,
+    ) -> Result<Option<String>, Error> {
+        self.get_output_download_url_with_permissions(job_id, ttl, Default::default())
+            .await
+    }
+
+    pub async fn get_output_download_url_with_permissions(
+        &self,
+        job_id: JobId,
+        ttl: Duration,
+        permissions: UrlPermissions,
+    ) -> Result<Option<String>, Error> {
+        self.inner
+            .get_output_download_url(job_id, ttl, permissions)
+            .await
+    }
+
+    /**
+     * Check if the source is reachable from this machine with the credentials in the config
+     */
+    pub async fn test_source_connection(&self, source: &Source) -> ConnectionTestResult {
+        self.inner.test_connection(&source.get_location()).await
+    }
+
+    /**
+     * Read the schema of a HDFS source from the Parquet footer or the CSV header on the storage
+     */
+    pub async fn infer_source_schema(&self, source: &Source) -> Result<SourceSchema, Error> {
+        self.inner.infer_schema(&source.get_location()).await
+    }
+
+    /**
+     * Jobs submitted by this client that are still running, and requests waiting for a free slot
+     * when `max_concurrent_jobs` is set
+     */
+    pub async fn queue_status(&self) -> QueueStatus {
+        self.inner.queue_status().await
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -123,6 +403,20 @@ pub struct FeathrClientImpl {
     job_client: job_client::Client,
     registry_client: Option<Arc<FeathrApiClient>>,
     var_source: Arc<dyn VarSource + Send + Sync>,
+    materializations: Arc<RwLock<HashMap<JobId, MaterializationInfo>>>,
+    consumptions: Arc<RwLock<HashMap<JobId, ConsumptionInfo>>>,
+    manifests: Arc<RwLock<HashMap<JobId, JobManifest>>>,
+    job_queue: Arc<JobQueue>,
+    open_lineage: Option<Arc<OpenLineageClient>>,
+    lineage_runs: Arc<RwLock<HashMap<JobId, (LineageRun, JobHandle)>>>,
+    retry_policy: Option<RetryPolicy>,
+    retries: Arc<RwLock<HashMap<JobId, RetryState>>>,
+    /// Keyed by the id of the first attempt
+    attempts: Arc<RwLock<HashMap<JobId, Vec<JobHandle>>>>,
+    prices: PriceTable,
+    /// Jobs submitted by this client and not known to be ended yet
+    unfinished: Arc<RwLock<HashMap<JobId, JobHandle>>>,
+    costs: Arc<RwLock<HashMap<JobId, JobCost>>>,
 }
 
 impl FeathrClientImpl {
@@ -130,59 +424,479 @@ impl FeathrClientImpl {
     where
         T: AsRef<Path>,
     {
-        let var_source = load_var_source(conf_file);
-        Ok(Self {
-            job_client: job_client::Client::from_var_source(var_source.clone()).await?,
-            registry_client: FeathrApiClient::from_var_source(var_source.clone())
-                .await
-                .ok()
-                .map(Arc::new),
-            var_source,
-        })
+        let var_source = try_load_var_source(conf_file)?;
+        Self::from_var_source(var_source).await
+    }
+
+    pub async fn load_with_overrides<T>(
+        conf_file: T,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, Error>
+    where
+        T: AsRef<Path>,
+    {
+        let var_source = try_load_var_source_with_overrides(conf_file, overrides)?;
+        Self::from_var_source(var_source).await
+    }
+
+    pub async fn load_profile_with_overrides<T>(
+        conf_file: T,
+        profile: Option<&str>,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, Error>
+    where
+        T: AsRef<Path>,
+    {
+        let var_source = try_load_var_source_with_profile(conf_file, profile, overrides)?;
+        Self::from_var_source(var_source).await
     }
 
     pub async fn from_str(content: &str) -> Result<Self, Error> {
-        let var_source = new_var_source(content);
+        let var_source = try_new_var_source(content)?;
+        Self::from_var_source(var_source).await
+    }
+
+    pub async fn from_str_with_overrides(
+        content: &str,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        let var_source = try_new_var_source_with_overrides(content, overrides)?;
+        Self::from_var_source(var_source).await
+    }
+
+    async fn from_var_source(var_source: Arc<dyn VarSource + Send + Sync>) -> Result<Self, Error> {
+        if let Some(config) = ObservabilityConfig::from_var_source(var_source.clone()).await? {
+            init_tracing(&config)?;
+        }
+        let job_client = job_client::Client::from_var_source(var_source.clone()).await?;
+        let job_queue =
+            JobQueue::from_var_source(job_client.get_backend(), var_source.clone()).await?;
         Ok(Self {
-            job_client: job_client::Client::from_var_source(var_source.clone()).await?,
+            job_client,
             registry_client: FeathrApiClient::from_var_source(var_source.clone())
                 .await
                 .ok()
                 .map(Arc::new),
+            open_lineage: OpenLineageClient::from_var_source(var_source.clone())
+                .await
+                .map(Arc::new),
             var_source,
+            materializations: Default::default(),
+            consumptions: Default::default(),
+            manifests: Default::default(),
+            job_queue: Arc::new(job_queue),
+            lineage_runs: Default::default(),
+            retry_policy: RetryPolicy::from_var_source(var_source.clone()).await?,
+            retries: Default::default(),
+            attempts: Default::default(),
+            prices: PriceTable::from_var_source(var_source.clone()).await?,
+            unfinished: Default::default(),
+            costs: Default::default(),
         })
     }
 
+    pub fn dump_config(&self) -> Result<String, Error> {
+        Ok(serde_yaml::to_string(&self.var_source.dump_config())?)
+    }
+
     pub fn get_registry_client(&self) -> Option<Arc<FeathrApiClient>> {
         self.registry_client.clone()
     }
 
-    pub async fn submit_job(&self, request: SubmitJobRequest) -> Result<JobId, Error> {
-        self.job_client
+    /**
+     * Template snippets and variables set in `spark_config`, applied to every job built from
+     * projects attached to this client
+     */
+    pub async fn get_template_snippets(
+        &self,
+    ) -> Result<(Vec<TemplateSnippet>, HashMap<String, String>), Error> {
+        let snippets = match self
+            .var_source
+            .get_environment_variable(&["spark_config", "template_snippets"])
+            .await
+        {
+            Ok(s) if !s.trim().is_empty() => serde_yaml::from_str(&s).map_err(|e| {
+                Error::InvalidConfig(format!("Invalid `spark_config.template_snippets`: {}", e))
+            })?,
+            _ => vec![],
+        };
+        let variables = match self
+            .var_source
+            .get_environment_variable(&["spark_config", "template_variables"])
+            .await
+        {
+            Ok(s) if !s.trim().is_empty() => serde_yaml::from_str(&s).map_err(|e| {
+                Error::InvalidConfig(format!("Invalid `spark_config.template_variables`: {}", e))
+            })?,
+            _ => Default::default(),
+        };
+        Ok((snippets, variables))
+    }
+
+    /**
+     * Generation jobs with feature configs larger than `spark_config.max_feature_config_size` bytes
+     * are split by anchor group, `0` disables splitting
+     */
+    pub async fn get_max_feature_config_size(&self) -> Result<usize, Error> {
+        match self
+            .var_source
+            .get_environment_variable(&["spark_config", "max_feature_config_size"])
+            .await
+        {
+            Ok(s) if !s.trim().is_empty() => s.trim().parse().map_err(|_| {
+                Error::InvalidOption("spark_config.max_feature_config_size".to_string(), s)
+            }),
+            _ => Ok(DEFAULT_MAX_FEATURE_CONFIG_SIZE),
+        }
+    }
+
+    /**
+     * Submit the job with the retry policy in `spark_config.retry`
+     */
+    pub async fn submit_job(&self, request: SubmitJobRequest) -> Result<JobHandle, Error> {
+        self.submit_job_with_retry(request, self.retry_policy.clone())
+            .await
+    }
+
+    pub async fn submit_job_with_retry(
+        &self,
+        request: SubmitJobRequest,
+        policy: Option<RetryPolicy>,
+    ) -> Result<JobHandle, Error> {
+        let handle = self.submit(request).await?;
+        if let Some(policy) = policy.filter(|p| p.max_retries > 0) {
+            self.retries.write().await.insert(
+                handle.id,
+                RetryState {
+                    policy,
+                    first: handle.id,
+                    attempt: 1,
+                },
+            );
+            self.attempts
+                .write()
+                .await
+                .insert(handle.id, vec![handle.clone()]);
+        }
+        Ok(handle)
+    }
+
+    /**
+     * Submit the job once a slot is free if `max_concurrent_jobs` is set, the request leaves the
+     * queue if the returned future is dropped before the job is submitted
+     */
+    #[instrument(skip_all, fields(job.name = %request.name, job.id = tracing::field::Empty))]
+    async fn submit(&self, request: SubmitJobRequest) -> Result<JobHandle, Error> {
+        let mut ticket = self.job_queue.enqueue(&request.name);
+        while !ticket.try_start() {
+            self.job_queue.wait(QUEUE_POLL_INTERVAL).await;
+            self.check_running_jobs(self.job_queue.jobs_to_check())
+                .await;
+        }
+        let materialization = request.materialization.clone();
+        let consumption = request.consumption.clone();
+        let manifest = JobManifest::new(&request);
+        let lineage_run = LineageRun::new(&request.name, request.lineage.clone());
+        let handle = match self
+            .job_client
             .submit_job(self.var_source.clone(), request)
             .await
+        {
+            Ok(handle) => handle,
+            Err(e) => {
+                if let Some(c) = &self.open_lineage {
+                    c.submission_failed(&lineage_run, &e).await;
+                }
+                return Err(e);
+            }
+        };
+        ticket.started(&handle);
+        tracing::Span::current().record("job.id", &tracing::field::display(handle.id));
+        if let Some(m) = materialization {
+            self.materializations.write().await.insert(handle.id, m);
+        }
+        if let Some(c) = consumption {
+            self.consumptions.write().await.insert(handle.id, c);
+        }
+        self.manifests.write().await.insert(handle.id, manifest);
+        self.unfinished
+            .write()
+            .await
+            .insert(handle.id, handle.clone());
+        if let Some(c) = &self.open_lineage {
+            c.started(&lineage_run, &handle).await;
+            self.lineage_runs
+                .write()
+                .await
+                .insert(handle.id, (lineage_run, handle.clone()));
+        }
+        Ok(handle)
     }
 
-    pub async fn submit_jobs(&self, requests: Vec<SubmitJobRequest>) -> Result<Vec<JobId>, Error> {
-        let mut ret = vec![];
-        for request in requests.into_iter() {
-            ret.push(
-                self.job_client
-                    .submit_job(self.var_source.clone(), request)
-                    .await?,
-            )
+    /**
+     * All requests are queued at once in the given order, so the queue positions are reported
+     * by `queue_status` while they're waiting.
+     * Feature configs shared by the requests are uploaded only once before queueing, nothing is
+     * submitted if the upload failed.
+     */
+    pub async fn submit_jobs(
+        &self,
+        mut requests: Vec<SubmitJobRequest>,
+    ) -> Result<Vec<Result<JobHandle, Error>>, Error> {
+        self.job_client.share_feature_configs(&mut requests).await?;
+        Ok(join_all(requests.into_iter().map(|request| self.submit_job(request))).await)
+    }
+
+    /**
+     * Release the slots of ended jobs so queued requests can be submitted
+     */
+    async fn check_running_jobs(&self, job_ids: Vec<JobId>) {
+        for job_id in job_ids {
+            match self.job_client.get_job_status(job_id).await {
+                Ok(status) if status.is_ended() => self.job_queue.job_ended(job_id),
+                Ok(_) => {}
+                Err(e) => debug!("Failed to get status of job {}, error: {}", job_id, e),
+            }
         }
-        Ok(ret)
     }
 
+    pub async fn queue_status(&self) -> QueueStatus {
+        let running = self.job_queue.status().running;
+        self.check_running_jobs(running.into_iter().map(|h| h.id).collect())
+            .await;
+        self.job_queue.status()
+    }
+
+    #[instrument(skip_all, fields(job.id = %job_id))]
     pub async fn wait_for_job(
         &self,
         job_id: JobId,
         timeout: Option<Duration>,
     ) -> Result<String, Error> {
-        let status = self.job_client.wait_for_job(job_id, timeout).await?;
-        debug!("Job {} completed with status {}", job_id, status);
-        self.job_client.get_job_log(job_id).await
+        let mut job_id = job_id;
+        loop {
+            let status = self.job_client.wait_for_job(job_id, timeout).await?;
+            debug!("Job {} completed with status {}", job_id, status);
+            self.job_queue.job_ended(job_id);
+            self.register_materialization(job_id, status).await;
+            if status == JobStatus::Success {
+                self.register_consumption(job_id).await;
+            }
+            self.emit_run_ended(job_id, status).await;
+            self.record_cost(job_id).await;
+            let log = self.job_client.get_job_log(job_id).await;
+            match self.retry_failed_job(job_id, status, &log).await? {
+                Some(handle) => job_id = handle.id,
+                None => return log,
+            }
+        }
+    }
+
+    /**
+     * Resubmit the ended job if it failed and its retry policy allows, the new attempt is tagged
+     * with the id of the first attempt and the attempt number
+     */
+    async fn retry_failed_job(
+        &self,
+        job_id: JobId,
+        status: JobStatus,
+        log: &Result<String, Error>,
+    ) -> Result<Option<JobHandle>, Error> {
+        let state = match self.retries.write().await.remove(&job_id) {
+            Some(state) if status == JobStatus::Failed => state,
+            _ => return Ok(None),
+        };
+        let driver_log = log.as_deref().unwrap_or_default();
+        if !state.policy.should_retry(state.attempt, driver_log) {
+            info!(
+                "Job {} failed at attempt {}, not retrying",
+                job_id, state.attempt
+            );
+            return Ok(None);
+        }
+        let manifest = self
+            .get_manifest(job_id)
+            .await
+            .ok_or(Error::UnknownJob(job_id.0))?;
+        let delay = state.policy.delay(state.attempt);
+        info!(
+            "Job {} failed at attempt {}, resubmitting in {:?}",
+            job_id, state.attempt, delay
+        );
+        tracing::info!(
+            job.id = %job_id,
+            job.retry_of = %state.first,
+            job.attempt = state.attempt,
+            "Resubmitting failed job"
+        );
+        tokio::time::sleep(delay).await;
+        let mut request = manifest.request;
+        request
+            .job_tags
+            .insert(RETRY_OF_TAG.to_string(), state.first.to_string());
+        request
+            .job_tags
+            .insert(ATTEMPT_TAG.to_string(), (state.attempt + 1).to_string());
+        let handle = self.submit(request).await?;
+        self.attempts
+            .write()
+            .await
+            .entry(state.first)
+            .or_default()
+            .push(handle.clone());
+        self.retries.write().await.insert(
+            handle.id,
+            RetryState {
+                attempt: state.attempt + 1,
+                ..state
+            },
+        );
+        Ok(Some(handle))
+    }
+
+    pub async fn get_job_attempts(&self, job_id: JobId) -> Vec<JobHandle> {
+        self.attempts
+            .read()
+            .await
+            .get(&job_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn get_manifest(&self, job_id: JobId) -> Option<JobManifest> {
+        self.manifests.read().await.get(&job_id).cloned()
+    }
+
+    pub async fn save_manifest(&self, job_id: JobId) -> Result<String, Error> {
+        let manifest = self
+            .get_manifest(job_id)
+            .await
+            .ok_or(Error::UnknownJob(job_id.0))?;
+        let url = match manifest.output_file_name() {
+            Some(url) if self.job_client.is_url_on_storage(&url) => url,
+            _ => self.job_client.get_remote_url(&manifest.config_file_name()),
+        };
+        debug!("Saving manifest of job {} to {}", job_id, url);
+        self.job_client
+            .write_remote_file(&url, manifest.to_json()?.as_bytes())
+            .await
+    }
+
+    pub async fn load_manifest(&self, path: &str) -> Result<JobManifest, Error> {
+        let content = if self.job_client.is_url_on_storage(path) {
+            String::from_utf8_lossy(&self.job_client.read_remote_file(path).await?).to_string()
+        } else {
+            tokio::fs::read_to_string(path).await?
+        };
+        JobManifest::from_json(&content)
+    }
+
+    /**
+     * Send the COMPLETE or FAIL event of the job submitted by this client
+     */
+    async fn emit_run_ended(&self, job_id: JobId, status: JobStatus) {
+        if let Some(c) = &self.open_lineage {
+            if let Some((run, handle)) = self.lineage_runs.write().await.remove(&job_id) {
+                c.ended(&run, &handle, status).await;
+            }
+        }
+    }
+
+    /**
+     * Estimate the cost of the ended job, the job is only recorded the first time it's observed
+     * as ended
+     */
+    async fn record_cost(&self, job_id: JobId) {
+        let handle = match self.unfinished.write().await.remove(&job_id) {
+            Some(handle) => handle,
+            None => return,
+        };
+        if let Some(manifest) = self.get_manifest(job_id).await {
+            let cost = JobCost::new(&handle, &manifest.request, Utc::now(), &self.prices);
+            debug!(
+                "Job {} ran for {:?}, estimated cost is {:.2} {}",
+                job_id, cost.runtime, cost.estimated_cost, cost.currency
+            );
+            self.costs.write().await.insert(job_id, cost);
+        }
+    }
+
+    pub async fn get_job_cost(&self, job_id: JobId) -> Option<JobCost> {
+        self.costs.read().await.get(&job_id).cloned()
+    }
+
+    pub async fn get_monthly_costs(&self) -> Vec<CostSummary> {
+        summarize_costs(self.costs.read().await.values())
+    }
+
+    /**
+     * Record which sinks hold which features after a feature generation job succeeded, the
+     * pending record is dropped once the job ended in any status
+     */
+    async fn register_materialization(&self, job_id: JobId, status: JobStatus) {
+        let info = match self.materializations.write().await.remove(&job_id) {
+            Some(info) if status == JobStatus::Success => info,
+            _ => return,
+        };
+        let registry = match self.get_registry_client() {
+            Some(r) => r,
+            None => return,
+        };
+        let name = format!(
+            "materialization_{}",
+            info.window_end.format("%Y%m%d%H%M%S")
+        );
+        for (idx, sink) in info.sinks.iter().enumerate() {
+            let def = api_models::MaterializationRecordDef {
+                name: if info.sinks.len() > 1 {
+                    format!("{}_{}", name, idx)
+                } else {
+                    name.clone()
+                },
+                sink: sink.get_location(),
+                features: info.features.clone(),
+                window_start: info.window_start,
+                window_end: info.window_end,
+                job_id: job_id.to_string(),
+                tags: info.tags.clone(),
+            };
+            if let Err(e) = registry
+                .new_materialization_record(info.project_id, def)
+                .await
+            {
+                warn!(
+                    "Failed to register materialization record for job {}, error: {}",
+                    job_id, e
+                );
+            }
+        }
+    }
+
+    /**
+     * Record the output of a feature joining job as a dataset consuming the joined features
+     */
+    async fn register_consumption(&self, job_id: JobId) {
+        let info = match self.consumptions.write().await.remove(&job_id) {
+            Some(info) => info,
+            None => return,
+        };
+        let registry = match self.get_registry_client() {
+            Some(r) => r,
+            None => return,
+        };
+        let def = api_models::DatasetDef {
+            name: dataset_name(&info.output),
+            path: info.output,
+            features: info.features,
+            job_id: job_id.to_string(),
+            tags: Default::default(),
+        };
+        if let Err(e) = registry.new_dataset(info.project_id, def).await {
+            warn!(
+                "Failed to register consumed features for job {}, error: {}",
+                job_id, e
+            );
+        }
     }
 
     pub async fn wait_for_jobs(
@@ -198,7 +912,14 @@ impl FeathrClientImpl {
     }
 
     pub async fn get_job_status(&self, job_id: JobId) -> Result<JobStatus, Error> {
-        self.job_client.get_job_status(job_id).await
+        let status = self.job_client.get_job_status(job_id).await?;
+        if status.is_ended() {
+            self.job_queue.job_ended(job_id);
+            self.register_materialization(job_id, status).await;
+            self.emit_run_ended(job_id, status).await;
+            self.record_cost(job_id).await;
+        }
+        Ok(status)
     }
 
     pub fn get_remote_url(&self, path: &str) -> String {
@@ -208,6 +929,57 @@ impl FeathrClientImpl {
     pub async fn get_job_output_url(&self, job_id: JobId) -> Result<Option<String>, crate::Error> {
         self.job_client.get_job_output_url(job_id).await
     }
+
+    pub async fn get_job_metadata(&self, job_id: JobId) -> Result<HashMap<String, String>, Error> {
+        self.job_client.get_job_metadata(job_id).await
+    }
+
+    pub async fn get_job_url(&self, job_id: JobId) -> Result<Option<String>, Error> {
+        self.job_client.get_job_url(job_id).await
+    }
+
+    pub async fn get_output_download_url(
+        &self,
+        job_id: JobId,
+        ttl: Duration,
+        permissions: UrlPermissions,
+    ) -> Result<Option<String>, Error> {
+        match self.get_job_output_url(job_id).await? {
+            Some(url) => Ok(Some(
+                presign::get_download_url(self.var_source.clone(), &url, ttl, permissions).await?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn test_connection(&self, location: &DataLocation) -> ConnectionTestResult {
+        connectivity::test_location(self.var_source.clone(), location).await
+    }
+
+    pub async fn infer_schema(&self, location: &DataLocation) -> Result<SourceSchema, Error> {
+        schema::infer_schema(&self.job_client, location).await
+    }
+}
+
+/**
+ * Dataset name derived from the last segment of the output path, i.e. `abfss://c@a.dfs.core.windows.net/demo/train.avro` becomes `train`
+ */
+fn dataset_name(output: &str) -> String {
+    let segment = output
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default();
+    let stem = segment.split('.').next().unwrap_or_default();
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.is_empty() {
+        "dataset".to_string()
+    } else {
+        name
+    }
 }
 
 #[cfg(test)]
@@ -288,13 +1060,27 @@ mod tests {
             .build()
             .unwrap();
         for r in reqs.iter() {
-            println!("{}:\n{}", r.job_config_file_name, r.feature_config);
-            println!("{}:\n{}", r.job_config_file_name, r.gen_job_config);
+            println!(
+                "{}:\n{}",
+                r.job_config_file_name,
+                r.feature_config.render().unwrap()
+            );
+            println!(
+                "{}:\n{}",
+                r.job_config_file_name,
+                r.job_config.render().unwrap()
+            );
         }
 
-        let job_ids = client.submit_jobs(reqs).await.unwrap();
+        let job_ids = client
+            .submit_jobs(reqs)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
 
-        let finished = job_ids.iter().map(|&id| client.wait_for_job(id, None));
+        let finished = job_ids.iter().map(|h| client.wait_for_job(h.id, None));
         let outputs: Vec<String> = join_all(finished)
             .await
             .into_iter()
@@ -443,7 +1229,7 @@ mod tests {
 
         println!(
             "features.conf:\n{}",
-            proj.get_feature_config().await.unwrap()
+            proj.get_feature_config().await.unwrap().render().unwrap()
         );
 
         let output = client.get_remote_url("a-output.bin");
@@ -467,8 +1253,12 @@ mod tests {
 
         println!(
             "features_join.conf:\n{}",
-            proj.get_feature_join_config(&ob, &[&anchor_query, &derived_query], &output)
-                .unwrap()
+            serde_json::to_string_pretty(
+                &proj
+                    .get_feature_join_config(&ob, &[&anchor_query, &derived_query], &output)
+                    .unwrap()
+            )
+            .unwrap()
         );
 
         let req = proj
@@ -478,11 +1268,12 @@ mod tests {
             .python_file("test-script/testudf.py")
             .output_location(output.parse().unwrap())
             .unwrap()
-            .build();
+            .build()
+            .unwrap();
 
         println!("Request: {:#?}", req);
 
-        let id = client.submit_job(req).await.log().unwrap();
+        let id = client.submit_job(req).await.log().unwrap().id;
 
         let log = client.wait_for_job(id, None).await.unwrap();
 
@@ -508,7 +1299,7 @@ mod tests {
         let proj = client.load_project("p1").await.unwrap();
         println!(
             "features.conf:\n{}",
-            proj.get_feature_config().await.unwrap()
+            proj.get_feature_config().await.unwrap().render().unwrap()
         );
 
         let location_id = TypedKey::new("DOLocationID", ValueType::INT32)
@@ -536,8 +1327,12 @@ mod tests {
 
         println!(
             "features_join.conf:\n{}",
-            proj.get_feature_join_config(&ob, &[&anchor_query, &derived_query], &output)
-                .unwrap()
+            serde_json::to_string_pretty(
+                &proj
+                    .get_feature_join_config(&ob, &[&anchor_query, &derived_query], &output)
+                    .unwrap()
+            )
+            .unwrap()
         );
 
         let req = proj
@@ -547,11 +1342,12 @@ mod tests {
             .python_file("test-script/testudf.py")
             .output_location(output.parse().unwrap())
             .unwrap()
-            .build();
+            .build()
+            .unwrap();
 
         println!("Request: {:#?}", req);
 
-        let id = client.submit_job(req).await.log().unwrap();
+        let id = client.submit_job(req).await.log().unwrap().id;
 
         let log = client.wait_for_job(id, None).await.unwrap();
 
@@ -0,0 +1,358 @@
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use tokio::{net::TcpStream, time::timeout};
+
+use crate::{presign, DataLocation, Error, GetSecretKeys, VarSource};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The source is reachable
+    Succeeded,
+    /// The endpoint accepts connections, but only the network path is tested, the credentials and
+    /// the data are not verified
+    Reachable,
+    /// The source is not reachable, or some required secrets are missing
+    Failed,
+    /// The source cannot be checked without running a Spark job
+    Unsupported,
+}
+
+impl Display for ConnectionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ConnectionStatus::Succeeded => "Succeeded",
+                ConnectionStatus::Reachable => "Reachable",
+                ConnectionStatus::Failed => "Failed",
+                ConnectionStatus::Unsupported => "Unsupported",
+            }
+        )
+    }
+}
+
+/**
+ * Result of the source connectivity test.
+ *
+ * The test runs on the local machine, a source that is only reachable from the Spark cluster
+ * may fail here while the job still works.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConnectionTestResult {
+    pub status: ConnectionStatus,
+    pub message: String,
+    pub elapsed: Duration,
+    pub tested_at: DateTime<Utc>,
+}
+
+impl ConnectionTestResult {
+    /**
+     * The source is fully verified, a reachability-only test is not a success
+     */
+    pub fn is_succeeded(&self) -> bool {
+        self.status == ConnectionStatus::Succeeded
+    }
+
+    /**
+     * The endpoint accepts connections, whether or not the credentials are verified
+     */
+    pub fn is_reachable(&self) -> bool {
+        matches!(
+            self.status,
+            ConnectionStatus::Succeeded | ConnectionStatus::Reachable
+        )
+    }
+}
+
+/**
+ * Lightweight connectivity check of the source location:
+ * - `http(s)` paths are checked with a HEAD request
+ * - `abfs(s)` and `wasb(s)` paths are listed with the storage key in the config
+ * - JDBC and Kafka endpoints are only checked with a TCP connection and reported as `Reachable`,
+ *   JDBC credentials are only checked for existence as verifying them needs the database driver
+ */
+pub(crate) async fn test_location(
+    var_source: Arc<dyn VarSource + Send + Sync>,
+    location: &DataLocation,
+) -> ConnectionTestResult {
+    let tested_at = Utc::now();
+    let start = Instant::now();
+    let (status, message) = match check_location(var_source, location).await {
+        Ok(r) => r,
+        Err(e) => (ConnectionStatus::Failed, e.to_string()),
+    };
+    debug!("Connectivity test: {}, {}", status, message);
+    ConnectionTestResult {
+        status,
+        message,
+        elapsed: start.elapsed(),
+        tested_at,
+    }
+}
+
+async fn check_location(
+    var_source: Arc<dyn VarSource + Send + Sync>,
+    location: &DataLocation,
+) -> Result<(ConnectionStatus, String), Error> {
+    match location {
+        DataLocation::InputContext => Ok((
+            ConnectionStatus::Succeeded,
+            "Input context is provided by the observation data".to_string(),
+        )),
+        DataLocation::Hdfs { path } => check_path(var_source, path).await,
+        DataLocation::Jdbc { url, .. } => {
+            // The job fails before connecting if any secret is missing
+            for key in location.get_secret_keys() {
+                if var_source.get_environment_variable(&[&key]).await.is_err() {
+                    return Ok((
+                        ConnectionStatus::Failed,
+                        format!("Secret `{}` is not set", key),
+                    ));
+                }
+            }
+            let (host, port) = parse_jdbc_address(url)?;
+            check_tcp(&host, port).await?;
+            Ok((
+                ConnectionStatus::Reachable,
+                format!(
+                    "Connected to {}:{}, only reachability is tested, credentials are not verified",
+                    host, port
+                ),
+            ))
+        }
+        DataLocation::Kafka { brokers, .. } => {
+            for broker in brokers {
+                let (host, port) = broker
+                    .rsplit_once(':')
+                    .and_then(|(h, p)| p.parse::<u16>().ok().map(|p| (h.to_string(), p)))
+                    .ok_or_else(|| Error::InvalidUrl(broker.to_owned()))?;
+                check_tcp(&host, port).await?;
+            }
+            Ok((
+                ConnectionStatus::Reachable,
+                format!(
+                    "Connected to {}, only reachability is tested, credentials are not verified",
+                    brokers.join(", ")
+                ),
+            ))
+        }
+        DataLocation::Generic { format, .. } => Ok((
+            ConnectionStatus::Unsupported,
+            format!("Connectivity test of `{}` source is not supported", format),
+        )),
+    }
+}
+
+async fn check_path(
+    var_source: Arc<dyn VarSource + Send + Sync>,
+    path: &str,
+) -> Result<(ConnectionStatus, String), Error> {
+    let scheme = path
+        .split_once("://")
+        .map(|(s, _)| s.to_lowercase())
+        .unwrap_or_default();
+    match scheme.as_str() {
+        "http" | "https" => {
+            let resp = reqwest::Client::new()
+                .head(path)
+                .timeout(CONNECT_TIMEOUT)
+                .send()
+                .await?;
+            let status = if resp.status().is_success() {
+                ConnectionStatus::Succeeded
+            } else {
+                ConnectionStatus::Failed
+            };
+            Ok((status, format!("HTTP status {}", resp.status())))
+        }
+        "abfs" | "abfss" | "wasb" | "wasbs" => {
+            let url =
+                presign::get_list_url(var_source, path, chrono::Duration::minutes(5)).await?;
+            let resp = reqwest::Client::new()
+                .get(url)
+                .timeout(CONNECT_TIMEOUT)
+                .send()
+                .await?;
+            if !resp.status().is_success() {
+                return Ok((
+                    ConnectionStatus::Failed,
+                    format!("Storage returned HTTP status {}", resp.status()),
+                ));
+            }
+            let body = resp.text().await?;
+            let prefix = path
+                .split_once("://")
+                .and_then(|(_, p)| p.split_once('/'))
+                .map(|(_, p)| p.trim_matches('/'))
+                .unwrap_or_default();
+            if contains_blob(&body, prefix) {
+                Ok((ConnectionStatus::Succeeded, format!("Found {}", path)))
+            } else {
+                Ok((ConnectionStatus::Failed, format!("{} doesn't exist", path)))
+            }
+        }
+        _ => Ok((
+            ConnectionStatus::Unsupported,
+            format!("Connectivity test of `{}` is not supported", path),
+        )),
+    }
+}
+
+async fn check_tcp(host: &str, port: u16) -> Result<(), Error> {
+    match timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port))).await {
+        Ok(r) => {
+            r?;
+            Ok(())
+        }
+        Err(_) => Err(Error::Timeout),
+    }
+}
+
+/**
+ * Check if the blob list response contains the file, or the directory with given path
+ */
+fn contains_blob(list_response: &str, path: &str) -> bool {
+    if path.is_empty() {
+        // The container itself
+        return true;
+    }
+    list_response
+        .split("<Name>")
+        .skip(1)
+        .filter_map(|s| s.split_once("</Name>").map(|(name, _)| name))
+        .map(|name| {
+            name.replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&")
+        })
+        .any(|name| name.trim_end_matches('/') == path)
+}
+
+/**
+ * Extract host and port from JDBC URL, e.g. `jdbc:sqlserver://host:1433;database=db`,
+ * `jdbc:postgresql://host/db` or `jdbc:oracle:thin:@//host:1521/service`
+ */
+fn parse_jdbc_address(url: &str) -> Result<(String, u16), Error> {
+    let invalid = || Error::InvalidUrl(url.to_string());
+    let rest = url.strip_prefix("jdbc:").ok_or_else(invalid)?;
+    let (subprotocol, address) = match rest.split_once('@') {
+        // Oracle thin driver
+        Some((subprotocol, address)) if subprotocol.starts_with("oracle") => {
+            ("oracle", address.trim_start_matches("//"))
+        }
+        _ => {
+            let (subprotocol, address) = rest.split_once("://").ok_or_else(invalid)?;
+            (subprotocol, address.rsplit_once('@').map(|(_, a)| a).unwrap_or(address))
+        }
+    };
+    let authority = address
+        .split(|c| c == '/' || c == ';' || c == '?')
+        .next()
+        .unwrap_or_default();
+    let (host, port) = if let Some(rest) = authority.strip_prefix('[') {
+        // IPv6 address
+        let (host, port) = rest.split_once(']').ok_or_else(invalid)?;
+        (host, port.strip_prefix(':'))
+    } else {
+        match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    let port = match port {
+        // Oracle SID format `host:port:sid`
+        Some(p) => p
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| invalid())?,
+        None => match subprotocol {
+            "sqlserver" => 1433,
+            "mysql" | "mariadb" => 3306,
+            "postgresql" => 5432,
+            "redshift" => 5439,
+            "oracle" => 1521,
+            "snowflake" => 443,
+            _ => return Err(invalid()),
+        },
+    };
+    Ok((host.to_string(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{new_var_source, JdbcAuth};
+
+    #[test]
+    fn test_parse_jdbc_address() {
+        assert_eq!(
+            parse_jdbc_address("jdbc:sqlserver://myserver.database.windows.net:1433;database=db")
+                .unwrap(),
+            ("myserver.database.windows.net".to_string(), 1433)
+        );
+        assert_eq!(
+            parse_jdbc_address("jdbc:postgresql://localhost/db?ssl=true").unwrap(),
+            ("localhost".to_string(), 5432)
+        );
+        assert_eq!(
+            parse_jdbc_address("jdbc:mysql://user:pass@[::1]:3307/db").unwrap(),
+            ("::1".to_string(), 3307)
+        );
+        assert_eq!(
+            parse_jdbc_address("jdbc:oracle:thin:@//dbhost:1522/service").unwrap(),
+            ("dbhost".to_string(), 1522)
+        );
+        assert_eq!(
+            parse_jdbc_address("jdbc:oracle:thin:@dbhost:1521:sid").unwrap(),
+            ("dbhost".to_string(), 1521)
+        );
+        assert!(parse_jdbc_address("jdbc:unknown://host/db").is_err());
+        assert!(parse_jdbc_address("sqlserver://host:1433").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_jdbc_reachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let location = DataLocation::Jdbc {
+            url: format!(
+                "jdbc:postgresql://127.0.0.1:{}/db",
+                listener.local_addr().unwrap().port()
+            ),
+            dbtable: Some("t".to_string()),
+            query: None,
+            auth: JdbcAuth::Anonymous,
+        };
+        let result = test_location(new_var_source(""), &location).await;
+        assert_eq!(result.status, ConnectionStatus::Reachable);
+        assert!(result.is_reachable());
+        // Not verified until the credentials are checked
+        assert!(!result.is_succeeded());
+    }
+
+    #[test]
+    fn test_contains_blob() {
+        let resp = "<EnumerationResults><Blobs><Blob><Name>data/dir-x</Name></Blob><BlobPrefix><Name>data/dir/</Name></BlobPrefix></Blobs></EnumerationResults>";
+        assert!(contains_blob(resp, "data/dir"));
+        assert!(contains_blob(resp, "data/dir-x"));
+        assert!(!contains_blob(resp, "data/di"));
+        assert!(contains_blob(resp, ""));
+    }
+}
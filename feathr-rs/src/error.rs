@@ -3,7 +3,7 @@ use std::sync::PoisonError;
 use chrono::{DateTime, Utc};
 use thiserror::Error;
 
-use crate::registry_client::api_models::EntityType;
+use crate::registry_client::api_models::{EntityType, RegistryErrorCode};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -13,7 +13,7 @@ pub enum Error {
     #[error("Project {0} not found")]
     ProjectNotFound(String),
 
-    #[error("Anchor group {0} not found")]
+    #[error("Source {0} not found")]
     SourceGroupNotFound(String),
 
     #[error("Anchor group {0} not found")]
@@ -28,6 +28,12 @@ pub enum Error {
     #[error("{2} key alias are provided while Anchor {0} has {1} keys")]
     MismatchKeyAlias(String, usize, usize),
 
+    #[error("{2} keys are provided in the query while feature {0} has {1} keys")]
+    MismatchQueryKey(String, usize, usize),
+
+    #[error("Key `{1}` of feature {0} is at a different position, keys must follow the order [{2}]")]
+    MisorderedKey(String, String, String),
+
     #[error("Key alias {1} not found in derived feature {0}, existing keys are: {2}")]
     KeyAliasNotFound(String, String, String),
 
@@ -43,6 +49,9 @@ pub enum Error {
     #[error("key alias {1} in derived feature {0} must come from its input features key alias list {2}")]
     InvalidDerivedKeyAlias(String, String, String),
 
+    #[error("Derived feature {0} references `{1}` which is not a feature in the project, use `add_input` to set the inputs explicitly")]
+    UnknownInputFeature(String, String),
+
     #[error("{0}")]
     SyncError(String),
 
@@ -52,15 +61,18 @@ pub enum Error {
     #[error(transparent)]
     LivyClientError(#[from] crate::livy_client::LivyClientError),
 
-    #[error(transparent)]
-    DbfsError(#[from] dbfs_client::DbfsError),
-
     #[error("Databricks API Error, Code={0}, Message='{1}'")]
     DatabricksApiError(String, String),
 
     #[error("HTTP Error, URL: '{0}', Status: {1}, Response: '{2}' ")]
     DatabricksHttpError(String, String, String),
 
+    #[error("Registry API Error, Code={0:?}, Message='{1}'")]
+    RegistryApiError(RegistryErrorCode, String),
+
+    #[error("Registry HTTP Error, URL: '{0}', Status: {1}, Response: '{2}'")]
+    RegistryHttpError(String, String, String),
+
     #[error("Invalid Url {0}")]
     InvalidUrl(String),
 
@@ -88,12 +100,18 @@ pub enum Error {
     #[error(transparent)]
     AzureError(#[from] azure_core::error::Error),
 
+    #[error(transparent)]
+    ParquetError(#[from] parquet::errors::ParquetError),
+
     #[error("Invalid Time Range {0} - {1}")]
     InvalidTimeRange(DateTime<Utc>, DateTime<Utc>),
 
     #[error("Unsupported Spark provider '{0}'")]
     UnsupportedSparkProvider(String),
 
+    #[error("{0} is not supported, supported runtimes are {1}, or set `spark_config.maven_artifact` explicitly")]
+    UnsupportedSparkRuntime(String, String),
+
     #[error("Entity({0}) has invalid type {1:?}")]
     InvalidEntityType(String, EntityType),
 
@@ -106,8 +124,41 @@ pub enum Error {
     #[error("{0}")]
     InvalidArgument(String),
 
+    #[error("Template snippet `{0}` is invalid: {1}")]
+    InvalidTemplateSnippet(String, String),
+
+    #[error("Parameter `{1}` of feature template `{0}` is not set")]
+    MissingTemplateParameter(String, String),
+
+    #[error("{0} is used by {1} and can't be deleted")]
+    EntityInUse(String, String),
+
     #[error("Feathr client is not connected to the registry")]
     DetachedClient,
+
+    #[error("Job {0} is not submitted by this client")]
+    UnknownJob(u64),
+
+    #[error("Unsupported job manifest version {0}")]
+    UnsupportedManifestVersion(u32),
+
+    #[error("Invalid timestamp format `{0}`, {1}")]
+    InvalidTimestampFormat(String, String),
+
+    #[error("Feature config is {0} bytes, exceeding the limit of {1} bytes, and can't be split, {2}")]
+    FeatureConfigTooLarge(usize, usize, String),
+}
+
+impl Error {
+    /**
+     * Code of the error returned by the registry, `None` if the error is not from the registry
+     */
+    pub fn registry_error_code(&self) -> Option<RegistryErrorCode> {
+        match self {
+            Error::RegistryApiError(code, _) => Some(*code),
+            _ => None,
+        }
+    }
 }
 
 impl<Guard> From<PoisonError<Guard>> for Error {
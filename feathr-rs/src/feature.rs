@@ -6,7 +6,7 @@ use uuid::Uuid;
 
 use crate::{
     project::{FeathrProjectImpl, FeathrProjectModifier},
-    DerivedTransformation, Error, FeatureType, Transformation, TypedKey,
+    DerivedTransformation, Error, FeatureType, Ownership, Transformation, TypedKey,
 };
 
 pub trait Feature
@@ -22,6 +22,7 @@ where
     fn get_transformation(&self) -> Transformation;
     fn get_key_alias(&self) -> Vec<String>;
     fn get_registry_tags(&self) -> HashMap<String, String>;
+    fn get_ownership(&self) -> Ownership;
 }
 
 #[derive(Clone, Debug)]
@@ -94,6 +95,10 @@ impl Feature for AnchorFeature {
     fn get_registry_tags(&self) -> HashMap<String, String> {
         self.inner.base.registry_tags.clone()
     }
+
+    fn get_ownership(&self) -> Ownership {
+        self.inner.base.ownership.clone()
+    }
 }
 
 impl ToString for AnchorFeature {
@@ -178,6 +183,10 @@ impl Feature for DerivedFeature {
     fn get_registry_tags(&self) -> HashMap<String, String> {
         self.inner.base.registry_tags.clone()
     }
+
+    fn get_ownership(&self) -> Ownership {
+        self.inner.base.ownership.clone()
+    }
 }
 
 impl ToString for DerivedFeature {
@@ -208,6 +217,8 @@ pub(crate) struct FeatureBase {
     pub(crate) feature_alias: String,
     #[serde(skip)]
     pub(crate) registry_tags: HashMap<String, String>,
+    #[serde(skip)]
+    pub(crate) ownership: Ownership,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -229,13 +240,14 @@ impl AnchorFeatureImpl {
                 key_alias.len(),
             ));
         }
+        let key_alias = align_key_alias(&self.get_name(), &self.base.key, key_alias)?;
         let mut imp = self.clone();
         imp.base.key = imp
             .base
             .key
             .iter()
-            .zip(key_alias.iter())
-            .map(|(key, &alias)| key.to_owned().key_column_alias(alias))
+            .zip(key_alias.into_iter())
+            .map(|(key, alias)| key.to_owned().key_column_alias(&alias))
             .collect();
         Ok(imp)
     }
@@ -287,6 +299,10 @@ impl Feature for AnchorFeatureImpl {
     fn get_registry_tags(&self) -> HashMap<String, String> {
         self.base.registry_tags.to_owned()
     }
+
+    fn get_ownership(&self) -> Ownership {
+        self.base.ownership.to_owned()
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
@@ -346,7 +362,19 @@ impl DerivedFeatureImpl {
             }
         }
         let mut ret = self.clone();
-        ret.key_alias = key_alias.into_iter().map(|&s| s.to_owned()).collect();
+        let mut sorted_key_alias = key_alias.to_vec();
+        let mut sorted_existing = existing_key_alias.clone();
+        sorted_key_alias.sort();
+        sorted_existing.sort();
+        // Same aliases in a different order, keep the order of the definition
+        ret.key_alias = if sorted_key_alias == sorted_existing {
+            existing_key_alias
+        } else {
+            key_alias.to_vec()
+        }
+        .into_iter()
+        .map(|s| s.to_owned())
+        .collect();
         Ok(ret)
     }
 
@@ -397,4 +425,55 @@ impl Feature for DerivedFeatureImpl {
     fn get_registry_tags(&self) -> HashMap<String, String> {
         self.base.registry_tags.to_owned()
     }
+
+    fn get_ownership(&self) -> Ownership {
+        self.base.ownership.to_owned()
+    }
+}
+
+/**
+ * Aliases named after the keys, by either the key column or the existing alias, are matched by
+ * name so they can be given in any order, other aliases are matched by position.
+ * An alias named after a key at another position is a mismatch.
+ */
+fn align_key_alias(
+    feature: &str,
+    keys: &[TypedKey],
+    key_alias: &[&str],
+) -> Result<Vec<String>, Error> {
+    let positions: Vec<Option<usize>> = key_alias
+        .iter()
+        .map(|&alias| {
+            keys.iter()
+                .position(|k| k.key_column == alias || k.key_column_alias.as_deref() == Some(alias))
+        })
+        .collect();
+    let mut matched: Vec<usize> = positions.iter().flatten().copied().collect();
+    matched.sort();
+    matched.dedup();
+    if matched.len() == keys.len() && positions.iter().all(Option::is_some) {
+        let mut ret = vec![String::new(); keys.len()];
+        for (&alias, pos) in key_alias.iter().zip(positions) {
+            ret[pos.unwrap_or_default()] = alias.to_string();
+        }
+        return Ok(ret);
+    }
+    for (idx, (&alias, pos)) in key_alias.iter().zip(positions).enumerate() {
+        if matches!(pos, Some(pos) if pos != idx) {
+            return Err(Error::MisorderedKey(
+                feature.to_string(),
+                alias.to_string(),
+                keys.iter()
+                    .map(|k| {
+                        k.key_column_alias
+                            .as_ref()
+                            .unwrap_or(&k.key_column)
+                            .as_str()
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+    }
+    Ok(key_alias.iter().map(|&s| s.to_string()).collect())
 }
@@ -11,8 +11,11 @@ use crate::{
         AnchorFeature, AnchorFeatureImpl, DerivedFeature, DerivedFeatureImpl, Feature, FeatureBase,
         InputFeature,
     },
+    lint::referenced_columns,
     project::{FeathrProjectImpl, FeathrProjectModifier},
-    Error, FeatureType, Transformation, TypedKey,
+    registry_client::api_models::EntityType,
+    Error, FeatureCost, FeatureRegistry, FeatureType, Ownership, Transformation, TypedKey,
+    COST_TAG,
 };
 
 #[derive(Debug)]
@@ -25,6 +28,7 @@ pub struct AnchorFeatureBuilder {
     keys: Vec<TypedKey>,
     feature_alias: String,
     registry_tags: HashMap<String, String>,
+    ownership: Ownership,
 }
 
 impl AnchorFeatureBuilder {
@@ -43,6 +47,7 @@ impl AnchorFeatureBuilder {
             keys: Default::default(),
             feature_alias: name.to_string(),
             registry_tags: Default::default(),
+            ownership: Default::default(),
         }
     }
 
@@ -65,6 +70,19 @@ impl AnchorFeatureBuilder {
         self
     }
 
+    /**
+     * Annotate the computation cost of the feature with the `feathr_cost` registry tag, it's
+     * inferred from the transformation if not set
+     */
+    pub fn cost(&mut self, cost: FeatureCost) -> &mut Self {
+        self.add_tag(COST_TAG, &cost.to_string())
+    }
+
+    pub fn ownership(&mut self, ownership: Ownership) -> &mut Self {
+        self.ownership = ownership;
+        self
+    }
+
     pub async fn build(&mut self) -> Result<AnchorFeature, Error> {
         let anchor = AnchorFeatureImpl {
             base: FeatureBase {
@@ -78,7 +96,8 @@ impl AnchorFeatureBuilder {
                     self.keys.clone()
                 },
                 feature_alias: self.feature_alias.clone(),
-                registry_tags: self.registry_tags.clone(),
+                registry_tags: self.owner.read().await.inherit_tags(&self.registry_tags),
+                ownership: self.ownership.clone(),
             },
             key_alias: self
                 .keys
@@ -108,7 +127,12 @@ pub struct DerivedFeatureBuilder {
     keys: Vec<TypedKey>,
     feature_alias: String,
     registry_tags: HashMap<String, String>,
+    ownership: Ownership,
     input_features: Vec<InputFeature>,
+    /// Qualified names of the input features in other projects
+    shared_inputs: Vec<String>,
+    /// Feature names found in the transform expression, used as inputs if none is added
+    referenced_features: Vec<String>,
 }
 
 impl DerivedFeatureBuilder {
@@ -125,15 +149,27 @@ impl DerivedFeatureBuilder {
             keys: Default::default(),
             feature_alias: name.to_string(),
             registry_tags: Default::default(),
+            ownership: Default::default(),
             input_features: Default::default(),
+            shared_inputs: Default::default(),
+            referenced_features: Default::default(),
         }
     }
 
+    /**
+     * Features referenced by an expression transform become the inputs of the derived feature,
+     * unless inputs are added explicitly with `add_input`
+     */
     pub fn transform<T>(&mut self, transform: T) -> &mut Self
     where
         T: Into<Transformation>,
     {
-        self.transform = Some(transform.into());
+        let transform = transform.into();
+        self.referenced_features = match &transform {
+            Transformation::Expression { def } => referenced_features(&def.sql_expr),
+            _ => vec![],
+        };
+        self.transform = Some(transform);
         self
     }
 
@@ -148,20 +184,105 @@ impl DerivedFeatureBuilder {
         self
     }
 
+    /**
+     * Annotate the computation cost of the feature with the `feathr_cost` registry tag, it's
+     * inferred from the transformation if not set
+     */
+    pub fn cost(&mut self, cost: FeatureCost) -> &mut Self {
+        self.add_tag(COST_TAG, &cost.to_string())
+    }
+
+    pub fn ownership(&mut self, ownership: Ownership) -> &mut Self {
+        self.ownership = ownership;
+        self
+    }
+
     pub fn add_input<T: Feature>(&mut self, feature: &T) -> &mut Self {
-        self.input_features.push(InputFeature {
-            id: feature.get_id(),
-            key: feature.get_key(),
-            feature: feature.get_name(),
-            is_anchor_feature: feature.is_anchor_feature(),
-        });
+        self.input_features.push(input_feature(feature));
+        self
+    }
+
+    /**
+     * Add a feature of another project as input, e.g. from a shared feature library, by the
+     * qualified name `project__anchor__feature` or `project__feature`.
+     * The feature is looked up in the registry when building, and the transform refers to it by
+     * the qualified name.
+     */
+    pub fn add_shared_input(&mut self, qualified_name: &str) -> &mut Self {
+        self.shared_inputs.push(qualified_name.to_string());
         self
     }
 
+    /**
+     * Look up the features referenced by the transform expression in the project
+     */
+    async fn detect_inputs(&self) -> Result<Vec<InputFeature>, Error> {
+        let project = self.owner.read().await;
+        self.referenced_features
+            .iter()
+            .filter(|name| !self.shared_inputs.contains(name))
+            .map(|name| {
+                if let Some(f) = project.anchor_features.get(name) {
+                    Ok(input_feature(f.as_ref()))
+                } else if let Some(f) = project.derivations.get(name) {
+                    Ok(input_feature(f.as_ref()))
+                } else {
+                    Err(Error::UnknownInputFeature(
+                        self.name.to_owned(),
+                        name.to_owned(),
+                    ))
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Look up the inputs from other projects in the registry the project is attached to
+     */
+    async fn resolve_shared_inputs(&self) -> Result<Vec<InputFeature>, Error> {
+        if self.shared_inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        let client = self
+            .owner
+            .read()
+            .await
+            .owner
+            .as_ref()
+            .and_then(|o| o.get_registry_client())
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "Derived feature `{}` has inputs from other projects, the project must be attached to a registry",
+                    self.name
+                ))
+            })?;
+        let mut ret = vec![];
+        for name in &self.shared_inputs {
+            let e = client.get_feature(name).await?;
+            let is_anchor_feature = match e.get_entity_type() {
+                EntityType::AnchorFeature => true,
+                EntityType::DerivedFeature => false,
+                _ => return Err(Error::UnknownInputFeature(self.name.clone(), name.clone())),
+            };
+            ret.push(InputFeature {
+                id: e.guid,
+                key: e.get_typed_key()?,
+                feature: name.to_owned(),
+                is_anchor_feature,
+            });
+        }
+        Ok(ret)
+    }
+
     pub async fn build(&mut self) -> Result<DerivedFeature, Error> {
+        let mut input_features = if self.input_features.is_empty() {
+            self.detect_inputs().await?
+        } else {
+            self.input_features.clone()
+        };
+        input_features.extend(self.resolve_shared_inputs().await?);
         // Validation
-        let key_alias: HashSet<String> = self
-            .input_features
+        let key_alias: HashSet<String> = input_features
             .iter()
             .flat_map(|i| {
                 i.key.iter().map(|k| {
@@ -197,7 +318,8 @@ impl DerivedFeatureBuilder {
                     self.keys.clone()
                 },
                 feature_alias: self.feature_alias.clone(),
-                registry_tags: self.registry_tags.clone(),
+                registry_tags: self.owner.read().await.inherit_tags(&self.registry_tags),
+                ownership: self.ownership.clone(),
             },
             key_alias: {
                 let aliases: Vec<String> = self
@@ -216,10 +338,9 @@ impl DerivedFeatureBuilder {
                     aliases
                 }
             },
-            inputs: self
-                .input_features
-                .iter()
-                .map(|f| (f.feature.to_owned(), f.to_owned()))
+            inputs: input_features
+                .into_iter()
+                .map(|f| (f.feature.to_owned(), f))
                 .collect(),
             transform: self
                 .transform
@@ -231,3 +352,50 @@ impl DerivedFeatureBuilder {
         self.owner.insert_derived(derived).await
     }
 }
+
+fn input_feature<T: Feature>(feature: &T) -> InputFeature {
+    InputFeature {
+        id: feature.get_id(),
+        key: feature.get_key(),
+        feature: feature.get_name(),
+        is_anchor_feature: feature.is_anchor_feature(),
+    }
+}
+
+/**
+ * Identifiers in the expression that may be feature names, in the order of first appearance.
+ * Only the part before `.` is kept as fields of struct features are accessed with `feature.field`.
+ */
+fn referenced_features(expr: &str) -> Vec<String> {
+    let mut ret: Vec<String> = vec![];
+    for column in referenced_columns(expr) {
+        let name = column.split('.').next().unwrap_or_default().to_string();
+        if !name.is_empty() && !ret.contains(&name) {
+            ret.push(name);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referenced_features() {
+        assert_eq!(
+            referenced_features("f_trip_distance * 2 + cast_float(f_trip_time_duration)"),
+            vec!["f_trip_distance", "f_trip_time_duration"]
+        );
+        assert_eq!(
+            referenced_features(
+                "CASE WHEN f_is_long_trip IS NULL THEN 'f_unknown' ELSE f_location.city END"
+            ),
+            vec!["f_is_long_trip", "f_location"]
+        );
+        assert_eq!(
+            referenced_features("cast(f_a AS DOUBLE) / f_a"),
+            vec!["f_a"]
+        );
+    }
+}
@@ -1,8 +1,11 @@
+use std::collections::HashMap;
+
+use log::warn;
 use serde::Serialize;
 
-use crate::{TypedKey, Feature};
+use crate::{Error, Feature, TypedKey};
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FeatureQuery {
     pub feature_list: Vec<String>,
@@ -56,4 +59,196 @@ where
     fn from(names: &[T]) -> Self {
         FeatureQuery::by_name(names)
     }
-}
\ No newline at end of file
+}
+
+/**
+ * Merges queries with identical keys and removes duplicated feature names, so Spark doesn't
+ * join the same feature more than once.
+ * A feature queried with different keys would produce conflicting columns, only the first
+ * occurrence is kept.
+ */
+pub(crate) fn normalize_queries<I>(queries: I) -> Vec<FeatureQuery>
+where
+    I: IntoIterator<Item = FeatureQuery>,
+{
+    let mut normalized: Vec<FeatureQuery> = vec![];
+    // Feature name -> keys it's queried with
+    let mut queried: HashMap<String, Vec<String>> = HashMap::new();
+    for q in queries {
+        let idx = match normalized.iter().position(|n| n.key == q.key) {
+            Some(idx) => idx,
+            None => {
+                normalized.push(FeatureQuery {
+                    feature_list: vec![],
+                    key: q.key.clone(),
+                });
+                normalized.len() - 1
+            }
+        };
+        for name in q.feature_list {
+            match queried.get(&name) {
+                Some(key) if key == &q.key => {}
+                Some(key) => warn!(
+                    "Feature `{}` is queried with keys [{}] and [{}], only the first is used",
+                    name,
+                    key.join(", "),
+                    q.key.join(", ")
+                ),
+                None => {
+                    queried.insert(name.clone(), q.key.clone());
+                    normalized[idx].feature_list.push(name);
+                }
+            }
+        }
+    }
+    normalized.retain(|q| !q.feature_list.is_empty());
+    normalized
+}
+
+/**
+ * Splits queries into one query per feature with the keys in the key order of the feature, so
+ * composite keys given in a different order than the feature definition still join on the right
+ * columns, use `normalize_queries` to merge them again.
+ * `feature_keys` returns the key columns of a feature in the definition order, `None` if the
+ * feature is unknown.
+ */
+pub(crate) fn align_query_keys<I, F>(
+    queries: I,
+    feature_keys: F,
+) -> Result<Vec<FeatureQuery>, Error>
+where
+    I: IntoIterator<Item = FeatureQuery>,
+    F: Fn(&str) -> Option<Vec<String>>,
+{
+    let mut aligned = vec![];
+    for q in queries {
+        for name in q.feature_list {
+            let key = match feature_keys(&name) {
+                Some(keys) => align_keys(&name, &q.key, &keys)?,
+                None => q.key.clone(),
+            };
+            aligned.push(FeatureQuery {
+                feature_list: vec![name],
+                key,
+            });
+        }
+    }
+    Ok(aligned)
+}
+
+/**
+ * Query keys named after the feature keys are matched by name, other keys are observation columns
+ * matched by position, a key named after a feature key at another position is a mismatch
+ */
+fn align_keys(
+    feature: &str,
+    key: &[String],
+    feature_keys: &[String],
+) -> Result<Vec<String>, Error> {
+    let dummy = TypedKey::DUMMY_KEY().key_column;
+    let feature_keys: Vec<&String> = feature_keys.iter().filter(|&k| k != &dummy).collect();
+    if feature_keys.is_empty() || key.iter().all(|k| k == &dummy) {
+        return Ok(key.to_vec());
+    }
+    if key.len() != feature_keys.len() {
+        return Err(Error::MismatchQueryKey(
+            feature.to_string(),
+            feature_keys.len(),
+            key.len(),
+        ));
+    }
+    let mut sorted_key: Vec<&String> = key.iter().collect();
+    let mut sorted_feature_keys = feature_keys.clone();
+    sorted_key.sort();
+    sorted_feature_keys.sort();
+    if sorted_key == sorted_feature_keys {
+        return Ok(feature_keys.into_iter().cloned().collect());
+    }
+    for (idx, k) in key.iter().enumerate() {
+        if feature_keys.contains(&k) && feature_keys[idx] != k {
+            return Err(Error::MisorderedKey(
+                feature.to_string(),
+                k.to_owned(),
+                feature_keys
+                    .iter()
+                    .map(|k| k.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ));
+        }
+    }
+    Ok(key.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(names: &[&str], key: &[&str]) -> FeatureQuery {
+        FeatureQuery {
+            feature_list: names.iter().map(|n| n.to_string()).collect(),
+            key: key.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_queries() {
+        let normalized = normalize_queries(vec![
+            query(&["f1", "f2", "f1"], &["user"]),
+            query(&["f3"], &["item"]),
+            query(&["f2", "f4"], &["user"]),
+            // Conflicting keys, f3 is already queried with `item`
+            query(&["f3"], &["user", "item"]),
+        ]);
+        assert_eq!(
+            normalized,
+            vec![
+                query(&["f1", "f2", "f4"], &["user"]),
+                query(&["f3"], &["item"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_align_query_keys() {
+        let feature_keys = |name: &str| match name {
+            "f_user_item" => Some(vec!["user".to_string(), "item".to_string()]),
+            "f_item_user" => Some(vec!["item".to_string(), "user".to_string()]),
+            "f_passthrough" => Some(vec!["NOT_NEEDED".to_string()]),
+            _ => None,
+        };
+        let aligned = align_query_keys(
+            vec![
+                query(&["f_user_item", "f_item_user"], &["item", "user"]),
+                query(&["f_passthrough", "f_unknown"], &["uid"]),
+            ],
+            feature_keys,
+        )
+        .unwrap();
+        assert_eq!(
+            normalize_queries(aligned),
+            vec![
+                query(&["f_user_item"], &["user", "item"]),
+                query(&["f_item_user"], &["item", "user"]),
+                query(&["f_passthrough", "f_unknown"], &["uid"]),
+            ]
+        );
+
+        // Observation columns are matched by position
+        let aligned =
+            align_query_keys(vec![query(&["f_user_item"], &["uid", "iid"])], feature_keys).unwrap();
+        assert_eq!(aligned, vec![query(&["f_user_item"], &["uid", "iid"])]);
+
+        assert!(matches!(
+            align_query_keys(vec![query(&["f_user_item"], &["uid"])], feature_keys),
+            Err(Error::MismatchQueryKey(_, 2, 1))
+        ));
+        assert!(matches!(
+            align_query_keys(
+                vec![query(&["f_user_item"], &["item", "iid"])],
+                feature_keys
+            ),
+            Err(Error::MisorderedKey(..))
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::{Captures, Regex};
+
+use crate::{
+    utils::str_to_dur, AnchorFeature, AnchorFeatureBuilder, AnchorGroup, Error, FeatureType,
+    Ownership, Transformation, TypedKey,
+};
+
+const WINDOW_PARAM: &str = "window";
+
+/**
+ * Pattern of a batch of anchor features that only differ in a few parameters, e.g. the same
+ * aggregation over different columns or windows.
+ * `{param}` placeholders in the name, the transformation and the tag values are replaced with the
+ * parameters of each feature, a window aggregation also takes its window from the `window`
+ * parameter if it's set, e.g. `7d`.
+ */
+#[derive(Clone, Debug)]
+pub struct FeatureTemplate {
+    name: String,
+    feature_type: FeatureType,
+    transform: Transformation,
+    keys: Vec<TypedKey>,
+    registry_tags: HashMap<String, String>,
+    ownership: Ownership,
+}
+
+impl FeatureTemplate {
+    pub fn new<T>(name_pattern: &str, feature_type: FeatureType, transform_pattern: T) -> Self
+    where
+        T: Into<Transformation>,
+    {
+        Self {
+            name: name_pattern.to_string(),
+            feature_type,
+            transform: transform_pattern.into(),
+            keys: Default::default(),
+            registry_tags: Default::default(),
+            ownership: Default::default(),
+        }
+    }
+
+    pub fn keys(&mut self, keys: &[&TypedKey]) -> &mut Self {
+        self.keys = keys.iter().map(|&k| k.to_owned()).collect();
+        self
+    }
+
+    pub fn add_tag(&mut self, key: &str, value_pattern: &str) -> &mut Self {
+        self.registry_tags
+            .insert(key.to_string(), value_pattern.to_string());
+        self
+    }
+
+    pub fn ownership(&mut self, ownership: Ownership) -> &mut Self {
+        self.ownership = ownership;
+        self
+    }
+
+    /**
+     * Create one feature builder for each parameter set, nothing is added to the anchor group
+     * until the builders are built
+     */
+    pub fn expand<I, P, K, V>(
+        &self,
+        group: &AnchorGroup,
+        params: I,
+    ) -> Result<Vec<AnchorFeatureBuilder>, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut names = HashSet::new();
+        let mut ret = vec![];
+        for p in params {
+            let p: HashMap<String, String> = p
+                .into_iter()
+                .map(|(k, v)| (k.as_ref().to_string(), v.as_ref().to_string()))
+                .collect();
+            let name = self.render(&self.name, &p)?;
+            if !names.insert(name.clone()) {
+                return Err(Error::InvalidArgument(format!(
+                    "Feature template `{}` generates duplicated feature name `{}`",
+                    self.name, name
+                )));
+            }
+            let mut builder = group.anchor(&name, self.feature_type.to_owned())?;
+            builder
+                .transform(self.render_transform(&p)?)
+                .keys(&self.keys.iter().collect::<Vec<_>>())
+                .ownership(self.ownership.to_owned());
+            for (key, value) in &self.registry_tags {
+                builder.add_tag(key, &self.render(value, &p)?);
+            }
+            ret.push(builder);
+        }
+        Ok(ret)
+    }
+
+    /**
+     * Expand the template and add all generated features to the anchor group
+     */
+    pub async fn build<I, P, K, V>(
+        &self,
+        group: &AnchorGroup,
+        params: I,
+    ) -> Result<Vec<AnchorFeature>, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut ret = vec![];
+        for mut builder in self.expand(group, params)? {
+            ret.push(builder.build().await?);
+        }
+        Ok(ret)
+    }
+
+    fn render_transform(&self, params: &HashMap<String, String>) -> Result<Transformation, Error> {
+        let mut transform = match &self.transform {
+            Transformation::Expression { def } => {
+                let mut def = def.to_owned();
+                def.sql_expr = self.render(&def.sql_expr, params)?;
+                Transformation::Expression { def }
+            }
+            Transformation::WindowAgg {
+                def_expr,
+                agg_func,
+                window,
+                group_by,
+                filter,
+                limit,
+            } => Transformation::WindowAgg {
+                def_expr: self.render(def_expr, params)?,
+                agg_func: agg_func.to_owned(),
+                window: window.to_owned(),
+                group_by: self.render_opt(group_by, params)?,
+                filter: self.render_opt(filter, params)?,
+                limit: limit.to_owned(),
+            },
+            Transformation::Udf { name } => Transformation::Udf {
+                name: self.render(name, params)?,
+            },
+        };
+        if let (Transformation::WindowAgg { window, .. }, Some(w)) =
+            (&mut transform, params.get(WINDOW_PARAM))
+        {
+            *window = Some(str_to_dur(w)?);
+        }
+        Ok(transform)
+    }
+
+    fn render_opt(
+        &self,
+        pattern: &Option<String>,
+        params: &HashMap<String, String>,
+    ) -> Result<Option<String>, Error> {
+        pattern.as_ref().map(|p| self.render(p, params)).transpose()
+    }
+
+    /**
+     * Replace `{param}` placeholders, other braces are kept as is
+     */
+    fn render(&self, pattern: &str, params: &HashMap<String, String>) -> Result<String, Error> {
+        let re = Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+        let mut missing = None;
+        let ret = re.replace_all(pattern, |caps: &Captures| match params.get(&caps[1]) {
+            Some(v) => v.to_owned(),
+            None => {
+                missing.get_or_insert_with(|| caps[1].to_string());
+                caps[0].to_string()
+            }
+        });
+        match missing {
+            Some(param) => Err(Error::MissingTemplateParameter(self.name.clone(), param)),
+            None => Ok(ret.into_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::Aggregation;
+
+    fn params(p: &[(&str, &str)]) -> HashMap<String, String> {
+        p.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_transform() {
+        let template = FeatureTemplate::new(
+            "f_{column}_{window}_sum",
+            FeatureType::FLOAT,
+            Transformation::window_agg("cast_float({column})", Aggregation::SUM, Duration::days(1))
+                .unwrap(),
+        );
+        let p = params(&[("column", "fare_amount"), ("window", "7d")]);
+        assert_eq!(
+            template.render(&template.name, &p).unwrap(),
+            "f_fare_amount_7d_sum"
+        );
+        assert!(matches!(
+            template.render_transform(&p).unwrap(),
+            Transformation::WindowAgg { def_expr, window, .. }
+                if def_expr == "cast_float(fare_amount)" && window == Some(Duration::days(7))
+        ));
+
+        // Braces not in the form of `{param}` are kept
+        let template = FeatureTemplate::new(
+            "f_{column}",
+            FeatureType::INT32,
+            "map('a', {column})['{ a }']",
+        );
+        assert_eq!(
+            template
+                .render_transform(&params(&[("column", "x")]))
+                .unwrap(),
+            Transformation::from("map('a', x)['{ a }']")
+        );
+
+        assert!(matches!(
+            template.render_transform(&params(&[("col", "x")])),
+            Err(Error::MissingTemplateParameter(_, p)) if p == "column"
+        ));
+    }
+}
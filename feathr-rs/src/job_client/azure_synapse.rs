@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
 use async_trait::async_trait;
 use azure_identity::DefaultAzureCredential;
@@ -11,9 +14,10 @@ use reqwest::Url;
 use crate::{
     livy_client::{
         AadAuthenticator, AzureSynapseClientBuilder, ClusterSize, LivyClient, LivyStates,
-        SparkRequest,
+        SparkJob, SparkRequest,
     },
-    JobClient, JobId, JobStatus, Logged, VarSource,
+    JobBackend, JobClient, JobHandle, JobId, JobStatus, Logged, SparkRuntime, VarSource,
+    JOB_PAGE_URL_KEY,
 };
 
 static NOOP_JAR: &'static [u8] = include_bytes!("../../template/noop-1.0.jar");
@@ -26,6 +30,8 @@ pub struct AzureSynapseClient {
     container: String,
     workspace_dir: String,
     maven_artifact: String,
+    /// Jobs submitted to other pools than the configured one
+    job_pools: RwLock<HashMap<JobId, String>>,
 }
 
 impl AzureSynapseClient {
@@ -54,6 +60,7 @@ impl AzureSynapseClient {
             container: container.to_string(),
             workspace_dir: workspace_dir.to_string(),
             maven_artifact: super::FEATHR_MAVEN_ARTIFACT.to_string(),
+            job_pools: Default::default(),
         })
     }
 
@@ -65,7 +72,7 @@ impl AzureSynapseClient {
                 .get_environment_variable(&["spark_config", "azure_synapse", "workspace_dir"])
                 .await?,
         )?;
-        Ok(Self {
+        let mut client = Self {
             livy_client: AzureSynapseClientBuilder::default()
                 .url(
                     var_source
@@ -90,19 +97,20 @@ impl AzureSynapseClient {
             storage_account,
             container,
             workspace_dir: workspace_dir.trim_start_matches("/").to_string(),
-            maven_artifact: var_source
-                .get_environment_variable(&["spark_config", "maven_artifact"])
-                .await
-                .ok()
-                .map(|s| {
-                    if s.is_empty() {
-                        super::FEATHR_MAVEN_ARTIFACT.to_string()
-                    } else {
-                        s
-                    }
-                })
-                .unwrap_or(super::FEATHR_MAVEN_ARTIFACT.to_string()),
-        })
+            maven_artifact: super::FEATHR_MAVEN_ARTIFACT.to_string(),
+            job_pools: Default::default(),
+        };
+        client.maven_artifact =
+            super::select_maven_artifact(&var_source, client.get_spark_runtime()).await?;
+        Ok(client)
+    }
+
+    /**
+     * Spark runtime of the configured pool
+     */
+    pub async fn get_spark_runtime(&self) -> Result<SparkRuntime, crate::Error> {
+        let pool = self.livy_client.get_pool().await?;
+        pool.properties.spark_version.parse()
     }
 
     pub fn default() -> Result<Self, crate::Error> {
@@ -124,6 +132,19 @@ impl AzureSynapseClient {
             container,
             workspace_dir: workspace_dir.trim_start_matches("/").to_string(),
             maven_artifact: super::FEATHR_MAVEN_ARTIFACT.to_string(),
+            job_pools: Default::default(),
+        })
+    }
+
+    /**
+     * Livy batch ids are only unique in a pool, jobs submitted to other pools by this client are
+     * looked up in their pools, other jobs are looked up in the configured pool
+     */
+    fn get_pool_client(&self, job_id: JobId) -> Option<LivyClient<AadAuthenticator>> {
+        self.job_pools.read().ok().and_then(|pools| {
+            pools
+                .get(&job_id)
+                .map(|pool| self.livy_client.with_pool(pool))
         })
     }
 }
@@ -161,7 +182,7 @@ impl JobClient for AzureSynapseClient {
         &self,
         var_source: Arc<dyn VarSource + Send + Sync>,
         request: super::SubmitJobRequest,
-    ) -> Result<JobId, crate::Error> {
+    ) -> Result<JobHandle, crate::Error> {
         let args = self.get_arguments(var_source.clone(), &request).await?;
 
         let main_jar_path = if request.main_jar_path.is_none() {
@@ -244,40 +265,80 @@ impl JobClient for AzureSynapseClient {
                 Default::default()
             },
             conf,
-            cluster_size: ClusterSize::MEDIUM(), // TODO:
+            cluster_size: request.cluster_size.unwrap_or_else(ClusterSize::MEDIUM),
             file: executable,
             files,
             jars,
-            name: request.name,
+            name: request.name.clone(),
             py_files,
             tags: request.job_tags,
             ..Default::default()
         };
         debug!("Job request: {:#?}", job);
-        let jid = self.livy_client.create_batch_job(job).await?.id;
-        debug!("Job submitted, id is {}", jid);
-        Ok(JobId(jid))
+        let pool = request.spark_pool.filter(|p| !p.trim().is_empty());
+        let pool_client = pool.as_ref().map(|p| self.livy_client.with_pool(p));
+        let job = pool_client
+            .as_ref()
+            .unwrap_or(&self.livy_client)
+            .create_batch_job(job)
+            .await?;
+        debug!("Job submitted, id is {}", job.id);
+        if let Ok(mut pools) = self.job_pools.write() {
+            match pool {
+                Some(pool) => pools.insert(JobId(job.id), pool),
+                None => pools.remove(&JobId(job.id)),
+            };
+        }
+        Ok(
+            JobHandle::new(JobId(job.id), JobBackend::AzureSynapse, &request.name)
+                .url(get_spark_ui_url(&job)),
+        )
     }
 
     async fn get_job_status(&self, job_id: JobId) -> Result<JobStatus, crate::Error> {
-        Ok(self.livy_client.get_batch_job(job_id.0).await?.state.into())
+        let pool_client = self.get_pool_client(job_id);
+        let livy_client = pool_client.as_ref().unwrap_or(&self.livy_client);
+        Ok(livy_client.get_batch_job(job_id.0).await?.state.into())
     }
 
     async fn get_job_log(&self, job_id: JobId) -> Result<String, crate::Error> {
-        Ok(self
-            .livy_client
+        let pool_client = self.get_pool_client(job_id);
+        let livy_client = pool_client.as_ref().unwrap_or(&self.livy_client);
+        Ok(livy_client
             .get_batch_job_driver_stdout_log(job_id.0)
             .await?)
     }
 
     async fn get_job_output_url(&self, job_id: JobId) -> Result<Option<String>, crate::Error> {
-        let job = self.livy_client.get_batch_job(job_id.0).await?;
+        let pool_client = self.get_pool_client(job_id);
+        let livy_client = pool_client.as_ref().unwrap_or(&self.livy_client);
+        let job = livy_client.get_batch_job(job_id.0).await?;
         Ok(job
             .tags
             .map(|t| t.get(super::OUTPUT_PATH_TAG).map(|s| s.to_owned()))
             .flatten())
     }
 
+    async fn get_job_metadata(
+        &self,
+        job_id: JobId,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        let pool_client = self.get_pool_client(job_id);
+        let livy_client = pool_client.as_ref().unwrap_or(&self.livy_client);
+        let job = livy_client.get_batch_job(job_id.0).await?;
+        let mut metadata: HashMap<String, String> = job
+            .app_info
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(k, v)| v.map(|v| (k, v)))
+            .collect();
+        if let Some(url) = get_spark_ui_url(&job) {
+            metadata.insert(JOB_PAGE_URL_KEY.to_string(), url);
+        }
+        Ok(metadata)
+    }
+
     async fn read_remote_file(&self, url: &str) -> Result<Bytes, crate::Error> {
         let (container, _, dir) = parse_abfs(url)?;
         debug!("Container: {}", container);
@@ -314,7 +375,7 @@ impl JobClient for AzureSynapseClient {
  * Convert Storage URL to Spark compatible format:
  * https://storage/container/path -> abfss://container@storage/path
  */
-fn http_to_abfs<T: AsRef<str>>(url: T) -> Result<String, crate::Error> {
+pub(super) fn http_to_abfs<T: AsRef<str>>(url: T) -> Result<String, crate::Error> {
     let url =
         Url::parse(url.as_ref()).map_err(|_| crate::Error::InvalidUrl(url.as_ref().to_string()))?;
     match url.scheme().to_lowercase().as_str() {
@@ -342,7 +403,18 @@ fn http_to_abfs<T: AsRef<str>>(url: T) -> Result<String, crate::Error> {
     }
 }
 
-fn parse_abfs<T: AsRef<str>>(abfs_url: T) -> Result<(String, String, String), crate::Error> {
+/**
+ * Livy reports Spark UI URL in the app info once the job is started
+ */
+pub(super) fn get_spark_ui_url(job: &SparkJob) -> Option<String> {
+    job.app_info
+        .as_ref()
+        .and_then(|info| info.get("sparkUiUrl").cloned().flatten())
+}
+
+pub(super) fn parse_abfs<T: AsRef<str>>(
+    abfs_url: T,
+) -> Result<(String, String, String), crate::Error> {
     let url = Url::parse(abfs_url.as_ref())
         .map_err(|_| crate::Error::InvalidUrl(abfs_url.as_ref().to_string()))?;
     let container = url.username().to_string();
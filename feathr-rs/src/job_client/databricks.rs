@@ -1,13 +1,61 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use dbfs_client::DbfsClient;
-use log::{debug, warn, trace};
+use futures::FutureExt;
+use log::{debug, trace};
+use reqwest::{RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncReadExt;
+use uuid::Uuid;
 
-use crate::{Error, JobClient, JobId, JobStatus, SubmitJobRequest, VarSource};
+use super::databricks_token::{
+    AadTokenProvider, DatabricksTokenProvider, StaticToken, TokenCallback,
+};
+use crate::{
+    Error, JobBackend, JobClient, JobHandle, JobId, JobStatus, SparkRuntime, SubmitJobRequest,
+    VarSource, JOB_PAGE_URL_KEY, PROJECT_TAG,
+};
+
+/// DBFS API reads and writes at most 1MB in a request
+const DBFS_BLOCK_SIZE: usize = 1 << 20;
+/// All runs of a job share the same task key
+const TASK_KEY: &str = "feathr";
+/// Notebook parameter passing the job arguments as a JSON array
+const NOTEBOOK_ARGS_PARAM: &str = "feathr_args";
+const DEFAULT_NOTEBOOK_DIR: &str = "/Shared/feathr";
+
+/**
+ * How jobs are submitted, some workspaces only allow notebook tasks
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DatabricksSubmissionMode {
+    /// Spark JAR task, or Spark Python task if the job has a PySpark driver
+    #[default]
+    SparkTask,
+    /// The driver is imported into the workspace as a notebook and run as a notebook task, the
+    /// arguments are passed as the `feathr_args` notebook parameter
+    Notebook,
+}
+
+impl FromStr for DatabricksSubmissionMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "" | "spark_task" => Ok(Self::SparkTask),
+            "notebook" => Ok(Self::Notebook),
+            _ => Err(Error::InvalidOption(
+                "spark_config.databricks.submission_mode".to_string(),
+                s.to_string(),
+            )),
+        }
+    }
+}
 
 #[async_trait]
 trait LoggedResponse {
@@ -43,12 +91,15 @@ struct DatabricksErrorResponse {
 
 #[derive(Debug)]
 pub struct DatabricksClient {
+    workspace_url: String,
     url_base: String,
-    dbfs: DbfsClient,
+    token_provider: RwLock<Arc<dyn DatabricksTokenProvider>>,
     client: reqwest::Client,
     workspace_dir: String,
     cluster: Cluster,
     maven_artifact: String,
+    submission_mode: DatabricksSubmissionMode,
+    notebook_dir: String,
 }
 
 impl DatabricksClient {
@@ -59,20 +110,27 @@ impl DatabricksClient {
         cluster: Option<Cluster>,
         maven_artifact: &str,
     ) -> Self {
-        let mut headers = reqwest::header::HeaderMap::new();
-        if !token.is_empty() {
-            headers.insert(
-                "Authorization",
-                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
-            );
-        }
+        Self::with_token_provider(
+            url_base,
+            Arc::new(StaticToken::new(token)),
+            workspace_dir,
+            cluster,
+            maven_artifact,
+        )
+    }
+
+    pub fn with_token_provider(
+        url_base: &str,
+        token_provider: Arc<dyn DatabricksTokenProvider>,
+        workspace_dir: &str,
+        cluster: Option<Cluster>,
+        maven_artifact: &str,
+    ) -> Self {
         Self {
-            url_base: format!("{}/api/2.0", url_base.trim_end_matches("/")),
-            dbfs: DbfsClient::new(url_base, token),
-            client: reqwest::ClientBuilder::new()
-                .default_headers(headers)
-                .build()
-                .unwrap(),
+            workspace_url: url_base.trim_end_matches("/").to_string(),
+            url_base: format!("{}/api/2.1", url_base.trim_end_matches("/")),
+            token_provider: RwLock::new(token_provider),
+            client: reqwest::Client::new(),
             workspace_dir: workspace_dir.to_string(),
             cluster: cluster.unwrap_or(Cluster::NewCluster(NewCluster {
                 num_workers: 2,
@@ -82,53 +140,212 @@ impl DatabricksClient {
                 custom_tags: Default::default(),
             })),
             maven_artifact: maven_artifact.to_string(),
+            submission_mode: Default::default(),
+            notebook_dir: DEFAULT_NOTEBOOK_DIR.to_string(),
         }
     }
 
-    async fn get_run_status(
-        &self,
-        id: u64,
-    ) -> Result<(JobStatus, String, Option<HashMap<String, String>>), Error> {
-        let url = format!("{}/jobs/runs/get-output?run_id={}", self.url_base, id);
-        let resp: GetRunOutputResponse = self
-            .client
-            .get(url)
-            .send()
+    /**
+     * Submit jobs as notebook tasks, notebooks are imported under `notebook_dir` in the workspace,
+     * default to `/Shared/feathr`
+     */
+    pub fn with_submission_mode(
+        mut self,
+        mode: DatabricksSubmissionMode,
+        notebook_dir: Option<&str>,
+    ) -> Self {
+        self.submission_mode = mode;
+        if let Some(dir) = notebook_dir {
+            self.notebook_dir = dir.trim_end_matches("/").to_string();
+        }
+        self
+    }
+
+    /**
+     * Replace the token provider, e.g. when a long-running service switches credentials, following
+     * requests use the new provider
+     */
+    pub fn set_token_provider(&self, token_provider: Arc<dyn DatabricksTokenProvider>) {
+        *self.token_provider.write().unwrap() = token_provider;
+    }
+
+    fn get_token_provider(&self) -> Arc<dyn DatabricksTokenProvider> {
+        self.token_provider.read().unwrap().clone()
+    }
+
+    /**
+     * Send the request with the current token, if the token is rejected, get a new one from the
+     * provider and try again once
+     */
+    async fn send<F>(&self, build: F) -> Result<reqwest::Response, Error>
+    where
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        let provider = self.get_token_provider();
+        let authenticate = |token: String| {
+            let builder = build(&self.client);
+            if token.is_empty() {
+                builder
+            } else {
+                builder.bearer_auth(token)
+            }
+        };
+        let resp = authenticate(provider.get_token().await?).send().await?;
+        let resp = if matches!(
+            resp.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            debug!("Databricks token rejected, retrying with a new token");
+            provider.invalidate().await;
+            authenticate(provider.get_token().await?).send().await?
+        } else {
+            resp
+        };
+        resp.detailed_error_for_status().await
+    }
+
+    /**
+     * Upload the file with the DBFS streaming API, the content is sent in blocks as a single
+     * request is limited to 1MB
+     */
+    async fn write_dbfs_file(&self, path: &str, content: &[u8]) -> Result<(), Error> {
+        let path = dbfs_path(path);
+        let url = format!("{}/api/2.0/dbfs/create", self.workspace_url);
+        let req = serde_json::json!({ "path": path, "overwrite": true });
+        let handle = self
+            .send(|c| c.post(&url).json(&req))
+            .await?
+            .json::<DbfsHandle>()
+            .await?
+            .handle;
+        let url = format!("{}/api/2.0/dbfs/add-block", self.workspace_url);
+        for block in content.chunks(DBFS_BLOCK_SIZE) {
+            let req = serde_json::json!({ "handle": handle, "data": base64::encode(block) });
+            self.send(|c| c.post(&url).json(&req)).await?;
+        }
+        let url = format!("{}/api/2.0/dbfs/close", self.workspace_url);
+        let req = serde_json::json!({ "handle": handle });
+        self.send(|c| c.post(&url).json(&req)).await?;
+        debug!("Uploaded {} bytes to dbfs:{}", content.len(), path);
+        Ok(())
+    }
+
+    async fn read_dbfs_file(&self, path: &str) -> Result<Vec<u8>, Error> {
+        let path = dbfs_path(path);
+        let url = format!("{}/api/2.0/dbfs/read", self.workspace_url);
+        let mut content = vec![];
+        loop {
+            let query = [
+                ("path", path.clone()),
+                ("offset", content.len().to_string()),
+                ("length", DBFS_BLOCK_SIZE.to_string()),
+            ];
+            let block: DbfsBlock = self
+                .send(|c| c.get(&url).query(&query))
+                .await?
+                .json()
+                .await?;
+            if block.bytes_read == 0 {
+                break;
+            }
+            content.extend(base64::decode(&block.data).map_err(|e| {
+                Error::DatabricksHttpError(url.clone(), "200".to_string(), e.to_string())
+            })?);
+        }
+        Ok(content)
+    }
+
+    /**
+     * Import the notebook into the workspace, notebooks are named after their content so unchanged
+     * notebooks don't change the job
+     */
+    async fn import_notebook(&self, name: &str, content: &str) -> Result<String, Error> {
+        let path = format!(
+            "{}/feathr_notebook_{}_{}",
+            self.notebook_dir,
+            name,
+            Uuid::new_v5(&Uuid::NAMESPACE_OID, content.as_bytes()).as_simple()
+        );
+        let url = format!("{}/api/2.0/workspace/mkdirs", self.workspace_url);
+        let req = serde_json::json!({ "path": self.notebook_dir });
+        self.send(|c| c.post(&url).json(&req)).await?;
+        let url = format!("{}/api/2.0/workspace/import", self.workspace_url);
+        let req = serde_json::json!({
+            "path": path,
+            "format": "SOURCE",
+            "language": "PYTHON",
+            "content": base64::encode(content),
+            "overwrite": true,
+        });
+        self.send(|c| c.post(&url).json(&req)).await?;
+        Ok(path)
+    }
+
+    async fn get_run(&self, id: u64) -> Result<RunInfo, Error> {
+        let url = format!("{}/jobs/runs/get?run_id={}", self.url_base, id);
+        let resp: RunInfo = self.send(|c| c.get(&url)).await?.json().await?;
+        trace!("Run response: {:#?}", resp);
+        Ok(resp)
+    }
+
+    async fn get_run_log(&self, id: u64) -> Result<String, Error> {
+        // Output can only be retrieved from the task run, not the job run
+        let task_run_id = self
+            .get_run(id)
             .await?
-            .detailed_error_for_status()
+            .tasks
+            .first()
+            .map(|t| t.run_id)
+            .unwrap_or(id);
+        let url = format!(
+            "{}/jobs/runs/get-output?run_id={}",
+            self.url_base, task_run_id
+        );
+        let resp: GetRunOutputResponse = self.send(|c| c.get(&url)).await?.json().await?;
+        Ok(vec![
+            resp.error.map(|s| format!("{}\n", s)).unwrap_or_default(),
+            resp.logs.map(|s| format!("{}\n", s)).unwrap_or_default(),
+            resp.error_trace
+                .map(|s| format!("{}\n", s))
+                .unwrap_or_default(),
+        ]
+        .join(""))
+    }
+
+    /**
+     * Find the job with the settings, create one if not found.
+     * Jobs are never reset, runs of other submissions keep using the settings they were triggered with
+     */
+    async fn ensure_job(&self, settings: JobSettings) -> Result<u64, Error> {
+        let settings = settings.versioned()?;
+        let url = format!("{}/jobs/list", self.url_base);
+        let resp: ListJobsResponse = self
+            .send(|c| {
+                c.get(&url)
+                    .query(&[("name", settings.name.as_str()), ("limit", "1")])
+            })
             .await?
             .json()
             .await?;
-        trace!("Status response: {:#?}", resp);
-        let status = match resp.metadata.state.life_cycle_state {
-            RunLifeCycleState::Pending => JobStatus::Starting,
-            RunLifeCycleState::Running | RunLifeCycleState::Terminating => JobStatus::Running,
-            RunLifeCycleState::Terminated => match resp.metadata.state.result_state {
-                Some(RunResultState::Success) => JobStatus::Success,
-                _ => JobStatus::Failed,
-            },
-            RunLifeCycleState::Skipped | RunLifeCycleState::InternalError => JobStatus::Failed,
-        };
 
-        Ok((
-            status,
-            vec![
-                resp.error.map(|s| format!("{}\n", s)).unwrap_or_default(),
-                resp.logs.map(|s| format!("{}\n", s)).unwrap_or_default(),
-                resp.error_trace
-                    .map(|s| format!("{}\n", s))
-                    .unwrap_or_default(),
-            ]
-            .join(""),
-            match resp.metadata.cluster_spec.cluster {
-                Cluster::ExistingClusterId(_) => {
-                    warn!("Cannot get output directory from existing cluster");
-                    Default::default()
-                }
-                Cluster::NewCluster(nc) => nc.custom_tags,
+        match resp.jobs.into_iter().next() {
+            Some(job) => Ok(job.job_id),
+            None => {
+                debug!("Creating job {}", settings.name);
+                trace!(
+                    "Job settings: {}",
+                    serde_json::to_string_pretty(&settings).unwrap()
+                );
+                let url = format!("{}/jobs/create", self.url_base);
+                let resp: CreateJobResponse = self
+                    .send(|c| c.post(&url).json(&settings))
+                    .await?
+                    .json()
+                    .await?;
+                debug!("Job created, id is {}", resp.job_id);
+                Ok(resp.job_id)
             }
-            .to_owned(),
-        ))
+        }
     }
 
     pub(crate) async fn from_var_source(
@@ -148,9 +365,42 @@ impl DatabricksClient {
             .trim_end_matches("/")
             .to_string();
 
-        let token = var_source
-            .get_environment_variable(&["DATABRICKS_WORKSPACE_TOKEN_VALUE"])
-            .await?;
+        let token_provider: Arc<dyn DatabricksTokenProvider> = match var_source
+            .get_environment_variable(&["spark_config", "databricks", "auth"])
+            .await
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "token" => {
+                // Fail early if the token is not set at all
+                let token = var_source
+                    .get_environment_variable(&["DATABRICKS_WORKSPACE_TOKEN_VALUE"])
+                    .await?;
+                // Read the token again once it's rejected, it may have been rotated in the
+                // environment or the Key Vault
+                let var_source = var_source.clone();
+                Arc::new(
+                    TokenCallback::new(move || {
+                        let var_source = var_source.clone();
+                        async move {
+                            var_source
+                                .get_environment_variable(&["DATABRICKS_WORKSPACE_TOKEN_VALUE"])
+                                .await
+                        }
+                        .boxed()
+                    })
+                    .with_token(&token),
+                )
+            }
+            "aad" => Arc::new(AadTokenProvider::default()),
+            s => {
+                return Err(crate::Error::InvalidOption(
+                    "spark_config.databricks.auth".to_string(),
+                    s.to_string(),
+                ))
+            }
+        };
 
         #[derive(Debug, Deserialize)]
         struct ConfigTemplate {
@@ -169,25 +419,44 @@ impl DatabricksClient {
         let config_template = serde_yaml::from_value::<ConfigTemplate>(value.to_owned())?;
         let nc = config_template.cluster;
 
-        let maven_artifact = var_source
-            .get_environment_variable(&["spark_config", "maven_artifact"])
+        let submission_mode: DatabricksSubmissionMode = var_source
+            .get_environment_variable(&["spark_config", "databricks", "submission_mode"])
+            .await
+            .unwrap_or_default()
+            .parse()?;
+        let notebook_dir = var_source
+            .get_environment_variable(&["spark_config", "databricks", "notebook_dir"])
             .await
             .ok()
-            .map(|s| if s.is_empty() {
-                super::FEATHR_MAVEN_ARTIFACT.to_string()
-            } else {
-                s
-            })
-            .unwrap_or(super::FEATHR_MAVEN_ARTIFACT.to_string());
-        debug!("Maven artifact: {}", maven_artifact);
+            .filter(|s| !s.trim().is_empty());
 
-        Ok(Self::new(
+        let mut client = Self::with_token_provider(
             &url_base,
-            &token,
+            token_provider,
             &workspace_dir,
             Some(nc),
-            &maven_artifact,
-        ))
+            super::FEATHR_MAVEN_ARTIFACT,
+        )
+        .with_submission_mode(submission_mode, notebook_dir.as_deref());
+        client.maven_artifact =
+            super::select_maven_artifact(&var_source, client.get_spark_runtime()).await?;
+        debug!("Maven artifact: {}", client.maven_artifact);
+        Ok(client)
+    }
+
+    /**
+     * Spark runtime of the new cluster in the config template, or of the existing cluster
+     */
+    pub async fn get_spark_runtime(&self) -> Result<SparkRuntime, Error> {
+        let spark_version = match &self.cluster {
+            Cluster::NewCluster(nc) => nc.spark_version.clone(),
+            Cluster::ExistingClusterId(id) => {
+                let url = format!("{}/api/2.0/clusters/get?cluster_id={}", self.workspace_url, id);
+                let resp: ClusterInfo = self.send(|c| c.get(&url)).await?.json().await?;
+                resp.spark_version
+            }
+        };
+        SparkRuntime::from_databricks_version(&spark_version)
     }
 }
 
@@ -219,34 +488,137 @@ struct RunState {
 }
 
 #[derive(Clone, Debug, Deserialize)]
-struct RunMetadata {
-    state: RunState,
-    cluster_spec: ClusterSpec,
+struct TaskRun {
+    run_id: u64,
     // Other fields omitted
 }
 
+#[derive(Clone, Debug, Default, Deserialize)]
+struct RunParameters {
+    #[serde(default)]
+    jar_params: Vec<String>,
+    #[serde(default)]
+    python_params: Vec<String>,
+    #[serde(default)]
+    notebook_params: HashMap<String, String>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
-struct ClusterSpec {
-    #[serde(flatten)]
-    cluster: Cluster,
+struct RunInfo {
+    job_id: u64,
+    state: RunState,
+    run_page_url: Option<String>,
+    #[serde(default)]
+    tasks: Vec<TaskRun>,
+    #[serde(default)]
+    overriding_parameters: RunParameters,
+    // Other fields omitted
+}
+
+impl RunInfo {
+    fn get_status(&self) -> JobStatus {
+        match self.state.life_cycle_state {
+            RunLifeCycleState::Pending => JobStatus::Starting,
+            RunLifeCycleState::Running | RunLifeCycleState::Terminating => JobStatus::Running,
+            RunLifeCycleState::Terminated => match self.state.result_state {
+                Some(RunResultState::Success) => JobStatus::Success,
+                _ => JobStatus::Failed,
+            },
+            RunLifeCycleState::Skipped | RunLifeCycleState::InternalError => JobStatus::Failed,
+        }
+    }
+
+    /**
+     * The output path is passed to the run as the parameter following `--output`
+     */
+    fn get_output(&self) -> Option<String> {
+        let overriding = &self.overriding_parameters;
+        let params = if !overriding.jar_params.is_empty() {
+            overriding.jar_params.to_owned()
+        } else if !overriding.python_params.is_empty() {
+            overriding.python_params.to_owned()
+        } else {
+            overriding
+                .notebook_params
+                .get(NOTEBOOK_ARGS_PARAM)
+                .and_then(|args| serde_json::from_str(args).ok())
+                .unwrap_or_default()
+        };
+        params
+            .iter()
+            .position(|p| p == "--output")
+            .and_then(|idx| params.get(idx + 1))
+            .filter(|o| !o.is_empty())
+            .cloned()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
 struct GetRunOutputResponse {
-    metadata: RunMetadata,
     error: Option<String>,
     logs: Option<String>,
     error_trace: Option<String>,
 }
 
+/**
+ * Every Feathr project has a Databricks job per job kind and settings, runs are triggered with
+ * per-run parameters
+ */
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct JobSettings {
+    name: String,
+    tasks: Vec<TaskSettings>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    max_concurrent_runs: u32,
+}
+
+impl JobSettings {
+    /**
+     * Append the fingerprint of the settings to the job name, so changed settings get a new job
+     * instead of resetting the one other runs were triggered from
+     */
+    fn versioned(mut self) -> Result<Self, Error> {
+        let fingerprint = Uuid::new_v5(&Uuid::NAMESPACE_OID, &serde_json::to_vec(&self)?);
+        let fingerprint = fingerprint.as_simple().to_string();
+        self.name = format!("{}_{}", self.name, &fingerprint[..8]);
+        Ok(self)
+    }
+}
+
+/**
+ * Job listing doesn't include the tasks unless `expand_tasks` is set, only the id is needed
+ */
+#[derive(Clone, Debug, Deserialize)]
+struct JobInfo {
+    job_id: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ListJobsResponse {
+    #[serde(default)]
+    jobs: Vec<JobInfo>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct CreateJobResponse {
+    job_id: u64,
+}
+
 #[derive(Clone, Debug, Serialize)]
-struct SubmitRunRequest {
-    tasks: Vec<SubmitRunSettings>,
-    run_name: String,
+struct RunNowRequest {
+    job_id: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    jar_params: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    python_params: Vec<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    notebook_params: HashMap<String, String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
-struct SubmitRunSettings {
+struct TaskSettings {
     task_key: String,
     #[serde(flatten)]
     cluster: Cluster,
@@ -269,6 +641,14 @@ pub struct NewCluster {
     pub custom_tags: Option<HashMap<String, String>>,
 }
 
+/**
+ * Response of `clusters/get`, only the fields used by the client
+ */
+#[derive(Clone, Debug, Deserialize)]
+struct ClusterInfo {
+    spark_version: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Cluster {
@@ -287,6 +667,57 @@ enum SparkTask {
         python_file: String,
         parameters: Vec<String>,
     },
+    NotebookTask {
+        notebook_path: String,
+        base_parameters: HashMap<String, String>,
+    },
+}
+
+/**
+ * Feature join and generation jobs of a project never share a Databricks job
+ */
+fn job_name(name: &str, job_tags: &HashMap<String, String>, generation: bool) -> String {
+    match job_tags.get(PROJECT_TAG) {
+        Some(project) if generation => format!("feathr_{}_generation", project),
+        Some(project) => format!("feathr_{}_join", project),
+        None => name.to_string(),
+    }
+}
+
+/**
+ * The notebook sets `sys.argv` from the notebook parameter and then runs the PySpark driver, or
+ * the main class if the job has no driver
+ */
+fn notebook_source(main_python_script: Option<&str>, main_class_name: &str) -> String {
+    let driver = match main_python_script {
+        Some(code) => code.to_string(),
+        None => format!(
+            r#"from functools import reduce
+from pyspark.sql import SparkSession
+
+spark = SparkSession.builder.getOrCreate()
+args = spark._sc._gateway.new_array(spark._sc._jvm.java.lang.String, len(sys.argv) - 1)
+for i, arg in enumerate(sys.argv[1:]):
+    args[i] = arg
+reduce(getattr, "{}".split("."), spark._jvm).main(args)
+"#,
+            main_class_name
+        ),
+    };
+    format!(
+        r#"# Databricks notebook source
+import json
+import sys
+
+dbutils.widgets.text("{param}", "[]")
+sys.argv = ["feathr_notebook.py"] + json.loads(dbutils.widgets.get("{param}"))
+
+# COMMAND ----------
+
+{driver}"#,
+        param = NOTEBOOK_ARGS_PARAM,
+        driver = driver
+    )
 }
 
 #[allow(dead_code)]
@@ -309,26 +740,47 @@ enum Library {
 }
 
 #[derive(Clone, Debug, Deserialize)]
-struct SubmitRunResponse {
+struct DbfsHandle {
+    handle: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct DbfsBlock {
+    bytes_read: usize,
+    data: String,
+}
+
+/**
+ * DBFS API takes absolute paths without the `dbfs:` scheme
+ */
+fn dbfs_path(path: &str) -> String {
+    format!(
+        "/{}",
+        path.trim_start_matches("dbfs:").trim_start_matches('/')
+    )
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RunNowResponse {
     run_id: u64,
 }
 
 #[async_trait]
 impl JobClient for DatabricksClient {
     async fn write_remote_file(&self, path: &str, content: &[u8]) -> Result<String, Error> {
-        self.dbfs.write_file(path, content).await?;
+        self.write_dbfs_file(path, content).await?;
         Ok(path.to_string())
     }
 
     async fn read_remote_file(&self, path: &str) -> Result<Bytes, Error> {
-        Ok(self.dbfs.read_file(path).await?.into())
+        Ok(self.read_dbfs_file(path).await?.into())
     }
 
     async fn submit_job(
         &self,
         var_source: Arc<dyn VarSource + Send + Sync>,
         request: SubmitJobRequest,
-    ) -> Result<JobId, Error> {
+    ) -> Result<JobHandle, Error> {
         let args = self.get_arguments(var_source.clone(), &request).await?;
 
         let main_jar_path = if request.main_jar_path.is_none() {
@@ -370,28 +822,76 @@ impl JobClient for DatabricksClient {
         let py_files = self.multi_upload_or_get_url(&request.python_files).await?;
         debug!("Python files uploaded, URLs: {:#?}", py_files);
 
-        let task = if let Some(code) = request.main_python_script {
+        // Parameters are passed per run, so the job definition can be shared by all runs
+        let (task, run) = if self.submission_mode == DatabricksSubmissionMode::Notebook {
+            let notebook_path = self
+                .import_notebook(
+                    &request.name,
+                    &notebook_source(
+                        request.main_python_script.as_deref(),
+                        &request.main_class_name,
+                    ),
+                )
+                .await?;
+            debug!("Notebook: {}", notebook_path);
+            (
+                SparkTask::NotebookTask {
+                    notebook_path,
+                    base_parameters: [(NOTEBOOK_ARGS_PARAM.to_string(), "[]".to_string())]
+                        .into_iter()
+                        .collect(),
+                },
+                RunNowRequest {
+                    job_id: 0,
+                    jar_params: vec![],
+                    python_params: vec![],
+                    notebook_params: [(
+                        NOTEBOOK_ARGS_PARAM.to_string(),
+                        serde_json::to_string(&args)?,
+                    )]
+                    .into_iter()
+                    .collect(),
+                },
+            )
+        } else if let Some(code) = request.main_python_script {
+            // Name the script after its content so unchanged script doesn't change the job
             let py_url = self
                 .write_remote_file(
                     &self.get_remote_url(&format!(
                         "feathr_pyspark_driver_{}_{}.py",
                         request.name,
-                        request.job_key.as_simple()
+                        Uuid::new_v5(&Uuid::NAMESPACE_OID, code.as_bytes()).as_simple()
                     )),
                     code.as_bytes(),
                 )
                 .await?;
             debug!("Main executable file: {}", py_url);
-            SparkTask::SparkPythonTask {
-                python_file: py_url,
-                parameters: args,
-            }
+            (
+                SparkTask::SparkPythonTask {
+                    python_file: py_url,
+                    parameters: vec![],
+                },
+                RunNowRequest {
+                    job_id: 0,
+                    jar_params: vec![],
+                    python_params: args,
+                    notebook_params: Default::default(),
+                },
+            )
         } else {
             debug!("Main class name: {}", request.main_class_name);
-            SparkTask::SparkJarTask {
-                main_class_name: request.main_class_name,
-                parameters: args,
-            }
+            (
+                SparkTask::SparkJarTask {
+                    main_class_name: request.main_class_name,
+                    parameters: vec![],
+                },
+                RunNowRequest {
+                    job_id: 0,
+                    jar_params: args,
+                    python_params: vec![],
+                    notebook_params: Default::default(),
+                },
+            )
         };
 
         let mut libraries: Vec<Library> = jars.into_iter().map(|jar| Library::Jar(jar)).collect();
@@ -405,68 +905,67 @@ impl JobClient for DatabricksClient {
             });
         }
 
-        let cluster = match self.cluster.clone() {
-            Cluster::NewCluster(mut cluster) => {
-                cluster.custom_tags = if request.output.is_empty() {
-                    None
-                } else {
-                    let tags: HashMap<String, String> = [("output".to_string(), request.output)]
-                        .into_iter()
-                        .collect();
-                    Some(tags)
-                };
-                Cluster::NewCluster(cluster)
-            }
-            Cluster::ExistingClusterId(cluster_id) => Cluster::ExistingClusterId(cluster_id),
-        };
-
-        let job = SubmitRunRequest {
-            tasks: vec![SubmitRunSettings {
-                task_key: request.job_key.as_simple().to_string(),
-                cluster,
+        // Jobs of the same project and kind share the Databricks job, so the workspace doesn't get
+        // one job per feature join or generation
+        let job_name = job_name(
+            &request.name,
+            &request.job_tags,
+            request.job_config.is_generation(),
+        );
+        let settings = JobSettings {
+            name: job_name,
+            tasks: vec![TaskSettings {
+                task_key: TASK_KEY.to_string(),
+                cluster: self.cluster.clone(),
                 task,
                 libraries,
             }],
-            run_name: request.name,
+            // Output path is passed as run parameter, don't put it in job tags
+            tags: Default::default(),
+            // Materialization jobs submit one run per window
+            max_concurrent_runs: 100,
         };
+
+        let job_id = self.ensure_job(settings).await?;
+        let run = RunNowRequest { job_id, ..run };
         trace!(
-            "Job request: {}",
-            serde_json::to_string_pretty(&job).unwrap()
+            "Run request: {}",
+            serde_json::to_string_pretty(&run).unwrap()
         );
 
-        let url = format!("{}/jobs/runs/submit", self.url_base);
+        let url = format!("{}/jobs/run-now", self.url_base);
         debug!("URL: {}", url);
-        let text = self
-            .client
-            .post(url)
-            .json(&job)
-            .send()
-            .await?
-            .detailed_error_for_status()
-            .await?
-            .text()
-            .await?;
+        let text = self.send(|c| c.post(&url).json(&run)).await?.text().await?;
         debug!("Response: {}", text);
-        let resp: SubmitRunResponse = serde_json::from_str(&text)?;
-        debug!("Job submitted, id is {}", resp.run_id);
-        Ok(JobId(resp.run_id))
+        let resp: RunNowResponse = serde_json::from_str(&text)?;
+        debug!("Job {} triggered, run id is {}", job_id, resp.run_id);
+        // Run page URL is available as soon as the run is created
+        let url = self.get_run(resp.run_id).await.ok().and_then(|r| r.run_page_url);
+        Ok(JobHandle::new(JobId(resp.run_id), JobBackend::Databricks, &request.name).url(url))
     }
 
     async fn get_job_status(&self, job_id: JobId) -> Result<JobStatus, Error> {
-        Ok(self.get_run_status(job_id.0).await?.0)
+        Ok(self.get_run(job_id.0).await?.get_status())
     }
 
     async fn get_job_log(&self, job_id: JobId) -> Result<String, Error> {
-        Ok(self.get_run_status(job_id.0).await?.1)
+        self.get_run_log(job_id.0).await
     }
 
     async fn get_job_output_url(&self, job_id: JobId) -> Result<Option<String>, Error> {
-        Ok(self
-            .get_run_status(job_id.0)
-            .await?
-            .2
-            .map(|tags| tags.get("output").map(|v| v.to_owned()))
-            .flatten())
+        Ok(self.get_run(job_id.0).await?.get_output())
+    }
+
+    async fn get_job_metadata(&self, job_id: JobId) -> Result<HashMap<String, String>, Error> {
+        let run = self.get_run(job_id.0).await?;
+        let mut metadata: HashMap<String, String> =
+            [("databricks_job_id".to_string(), run.job_id.to_string())]
+                .into_iter()
+                .collect();
+        if let Some(url) = run.run_page_url {
+            metadata.insert(JOB_PAGE_URL_KEY.to_string(), url);
+        }
+        Ok(metadata)
     }
 
     async fn upload_or_get_url(&self, path: &str) -> Result<String, Error> {
@@ -522,8 +1021,8 @@ mod tests {
         ];
         println!("{}", serde_json::to_string_pretty(&lib).unwrap());
 
-        let x = SubmitRunSettings {
-            task_key: uuid::Uuid::new_v4().to_string(),
+        let x = TaskSettings {
+            task_key: TASK_KEY.to_string(),
             cluster: Cluster::NewCluster(NewCluster {
                 num_workers: 2,
                 spark_version: "9.1.x-scala2.12".to_string(),
@@ -540,6 +1039,68 @@ mod tests {
         println!("{}", serde_json::to_string_pretty(&x).unwrap());
     }
 
+    #[test]
+    fn run_output() {
+        let s = r#"{"job_id":1,"run_id":2,"state":{"life_cycle_state":"TERMINATED","result_state":"SUCCESS"},"run_page_url":"https://adb-1.azuredatabricks.net/?o=1#job/1/run/2","tasks":[{"run_id":3}],"overriding_parameters":{"jar_params":["--input","abc","--output","abfss://out"]}}"#;
+        let run: RunInfo = serde_json::from_str(s).unwrap();
+        assert_eq!(run.get_status(), JobStatus::Success);
+        assert_eq!(run.get_output(), Some("abfss://out".to_string()));
+        assert_eq!(run.tasks[0].run_id, 3);
+
+        let s = r#"{"job_id":1,"state":{"life_cycle_state":"RUNNING"},"overriding_parameters":{"notebook_params":{"feathr_args":"[\"--output\",\"dbfs:/out\"]"}}}"#;
+        let run: RunInfo = serde_json::from_str(s).unwrap();
+        assert_eq!(run.get_output(), Some("dbfs:/out".to_string()));
+    }
+
+    #[test]
+    fn job_names() {
+        let tags: HashMap<String, String> = [(PROJECT_TAG.to_string(), "p1".to_string())]
+            .into_iter()
+            .collect();
+        assert_eq!(job_name("j1", &tags, false), "feathr_p1_join");
+        assert_eq!(job_name("j1", &tags, true), "feathr_p1_generation");
+        assert_eq!(job_name("j1", &Default::default(), true), "j1");
+
+        let settings = |main_class_name: &str| JobSettings {
+            name: "feathr_p1_join".to_string(),
+            tasks: vec![TaskSettings {
+                task_key: TASK_KEY.to_string(),
+                cluster: Cluster::ExistingClusterId("c1".to_string()),
+                task: SparkTask::SparkJarTask {
+                    main_class_name: main_class_name.to_string(),
+                    parameters: vec![],
+                },
+                libraries: vec![],
+            }],
+            tags: Default::default(),
+            max_concurrent_runs: 100,
+        };
+        let v1 = settings("a.Main").versioned().unwrap().name;
+        assert!(v1.starts_with("feathr_p1_join_"));
+        assert_eq!(v1, settings("a.Main").versioned().unwrap().name);
+        assert_ne!(v1, settings("b.Main").versioned().unwrap().name);
+    }
+
+    #[test]
+    fn notebook_mode() {
+        assert_eq!(
+            "Notebook".parse::<DatabricksSubmissionMode>().unwrap(),
+            DatabricksSubmissionMode::Notebook
+        );
+        assert_eq!(
+            "".parse::<DatabricksSubmissionMode>().unwrap(),
+            DatabricksSubmissionMode::SparkTask
+        );
+        assert!("jar".parse::<DatabricksSubmissionMode>().is_err());
+
+        let source = notebook_source(None, "com.linkedin.feathr.offline.job.FeatureGenJob");
+        assert!(source.starts_with("# Databricks notebook source\n"));
+        assert!(source.contains(r#"dbutils.widgets.get("feathr_args")"#));
+        assert!(source.contains(r#"reduce(getattr, "com.linkedin.feathr.offline.job.FeatureGenJob".split("."), spark._jvm)"#));
+        let source = notebook_source(Some("print('driver')"), "unused");
+        assert!(source.ends_with("print('driver')"));
+    }
+
     #[test]
     fn cluster_conf() {
         #[derive(Debug, Deserialize)]
@@ -0,0 +1,185 @@
+use std::{fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+use azure_core::auth::{TokenCredential, TokenResponse};
+use azure_identity::DefaultAzureCredential;
+use chrono::{Duration, Utc};
+use futures::future::BoxFuture;
+use tokio::sync::Mutex;
+
+use crate::Error;
+
+/// Application id of Azure Databricks, the resource AAD tokens are requested for
+const AZURE_DATABRICKS_RESOURCE: &str = "2ff814a6-3304-4ab8-85cb-cd0e6f879c1d";
+
+/**
+ * Supplies the bearer token of the Databricks REST API.
+ * The token is requested before every API call, so providers should cache it, and `invalidate` is
+ * called when Databricks rejects the token so the next call gets a fresh one.
+ */
+#[async_trait]
+pub trait DatabricksTokenProvider: Debug + Send + Sync {
+    async fn get_token(&self) -> Result<String, Error>;
+
+    /**
+     * The token returned by the last `get_token` has been rejected, e.g. it expired or was rotated
+     */
+    async fn invalidate(&self) {}
+}
+
+/**
+ * Personal access token that never changes
+ */
+#[derive(Clone)]
+pub struct StaticToken(String);
+
+impl StaticToken {
+    pub fn new(token: &str) -> Self {
+        Self(token.to_string())
+    }
+}
+
+impl Debug for StaticToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StaticToken").finish()
+    }
+}
+
+#[async_trait]
+impl DatabricksTokenProvider for StaticToken {
+    async fn get_token(&self) -> Result<String, Error> {
+        Ok(self.0.clone())
+    }
+}
+
+/**
+ * AAD token of Azure Databricks, accepted by the REST API in place of a personal access token.
+ * The token is renewed 30 seconds before it expires.
+ */
+pub struct AadTokenProvider {
+    credential: Arc<dyn TokenCredential + Send + Sync>,
+    token: Mutex<Option<TokenResponse>>,
+}
+
+impl AadTokenProvider {
+    pub fn new(credential: Arc<dyn TokenCredential + Send + Sync>) -> Self {
+        Self {
+            credential,
+            token: Default::default(),
+        }
+    }
+}
+
+impl Default for AadTokenProvider {
+    fn default() -> Self {
+        Self::new(Arc::new(DefaultAzureCredential::default()))
+    }
+}
+
+impl Debug for AadTokenProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AadTokenProvider").finish()
+    }
+}
+
+#[async_trait]
+impl DatabricksTokenProvider for AadTokenProvider {
+    async fn get_token(&self) -> Result<String, Error> {
+        let mut token = self.token.lock().await;
+        let expired = token
+            .as_ref()
+            .map_or(true, |t| t.expires_on - Duration::seconds(30) < Utc::now());
+        if expired {
+            *token = Some(self.credential.get_token(AZURE_DATABRICKS_RESOURCE).await?);
+        }
+        Ok(token
+            .as_ref()
+            .map(|t| t.token.secret().to_owned())
+            .unwrap_or_default())
+    }
+
+    async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+}
+
+pub type TokenRefreshFn = dyn Fn() -> BoxFuture<'static, Result<String, Error>> + Send + Sync;
+
+/**
+ * Calls back for a token the first time one is needed and every time the previous one is rejected,
+ * e.g. to read a rotated token from a secret store
+ */
+pub struct TokenCallback {
+    refresh: Arc<TokenRefreshFn>,
+    token: Mutex<Option<String>>,
+}
+
+impl TokenCallback {
+    pub fn new<F>(refresh: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Result<String, Error>> + Send + Sync + 'static,
+    {
+        Self {
+            refresh: Arc::new(refresh),
+            token: Default::default(),
+        }
+    }
+
+    /**
+     * Start with a known token, the callback is only called after it's rejected
+     */
+    pub fn with_token(self, token: &str) -> Self {
+        Self {
+            token: Mutex::new(Some(token.to_string())),
+            ..self
+        }
+    }
+}
+
+impl Debug for TokenCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenCallback").finish()
+    }
+}
+
+#[async_trait]
+impl DatabricksTokenProvider for TokenCallback {
+    async fn get_token(&self) -> Result<String, Error> {
+        let mut token = self.token.lock().await;
+        if let Some(t) = token.as_ref() {
+            return Ok(t.clone());
+        }
+        let t = (self.refresh)().await?;
+        *token = Some(t.clone());
+        Ok(t)
+    }
+
+    async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_callback() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let c = calls.clone();
+        let provider = TokenCallback::new(move || {
+            let n = c.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(format!("token{}", n)) }.boxed()
+        });
+        assert_eq!(provider.get_token().await.unwrap(), "token0");
+        assert_eq!(provider.get_token().await.unwrap(), "token0");
+        provider.invalidate().await;
+        assert_eq!(provider.get_token().await.unwrap(), "token1");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(format!("{:?}", StaticToken::new("secret")), "StaticToken");
+    }
+}
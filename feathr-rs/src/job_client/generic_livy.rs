@@ -0,0 +1,410 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::debug;
+
+use super::{
+    azure_synapse::get_spark_ui_url,
+    staging::{AdlsStager, FileStager, WebHdfsStager},
+    SubmitJobRequest,
+};
+use crate::{
+    livy_client::{BasicAuthenticator, LivyClient, LivyStates, SparkRequest},
+    JobBackend, JobClient, JobHandle, JobId, JobStatus, VarSource, JOB_PAGE_URL_KEY,
+};
+
+static NOOP_JAR: &'static [u8] = include_bytes!("../../template/noop-1.0.jar");
+
+/**
+ * Authentication method of the Livy endpoint
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LivyAuth {
+    None,
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
+
+/**
+ * Files uploaded for a job, the executable is either the PySpark driver or the main jar
+ */
+#[derive(Clone, Debug, Default)]
+struct StagedFiles {
+    executable: String,
+    files: Vec<String>,
+    jars: Vec<String>,
+    py_files: Vec<String>,
+}
+
+/**
+ * Runs jobs on any Livy server, e.g. HDInsight or on-premise clusters.
+ *
+ * Files are staged by a `FileStager` in a workspace dir accessible from the cluster, an ADLS Gen2
+ * dir or an HDFS dir via WebHDFS.
+ */
+#[derive(Debug)]
+pub struct GenericLivyClient {
+    livy_client: LivyClient<Option<BasicAuthenticator>>,
+    stager: Arc<dyn FileStager>,
+    maven_artifact: String,
+}
+
+impl GenericLivyClient {
+    /**
+     * Stage files in the ADLS Gen2 `workspace_dir` with the storage account key
+     */
+    pub fn new(
+        livy_url: &str,
+        auth: LivyAuth,
+        storage_key: &str,
+        workspace_dir: &str,
+    ) -> Result<Self, crate::Error> {
+        Ok(Self::with_stager(
+            livy_url,
+            auth,
+            Arc::new(AdlsStager::new(storage_key, workspace_dir)?),
+        ))
+    }
+
+    pub fn with_stager(livy_url: &str, auth: LivyAuth, stager: Arc<dyn FileStager>) -> Self {
+        let authenticator = match auth {
+            LivyAuth::None => None,
+            LivyAuth::Basic { username, password } => {
+                Some(BasicAuthenticator::new(&username, password.as_deref()))
+            }
+        };
+        Self {
+            livy_client: LivyClient::<Option<BasicAuthenticator>>::with_authenticator(
+                reqwest::Client::new(),
+                livy_url,
+                // Driver logs are fetched via the standard Livy API, the log base is not used
+                livy_url,
+                authenticator,
+            ),
+            stager,
+            maven_artifact: super::FEATHR_MAVEN_ARTIFACT.to_string(),
+        }
+    }
+
+    pub(crate) async fn from_var_source(
+        var_source: Arc<dyn VarSource + Send + Sync>,
+    ) -> Result<Self, crate::Error> {
+        let auth = match var_source
+            .get_environment_variable(&["spark_config", "generic_livy", "auth"])
+            .await
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "none" => LivyAuth::None,
+            "basic" => LivyAuth::Basic {
+                username: var_source
+                    .get_environment_variable(&["spark_config", "generic_livy", "username"])
+                    .await?,
+                password: var_source
+                    .get_environment_variable(&["LIVY_PASSWORD"])
+                    .await
+                    .ok(),
+            },
+            s => {
+                return Err(crate::Error::InvalidOption(
+                    "spark_config.generic_livy.auth".to_string(),
+                    s.to_string(),
+                ))
+            }
+        };
+        let workspace_dir = var_source
+            .get_environment_variable(&["spark_config", "generic_livy", "workspace_dir"])
+            .await?;
+        let stager: Arc<dyn FileStager> = match var_source
+            .get_environment_variable(&["spark_config", "generic_livy", "storage"])
+            .await
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "adls" => Arc::new(AdlsStager::new(
+                &var_source.get_environment_variable(&["ADLS_KEY"]).await?,
+                &workspace_dir,
+            )?),
+            "webhdfs" => Arc::new(WebHdfsStager::new(
+                &var_source
+                    .get_environment_variable(&["spark_config", "generic_livy", "webhdfs_url"])
+                    .await?,
+                &workspace_dir,
+                var_source
+                    .get_environment_variable(&["spark_config", "generic_livy", "webhdfs_user"])
+                    .await
+                    .ok()
+                    .as_deref(),
+            )?),
+            s => {
+                return Err(crate::Error::InvalidOption(
+                    "spark_config.generic_livy.storage".to_string(),
+                    s.to_string(),
+                ))
+            }
+        };
+        let mut client = Self::with_stager(
+            &var_source
+                .get_environment_variable(&["spark_config", "generic_livy", "livy_url"])
+                .await?,
+            auth,
+            stager,
+        );
+        // Livy doesn't tell the Spark version, it's only known if `spark_config.spark_runtime` is set
+        client.maven_artifact = super::select_maven_artifact(&var_source, async {
+            Err(crate::Error::MissingOption(
+                "spark_config.spark_runtime".to_string(),
+            ))
+        })
+        .await?;
+        Ok(client)
+    }
+
+    /**
+     * Livy batch of the job, `main_jar` tells if the Feathr runtime is one of the staged jars,
+     * otherwise it's pulled from Maven
+     */
+    fn spark_request(
+        &self,
+        request: SubmitJobRequest,
+        args: Vec<String>,
+        main_jar: bool,
+        staged: StagedFiles,
+    ) -> SparkRequest {
+        let mut conf = request.configuration;
+        if !main_jar {
+            let v = match conf.get("spark.jars.packages") {
+                Some(v) => format!("{},{}", v, self.maven_artifact),
+                None => self.maven_artifact.clone(),
+            };
+            conf.insert("spark.jars.packages".to_string(), v);
+        }
+
+        // Cluster size is left to the server defaults, plain Livy doesn't know Synapse node sizes
+        SparkRequest {
+            args,
+            class_name: if request.main_python_script.is_none() {
+                request.main_class_name
+            } else {
+                Default::default()
+            },
+            conf,
+            cluster_size: request.cluster_size.unwrap_or_default(),
+            file: staged.executable,
+            files: staged.files,
+            jars: staged.jars,
+            name: request.name,
+            py_files: staged.py_files,
+            tags: request.job_tags,
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait]
+impl JobClient for GenericLivyClient {
+    async fn write_remote_file(&self, path: &str, content: &[u8]) -> Result<String, crate::Error> {
+        self.stager.write_file(path, content).await
+    }
+
+    async fn submit_job(
+        &self,
+        var_source: Arc<dyn VarSource + Send + Sync>,
+        mut request: SubmitJobRequest,
+    ) -> Result<JobHandle, crate::Error> {
+        let args = self.get_arguments(var_source.clone(), &request).await?;
+
+        let main_jar_path = if request.main_jar_path.is_none() {
+            var_source
+                .get_environment_variable(&[
+                    "spark_config",
+                    "generic_livy",
+                    "feathr_runtime_location",
+                ])
+                .await
+                .ok()
+        } else {
+            request.main_jar_path.clone()
+        };
+
+        let mut orig_files: Vec<String> = vec![];
+        let mut orig_jars: Vec<String> = match main_jar_path.clone() {
+            Some(p) => vec![p],
+            None => {
+                if request.main_python_script.is_none() {
+                    let noop_jar = self
+                        .get_remote_url(&format!("noop_{}_{}.jar", request.name, request.job_key));
+                    self.write_remote_file(&noop_jar, NOOP_JAR).await?;
+                    vec![noop_jar]
+                } else {
+                    vec![]
+                }
+            }
+        };
+
+        for f in std::mem::take(&mut request.reference_files) {
+            if f.ends_with(".jar") {
+                orig_jars.push(f)
+            } else {
+                orig_files.push(f)
+            }
+        }
+
+        let jars = self.multi_upload_or_get_url(&orig_jars).await?;
+        let files = self.multi_upload_or_get_url(&orig_files).await?;
+        let py_files = self.multi_upload_or_get_url(&request.python_files).await?;
+
+        let executable = if let Some(code) = request.main_python_script.clone() {
+            self.write_remote_file(
+                &self.get_remote_url(&format!(
+                    "feathr_pyspark_driver_{}_{}.py",
+                    request.name, request.job_key
+                )),
+                code.as_bytes(),
+            )
+            .await?
+        } else {
+            jars[0].clone()
+        };
+
+        debug!("Main executable file: {}", executable);
+
+        let name = request.name.clone();
+        let job = self.spark_request(
+            request,
+            args,
+            main_jar_path.is_some(),
+            StagedFiles {
+                executable,
+                files,
+                jars,
+                py_files,
+            },
+        );
+        debug!("Job request: {:#?}", job);
+        let job = self.livy_client.create_batch_job(job).await?;
+        debug!("Job submitted, id is {}", job.id);
+        Ok(
+            JobHandle::new(JobId(job.id), JobBackend::GenericLivy, &name)
+                .url(get_spark_ui_url(&job)),
+        )
+    }
+
+    async fn get_job_status(&self, job_id: JobId) -> Result<JobStatus, crate::Error> {
+        let state: LivyStates = self.livy_client.get_batch_job(job_id.0).await?.state;
+        Ok(state.into())
+    }
+
+    async fn get_job_log(&self, job_id: JobId) -> Result<String, crate::Error> {
+        Ok(self
+            .livy_client
+            .get_batch_job_log(job_id.0)
+            .await?
+            .join("\n"))
+    }
+
+    async fn get_job_output_url(&self, job_id: JobId) -> Result<Option<String>, crate::Error> {
+        let job = self.livy_client.get_batch_job(job_id.0).await?;
+        Ok(job
+            .tags
+            .map(|t| t.get(super::OUTPUT_PATH_TAG).map(|s| s.to_owned()))
+            .flatten())
+    }
+
+    async fn get_job_metadata(
+        &self,
+        job_id: JobId,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        let job = self.livy_client.get_batch_job(job_id.0).await?;
+        let mut metadata: HashMap<String, String> = job
+            .app_info
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(k, v)| v.map(|v| (k, v)))
+            .collect();
+        if let Some(url) = get_spark_ui_url(&job) {
+            metadata.insert(JOB_PAGE_URL_KEY.to_string(), url);
+        }
+        Ok(metadata)
+    }
+
+    async fn read_remote_file(&self, url: &str) -> Result<Bytes, crate::Error> {
+        self.stager.read_file(url).await
+    }
+
+    fn get_remote_url(&self, filename: &str) -> String {
+        self.stager.get_remote_url(filename)
+    }
+
+    fn is_url_on_storage(&self, url: &str) -> bool {
+        self.stager.is_url_on_storage(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> GenericLivyClient {
+        GenericLivyClient::with_stager(
+            "http://livy:8998",
+            LivyAuth::None,
+            Arc::new(WebHdfsStager::new("http://nn:9870", "hdfs://nn:8020/ws", None).unwrap()),
+        )
+    }
+
+    #[test]
+    fn spark_request() {
+        let client = client();
+        assert_eq!(client.get_remote_url("a.py"), "hdfs://nn:8020/ws/a.py");
+        assert!(client.is_url_on_storage("hdfs://nn:8020/ws/a.py"));
+        assert!(!client.is_url_on_storage("/tmp/a.py"));
+
+        let request = SubmitJobRequest {
+            name: "j1".to_string(),
+            main_class_name: "com.linkedin.feathr.offline.job.FeatureJoinJob".to_string(),
+            configuration: [("spark.jars.packages".to_string(), "a:b:1".to_string())]
+                .into_iter()
+                .collect(),
+            job_tags: [("k".to_string(), "v".to_string())].into_iter().collect(),
+            ..Default::default()
+        };
+        let staged = StagedFiles {
+            executable: "hdfs://nn:8020/ws/noop.jar".to_string(),
+            jars: vec!["hdfs://nn:8020/ws/noop.jar".to_string()],
+            ..Default::default()
+        };
+        let job = client.spark_request(
+            request.clone(),
+            vec!["--input".to_string()],
+            false,
+            staged.clone(),
+        );
+        assert_eq!(job.name, "j1");
+        assert_eq!(job.file, "hdfs://nn:8020/ws/noop.jar");
+        assert_eq!(job.class_name, request.main_class_name);
+        assert_eq!(job.args, vec!["--input"]);
+        assert_eq!(job.tags["k"], "v");
+        // The runtime is pulled from Maven along with the configured packages
+        assert_eq!(
+            job.conf["spark.jars.packages"],
+            format!("a:b:1,{}", client.maven_artifact)
+        );
+
+        // PySpark jobs have no main class, the runtime jar is staged
+        let request = SubmitJobRequest {
+            main_python_script: Some("print()".to_string()),
+            configuration: Default::default(),
+            ..request
+        };
+        let job = client.spark_request(request, vec![], true, staged);
+        assert!(job.class_name.is_empty());
+        assert!(!job.conf.contains_key("spark.jars.packages"));
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use log::debug;
+
+use crate::{
+    Error, JobBackend, JobClient, JobHandle, JobId, JobStatus, SubmitJobRequest, VarSource,
+};
+
+const DEFAULT_STATUSES: &[JobStatus] =
+    &[JobStatus::Starting, JobStatus::Running, JobStatus::Success];
+
+/**
+ * Job submitted to the `MockJobClient`
+ */
+#[derive(Clone, Debug)]
+pub struct MockJob {
+    pub handle: JobHandle,
+    pub request: SubmitJobRequest,
+    /// Arguments the job would be started with on a real cluster
+    pub arguments: Vec<String>,
+    /// Statuses returned by the following status checks, the last one is kept once reached
+    statuses: VecDeque<JobStatus>,
+}
+
+/**
+ * Runs nothing, for testing pipelines without a Spark cluster.
+ *
+ * Files are staged in a local directory and jobs are recorded along with the arguments they would
+ * be started with, job ids start from 1 in the order of submission. Each status check of a job
+ * returns the next status in its script, `Starting`, `Running` and then `Success` by default.
+ */
+#[derive(Debug)]
+pub struct MockJobClient {
+    workspace_dir: PathBuf,
+    next_id: AtomicU64,
+    statuses: Mutex<Vec<JobStatus>>,
+    jobs: Mutex<HashMap<JobId, MockJob>>,
+}
+
+impl MockJobClient {
+    pub fn new<T>(workspace_dir: T) -> Self
+    where
+        T: AsRef<Path>,
+    {
+        Self {
+            workspace_dir: workspace_dir.as_ref().to_owned(),
+            next_id: AtomicU64::new(1),
+            statuses: Mutex::new(DEFAULT_STATUSES.to_vec()),
+            jobs: Default::default(),
+        }
+    }
+
+    /**
+     * Read `spark_config.mock.workspace_dir`, default to `feathr_mock` under the temp dir, and the
+     * status script from `spark_config.mock.statuses`, e.g. `Starting,Running,Failed`
+     */
+    pub(crate) async fn from_var_source(
+        var_source: Arc<dyn VarSource + Send + Sync>,
+    ) -> Result<Self, crate::Error> {
+        let workspace_dir = match var_source
+            .get_environment_variable(&["spark_config", "mock", "workspace_dir"])
+            .await
+        {
+            Ok(dir) if !dir.trim().is_empty() => PathBuf::from(dir.trim()),
+            _ => std::env::temp_dir().join("feathr_mock"),
+        };
+        let client = Self::new(workspace_dir);
+        if let Ok(statuses) = var_source
+            .get_environment_variable(&["spark_config", "mock", "statuses"])
+            .await
+        {
+            client.set_statuses(&parse_statuses(&statuses)?);
+        }
+        Ok(client)
+    }
+
+    /**
+     * Status script of the jobs submitted after this call, an empty script means `Success`
+     */
+    pub fn set_statuses(&self, statuses: &[JobStatus]) {
+        *self.statuses.lock().unwrap() = statuses.to_vec();
+    }
+
+    /**
+     * Replace the remaining status script of a submitted job
+     */
+    pub fn set_job_statuses(&self, job_id: JobId, statuses: &[JobStatus]) -> Result<(), Error> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&job_id).ok_or(Error::UnknownJob(job_id.0))?;
+        job.statuses = statuses.iter().copied().collect();
+        Ok(())
+    }
+
+    pub fn get_job(&self, job_id: JobId) -> Option<MockJob> {
+        self.jobs.lock().unwrap().get(&job_id).cloned()
+    }
+
+    /**
+     * All submitted jobs in the order of submission
+     */
+    pub fn get_jobs(&self) -> Vec<MockJob> {
+        let mut jobs: Vec<MockJob> = self.jobs.lock().unwrap().values().cloned().collect();
+        jobs.sort_by_key(|j| j.handle.id);
+        jobs
+    }
+
+    fn local_path(&self, url: &str) -> PathBuf {
+        PathBuf::from(url.trim_start_matches("file://"))
+    }
+}
+
+fn parse_statuses(s: &str) -> Result<Vec<JobStatus>, Error> {
+    // Either a comma separated string or a YAML list
+    let names: Vec<String> = serde_yaml::from_str::<Vec<String>>(s)
+        .unwrap_or_else(|_| s.split(',').map(ToString::to_string).collect());
+    names
+        .iter()
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty())
+        .map(|n| match n.to_lowercase().as_str() {
+            "starting" => Ok(JobStatus::Starting),
+            "running" => Ok(JobStatus::Running),
+            "success" => Ok(JobStatus::Success),
+            "failed" => Ok(JobStatus::Failed),
+            _ => Err(Error::InvalidOption(
+                "spark_config.mock.statuses".to_string(),
+                n.to_string(),
+            )),
+        })
+        .collect()
+}
+
+#[async_trait]
+impl JobClient for MockJobClient {
+    async fn write_remote_file(&self, path: &str, content: &[u8]) -> Result<String, crate::Error> {
+        let local_path = self.local_path(path);
+        if let Some(dir) = local_path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&local_path, content).await?;
+        Ok(path.to_string())
+    }
+
+    async fn read_remote_file(&self, path: &str) -> Result<Bytes, crate::Error> {
+        Ok(Bytes::from(tokio::fs::read(self.local_path(path)).await?))
+    }
+
+    async fn submit_job(
+        &self,
+        var_source: Arc<dyn VarSource + Send + Sync>,
+        request: SubmitJobRequest,
+    ) -> Result<JobHandle, crate::Error> {
+        // Configs are staged and secrets are resolved as if the job was submitted to a cluster
+        let arguments = self.get_arguments(var_source, &request).await?;
+        let id = JobId(self.next_id.fetch_add(1, Ordering::SeqCst));
+        let handle = JobHandle::new(id, JobBackend::Mock, &request.name);
+        debug!("Mock job {} submitted, arguments: {:?}", id, arguments);
+        let statuses = self.statuses.lock().unwrap().iter().copied().collect();
+        self.jobs.lock().unwrap().insert(
+            id,
+            MockJob {
+                handle: handle.clone(),
+                request,
+                arguments,
+                statuses,
+            },
+        );
+        Ok(handle)
+    }
+
+    async fn get_job_status(&self, job_id: JobId) -> Result<JobStatus, crate::Error> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&job_id).ok_or(Error::UnknownJob(job_id.0))?;
+        Ok(if job.statuses.len() > 1 {
+            job.statuses.pop_front().unwrap_or(JobStatus::Success)
+        } else {
+            job.statuses.front().copied().unwrap_or(JobStatus::Success)
+        })
+    }
+
+    async fn get_job_log(&self, job_id: JobId) -> Result<String, crate::Error> {
+        let job = self.get_job(job_id).ok_or(Error::UnknownJob(job_id.0))?;
+        Ok(format!(
+            "Mock job {} `{}`\nArguments: {}",
+            job_id,
+            job.handle.name,
+            job.arguments.join(" ")
+        ))
+    }
+
+    async fn get_job_output_url(&self, job_id: JobId) -> Result<Option<String>, crate::Error> {
+        let job = self.get_job(job_id).ok_or(Error::UnknownJob(job_id.0))?;
+        Ok(job.request.job_tags.get(super::OUTPUT_PATH_TAG).cloned())
+    }
+
+    fn get_remote_url(&self, filename: &str) -> String {
+        format!("file://{}", self.workspace_dir.join(filename).display())
+    }
+
+    fn is_url_on_storage(&self, url: &str) -> bool {
+        url.starts_with("file://")
+    }
+
+    fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_job_client() {
+        let dir = std::env::temp_dir().join(format!("feathr_mock_{}", uuid::Uuid::new_v4()));
+        let client = MockJobClient::new(&dir);
+        let url = client.get_remote_url("features.conf");
+        assert!(client.is_url_on_storage(&url));
+        client
+            .write_remote_file(&url, b"anchors: {}")
+            .await
+            .unwrap();
+        assert_eq!(
+            client.read_remote_file(&url).await.unwrap(),
+            Bytes::from_static(b"anchors: {}")
+        );
+
+        let var_source = crate::new_var_source("");
+        let request = SubmitJobRequest {
+            name: "job".to_string(),
+            job_config_file_name: "job.conf".to_string(),
+            job_config: crate::JobConfig::Rendered {
+                generation: true,
+                content: "{}".to_string(),
+            },
+            ..Default::default()
+        };
+        let h1 = client
+            .submit_job(var_source.clone(), request.clone())
+            .await
+            .unwrap();
+        client.set_statuses(&parse_statuses("Running, FAILED").unwrap());
+        let h2 = client.submit_job(var_source, request).await.unwrap();
+        assert!(dir.join("job.conf").exists());
+        assert_eq!((h1.id, h2.id), (JobId(1), JobId(2)));
+
+        for expected in [
+            JobStatus::Starting,
+            JobStatus::Running,
+            JobStatus::Success,
+            JobStatus::Success,
+        ] {
+            assert_eq!(client.get_job_status(h1.id).await.unwrap(), expected);
+        }
+        assert_eq!(
+            client.wait_for_job(h2.id, None).await.unwrap(),
+            JobStatus::Failed
+        );
+        assert!(client.get_job_status(JobId(3)).await.is_err());
+        assert!(parse_statuses("[Starting, Done]").is_err());
+        assert_eq!(client.get_jobs().len(), 2);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_share_feature_configs() {
+        let dir = std::env::temp_dir().join(format!("feathr_mock_{}", uuid::Uuid::new_v4()));
+        let client = MockJobClient::new(&dir);
+        let request = SubmitJobRequest {
+            job_key: uuid::Uuid::new_v4(),
+            name: "job".to_string(),
+            feature_config: serde_json::from_str(r#"{"anchors": {"g1": {}}}"#).unwrap(),
+            job_config_file_name: "job.conf".to_string(),
+            job_config: crate::JobConfig::Rendered {
+                generation: true,
+                content: "{}".to_string(),
+            },
+            ..Default::default()
+        };
+        let mut other = request.clone();
+        other.job_key = uuid::Uuid::new_v4();
+        other.feature_config = Default::default();
+        let mut requests = vec![request.clone(), request, other];
+        client.share_feature_configs(&mut requests).await.unwrap();
+        let urls: Vec<String> = requests
+            .iter()
+            .map(|r| r.feature_config_url.clone().unwrap())
+            .collect();
+        assert_eq!(urls[0], urls[1]);
+        assert_ne!(urls[0], urls[2]);
+
+        let job = client
+            .submit_job(crate::new_var_source(""), requests.remove(1))
+            .await
+            .unwrap();
+        let arguments = client.get_job(job.id).unwrap().arguments;
+        let pos = arguments
+            .iter()
+            .position(|a| a == "--feature-config")
+            .unwrap();
+        assert_eq!(arguments[pos + 1], urls[0]);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,5 +1,11 @@
 mod azure_synapse;
 mod databricks;
+mod databricks_token;
+mod generic_livy;
+#[cfg(feature = "testing")]
+mod mock;
+mod spark_runtime;
+mod staging;
 
 use std::{collections::HashMap, fs::File, io::Read, path::Path, sync::Arc, time::Instant};
 
@@ -7,28 +13,48 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{DateTime, Duration, Utc};
 use handlebars::Handlebars;
-use log::{debug, trace};
+use log::{debug, info, trace};
 use reqwest::Url;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::instrument;
 use uuid::Uuid;
 
+use crate::materialization::PARTITION_OVERWRITE_MODE;
+use crate::open_lineage::{JobLineage, LineageDataset};
 use crate::{
-    load_var_source, DateTimeResolution, Error, MaterializationSettingsBuilder, OutputSink,
-    VarSource, GetSecretKeys, DataLocation,
+    load_var_source, ClusterSize, DateTimeResolution, Error, FeatureConfig, FeatureJoinConfig,
+    JobConfig, MaterializationSettingsBuilder, OutputSink, VarSource, GetSecretKeys, DataLocation,
+    CoercionWarning, QueryCost, SinkSelection, FeatureConfigBatch, GenericSink, DEFAULT_MAX_FEATURE_CONFIG_SIZE,
+    RenderedConfig, SaveMode, WindowOptions,
 };
 
 pub use azure_synapse::AzureSynapseClient;
-pub use databricks::DatabricksClient;
+pub use databricks::{DatabricksClient, DatabricksSubmissionMode};
+pub use databricks_token::{
+    AadTokenProvider, DatabricksTokenProvider, StaticToken, TokenCallback, TokenRefreshFn,
+};
+pub use generic_livy::{GenericLivyClient, LivyAuth};
+#[cfg(feature = "testing")]
+pub use mock::{MockJob, MockJobClient};
+pub use spark_runtime::{ArtifactCompatibility, SparkRuntime, COMPATIBILITY_MATRIX};
+pub use staging::{AdlsStager, FileStager, WebHdfsStager};
+pub(crate) use spark_runtime::select_maven_artifact;
 
 pub(crate) const OUTPUT_PATH_TAG: &str = "output_path";
+/// Job tag of jobs built from a project, the name of the project
+pub const PROJECT_TAG: &str = "feathr_project";
+/// Job tag of generation jobs split by the size of the feature config, e.g. `2/3`
+pub const BATCH_TAG: &str = "feathr_batch";
+/// Job metadata key of the URL to the job page on the Spark cluster
+pub const JOB_PAGE_URL_KEY: &str = "job_page_url";
 pub(crate) const JOIN_JOB_MAIN_CLASS_NAME: &str = "com.linkedin.feathr.offline.job.FeatureJoinJob";
 pub(crate) const GEN_JOB_MAIN_CLASS_NAME: &str = "com.linkedin.feathr.offline.job.FeatureGenJob";
 const PYTHON_TEMPLATE: &str = include_str!("../../template/feathr_pyspark_driver_template.py.hbr");
 
 const FEATHR_MAVEN_ARTIFACT: &str = "com.linkedin.feathr:feathr_2.12:0.4.0";
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct SubmitJobRequest {
     pub job_key: Uuid,
     pub name: String,
@@ -38,21 +64,66 @@ pub struct SubmitJobRequest {
     pub main_jar_path: Option<String>,
     pub main_class_name: String,
     pub main_python_script: Option<String>,
-    pub feature_config: String,
-    pub join_job_config: String,
-    pub gen_job_config: String,
+    #[serde(with = "crate::job_config::rendered_feature_config")]
+    pub feature_config: FeatureConfig,
+    /// URL of the feature config already uploaded, e.g. shared by all jobs of a backfill, the config
+    /// is uploaded along with the job if not set.
+    /// Not saved in job manifests, the resubmitted job uploads the config again
+    #[serde(skip)]
+    pub feature_config_url: Option<String>,
+    /// Saved as `join_job_config` and `gen_job_config` in job manifests
+    #[serde(flatten)]
+    pub job_config: JobConfig,
     pub python_files: Vec<String>,
     pub reference_files: Vec<String>,
     pub job_tags: HashMap<String, String>,
     // TODO:
     pub secret_key: Vec<String>,
     pub configuration: HashMap<String, String>,
+    /// Spark pool to run the job on instead of the configured one, only used by Azure Synapse
+    pub spark_pool: Option<String>,
+    /// Driver and executor resources, a medium size is used if not set
+    pub cluster_size: Option<ClusterSize>,
+    /// Not saved in job manifests, the resubmitted job doesn't create registry records again
+    #[serde(skip)]
+    pub materialization: Option<MaterializationInfo>,
+    /// Not saved in job manifests, the resubmitted job doesn't create registry records again
+    #[serde(skip)]
+    pub consumption: Option<ConsumptionInfo>,
+    /// Features and datasets of the job, reported to OpenLineage if configured
+    pub lineage: JobLineage,
+}
+
+/**
+ * What a feature generation job writes, used to register the materialization record once the job succeeded
+ */
+#[derive(Clone, Debug)]
+pub struct MaterializationInfo {
+    pub project_id: Uuid,
+    pub features: Vec<Uuid>,
+    pub sinks: Vec<OutputSink>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    /// Registry tags of the records, so they pass the same tag policies as the project
+    pub tags: HashMap<String, String>,
+}
+
+/**
+ * What a feature joining job reads and writes, used to register the output dataset as a consumer
+ * of the features once the job succeeded
+ */
+#[derive(Clone, Debug)]
+pub struct ConsumptionInfo {
+    pub project_id: Uuid,
+    pub features: Vec<Uuid>,
+    pub output: String,
 }
 
 /**
  * Spark Job Id
  */
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
 pub struct JobId(pub u64);
 
 impl std::fmt::Display for JobId {
@@ -61,6 +132,83 @@ impl std::fmt::Display for JobId {
     }
 }
 
+/**
+ * The Spark platform a job is submitted to, the meaning of `JobId` depends on it
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum JobBackend {
+    /// `JobId` is the Livy batch id
+    AzureSynapse,
+    /// `JobId` is the run id
+    Databricks,
+    /// `JobId` is the Livy batch id
+    GenericLivy,
+    /// `JobId` is assigned by the `MockJobClient` in the order of submission
+    #[cfg(feature = "testing")]
+    Mock,
+}
+
+impl std::fmt::Display for JobBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match &self {
+                JobBackend::AzureSynapse => "AzureSynapse",
+                JobBackend::Databricks => "Databricks",
+                JobBackend::GenericLivy => "GenericLivy",
+                #[cfg(feature = "testing")]
+                JobBackend::Mock => "Mock",
+            }
+        )
+    }
+}
+
+/**
+ * Submitted Spark job
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct JobHandle {
+    pub id: JobId,
+    pub backend: JobBackend,
+    /// Link to the job page, may not be available until the job is started
+    pub url: Option<String>,
+    pub name: String,
+    pub submitted_at: DateTime<Utc>,
+}
+
+impl JobHandle {
+    pub fn new(id: JobId, backend: JobBackend, name: &str) -> Self {
+        Self {
+            id,
+            backend,
+            url: None,
+            name: name.to_string(),
+            submitted_at: Utc::now(),
+        }
+    }
+
+    pub fn url<T>(mut self, url: Option<T>) -> Self
+    where
+        T: ToString,
+    {
+        self.url = url.map(|u| u.to_string());
+        self
+    }
+}
+
+impl From<JobHandle> for JobId {
+    fn from(h: JobHandle) -> Self {
+        h.id
+    }
+}
+
+impl std::fmt::Display for JobHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} job {}", self.backend, self.id)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum JobStatus {
     Starting,
@@ -115,7 +263,7 @@ where
         &self,
         var_source: Arc<dyn VarSource + Send + Sync>,
         request: SubmitJobRequest,
-    ) -> Result<JobId, crate::Error>;
+    ) -> Result<JobHandle, crate::Error>;
 
     /**
      * Get job status
@@ -132,6 +280,23 @@ where
      */
     async fn get_job_output_url(&self, job_id: JobId) -> Result<Option<String>, crate::Error>;
 
+    /**
+     * Get platform specific job metadata, e.g. `JOB_PAGE_URL_KEY`
+     */
+    async fn get_job_metadata(
+        &self,
+        _job_id: JobId,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        Ok(Default::default())
+    }
+
+    /**
+     * Get the link to the job page, it may not be available until the job is started
+     */
+    async fn get_job_url(&self, job_id: JobId) -> Result<Option<String>, crate::Error> {
+        Ok(self.get_job_metadata(job_id).await?.remove(JOB_PAGE_URL_KEY))
+    }
+
     /**
      * Construct remote URL for the filename
      */
@@ -153,6 +318,14 @@ where
         Ok(ret)
     }
 
+    /**
+     * Time between the status checks of `wait_for_job`
+     */
+    fn poll_interval(&self) -> std::time::Duration {
+        // Check every few seconds
+        std::time::Duration::from_secs(10)
+    }
+
     /**
      * Wait until the job is ended successfully or not
      */
@@ -174,8 +347,7 @@ where
                     }
                 }
             }
-            // Check every few seconds
-            tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+            tokio::time::sleep(self.poll_interval()).await;
         }
         Err(crate::Error::Timeout)
     }
@@ -243,6 +415,50 @@ where
         })
     }
 
+    /**
+     * Upload the rendered feature config of the request and returns the Spark compatible URL
+     */
+    async fn upload_feature_config(
+        &self,
+        request: &SubmitJobRequest,
+        content: &str,
+    ) -> Result<String, crate::Error> {
+        let url = self.get_remote_url(&format!(
+            "features_{}_{}.conf",
+            request.name,
+            request.job_key.as_simple()
+        ));
+        self.write_remote_file(&url, content.as_bytes()).await
+    }
+
+    /**
+     * Upload each distinct feature config of the requests only once and let the requests refer to
+     * it by URL, e.g. the jobs built by `SubmitGenerationJobRequestBuilder` for every step of a
+     * backfill share the same config
+     */
+    async fn share_feature_configs(
+        &self,
+        requests: &mut [SubmitJobRequest],
+    ) -> Result<(), crate::Error> {
+        let mut uploaded: HashMap<String, String> = Default::default();
+        for request in requests
+            .iter_mut()
+            .filter(|r| r.feature_config_url.is_none())
+        {
+            let content = request.feature_config.render()?;
+            let url = match uploaded.get(&content) {
+                Some(url) => url.to_owned(),
+                None => {
+                    let url = self.upload_feature_config(request, &content).await?;
+                    uploaded.insert(content, url.clone());
+                    url
+                }
+            };
+            request.feature_config_url = Some(url);
+        }
+        Ok(())
+    }
+
     /**
      * Generate arguments for the Spark job
      */
@@ -253,7 +469,21 @@ where
     ) -> Result<Vec<String>, crate::Error> {
         let mut secrets: HashMap<String, String> = Default::default();
         for secret in request.secret_key.iter() {
-            secrets.insert(secret.to_string(), var_source.get_environment_variable(&[secret]).await?);
+            if secrets.contains_key(secret) {
+                continue;
+            }
+            let value = match var_source.get_environment_variable(&[secret]).await {
+                Ok(value) => value,
+                // JDBC sources without their own credentials use the global ones
+                Err(e) => match global_jdbc_credential(secret) {
+                    Some(global) => var_source
+                        .get_environment_variable(&[global])
+                        .await
+                        .map_err(|_| e)?,
+                    None => return Err(e),
+                },
+            };
+            secrets.insert(secret.to_string(), value);
         }
         let mut ret: Vec<String> = vec![
             "--s3-config".to_string(),
@@ -270,20 +500,21 @@ where
             serde_json::to_string(&secrets)?,
         ];
 
-        let feature_config_url = self.get_remote_url(&format!(
-            "features_{}_{}.conf",
-            request.name, request.job_key.as_simple()
-        ));
-        let feature_config_url = self
-            .write_remote_file(&feature_config_url, &request.feature_config.as_bytes())
-            .await?;
+        let feature_config_url = match &request.feature_config_url {
+            Some(url) => url.to_owned(),
+            None => {
+                self.upload_feature_config(request, &request.feature_config.render()?)
+                    .await?
+            }
+        };
         ret.extend(vec!["--feature-config".to_string(), feature_config_url].into_iter());
 
         let job_config_url = self.get_remote_url(&request.job_config_file_name);
-        if request.gen_job_config.is_empty() {
+        let job_config = request.job_config.render()?;
+        if !request.job_config.is_generation() {
             // This is a feature joining job request
             let job_config_url = self
-                .write_remote_file(&job_config_url, &request.join_job_config.as_bytes())
+                .write_remote_file(&job_config_url, &job_config.as_bytes())
                 .await?;
             ret.extend(
                 vec![
@@ -301,7 +532,7 @@ where
         } else {
             // This is a feature generation job request
             let job_config_url = self
-                .write_remote_file(&job_config_url, &request.gen_job_config.as_bytes())
+                .write_remote_file(&job_config_url, &job_config.as_bytes())
                 .await?;
             ret.extend(
                 vec![
@@ -405,6 +636,11 @@ where
         .unwrap())
     }
 
+    /**
+     * Global JDBC settings, sources created with `JdbcSourceAuth` read their credentials from
+     * `{SOURCE_NAME}_USER`, `{SOURCE_NAME}_PASSWORD` and `{SOURCE_NAME}_TOKEN` and only fall back
+     * to these
+     */
     async fn get_sql_config(
         &self,
         var_source: Arc<dyn VarSource + Send + Sync>,
@@ -552,6 +788,39 @@ where
     }
 }
 
+/**
+ * Where a template snippet is inserted into the PySpark driver
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetPosition {
+    /// Right after the Spark session is created, e.g. `spark.conf.set(...)`
+    SparkSession,
+    /// After user files are imported and before UDFs are collected, e.g. UDF registration
+    Preamble,
+}
+
+/**
+ * User provided Handlebars fragment rendered into the PySpark driver, it can refer to
+ * `job_name` and the template variables set on the job builder or in the config
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateSnippet {
+    pub name: String,
+    pub position: SnippetPosition,
+    pub template: String,
+}
+
+impl TemplateSnippet {
+    pub fn new(name: &str, position: SnippetPosition, template: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            position,
+            template: template.to_string(),
+        }
+    }
+}
+
 /**
  * Builder to build a Spark Job submitting request
  */
@@ -564,18 +833,30 @@ pub struct SubmitJoiningJobRequestBuilder {
     python_files: Vec<String>,
     reference_files: Vec<String>,
     configuration: HashMap<String, String>,
-    feature_config: String,
-    feature_join_config: String,
+    spark_pool: Option<String>,
+    cluster_size: Option<ClusterSize>,
+    feature_config: FeatureConfig,
+    feature_join_config: FeatureJoinConfig,
     secret_keys: Vec<String>,
     user_functions: HashMap<String, String>,
+    snippets: Vec<TemplateSnippet>,
+    template_variables: HashMap<String, String>,
+    lineage: JobLineage,
+    coercion_warnings: Vec<CoercionWarning>,
+    query_cost: QueryCost,
+
+    project_name: Option<String>,
+    project_id: Option<Uuid>,
+    feature_ids: Vec<Uuid>,
+    track_consumption: bool,
 }
 
 impl SubmitJoiningJobRequestBuilder {
     pub(crate) fn new_join(
         job_name: String,
         input_path: String,
-        feature_config: String,
-        job_config: String, // feature_join_config or feature_gen_config
+        feature_config: FeatureConfig,
+        feature_join_config: FeatureJoinConfig,
         secret_keys: Vec<String>,
         user_functions: HashMap<String, String>,
     ) -> Self {
@@ -588,13 +869,81 @@ impl SubmitJoiningJobRequestBuilder {
             python_files: Default::default(),
             reference_files: Default::default(),
             configuration: Default::default(),
+            spark_pool: None,
+            cluster_size: None,
             feature_config,
-            feature_join_config: job_config,
+            feature_join_config,
             secret_keys,
             user_functions,
+            snippets: Default::default(),
+            template_variables: Default::default(),
+            lineage: Default::default(),
+            coercion_warnings: Default::default(),
+            query_cost: Default::default(),
+            project_name: None,
+            project_id: None,
+            feature_ids: Default::default(),
+            track_consumption: false,
         }
     }
 
+    pub(crate) fn lineage(&mut self, lineage: JobLineage) -> &mut Self {
+        self.lineage = lineage;
+        self
+    }
+
+    pub(crate) fn coercion_warnings(&mut self, warnings: Vec<CoercionWarning>) -> &mut Self {
+        self.coercion_warnings = warnings;
+        self
+    }
+
+    /**
+     * Lossy or failing type conversions in the feature join, they don't stop the job from being
+     * submitted but the joined values may be truncated or null
+     */
+    pub fn get_coercion_warnings(&self) -> &[CoercionWarning] {
+        &self.coercion_warnings
+    }
+
+    pub(crate) fn query_cost(&mut self, cost: QueryCost) -> &mut Self {
+        self.query_cost = cost;
+        self
+    }
+
+    /**
+     * Estimated computation cost of the feature join, the job is submitted as is even if it's
+     * predicted to be expensive, the suggested resources can be set with `cluster_size`
+     */
+    pub fn get_query_cost(&self) -> &QueryCost {
+        &self.query_cost
+    }
+
+    /**
+     * Tag the job with the project it's built from, so its cost is attributed to the project
+     */
+    pub(crate) fn project_name(&mut self, name: &str) -> &mut Self {
+        self.project_name = Some(name.to_string());
+        self
+    }
+
+    /**
+     * Set the registry ids of the project and the joined features
+     */
+    pub(crate) fn registry_ids(&mut self, project_id: Uuid, feature_ids: Vec<Uuid>) -> &mut Self {
+        self.project_id = Some(project_id);
+        self.feature_ids = feature_ids;
+        self
+    }
+
+    /**
+     * Register the output as a dataset consuming the joined features once the job succeeded,
+     * only works for projects stored in the registry
+     */
+    pub fn track_consumption(&mut self, track: bool) -> &mut Self {
+        self.track_consumption = track;
+        self
+    }
+
     /**
      * Set main Python script content for this job
      */
@@ -606,6 +955,42 @@ impl SubmitJoiningJobRequestBuilder {
         self
     }
 
+    /**
+     * Add a Handlebars snippet to the PySpark driver, replaces the snippet with the same name.
+     * Setting any snippet makes the job run through the PySpark driver even without UDFs.
+     */
+    pub fn snippet(&mut self, snippet: TemplateSnippet) -> &mut Self {
+        self.snippets.retain(|s| s.name != snippet.name);
+        self.snippets.push(snippet);
+        self
+    }
+
+    /**
+     * Set a variable referenced by template snippets
+     */
+    pub fn template_variable(&mut self, name: &str, value: &str) -> &mut Self {
+        self.template_variables
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /**
+     * Run the job on another Spark pool instead of the configured one, only supported by Azure
+     * Synapse
+     */
+    pub fn spark_pool(&mut self, pool: &str) -> &mut Self {
+        self.spark_pool = Some(pool.to_string());
+        self
+    }
+
+    /**
+     * Set driver and executor resources of the job
+     */
+    pub fn cluster_size(&mut self, size: ClusterSize) -> &mut Self {
+        self.cluster_size = Some(size);
+        self
+    }
+
     /**
      * Set output path for the Spark job
      */
@@ -617,15 +1002,33 @@ impl SubmitJoiningJobRequestBuilder {
     }
 
     /**
-     * Create Spark job request
+     * Create Spark job request, fails if a template snippet is invalid or refers to an unset variable
      */
-    pub fn build(&self) -> SubmitJobRequest {
+    pub fn build(&self) -> Result<SubmitJobRequest, Error> {
         let output = self.output_path.clone().unwrap(); // TODO: Validation
-        let job_tags: HashMap<String, String> = [(OUTPUT_PATH_TAG.to_string(), output.clone())]
+        let mut job_tags: HashMap<String, String> = [(OUTPUT_PATH_TAG.to_string(), output.clone())]
             .into_iter()
             .collect();
+        if let Some(name) = &self.project_name {
+            job_tags.insert(PROJECT_TAG.to_string(), name.to_owned());
+        }
+        let consumption = self
+            .project_id
+            .filter(|_| self.track_consumption)
+            .map(|project_id| ConsumptionInfo {
+                project_id,
+                features: self.feature_ids.to_owned(),
+                output: output.clone(),
+            });
+        let main_python_script = gen_main_python(
+            &self.job_name,
+            &self.user_functions,
+            &self.python_files,
+            &self.snippets,
+            &self.template_variables,
+        )?;
         let job_key = Uuid::new_v4();
-        SubmitJobRequest {
+        Ok(SubmitJobRequest {
             job_key,
             name: self.job_name.to_owned(),
             job_config_file_name: format!("feathr_join_config_{}_{}.conf", self.job_name, job_key.as_simple()),
@@ -636,16 +1039,21 @@ impl SubmitJoiningJobRequestBuilder {
                 .main_class_name
                 .to_owned()
                 .unwrap_or_else(|| JOIN_JOB_MAIN_CLASS_NAME.to_string()),
-            main_python_script: gen_main_python(&self.user_functions, &self.python_files),
+            main_python_script,
             feature_config: self.feature_config.to_owned(),
-            join_job_config: self.feature_join_config.to_owned(),
-            gen_job_config: Default::default(),
+            feature_config_url: None,
+            job_config: JobConfig::Join(self.feature_join_config.to_owned()),
             python_files: self.python_files.to_owned(),
             reference_files: self.reference_files.to_owned(),
             job_tags,
             configuration: self.configuration.to_owned(),
+            spark_pool: self.spark_pool.to_owned(),
+            cluster_size: self.cluster_size.to_owned(),
             secret_key: self.secret_keys.to_owned(),
-        }
+            materialization: None,
+            consumption,
+            lineage: self.lineage.to_owned(),
+        })
     }
 }
 
@@ -657,15 +1065,27 @@ pub struct SubmitGenerationJobRequestBuilder {
     python_files: Vec<String>,
     reference_files: Vec<String>,
     configuration: HashMap<String, String>,
-    feature_config: String,
+    spark_pool: Option<String>,
+    cluster_size: Option<ClusterSize>,
+    feature_config: FeatureConfig,
+    max_feature_config_size: usize,
     secret_keys: Vec<String>,
 
     start: DateTime<Utc>,
     end: DateTime<Utc>,
     step: DateTimeResolution,
+    window: WindowOptions,
     materialization_builder: MaterializationSettingsBuilder,
 
     user_functions: HashMap<String, String>,
+    snippets: Vec<TemplateSnippet>,
+    template_variables: HashMap<String, String>,
+
+    project_name: Option<String>,
+    project_id: Option<Uuid>,
+    feature_ids: HashMap<String, Uuid>,
+    registry_tags: HashMap<String, String>,
+    lineage_inputs: Vec<LineageDataset>,
 }
 
 impl SubmitGenerationJobRequestBuilder {
@@ -673,7 +1093,7 @@ impl SubmitGenerationJobRequestBuilder {
         job_name: String,
         feature_names: &[String],
         input_path: String,
-        feature_config: String,
+        feature_config: FeatureConfig,
         secret_keys: Vec<String>,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
@@ -688,16 +1108,75 @@ impl SubmitGenerationJobRequestBuilder {
             python_files: Default::default(),
             reference_files: Default::default(),
             configuration: Default::default(),
+            spark_pool: None,
+            cluster_size: None,
             feature_config,
+            max_feature_config_size: DEFAULT_MAX_FEATURE_CONFIG_SIZE,
             secret_keys,
             start,
             end,
             step,
+            window: Default::default(),
             materialization_builder: MaterializationSettingsBuilder::new(&job_name, feature_names),
             user_functions,
+            snippets: Default::default(),
+            template_variables: Default::default(),
+            project_name: None,
+            project_id: None,
+            feature_ids: Default::default(),
+            registry_tags: Default::default(),
+            lineage_inputs: Default::default(),
         }
     }
 
+    /**
+     * Tag the jobs with the project they're built from, so their costs are attributed to the
+     * project
+     */
+    pub(crate) fn project_name(&mut self, name: &str) -> &mut Self {
+        self.project_name = Some(name.to_string());
+        self
+    }
+
+    /**
+     * Set the registry ids of the project and features, a materialization record will be
+     * registered for every succeeded job built from this builder
+     */
+    pub(crate) fn registry_ids(
+        &mut self,
+        project_id: Uuid,
+        feature_ids: HashMap<String, Uuid>,
+    ) -> &mut Self {
+        self.project_id = Some(project_id);
+        self.feature_ids = feature_ids;
+        self
+    }
+
+    /**
+     * Set the registry tags of the materialization records
+     */
+    pub(crate) fn registry_tags(&mut self, tags: HashMap<String, String>) -> &mut Self {
+        self.registry_tags = tags;
+        self
+    }
+
+    /**
+     * Set the datasets the features are computed from, outputs are the sinks of each job
+     */
+    pub(crate) fn lineage_inputs(&mut self, inputs: Vec<LineageDataset>) -> &mut Self {
+        self.lineage_inputs = inputs;
+        self
+    }
+
+    /**
+     * Set the timezone, alignment and end inclusiveness of the windows, windows are in UTC and end
+     * at `end` by default
+     */
+    pub fn window_options(&mut self, options: WindowOptions) -> &mut Self {
+        self.window = options;
+        self
+    }
+
     pub fn sink<T>(&mut self, sink: T) -> &mut Self
     where
         T: Into<OutputSink>,
@@ -722,6 +1201,16 @@ impl SubmitGenerationJobRequestBuilder {
         self
     }
 
+    /**
+     * Add a sink receiving only the selected features, e.g. only the embedding features to Redis
+     * while all features go to the other sinks
+     */
+    pub fn selected_sink(&mut self, selection: SinkSelection) -> &mut Self {
+        self.secret_keys.extend(selection.get_secret_keys());
+        self.materialization_builder.selected_sink(selection);
+        self
+    }
+
     pub fn feature<T>(&mut self, feature: T) -> &mut Self
     where
         T: ToString,
@@ -750,18 +1239,169 @@ impl SubmitGenerationJobRequestBuilder {
         self
     }
 
+    /**
+     * Add a Handlebars snippet to the PySpark driver, replaces the snippet with the same name.
+     * Setting any snippet makes the jobs run through the PySpark driver even without UDFs.
+     */
+    pub fn snippet(&mut self, snippet: TemplateSnippet) -> &mut Self {
+        self.snippets.retain(|s| s.name != snippet.name);
+        self.snippets.push(snippet);
+        self
+    }
+
+    /**
+     * Set a variable referenced by template snippets
+     */
+    pub fn template_variable(&mut self, name: &str, value: &str) -> &mut Self {
+        self.template_variables
+            .insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /**
+     * Run the jobs on another Spark pool instead of the configured one, only supported by Azure
+     * Synapse
+     */
+    pub fn spark_pool(&mut self, pool: &str) -> &mut Self {
+        self.spark_pool = Some(pool.to_string());
+        self
+    }
+
+    /**
+     * Set driver and executor resources of every generated job
+     */
+    pub fn cluster_size(&mut self, size: ClusterSize) -> &mut Self {
+        self.cluster_size = Some(size);
+        self
+    }
+
+    /**
+     * Split the jobs by anchor group if the feature config exceeds this many bytes, default to
+     * `spark_config.max_feature_config_size` or 1MB, 0 disables the splitting
+     */
+    pub fn max_feature_config_size(&mut self, size: usize) -> &mut Self {
+        self.max_feature_config_size = size;
+        self
+    }
+
+    /**
+     * How the features are split into batches, each batch runs as a separate job for every step
+     */
+    pub fn plan_batches(&self) -> Result<Vec<FeatureConfigBatch>, Error> {
+        Ok(self
+            .feature_config
+            .split(
+                &self.materialization_builder.features,
+                self.max_feature_config_size,
+            )?
+            .into_iter()
+            .map(|(_, batch)| batch)
+            .collect())
+    }
+
+    /**
+     * Generation configs of the jobs `build` creates, one per step and batch
+     */
+    pub fn render_job_configs(&self) -> Result<Vec<RenderedConfig>, Error> {
+        self.build()?
+            .iter()
+            .map(|r| RenderedConfig::try_from(&r.job_config))
+            .collect()
+    }
+
     /**
      * Create Spark job request
      */
     pub fn build(&self) -> Result<Vec<SubmitJobRequest>, Error> {
+        let batches = self.feature_config.split(
+            &self.materialization_builder.features,
+            self.max_feature_config_size,
+        )?;
+        if batches.len() > 1 {
+            info!(
+                "Feature config of job {} is {} bytes, exceeding the limit of {} bytes, split into {} batches",
+                self.job_name,
+                self.feature_config.estimate_size(),
+                self.max_feature_config_size,
+                batches.len()
+            );
+        }
+        let count = batches.len();
+        let mut requests = vec![];
+        for (i, (feature_config, batch)) in batches.into_iter().enumerate() {
+            if count > 1 {
+                info!(
+                    "Batch {}/{} of job {}: {} bytes, anchor groups [{}], features [{}]",
+                    i + 1,
+                    count,
+                    self.job_name,
+                    batch.size,
+                    batch.anchors.join(", "),
+                    batch.features.join(", ")
+                );
+            }
+            let position = Some((i + 1, count)).filter(|_| count > 1);
+            requests.extend(self.build_batch(feature_config, batch.features, position)?);
+        }
+        Ok(requests)
+    }
+
+    /**
+     * Requests of every step computing the features of one batch, `position` is the 1-based index
+     * of the batch and the number of batches if the job is split
+     */
+    fn build_batch(
+        &self,
+        feature_config: FeatureConfig,
+        features: Vec<String>,
+        position: Option<(usize, usize)>,
+    ) -> Result<Vec<SubmitJobRequest>, Error> {
+        let (start, end) = self.window.resolve(self.start, self.end, self.step)?;
         let mat_settings = self
             .materialization_builder
-            .build(self.start, self.end, self.step)?;
+            .with_features(features)
+            .build(start, end, self.step)?;
+        let main_python_script = gen_main_python(
+            &self.job_name,
+            &self.user_functions,
+            &self.python_files,
+            &self.snippets,
+            &self.template_variables,
+        )?;
+        // Configs are uploaded by the job key, so every batch needs its own
         let job_key = Uuid::new_v4();
         Ok(mat_settings
             .into_iter()
-            .map(|s| {
-                let conf = serde_json::to_string_pretty(&s).unwrap();
+            .map(|mut s| {
+                if let Some((index, _)) = position {
+                    // Batches would overwrite the output of each other
+                    let dir = format!("batch_{}", index);
+                    for sink in s.all_sinks_mut() {
+                        if let OutputSink::Hdfs(GenericSink {
+                            location: DataLocation::Hdfs { path },
+                            ..
+                        }) = sink
+                        {
+                            *path = format!("{}/{}", path.trim_end_matches('/'), dir);
+                        }
+                    }
+                }
+                let mut configuration = self.configuration.to_owned();
+                // Without it Spark drops the other windows when overwriting a partitioned table
+                if s.all_sinks().any(|sink| {
+                    matches!(
+                        sink,
+                        OutputSink::Hdfs(GenericSink {
+                            save_mode: Some(SaveMode::OverwriteWindow),
+                            ..
+                        })
+                    )
+                }) {
+                    let (key, value) = PARTITION_OVERWRITE_MODE;
+                    configuration
+                        .entry(key.to_string())
+                        .or_insert_with(|| value.to_string());
+                }
                 SubmitJobRequest {
                     job_key,
                     name: self.job_name.to_owned(),
@@ -778,15 +1418,44 @@ impl SubmitGenerationJobRequestBuilder {
                         .main_class_name
                         .to_owned()
                         .unwrap_or_else(|| GEN_JOB_MAIN_CLASS_NAME.to_string()),
-                    main_python_script: gen_main_python(&self.user_functions, &self.python_files),
-                    feature_config: self.feature_config.to_owned(),
-                    join_job_config: Default::default(),
-                    gen_job_config: conf,
+                    main_python_script: main_python_script.clone(),
+                    feature_config: feature_config.to_owned(),
+                    // Uploaded once for all steps by `submit_jobs`
+                    feature_config_url: None,
                     python_files: self.python_files.to_owned(),
                     reference_files: self.reference_files.to_owned(),
-                    job_tags: Default::default(),
-                    configuration: self.configuration.to_owned(),
+                    job_tags: self
+                        .project_name
+                        .iter()
+                        .map(|name| (PROJECT_TAG.to_string(), name.to_owned()))
+                        .chain(position.map(|(index, count)| {
+                            (BATCH_TAG.to_string(), format!("{}/{}", index, count))
+                        }))
+                        .collect(),
+                    configuration,
+                    spark_pool: self.spark_pool.to_owned(),
+                    cluster_size: self.cluster_size.to_owned(),
                     secret_key: self.secret_keys.to_owned(),
+                    materialization: self.project_id.map(|project_id| MaterializationInfo {
+                        project_id,
+                        features: s
+                            .feature_names
+                            .iter()
+                            .filter_map(|name| self.feature_ids.get(name).copied())
+                            .collect(),
+                        sinks: s.all_sinks().cloned().collect(),
+                        window_start: s.operational.end_time - self.step.to_duration(),
+                        window_end: s.operational.end_time,
+                        tags: self.registry_tags.to_owned(),
+                    }),
+                    consumption: None,
+                    lineage: JobLineage::new(
+                        &s.feature_names,
+                        self.lineage_inputs.to_owned(),
+                        s.all_sinks().flat_map(LineageDataset::from_sink),
+                    ),
+                    // Takes the settings, so it goes after the fields reading them
+                    job_config: JobConfig::Generation(s),
                 }
             })
             .collect())
@@ -803,13 +1472,65 @@ fn encode_buf(buf: &[u8]) -> String {
     v.join("\n")
 }
 
+/**
+ * The global variable a JDBC credential falls back to, e.g. `JDBC_USER` for `nycTaxi_USER`
+ */
+fn global_jdbc_credential(secret: &str) -> Option<&'static str> {
+    if secret.ends_with("_USER") {
+        Some("JDBC_USER")
+    } else if secret.ends_with("_PASSWORD") {
+        Some("JDBC_PASSWORD")
+    } else if secret.ends_with("_TOKEN") {
+        Some("JDBC_TOKEN")
+    } else {
+        None
+    }
+}
+
+/**
+ * Render template snippets with `job_name` and the template variables, grouped by position
+ */
+fn render_snippets(
+    job_name: &str,
+    snippets: &[TemplateSnippet],
+    variables: &HashMap<String, String>,
+) -> Result<HashMap<SnippetPosition, Vec<String>>, Error> {
+    let mut ctx: HashMap<&str, &str> = variables
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    ctx.insert("job_name", job_name);
+    let mut hbs = Handlebars::new();
+    hbs.set_strict_mode(true);
+    hbs.register_escape_fn(handlebars::no_escape);
+    let mut ret: HashMap<SnippetPosition, Vec<String>> = HashMap::new();
+    for snippet in snippets {
+        hbs.register_template_string(&snippet.name, &snippet.template)
+            .map_err(|e| Error::InvalidTemplateSnippet(snippet.name.clone(), e.to_string()))?;
+        let rendered = hbs.render(&snippet.name, &ctx).map_err(|e| {
+            let mut available: Vec<&str> = ctx.keys().copied().collect();
+            available.sort();
+            Error::InvalidTemplateSnippet(
+                snippet.name.clone(),
+                format!("{}, available variables: {}", e, available.join(", ")),
+            )
+        })?;
+        ret.entry(snippet.position).or_default().push(rendered);
+    }
+    Ok(ret)
+}
+
 fn gen_main_python(
+    job_name: &str,
     user_functions: &HashMap<String, String>,
     python_files: &[String],
-) -> Option<String> {
-    if user_functions.is_empty() {
-        return None;
+    snippets: &[TemplateSnippet],
+    template_variables: &HashMap<String, String>,
+) -> Result<Option<String>, Error> {
+    if user_functions.is_empty() && snippets.is_empty() {
+        return Ok(None);
     }
+    let mut rendered = render_snippets(job_name, snippets, template_variables)?;
 
     let imports: Vec<String> = python_files
         .into_iter()
@@ -851,22 +1572,33 @@ fn gen_main_python(
         user_functions: &'a HashMap<String, String>,
         imports: &'b [String],
         embeds: &'b HashMap<String, String>,
+        session_snippets: Vec<String>,
+        preamble_snippets: Vec<String>,
     }
     let ctx = Context {
         user_functions,
         imports: &imports,
         embeds: &embeds,
+        session_snippets: rendered
+            .remove(&SnippetPosition::SparkSession)
+            .unwrap_or_default(),
+        preamble_snippets: rendered
+            .remove(&SnippetPosition::Preamble)
+            .unwrap_or_default(),
     };
     let mut hbs = Handlebars::new();
     hbs.register_escape_fn(handlebars::no_escape);
     hbs.register_template_string("py", PYTHON_TEMPLATE).unwrap();
-    Some(hbs.render("py", &ctx).unwrap())
+    Ok(Some(hbs.render("py", &ctx).unwrap()))
 }
 
 #[derive(Clone, Debug)]
 pub enum Client {
     AzureSynapse(Arc<AzureSynapseClient>),
     Databricks(Arc<DatabricksClient>),
+    GenericLivy(Arc<GenericLivyClient>),
+    #[cfg(feature = "testing")]
+    Mock(Arc<MockJobClient>),
 }
 
 #[async_trait]
@@ -874,10 +1606,14 @@ impl JobClient for Client {
     /**
      * Create file on the remote side and returns Spark compatible URL of the file
      */
+    #[instrument(skip_all, fields(path = path))]
     async fn write_remote_file(&self, path: &str, content: &[u8]) -> Result<String, crate::Error> {
         match self {
             Client::AzureSynapse(c) => c.write_remote_file(path, content),
             Client::Databricks(c) => c.write_remote_file(path, content),
+            Client::GenericLivy(c) => c.write_remote_file(path, content),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.write_remote_file(path, content),
         }
         .await
     }
@@ -885,10 +1621,14 @@ impl JobClient for Client {
     /**
      * Read file content from a Spark compatible URL
      */
+    #[instrument(skip_all, fields(path = path))]
     async fn read_remote_file(&self, path: &str) -> Result<Bytes, crate::Error> {
         match self {
             Client::AzureSynapse(c) => c.read_remote_file(path),
             Client::Databricks(c) => c.read_remote_file(path),
+            Client::GenericLivy(c) => c.read_remote_file(path),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.read_remote_file(path),
         }
         .await
     }
@@ -896,14 +1636,18 @@ impl JobClient for Client {
     /**
      * Submit Spark job, upload files if necessary
      */
+    #[instrument(skip_all, fields(job.name = %request.name))]
     async fn submit_job(
         &self,
         var_source: Arc<dyn VarSource + Send + Sync>,
         request: SubmitJobRequest,
-    ) -> Result<JobId, crate::Error> {
+    ) -> Result<JobHandle, crate::Error> {
         match self {
             Client::AzureSynapse(c) => c.submit_job(var_source, request),
             Client::Databricks(c) => c.submit_job(var_source, request),
+            Client::GenericLivy(c) => c.submit_job(var_source, request),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.submit_job(var_source, request),
         }
         .await
     }
@@ -911,10 +1655,14 @@ impl JobClient for Client {
     /**
      * Get job status
      */
+    #[instrument(skip_all, fields(job.id = %job_id))]
     async fn get_job_status(&self, job_id: JobId) -> Result<JobStatus, crate::Error> {
         match self {
             Client::AzureSynapse(c) => c.get_job_status(job_id),
             Client::Databricks(c) => c.get_job_status(job_id),
+            Client::GenericLivy(c) => c.get_job_status(job_id),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.get_job_status(job_id),
         }
         .await
     }
@@ -926,6 +1674,9 @@ impl JobClient for Client {
         match self {
             Client::AzureSynapse(c) => c.get_job_log(job_id),
             Client::Databricks(c) => c.get_job_log(job_id),
+            Client::GenericLivy(c) => c.get_job_log(job_id),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.get_job_log(job_id),
         }
         .await
     }
@@ -937,6 +1688,26 @@ impl JobClient for Client {
         match self {
             Client::AzureSynapse(c) => c.get_job_output_url(job_id),
             Client::Databricks(c) => c.get_job_output_url(job_id),
+            Client::GenericLivy(c) => c.get_job_output_url(job_id),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.get_job_output_url(job_id),
+        }
+        .await
+    }
+
+    /**
+     * Get platform specific job metadata
+     */
+    async fn get_job_metadata(
+        &self,
+        job_id: JobId,
+    ) -> Result<HashMap<String, String>, crate::Error> {
+        match self {
+            Client::AzureSynapse(c) => c.get_job_metadata(job_id),
+            Client::Databricks(c) => c.get_job_metadata(job_id),
+            Client::GenericLivy(c) => c.get_job_metadata(job_id),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.get_job_metadata(job_id),
         }
         .await
     }
@@ -948,6 +1719,9 @@ impl JobClient for Client {
         match self {
             Client::AzureSynapse(c) => c.get_remote_url(filename),
             Client::Databricks(c) => c.get_remote_url(filename),
+            Client::GenericLivy(c) => c.get_remote_url(filename),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.get_remote_url(filename),
         }
     }
 
@@ -958,6 +1732,19 @@ impl JobClient for Client {
         match self {
             Client::AzureSynapse(c) => c.is_url_on_storage(url),
             Client::Databricks(c) => c.is_url_on_storage(url),
+            Client::GenericLivy(c) => c.is_url_on_storage(url),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.is_url_on_storage(url),
+        }
+    }
+
+    fn poll_interval(&self) -> std::time::Duration {
+        match self {
+            Client::AzureSynapse(c) => c.poll_interval(),
+            Client::Databricks(c) => c.poll_interval(),
+            Client::GenericLivy(c) => c.poll_interval(),
+            #[cfg(feature = "testing")]
+            Client::Mock(c) => c.poll_interval(),
         }
     }
 }
@@ -985,19 +1772,34 @@ impl Client {
             "databricks" => Client::Databricks(Arc::new(
                 DatabricksClient::from_var_source(var_source).await?,
             )),
+            "generic_livy" => Client::GenericLivy(Arc::new(
+                GenericLivyClient::from_var_source(var_source).await?,
+            )),
+            #[cfg(feature = "testing")]
+            "mock" => Client::Mock(Arc::new(MockJobClient::from_var_source(var_source).await?)),
             _ => {
                 return Err(Error::UnsupportedSparkProvider(provider));
             }
         };
         Ok(client)
     }
+
+    pub fn get_backend(&self) -> JobBackend {
+        match self {
+            Client::AzureSynapse(_) => JobBackend::AzureSynapse,
+            Client::Databricks(_) => JobBackend::Databricks,
+            Client::GenericLivy(_) => JobBackend::GenericLivy,
+            #[cfg(feature = "testing")]
+            Client::Mock(_) => JobBackend::Mock,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
-    use super::gen_main_python;
+    use super::{gen_main_python, SnippetPosition, TemplateSnippet};
 
     #[test]
     fn test_template() {
@@ -1011,7 +1813,50 @@ mod tests {
             "/Users/chenxu/repos/feathr/feathr_project/feathr/constants.py".to_string(),
             "/Users/chenxu/repos/feathr/feathr_project/feathr/anchor.py".to_string(),
         ];
-        let s = gen_main_python(&user_functions, &files);
-        println!("{}", s.unwrap());
+        let s = gen_main_python("test", &user_functions, &files, &[], &Default::default());
+        println!("{}", s.unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_template_snippets() {
+        let snippets = vec![
+            TemplateSnippet::new(
+                "shuffle",
+                SnippetPosition::SparkSession,
+                r#"spark.conf.set("spark.sql.shuffle.partitions", "{{partitions}}")"#,
+            ),
+            TemplateSnippet::new(
+                "udf",
+                SnippetPosition::Preamble,
+                r#"spark.udf.registerJavaFunction("{{job_name}}_udf", "com.example.Udf")"#,
+            ),
+        ];
+        let variables: HashMap<String, String> = [("partitions".to_string(), "64".to_string())]
+            .into_iter()
+            .collect();
+        let s = gen_main_python("test", &Default::default(), &[], &snippets, &variables)
+            .unwrap()
+            .unwrap();
+        let session = s
+            .find(r#"spark.conf.set("spark.sql.shuffle.partitions", "64")"#)
+            .unwrap();
+        let udf = s
+            .find(r#"spark.udf.registerJavaFunction("test_udf", "com.example.Udf")"#)
+            .unwrap();
+        assert!(session < s.find("def submit_spark_job").unwrap());
+        assert!(udf > s.find("def submit_spark_job").unwrap());
+        assert!(udf < s.find("feature_names_funcs = {").unwrap());
+
+        let e = gen_main_python(
+            "test",
+            &Default::default(),
+            &[],
+            &snippets,
+            &Default::default(),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(e.contains("shuffle"));
+        assert!(e.contains("partitions"));
     }
 }
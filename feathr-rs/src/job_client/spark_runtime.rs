@@ -0,0 +1,240 @@
+use std::{fmt::Display, future::Future, str::FromStr, sync::Arc};
+
+use log::{debug, warn};
+
+use crate::{Error, VarSource};
+
+/**
+ * Feathr artifacts built for each Spark version and Scala binary version
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArtifactCompatibility {
+    /// Major and minor version of Spark
+    pub spark_version: (u32, u32),
+    pub scala_version: &'static str,
+    pub feathr_version: &'static str,
+}
+
+impl ArtifactCompatibility {
+    pub fn maven_artifact(&self) -> String {
+        format!(
+            "com.linkedin.feathr:feathr_{}:{}",
+            self.scala_version, self.feathr_version
+        )
+    }
+}
+
+/**
+ * Runtimes not listed here are rejected unless `spark_config.maven_artifact` is set
+ */
+pub const COMPATIBILITY_MATRIX: &[ArtifactCompatibility] = &[
+    ArtifactCompatibility {
+        spark_version: (3, 1),
+        scala_version: "2.12",
+        feathr_version: "0.4.0",
+    },
+    ArtifactCompatibility {
+        spark_version: (3, 2),
+        scala_version: "2.12",
+        feathr_version: "0.9.0",
+    },
+];
+
+/**
+ * Spark and Scala versions of a cluster runtime
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparkRuntime {
+    /// Major and minor version of Spark
+    pub spark_version: (u32, u32),
+    /// Scala binary version, e.g. `2.12`
+    pub scala_version: String,
+}
+
+impl SparkRuntime {
+    pub fn new(spark_version: (u32, u32)) -> Self {
+        Self {
+            spark_version,
+            scala_version: default_scala_version(spark_version).to_string(),
+        }
+    }
+
+    /**
+     * Runtime of a Databricks runtime version, e.g. `9.1.x-scala2.12` or `10.4.x-photon-scala2.12`
+     */
+    pub fn from_databricks_version(version: &str) -> Result<Self, Error> {
+        let invalid =
+            || Error::InvalidOption("databricks spark_version".to_string(), version.to_string());
+        let (major, minor) = parse_major_minor(version).ok_or_else(invalid)?;
+        // Spark versions in the Databricks runtime release notes
+        let spark_version = match (major, minor) {
+            (6, 4..) => (2, 4),
+            (7, _) => (3, 0),
+            (8, _) | (9, _) => (3, 1),
+            (10, _) => (3, 2),
+            (11, _) | (12, _) => (3, 3),
+            (13, _) => (3, 4),
+            (14, _) => (3, 5),
+            _ => {
+                return Err(Error::UnsupportedSparkRuntime(
+                    format!("Databricks runtime {}", version),
+                    supported_runtimes(),
+                ))
+            }
+        };
+        let mut runtime = Self::new(spark_version);
+        if let Some(scala) = parse_scala_suffix(version) {
+            runtime.scala_version = scala;
+        }
+        Ok(runtime)
+    }
+
+    /**
+     * The Feathr artifact built for the runtime
+     */
+    pub fn compatible_artifact(&self) -> Result<&'static ArtifactCompatibility, Error> {
+        COMPATIBILITY_MATRIX
+            .iter()
+            .find(|c| {
+                c.spark_version == self.spark_version && c.scala_version == self.scala_version
+            })
+            .ok_or_else(|| Error::UnsupportedSparkRuntime(self.to_string(), supported_runtimes()))
+    }
+}
+
+/**
+ * `3.1`, or `3.1-scala2.12` with the Scala binary version
+ */
+impl FromStr for SparkRuntime {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spark_version = parse_major_minor(s.trim()).ok_or_else(|| {
+            Error::InvalidOption("spark_config.spark_runtime".to_string(), s.to_string())
+        })?;
+        let mut runtime = Self::new(spark_version);
+        if let Some(scala) = parse_scala_suffix(s) {
+            runtime.scala_version = scala;
+        }
+        Ok(runtime)
+    }
+}
+
+impl Display for SparkRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Spark {}.{} (Scala {})",
+            self.spark_version.0, self.spark_version.1, self.scala_version
+        )
+    }
+}
+
+/**
+ * `spark_config.maven_artifact` if it's set, otherwise the artifact compatible with
+ * `spark_config.spark_runtime` or the runtime detected from the cluster.
+ * The detection only runs if needed, the default artifact is used if it fails
+ */
+pub(crate) async fn select_maven_artifact<F>(
+    var_source: &Arc<dyn VarSource + Send + Sync>,
+    detect: F,
+) -> Result<String, Error>
+where
+    F: Future<Output = Result<SparkRuntime, Error>>,
+{
+    if let Ok(artifact) = var_source
+        .get_environment_variable(&["spark_config", "maven_artifact"])
+        .await
+    {
+        if !artifact.trim().is_empty() {
+            return Ok(artifact);
+        }
+    }
+    let runtime = match var_source
+        .get_environment_variable(&["spark_config", "spark_runtime"])
+        .await
+    {
+        Ok(s) if !s.trim().is_empty() => s.parse()?,
+        _ => match detect.await {
+            Ok(runtime) => runtime,
+            Err(e @ Error::UnsupportedSparkRuntime(..)) => return Err(e),
+            Err(e) => {
+                warn!(
+                    "Failed to detect the Spark runtime, using {}: {}",
+                    super::FEATHR_MAVEN_ARTIFACT,
+                    e
+                );
+                return Ok(super::FEATHR_MAVEN_ARTIFACT.to_string());
+            }
+        },
+    };
+    let artifact = runtime.compatible_artifact()?.maven_artifact();
+    debug!("Selected {} for {}", artifact, runtime);
+    Ok(artifact)
+}
+
+fn default_scala_version(spark_version: (u32, u32)) -> &'static str {
+    if spark_version < (3, 0) {
+        "2.11"
+    } else {
+        "2.12"
+    }
+}
+
+fn parse_major_minor(s: &str) -> Option<(u32, u32)> {
+    let mut parts = s.split(|c: char| !c.is_ascii_digit());
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+fn parse_scala_suffix(s: &str) -> Option<String> {
+    s.rsplit_once("scala")
+        .map(|(_, v)| v.trim().to_string())
+        .filter(|v| parse_major_minor(v).is_some())
+}
+
+fn supported_runtimes() -> String {
+    COMPATIBILITY_MATRIX
+        .iter()
+        .map(|c| {
+            format!(
+                "Spark {}.{} (Scala {})",
+                c.spark_version.0, c.spark_version.1, c.scala_version
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compatible_artifact() {
+        let runtime = SparkRuntime::from_databricks_version("9.1.x-scala2.12").unwrap();
+        assert_eq!(runtime.spark_version, (3, 1));
+        assert_eq!(
+            runtime.compatible_artifact().unwrap().maven_artifact(),
+            "com.linkedin.feathr:feathr_2.12:0.4.0"
+        );
+        let runtime = SparkRuntime::from_databricks_version("10.4.x-photon-scala2.12").unwrap();
+        assert_eq!(runtime.spark_version, (3, 2));
+
+        let runtime: SparkRuntime = "3.1".parse().unwrap();
+        assert_eq!(runtime.scala_version, "2.12");
+        let runtime: SparkRuntime = "2.4".parse().unwrap();
+        assert_eq!(runtime.scala_version, "2.11");
+        assert!(matches!(
+            runtime.compatible_artifact(),
+            Err(Error::UnsupportedSparkRuntime(_, _))
+        ));
+        let runtime: SparkRuntime = "3.2-scala2.13".parse().unwrap();
+        assert_eq!(runtime.scala_version, "2.13");
+        assert!(runtime.compatible_artifact().is_err());
+
+        assert!("latest".parse::<SparkRuntime>().is_err());
+        assert!(SparkRuntime::from_databricks_version("5.5.x-scala2.11").is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use azure_storage::storage_shared_key_credential::StorageSharedKeyCredential;
+use azure_storage_datalake::clients::DataLakeClient;
+use bytes::Bytes;
+use log::debug;
+use reqwest::{header::LOCATION, redirect::Policy, Method, Response};
+
+use super::azure_synapse::{http_to_abfs, parse_abfs};
+use crate::{Error, Logged};
+
+/**
+ * Stores job files in a location the Spark cluster can read, e.g. the workspace dir of a Livy
+ * server
+ */
+#[async_trait]
+pub trait FileStager: Debug + Send + Sync {
+    /**
+     * URL of the file in the workspace dir, as seen by the cluster
+     */
+    fn get_remote_url(&self, filename: &str) -> String;
+
+    /**
+     * The cluster can read the file directly, it doesn't need to be staged
+     */
+    fn is_url_on_storage(&self, url: &str) -> bool {
+        url.starts_with("abfs://")
+            || url.starts_with("abfss://")
+            || url.starts_with("wasb://")
+            || url.starts_with("wasbs://")
+            || url.starts_with("hdfs://")
+    }
+
+    /**
+     * Write the file and return the URL the cluster reads it from
+     */
+    async fn write_file(&self, url: &str, content: &[u8]) -> Result<String, Error>;
+
+    async fn read_file(&self, url: &str) -> Result<Bytes, Error>;
+}
+
+/**
+ * Stages files in an ADLS Gen2 workspace dir with the storage account key
+ */
+#[derive(Debug)]
+pub struct AdlsStager {
+    storage_client: DataLakeClient,
+    storage_account: String,
+    container: String,
+    workspace_dir: String,
+}
+
+impl AdlsStager {
+    /**
+     * `workspace_dir` is an `abfss://` URL
+     */
+    pub fn new(storage_key: &str, workspace_dir: &str) -> Result<Self, Error> {
+        let (container, storage_account, workspace_dir) = parse_abfs(workspace_dir)?;
+        Ok(Self {
+            storage_client: DataLakeClient::new(
+                StorageSharedKeyCredential::new(storage_account.clone(), storage_key.to_string()),
+                None,
+            ),
+            storage_account,
+            container,
+            workspace_dir: workspace_dir.trim_start_matches("/").to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl FileStager for AdlsStager {
+    fn get_remote_url(&self, filename: &str) -> String {
+        format!(
+            "abfss://{}@{}.dfs.core.windows.net/{}",
+            self.container,
+            self.storage_account,
+            [self.workspace_dir.as_str().trim_end_matches("/"), filename]
+                .join("/")
+                .trim_start_matches("/")
+                .to_string()
+        )
+    }
+
+    async fn write_file(&self, url: &str, content: &[u8]) -> Result<String, Error> {
+        let (container, _, path) = parse_abfs(url)?;
+        let fs_client = self
+            .storage_client
+            .clone()
+            .into_file_system_client(container);
+        // Create file system and ignore error, in case the file system already exists
+        fs_client.create().into_future().await.log().ok();
+        let file_client = fs_client.get_file_client(path);
+        // Delete existing file and ignore error
+        file_client.delete().into_future().await.log().ok();
+        file_client.create().into_future().await.log()?;
+        file_client
+            .append(0, bytes::Bytes::from(content.to_owned()))
+            .into_future()
+            .await
+            .log()?;
+        file_client
+            .flush(content.len() as i64)
+            .into_future()
+            .await
+            .log()?;
+        http_to_abfs(file_client.url().log()?)
+    }
+
+    async fn read_file(&self, url: &str) -> Result<Bytes, Error> {
+        let (container, _, dir) = parse_abfs(url)?;
+        let fs_client = self
+            .storage_client
+            .clone()
+            .into_file_system_client(container);
+        let file_client = fs_client.get_file_client(dir);
+        Ok(file_client.read().into_future().await?.data)
+    }
+}
+
+/**
+ * Stages files in an HDFS workspace dir via the WebHDFS REST API of the name node, e.g. on
+ * on-premise clusters without cloud storage.
+ * The name node redirects reads and writes to a data node, which must be reachable from the client.
+ */
+#[derive(Debug)]
+pub struct WebHdfsStager {
+    client: reqwest::Client,
+    webhdfs_url: String,
+    workspace_dir: String,
+    user: Option<String>,
+}
+
+impl WebHdfsStager {
+    /**
+     * `webhdfs_url` is the HTTP address of the name node, e.g. `http://namenode:9870`,
+     * `workspace_dir` is an `hdfs://` URL, `user` is sent as `user.name` with simple authentication
+     */
+    pub fn new(webhdfs_url: &str, workspace_dir: &str, user: Option<&str>) -> Result<Self, Error> {
+        hdfs_path(workspace_dir)?;
+        Ok(Self {
+            // Redirects are followed manually, so the content is only sent to the data node
+            client: reqwest::Client::builder()
+                .redirect(Policy::none())
+                .build()?,
+            webhdfs_url: webhdfs_url.trim_end_matches('/').to_string(),
+            workspace_dir: workspace_dir.trim_end_matches('/').to_string(),
+            user: user.map(ToString::to_string),
+        })
+    }
+
+    /**
+     * WebHDFS endpoint of the `hdfs://` URL
+     */
+    fn endpoint(&self, url: &str) -> Result<String, Error> {
+        Ok(format!(
+            "{}/webhdfs/v1{}",
+            self.webhdfs_url,
+            hdfs_path(url)?
+        ))
+    }
+
+    /**
+     * Send the operation to the name node and follow the redirect to the data node, `content` is
+     * only sent to the data node
+     */
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        query: &[(&str, &str)],
+        content: Option<&[u8]>,
+    ) -> Result<Response, Error> {
+        let mut builder = self
+            .client
+            .request(method.clone(), self.endpoint(url)?)
+            .query(query);
+        if let Some(user) = &self.user {
+            builder = builder.query(&[("user.name", user)]);
+        }
+        let resp = builder.send().await?;
+        if !resp.status().is_redirection() {
+            return Ok(resp.error_for_status()?);
+        }
+        let location = resp
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| Error::InvalidUrl(format!("{} redirected without location", url)))?
+            .to_string();
+        debug!("WebHDFS redirected to {}", location);
+        let mut builder = self.client.request(method, location);
+        if let Some(content) = content {
+            builder = builder.body(content.to_vec());
+        }
+        Ok(builder.send().await?.error_for_status()?)
+    }
+}
+
+#[async_trait]
+impl FileStager for WebHdfsStager {
+    fn get_remote_url(&self, filename: &str) -> String {
+        format!(
+            "{}/{}",
+            self.workspace_dir,
+            filename.trim_start_matches('/')
+        )
+    }
+
+    async fn write_file(&self, url: &str, content: &[u8]) -> Result<String, Error> {
+        self.send(
+            Method::PUT,
+            url,
+            &[("op", "CREATE"), ("overwrite", "true")],
+            Some(content),
+        )
+        .await?;
+        Ok(url.to_string())
+    }
+
+    async fn read_file(&self, url: &str) -> Result<Bytes, Error> {
+        Ok(self
+            .send(Method::GET, url, &[("op", "OPEN")], None)
+            .await?
+            .bytes()
+            .await?)
+    }
+}
+
+/**
+ * Absolute path of an `hdfs://` URL, the name node address is dropped
+ */
+fn hdfs_path(url: &str) -> Result<&str, Error> {
+    let rest = url
+        .strip_prefix("hdfs://")
+        .ok_or_else(|| Error::InvalidUrl(url.to_string()))?;
+    rest.find('/')
+        .map(|idx| &rest[idx..])
+        .ok_or_else(|| Error::InvalidUrl(url.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    #[test]
+    fn webhdfs_urls() {
+        let stager =
+            WebHdfsStager::new("http://nn:9870/", "hdfs://nn:8020/feathr/ws/", Some("u1")).unwrap();
+        let url = stager.get_remote_url("noop.jar");
+        assert_eq!(url, "hdfs://nn:8020/feathr/ws/noop.jar");
+        assert!(stager.is_url_on_storage(&url));
+        assert_eq!(
+            stager.endpoint(&url).unwrap(),
+            "http://nn:9870/webhdfs/v1/feathr/ws/noop.jar"
+        );
+        assert!(WebHdfsStager::new("http://nn:9870", "/feathr/ws", None).is_err());
+        assert!(hdfs_path("hdfs://nn:8020").is_err());
+    }
+
+    /**
+     * Plays both the name node and the data node, requests are recorded as
+     * `METHOD path-and-query body`
+     */
+    async fn webhdfs_server(requests: Arc<Mutex<Vec<String>>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut buf = vec![];
+                let mut chunk = [0u8; 1024];
+                // Read the headers and the body of the only request on the connection
+                let (head, body) = loop {
+                    let n = conn.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    let (head, body) = text.split_once("\r\n\r\n").unwrap_or((&text, ""));
+                    let len: usize = head
+                        .to_lowercase()
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:")?.trim().parse().ok())
+                        .unwrap_or_default();
+                    if n == 0 || (text.contains("\r\n\r\n") && body.len() >= len) {
+                        break (head.to_string(), body.to_string());
+                    }
+                };
+                let line = head.lines().next().unwrap_or_default().to_string();
+                let mut parts = line.split(' ');
+                let (method, target) = (parts.next().unwrap(), parts.next().unwrap());
+                requests
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {} {}", method, target, body));
+                let resp = if target.starts_with("/webhdfs/") {
+                    format!(
+                        "HTTP/1.1 307 Temporary Redirect\r\nLocation: http://{}/datanode{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        addr, target
+                    )
+                } else if method == "GET" {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\ncontent"
+                        .to_string()
+                } else {
+                    "HTTP/1.1 201 Created\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string()
+                };
+                conn.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn webhdfs_staging() {
+        let requests: Arc<Mutex<Vec<String>>> = Default::default();
+        let url = webhdfs_server(requests.clone()).await;
+        let stager = WebHdfsStager::new(&url, "hdfs://nn:8020/ws", Some("u1")).unwrap();
+
+        let file = stager.get_remote_url("a.py");
+        assert_eq!(stager.write_file(&file, b"content").await.unwrap(), file);
+        assert_eq!(stager.read_file(&file).await.unwrap().as_ref(), b"content");
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(
+            requests,
+            vec![
+                // Content is only sent to the data node
+                "PUT /webhdfs/v1/ws/a.py?op=CREATE&overwrite=true&user.name=u1 ",
+                "PUT /datanode/webhdfs/v1/ws/a.py?op=CREATE&overwrite=true&user.name=u1 content",
+                "GET /webhdfs/v1/ws/a.py?op=OPEN&user.name=u1 ",
+                "GET /datanode/webhdfs/v1/ws/a.py?op=OPEN&user.name=u1 ",
+            ]
+        );
+    }
+}
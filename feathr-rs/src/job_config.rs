@@ -1,8 +1,156 @@
-use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::{ObservationSettings, FeatureQuery};
+use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
-#[derive(Clone, Debug, Serialize)]
+use crate::{
+    Error, FeatureQuery, MaterializationSettings, ObservationSettings, OutputFormat, OutputSink,
+};
+
+/// Default limit of the feature config of a generation job in bytes, see `FeatureConfig::split`
+pub const DEFAULT_MAX_FEATURE_CONFIG_SIZE: usize = 1024 * 1024;
+
+/**
+ * Definitions of the sources, anchors and derived features used by a job, written to the feature
+ * config file when the job is submitted
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeatureConfig {
+    #[serde(default)]
+    pub anchors: Map<String, Value>,
+    #[serde(default)]
+    pub derivations: Map<String, Value>,
+    #[serde(default)]
+    pub sources: Map<String, Value>,
+}
+
+impl FeatureConfig {
+    pub fn render(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /**
+     * Size of the rendered config in bytes, estimated by adding up the sizes of the entries so
+     * subsets of the config can be measured without rendering them
+     */
+    pub fn estimate_size(&self) -> usize {
+        let empty = Self::default().render().map_or(0, |s| s.len());
+        empty
+            + self
+                .anchors
+                .iter()
+                .chain(self.derivations.iter())
+                .chain(self.sources.iter())
+                .map(|(name, value)| entry_size(name, value))
+                .sum::<usize>()
+    }
+
+    /**
+     * Split the config of a generation job of the features into configs of at most `max_size`
+     * bytes, whole anchor groups are packed into each config along with their sources.
+     * The config is kept as is if it fits or `max_size` is 0.
+     */
+    pub fn split(
+        &self,
+        features: &[String],
+        max_size: usize,
+    ) -> Result<Vec<(FeatureConfig, FeatureConfigBatch)>, Error> {
+        let size = self.estimate_size();
+        if max_size == 0 || size <= max_size {
+            return Ok(vec![(
+                self.clone(),
+                FeatureConfigBatch {
+                    anchors: self.anchors.keys().cloned().collect(),
+                    features: features.to_vec(),
+                    size,
+                },
+            )]);
+        }
+        let too_large = |reason: String| Err(Error::FeatureConfigTooLarge(size, max_size, reason));
+        let mut anchor_of: HashMap<&str, &str> = HashMap::new();
+        for (name, anchor) in &self.anchors {
+            if let Some(Value::Object(anchor_features)) = anchor.get("features") {
+                for f in anchor_features.keys() {
+                    anchor_of.insert(f, name);
+                }
+            }
+        }
+        let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for f in features {
+            match anchor_of.get(f.as_str()) {
+                Some(&anchor) => groups.entry(anchor).or_default().push(f.to_owned()),
+                // Inputs of a derived feature may end up in different batches
+                None => return too_large(format!("`{}` is not an anchor feature", f)),
+            }
+        }
+
+        let empty = Self::default().estimate_size();
+        let mut batches = vec![];
+        let mut config = Self::default();
+        let mut batch = FeatureConfigBatch::default();
+        for (anchor, anchor_features) in groups {
+            let value = &self.anchors[anchor];
+            let source = value
+                .get("source")
+                .and_then(Value::as_str)
+                .and_then(|s| self.sources.get_key_value(s));
+            // Sources shared by anchor groups in the same batch are only counted once
+            let cost = |config: &FeatureConfig| {
+                entry_size(anchor, value)
+                    + source
+                        .filter(|(s, _)| !config.sources.contains_key(*s))
+                        .map_or(0, |(s, v)| entry_size(s, v))
+            };
+            if !batch.anchors.is_empty() && batch.size + cost(&config) > max_size {
+                batches.push((std::mem::take(&mut config), std::mem::take(&mut batch)));
+            }
+            if batch.anchors.is_empty() {
+                batch.size = empty;
+                if empty + cost(&config) > max_size {
+                    return too_large(format!(
+                        "anchor group `{}` alone is {} bytes",
+                        anchor,
+                        empty + cost(&config)
+                    ));
+                }
+            }
+            batch.size += cost(&config);
+            config.anchors.insert(anchor.to_string(), value.to_owned());
+            if let Some((s, v)) = source {
+                config.sources.insert(s.to_owned(), v.to_owned());
+            }
+            batch.anchors.push(anchor.to_string());
+            batch.features.extend(anchor_features);
+        }
+        batches.push((config, batch));
+        Ok(batches)
+    }
+}
+
+/**
+ * Anchor groups and features of one of the generation jobs split from a job with a too large
+ * feature config, see `FeatureConfig::split`
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct FeatureConfigBatch {
+    pub anchors: Vec<String>,
+    pub features: Vec<String>,
+    /// Estimated size of the feature config in bytes
+    pub size: usize,
+}
+
+/**
+ * Size of the `"name": value,` line in the rendered config, lines of the value are indented by
+ * 2 more levels
+ */
+fn entry_size(name: &str, value: &Value) -> usize {
+    const INDENT: usize = 4;
+    let rendered = serde_json::to_string_pretty(value).unwrap_or_default();
+    // Indent, quotes, `: `, `,` and the line break
+    INDENT + name.len() + 6 + rendered.len() + (rendered.lines().count() - 1) * INDENT
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FeatureJoinConfig {
     #[serde(flatten)]
@@ -11,6 +159,240 @@ pub struct FeatureJoinConfig {
     pub output_path: String,
 }
 
-// TODO:
-pub struct FeatureGenConfig;
+/**
+ * Each feature generation job materializes one time window
+ */
+pub type FeatureGenConfig = MaterializationSettings;
+
+/**
+ * Job config of a request, kept typed until the request is submitted so it can still be changed
+ * after the request is built
+ */
+#[derive(Clone, Debug)]
+pub enum JobConfig {
+    Join(FeatureJoinConfig),
+    Generation(FeatureGenConfig),
+    /// Config loaded from a job manifest, it's submitted as is
+    Rendered {
+        generation: bool,
+        content: String,
+    },
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self::Rendered {
+            generation: false,
+            content: Default::default(),
+        }
+    }
+}
+
+impl JobConfig {
+    pub fn is_generation(&self) -> bool {
+        match self {
+            JobConfig::Join(_) => false,
+            JobConfig::Generation(_) => true,
+            JobConfig::Rendered { generation, .. } => *generation,
+        }
+    }
 
+    pub fn join_config_mut(&mut self) -> Option<&mut FeatureJoinConfig> {
+        match self {
+            JobConfig::Join(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn gen_config_mut(&mut self) -> Option<&mut FeatureGenConfig> {
+        match self {
+            JobConfig::Generation(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /**
+     * Change the format of all HDFS sinks of a feature generation job
+     */
+    pub fn set_output_format(&mut self, format: OutputFormat) -> Result<&mut Self, Error> {
+        match self.gen_config_mut() {
+            Some(c) => {
+                for sink in c.all_sinks_mut() {
+                    if let OutputSink::Hdfs(s) = sink {
+                        s.format = Some(format);
+                    }
+                }
+            }
+            None => {
+                return Err(Error::InvalidArgument(
+                    "Output format can only be set on typed feature generation job configs"
+                        .to_string(),
+                ))
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn render(&self) -> Result<String, Error> {
+        Ok(match self {
+            JobConfig::Join(c) => serde_json::to_string_pretty(c)?,
+            JobConfig::Generation(c) => serde_json::to_string_pretty(c)?,
+            JobConfig::Rendered { content, .. } => content.to_owned(),
+        })
+    }
+}
+
+/**
+ * A config as it is written to the job, and the value parsed from it, so configs can be inspected
+ * and compared before the jobs are submitted
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedConfig {
+    pub content: String,
+    pub value: Value,
+}
+
+impl RenderedConfig {
+    pub fn new(content: String) -> Result<Self, Error> {
+        let value = serde_json::from_str(&content)?;
+        Ok(Self { content, value })
+    }
+}
+
+impl TryFrom<&FeatureConfig> for RenderedConfig {
+    type Error = Error;
+
+    fn try_from(config: &FeatureConfig) -> Result<Self, Self::Error> {
+        Self::new(config.render()?)
+    }
+}
+
+impl TryFrom<&JobConfig> for RenderedConfig {
+    type Error = Error;
+
+    fn try_from(config: &JobConfig) -> Result<Self, Self::Error> {
+        Self::new(config.render()?)
+    }
+}
+
+/**
+ * Saved in job manifests as the rendered `join_job_config` and `gen_job_config` fields, one of them
+ * is empty
+ */
+impl Serialize for JobConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let content = self.render().map_err(serde::ser::Error::custom)?;
+        let (join_config, gen_config) = if self.is_generation() {
+            (String::new(), content)
+        } else {
+            (content, String::new())
+        };
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("join_job_config", &join_config)?;
+        map.serialize_entry("gen_job_config", &gen_config)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for JobConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RenderedJobConfig {
+            #[serde(default)]
+            join_job_config: String,
+            #[serde(default)]
+            gen_job_config: String,
+        }
+        let c = RenderedJobConfig::deserialize(deserializer)?;
+        Ok(if c.gen_job_config.is_empty() {
+            JobConfig::Rendered {
+                generation: false,
+                content: c.join_job_config,
+            }
+        } else {
+            JobConfig::Rendered {
+                generation: true,
+                content: c.gen_job_config,
+            }
+        })
+    }
+}
+
+/**
+ * Feature configs are saved in job manifests as rendered strings
+ */
+pub(crate) mod rendered_feature_config {
+    use super::*;
+
+    pub fn serialize<S>(config: &FeatureConfig, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&config.render().map_err(serde::ser::Error::custom)?)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<FeatureConfig, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        if s.trim().is_empty() {
+            return Ok(Default::default());
+        }
+        serde_json::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_split_feature_config() {
+        let def = "x".repeat(100);
+        let config: FeatureConfig = serde_json::from_value(json!({
+            "anchors": {
+                "a1": {"source": "s1", "features": {"f1": {"def": def}, "f2": {"def": "y"}}},
+                "a2": {"source": "s1", "features": {"f3": {"def": def}}},
+                "a3": {"source": "s2", "features": {"f4": {"def": def}}},
+                "a4": {"source": "s2", "features": {"f5": {"def": def}}},
+            },
+            "sources": {"s1": {"location": {"path": "p1"}}, "s2": {"location": {"path": "p2"}}},
+        }))
+        .unwrap();
+        let size = config.estimate_size();
+        assert!(size.abs_diff(config.render().unwrap().len()) < size / 20);
+
+        let features: Vec<String> = ["f1", "f2", "f3", "f4"]
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        let batches = config.split(&features, 0).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].0, config);
+
+        let batches = config.split(&features, size / 2 + 100).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].1.anchors, vec!["a1", "a2"]);
+        assert_eq!(batches[0].1.features, vec!["f1", "f2", "f3"]);
+        assert_eq!(batches[1].1.anchors, vec!["a3"]);
+        assert_eq!(batches[1].0.sources.keys().collect::<Vec<_>>(), vec!["s2"]);
+        for (config, batch) in &batches {
+            assert!(batch.size <= size / 2 + 100);
+            assert_eq!(config.estimate_size(), batch.size);
+        }
+
+        assert!(config.split(&features, 200).is_err());
+        assert!(config
+            .split(&["f1".to_string(), "d1".to_string()], size / 2)
+            .is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ClusterSize, Error, JobBackend, JobHandle, JobId, SubmitJobRequest, VarSource, PROJECT_TAG,
+};
+
+const DEFAULT_CURRENCY: &str = "USD";
+
+#[cfg(feature = "testing")]
+const FREE: BackendPrice = BackendPrice {
+    vcore_hour: 0.0,
+    memory_gb_hour: 0.0,
+};
+
+fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+/**
+ * Unit prices of a backend, a price not set is 0
+ */
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackendPrice {
+    #[serde(default)]
+    pub vcore_hour: f64,
+    #[serde(default)]
+    pub memory_gb_hour: f64,
+}
+
+/**
+ * Prices used to estimate the cost of finished jobs, read from the `spark_config.cost` section, e.g.
+ * ```yaml
+ * spark_config:
+ *   cost:
+ *     currency: USD
+ *     azure_synapse:
+ *       vcore_hour: 0.143
+ *     databricks:
+ *       vcore_hour: 0.15
+ *       memory_gb_hour: 0.01
+ * ```
+ */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PriceTable {
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default)]
+    pub azure_synapse: BackendPrice,
+    #[serde(default)]
+    pub databricks: BackendPrice,
+    #[serde(default)]
+    pub generic_livy: BackendPrice,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        Self {
+            currency: default_currency(),
+            azure_synapse: Default::default(),
+            databricks: Default::default(),
+            generic_livy: Default::default(),
+        }
+    }
+}
+
+impl PriceTable {
+    /**
+     * All prices are 0 if the section is not set
+     */
+    pub(crate) async fn from_var_source(
+        var_source: Arc<dyn VarSource + Send + Sync>,
+    ) -> Result<Self, Error> {
+        match var_source
+            .get_environment_variable(&["spark_config", "cost"])
+            .await
+        {
+            Ok(s) if !s.trim().is_empty() => serde_yaml::from_str(&s)
+                .map_err(|e| Error::InvalidConfig(format!("Invalid `spark_config.cost`: {}", e))),
+            _ => Ok(Default::default()),
+        }
+    }
+
+    pub fn price(&self, backend: JobBackend) -> &BackendPrice {
+        match backend {
+            JobBackend::AzureSynapse => &self.azure_synapse,
+            JobBackend::Databricks => &self.databricks,
+            JobBackend::GenericLivy => &self.generic_livy,
+            // Mock jobs use no resources
+            #[cfg(feature = "testing")]
+            JobBackend::Mock => &FREE,
+        }
+    }
+
+    pub fn estimate(
+        &self,
+        backend: JobBackend,
+        cluster_size: &ClusterSize,
+        runtime: Duration,
+    ) -> f64 {
+        let price = self.price(backend);
+        let hours = runtime.as_secs_f64() / 3600.0;
+        hours
+            * (vcores(cluster_size) as f64 * price.vcore_hour
+                + memory_gb(cluster_size) * price.memory_gb_hour)
+    }
+}
+
+fn vcores(size: &ClusterSize) -> u64 {
+    size.driver_cores + size.executor_cores * size.num_executors
+}
+
+fn memory_gb(size: &ClusterSize) -> f64 {
+    parse_memory_gb(&size.driver_memory)
+        + parse_memory_gb(&size.executor_memory) * size.num_executors as f64
+}
+
+/**
+ * Spark memory strings, e.g. `4g` or `512m`, numbers without unit are in MB, invalid values are 0
+ */
+fn parse_memory_gb(s: &str) -> f64 {
+    let s = s.trim().to_lowercase();
+    let s = s.strip_suffix('b').unwrap_or(&s);
+    let (number, scale) = match s.chars().last() {
+        Some('k') => (&s[..s.len() - 1], 1.0 / 1024.0 / 1024.0),
+        Some('m') => (&s[..s.len() - 1], 1.0 / 1024.0),
+        Some('g') => (&s[..s.len() - 1], 1.0),
+        Some('t') => (&s[..s.len() - 1], 1024.0),
+        _ => (s, 1.0 / 1024.0),
+    };
+    number.trim().parse::<f64>().unwrap_or_default() * scale
+}
+
+/**
+ * Resources and estimated cost of a finished job.
+ * The runtime is measured from the submission to the time the client observed the job ended, so
+ * it includes the time waiting for the cluster to start.
+ */
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobCost {
+    pub job_id: JobId,
+    pub backend: JobBackend,
+    pub name: String,
+    /// Project the job is built from, `None` for jobs not built from a project
+    pub project: Option<String>,
+    /// A medium size is assumed if the job request doesn't set it
+    pub cluster_size: ClusterSize,
+    pub submitted_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub runtime: Duration,
+    pub estimated_cost: f64,
+    pub currency: String,
+}
+
+impl JobCost {
+    pub(crate) fn new(
+        handle: &JobHandle,
+        request: &SubmitJobRequest,
+        ended_at: DateTime<Utc>,
+        prices: &PriceTable,
+    ) -> Self {
+        let cluster_size = request
+            .cluster_size
+            .clone()
+            .unwrap_or_else(ClusterSize::MEDIUM);
+        let runtime = (ended_at - handle.submitted_at)
+            .to_std()
+            .unwrap_or_default();
+        Self {
+            job_id: handle.id,
+            backend: handle.backend,
+            name: handle.name.to_owned(),
+            project: request.job_tags.get(PROJECT_TAG).cloned(),
+            estimated_cost: prices.estimate(handle.backend, &cluster_size, runtime),
+            cluster_size,
+            submitted_at: handle.submitted_at,
+            ended_at,
+            runtime,
+            currency: prices.currency.to_owned(),
+        }
+    }
+
+    pub fn vcore_hours(&self) -> f64 {
+        vcores(&self.cluster_size) as f64 * self.runtime.as_secs_f64() / 3600.0
+    }
+}
+
+/**
+ * Costs of the jobs of a project ended in a month
+ */
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostSummary {
+    pub project: Option<String>,
+    /// In the form of `2022-10`, by the time the jobs ended
+    pub month: String,
+    pub jobs: usize,
+    pub runtime: Duration,
+    pub vcore_hours: f64,
+    pub estimated_cost: f64,
+    pub currency: String,
+}
+
+/**
+ * Aggregate the job costs per project and month, ordered by project and then month
+ */
+pub fn summarize_costs<'a, I>(costs: I) -> Vec<CostSummary>
+where
+    I: IntoIterator<Item = &'a JobCost>,
+{
+    let mut summaries: BTreeMap<(Option<String>, String), CostSummary> = BTreeMap::new();
+    for cost in costs {
+        let month = cost.ended_at.format("%Y-%m").to_string();
+        let summary = summaries
+            .entry((cost.project.clone(), month.clone()))
+            .or_insert_with(|| CostSummary {
+                project: cost.project.clone(),
+                month,
+                jobs: 0,
+                runtime: Duration::ZERO,
+                vcore_hours: 0.0,
+                estimated_cost: 0.0,
+                currency: cost.currency.to_owned(),
+            });
+        summary.jobs += 1;
+        summary.runtime += cost.runtime;
+        summary.vcore_hours += cost.vcore_hours();
+        summary.estimated_cost += cost.estimated_cost;
+    }
+    summaries.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_job_cost() {
+        assert_eq!(parse_memory_gb("4g"), 4.0);
+        assert_eq!(parse_memory_gb("512m"), 0.5);
+        assert_eq!(parse_memory_gb("2048"), 2.0);
+        assert_eq!(parse_memory_gb("1TB"), 1024.0);
+        assert_eq!(parse_memory_gb("lots"), 0.0);
+
+        let prices: PriceTable =
+            serde_yaml::from_str("azure_synapse:\n  vcore_hour: 0.5\n  memory_gb_hour: 0.1\n")
+                .unwrap();
+        assert_eq!(prices.currency, "USD");
+        let submitted_at = Utc.ymd(2022, 10, 31).and_hms(23, 0, 0);
+        let mut handle = JobHandle::new(JobId(1), JobBackend::AzureSynapse, "job1");
+        handle.submitted_at = submitted_at;
+        let mut request = SubmitJobRequest::default();
+        request
+            .job_tags
+            .insert(PROJECT_TAG.to_string(), "project1".to_string());
+
+        // Medium size has 6 vCores and 12 GB memory
+        let cost = JobCost::new(
+            &handle,
+            &request,
+            submitted_at + chrono::Duration::hours(2),
+            &prices,
+        );
+        assert_eq!(cost.runtime, Duration::from_secs(7200));
+        assert_eq!(cost.vcore_hours(), 12.0);
+        assert!((cost.estimated_cost - 8.4).abs() < 1e-9);
+        // No price for Databricks
+        handle.backend = JobBackend::Databricks;
+        let free = JobCost::new(
+            &handle,
+            &request,
+            submitted_at + chrono::Duration::minutes(30),
+            &prices,
+        );
+        assert_eq!(free.estimated_cost, 0.0);
+
+        let summaries = summarize_costs(&[cost, free]);
+        assert_eq!(summaries.len(), 2);
+        // The first job ended in the next month
+        assert_eq!(summaries[0].month, "2022-10");
+        assert_eq!(summaries[0].jobs, 1);
+        assert_eq!(summaries[0].estimated_cost, 0.0);
+        assert_eq!(summaries[1].month, "2022-11");
+        assert_eq!(summaries[1].project.as_deref(), Some("project1"));
+        assert_eq!(summaries[1].runtime, Duration::from_secs(7200));
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, SubmitJobRequest};
+
+/// Version of the manifest format, manifests with newer versions are rejected
+pub const JOB_MANIFEST_VERSION: u32 = 1;
+
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/**
+ * Everything needed to submit a job again, the request contains the generated feature configs
+ * and the job configs, credentials are resolved from the client config when the job is submitted
+ * so they are never saved in the manifest.
+ */
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobManifest {
+    pub version: u32,
+    /// Version of the Feathr client that created the manifest
+    pub client_version: String,
+    pub created_at: DateTime<Utc>,
+    pub request: SubmitJobRequest,
+}
+
+impl JobManifest {
+    pub fn new(request: &SubmitJobRequest) -> Self {
+        Self {
+            version: JOB_MANIFEST_VERSION,
+            client_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            request: request.to_owned(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let manifest: Self = serde_json::from_str(json)?;
+        if manifest.version > JOB_MANIFEST_VERSION {
+            return Err(Error::UnsupportedManifestVersion(manifest.version));
+        }
+        Ok(manifest)
+    }
+
+    /**
+     * The manifest is stored next to the job output, i.e. `output.avro.manifest.json` for the
+     * output `output.avro`
+     */
+    pub(crate) fn output_file_name(&self) -> Option<String> {
+        let output = self.request.output.trim_end_matches('/');
+        (!output.is_empty()).then(|| format!("{}{}", output, MANIFEST_SUFFIX))
+    }
+
+    /**
+     * File name in the workspace, used if the job has no output path on the storage, e.g. feature
+     * generation jobs
+     */
+    pub(crate) fn config_file_name(&self) -> String {
+        format!(
+            "{}{}",
+            self.request.job_config_file_name.trim_end_matches(".conf"),
+            MANIFEST_SUFFIX
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JobConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let request = SubmitJobRequest {
+            name: "test_job".to_string(),
+            job_config_file_name: "feathr_join_config_test_job_1234.conf".to_string(),
+            output: "abfss://c@a.dfs.core.windows.net/output.avro/".to_string(),
+            feature_config: serde_json::from_str(r#"{"anchors": {"g1": {}}}"#).unwrap(),
+            job_config: JobConfig::Rendered {
+                generation: false,
+                content: "{}".to_string(),
+            },
+            python_files: vec!["udf.py".to_string()],
+            ..Default::default()
+        };
+        let manifest = JobManifest::new(&request);
+        assert_eq!(
+            manifest.output_file_name().unwrap(),
+            "abfss://c@a.dfs.core.windows.net/output.avro.manifest.json"
+        );
+        assert_eq!(
+            manifest.config_file_name(),
+            "feathr_join_config_test_job_1234.manifest.json"
+        );
+        let loaded = JobManifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.request.name, request.name);
+        assert_eq!(loaded.request.job_key, request.job_key);
+        assert_eq!(loaded.request.feature_config, request.feature_config);
+        assert!(!loaded.request.job_config.is_generation());
+        assert_eq!(loaded.request.job_config.render().unwrap(), "{}");
+        assert_eq!(loaded.request.python_files, request.python_files);
+
+        let mut newer = manifest;
+        newer.version = JOB_MANIFEST_VERSION + 1;
+        assert!(matches!(
+            JobManifest::from_json(&newer.to_json().unwrap()),
+            Err(Error::UnsupportedManifestVersion(_))
+        ));
+
+        assert!(JobManifest::new(&Default::default())
+            .output_file_name()
+            .is_none());
+    }
+}
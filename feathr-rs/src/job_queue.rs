@@ -0,0 +1,309 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::Serialize;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::{JobBackend, JobHandle, JobId, VarSource};
+
+/// Interval of checking running jobs while there are requests waiting in the queue
+pub(crate) const QUEUE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/**
+ * A job request waiting for a free slot
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct QueuedJob {
+    pub queue_id: Uuid,
+    /// 1-based position in the queue
+    pub position: usize,
+    pub name: String,
+    pub queued_at: DateTime<Utc>,
+}
+
+/**
+ * Snapshot of the client side job queue
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct QueueStatus {
+    pub backend: JobBackend,
+    /// `None` if the number of concurrent jobs is not limited
+    pub max_concurrent_jobs: Option<usize>,
+    /// Jobs submitted by this client and not known to be ended yet, only tracked if the number of
+    /// concurrent jobs is limited
+    pub running: Vec<JobHandle>,
+    pub queued: Vec<QueuedJob>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    running: HashMap<JobId, JobHandle>,
+    /// Slots taken by requests being submitted
+    submitting: usize,
+    waiting: VecDeque<(Uuid, String, DateTime<Utc>)>,
+    last_checked: Option<Instant>,
+}
+
+/**
+ * Limits the number of concurrent jobs submitted to the backend, requests exceeding the limit
+ * wait in FIFO order and are submitted as slots free up.
+ *
+ * Slots are freed once the job is observed as ended, either by `wait_for_job`, `get_job_status`
+ * or the periodic check while there are requests waiting.
+ */
+#[derive(Debug)]
+pub(crate) struct JobQueue {
+    backend: JobBackend,
+    max_concurrent_jobs: Option<usize>,
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+impl JobQueue {
+    pub(crate) fn new(backend: JobBackend, max_concurrent_jobs: Option<usize>) -> Self {
+        Self {
+            backend,
+            max_concurrent_jobs: max_concurrent_jobs.filter(|&n| n > 0),
+            state: Default::default(),
+            notify: Notify::new(),
+        }
+    }
+
+    /**
+     * Read `max_concurrent_jobs` from the backend section of `spark_config`, e.g.
+     * `spark_config.azure_synapse.max_concurrent_jobs`, or from `spark_config.max_concurrent_jobs`
+     * which applies to all backends. Not set or 0 means unlimited.
+     */
+    pub(crate) async fn from_var_source(
+        backend: JobBackend,
+        var_source: Arc<dyn VarSource + Send + Sync>,
+    ) -> Result<Self, crate::Error> {
+        let section = match backend {
+            JobBackend::AzureSynapse => "azure_synapse",
+            JobBackend::Databricks => "databricks",
+            JobBackend::GenericLivy => "generic_livy",
+            #[cfg(feature = "testing")]
+            JobBackend::Mock => "mock",
+        };
+        let value = match var_source
+            .get_environment_variable(&["spark_config", section, "max_concurrent_jobs"])
+            .await
+        {
+            Ok(v) => Some(v),
+            Err(_) => var_source
+                .get_environment_variable(&["spark_config", "max_concurrent_jobs"])
+                .await
+                .ok(),
+        };
+        let max_concurrent_jobs = match value {
+            Some(v) => Some(v.trim().parse::<usize>().map_err(|_| {
+                crate::Error::InvalidConfig(format!("Invalid max_concurrent_jobs `{}`", v))
+            })?),
+            None => None,
+        };
+        Ok(Self::new(backend, max_concurrent_jobs))
+    }
+
+    /**
+     * Put a request at the end of the queue, the request leaves the queue when the ticket is dropped
+     */
+    pub(crate) fn enqueue(&self, name: &str) -> QueueTicket<'_> {
+        let id = Uuid::new_v4();
+        self.lock()
+            .waiting
+            .push_back((id, name.to_string(), Utc::now()));
+        QueueTicket {
+            queue: self,
+            id,
+            state: TicketState::Waiting,
+        }
+    }
+
+    /**
+     * Wait until the queue changes or the timeout elapsed
+     */
+    pub(crate) async fn wait(&self, timeout: std::time::Duration) {
+        let _ = tokio::time::timeout(timeout, self.notify.notified()).await;
+    }
+
+    /**
+     * Running jobs to be checked, returns empty if they were checked within `QUEUE_POLL_INTERVAL`
+     */
+    pub(crate) fn jobs_to_check(&self) -> Vec<JobId> {
+        let mut state = self.lock();
+        if state.waiting.is_empty()
+            || state
+                .last_checked
+                .map(|t| t.elapsed() < QUEUE_POLL_INTERVAL)
+                .unwrap_or(false)
+        {
+            return vec![];
+        }
+        state.last_checked = Some(Instant::now());
+        state.running.keys().copied().collect()
+    }
+
+    /**
+     * Free the slot taken by the job
+     */
+    pub(crate) fn job_ended(&self, job_id: JobId) {
+        if self.lock().running.remove(&job_id).is_some() {
+            debug!("Job {} ended, slot released", job_id);
+            self.notify.notify_waiters();
+        }
+    }
+
+    pub(crate) fn status(&self) -> QueueStatus {
+        let state = self.lock();
+        let mut running: Vec<JobHandle> = state.running.values().cloned().collect();
+        running.sort_by_key(|h| h.submitted_at);
+        QueueStatus {
+            backend: self.backend,
+            max_concurrent_jobs: self.max_concurrent_jobs,
+            running,
+            queued: state
+                .waiting
+                .iter()
+                .enumerate()
+                .map(|(idx, (id, name, queued_at))| QueuedJob {
+                    queue_id: *id,
+                    position: idx + 1,
+                    name: name.to_owned(),
+                    queued_at: *queued_at,
+                })
+                .collect(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        // The state is always consistent as no code panics while holding the lock
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TicketState {
+    Waiting,
+    Submitting,
+    Done,
+}
+
+/**
+ * Position of a request in the queue
+ */
+#[derive(Debug)]
+pub(crate) struct QueueTicket<'a> {
+    queue: &'a JobQueue,
+    id: Uuid,
+    state: TicketState,
+}
+
+impl<'a> QueueTicket<'a> {
+    /**
+     * Take a slot if the request is at the head of the queue and a slot is free
+     */
+    pub(crate) fn try_start(&mut self) -> bool {
+        if self.state != TicketState::Waiting {
+            return true;
+        }
+        let mut state = self.queue.lock();
+        let is_head = state.waiting.front().map(|(id, _, _)| *id) == Some(self.id);
+        let has_slot = self
+            .queue
+            .max_concurrent_jobs
+            .map(|max| state.running.len() + state.submitting < max)
+            .unwrap_or(true);
+        if is_head && has_slot {
+            state.waiting.pop_front();
+            state.submitting += 1;
+            self.state = TicketState::Submitting;
+            drop(state);
+            // Next request may also be able to start
+            self.queue.notify.notify_waiters();
+            true
+        } else {
+            false
+        }
+    }
+
+    /**
+     * The job is submitted, the slot is held until the job is ended.
+     * Jobs are not tracked without a limit, as nothing waits for the slots to be freed
+     */
+    pub(crate) fn started(mut self, handle: &JobHandle) {
+        let mut state = self.queue.lock();
+        if self.state == TicketState::Submitting {
+            state.submitting -= 1;
+        }
+        if self.queue.max_concurrent_jobs.is_some() {
+            state.running.insert(handle.id, handle.clone());
+        }
+        self.state = TicketState::Done;
+    }
+}
+
+impl<'a> Drop for QueueTicket<'a> {
+    fn drop(&mut self) {
+        let mut state = self.queue.lock();
+        match self.state {
+            TicketState::Waiting => state.waiting.retain(|(id, _, _)| *id != self.id),
+            TicketState::Submitting => state.submitting -= 1,
+            TicketState::Done => return,
+        }
+        drop(state);
+        self.queue.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let queue = JobQueue::new(JobBackend::AzureSynapse, Some(1));
+        let mut t1 = queue.enqueue("job1");
+        let mut t2 = queue.enqueue("job2");
+        let mut t3 = queue.enqueue("job3");
+        // Requests start in FIFO order
+        assert!(!t2.try_start());
+        assert!(t1.try_start());
+        assert!(!t2.try_start());
+        let status = queue.status();
+        assert_eq!(status.queued.len(), 2);
+        assert_eq!(status.queued[0].name, "job2");
+        assert_eq!(status.queued[0].position, 1);
+
+        let h1 = JobHandle::new(JobId(1), JobBackend::AzureSynapse, "job1");
+        t1.started(&h1);
+        assert_eq!(queue.status().running, vec![h1]);
+        assert!(!t2.try_start());
+        queue.job_ended(JobId(1));
+        assert!(t2.try_start());
+
+        // Failed submission releases the slot
+        drop(t2);
+        assert!(t3.try_start());
+        assert!(queue.status().queued.is_empty());
+
+        // Cancelled request leaves the queue
+        let t4 = queue.enqueue("job4");
+        assert_eq!(queue.status().queued.len(), 1);
+        drop(t4);
+        assert!(queue.status().queued.is_empty());
+
+        let queue = JobQueue::new(JobBackend::Databricks, None);
+        let mut t1 = queue.enqueue("job1");
+        let mut t2 = queue.enqueue("job2");
+        assert!(t1.try_start());
+        assert!(t2.try_start());
+        t1.started(&JobHandle::new(JobId(1), JobBackend::Databricks, "job1"));
+        assert!(queue.status().running.is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use std::{sync::Arc, time::Duration};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{utils::str_to_dur, Error, JobId, VarSource};
+
+/// Job tag of resubmitted jobs, the id of the first attempt
+pub const RETRY_OF_TAG: &str = "feathr_retry_of";
+/// Job tag of resubmitted jobs, 1-based number of the attempt
+pub const ATTEMPT_TAG: &str = "feathr_attempt";
+
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/**
+ * Resubmit failed jobs when they're waited by `FeathrClient::wait_for_job`.
+ * A failed job is resubmitted if its driver log matches any of `retry_on`, or on any failure if
+ * `retry_on` is empty. The delay before each resubmission starts at `backoff` and doubles after
+ * each attempt, up to `max_backoff`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Regular expressions matched against the driver log of the failed job
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            retry_on: Default::default(),
+            backoff: DEFAULT_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /**
+     * Only retry failures with driver logs matching the pattern, can be called multiple times
+     */
+    pub fn retry_on(&mut self, pattern: &str) -> Result<&mut Self, Error> {
+        Regex::new(pattern).map_err(|e| {
+            Error::InvalidArgument(format!("Invalid retry pattern `{}`: {}", pattern, e))
+        })?;
+        self.retry_on.push(pattern.to_string());
+        Ok(self)
+    }
+
+    pub fn backoff(&mut self, backoff: Duration, max_backoff: Duration) -> &mut Self {
+        self.backoff = backoff;
+        self.max_backoff = max_backoff.max(backoff);
+        self
+    }
+
+    /**
+     * Read the `spark_config.retry` section, `None` if `max_retries` is not set or 0, e.g.
+     * ```yaml
+     * spark_config:
+     *   retry:
+     *     max_retries: 3
+     *     retry_on:
+     *       - 'Container killed by YARN'
+     *       - 'java.net.SocketTimeoutException'
+     *     backoff: 30s
+     *     max_backoff: 10m
+     * ```
+     */
+    pub(crate) async fn from_var_source(
+        var_source: Arc<dyn VarSource + Send + Sync>,
+    ) -> Result<Option<Self>, Error> {
+        let get = |key: &'static str| {
+            let var_source = var_source.clone();
+            async move {
+                var_source
+                    .get_environment_variable(&["spark_config", "retry", key])
+                    .await
+                    .ok()
+                    .filter(|s| !s.trim().is_empty())
+            }
+        };
+        let max_retries = match get("max_retries").await {
+            Some(s) => serde_yaml::from_str::<u32>(&s).map_err(|_| {
+                Error::InvalidOption("spark_config.retry.max_retries".to_string(), s.to_owned())
+            })?,
+            None => 0,
+        };
+        if max_retries == 0 {
+            return Ok(None);
+        }
+        let mut policy = Self::new(max_retries);
+        if let Some(s) = get("retry_on").await {
+            let patterns: Vec<String> = serde_yaml::from_str(&s).map_err(|_| {
+                Error::InvalidOption("spark_config.retry.retry_on".to_string(), s.to_owned())
+            })?;
+            for p in patterns {
+                policy.retry_on(&p)?;
+            }
+        }
+        let duration = |key: &str, s: String| {
+            let value: String = serde_yaml::from_str(&s).unwrap_or_else(|_| s.to_owned());
+            str_to_dur(&value)
+                .ok()
+                .and_then(|d| d.to_std().ok())
+                .ok_or_else(|| Error::InvalidOption(format!("spark_config.retry.{}", key), s))
+        };
+        let backoff = match get("backoff").await {
+            Some(s) => duration("backoff", s)?,
+            None => DEFAULT_BACKOFF,
+        };
+        let max_backoff = match get("max_backoff").await {
+            Some(s) => duration("max_backoff", s)?,
+            None => DEFAULT_MAX_BACKOFF,
+        };
+        policy.backoff(backoff, max_backoff);
+        Ok(Some(policy))
+    }
+
+    /**
+     * Whether the job failed at the 1-based `attempt` should be resubmitted
+     */
+    pub fn should_retry(&self, attempt: u32, driver_log: &str) -> bool {
+        attempt <= self.max_retries
+            && (self.retry_on.is_empty()
+                || self
+                    .retry_on
+                    .iter()
+                    .filter_map(|p| Regex::new(p).ok())
+                    .any(|re| re.is_match(driver_log)))
+    }
+
+    /**
+     * Delay before resubmitting the job failed at the 1-based `attempt`
+     */
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .checked_mul(1 << attempt.saturating_sub(1).min(16))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/**
+ * Attempts of a job submitted with a retry policy
+ */
+#[derive(Clone, Debug)]
+pub(crate) struct RetryState {
+    pub(crate) policy: RetryPolicy,
+    /// Id of the first attempt
+    pub(crate) first: JobId,
+    /// 1-based number of the attempt
+    pub(crate) attempt: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::try_new_var_source;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_policy() {
+        let mut policy = RetryPolicy::new(2);
+        policy
+            .retry_on("Container killed by YARN")
+            .unwrap()
+            .backoff(Duration::from_secs(10), Duration::from_secs(25));
+        assert!(policy.retry_on("(").is_err());
+
+        assert!(policy.should_retry(1, "ExecutorLostFailure: Container killed by YARN"));
+        assert!(!policy.should_retry(1, "AnalysisException: cannot resolve column"));
+        assert!(policy.should_retry(2, "Container killed by YARN"));
+        assert!(!policy.should_retry(3, "Container killed by YARN"));
+        // Any failure is retried without patterns
+        assert!(RetryPolicy::new(1).should_retry(1, "AnalysisException"));
+
+        assert_eq!(policy.delay(1), Duration::from_secs(10));
+        assert_eq!(policy.delay(2), Duration::from_secs(20));
+        assert_eq!(policy.delay(3), Duration::from_secs(25));
+        assert_eq!(policy.delay(100), Duration::from_secs(25));
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_config() {
+        let var_source = try_new_var_source(
+            r#"
+spark_config:
+  spark_cluster: local
+  retry:
+    max_retries: 3
+    retry_on:
+      - 'Container killed by YARN'
+    backoff: 1m
+online_store: {}
+feature_registry: {}
+"#,
+        )
+        .unwrap();
+        let policy = RetryPolicy::from_var_source(var_source)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(policy.max_retries, 3);
+        assert_eq!(
+            policy.retry_on,
+            vec!["Container killed by YARN".to_string()]
+        );
+        assert_eq!(policy.backoff, Duration::from_secs(60));
+        assert_eq!(policy.max_backoff, DEFAULT_MAX_BACKOFF);
+
+        let var_source = try_new_var_source(
+            "spark_config:\n  spark_cluster: local\nonline_store: {}\nfeature_registry: {}\n",
+        )
+        .unwrap();
+        assert!(RetryPolicy::from_var_source(var_source)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -3,6 +3,7 @@ mod error;
 mod var_source;
 mod feature;
 mod feature_builder;
+mod feature_template;
 mod model;
 mod source;
 mod observation;
@@ -11,17 +12,29 @@ mod materialization;
 mod job_config;
 mod utils;
 mod job_client;
+mod job_queue;
+mod job_retry;
+mod job_cost;
+mod query_cost;
+mod job_manifest;
 mod registry_client;
 mod livy_client;
+mod presign;
+mod connectivity;
+mod lint;
+mod schema;
+mod open_lineage;
+mod observability;
 mod client;
 
 use log::trace;
 pub use livy_client::*;
 pub use project::{AnchorGroup, AnchorGroupBuilder, FeathrProject};
 pub use error::Error;
-pub use var_source::{VarSource, new_var_source, load_var_source, default_var_source};
+pub use var_source::{VarSource, new_var_source, load_var_source, try_new_var_source, try_new_var_source_with_overrides, try_load_var_source, try_load_var_source_with_overrides, try_load_var_source_with_profile, default_var_source, PROFILE_ENV};
 pub use feature::{AnchorFeature, DerivedFeature, Feature};
 pub use feature_builder::{AnchorFeatureBuilder, DerivedFeatureBuilder};
+pub use feature_template::FeatureTemplate;
 pub use model::*;
 pub use source::*;
 pub use observation::*;
@@ -30,7 +43,18 @@ pub use materialization::*;
 pub use job_config::*;
 pub use utils::ExtDuration;
 pub use job_client::*;
-pub use registry_client::{FeatureRegistry, FeathrApiClient};
+pub use job_queue::{QueueStatus, QueuedJob};
+pub use job_retry::{RetryPolicy, ATTEMPT_TAG, RETRY_OF_TAG};
+pub use job_cost::{summarize_costs, BackendPrice, CostSummary, JobCost, PriceTable};
+pub use query_cost::{FeatureCost, FeatureCostEstimate, QueryCost, SparkSizingHint, COST_TAG, EXPENSIVE_QUERY_COST};
+pub use job_manifest::{JobManifest, JOB_MANIFEST_VERSION};
+pub use registry_client::{FeatureRegistry, FeathrApiClient, RegistryErrorCode};
+pub use presign::UrlPermissions;
+pub use connectivity::{ConnectionStatus, ConnectionTestResult};
+pub use lint::{Diagnostic, LintKind, LintReport, Severity};
+pub use schema::{Column, SourceSchema};
+pub use open_lineage::{JobLineage, LineageDataset};
+pub use observability::{shutdown_tracing, ObservabilityConfig};
 pub use client::FeathrClient;
 
 /// Log if `Result` is an error
@@ -57,6 +81,20 @@ where
     t == &Default::default()
 }
 
+/**
+ * Optional components compiled into this build, bindings use this to detect them at runtime
+ * instead of failing when they're called
+ */
+pub fn enabled_features() -> Vec<&'static str> {
+    [
+        ("testing", cfg!(feature = "testing")),
+        ("otlp", cfg!(feature = "otlp")),
+    ]
+    .into_iter()
+    .filter_map(|(name, enabled)| enabled.then(|| name))
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use dotenv;
@@ -0,0 +1,395 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{project::FeathrProjectImpl, Feature, SourceSchema, Transformation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The job will fail or produce wrong results
+    Error,
+    /// Likely a mistake, but the job still works
+    Warning,
+    /// Not a problem by itself, e.g. a feature not used by the given queries
+    Info,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Severity::Error => "Error",
+                Severity::Warning => "Warning",
+                Severity::Info => "Info",
+            }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintKind {
+    /// The source is not used by any anchor group
+    UnusedSource,
+    /// The feature is not requested by any query, directly or as an input of a derived feature
+    UnreferencedFeature,
+    /// The window aggregation feature is anchored to a source without timestamp column
+    MissingTimestampColumn,
+    /// The same alias is used by more than one key of a feature
+    DuplicateKeyAlias,
+    /// The same feature name is defined by more than one anchor group or derivation
+    ConflictingFeatureName,
+    /// The key or the transformation uses a column not in the source schema
+    UnknownColumn,
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LintKind::UnusedSource => "UnusedSource",
+                LintKind::UnreferencedFeature => "UnreferencedFeature",
+                LintKind::MissingTimestampColumn => "MissingTimestampColumn",
+                LintKind::DuplicateKeyAlias => "DuplicateKeyAlias",
+                LintKind::ConflictingFeatureName => "ConflictingFeatureName",
+                LintKind::UnknownColumn => "UnknownColumn",
+            }
+        )
+    }
+}
+
+/**
+ * A problem found in the project, `entity` is the name of the source or the feature
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: LintKind,
+    pub entity: String,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            self.severity, self.kind, self.entity, self.message
+        )
+    }
+}
+
+/**
+ * Result of `FeathrProject::lint`, diagnostics are sorted by severity then by entity name
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub(crate) fn sort(&mut self) {
+        self.diagnostics
+            .sort_by(|a, b| (a.severity, &a.entity, a.kind).cmp(&(b.severity, &b.entity, b.kind)));
+    }
+
+    fn push<T: ToString>(&mut self, severity: Severity, kind: LintKind, entity: &str, message: T) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            kind,
+            entity: entity.to_string(),
+            message: message.to_string(),
+        })
+    }
+}
+
+impl FeathrProjectImpl {
+    /**
+     * Checks the project without contacting the registry or the Spark cluster.
+     * Unreferenced features are only reported if `queried` is not empty.
+     */
+    pub(crate) fn lint(&self, queried: &[String]) -> LintReport {
+        let mut report = LintReport::default();
+        self.lint_sources(&mut report);
+        self.lint_features(&mut report);
+        if !queried.is_empty() {
+            self.lint_references(queried, &mut report);
+        }
+        report.sort();
+        report
+    }
+
+    /**
+     * Checks the columns used by anchor features against the schemas of their sources, keyed by
+     * source name. Anchor groups whose source has no schema are skipped.
+     * Columns are extracted from SQL expressions heuristically, so unknown ones are only warnings.
+     */
+    pub(crate) fn lint_columns(
+        &self,
+        schemas: &HashMap<String, SourceSchema>,
+        report: &mut LintReport,
+    ) {
+        for (group, features) in &self.anchor_map {
+            let source = match self.anchor_groups.get(group) {
+                Some(g) => &g.source.inner,
+                None => continue,
+            };
+            let schema = match schemas.get(&source.name) {
+                Some(s) => s,
+                None => continue,
+            };
+            for name in features {
+                let f = match self.anchor_features.get(name) {
+                    Some(f) => f,
+                    None => continue,
+                };
+                let mut columns: Vec<String> = f
+                    .base
+                    .key
+                    .iter()
+                    .filter(|k| k.key_column != "NOT_NEEDED")
+                    .flat_map(|k| referenced_columns(&k.key_column))
+                    .collect();
+                match &f.transform {
+                    Transformation::Expression { def } => {
+                        columns.extend(referenced_columns(&def.sql_expr))
+                    }
+                    Transformation::WindowAgg { def_expr, .. } => {
+                        columns.extend(referenced_columns(def_expr))
+                    }
+                    Transformation::Udf { .. } => {}
+                }
+                columns.sort();
+                columns.dedup();
+                for column in columns {
+                    // `a.b` can be a nested column or a field of struct column `a`
+                    let root = column.split('.').next().unwrap_or_default();
+                    if schema.get_column(&column).is_none()
+                        && !schema
+                            .columns
+                            .iter()
+                            .any(|c| c.name.split('.').next() == Some(root))
+                    {
+                        report.push(
+                            Severity::Warning,
+                            LintKind::UnknownColumn,
+                            name,
+                            format!("Column {} is not in source {}", column, source.name),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn lint_sources(&self, report: &mut LintReport) {
+        let used: HashSet<String> = self
+            .anchor_groups
+            .values()
+            .flat_map(|g| std::iter::once(&g.source).chain(g.joined_sources.iter()))
+            .map(|s| s.get_name())
+            .collect();
+        for (name, source) in &self.sources {
+            if !source.is_input_context() && !used.contains(name) {
+                report.push(
+                    Severity::Warning,
+                    LintKind::UnusedSource,
+                    name,
+                    "Source is not used by any anchor group",
+                );
+            }
+        }
+    }
+
+    fn lint_features(&self, report: &mut LintReport) {
+        // Feature name -> anchor groups defining it
+        let mut defined_in: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (group, features) in &self.anchor_map {
+            for name in features {
+                defined_in.entry(name).or_default().push(group);
+            }
+        }
+        for (name, groups) in &mut defined_in {
+            groups.sort();
+            if groups.len() > 1 {
+                report.push(
+                    Severity::Error,
+                    LintKind::ConflictingFeatureName,
+                    name,
+                    format!("Feature is defined by anchor groups {}", groups.join(", ")),
+                );
+            }
+            if self.derivations.contains_key(*name) {
+                report.push(
+                    Severity::Error,
+                    LintKind::ConflictingFeatureName,
+                    name,
+                    "Feature is defined by both an anchor group and a derivation",
+                );
+            }
+        }
+
+        for (group, features) in &self.anchor_map {
+            let source = match self.anchor_groups.get(group) {
+                Some(g) => &g.source.inner,
+                None => continue,
+            };
+            for name in features {
+                let f = match self.anchor_features.get(name) {
+                    Some(f) => f,
+                    None => continue,
+                };
+                if matches!(f.transform, Transformation::WindowAgg { .. })
+                    && !source.is_input_context()
+                    && source.time_window_parameters.is_none()
+                {
+                    report.push(
+                        Severity::Error,
+                        LintKind::MissingTimestampColumn,
+                        name,
+                        format!(
+                            "Window aggregation needs a timestamp column, but source {} doesn't have one",
+                            source.name
+                        ),
+                    );
+                }
+            }
+        }
+
+        let key_aliases = self
+            .anchor_features
+            .iter()
+            .map(|(name, f)| (name, f.get_key_alias()))
+            .chain(
+                self.derivations
+                    .iter()
+                    .map(|(name, f)| (name, f.get_key_alias())),
+            );
+        for (name, aliases) in key_aliases {
+            let mut seen = HashSet::new();
+            let mut duplicated: Vec<&String> =
+                aliases.iter().filter(|&a| !seen.insert(a)).collect();
+            duplicated.sort();
+            duplicated.dedup();
+            if !duplicated.is_empty() {
+                report.push(
+                    Severity::Error,
+                    LintKind::DuplicateKeyAlias,
+                    name,
+                    format!(
+                        "Key alias {} is used more than once",
+                        duplicated
+                            .into_iter()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                );
+            }
+        }
+    }
+
+    fn lint_references(&self, queried: &[String], report: &mut LintReport) {
+        let mut pending: Vec<String> = queried.to_vec();
+        let mut referenced: HashSet<String> = Default::default();
+        while let Some(name) = pending.pop() {
+            if !referenced.insert(name.clone()) {
+                continue;
+            }
+            if let Some(d) = self.derivations.get(&name) {
+                pending.extend(d.inputs.values().map(|i| i.feature.to_owned()));
+            }
+        }
+        for name in self
+            .anchor_features
+            .keys()
+            .chain(self.derivations.keys())
+            .filter(|&name| !referenced.contains(name))
+            .collect::<HashSet<_>>()
+        {
+            report.push(
+                Severity::Info,
+                LintKind::UnreferencedFeature,
+                name,
+                "Feature is not referenced by any query",
+            );
+        }
+    }
+}
+
+const SQL_KEYWORDS: &[&str] = &[
+    "and", "or", "not", "null", "is", "in", "like", "rlike", "between", "case", "when", "then",
+    "else", "end", "true", "false", "as", "distinct", "interval", "exists", "asc", "desc", "year",
+    "month", "week", "day", "hour", "minute", "second",
+];
+
+/**
+ * Identifiers in a SQL expression that look like column names, string literals, function names,
+ * keywords and the type names after `AS` are skipped
+ */
+pub(crate) fn referenced_columns(expr: &str) -> Vec<String> {
+    let mut columns = vec![];
+    let mut chars = expr.chars().peekable();
+    let mut after_as = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                while let Some(n) = chars.next() {
+                    if n == '\\' {
+                        chars.next();
+                    } else if n == c {
+                        break;
+                    }
+                }
+            }
+            '`' => {
+                let quoted: String = chars.by_ref().take_while(|&n| n != '`').collect();
+                columns.push(quoted);
+            }
+            c if c.is_ascii_digit() => {
+                while chars
+                    .peek()
+                    .map_or(false, |n| n.is_ascii_alphanumeric() || *n == '.')
+                {
+                    chars.next();
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(&n) = chars.peek() {
+                    if n.is_ascii_alphanumeric() || n == '_' || n == '.' {
+                        ident.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                while chars.peek().map_or(false, |n| n.is_whitespace()) {
+                    chars.next();
+                }
+                let is_function = chars.peek() == Some(&'(');
+                let lower = ident.to_lowercase();
+                if !is_function && !after_as && !SQL_KEYWORDS.contains(&lower.as_str()) {
+                    columns.push(ident);
+                }
+                after_as = lower == "as";
+            }
+            _ => {}
+        }
+    }
+    columns
+}
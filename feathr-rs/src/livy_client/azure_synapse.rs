@@ -9,7 +9,9 @@ use oauth2::AccessToken;
 use reqwest::RequestBuilder;
 use thiserror::Error;
 
-use super::{Authenticator, LivyClient, LivyClientError, Result};
+use super::{Authenticator, BigDataPoolResource, LivyClient, LivyClientError, Result};
+
+const BIG_DATA_POOL_API_VERSION: &str = "2020-12-01";
 
 #[derive(Debug, Error)]
 pub enum AzureSynapseError {
@@ -23,8 +25,9 @@ pub enum AzureSynapseError {
     DefaultCredentialError(#[from] azure_core::error::Error),
 }
 
+#[derive(Clone)]
 pub struct AadAuthenticator {
-    credential: DefaultAzureCredential,
+    credential: Arc<DefaultAzureCredential>,
     token: Arc<RwLock<TokenResponse>>,
 }
 
@@ -120,7 +123,7 @@ impl AzureSynapseClientBuilder {
         // It expired long long ago
         let t = chrono::NaiveDateTime::from_timestamp(0, 0);
         let auth = AadAuthenticator {
-            credential: self.credential,
+            credential: Arc::new(self.credential),
             token: Arc::new(RwLock::new(TokenResponse::new(
                 AccessToken::new(Default::default()),
                 DateTime::from_utc(t, chrono::Utc),
@@ -139,6 +142,45 @@ impl AzureSynapseClientBuilder {
     }
 }
 
+impl LivyClient<AadAuthenticator> {
+    /**
+     * The big data pool this client submits jobs to
+     */
+    pub async fn get_pool(&self) -> Result<BigDataPoolResource> {
+        let (workspace, pool) = match (
+            self.url_base.split_once("/livyApi/"),
+            self.url_base.rsplit_once("/sparkpools/"),
+        ) {
+            (Some((workspace, _)), Some((_, pool))) => (workspace, pool),
+            _ => return Err(AzureSynapseError::MissingSynapsePool.into()),
+        };
+        let url = format!(
+            "{}/bigDataPools/{}?api-version={}",
+            workspace, pool, BIG_DATA_POOL_API_VERSION
+        );
+        Ok(serde_json::from_str(&self.get_raw(&url).await?)?)
+    }
+
+    /**
+     * Client of another Spark pool in the same Synapse workspace, the AAD token is shared
+     */
+    pub fn with_pool<T>(&self, pool: T) -> Self
+    where
+        T: AsRef<str>,
+    {
+        let replace_pool = |base: &str| match base.rsplit_once("/sparkpools/") {
+            Some((prefix, _)) => format!("{}/sparkpools/{}", prefix, pool.as_ref()),
+            None => base.to_string(),
+        };
+        LivyClient {
+            client: self.client.clone(),
+            url_base: replace_pool(&self.url_base),
+            log_base: replace_pool(&self.log_base),
+            authenticator: self.authenticator.clone(),
+        }
+    }
+}
+
 impl Default for AzureSynapseClientBuilder {
     fn default() -> Self {
         Self {
@@ -149,3 +191,30 @@ impl Default for AzureSynapseClientBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_pool() {
+        let client = AzureSynapseClientBuilder::default()
+            .url("https://ws1.dev.azuresynapse.net")
+            .pool("spark1")
+            .build()
+            .unwrap();
+        let other = client.with_pool("bigpool");
+        assert_eq!(
+            other.url_base,
+            "https://ws1.dev.azuresynapse.net/livyApi/versions/2022-02-22-preview/sparkpools/bigpool"
+        );
+        assert_eq!(
+            other.log_base,
+            "https://ws1.dev.azuresynapse.net/sparkhistory/api/v1/sparkpools/bigpool"
+        );
+        assert!(Arc::ptr_eq(
+            &client.authenticator.token,
+            &other.authenticator.token
+        ));
+    }
+}
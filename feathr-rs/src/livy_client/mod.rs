@@ -107,6 +107,23 @@ pub struct BasicAuthenticator {
     password: Option<String>,
 }
 
+impl std::fmt::Debug for BasicAuthenticator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BasicAuthenticator")
+            .field("username", &self.username)
+            .finish()
+    }
+}
+
+impl BasicAuthenticator {
+    pub fn new(username: &str, password: Option<&str>) -> Self {
+        Self {
+            username: username.to_string(),
+            password: password.map(|p| p.to_string()),
+        }
+    }
+}
+
 #[async_trait]
 impl Authenticator for BasicAuthenticator {
     async fn authenticate(
@@ -117,6 +134,25 @@ impl Authenticator for BasicAuthenticator {
     }
 }
 
+/**
+ * Optional authentication, no auth if `None`
+ */
+#[async_trait]
+impl<T> Authenticator for Option<T>
+where
+    T: Authenticator + Send + Sync,
+{
+    async fn authenticate(
+        &self,
+        builder: RequestBuilder,
+    ) -> std::result::Result<RequestBuilder, LivyClientError> {
+        match self {
+            Some(auth) => auth.authenticate(builder).await,
+            None => Ok(builder),
+        }
+    }
+}
+
 /**
  * Livy API client
  */
@@ -355,6 +391,34 @@ impl<T: Authenticator> LivyClient<T> {
         self.get_driver_log(id, &app_id, "stderr").await
     }
 
+    /**
+     * Get driver log via the standard Livy API, works with any Livy server
+     */
+    pub async fn get_batch_job_log(&self, id: u64) -> Result<Vec<String>> {
+        let mut ret: Vec<String> = vec![];
+        loop {
+            let resp = self
+                .get::<(), SparkJobLog>(
+                    &format!(
+                        "{}/batches/{}/log?from={}&size=1000",
+                        self.url_base,
+                        id,
+                        ret.len()
+                    ),
+                    None,
+                )
+                .await?;
+            if resp.log.is_empty() {
+                break;
+            }
+            ret.extend(resp.log.into_iter());
+            if ret.len() >= resp.total {
+                break;
+            }
+        }
+        Ok(ret)
+    }
+
     fn remove_trailing_slash(s: &str) -> String {
         if s.ends_with('/') {
             &s[0..s.len() - 1]
@@ -284,6 +284,22 @@ pub enum BigDataPoolReferenceType {
     BigDataPoolReference,
 }
 
+/**
+ * Big data pool of a Synapse workspace, only the fields used by the client
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BigDataPoolResource {
+    pub name: String,
+    pub properties: BigDataPoolProperties,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BigDataPoolProperties {
+    /// Spark version of the pool, e.g. `3.1`
+    pub spark_version: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CloudError {
     pub error: CloudErrorBody,
@@ -331,6 +347,15 @@ pub struct SparkJobCollection {
     pub sessions: Vec<SparkJob>,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SparkJobLog {
+    pub id: u64,
+    pub from: usize,
+    pub total: usize,
+    pub log: Vec<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SparkStatementCollection {
     pub statements: Vec<SparkStatement>,
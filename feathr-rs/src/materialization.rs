@@ -1,10 +1,24 @@
-use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone, Timelike, Utc};
+use hmac::{Hmac, Mac};
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 
-use crate::{DataLocation, Error, GetSecretKeys};
+use crate::{utils::parse_secret, DataLocation, Error, GetSecretKeys};
 
 const END_TIME_FORMAT: &str = "yyyy-MM-dd HH:mm:ss";
 
+const DAILY_PARTITION_PATTERN: &str = "daily/{yyyy}/{MM}/{dd}";
+const HOURLY_PARTITION_PATTERN: &str = "hourly/{yyyy}/{MM}/{dd}/{HH}";
+/// Placeholders in the time partition pattern and the corresponding `chrono` formats
+const PARTITION_PLACEHOLDERS: &[(&str, &str)] =
+    &[("yyyy", "%Y"), ("MM", "%m"), ("dd", "%d"), ("HH", "%H")];
+/// Spark config making overwrites replace only the partitions being written
+pub(crate) const PARTITION_OVERWRITE_MODE: (&str, &str) =
+    ("spark.sql.sources.partitionOverwriteMode", "dynamic");
+
 mod job_date_format {
     pub fn serialize<S>(
         date: &chrono::DateTime<chrono::Utc>,
@@ -24,7 +38,256 @@ pub enum DateTimeResolution {
     Hourly,
 }
 
-#[derive(Clone, Debug, Serialize)]
+impl DateTimeResolution {
+    pub fn to_duration(self) -> Duration {
+        match self {
+            DateTimeResolution::Daily => Duration::days(1),
+            DateTimeResolution::Hourly => Duration::hours(1),
+        }
+    }
+
+    /**
+     * Start of the day or hour containing `time` in the timezone
+     */
+    fn floor(self, time: DateTime<Utc>, timezone: &FixedOffset) -> DateTime<Utc> {
+        let local = time.with_timezone(timezone).naive_local();
+        let hour = match self {
+            DateTimeResolution::Daily => 0,
+            DateTimeResolution::Hourly => local.hour(),
+        };
+        let naive = local.date().and_hms(hour, 0, 0);
+        timezone
+            .from_local_datetime(&naive)
+            .single()
+            .expect("Fixed offsets are never ambiguous")
+            .with_timezone(&Utc)
+    }
+}
+
+/**
+ * How the start and end of a materialization are aligned to the boundaries of the step
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowAlignment {
+    /// Windows end at `end` and every step before it, wherever the boundaries are
+    #[default]
+    None,
+    /// `start` and `end` must be on the boundaries of the step in the timezone
+    Strict,
+    /// `start` is rounded down and `end` is rounded up to the boundaries of the step
+    Expand,
+}
+
+impl FromStr for WindowAlignment {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(WindowAlignment::None),
+            "strict" => Ok(WindowAlignment::Strict),
+            "expand" => Ok(WindowAlignment::Expand),
+            _ => Err(Error::InvalidOption("alignment".to_string(), s.to_string())),
+        }
+    }
+}
+
+/**
+ * Timezone and boundary rules of the materialization windows.
+ * Windows are aligned to the days or hours in the timezone, the end time passed to the jobs is
+ * still in UTC, e.g. a daily window in `+08:00` ends at `16:00:00` UTC.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowOptions {
+    pub timezone: FixedOffset,
+    pub alignment: WindowAlignment,
+    /// Also materialize the window containing `end`, otherwise the windows end at or before `end`
+    pub end_inclusive: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            timezone: FixedOffset::east(0),
+            alignment: Default::default(),
+            end_inclusive: false,
+        }
+    }
+}
+
+impl WindowOptions {
+    /**
+     * Parse a fixed UTC offset, `UTC`, `Z`, `+08:00`, `-0530` or `+8`.
+     * Named timezones are not supported as the offset of a window must not change with DST
+     */
+    pub fn parse_timezone(tz: &str) -> Result<FixedOffset, Error> {
+        let invalid = || Error::InvalidOption("timezone".to_string(), tz.to_string());
+        let s = tz.trim();
+        if s.eq_ignore_ascii_case("utc") || s.eq_ignore_ascii_case("z") {
+            return Ok(FixedOffset::east(0));
+        }
+        let (sign, rest) = match s.chars().next() {
+            Some('+') => (1, &s[1..]),
+            Some('-') => (-1, &s[1..]),
+            _ => return Err(invalid()),
+        };
+        let (hours, minutes) = match rest.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if rest.len() == 4 => rest.split_at(2),
+            None => (rest, "0"),
+        };
+        let hours: i32 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+        if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+            return Err(invalid());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+    }
+
+    pub fn timezone(mut self, tz: &str) -> Result<Self, Error> {
+        self.timezone = Self::parse_timezone(tz)?;
+        Ok(self)
+    }
+
+    /**
+     * Read a date and time without timezone in the timezone of the windows
+     */
+    pub fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        self.timezone
+            .from_local_datetime(&local)
+            .single()
+            .expect("Fixed offsets are never ambiguous")
+            .with_timezone(&Utc)
+    }
+
+    /**
+     * The range covered by the windows after applying the alignment and the inclusive end
+     */
+    pub fn resolve(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        step: DateTimeResolution,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>), Error> {
+        if start >= end {
+            return Err(Error::InvalidTimeRange(start, end));
+        }
+        let step_duration = step.to_duration();
+        let floor = |t| step.floor(t, &self.timezone);
+        let (start, end) = match self.alignment {
+            WindowAlignment::None => (
+                start,
+                if self.end_inclusive {
+                    end + step_duration
+                } else {
+                    end
+                },
+            ),
+            WindowAlignment::Strict => {
+                for t in [start, end] {
+                    if floor(t) != t {
+                        return Err(Error::InvalidArgument(format!(
+                            "{} is not at the start of {} in timezone {}, use the `expand` alignment to round it",
+                            t.with_timezone(&self.timezone),
+                            match step {
+                                DateTimeResolution::Daily => "a day",
+                                DateTimeResolution::Hourly => "an hour",
+                            },
+                            self.timezone,
+                        )));
+                    }
+                }
+                (
+                    start,
+                    if self.end_inclusive {
+                        end + step_duration
+                    } else {
+                        end
+                    },
+                )
+            }
+            WindowAlignment::Expand => {
+                let end_floor = floor(end);
+                (
+                    floor(start),
+                    if self.end_inclusive || end_floor != end {
+                        end_floor + step_duration
+                    } else {
+                        end
+                    },
+                )
+            }
+        };
+        Ok((start, end))
+    }
+}
+
+/**
+ * Transformation of the key column values before they're written into the online store, e.g. to
+ * keep user ids out of Redis. Online lookups must transform the keys with `apply` the same way
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum KeyTransform {
+    #[default]
+    None,
+    /// Hex encoded SHA-256 of the key
+    Sha256,
+    /// Hex encoded HMAC-SHA256 of the key, the secret is a `${VAR}` placeholder resolved by the
+    /// `VarSource` when the job is submitted
+    Hmac { secret: String },
+}
+
+impl KeyTransform {
+    /**
+     * `kind` is one of `none`, `sha256` and `hmac`, the latter reads the secret from the variable
+     * `secret_var`, e.g. `REDIS_KEY_SECRET`
+     */
+    pub fn new(kind: &str, secret_var: Option<&str>) -> Result<Self, Error> {
+        match (kind.to_lowercase().as_str(), secret_var) {
+            ("none", _) => Ok(KeyTransform::None),
+            ("sha256", _) => Ok(KeyTransform::Sha256),
+            ("hmac", Some(var)) if !var.trim().is_empty() => Ok(KeyTransform::Hmac {
+                secret: format!("${{{}}}", var.trim()),
+            }),
+            ("hmac", _) => Err(Error::MissingOption("key_secret".to_string())),
+            _ => Err(Error::InvalidOption(
+                "key_transform".to_string(),
+                kind.to_string(),
+            )),
+        }
+    }
+
+    /**
+     * Transform a key column value, `secret` is the value of the secret variable and is only
+     * used by HMAC
+     */
+    pub fn apply(&self, key: &str, secret: Option<&str>) -> Result<String, Error> {
+        let digest = match self {
+            KeyTransform::None => return Ok(key.to_string()),
+            KeyTransform::Sha256 => Sha256::digest(key.as_bytes()).to_vec(),
+            KeyTransform::Hmac { .. } => {
+                let secret =
+                    secret.ok_or_else(|| Error::MissingOption("key_secret".to_string()))?;
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                    .map_err(|e| Error::InvalidConfig(format!("Failed to hash the key, {}", e)))?;
+                mac.update(key.as_bytes());
+                mac.finalize().into_bytes().to_vec()
+            }
+        };
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+impl GetSecretKeys for KeyTransform {
+    fn get_secret_keys(&self) -> Vec<String> {
+        match self {
+            KeyTransform::Hmac { secret } => parse_secret(secret).into_iter().collect(),
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 pub struct RedisSink {
     pub table_name: String,
     pub streaming: bool,
@@ -34,6 +297,8 @@ pub struct RedisSink {
         serialize_with = "ser_timeout"
     )]
     pub streaming_timeout: Option<Duration>,
+    #[serde(rename = "keyTransform", skip_serializing_if = "crate::is_default")]
+    pub key_transform: KeyTransform,
 }
 
 impl RedisSink {
@@ -42,6 +307,7 @@ impl RedisSink {
             table_name: table_name.to_string(),
             streaming: false,
             streaming_timeout: None,
+            key_transform: KeyTransform::None,
         }
     }
 
@@ -50,14 +316,75 @@ impl RedisSink {
             table_name: table_name.to_string(),
             streaming: true,
             streaming_timeout: Some(timeout),
+            key_transform: KeyTransform::None,
         }
     }
+
+    pub fn key_transform(mut self, key_transform: KeyTransform) -> Self {
+        self.key_transform = key_transform;
+        self
+    }
 }
 
-#[derive(Clone, Debug, Serialize)]
+/**
+ * File format of the HDFS sink output
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OutputFormat {
+    Parquet,
+    Avro,
+    Delta,
+}
+
+/**
+ * What happens to the existing data in the output directory
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SaveMode {
+    /// Replace everything under the output path
+    Overwrite,
+    /// Add rows to the existing output, re-running a window duplicates its rows
+    Append,
+    /// Replace only the directory of the window, needs a time partition pattern with a directory
+    /// per window, so re-running a window is idempotent
+    #[serde(rename = "OVERWRITE")]
+    OverwriteWindow,
+    /// Fail the job if the output directory of the window already exists
+    ErrorIfExists,
+}
+
+impl FromStr for SaveMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "overwrite" => Ok(SaveMode::Overwrite),
+            "append" => Ok(SaveMode::Append),
+            "overwrite-window" => Ok(SaveMode::OverwriteWindow),
+            "fail-if-exists" | "error-if-exists" => Ok(SaveMode::ErrorIfExists),
+            _ => Err(Error::InvalidOption("save_mode".to_string(), s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 pub struct GenericSink {
     #[serde(flatten)]
     pub location: DataLocation,
+    /// Only used by HDFS sinks, Spark decides the format if not set
+    #[serde(rename = "outputFormat", skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+    /// Directory layout under the output path, e.g. `daily/{yyyy}/{MM}/{dd}`, only used by HDFS sinks
+    #[serde(
+        rename = "timePartitionPattern",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub time_partition_pattern: Option<String>,
+    /// Only used by HDFS sinks, Spark overwrites the output if not set
+    #[serde(rename = "saveMode", skip_serializing_if = "Option::is_none")]
+    pub save_mode: Option<SaveMode>,
     #[serde(skip_serializing_if = "crate::is_default")]
     pub streaming: bool,
     #[serde(
@@ -72,6 +399,9 @@ impl GenericSink {
     pub fn new(location: DataLocation) -> Self {
         Self {
             location,
+            format: None,
+            time_partition_pattern: None,
+            save_mode: None,
             streaming: false,
             streaming_timeout: None,
         }
@@ -80,11 +410,30 @@ impl GenericSink {
     pub fn with_timeout(location: DataLocation, timeout: Duration) -> Self {
         Self {
             location,
+            format: None,
+            time_partition_pattern: None,
+            save_mode: None,
             streaming: true,
             streaming_timeout: Some(timeout),
         }
     }
 
+    /**
+     * Output directory of the window ends at `end_time`, the time partition pattern is applied
+     * if set
+     */
+    pub fn partition_path(&self, end_time: DateTime<Utc>) -> String {
+        let path = self.location.to_string();
+        match &self.time_partition_pattern {
+            Some(pattern) => format!(
+                "{}/{}",
+                path.trim_end_matches('/'),
+                format_partition(pattern, end_time)
+            ),
+            None => path,
+        }
+    }
+
     pub fn get_secret_keys(&self) -> Vec<String> {
         self.location.get_secret_keys()
     }
@@ -97,10 +446,22 @@ pub enum OutputSink {
     Hdfs(GenericSink),
 }
 
+impl OutputSink {
+    /**
+     * Human readable location of the sink, secrets are kept as placeholders
+     */
+    pub fn get_location(&self) -> String {
+        match &self {
+            OutputSink::Redis(s) => format!("redis:{}", s.table_name),
+            OutputSink::Hdfs(s) => s.location.to_string(),
+        }
+    }
+}
+
 impl GetSecretKeys for OutputSink {
     fn get_secret_keys(&self) -> Vec<String> {
         match &self {
-            OutputSink::Redis(_) => vec![],
+            OutputSink::Redis(s) => s.key_transform.get_secret_keys(),
             OutputSink::Hdfs(l) => l.get_secret_keys(),
         }
     }
@@ -136,6 +497,163 @@ impl From<&DataLocation> for OutputSink {
     }
 }
 
+/**
+ * Builder of the HDFS sink with the output layout options
+ */
+pub struct HdfsSinkBuilder {
+    path: String,
+    format: Option<OutputFormat>,
+    time_partition_pattern: Option<String>,
+    save_mode: Option<SaveMode>,
+}
+
+impl HdfsSinkBuilder {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            format: None,
+            time_partition_pattern: None,
+            save_mode: None,
+        }
+    }
+
+    pub fn format(&mut self, format: OutputFormat) -> &mut Self {
+        self.format = Some(format);
+        self
+    }
+
+    /**
+     * Write each materialization window into its own directory under the output path, the
+     * pattern can contain placeholders `{yyyy}`, `{MM}`, `{dd}` and `{HH}`, which are replaced
+     * with the window end time
+     */
+    pub fn time_partition_pattern(&mut self, pattern: &str) -> &mut Self {
+        self.time_partition_pattern = Some(pattern.to_string());
+        self
+    }
+
+    /**
+     * Use the default layout of the resolution, `daily/{yyyy}/{MM}/{dd}` or
+     * `hourly/{yyyy}/{MM}/{dd}/{HH}`
+     */
+    pub fn partition_by(&mut self, resolution: DateTimeResolution) -> &mut Self {
+        self.time_partition_pattern(match resolution {
+            DateTimeResolution::Daily => DAILY_PARTITION_PATTERN,
+            DateTimeResolution::Hourly => HOURLY_PARTITION_PATTERN,
+        })
+    }
+
+    pub fn save_mode(&mut self, save_mode: SaveMode) -> &mut Self {
+        self.save_mode = Some(save_mode);
+        self
+    }
+
+    pub fn overwrite(&mut self) -> &mut Self {
+        self.save_mode(SaveMode::Overwrite)
+    }
+
+    pub fn append(&mut self) -> &mut Self {
+        self.save_mode(SaveMode::Append)
+    }
+
+    /**
+     * Replace only the output of the window, so backfills can be re-run, the time partition
+     * pattern must give every window its own directory
+     */
+    pub fn overwrite_window(&mut self) -> &mut Self {
+        self.save_mode(SaveMode::OverwriteWindow)
+    }
+
+    pub fn fail_if_exists(&mut self) -> &mut Self {
+        self.save_mode(SaveMode::ErrorIfExists)
+    }
+
+    pub fn build(&self) -> Result<OutputSink, Error> {
+        if self.path.trim().is_empty() {
+            return Err(Error::MissingOption("path".to_string()));
+        }
+        if let Some(pattern) = &self.time_partition_pattern {
+            validate_partition_pattern(pattern)?;
+        }
+        if self.save_mode == Some(SaveMode::OverwriteWindow)
+            && self.time_partition_pattern.is_none()
+        {
+            return Err(Error::InvalidArgument(format!(
+                "Sink `{}` overwrites the window but has no time partition pattern",
+                self.path
+            )));
+        }
+        Ok(OutputSink::Hdfs(GenericSink {
+            format: self.format,
+            time_partition_pattern: self.time_partition_pattern.clone(),
+            save_mode: self.save_mode,
+            ..GenericSink::new(DataLocation::Hdfs {
+                path: self.path.clone(),
+            })
+        }))
+    }
+}
+
+/**
+ * Placeholders must be one of `{yyyy}`, `{MM}`, `{dd}` and `{HH}`, and the pattern must be a
+ * relative path
+ */
+pub(crate) fn validate_partition_pattern(pattern: &str) -> Result<(), Error> {
+    let invalid = || {
+        Err(Error::InvalidOption(
+            "time_partition_pattern".to_string(),
+            pattern.to_string(),
+        ))
+    };
+    if pattern.is_empty() || pattern.starts_with('/') || pattern.split('/').any(|p| p == "..") {
+        return invalid();
+    }
+    let mut rest = pattern;
+    loop {
+        match (rest.find('{'), rest.find('}')) {
+            (None, None) => return Ok(()),
+            (Some(start), Some(end))
+                if start < end
+                    && PARTITION_PLACEHOLDERS
+                        .iter()
+                        .any(|(p, _)| *p == &rest[start + 1..end]) =>
+            {
+                rest = &rest[end + 1..]
+            }
+            _ => return invalid(),
+        }
+    }
+}
+
+/**
+ * Every window of the resolution is written into its own directory only if the pattern has all
+ * placeholders down to the resolution, e.g. `{yyyy}/{MM}` puts all daily windows of a month into
+ * the same directory
+ */
+fn is_partitioned_by(pattern: &str, resolution: DateTimeResolution) -> bool {
+    let needed = match resolution {
+        DateTimeResolution::Daily => 3,
+        DateTimeResolution::Hourly => 4,
+    };
+    PARTITION_PLACEHOLDERS[..needed]
+        .iter()
+        .all(|(p, _)| pattern.contains(&format!("{{{}}}", p)))
+}
+
+/**
+ * Replace the placeholders in the pattern with the time
+ */
+pub(crate) fn format_partition(pattern: &str, time: DateTime<Utc>) -> String {
+    PARTITION_PLACEHOLDERS
+        .iter()
+        .fold(pattern.to_owned(), |p, (placeholder, format)| {
+            p.replace(
+                &format!("{{{}}}", placeholder),
+                &time.format(format).to_string(),
+            )
+        })
+}
+
 fn ser_timeout<S>(v: &Option<Duration>, ser: S) -> Result<S::Ok, S::Error>
 where
     S: serde::Serializer,
@@ -156,6 +674,19 @@ pub struct MaterializationOperation {
     pub resolution: DateTimeResolution,
     #[serde(rename = "output")]
     pub sinks: Vec<OutputSink>,
+    /// Key column -> output column, key columns not in the map keep their names
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub key_mapping: BTreeMap<String, String>,
+}
+
+/**
+ * Sinks sharing the same feature selection and key mapping
+ */
+#[derive(Clone, Debug, Serialize)]
+pub struct MaterializationSection {
+    pub operational: MaterializationOperation,
+    #[serde(rename = "features")]
+    pub feature_names: Vec<String>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -163,11 +694,87 @@ pub struct MaterializationSettings {
     pub operational: MaterializationOperation,
     #[serde(rename = "features")]
     pub feature_names: Vec<String>,
+    /// Sinks with their own feature selection or key mapping, grouped into operational sections
+    #[serde(rename = "sinkSections", skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<MaterializationSection>,
 }
 
+impl MaterializationSettings {
+    /**
+     * Sinks of all operational sections
+     */
+    pub fn all_sinks(&self) -> impl Iterator<Item = &OutputSink> {
+        self.operational.sinks.iter().chain(
+            self.sections
+                .iter()
+                .flat_map(|s| s.operational.sinks.iter()),
+        )
+    }
+
+    pub fn all_sinks_mut(&mut self) -> impl Iterator<Item = &mut OutputSink> {
+        self.operational.sinks.iter_mut().chain(
+            self.sections
+                .iter_mut()
+                .flat_map(|s| s.operational.sinks.iter_mut()),
+        )
+    }
+}
+
+/**
+ * A sink receiving only some of the materialized features, optionally with renamed key columns,
+ * e.g. only the embedding features go to Redis while all features go to HDFS
+ */
+#[derive(Clone, Debug)]
+pub struct SinkSelection {
+    pub sink: OutputSink,
+    /// All materialized features if not set
+    pub features: Option<Vec<String>>,
+    pub key_mapping: BTreeMap<String, String>,
+}
+
+impl SinkSelection {
+    pub fn new<T>(sink: T) -> Self
+    where
+        T: Into<OutputSink>,
+    {
+        Self {
+            sink: sink.into(),
+            features: None,
+            key_mapping: Default::default(),
+        }
+    }
+
+    pub fn features<T>(mut self, features: &[T]) -> Self
+    where
+        T: ToString,
+    {
+        self.features = Some(features.iter().map(|f| f.to_string()).collect());
+        self
+    }
+
+    /**
+     * Write the key column under another name
+     */
+    pub fn key_mapping(mut self, key_column: &str, output_column: &str) -> Self {
+        self.key_mapping
+            .insert(key_column.to_string(), output_column.to_string());
+        self
+    }
+}
+
+impl GetSecretKeys for SinkSelection {
+    fn get_secret_keys(&self) -> Vec<String> {
+        self.sink.get_secret_keys()
+    }
+}
+
+/// Selected features, key mapping and the sinks
+type SinkGroup = (Vec<String>, BTreeMap<String, String>, Vec<OutputSink>);
+
 pub struct MaterializationSettingsBuilder {
     pub(crate) name: String,
     pub(crate) sinks: Vec<OutputSink>,
+    pub(crate) selected_sinks: Vec<SinkSelection>,
     pub(crate) features: Vec<String>,
 }
 
@@ -176,6 +783,7 @@ impl MaterializationSettingsBuilder {
         Self {
             name: name.to_string(),
             sinks: Default::default(),
+            selected_sinks: Default::default(),
             features: features.to_owned(),
         }
     }
@@ -197,6 +805,91 @@ impl MaterializationSettingsBuilder {
         self
     }
 
+    /**
+     * Add a sink receiving its own selection of the features
+     */
+    pub fn selected_sink(&mut self, selection: SinkSelection) -> &mut Self {
+        self.selected_sinks.push(selection);
+        self
+    }
+
+    /**
+     * Same settings for a subset of the features, selected sinks only keep the selected features in
+     * the subset, and are dropped if none of them is
+     */
+    pub(crate) fn with_features(&self, features: Vec<String>) -> Self {
+        let selected_sinks = self
+            .selected_sinks
+            .iter()
+            .filter_map(|selection| match &selection.features {
+                Some(selected) => {
+                    let selected: Vec<String> = selected
+                        .iter()
+                        .filter(|f| features.contains(f))
+                        .cloned()
+                        .collect();
+                    (!selected.is_empty()).then(|| SinkSelection {
+                        features: Some(selected),
+                        ..selection.clone()
+                    })
+                }
+                None => Some(selection.clone()),
+            })
+            .collect();
+        Self {
+            name: self.name.clone(),
+            sinks: self.sinks.clone(),
+            selected_sinks,
+            features,
+        }
+    }
+
+    /**
+     * Selected sinks grouped by the feature selection and the key mapping, in the order of
+     * their first sinks
+     */
+    fn sink_groups(&self) -> Result<Vec<SinkGroup>, Error> {
+        let mut groups: Vec<SinkGroup> = vec![];
+        for selection in &self.selected_sinks {
+            let features = match &selection.features {
+                Some(features) => {
+                    if features.is_empty() {
+                        return Err(Error::InvalidArgument(format!(
+                            "Sink `{}` has no feature selected",
+                            selection.sink.get_location()
+                        )));
+                    }
+                    if let Some(f) = features.iter().find(|f| !self.features.contains(f)) {
+                        return Err(Error::InvalidArgument(format!(
+                            "Feature `{}` selected for sink `{}` is not materialized",
+                            f,
+                            selection.sink.get_location()
+                        )));
+                    }
+                    // Keep the order of the materialized features
+                    self.features
+                        .iter()
+                        .filter(|f| features.contains(f))
+                        .cloned()
+                        .collect()
+                }
+                None => self.features.clone(),
+            };
+            match groups
+                .iter_mut()
+                .find(|(f, m, _)| f == &features && m == &selection.key_mapping)
+            {
+                Some((_, _, sinks)) => sinks.push(selection.sink.clone()),
+                None => groups.push((
+                    features,
+                    selection.key_mapping.clone(),
+                    vec![selection.sink.clone()],
+                )),
+            }
+        }
+        Ok(groups)
+    }
+
     pub fn build(
         &self,
         start: DateTime<Utc>,
@@ -206,24 +899,84 @@ impl MaterializationSettingsBuilder {
         if start >= end {
             return Err(Error::InvalidTimeRange(start, end));
         }
-        let seconds = (end - start).num_seconds();
-        let step_sec = match step {
-            DateTimeResolution::Daily => 86400,
-            DateTimeResolution::Hourly => 3600,
+        let all_sinks = || {
+            self.sinks
+                .iter()
+                .chain(self.selected_sinks.iter().map(|s| &s.sink))
         };
+        for sink in all_sinks() {
+            if let OutputSink::Hdfs(s) = sink {
+                s.location.validate()?;
+            }
+        }
+        // Hourly partitions of a daily job would all be written at the same hour
+        if step == DateTimeResolution::Daily {
+            if let Some(pattern) = all_sinks()
+                .filter_map(|s| match s {
+                    OutputSink::Hdfs(s) => s.time_partition_pattern.as_ref(),
+                    _ => None,
+                })
+                .find(|p| p.contains("{HH}"))
+            {
+                return Err(Error::InvalidArgument(format!(
+                    "Time partition pattern `{}` needs hourly materialization",
+                    pattern
+                )));
+            }
+        }
+        let seconds = (end - start).num_seconds();
+        let step_sec = step.to_duration().num_seconds();
+        // Windows sharing a directory would overwrite or fail on the output of each other
+        let multi_window = seconds > step_sec;
+        for sink in all_sinks() {
+            if let OutputSink::Hdfs(s) = sink {
+                let shared = !s
+                    .time_partition_pattern
+                    .as_ref()
+                    .map_or(false, |p| is_partitioned_by(p, step));
+                match s.save_mode {
+                    Some(SaveMode::OverwriteWindow) if shared => {
+                        return Err(Error::InvalidArgument(format!(
+                            "Sink `{}` overwrites the window but its time partition pattern doesn't have a directory per {} window",
+                            s.location,
+                            format!("{:?}", step).to_lowercase()
+                        )));
+                    }
+                    Some(SaveMode::ErrorIfExists) if shared && multi_window => {
+                        return Err(Error::InvalidArgument(format!(
+                            "Sink `{}` fails if the output exists but all windows write into the same directory",
+                            s.location
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let groups = self.sink_groups()?;
         let ret: Vec<MaterializationSettings> = (0..seconds)
             .step_by(step_sec as usize)
             .map(|delta| {
                 let end_time = end - Duration::seconds(delta);
-                MaterializationSettings {
-                    operational: MaterializationOperation {
+                let operational = |sinks: &[OutputSink], key_mapping: &BTreeMap<String, String>| {
+                    MaterializationOperation {
                         name: self.name.clone(),
                         end_time,
                         end_time_format: END_TIME_FORMAT,
                         resolution: step,
-                        sinks: self.sinks.clone(),
-                    },
+                        sinks: sinks.to_vec(),
+                        key_mapping: key_mapping.clone(),
+                    }
+                };
+                MaterializationSettings {
+                    operational: operational(&self.sinks, &Default::default()),
                     feature_names: self.features.clone(),
+                    sections: groups
+                        .iter()
+                        .map(|(features, key_mapping, sinks)| MaterializationSection {
+                            operational: operational(sinks, key_mapping),
+                            feature_names: features.clone(),
+                        })
+                        .collect(),
                 }
             })
             .collect();
@@ -233,7 +986,7 @@ impl MaterializationSettingsBuilder {
 
 #[cfg(test)]
 mod tests {
-    use chrono::{Duration, Utc};
+    use chrono::{Duration, FixedOffset, TimeZone, Utc};
 
     use crate::*;
 
@@ -243,6 +996,7 @@ mod tests {
             table_name: "table1".to_string(),
             streaming: true,
             streaming_timeout: Some(Duration::seconds(10)),
+            key_transform: KeyTransform::None,
         };
 
         println!("{}", serde_json::to_string_pretty(&rs).unwrap());
@@ -251,6 +1005,7 @@ mod tests {
             table_name: "table1".to_string(),
             streaming: true,
             streaming_timeout: None,
+            key_transform: KeyTransform::None,
         });
 
         println!("{}", serde_json::to_string_pretty(&rs).unwrap());
@@ -267,6 +1022,33 @@ mod tests {
         println!("{}", serde_json::to_string_pretty(&cs).unwrap());
     }
 
+    #[test]
+    fn key_transform() {
+        assert_eq!(
+            KeyTransform::Sha256.apply("user1", None).unwrap(),
+            "0a041b9462caa4a31bac3567e0b6e6fd9100787db2ab433d96f6d178cabfce90"
+        );
+        let hmac = KeyTransform::new("HMAC", Some("REDIS_KEY_SECRET")).unwrap();
+        assert_eq!(
+            hmac.apply("user1", Some("secret")).unwrap(),
+            "590723609fa552658d3fc7aad1d720245b3e5bd904e6d59b0e4433b4b5c749b4"
+        );
+        assert!(hmac.apply("user1", None).is_err());
+        assert!(KeyTransform::new("hmac", None).is_err());
+        assert!(KeyTransform::new("md5", None).is_err());
+
+        let sink = OutputSink::Redis(RedisSink::new("table1").key_transform(hmac));
+        assert_eq!(sink.get_secret_keys(), vec!["REDIS_KEY_SECRET".to_string()]);
+        let json = serde_json::to_value(&sink).unwrap();
+        assert_eq!(json["params"]["keyTransform"]["type"], "HMAC");
+        assert_eq!(
+            json["params"]["keyTransform"]["secret"],
+            "${REDIS_KEY_SECRET}"
+        );
+        let json = serde_json::to_value(&RedisSink::new("table1")).unwrap();
+        assert!(json.get("keyTransform").is_none());
+    }
+
     #[test]
     fn test_build() {
         let now = Utc::now();
@@ -286,4 +1068,222 @@ mod tests {
         assert_eq!(b.len(), 3);
         assert_eq!(b[1].operational.name, b[0].operational.name);
     }
+
+    #[test]
+    fn test_sink_fan_out() {
+        let end = Utc.ymd(2022, 8, 1).and_hms(0, 0, 0);
+        let features = ["f_embedding".to_string(), "f_fare".to_string()];
+        let settings = MaterializationSettingsBuilder::new("some_name", &features)
+            .sink(HdfsSinkBuilder::new("/output").build().unwrap())
+            .selected_sink(
+                SinkSelection::new(RedisSink::new("embeddings"))
+                    .features(&["f_embedding"])
+                    .key_mapping("DOLocationID", "location_id"),
+            )
+            .build(end - Duration::days(1), end, DateTimeResolution::Daily)
+            .unwrap();
+        assert_eq!(settings.len(), 1);
+        assert_eq!(settings[0].all_sinks().count(), 2);
+        let conf = serde_json::to_value(&settings[0]).unwrap();
+        assert_eq!(conf["features"], serde_json::json!(features));
+        assert_eq!(conf["operational"]["output"][0]["name"], "HDFS");
+        assert!(conf["operational"].get("keyMapping").is_none());
+        let section = &conf["sinkSections"][0];
+        assert_eq!(section["features"], serde_json::json!(["f_embedding"]));
+        assert_eq!(section["operational"]["output"][0]["name"], "REDIS");
+        assert_eq!(
+            section["operational"]["keyMapping"],
+            serde_json::json!({ "DOLocationID": "location_id" })
+        );
+
+        // Only materialized features can be selected
+        assert!(MaterializationSettingsBuilder::new("some_name", &features)
+            .selected_sink(SinkSelection::new(RedisSink::new("t")).features(&["f_other"]))
+            .build(end - Duration::days(1), end, DateTimeResolution::Daily)
+            .is_err());
+    }
+
+    #[test]
+    fn test_hdfs_sink_layout() {
+        let sink = HdfsSinkBuilder::new("abfss://c@a.dfs.core.windows.net/features")
+            .format(OutputFormat::Delta)
+            .partition_by(DateTimeResolution::Daily)
+            .append()
+            .build()
+            .unwrap();
+        let end = Utc.ymd(2022, 8, 1).and_hms(0, 0, 0);
+        let settings = MaterializationSettingsBuilder::new("some_name", &["abc".to_string()])
+            .sink(&sink)
+            .build(end - Duration::days(2), end, DateTimeResolution::Daily)
+            .unwrap();
+        assert_eq!(settings.len(), 2);
+        let conf = serde_json::to_value(&settings[0]).unwrap();
+        assert_eq!(
+            conf["operational"]["output"][0],
+            serde_json::json!({
+                "name": "HDFS",
+                "params": {
+                    "path": "abfss://c@a.dfs.core.windows.net/features",
+                    "outputFormat": "DELTA",
+                    "timePartitionPattern": "daily/{yyyy}/{MM}/{dd}",
+                    "saveMode": "APPEND",
+                }
+            })
+        );
+        match &sink {
+            OutputSink::Hdfs(s) => assert_eq!(
+                s.partition_path(end),
+                "abfss://c@a.dfs.core.windows.net/features/daily/2022/08/01"
+            ),
+            _ => panic!("HDFS sink expected"),
+        }
+
+        // Options are not set unless specified
+        let conf = serde_json::to_value(HdfsSinkBuilder::new("/output").build().unwrap()).unwrap();
+        assert_eq!(conf["params"], serde_json::json!({ "path": "/output" }));
+
+        let hourly = HdfsSinkBuilder::new("/output")
+            .partition_by(DateTimeResolution::Hourly)
+            .build()
+            .unwrap();
+        assert!(
+            MaterializationSettingsBuilder::new("some_name", &["abc".to_string()])
+                .sink(&hourly)
+                .build(end - Duration::days(1), end, DateTimeResolution::Daily)
+                .is_err()
+        );
+
+        for pattern in [
+            "{yyyy}/{mm}",
+            "/{yyyy}",
+            "{yyyy}/../x",
+            "{yyyy",
+            "yyyy}/{MM}",
+            "",
+        ] {
+            assert!(
+                HdfsSinkBuilder::new("/output")
+                    .time_partition_pattern(pattern)
+                    .build()
+                    .is_err(),
+                "{}",
+                pattern
+            );
+        }
+        assert!(HdfsSinkBuilder::new("").build().is_err());
+    }
+
+    #[test]
+    fn test_save_modes() {
+        let end = Utc.ymd(2022, 8, 1).and_hms(0, 0, 0);
+        let build = |sink: &OutputSink, days| {
+            MaterializationSettingsBuilder::new("some_name", &["abc".to_string()])
+                .sink(sink)
+                .build(end - Duration::days(days), end, DateTimeResolution::Daily)
+        };
+        let sink = HdfsSinkBuilder::new("/output")
+            .partition_by(DateTimeResolution::Daily)
+            .overwrite_window()
+            .build()
+            .unwrap();
+        let settings = build(&sink, 2).unwrap();
+        let conf = serde_json::to_value(&settings[0]).unwrap();
+        assert_eq!(
+            conf["operational"]["output"][0]["params"]["saveMode"],
+            "OVERWRITE"
+        );
+        assert!(HdfsSinkBuilder::new("/output")
+            .overwrite_window()
+            .build()
+            .is_err());
+        // All daily windows of a month would share the directory
+        let monthly = HdfsSinkBuilder::new("/output")
+            .time_partition_pattern("{yyyy}/{MM}")
+            .overwrite_window()
+            .build()
+            .unwrap();
+        assert!(build(&monthly, 1).is_err());
+
+        let unpartitioned = HdfsSinkBuilder::new("/output")
+            .fail_if_exists()
+            .build()
+            .unwrap();
+        assert!(build(&unpartitioned, 1).is_ok());
+        assert!(build(&unpartitioned, 2).is_err());
+
+        assert_eq!(
+            "overwrite_window".parse::<SaveMode>().unwrap(),
+            SaveMode::OverwriteWindow
+        );
+        assert_eq!(
+            "fail-if-exists".parse::<SaveMode>().unwrap(),
+            SaveMode::ErrorIfExists
+        );
+        assert!("upsert".parse::<SaveMode>().is_err());
+    }
+
+    #[test]
+    fn test_window_options() {
+        let tz = WindowOptions::parse_timezone("+08:00").unwrap();
+        assert_eq!(tz, FixedOffset::east(8 * 3600));
+        assert_eq!(
+            WindowOptions::parse_timezone("-0530").unwrap(),
+            FixedOffset::west(5 * 3600 + 1800)
+        );
+        assert_eq!(WindowOptions::parse_timezone("+8").unwrap(), tz);
+        assert_eq!(
+            WindowOptions::parse_timezone("UTC").unwrap(),
+            FixedOffset::east(0)
+        );
+        assert!(WindowOptions::parse_timezone("Asia/Shanghai").is_err());
+        assert!(WindowOptions::parse_timezone("+25:00").is_err());
+
+        let options = WindowOptions {
+            timezone: tz,
+            alignment: WindowAlignment::Expand,
+            end_inclusive: false,
+        };
+        // 2022-08-01 10:00 in +08:00
+        let start = Utc.ymd(2022, 8, 1).and_hms(2, 0, 0);
+        let end = start + Duration::days(1);
+        let (s, e) = options
+            .resolve(start, end, DateTimeResolution::Daily)
+            .unwrap();
+        assert_eq!(s, Utc.ymd(2022, 7, 31).and_hms(16, 0, 0));
+        assert_eq!(e, Utc.ymd(2022, 8, 2).and_hms(16, 0, 0));
+
+        let options = WindowOptions {
+            alignment: WindowAlignment::Strict,
+            ..options
+        };
+        assert!(options
+            .resolve(start, end, DateTimeResolution::Daily)
+            .is_err());
+        let (s, e) = options
+            .resolve(start, end, DateTimeResolution::Hourly)
+            .unwrap();
+        assert_eq!((s, e), (start, end));
+
+        let options = WindowOptions {
+            end_inclusive: true,
+            ..options
+        };
+        let (_, e) = options
+            .resolve(start, end, DateTimeResolution::Hourly)
+            .unwrap();
+        assert_eq!(e, end + Duration::hours(1));
+        assert!(options
+            .resolve(end, start, DateTimeResolution::Hourly)
+            .is_err());
+
+        let local = chrono::NaiveDate::from_ymd(2022, 8, 1).and_hms(0, 0, 0);
+        assert_eq!(
+            options.to_utc(local),
+            Utc.ymd(2022, 7, 31).and_hms(16, 0, 0)
+        );
+        assert_eq!(
+            "Expand".parse::<WindowAlignment>().unwrap(),
+            WindowAlignment::Expand
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use chrono::Duration;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -29,6 +31,138 @@ impl Default for ValueType {
     }
 }
 
+/**
+ * How Spark converts a value of one type to another
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Coercion {
+    /// Same type, or either type is unspecified
+    Identity,
+    /// No value is changed, e.g. INT32 to INT64
+    Widening,
+    /// Values may be truncated, rounded or overflow, e.g. DOUBLE to INT32
+    Narrowing,
+    /// Values are parsed or formatted, unparsable ones become null, e.g. STRING to INT64
+    Cast,
+    /// Spark cannot convert the values, e.g. BYTES to DOUBLE
+    Incompatible,
+}
+
+impl Display for Coercion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/**
+ * Conversions between different value types, pairs not listed are incompatible
+ */
+const COERCION_RULES: &[(ValueType, ValueType, Coercion)] = &[
+    (ValueType::BOOL, ValueType::INT32, Coercion::Cast),
+    (ValueType::BOOL, ValueType::INT64, Coercion::Cast),
+    (ValueType::BOOL, ValueType::FLOAT, Coercion::Cast),
+    (ValueType::BOOL, ValueType::DOUBLE, Coercion::Cast),
+    (ValueType::BOOL, ValueType::STRING, Coercion::Cast),
+    (ValueType::INT32, ValueType::BOOL, Coercion::Cast),
+    (ValueType::INT32, ValueType::INT64, Coercion::Widening),
+    // FLOAT has 24 bits of mantissa
+    (ValueType::INT32, ValueType::FLOAT, Coercion::Narrowing),
+    (ValueType::INT32, ValueType::DOUBLE, Coercion::Widening),
+    (ValueType::INT32, ValueType::STRING, Coercion::Cast),
+    (ValueType::INT64, ValueType::BOOL, Coercion::Cast),
+    (ValueType::INT64, ValueType::INT32, Coercion::Narrowing),
+    (ValueType::INT64, ValueType::FLOAT, Coercion::Narrowing),
+    (ValueType::INT64, ValueType::DOUBLE, Coercion::Narrowing),
+    (ValueType::INT64, ValueType::STRING, Coercion::Cast),
+    (ValueType::FLOAT, ValueType::BOOL, Coercion::Cast),
+    (ValueType::FLOAT, ValueType::INT32, Coercion::Narrowing),
+    (ValueType::FLOAT, ValueType::INT64, Coercion::Narrowing),
+    (ValueType::FLOAT, ValueType::DOUBLE, Coercion::Widening),
+    (ValueType::FLOAT, ValueType::STRING, Coercion::Cast),
+    (ValueType::DOUBLE, ValueType::BOOL, Coercion::Cast),
+    (ValueType::DOUBLE, ValueType::INT32, Coercion::Narrowing),
+    (ValueType::DOUBLE, ValueType::INT64, Coercion::Narrowing),
+    (ValueType::DOUBLE, ValueType::FLOAT, Coercion::Narrowing),
+    (ValueType::DOUBLE, ValueType::STRING, Coercion::Cast),
+    (ValueType::STRING, ValueType::BOOL, Coercion::Cast),
+    (ValueType::STRING, ValueType::INT32, Coercion::Cast),
+    (ValueType::STRING, ValueType::INT64, Coercion::Cast),
+    (ValueType::STRING, ValueType::FLOAT, Coercion::Cast),
+    (ValueType::STRING, ValueType::DOUBLE, Coercion::Cast),
+    (ValueType::STRING, ValueType::BYTES, Coercion::Cast),
+    (ValueType::BYTES, ValueType::STRING, Coercion::Cast),
+];
+
+impl ValueType {
+    /**
+     * How values of this type are converted to `target`, see `COERCION_RULES`
+     */
+    pub fn coercion_to(self, target: ValueType) -> Coercion {
+        if self == target || self == ValueType::UNSPECIFIED || target == ValueType::UNSPECIFIED {
+            return Coercion::Identity;
+        }
+        COERCION_RULES
+            .iter()
+            .find(|(from, to, _)| *from == self && *to == target)
+            .map(|(_, _, c)| *c)
+            .unwrap_or(Coercion::Incompatible)
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            ValueType::INT32 | ValueType::INT64 | ValueType::FLOAT | ValueType::DOUBLE
+        )
+    }
+}
+
+/**
+ * A feature value converted to another type in a feature join, only lossy or failing conversions
+ * are reported
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoercionWarning {
+    pub feature: String,
+    /// Where the conversion happens, e.g. `aggregation AVG`, `input feature f1` or
+    /// `key user_id shared with f1`
+    pub context: String,
+    pub from: ValueType,
+    pub to: ValueType,
+    pub coercion: Coercion,
+}
+
+impl CoercionWarning {
+    /**
+     * `None` if the conversion doesn't lose or break any value
+     */
+    pub(crate) fn check(
+        feature: &str,
+        context: String,
+        from: ValueType,
+        to: ValueType,
+    ) -> Option<Self> {
+        let coercion = from.coercion_to(to);
+        (coercion >= Coercion::Narrowing).then(|| Self {
+            feature: feature.to_string(),
+            context,
+            from,
+            to,
+            coercion,
+        })
+    }
+}
+
+impl Display for CoercionWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} converts {:?} to {:?} ({})",
+            self.feature, self.context, self.from, self.to, self.coercion
+        )
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum VectorType {
     TENSOR,
@@ -192,6 +326,45 @@ impl TypedKey {
     }
 }
 
+/**
+ * Owner and contact info of a project or a feature, stored in the registry
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ownership {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_team: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub support_channel: Option<String>,
+}
+
+impl Ownership {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn owner_email(mut self, email: &str) -> Self {
+        self.owner_email = Some(email.to_owned());
+        self
+    }
+
+    pub fn owner_team(mut self, team: &str) -> Self {
+        self.owner_team = Some(team.to_owned());
+        self
+    }
+
+    pub fn support_channel(mut self, channel: &str) -> Self {
+        self.support_channel = Some(channel.to_owned());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.owner_email.is_none() && self.owner_team.is_none() && self.support_channel.is_none()
+    }
+}
+
 /**
  * The built-in aggregation functions for LookupFeature
  */
@@ -215,6 +388,23 @@ pub enum Aggregation {
     LATEST,
 }
 
+impl Aggregation {
+    /**
+     * Type Spark produces when aggregating values of `input`, `None` if the aggregation is not
+     * defined for the type, element-wise aggregations and UNION keep the type of the elements
+     */
+    pub fn result_type(self, input: ValueType) -> Option<ValueType> {
+        match self {
+            Aggregation::AVG if input.is_numeric() => Some(ValueType::DOUBLE),
+            Aggregation::SUM if input == ValueType::INT32 => Some(ValueType::INT64),
+            Aggregation::SUM if input == ValueType::FLOAT => Some(ValueType::DOUBLE),
+            Aggregation::SUM if input.is_numeric() => Some(input),
+            Aggregation::AVG | Aggregation::SUM if input != ValueType::UNSPECIFIED => None,
+            _ => Some(input),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExpressionDef {
@@ -291,6 +481,24 @@ impl Transformation {
             limit: None,
         })
     }
+
+    /**
+     * Window aggregation without its own window, the window is inherited from the default window
+     * of the anchor group
+     */
+    pub fn window_agg_inherited(
+        def_expr: &str,
+        agg_func: Aggregation,
+    ) -> Result<Self, crate::Error> {
+        Ok(Self::WindowAgg {
+            def_expr: def_expr.to_string(),
+            agg_func: Some(agg_func),
+            window: None,
+            group_by: None,
+            filter: None,
+            limit: None,
+        })
+    }
 }
 
 impl<T> From<T> for Transformation
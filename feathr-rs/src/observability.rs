@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use log::{debug, warn};
+
+use crate::{Error, VarSource};
+
+const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4317";
+const DEFAULT_SERVICE_NAME: &str = "feathr-client";
+
+static TRACING_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Settings of the `observability` section, spans of the client, the job client and the registry
+ * client are exported via OTLP if `enabled` is true and the crate is built with the `otlp` feature
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObservabilityConfig {
+    pub otlp_endpoint: String,
+    pub service_name: String,
+    /// Ratio of the traces to export, between 0 and 1
+    pub sample_ratio: f64,
+}
+
+impl ObservabilityConfig {
+    /**
+     * `None` if tracing is not enabled
+     */
+    pub(crate) async fn from_var_source(
+        var_source: Arc<dyn VarSource + Send + Sync>,
+    ) -> Result<Option<Self>, Error> {
+        let enabled = match var_source
+            .get_environment_variable(&["observability", "enabled"])
+            .await
+        {
+            Ok(s) => scalar(&s).parse::<bool>().map_err(|_| {
+                Error::InvalidOption("observability.enabled".to_string(), s.to_owned())
+            })?,
+            Err(_) => false,
+        };
+        if !enabled {
+            return Ok(None);
+        }
+        let sample_ratio = match var_source
+            .get_environment_variable(&["observability", "sample_ratio"])
+            .await
+        {
+            Ok(s) => scalar(&s)
+                .parse::<f64>()
+                .ok()
+                .filter(|r| (0.0..=1.0).contains(r))
+                .ok_or_else(|| {
+                    Error::InvalidOption("observability.sample_ratio".to_string(), s.to_owned())
+                })?,
+            Err(_) => 1.0,
+        };
+        Ok(Some(Self {
+            otlp_endpoint: var_source
+                .get_environment_variable(&["observability", "otlp_endpoint"])
+                .await
+                .unwrap_or_else(|_| DEFAULT_OTLP_ENDPOINT.to_string()),
+            service_name: var_source
+                .get_environment_variable(&["observability", "service_name"])
+                .await
+                .unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string()),
+            sample_ratio,
+        }))
+    }
+}
+
+/**
+ * Non-string YAML scalars are read as YAML documents, e.g. `---\ntrue`
+ */
+fn scalar(s: &str) -> &str {
+    s.trim().trim_start_matches("---").trim()
+}
+
+/**
+ * Install the OTLP exporter as the global tracing subscriber, only the first call takes effect.
+ * The exporter is not installed if the application has already set a global subscriber, spans
+ * go to that subscriber instead.
+ */
+#[cfg(feature = "otlp")]
+pub(crate) fn init_tracing(config: &ObservabilityConfig) -> Result<(), Error> {
+    use opentelemetry::{
+        sdk::{trace, Resource},
+        KeyValue,
+    };
+    use opentelemetry_otlp::WithExportConfig;
+    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+    if TRACING_INITIALIZED.swap(true, Ordering::SeqCst) {
+        debug!("Tracing is already initialized");
+        return Ok(());
+    }
+    debug!("Exporting spans to {}", config.otlp_endpoint);
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(config.otlp_endpoint.clone()),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(trace::Sampler::TraceIdRatioBased(config.sample_ratio))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    config.service_name.clone(),
+                )])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)
+        .map_err(|e| Error::InvalidConfig(format!("Failed to create OTLP exporter, {}", e)))?;
+    if let Err(e) = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+    {
+        warn!(
+            "Global tracing subscriber is already set, OTLP exporter is not installed, {}",
+            e
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "otlp"))]
+pub(crate) fn init_tracing(_config: &ObservabilityConfig) -> Result<(), Error> {
+    if TRACING_INITIALIZED.swap(true, Ordering::SeqCst) {
+        debug!("Tracing is already initialized");
+        return Ok(());
+    }
+    warn!("`observability` is enabled but the client is built without the `otlp` feature, spans are not exported");
+    Ok(())
+}
+
+/**
+ * Flush pending spans, call it before the application exits
+ */
+pub fn shutdown_tracing() {
+    #[cfg(feature = "otlp")]
+    if TRACING_INITIALIZED.load(Ordering::SeqCst) {
+        opentelemetry::global::shutdown_tracer_provider();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::try_new_var_source;
+
+    use super::*;
+
+    const BASE_CONFIG: &str = r#"
+spark_config:
+  spark_cluster: local
+online_store: {}
+feature_registry: {}
+"#;
+
+    #[tokio::test]
+    async fn test_observability_config() {
+        let var_source = try_new_var_source(BASE_CONFIG).unwrap();
+        assert!(ObservabilityConfig::from_var_source(var_source)
+            .await
+            .unwrap()
+            .is_none());
+
+        let var_source = try_new_var_source(format!(
+            "{}observability:\n  enabled: true\n  otlp_endpoint: http://collector:4317\n  sample_ratio: 0.5\n",
+            BASE_CONFIG
+        ))
+        .unwrap();
+        assert_eq!(
+            ObservabilityConfig::from_var_source(var_source)
+                .await
+                .unwrap(),
+            Some(ObservabilityConfig {
+                otlp_endpoint: "http://collector:4317".to_string(),
+                service_name: DEFAULT_SERVICE_NAME.to_string(),
+                sample_ratio: 0.5,
+            })
+        );
+
+        let var_source = try_new_var_source(format!(
+            "{}observability:\n  enabled: true\n  sample_ratio: 2\n",
+            BASE_CONFIG
+        ))
+        .unwrap();
+        assert!(matches!(
+            ObservabilityConfig::from_var_source(var_source).await,
+            Err(Error::InvalidOption(..))
+        ));
+    }
+}
@@ -1,19 +1,36 @@
+use std::str::FromStr;
+
 use serde::{ser::SerializeStruct, Serialize};
 
 use crate::{DataLocation, GetSecretKeys};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ObservationSettings {
     pub observation_path: DataLocation,
     pub settings: Option<ObservationInnerSettings>,
 }
 
 impl ObservationSettings {
+    /**
+     * `format` is `epoch`, `epoch_millis` or a Java `SimpleDateFormat` pattern, invalid patterns
+     * are rejected here instead of failing the Spark job
+     */
     pub fn new<T>(
         observation_path: T,
         timestamp_column: &str,
         format: &str,
     ) -> Result<Self, crate::Error>
+    where
+        T: AsRef<str>,
+    {
+        Self::with_timestamp_format(observation_path, timestamp_column, format.parse()?)
+    }
+
+    pub fn with_timestamp_format<T>(
+        observation_path: T,
+        timestamp_column: &str,
+        format: TimestampFormat,
+    ) -> Result<Self, crate::Error>
     where
         T: AsRef<str>,
     {
@@ -23,7 +40,7 @@ impl ObservationSettings {
                 join_time_settings: JoinTimeSettings {
                     timestamp_column: TimestampColumn {
                         def: timestamp_column.to_string(),
-                        format: format.into(),
+                        format,
                     },
                 },
             }),
@@ -77,54 +94,153 @@ impl<'a> Into<ObservationSettings> for &'a ObservationSettings {
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ObservationInnerSettings {
     pub join_time_settings: JoinTimeSettings,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct JoinTimeSettings {
     pub timestamp_column: TimestampColumn,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TimestampColumn {
     pub def: String,
-    pub format: TimestampColumnFormat,
+    pub format: TimestampFormat,
 }
 
-#[derive(Clone, Debug)]
-pub enum TimestampColumnFormat {
+/**
+ * Format of a timestamp column, seconds or milliseconds since the epoch, or a Java
+ * `SimpleDateFormat` pattern like `yyyy-MM-dd HH:mm:ss`
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimestampFormat {
     Epoch,
     EpochMillis,
+    /// A validated `SimpleDateFormat` pattern, use `parse` to construct it
     Custom(String),
 }
 
-impl Serialize for TimestampColumnFormat {
+#[deprecated(note = "Use `TimestampFormat` instead")]
+pub type TimestampColumnFormat = TimestampFormat;
+
+impl TimestampFormat {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TimestampFormat::Epoch => "epoch",
+            TimestampFormat::EpochMillis => "epoch_millis",
+            TimestampFormat::Custom(s) => s.as_str(),
+        }
+    }
+}
+
+impl Serialize for TimestampFormat {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(match &self {
-            TimestampColumnFormat::Epoch => "epoch",
-            TimestampColumnFormat::EpochMillis => "epoch_millis",
-            TimestampColumnFormat::Custom(s) => s.as_str(),
-        })
+        serializer.serialize_str(self.as_str())
     }
 }
 
-impl<T> From<T> for TimestampColumnFormat
-where
-    T: AsRef<str>,
-{
-    fn from(s: T) -> Self {
-        match s.as_ref().to_lowercase().as_str() {
-            "epoch" => TimestampColumnFormat::Epoch,
-            "epoch_millis" => TimestampColumnFormat::EpochMillis,
-            _ => TimestampColumnFormat::Custom(s.as_ref().to_string()),
+impl FromStr for TimestampFormat {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "epoch" => Ok(TimestampFormat::Epoch),
+            "epoch_millis" => Ok(TimestampFormat::EpochMillis),
+            _ => match check_pattern(s) {
+                Ok(_) => Ok(TimestampFormat::Custom(s.to_string())),
+                Err(reason) => Err(crate::Error::InvalidTimestampFormat(s.to_string(), reason)),
+            },
+        }
+    }
+}
+
+/**
+ * Letters with a meaning in `SimpleDateFormat` patterns, other ASCII letters must be quoted
+ */
+const PATTERN_LETTERS: &str = "GyYMLwWDdFEuaHkKhmsSzZX";
+
+/**
+ * Same checks as the `SimpleDateFormat` constructor, plus the pattern must contain at least one
+ * date or time field
+ */
+fn check_pattern(pattern: &str) -> Result<(), String> {
+    let mut chars = pattern.chars().peekable();
+    let mut has_field = false;
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // Quoted text, `''` is a single quote both inside and outside quoted text
+            if chars.next_if_eq(&'\'').is_some() {
+                continue;
+            }
+            loop {
+                match chars.next() {
+                    Some('\'') if chars.next_if_eq(&'\'').is_none() => break,
+                    Some(_) => {}
+                    None => return Err("unterminated quote".to_string()),
+                }
+            }
+        } else if c.is_ascii_alphabetic() {
+            if !PATTERN_LETTERS.contains(c) {
+                return Err(format!("illegal pattern character `{}`", c));
+            }
+            let mut count = 1;
+            while chars.next_if_eq(&c).is_some() {
+                count += 1;
+            }
+            if c == 'X' && count > 3 {
+                return Err("`X` can be repeated at most 3 times".to_string());
+            }
+            has_field = true;
+        }
+    }
+    if has_field {
+        Ok(())
+    } else {
+        Err("no date or time field".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_format() {
+        assert_eq!(
+            "EPOCH".parse::<TimestampFormat>().unwrap(),
+            TimestampFormat::Epoch
+        );
+        assert_eq!(
+            "epoch_millis".parse::<TimestampFormat>().unwrap(),
+            TimestampFormat::EpochMillis
+        );
+        for pattern in [
+            "yyyy-MM-dd HH:mm:ss",
+            "yyyy-MM-dd'T'HH:mm:ss.SSSXXX",
+            "EEE, d MMM yyyy HH:mm:ss Z",
+            "hh 'o''clock' a",
+        ] {
+            assert_eq!(
+                pattern.parse::<TimestampFormat>().unwrap(),
+                TimestampFormat::Custom(pattern.to_string())
+            );
+        }
+        for pattern in [
+            "",
+            "epoch_seconds",
+            "yyyy-MM-ddTHH:mm:ss",
+            "yyyy-MM-dd 'T",
+            "XXXX",
+        ] {
+            assert!(pattern.parse::<TimestampFormat>().is_err(), "{}", pattern);
         }
     }
 }
@@ -0,0 +1,400 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{DataLocation, JobHandle, JobStatus, OutputSink, VarSource};
+
+const PRODUCER: &str = "https://github.com/Azure/feathr/tree/main/feathr-rs";
+const SCHEMA_URL: &str = "https://openlineage.io/spec/1-0-5/OpenLineage.json#/definitions/RunEvent";
+const ERROR_FACET_SCHEMA_URL: &str =
+    "https://openlineage.io/spec/facets/1-0-0/ErrorMessageRunFacet.json";
+const FEATHR_FACET_SCHEMA_URL: &str =
+    "https://github.com/Azure/feathr/tree/main/feathr-rs/facets/FeathrJobFacet.json";
+const DEFAULT_NAMESPACE: &str = "feathr";
+
+/**
+ * Dataset as identified by OpenLineage, see https://openlineage.io/docs/spec/naming
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct LineageDataset {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl LineageDataset {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    /**
+     * Datasets of the data location, Kafka source has one dataset per topic and input context has none
+     */
+    pub fn from_location(location: &DataLocation) -> Vec<Self> {
+        match location {
+            DataLocation::Hdfs { path } => vec![Self::from_path(path)],
+            DataLocation::Jdbc {
+                url,
+                dbtable,
+                query,
+                ..
+            } => {
+                let namespace = url
+                    .trim_start_matches("jdbc:")
+                    .split(|c| c == ';' || c == '?')
+                    .next()
+                    .unwrap_or_default();
+                // Strip the database name, it's part of the dataset name for some databases
+                let (namespace, database) = match namespace.split_once("://") {
+                    Some((scheme, rest)) => match rest.split_once('/') {
+                        Some((authority, db)) => (format!("{}://{}", scheme, authority), db),
+                        None => (namespace.to_string(), ""),
+                    },
+                    None => (namespace.to_string(), ""),
+                };
+                let table = match (dbtable, query) {
+                    (Some(t), _) => t.to_owned(),
+                    (None, Some(_)) => "query".to_string(),
+                    (None, None) => return vec![],
+                };
+                let name = if database.is_empty() {
+                    table
+                } else {
+                    format!("{}.{}", database, table)
+                };
+                vec![Self { namespace, name }]
+            }
+            DataLocation::Kafka {
+                brokers, topics, ..
+            } => {
+                let namespace = format!(
+                    "kafka://{}",
+                    brokers.first().map(|s| s.as_str()).unwrap_or_default()
+                );
+                topics.iter().map(|t| Self::new(&namespace, t)).collect()
+            }
+            DataLocation::Generic {
+                format, options, ..
+            } => ["path", "dbtable", "table"]
+                .iter()
+                .find_map(|k| options.get(*k))
+                .map(|name| match name.split_once("://") {
+                    Some(_) => Self::from_path(name),
+                    None => Self::new(format, name),
+                })
+                .into_iter()
+                .collect(),
+            DataLocation::InputContext => vec![],
+        }
+    }
+
+    pub fn from_sink(sink: &OutputSink) -> Vec<Self> {
+        match sink {
+            OutputSink::Redis(s) => vec![Self::new("redis", &s.table_name)],
+            OutputSink::Hdfs(s) => Self::from_location(&s.location),
+        }
+    }
+
+    /**
+     * `scheme://authority/path` becomes namespace `scheme://authority` and name `/path`
+     */
+    fn from_path(path: &str) -> Self {
+        match path.split_once("://") {
+            Some((scheme, rest)) => {
+                let (authority, name) = match rest.find('/') {
+                    Some(idx) => rest.split_at(idx),
+                    None => (rest, "/"),
+                };
+                Self::new(&format!("{}://{}", scheme, authority), name)
+            }
+            None => Self::new("file", path),
+        }
+    }
+}
+
+/**
+ * What the job reads and writes, reported to OpenLineage
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JobLineage {
+    pub features: Vec<String>,
+    pub inputs: Vec<LineageDataset>,
+    pub outputs: Vec<LineageDataset>,
+}
+
+impl JobLineage {
+    pub(crate) fn new<I, O>(features: &[String], inputs: I, outputs: O) -> Self
+    where
+        I: IntoIterator<Item = LineageDataset>,
+        O: IntoIterator<Item = LineageDataset>,
+    {
+        let mut inputs: Vec<_> = inputs.into_iter().collect();
+        inputs.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+        inputs.dedup();
+        Self {
+            features: features.to_vec(),
+            inputs,
+            outputs: outputs.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub(crate) enum EventType {
+    Start,
+    Complete,
+    Fail,
+}
+
+/**
+ * Run of a submitted job, kept until the job is ended
+ */
+#[derive(Clone, Debug)]
+pub(crate) struct LineageRun {
+    pub(crate) run_id: Uuid,
+    pub(crate) job_name: String,
+    pub(crate) lineage: JobLineage,
+}
+
+impl LineageRun {
+    pub(crate) fn new(job_name: &str, lineage: JobLineage) -> Self {
+        Self {
+            run_id: Uuid::new_v4(),
+            job_name: job_name.to_string(),
+            lineage,
+        }
+    }
+
+    fn to_event(
+        &self,
+        namespace: &str,
+        event_type: EventType,
+        handle: Option<&JobHandle>,
+        error: Option<&str>,
+        event_time: DateTime<Utc>,
+    ) -> serde_json::Value {
+        let mut run_facets = serde_json::Map::new();
+        run_facets.insert(
+            "feathr".to_string(),
+            json!({
+                "_producer": PRODUCER,
+                "_schemaURL": FEATHR_FACET_SCHEMA_URL,
+                "features": self.lineage.features,
+                "backend": handle.map(|h| h.backend.to_string()),
+                "jobId": handle.map(|h| h.id),
+                "jobUrl": handle.and_then(|h| h.url.clone()),
+            }),
+        );
+        if let Some(message) = error {
+            run_facets.insert(
+                "errorMessage".to_string(),
+                json!({
+                    "_producer": PRODUCER,
+                    "_schemaURL": ERROR_FACET_SCHEMA_URL,
+                    "message": message,
+                    "programmingLanguage": "Spark",
+                }),
+            );
+        }
+        json!({
+            "eventType": event_type,
+            "eventTime": event_time,
+            "run": {
+                "runId": self.run_id,
+                "facets": run_facets,
+            },
+            "job": {
+                "namespace": namespace,
+                "name": self.job_name,
+            },
+            "inputs": self.lineage.inputs,
+            "outputs": self.lineage.outputs,
+            "producer": PRODUCER,
+            "schemaURL": SCHEMA_URL,
+        })
+    }
+}
+
+/**
+ * Posts run events to the OpenLineage HTTP endpoint, e.g. Marquez, configured in `open_lineage`
+ * section of the config. Failures are only logged, they never fail the job.
+ */
+#[derive(Clone, Debug)]
+pub(crate) struct OpenLineageClient {
+    url: String,
+    namespace: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenLineageClient {
+    /**
+     * Returns `None` if `open_lineage.url` is not configured
+     */
+    pub(crate) async fn from_var_source(
+        var_source: Arc<dyn VarSource + Send + Sync>,
+    ) -> Option<Self> {
+        let url = var_source
+            .get_environment_variable(&["open_lineage", "url"])
+            .await
+            .ok()
+            .filter(|s| !s.trim().is_empty())?;
+        Some(Self {
+            url: format!("{}/api/v1/lineage", url.trim().trim_end_matches('/')),
+            namespace: var_source
+                .get_environment_variable(&["open_lineage", "namespace"])
+                .await
+                .unwrap_or_else(|_| DEFAULT_NAMESPACE.to_string()),
+            api_key: var_source
+                .get_environment_variable(&["OPEN_LINEAGE_API_KEY"])
+                .await
+                .ok(),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    pub(crate) async fn started(&self, run: &LineageRun, handle: &JobHandle) {
+        self.emit(run.to_event(
+            &self.namespace,
+            EventType::Start,
+            Some(handle),
+            None,
+            handle.submitted_at,
+        ))
+        .await
+    }
+
+    pub(crate) async fn ended(&self, run: &LineageRun, handle: &JobHandle, status: JobStatus) {
+        let (event_type, error) = match status {
+            JobStatus::Success => (EventType::Complete, None),
+            _ => (
+                EventType::Fail,
+                Some(format!("{} ended with status {}", handle, status)),
+            ),
+        };
+        self.emit(run.to_event(
+            &self.namespace,
+            event_type,
+            Some(handle),
+            error.as_deref(),
+            Utc::now(),
+        ))
+        .await
+    }
+
+    pub(crate) async fn submission_failed(&self, run: &LineageRun, error: &crate::Error) {
+        self.emit(run.to_event(
+            &self.namespace,
+            EventType::Fail,
+            None,
+            Some(&error.to_string()),
+            Utc::now(),
+        ))
+        .await
+    }
+
+    async fn emit(&self, event: serde_json::Value) {
+        debug!("OpenLineage event: {}", event);
+        let mut req = self.client.post(&self.url).json(&event);
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        match req.send().await {
+            Ok(resp) if resp.status().is_success() => {}
+            Ok(resp) => warn!(
+                "Failed to send OpenLineage event to {}, status: {}",
+                self.url,
+                resp.status()
+            ),
+            Err(e) => warn!(
+                "Failed to send OpenLineage event to {}, error: {}",
+                self.url, e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{JdbcAuth, JobBackend, JobId};
+
+    #[test]
+    fn test_datasets() {
+        assert_eq!(
+            LineageDataset::from_location(&DataLocation::Hdfs {
+                path: "abfss://feathr@myaccount.dfs.core.windows.net/data/trips.csv".to_string()
+            }),
+            vec![LineageDataset::new(
+                "abfss://feathr@myaccount.dfs.core.windows.net",
+                "/data/trips.csv"
+            )]
+        );
+        assert_eq!(
+            LineageDataset::from_location(&DataLocation::Jdbc {
+                url: "jdbc:postgresql://localhost:5432/feathr?ssl=true".to_string(),
+                dbtable: Some("public.trips".to_string()),
+                query: None,
+                auth: JdbcAuth::Anonymous,
+            }),
+            vec![LineageDataset::new(
+                "postgresql://localhost:5432",
+                "feathr.public.trips"
+            )]
+        );
+        assert_eq!(
+            LineageDataset::from_location(&DataLocation::Generic {
+                _type: "generic".to_string(),
+                format: "cosmos.oltp".to_string(),
+                mode: None,
+                options: HashMap::new(),
+            }),
+            vec![]
+        );
+        assert!(LineageDataset::from_location(&DataLocation::InputContext).is_empty());
+    }
+
+    #[test]
+    fn test_event() {
+        let run = LineageRun::new(
+            "p1_feathr_feature_join_job",
+            JobLineage::new(
+                &["f1".to_string()],
+                vec![
+                    LineageDataset::new("wasbs://c@a.blob.core.windows.net", "/b"),
+                    LineageDataset::new("wasbs://c@a.blob.core.windows.net", "/a"),
+                    LineageDataset::new("wasbs://c@a.blob.core.windows.net", "/b"),
+                ],
+                vec![LineageDataset::new("redis", "table1")],
+            ),
+        );
+        let handle = JobHandle::new(
+            JobId(42),
+            JobBackend::AzureSynapse,
+            "p1_feathr_feature_join_job",
+        );
+        let event = run.to_event(
+            "feathr",
+            EventType::Fail,
+            Some(&handle),
+            Some("failed"),
+            Utc::now(),
+        );
+        assert_eq!(event["eventType"], "FAIL");
+        assert_eq!(event["inputs"].as_array().unwrap().len(), 2);
+        assert_eq!(event["inputs"][0]["name"], "/a");
+        assert_eq!(event["outputs"][0]["namespace"], "redis");
+        assert_eq!(event["run"]["facets"]["feathr"]["jobId"], 42);
+        assert_eq!(event["run"]["facets"]["feathr"]["features"][0], "f1");
+        assert_eq!(event["run"]["facets"]["errorMessage"]["message"], "failed");
+    }
+}
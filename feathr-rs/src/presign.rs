@@ -0,0 +1,328 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use log::debug;
+use reqwest::Url;
+use sha2::Sha256;
+
+use crate::{Error, VarSource};
+
+const SAS_VERSION: &str = "2020-12-06";
+
+/**
+ * Permissions granted by a generated download URL
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UrlPermissions {
+    pub read: bool,
+    pub write: bool,
+    pub delete: bool,
+    pub list: bool,
+}
+
+impl Default for UrlPermissions {
+    /**
+     * Read only, listing is needed as Spark outputs are directories
+     */
+    fn default() -> Self {
+        Self {
+            read: true,
+            write: false,
+            delete: false,
+            list: true,
+        }
+    }
+}
+
+impl UrlPermissions {
+    pub fn read_only() -> Self {
+        Default::default()
+    }
+
+    pub fn write(mut self, write: bool) -> Self {
+        self.write = write;
+        self
+    }
+
+    pub fn delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /**
+     * Permission string in the order required by Azure Storage
+     */
+    fn to_sas_string(self) -> String {
+        [
+            (self.read, 'r'),
+            (self.write, 'w'),
+            (self.delete, 'd'),
+            (self.list, 'l'),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, c)| c)
+        .collect()
+    }
+}
+
+/**
+ * Storage location of a job output
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum OutputLocation {
+    /// ADLS Gen2, `abfs[s]://container@account.dfs.core.windows.net/path`
+    Adls {
+        account: String,
+        container: String,
+        path: String,
+    },
+    /// Azure Blob, `wasb[s]://container@account.blob.core.windows.net/path`
+    Blob {
+        account: String,
+        container: String,
+        path: String,
+    },
+}
+
+impl OutputLocation {
+    fn parse(url: &str) -> Result<Self, Error> {
+        let u = Url::parse(url).map_err(|_| Error::InvalidUrl(url.to_string()))?;
+        let account = u
+            .host_str()
+            .and_then(|h| h.split('.').next())
+            .filter(|a| !a.is_empty())
+            .ok_or_else(|| Error::InvalidUrl(url.to_string()))?
+            .to_string();
+        let container = u.username().to_string();
+        if container.is_empty() {
+            return Err(Error::InvalidUrl(url.to_string()));
+        }
+        let path = u.path().trim_matches('/').to_string();
+        match u.scheme() {
+            "abfs" | "abfss" => Ok(Self::Adls {
+                account,
+                container,
+                path,
+            }),
+            "wasb" | "wasbs" => Ok(Self::Blob {
+                account,
+                container,
+                path,
+            }),
+            s => Err(Error::InvalidArgument(format!(
+                "Cannot generate download URL for `{}` output location",
+                s
+            ))),
+        }
+    }
+}
+
+/**
+ * Generate a service SAS URL for the job output location.
+ *
+ * ADLS Gen2 outputs get a directory scoped SAS, Blob outputs get a container scoped SAS as
+ * directory SAS is only supported on accounts with hierarchical namespace enabled.
+ */
+pub(crate) async fn get_download_url(
+    var_source: Arc<dyn VarSource + Send + Sync>,
+    output_url: &str,
+    ttl: Duration,
+    permissions: UrlPermissions,
+) -> Result<String, Error> {
+    let location = OutputLocation::parse(output_url)?;
+    let (account_var, key_var) = match &location {
+        OutputLocation::Adls { .. } => ("ADLS_ACCOUNT", "ADLS_KEY"),
+        OutputLocation::Blob { .. } => ("BLOB_ACCOUNT", "BLOB_KEY"),
+    };
+    let account = var_source.get_environment_variable(&[account_var]).await?;
+    let key = var_source.get_environment_variable(&[key_var]).await?;
+    sign_url(&location, &account, &key, Utc::now() + ttl, permissions)
+}
+
+/**
+ * Generate a URL that lists blobs under the location, used to check if the location exists.
+ *
+ * Blob endpoint is used for ADLS Gen2 as well, listing works for both files and directories there.
+ */
+pub(crate) async fn get_list_url(
+    var_source: Arc<dyn VarSource + Send + Sync>,
+    url: &str,
+    ttl: Duration,
+) -> Result<String, Error> {
+    let (account_var, key_var, account, container, path) = match OutputLocation::parse(url)? {
+        OutputLocation::Adls {
+            account,
+            container,
+            path,
+        } => ("ADLS_ACCOUNT", "ADLS_KEY", account, container, path),
+        OutputLocation::Blob {
+            account,
+            container,
+            path,
+        } => ("BLOB_ACCOUNT", "BLOB_KEY", account, container, path),
+    };
+    let configured_account = var_source.get_environment_variable(&[account_var]).await?;
+    let key = var_source.get_environment_variable(&[key_var]).await?;
+    let location = OutputLocation::Blob {
+        account,
+        container,
+        path: Default::default(),
+    };
+    let permissions = UrlPermissions {
+        read: false,
+        write: false,
+        delete: false,
+        list: true,
+    };
+    let signed = sign_url(
+        &location,
+        &configured_account,
+        &key,
+        Utc::now() + ttl,
+        permissions,
+    )?;
+    let mut url = Url::parse(&signed).map_err(|_| Error::InvalidUrl(signed.clone()))?;
+    url.query_pairs_mut()
+        .append_pair("restype", "container")
+        .append_pair("comp", "list")
+        .append_pair("delimiter", "/")
+        .append_pair("prefix", &path);
+    Ok(url.to_string())
+}
+
+fn sign_url(
+    location: &OutputLocation,
+    configured_account: &str,
+    key: &str,
+    expiry: DateTime<Utc>,
+    permissions: UrlPermissions,
+) -> Result<String, Error> {
+    let (account, container, path, host) = match location {
+        OutputLocation::Adls {
+            account,
+            container,
+            path,
+        } => (account, container, path, "dfs"),
+        OutputLocation::Blob {
+            account,
+            container,
+            path,
+        } => (account, container, path, "blob"),
+    };
+    if account != configured_account {
+        return Err(Error::InvalidConfig(format!(
+            "Output is stored in account `{}`, but credential is only configured for `{}`",
+            account, configured_account
+        )));
+    }
+    let (resource, canonicalized, depth) = match location {
+        OutputLocation::Adls { .. } if !path.is_empty() => (
+            "d",
+            format!("/blob/{}/{}/{}", account, container, path),
+            Some(path.split('/').count()),
+        ),
+        _ => ("c", format!("/blob/{}/{}", account, container), None),
+    };
+    let sp = permissions.to_sas_string();
+    let se = expiry.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let string_to_sign = [
+        sp.as_str(),
+        "",
+        se.as_str(),
+        canonicalized.as_str(),
+        "",
+        "",
+        "https",
+        SAS_VERSION,
+        resource,
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+        "",
+    ]
+    .join("\n");
+    let sig = hmac_sha256(key, &string_to_sign)?;
+
+    let mut url = Url::parse(&format!(
+        "https://{}.{}.core.windows.net/{}",
+        account, host, container
+    ))
+    .map_err(|_| Error::InvalidUrl(canonicalized.clone()))?;
+    if !path.is_empty() {
+        url.path_segments_mut()
+            .map_err(|_| Error::InvalidUrl(canonicalized.clone()))?
+            .extend(path.split('/'));
+    }
+    {
+        let mut q = url.query_pairs_mut();
+        q.append_pair("sv", SAS_VERSION)
+            .append_pair("sr", resource)
+            .append_pair("sp", &sp)
+            .append_pair("se", &se)
+            .append_pair("spr", "https");
+        if let Some(depth) = depth {
+            q.append_pair("sdd", &depth.to_string());
+        }
+        q.append_pair("sig", &sig);
+    }
+    debug!("Generated download URL for {}, expires at {}", canonicalized, se);
+    Ok(url.to_string())
+}
+
+fn hmac_sha256(key: &str, data: &str) -> Result<String, Error> {
+    let key = base64::decode(key)
+        .map_err(|e| Error::InvalidConfig(format!("Invalid storage account key, {}", e)))?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+        .map_err(|e| Error::InvalidConfig(format!("Failed to sign the URL, {}", e)))?;
+    mac.update(data.as_bytes());
+    Ok(base64::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_sign_url() {
+        let key = base64::encode("secret");
+        let expiry = Utc.ymd(2022, 9, 1).and_hms(0, 0, 0);
+
+        let loc = OutputLocation::parse(
+            "abfss://feathr@myaccount.dfs.core.windows.net/output/join_1",
+        )
+        .unwrap();
+        let url = sign_url(&loc, "myaccount", &key, expiry, Default::default()).unwrap();
+        assert!(url.starts_with("https://myaccount.dfs.core.windows.net/feathr/output/join_1?"));
+        assert!(url.contains("sr=d"));
+        assert!(url.contains("sdd=2"));
+        assert!(url.contains("sp=rl"));
+        assert!(url.contains("se=2022-09-01T00%3A00%3A00Z"));
+        assert!(url.contains("sig="));
+
+        let loc =
+            OutputLocation::parse("wasbs://feathr@myaccount.blob.core.windows.net/output").unwrap();
+        let url = sign_url(
+            &loc,
+            "myaccount",
+            &key,
+            expiry,
+            UrlPermissions::read_only().write(true),
+        )
+        .unwrap();
+        assert!(url.starts_with("https://myaccount.blob.core.windows.net/feathr/output?"));
+        assert!(url.contains("sr=c"));
+        assert!(url.contains("sp=rwl"));
+
+        // Credential of another account cannot sign the URL
+        assert!(sign_url(&loc, "other", &key, expiry, Default::default()).is_err());
+        // Not a cloud storage location
+        assert!(OutputLocation::parse("hdfs://namenode/output").is_err());
+    }
+}
@@ -1,11 +1,13 @@
 use std::str::FromStr;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::{BTreeMap, HashMap, HashSet}, sync::Arc};
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use log::{debug, warn};
 use serde::ser::SerializeStruct;
 use serde::Serialize;
 use tokio::sync::RwLock;
+use tracing::{field, instrument, Span};
 use uuid::Uuid;
 
 use crate::client::FeathrClientImpl;
@@ -13,12 +15,15 @@ use crate::feature::{
     AnchorFeature, AnchorFeatureImpl, DerivedFeature, DerivedFeatureImpl, InputFeature,
 };
 use crate::feature_builder::{AnchorFeatureBuilder, DerivedFeatureBuilder};
-use crate::registry_client::api_models::{EdgeType, EntityLineage, EntityType};
+use crate::feature_query::{align_query_keys, normalize_queries};
+use crate::query_cost::FeatureCostEstimate;
+use crate::open_lineage::{JobLineage, LineageDataset};
+use crate::registry_client::api_models::{EdgeType, EntityAttributes, EntityLineage, EntityType};
 use crate::{
-    DataLocation, DateTimeResolution, Error, Feature, FeatureQuery, FeatureRegistry, FeatureType,
-    GenericSourceBuilder, GetSecretKeys, HdfsSourceBuilder, JdbcSourceBuilder, KafkaSourceBuilder,
-    ObservationSettings, Source, SourceImpl, SubmitGenerationJobRequestBuilder,
-    SubmitJoiningJobRequestBuilder, TypedKey,
+    Aggregation, Coercion, CoercionWarning, ConnectionTestResult, DataLocation, DateTimeResolution, Error, FeathrApiClient, Feature, FeatureConfig, FeatureJoinConfig, FeatureQuery, FeatureRegistry, FeatureType,
+    EventHubSourceBuilder, GenericSourceBuilder, GetSecretKeys, HdfsSourceBuilder, JdbcSourceBuilder, JobConfig, KafkaSourceBuilder,
+    LintReport, ObservationSettings, Ownership, QueryCost, RenderedConfig, Source, SourceImpl, SubmitGenerationJobRequestBuilder,
+    SubmitJoiningJobRequestBuilder, TemplateSnippet, Transformation, TypedKey, ValueType,
 };
 
 /**
@@ -46,6 +51,8 @@ impl FeathrProject {
             anchor_map: Default::default(),
             sources: Default::default(),
             registry_tags: Default::default(),
+            default_tags: Default::default(),
+            ownership: Default::default(),
         }));
         inner
             .insert_source(SourceImpl::INPUT_CONTEXT())
@@ -70,6 +77,8 @@ impl FeathrProject {
             anchor_map: Default::default(),
             sources: Default::default(),
             registry_tags: Default::default(),
+            default_tags: Default::default(),
+            ownership: Default::default(),
         }));
         inner
             .insert_source(SourceImpl::INPUT_CONTEXT())
@@ -94,6 +103,240 @@ impl FeathrProject {
         self.inner.read().await.registry_tags.to_owned()
     }
 
+    /**
+     * Tags merged into the registry tags of every source, anchor group and feature built in this
+     * project, tags added to the entity take precedence
+     */
+    pub async fn get_default_tags(&self) -> HashMap<String, String> {
+        self.inner.read().await.default_tags.to_owned()
+    }
+
+    pub async fn get_ownership(&self) -> Ownership {
+        self.inner.read().await.ownership.to_owned()
+    }
+
+    /**
+     * Replace the owner and contact info of the project, the change is saved to the registry if the project is attached
+     */
+    pub async fn set_ownership(&self, ownership: Ownership) -> Result<(), Error> {
+        let mut w = self.inner.write().await;
+        if let Some(c) = w.owner.as_ref().and_then(|o| o.get_registry_client()) {
+            c.set_project_ownership(w.id, ownership.clone()).await?;
+        }
+        w.ownership = ownership;
+        Ok(())
+    }
+
+    /**
+     * Markdown documentation of the project saved in the registry
+     */
+    pub async fn get_docs(&self) -> Result<String, Error> {
+        let (c, id) = self.docs_target(None).await?;
+        c.get_entity_docs(id).await
+    }
+
+    /**
+     * Replace the markdown documentation of the project in the registry
+     */
+    pub async fn set_docs(&self, docs: &str) -> Result<(), Error> {
+        let (c, id) = self.docs_target(None).await?;
+        c.set_entity_docs(id, docs).await
+    }
+
+    /**
+     * Markdown documentation of an anchor or derived feature saved in the registry
+     */
+    pub async fn get_feature_docs(&self, name: &str) -> Result<String, Error> {
+        let (c, id) = self.docs_target(Some(name)).await?;
+        c.get_entity_docs(id).await
+    }
+
+    /**
+     * Replace the markdown documentation of an anchor or derived feature in the registry
+     */
+    pub async fn set_feature_docs(&self, name: &str, docs: &str) -> Result<(), Error> {
+        let (c, id) = self.docs_target(Some(name)).await?;
+        c.set_entity_docs(id, docs).await
+    }
+
+    /**
+     * Docs are only stored in the registry, the project must be attached
+     */
+    async fn docs_target(
+        &self,
+        feature: Option<&str>,
+    ) -> Result<(Arc<FeathrApiClient>, Uuid), Error> {
+        let r = self.inner.read().await;
+        let c = r
+            .owner
+            .as_ref()
+            .and_then(|o| o.get_registry_client())
+            .ok_or(Error::DetachedClient)?;
+        let id = match feature {
+            Some(name) => r
+                .get_feature_ids()
+                .get(name)
+                .copied()
+                .ok_or_else(|| Error::FeatureNotFound(name.to_string()))?,
+            None => r.id,
+        };
+        Ok((c, id))
+    }
+
+    /**
+     * Check if the source is reachable, the verification time is saved to the registry if the test
+     * passes and the project is attached.
+     * Sources only tested for reachability, e.g. JDBC and Kafka, are not marked as verified
+     */
+    pub async fn test_source_connection(&self, name: &str) -> Result<ConnectionTestResult, Error> {
+        let source = self.get_source(name).await?;
+        let r = self.inner.read().await;
+        let owner = r.owner.as_ref().ok_or_else(|| {
+            Error::InvalidArgument(format!("Project {} is not attached to a client", r.name))
+        })?;
+        let result = owner.test_connection(&source.get_location()).await;
+        if result.is_succeeded() {
+            if let Some(c) = owner.get_registry_client() {
+                c.set_source_verified(r.id, source.get_id()).await?;
+            }
+        }
+        Ok(result)
+    }
+
+    /**
+     * Find problems in the feature definitions before submitting any job, the project is not changed.
+     * Features not used by `feature_query` are reported unless `feature_query` is empty.
+     */
+    pub async fn lint<Q>(&self, feature_query: &[&Q]) -> LintReport
+    where
+        Q: Into<FeatureQuery> + Clone,
+    {
+        let feature_names: Vec<String> = feature_query
+            .iter()
+            .flat_map(|&q| q.clone().into().feature_list.into_iter())
+            .collect();
+        self.inner.read().await.lint(&feature_names)
+    }
+
+    /**
+     * Lossy or failing type conversions in the feature join of `feature_query`, e.g. an INT32
+     * feature aggregated with AVG, the join job builder has the same warnings
+     */
+    pub async fn coercion_warnings<Q>(
+        &self,
+        feature_query: &[&Q],
+    ) -> Result<Vec<CoercionWarning>, Error>
+    where
+        Q: Into<FeatureQuery> + Clone,
+    {
+        let r = self.inner.read().await;
+        let aligned = r.align_queries(feature_query.iter().map(|&q| q.clone().into()))?;
+        Ok(r.coercion_warnings(&aligned))
+    }
+
+    /**
+     * Estimated computation cost of the feature join of `feature_query`, from the `feathr_cost`
+     * tags of the features or inferred from their transformations, with suggested Spark resources
+     * if the join is predicted to be expensive
+     */
+    pub async fn estimate_query_cost<Q>(&self, feature_query: &[&Q]) -> Result<QueryCost, Error>
+    where
+        Q: Into<FeatureQuery> + Clone,
+    {
+        let r = self.inner.read().await;
+        let aligned = r.align_queries(feature_query.iter().map(|&q| q.clone().into()))?;
+        Ok(r.query_cost(&aligned))
+    }
+
+    /**
+     * Ready-to-run snippets getting the offline features of the project and materializing them,
+     * generated by the registry from the registered definitions, so the project must be attached.
+     * `lang` is one of the languages supported by the registry, e.g. `python`
+     */
+    pub async fn codegen(&self, lang: &str) -> Result<String, Error> {
+        let r = self.inner.read().await;
+        let c = r
+            .owner
+            .as_ref()
+            .and_then(|o| o.get_registry_client())
+            .ok_or(Error::DetachedClient)?;
+        let name = r.name.clone();
+        drop(r);
+        c.get_project_codegen(&name, lang).await
+    }
+
+    /**
+     * The feature config submitted with the jobs of this project
+     */
+    pub async fn render_feature_config(&self) -> Result<RenderedConfig, Error> {
+        RenderedConfig::try_from(&self.get_feature_config().await?)
+    }
+
+    /**
+     * The join config of a feature join job, the output path is left empty if `output` is not set
+     */
+    pub async fn render_join_config<O, Q>(
+        &self,
+        observation_settings: O,
+        feature_query: &[&Q],
+        output: Option<&str>,
+    ) -> Result<RenderedConfig, Error>
+    where
+        O: Into<ObservationSettings>,
+        Q: Into<FeatureQuery> + Clone,
+    {
+        let aligned = self
+            .inner
+            .read()
+            .await
+            .align_queries(feature_query.iter().map(|&q| q.clone().into()))?;
+        let config = FeatureJoinConfig {
+            observation_settings: observation_settings.into(),
+            feature_list: normalize_queries(aligned),
+            output_path: match output {
+                Some(output) => DataLocation::from_str(output)?.to_argument()?,
+                None => Default::default(),
+            },
+        };
+        RenderedConfig::try_from(&JobConfig::Join(config))
+    }
+
+    /**
+     * Check the key and transformation columns of anchor features against the schemas of their
+     * sources, read from the Parquet footers or CSV headers on the storage.
+     * Sources whose schema cannot be inferred are skipped.
+     */
+    pub async fn validate_columns(&self) -> Result<LintReport, Error> {
+        let (owner, sources) = {
+            let r = self.inner.read().await;
+            let owner = r.owner.clone().ok_or_else(|| {
+                Error::InvalidArgument(format!("Project {} is not attached to a client", r.name))
+            })?;
+            let sources: Vec<(String, DataLocation)> = r
+                .anchor_groups
+                .values()
+                .map(|g| (g.source.get_name(), g.source.get_location()))
+                .collect();
+            (owner, sources)
+        };
+        let mut schemas = HashMap::new();
+        for (name, location) in sources {
+            if schemas.contains_key(&name) {
+                continue;
+            }
+            match owner.infer_schema(&location).await {
+                Ok(schema) => {
+                    schemas.insert(name, schema);
+                }
+                Err(e) => debug!("Skipping source {}, error: {}", name, e),
+            }
+        }
+        let mut report = LintReport::default();
+        self.inner.read().await.lint_columns(&schemas, &mut report);
+        report.sort();
+        Ok(report)
+    }
+
     pub async fn get_sources(&self) -> Vec<String> {
         self.inner
             .read()
@@ -193,6 +436,30 @@ impl FeathrProject {
         })
     }
 
+    /**
+     * Delete the source, fails if any anchor group still reads from it.
+     * The source is also deleted from the registry if the project is attached
+     */
+    pub async fn delete_source(&self, name: &str) -> Result<(), Error> {
+        self.inner.write().await.remove_source(name).await
+    }
+
+    /**
+     * Delete the anchor group, fails if it still contains any feature.
+     * The group is also deleted from the registry if the project is attached
+     */
+    pub async fn delete_anchor_group(&self, name: &str) -> Result<(), Error> {
+        self.inner.write().await.remove_anchor_group(name).await
+    }
+
+    /**
+     * Delete the anchor or derived feature, fails if any derived feature uses it as an input.
+     * The feature is also deleted from the registry if the project is attached
+     */
+    pub async fn delete_feature(&self, name: &str) -> Result<(), Error> {
+        self.inner.write().await.remove_feature(name).await
+    }
+
     /**
      * Start creating an anchor group, with given name and data source
      */
@@ -228,6 +495,19 @@ impl FeathrProject {
         KafkaSourceBuilder::new(self.inner.clone(), name)
     }
 
+    /**
+     * Start creating a streaming source reading the event hub through the Kafka endpoint of the
+     * Event Hubs namespace, authenticated with AAD by default
+     */
+    pub fn eventhub_source(
+        &self,
+        name: &str,
+        namespace: &str,
+        event_hub: &str,
+    ) -> EventHubSourceBuilder {
+        EventHubSourceBuilder::new(self.inner.clone(), name, namespace, event_hub)
+    }
+
     pub fn generic_source(&self, name: &str, format: &str) -> GenericSourceBuilder {
         GenericSourceBuilder::new(self.inner.clone(), name, format)
     }
@@ -245,6 +525,7 @@ impl FeathrProject {
     /**
      * Creates the Spark job request for a feature-joining job
      */
+    #[instrument(skip_all, fields(project = field::Empty))]
     pub async fn feature_join_job<O, Q, L>(
         &self,
         observation_settings: O,
@@ -256,7 +537,21 @@ impl FeathrProject {
         Q: Into<FeatureQuery> + Clone,
         L: AsRef<str>,
     {
-        let fq: Vec<FeatureQuery> = feature_query.iter().map(|&q| q.clone().into()).collect();
+        Span::current().record("project", &self.inner.read().await.name.as_str());
+        let aligned = self
+            .inner
+            .read()
+            .await
+            .align_queries(feature_query.iter().map(|&q| q.clone().into()))?;
+        let warnings = self.inner.read().await.coercion_warnings(&aligned);
+        for w in &warnings {
+            warn!("Type coercion in the feature join, {}", w);
+        }
+        let cost = self.inner.read().await.query_cost(&aligned);
+        for w in cost.warnings() {
+            warn!("{}", w);
+        }
+        let fq = normalize_queries(aligned.clone());
         let feature_names: Vec<String> = fq
             .into_iter()
             .flat_map(|q| q.feature_list.into_iter())
@@ -267,19 +562,61 @@ impl FeathrProject {
         secret_keys.extend(output_location.get_secret_keys());
 
         let ob = observation_settings.into();
-        Ok(SubmitJoiningJobRequestBuilder::new_join(
+        let lineage = JobLineage::new(
+            &feature_names,
+            LineageDataset::from_location(&ob.observation_path)
+                .into_iter()
+                .chain(self.inner.read().await.get_input_datasets(&feature_names)),
+            LineageDataset::from_location(&output_location),
+        );
+        let mut builder = SubmitJoiningJobRequestBuilder::new_join(
             format!("{}_feathr_feature_join_job", self.inner.read().await.name),
             ob.observation_path.to_string(),
             self.get_feature_config().await?,
-            self.get_feature_join_config(ob, feature_query, output_location.to_argument()?)?,
+            self.get_feature_join_config(
+                ob,
+                &aligned.iter().collect::<Vec<_>>(),
+                output_location.to_argument()?,
+            )?,
             secret_keys,
             self.get_user_functions(&feature_names).await?,
-        ))
+        );
+        builder
+            .lineage(lineage)
+            .project_name(&self.inner.read().await.name)
+            .coercion_warnings(warnings)
+            .query_cost(cost);
+        let (snippets, variables) = self.get_template_snippets().await?;
+        for snippet in snippets {
+            builder.snippet(snippet);
+        }
+        for (name, value) in variables {
+            builder.template_variable(&name, &value);
+        }
+        let r = self.inner.read().await;
+        // Datasets can only be registered for projects stored in the registry
+        if r
+            .owner
+            .as_ref()
+            .and_then(|o| o.get_registry_client())
+            .is_some()
+        {
+            let feature_ids = r.get_feature_ids();
+            builder.registry_ids(
+                r.id,
+                feature_names
+                    .iter()
+                    .filter_map(|name| feature_ids.get(name).copied())
+                    .collect(),
+            );
+        }
+        Ok(builder)
     }
 
     /**
      * Creates the Spark job request for a feature-generation job
      */
+    #[instrument(skip_all, fields(project = field::Empty))]
     pub async fn feature_gen_job<T>(
         &self,
         feature_names: &[T],
@@ -290,8 +627,9 @@ impl FeathrProject {
     where
         T: ToString,
     {
+        Span::current().record("project", &self.inner.read().await.name.as_str());
         let feature_names: Vec<String> = feature_names.into_iter().map(|f| f.to_string()).collect();
-        Ok(SubmitGenerationJobRequestBuilder::new_gen(
+        let mut builder = SubmitGenerationJobRequestBuilder::new_gen(
             format!(
                 "{}_feathr_feature_materialization_job",
                 self.inner.read().await.name
@@ -304,7 +642,45 @@ impl FeathrProject {
             end,
             step,
             self.get_user_functions(&feature_names).await?,
-        ))
+        );
+        builder.project_name(&self.inner.read().await.name);
+        let (snippets, variables) = self.get_template_snippets().await?;
+        for snippet in snippets {
+            builder.snippet(snippet);
+        }
+        for (name, value) in variables {
+            builder.template_variable(&name, &value);
+        }
+        let owner = self.inner.read().await.owner.clone();
+        if let Some(o) = owner {
+            builder.max_feature_config_size(o.get_max_feature_config_size().await?);
+        }
+        let r = self.inner.read().await;
+        // Materialization records can only be registered for projects stored in the registry
+        if r
+            .owner
+            .as_ref()
+            .and_then(|o| o.get_registry_client())
+            .is_some()
+        {
+            builder.registry_ids(r.id, r.get_feature_ids());
+            builder.registry_tags(r.inherit_tags(&r.registry_tags));
+        }
+        builder.lineage_inputs(r.get_input_datasets(&feature_names));
+        Ok(builder)
+    }
+
+    /**
+     * Template snippets and variables from the config of the client this project is attached to
+     */
+    async fn get_template_snippets(
+        &self,
+    ) -> Result<(Vec<TemplateSnippet>, HashMap<String, String>), Error> {
+        let owner = self.inner.read().await.owner.clone();
+        match owner {
+            Some(o) => o.get_template_snippets().await,
+            None => Ok(Default::default()),
+        }
     }
 
     pub(crate) async fn get_user_functions(
@@ -318,10 +694,9 @@ impl FeathrProject {
         Ok(self.inner.read().await.get_secret_keys())
     }
 
-    pub(crate) async fn get_feature_config(&self) -> Result<String, Error> {
+    pub(crate) async fn get_feature_config(&self) -> Result<FeatureConfig, Error> {
         let r = self.inner.read().await;
-        let s = serde_json::to_string_pretty(&*r).unwrap();
-        Ok(s)
+        Ok(serde_json::from_value(serde_json::to_value(&*r)?)?)
     }
 
     pub(crate) fn get_feature_join_config<O, Q, T>(
@@ -329,31 +704,18 @@ impl FeathrProject {
         observation_settings: O,
         feature_query: &[&Q],
         output: T,
-    ) -> Result<String, Error>
+    ) -> Result<FeatureJoinConfig, Error>
     where
         O: Into<ObservationSettings>,
         Q: Into<FeatureQuery> + Clone,
         T: ToString,
     {
         // TODO: Validate feature names
-
-        #[derive(Clone, Debug, Serialize)]
-        #[serde(rename_all = "camelCase")]
-        struct FeatureJoinConfig {
-            #[serde(flatten)]
-            observation_settings: ObservationSettings,
-            feature_list: Vec<FeatureQuery>,
-            output_path: String,
-        }
-        let cfg = FeatureJoinConfig {
+        Ok(FeatureJoinConfig {
             observation_settings: observation_settings.into(),
-            feature_list: feature_query
-                .into_iter()
-                .map(|&q| q.to_owned().into())
-                .collect(),
+            feature_list: normalize_queries(feature_query.iter().map(|&q| q.to_owned().into())),
             output_path: output.to_string().parse::<DataLocation>()?.to_argument()?,
-        };
-        Ok(serde_json::to_string_pretty(&cfg)?)
+        })
     }
 }
 
@@ -369,6 +731,8 @@ pub(crate) struct FeathrProjectImpl {
     pub(crate) anchor_map: HashMap<String, Vec<String>>,
     pub(crate) sources: HashMap<String, Arc<SourceImpl>>,
     pub(crate) registry_tags: HashMap<String, String>,
+    pub(crate) default_tags: HashMap<String, String>,
+    pub(crate) ownership: Ownership,
 }
 
 impl Serialize for FeathrProjectImpl {
@@ -403,10 +767,9 @@ impl Serialize for FeathrProjectImpl {
                 let anchors: HashMap<_, _> = self.anchor_map[name]
                     .iter()
                     .map(|f_name| {
-                        (
-                            f_name.to_owned(),
-                            self.anchor_features[f_name].as_ref().to_owned(),
-                        )
+                        let mut f = self.anchor_features[f_name].as_ref().to_owned();
+                        g.inherit_window(&mut f.transform);
+                        (f_name.to_owned(), f)
                     })
                     .collect();
 
@@ -437,6 +800,15 @@ impl Serialize for FeathrProjectImpl {
 }
 
 impl FeathrProjectImpl {
+    /**
+     * Registry tags of a new entity, the default tags of the project overridden by `tags`
+     */
+    pub(crate) fn inherit_tags(&self, tags: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut merged = self.default_tags.clone();
+        merged.extend(tags.iter().map(|(k, v)| (k.to_owned(), v.to_owned())));
+        merged
+    }
+
     fn get_anchor_group_key_alias(&self, group: &str) -> Vec<String> {
         self.anchor_map
             .get(group)
@@ -452,6 +824,163 @@ impl FeathrProjectImpl {
             .collect()
     }
 
+    /**
+     * Key columns of the feature in the definition order
+     */
+    fn get_feature_key_columns(&self, name: &str) -> Option<Vec<String>> {
+        self.anchor_features
+            .get(name)
+            .map(|f| f.get_key())
+            .or_else(|| self.derivations.get(name).map(|f| f.get_key()))
+            .map(|keys| keys.into_iter().map(|k| k.key_column).collect())
+    }
+
+    /**
+     * Reorder query keys to the key order of each queried feature, see `align_query_keys`
+     */
+    pub(crate) fn align_queries<I>(&self, queries: I) -> Result<Vec<FeatureQuery>, Error>
+    where
+        I: IntoIterator<Item = FeatureQuery>,
+    {
+        align_query_keys(queries, |name| self.get_feature_key_columns(name))
+    }
+
+    fn get_feature_value_type(&self, name: &str) -> Option<ValueType> {
+        self.anchor_features
+            .get(name)
+            .map(|f| f.base.feature_type.val_type)
+            .or_else(|| {
+                self.derivations
+                    .get(name)
+                    .map(|f| f.base.feature_type.val_type)
+            })
+    }
+
+    /**
+     * Lossy or failing type conversions when joining the queries aligned by `align_queries`.
+     * Window aggregations are checked against the declared value types, derived features against
+     * their inputs, and features joined on the same observation column against each other
+     */
+    pub(crate) fn coercion_warnings(&self, queries: &[FeatureQuery]) -> Vec<CoercionWarning> {
+        let mut warnings = vec![];
+        // Inputs of derived features are joined as well
+        let mut pending: Vec<String> = queries
+            .iter()
+            .flat_map(|q| q.feature_list.iter().cloned())
+            .collect();
+        let mut visited: HashSet<String> = HashSet::new();
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            if let Some(f) = self.anchor_features.get(&name) {
+                if let Transformation::WindowAgg {
+                    agg_func: Some(agg),
+                    ..
+                } = &f.transform
+                {
+                    let val_type = f.base.feature_type.val_type;
+                    let context = format!("aggregation {:?}", agg);
+                    match agg.result_type(val_type) {
+                        Some(t) => {
+                            warnings.extend(CoercionWarning::check(&name, context, t, val_type))
+                        }
+                        None => warnings.push(CoercionWarning {
+                            feature: name.clone(),
+                            context,
+                            from: val_type,
+                            to: val_type,
+                            coercion: Coercion::Incompatible,
+                        }),
+                    }
+                }
+            } else if let Some(f) = self.derivations.get(&name) {
+                for input in f.inputs.values() {
+                    if let Some(t) = self.get_feature_value_type(&input.feature) {
+                        warnings.extend(CoercionWarning::check(
+                            &name,
+                            format!("input feature {}", input.feature),
+                            t,
+                            f.base.feature_type.val_type,
+                        ));
+                    }
+                    pending.push(input.feature.clone());
+                }
+            }
+        }
+
+        // Observation column -> features joined on it and their key types
+        let dummy = TypedKey::DUMMY_KEY().key_column;
+        let mut key_types: BTreeMap<&str, Vec<(&str, ValueType)>> = BTreeMap::new();
+        for q in queries {
+            for name in &q.feature_list {
+                let keys = match self.anchor_features.get(name) {
+                    Some(f) => &f.base.key,
+                    None => match self.derivations.get(name) {
+                        Some(f) => &f.base.key,
+                        None => continue,
+                    },
+                };
+                for (column, key) in q.key.iter().zip(keys.iter()) {
+                    if key.key_column != dummy {
+                        key_types
+                            .entry(column)
+                            .or_default()
+                            .push((name, key.key_column_type));
+                    }
+                }
+            }
+        }
+        for (column, mut features) in key_types {
+            features.sort_by_key(|(name, _)| *name);
+            features.dedup_by_key(|(name, _)| *name);
+            let (first, first_type) = features[0];
+            for (name, t) in &features[1..] {
+                warnings.extend(CoercionWarning::check(
+                    name,
+                    format!("key {} shared with {}", column, first),
+                    first_type,
+                    *t,
+                ));
+            }
+        }
+        warnings.sort_by(|a, b| (&a.feature, &a.context).cmp(&(&b.feature, &b.context)));
+        warnings
+    }
+
+    /**
+     * Cost of the features joined by the queries aligned by `align_queries`, inputs of derived
+     * features are computed as well, features not found are skipped
+     */
+    pub(crate) fn query_cost(&self, queries: &[FeatureQuery]) -> QueryCost {
+        let mut estimates = vec![];
+        let mut pending: Vec<String> = queries
+            .iter()
+            .flat_map(|q| q.feature_list.iter().cloned())
+            .collect();
+        let mut visited: HashSet<String> = HashSet::new();
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            if let Some(f) = self.anchor_features.get(&name) {
+                estimates.push(FeatureCostEstimate::new(
+                    &name,
+                    &f.base.registry_tags,
+                    &f.transform,
+                ));
+            } else if let Some(f) = self.derivations.get(&name) {
+                estimates.push(FeatureCostEstimate::new(
+                    &name,
+                    &f.base.registry_tags,
+                    &f.transform.clone().into(),
+                ));
+                pending.extend(f.inputs.values().map(|i| i.feature.clone()));
+            }
+        }
+        QueryCost::new(estimates)
+    }
+
     fn get_anchor_feature(&self, group: &str, name: &str) -> Result<Arc<AnchorFeatureImpl>, Error> {
         self.anchor_map
             .get(group)
@@ -507,6 +1036,7 @@ impl FeathrProjectImpl {
             .anchor_groups
             .get_mut(group)
             .ok_or_else(|| Error::AnchorGroupNotFound(group.to_string()))?;
+        g.validate_window(&f)?;
 
         if let Some(c) = self
             .owner
@@ -567,10 +1097,91 @@ impl FeathrProjectImpl {
         Ok(ret)
     }
 
+    /**
+     * Same check as the registry does before deleting an entity, nothing depends on it
+     */
+    async fn remove_source(&mut self, name: &str) -> Result<(), Error> {
+        let source = self
+            .sources
+            .get(name)
+            .ok_or_else(|| Error::SourceGroupNotFound(name.to_string()))?
+            .clone();
+        if let Some(g) = self.anchor_groups.values().find(|g| {
+            std::iter::once(&g.source)
+                .chain(g.joined_sources.iter())
+                .any(|s| s.get_name() == name)
+        }) {
+            return Err(Error::EntityInUse(
+                format!("Source {}", name),
+                format!("anchor group {}", g.name),
+            ));
+        }
+        self.delete_from_registry(source.id).await?;
+        self.sources.remove(name);
+        Ok(())
+    }
+
+    async fn remove_anchor_group(&mut self, name: &str) -> Result<(), Error> {
+        let group = self
+            .anchor_groups
+            .get(name)
+            .ok_or_else(|| Error::AnchorGroupNotFound(name.to_string()))?
+            .clone();
+        if let Some(f) = self.anchor_map.get(name).and_then(|f| f.first()) {
+            return Err(Error::EntityInUse(
+                format!("Anchor group {}", name),
+                format!("feature {}", f),
+            ));
+        }
+        self.delete_from_registry(group.id).await?;
+        self.anchor_groups.remove(name);
+        self.anchor_map.remove(name);
+        Ok(())
+    }
+
+    async fn remove_feature(&mut self, name: &str) -> Result<(), Error> {
+        let id = self
+            .get_feature_ids()
+            .get(name)
+            .copied()
+            .ok_or_else(|| Error::FeatureNotFound(name.to_string()))?;
+        if let Some(d) = self
+            .derivations
+            .values()
+            .find(|d| d.inputs.values().any(|i| i.feature == name))
+        {
+            return Err(Error::EntityInUse(
+                format!("Feature {}", name),
+                format!("derived feature {}", d.base.name),
+            ));
+        }
+        self.delete_from_registry(id).await?;
+        if self.anchor_features.remove(name).is_some() {
+            for features in self.anchor_map.values_mut() {
+                features.retain(|f| f != name);
+            }
+        } else {
+            self.derivations.remove(name);
+        }
+        Ok(())
+    }
+
+    async fn delete_from_registry(&self, id: Uuid) -> Result<(), Error> {
+        if let Some(c) = self
+            .owner
+            .clone()
+            .map(|o| o.get_registry_client())
+            .flatten()
+        {
+            c.delete_entity(id).await?;
+        }
+        Ok(())
+    }
+
     fn get_user_functions(&self, feature_names: &[String]) -> HashMap<String, String> {
         let mut ret = HashMap::new();
         for (_, g) in &self.anchor_groups {
-            if let Some(pp) = g.source.get_preprocessing() {
+            if let Some(pp) = g.get_preprocessing() {
                 let features = self.anchor_map[&g.name]
                     .iter()
                     .filter_map(|name| {
@@ -588,6 +1199,47 @@ impl FeathrProjectImpl {
         ret
     }
 
+    /**
+     * Datasets the features are computed from, derived features are resolved to their input features
+     */
+    fn get_input_datasets(&self, feature_names: &[String]) -> Vec<LineageDataset> {
+        let mut pending: Vec<String> = feature_names.to_vec();
+        let mut visited: HashSet<String> = Default::default();
+        let mut groups: HashSet<&str> = Default::default();
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            if let Some(d) = self.derivations.get(&name) {
+                pending.extend(d.inputs.values().map(|i| i.feature.to_owned()));
+            } else if let Some((group, _)) = self
+                .anchor_map
+                .iter()
+                .find(|(_, features)| features.contains(&name))
+            {
+                groups.insert(group.as_str());
+            }
+        }
+        groups
+            .into_iter()
+            .filter_map(|g| self.anchor_groups.get(g))
+            .flat_map(|g| std::iter::once(&g.source).chain(g.joined_sources.iter()))
+            .flat_map(|s| LineageDataset::from_location(&s.get_location()))
+            .collect()
+    }
+
+    fn get_feature_ids(&self) -> HashMap<String, Uuid> {
+        self.anchor_features
+            .iter()
+            .map(|(name, f)| (name.to_owned(), f.base.id))
+            .chain(
+                self.derivations
+                    .iter()
+                    .map(|(name, f)| (name.to_owned(), f.base.id)),
+            )
+            .collect()
+    }
+
     fn get_secret_keys(&self) -> Vec<String> {
         self.sources
             .iter()
@@ -604,11 +1256,118 @@ pub(crate) struct AnchorGroupImpl {
     pub(crate) version: u64,
     pub(crate) name: String,
     pub(crate) source: Source,
+    pub(crate) joined_sources: Vec<Source>,
+    pub(crate) join_keys: Vec<String>,
+    pub(crate) preprocessing: Option<String>,
+    pub(crate) default_window: Option<Duration>,
     pub(crate) registry_tags: HashMap<String, String>,
 }
 
-#[derive(Clone, Debug)]
-pub struct AnchorGroup {
+impl AnchorGroupImpl {
+    /**
+     * Window aggregation features without a window use the default window of the group
+     */
+    fn inherit_window(&self, transform: &mut Transformation) {
+        if let Transformation::WindowAgg { window, .. } = transform {
+            if window.is_none() {
+                *window = self.default_window;
+            }
+        }
+    }
+
+    /**
+     * A window aggregation without a window only makes sense with the `NOP` aggregation if the
+     * group has no default window
+     */
+    fn validate_window(&self, f: &AnchorFeatureImpl) -> Result<(), Error> {
+        match &f.transform {
+            Transformation::WindowAgg {
+                window: None,
+                agg_func: Some(agg),
+                ..
+            } if self.default_window.is_none() && *agg != Aggregation::NOP => {
+                Err(Error::InvalidArgument(format!(
+                    "Feature {} aggregates with {:?} but neither the feature nor the anchor group {} has a window",
+                    f.get_name(),
+                    agg,
+                    self.name
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /**
+     * Python expression applied to the source before computing the features of the group, the
+     * anchor-level preprocessing runs after the sources are preprocessed and joined
+     */
+    fn get_preprocessing(&self) -> Option<String> {
+        let base = self
+            .get_join_function()
+            .or_else(|| self.source.get_preprocessing());
+        match (base, &self.preprocessing) {
+            (Some(base), Some(pp)) => Some(format!("lambda df: ({})(({})(df))", pp, base)),
+            (base, pp) => base.or_else(|| pp.to_owned()),
+        }
+    }
+
+    /**
+     * Python expression loading the joined sources and joining them with the primary source,
+     * `None` if the anchor group has only one source
+     */
+    fn get_join_function(&self) -> Option<String> {
+        if self.joined_sources.is_empty() {
+            return None;
+        }
+        let apply = |source: &Source, df: String| match source.get_preprocessing() {
+            Some(pp) => format!("({})({})", pp, df),
+            None => df,
+        };
+        let joined = self
+            .joined_sources
+            .iter()
+            .map(|s| {
+                let spec = get_source_spec(&s.get_location()).unwrap_or_default();
+                apply(s, format!(r#"load_source(r"""{}""")"#, spec))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(format!(
+            "lambda df: join_sources({}, {}, [{}])",
+            apply(&self.source, "df".to_string()),
+            serde_json::to_string(&self.join_keys).unwrap_or_default(),
+            joined
+        ))
+    }
+}
+
+/**
+ * The JSON spec `load_source` in the PySpark driver uses to read a joined source
+ */
+fn get_source_spec(location: &DataLocation) -> Result<String, Error> {
+    let spec = match location {
+        DataLocation::Hdfs { path } => serde_json::json!({ "path": path }),
+        DataLocation::Generic {
+            format, options, ..
+        } => {
+            // Option keys are escaped for the feathr config, Spark needs the original ones
+            let options: HashMap<String, String> = options
+                .iter()
+                .map(|(k, v)| (k.replace("__", "."), v.to_owned()))
+                .collect();
+            serde_json::json!({ "format": format, "options": options })
+        }
+        _ => {
+            return Err(Error::InvalidArgument(
+                "Only HDFS and generic sources can be joined by an anchor group".to_string(),
+            ))
+        }
+    };
+    Ok(spec.to_string())
+}
+
+#[derive(Clone, Debug)]
+pub struct AnchorGroup {
     owner: Arc<RwLock<FeathrProjectImpl>>,
     inner: Arc<AnchorGroupImpl>,
 }
@@ -659,6 +1418,10 @@ pub struct AnchorGroupBuilder {
     owner: Arc<RwLock<FeathrProjectImpl>>,
     name: String,
     source: Source,
+    joined_sources: Vec<Source>,
+    join_keys: Vec<String>,
+    preprocessing: Option<String>,
+    default_window: Option<Duration>,
     registry_tags: HashMap<String, String>,
 }
 
@@ -668,10 +1431,48 @@ impl AnchorGroupBuilder {
             owner,
             name: name.to_string(),
             source: source,
+            joined_sources: Default::default(),
+            join_keys: Default::default(),
+            preprocessing: Default::default(),
+            default_window: Default::default(),
             registry_tags: Default::default(),
         }
     }
 
+    /**
+     * Left join another source to the primary source on the join keys before computing features,
+     * e.g. enrich a fact table with a dimension table
+     */
+    pub fn join_source(&mut self, source: Source) -> &mut Self {
+        self.joined_sources.push(source);
+        self
+    }
+
+    /**
+     * Columns the joined sources are joined on, they must exist in all sources
+     */
+    pub fn join_keys(&mut self, keys: &[&str]) -> &mut Self {
+        self.join_keys = keys.iter().map(|k| k.to_string()).collect();
+        self
+    }
+
+    /**
+     * Python function applied to the source of this group only, after the preprocessing of the
+     * source, so anchor groups sharing a source can preprocess it differently
+     */
+    pub fn preprocessing(&mut self, preprocessing: &str) -> &mut Self {
+        self.preprocessing = Some(preprocessing.to_string());
+        self
+    }
+
+    /**
+     * Window of the window aggregation features in this group not setting their own
+     */
+    pub fn default_window(&mut self, window: Duration) -> &mut Self {
+        self.default_window = Some(window);
+        self
+    }
+
     pub fn add_registry_tag(&mut self, key: &str, value: &str) -> &mut Self {
         self.registry_tags
             .insert(key.to_string(), value.to_string());
@@ -679,12 +1480,33 @@ impl AnchorGroupBuilder {
     }
 
     pub async fn build(&mut self) -> Result<AnchorGroup, Error> {
+        if !self.joined_sources.is_empty() {
+            if self.join_keys.is_empty() {
+                return Err(Error::InvalidArgument(format!(
+                    "Anchor group {} joins multiple sources but has no join keys",
+                    self.name
+                )));
+            }
+            if matches!(self.source.get_location(), DataLocation::InputContext) {
+                return Err(Error::InvalidArgument(format!(
+                    "Anchor group {} cannot join sources to the input context",
+                    self.name
+                )));
+            }
+            for s in &self.joined_sources {
+                get_source_spec(&s.get_location())?;
+            }
+        }
         let group = AnchorGroupImpl {
             id: Uuid::new_v4(),
             version: 1,
             name: self.name.clone(),
             source: self.source.clone(),
-            registry_tags: self.registry_tags.clone(),
+            joined_sources: self.joined_sources.clone(),
+            join_keys: self.join_keys.clone(),
+            preprocessing: self.preprocessing.clone(),
+            default_window: self.default_window,
+            registry_tags: self.owner.read().await.inherit_tags(&self.registry_tags),
         };
 
         Ok(self.owner.insert_anchor_group(group).await?)
@@ -743,103 +1565,116 @@ impl TryFrom<EntityLineage> for FeathrProjectImpl {
     type Error = Error;
 
     fn try_from(value: EntityLineage) -> Result<Self, Self::Error> {
-        // Build relation maps
-        let belongs_map: HashMap<Uuid, String> = value
-            .relations
-            .iter()
-            .filter(|&r| r.edge_type == EdgeType::BelongsTo)
-            .map(|r| (r.from.to_owned(), value.guid_entity_map[&r.to].get_name()))
-            .collect();
-        let consumes_map: HashMap<Uuid, String> = value
-            .relations
-            .iter()
-            .filter(|&r| r.edge_type == EdgeType::Consumes)
-            .map(|r| (r.from.to_owned(), value.guid_entity_map[&r.to].get_name()))
-            .collect();
-        let (_, entity) = value
-            .guid_entity_map
+        let entities = &value.guid_entity_map;
+        let (project_id, entity) = entities
             .iter()
             .find(|(_, entity)| entity.get_entity_type() == EntityType::Project)
             .ok_or_else(|| Error::ProjectNotFound(Default::default()))?;
         let mut project: FeathrProjectImpl = entity.to_owned().try_into()?;
+        // The lineage also has the inputs of derived features from other projects
+        let contained: HashSet<Uuid> = value
+            .relations
+            .iter()
+            .filter(|r| r.edge_type == EdgeType::Contains && r.from == *project_id)
+            .map(|r| r.to)
+            .collect();
         // Add sources into project
-        project.sources = value
-            .guid_entity_map
+        for (_, e) in entities
             .iter()
             .filter(|(_, entity)| entity.get_entity_type() == EntityType::Source)
-            .filter_map(|(_, e)| {
-                e.to_owned()
-                    .try_into()
-                    .ok()
-                    .map(|i: SourceImpl| (i.name.clone(), Arc::new(i)))
-            })
-            .collect();
-        // Add all anchor groups into project
-        project.anchor_groups = value
-            .guid_entity_map
+        {
+            let s: SourceImpl = e.to_owned().try_into()?;
+            project.sources.insert(s.name.clone(), Arc::new(s));
+        }
+        // The placeholder source is not registered if no anchor uses it
+        let input_context = SourceImpl::INPUT_CONTEXT();
+        project
+            .sources
+            .entry(input_context.name.clone())
+            .or_insert_with(|| Arc::new(input_context));
+        // Add all anchor groups into project, with the source they consume
+        for (id, e) in entities
             .iter()
             .filter(|(_, entity)| entity.get_entity_type() == EntityType::Anchor)
-            .filter_map(|(id, e)| {
-                e.to_owned().try_into().ok().map(|mut i: AnchorGroupImpl| {
-                    i.source = Source {
-                        inner: project.sources[&consumes_map[id]].to_owned(),
-                    };
-                    (i.name.clone(), Arc::new(i))
+        {
+            let mut g: AnchorGroupImpl = e.to_owned().try_into()?;
+            let joined_ids: Vec<Uuid> = match &e.attributes {
+                EntityAttributes::Anchor(a) => a.joined_sources.iter().map(|s| s.get_id()).collect(),
+                _ => Default::default(),
+            };
+            // The primary source is the one not joined
+            let source_name = value
+                .get_targets(*id, EdgeType::Consumes, EntityType::Source)
+                .find(|(source_id, _)| !joined_ids.contains(source_id))
+                .map(|(_, s)| s.get_name())
+                .unwrap_or_else(|| SourceImpl::INPUT_CONTEXT().name);
+            let find_source = |name: String| {
+                project
+                    .sources
+                    .get(&name)
+                    .map(|s| Source { inner: s.to_owned() })
+                    .ok_or_else(|| Error::SourceGroupNotFound(name))
+            };
+            g.source = find_source(source_name)?;
+            g.joined_sources = joined_ids
+                .iter()
+                .map(|source_id| {
+                    let name = entities
+                        .get(source_id)
+                        .map(|s| s.get_name())
+                        .unwrap_or_else(|| source_id.to_string());
+                    find_source(name)
                 })
-            })
-            .collect();
-        project.anchor_map = project
-            .anchor_groups
-            .iter()
-            .map(|(k, _)| (k.to_owned(), Default::default()))
-            .collect();
-        // Find all anchor features
-        let anchor_features: HashMap<Uuid, AnchorFeatureImpl> = value
-            .guid_entity_map
-            .iter()
-            .filter(|(_, entity)| entity.get_entity_type() == EntityType::AnchorFeature)
-            .filter_map(|(id, e)| e.to_owned().try_into().ok().map(|e| (id.to_owned(), e)))
-            .collect();
-        // Add all anchor features into corresponding anchor groups
-        for (uuid, f) in anchor_features {
-            let g = project.anchor_groups[&belongs_map[&uuid]].to_owned();
-            if let Some(g) = project.anchor_map.get_mut(&g.name) {
-                g.push(f.get_name());
-            }
+                .collect::<Result<_, _>>()?;
+            project
+                .anchor_map
+                .insert(g.name.clone(), Default::default());
+            project.anchor_groups.insert(g.name.clone(), Arc::new(g));
+        }
+        // Add all anchor features into corresponding anchor groups.
+        // An anchor feature belongs to both the project and the anchor, only the latter is used here
+        for (id, e) in entities.iter().filter(|(id, entity)| {
+            entity.get_entity_type() == EntityType::AnchorFeature && contained.contains(id)
+        }) {
+            let f: AnchorFeatureImpl = e.to_owned().try_into()?;
+            let group = value
+                .get_targets(*id, EdgeType::BelongsTo, EntityType::Anchor)
+                .next()
+                .map(|(_, g)| g.get_name())
+                .ok_or_else(|| Error::AnchorGroupNotFound(f.get_name()))?;
+            project
+                .anchor_map
+                .get_mut(&group)
+                .ok_or_else(|| Error::AnchorGroupNotFound(group.clone()))?
+                .push(f.get_name());
             project.anchor_features.insert(f.get_name(), Arc::new(f));
         }
-        // Add all derived features into project
-        project.derivations = value
-            .guid_entity_map
-            .iter()
-            .filter(|(_, entity)| entity.get_entity_type() == EntityType::DerivedFeature)
-            .filter_map(|(id, e)| {
-                e.to_owned()
-                    .try_into()
-                    .ok()
-                    .map(|mut i: DerivedFeatureImpl| {
-                        i.inputs = value
-                            .relations
-                            .iter()
-                            .filter(|&r| r.edge_type == EdgeType::Consumes && &r.from == id)
-                            .filter_map(|r| {
-                                value.guid_entity_map[&r.to].get_typed_key().ok().map(|k| {
-                                    InputFeature {
-                                        id: r.to,
-                                        key: k,
-                                        feature: value.guid_entity_map[&r.to].get_name(),
-                                        is_anchor_feature: value.guid_entity_map[&r.to]
-                                            .get_entity_type()
-                                            == EntityType::AnchorFeature,
-                                    }
-                                })
-                            })
-                            .map(|f| (f.feature.clone(), f))
-                            .collect();
-                        (i.base.name.clone(), Arc::new(i))
-                    })
-            })
-            .collect();
+        // The registry doesn't keep the order features were added, sort them to make it stable
+        project.anchor_map.values_mut().for_each(|v| v.sort());
+        // Add all derived features into project, with their input features
+        for (id, e) in entities.iter().filter(|(id, entity)| {
+            entity.get_entity_type() == EntityType::DerivedFeature && contained.contains(id)
+        }) {
+            let mut f: DerivedFeatureImpl = e.to_owned().try_into()?;
+            for (input_id, input) in value
+                .get_targets(*id, EdgeType::Consumes, EntityType::AnchorFeature)
+                .chain(value.get_targets(*id, EdgeType::Consumes, EntityType::DerivedFeature))
+            {
+                let input = InputFeature {
+                    id: input_id,
+                    key: input.get_typed_key()?,
+                    // Features of other projects are referred by the qualified names
+                    feature: if contained.contains(&input_id) {
+                        input.get_name()
+                    } else {
+                        input.get_qualified_name()
+                    },
+                    is_anchor_feature: input.get_entity_type() == EntityType::AnchorFeature,
+                };
+                f.inputs.insert(input.feature.clone(), input);
+            }
+            project.derivations.insert(f.base.name.clone(), Arc::new(f));
+        }
 
         // NOTE: returned project doesn't have owner, need to be set later
         Ok(project)
@@ -848,6 +1683,13 @@ impl TryFrom<EntityLineage> for FeathrProjectImpl {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
+    use chrono::Duration;
+    use uuid::Uuid;
+
+    use super::FeathrProjectImpl;
+    use crate::registry_client::api_models::EntityLineage;
     use crate::*;
 
     #[tokio::test]
@@ -881,6 +1723,660 @@ mod tests {
             .await
             .unwrap();
         let s = proj.get_feature_config().await.unwrap();
-        println!("{}", s);
+        assert!(s.anchors.contains_key("g1"));
+        assert!(s.derivations.contains_key("d1"));
+        println!("{}", s.render().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_detect_inputs() {
+        let proj = FeathrProject::new_detached("p1").await;
+        let s = proj
+            .hdfs_source(
+                "s1",
+                "wasbs://public@azurefeathrstorage.blob.core.windows.net/s1.csv",
+            )
+            .build()
+            .await
+            .unwrap();
+        let g1 = proj.anchor_group("g1", s).build().await.unwrap();
+        let k1 = TypedKey::new("c1", ValueType::INT32);
+        let f1 = g1
+            .anchor("f1", FeatureType::INT32)
+            .unwrap()
+            .transform("x")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+        let d1 = proj
+            .derived_feature("d1", FeatureType::INT32)
+            .transform("f1 + 1")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(d1.inner.inputs["f1"].id, f1.get_id());
+
+        let d2 = proj
+            .derived_feature("d2", FeatureType::INT32)
+            .transform("CASE WHEN d1 > 0 THEN f1 ELSE 0 END")
+            .build()
+            .await
+            .unwrap();
+        let mut inputs: Vec<&String> = d2.inner.inputs.keys().collect();
+        inputs.sort();
+        assert_eq!(inputs, vec!["d1", "f1"]);
+        assert!(!d2.inner.inputs["d1"].is_anchor_feature);
+
+        assert!(matches!(
+            proj.derived_feature("d3", FeatureType::INT32)
+                .transform("f2 * 2")
+                .build()
+                .await,
+            Err(Error::UnknownInputFeature(d, f)) if d == "d3" && f == "f2"
+        ));
+        // Explicit inputs are used as is
+        proj.derived_feature("d3", FeatureType::INT32)
+            .transform("f2 * 2")
+            .add_input(&f1)
+            .build()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_default_tags() {
+        let proj = FeathrProject::new_detached("p1").await;
+        proj.inner.write().await.default_tags = [("team", "ads"), ("tier", "dev")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let s = proj
+            .hdfs_source(
+                "s1",
+                "wasbs://public@azurefeathrstorage.blob.core.windows.net/s1.csv",
+            )
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(s.inner.registry_tags, proj.get_default_tags().await);
+        let g1 = proj.anchor_group("g1", s).build().await.unwrap();
+        let f1 = g1
+            .anchor("f1", FeatureType::INT32)
+            .unwrap()
+            .transform("x")
+            .add_tag("tier", "prod")
+            .build()
+            .await
+            .unwrap();
+        // Tags of the feature take precedence
+        let tags = f1.get_registry_tags();
+        assert_eq!(tags["team"], "ads");
+        assert_eq!(tags["tier"], "prod");
+    }
+
+    #[tokio::test]
+    async fn test_delete() {
+        let proj = FeathrProject::new_detached("p1").await;
+        let s = proj
+            .hdfs_source(
+                "s1",
+                "wasbs://public@azurefeathrstorage.blob.core.windows.net/s1.csv",
+            )
+            .build()
+            .await
+            .unwrap();
+        let g1 = proj.anchor_group("g1", s).build().await.unwrap();
+        let k1 = TypedKey::new("c1", ValueType::INT32);
+        let f = g1
+            .anchor("f1", FeatureType::INT32)
+            .unwrap()
+            .transform("x")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+        proj.derived_feature("d1", FeatureType::INT32)
+            .add_input(&f)
+            .transform("f1 + 1")
+            .build()
+            .await
+            .unwrap();
+
+        // Everything is in use except the derived feature
+        assert!(matches!(
+            proj.delete_source("s1").await,
+            Err(Error::EntityInUse(..))
+        ));
+        assert!(matches!(
+            proj.delete_anchor_group("g1").await,
+            Err(Error::EntityInUse(..))
+        ));
+        assert!(matches!(
+            proj.delete_feature("f1").await,
+            Err(Error::EntityInUse(..))
+        ));
+
+        proj.delete_feature("d1").await.unwrap();
+        proj.delete_feature("f1").await.unwrap();
+        proj.delete_anchor_group("g1").await.unwrap();
+        proj.delete_source("s1").await.unwrap();
+        assert!(proj.get_derived_features().await.is_empty());
+        assert!(proj.get_anchor_features().await.is_empty());
+        assert!(proj.get_anchor_groups().await.is_empty());
+        assert!(!proj.get_sources().await.contains(&"s1".to_string()));
+        assert!(matches!(
+            proj.delete_feature("f1").await,
+            Err(Error::FeatureNotFound(_))
+        ));
+    }
+
+    /**
+     * Build the lineage of the project the same way as the registry does
+     */
+    async fn to_lineage(proj: &FeathrProject) -> EntityLineage {
+        use crate::registry_client::api_models::*;
+        use serde_json::{json, Value};
+
+        let p = proj.inner.read().await;
+        let mut entities = vec![];
+        let mut relations = vec![];
+        let mut connect = |from: Uuid, to: Uuid, edge_type: EdgeType, reverse: EdgeType| {
+            relations.push(Relationship {
+                edge_type,
+                from,
+                to,
+            });
+            relations.push(Relationship {
+                edge_type: reverse,
+                from: to,
+                to: from,
+            });
+        };
+        let entity = |id: Uuid, version: u64, type_name: &str, mut attributes: Value| {
+            let name = attributes["name"].as_str().unwrap().to_string();
+            attributes["qualifiedName"] = json!(format!("{}__{}", p.name, name));
+            json!({
+                "guid": id,
+                "status": "Active",
+                "displayText": name,
+                "version": version,
+                "typeName": type_name,
+                "attributes": attributes,
+            })
+        };
+        entities.push(entity(
+            p.id,
+            p.version,
+            "feathr_workspace_v1",
+            json!({
+                "name": p.name,
+                "anchors": [],
+                "sources": [],
+                "anchorFeatures": [],
+                "derivedFeatures": [],
+                "tags": p.registry_tags,
+            }),
+        ));
+        for s in p.sources.values() {
+            // Options are flattened into the attributes
+            let mut attr = serde_json::to_value(SourceDef::from(s.as_ref().to_owned())).unwrap();
+            let options = attr.as_object_mut().unwrap().remove("options").unwrap();
+            attr.as_object_mut()
+                .unwrap()
+                .extend(options.as_object().unwrap().clone());
+            entities.push(entity(s.id, s.version, "feathr_source_v1", attr));
+            connect(p.id, s.id, EdgeType::Contains, EdgeType::BelongsTo);
+        }
+        for g in p.anchor_groups.values() {
+            entities.push(entity(
+                g.id,
+                g.version,
+                "feathr_anchor_v1",
+                json!({
+                    "name": g.name,
+                    "features": [],
+                    "joinedSources": g.joined_sources.iter().map(|s| json!({
+                        "guid": s.get_id(),
+                        "typeName": "feathr_source_v1",
+                        "uniqueAttributes": {"qualifiedName": format!("{}__{}", p.name, s.get_name())},
+                    })).collect::<Vec<_>>(),
+                    "joinKeys": g.join_keys,
+                    "preprocessing": g.preprocessing,
+                    "defaultWindow": g.default_window.map(crate::utils::dur_to_string),
+                    "tags": g.registry_tags,
+                }),
+            ));
+            connect(p.id, g.id, EdgeType::Contains, EdgeType::BelongsTo);
+            for s in std::iter::once(&g.source).chain(g.joined_sources.iter()) {
+                connect(g.id, s.get_id(), EdgeType::Consumes, EdgeType::Produces);
+            }
+            for name in &p.anchor_map[&g.name] {
+                let f = &p.anchor_features[name];
+                let mut attr =
+                    serde_json::to_value(AnchorFeatureDef::from(f.as_ref().to_owned())).unwrap();
+                attr["type"] = attr["featureType"].take();
+                entities.push(entity(
+                    f.base.id,
+                    f.base.version,
+                    "feathr_anchor_feature_v1",
+                    attr,
+                ));
+                connect(p.id, f.base.id, EdgeType::Contains, EdgeType::BelongsTo);
+                connect(g.id, f.base.id, EdgeType::Contains, EdgeType::BelongsTo);
+                connect(
+                    f.base.id,
+                    g.source.get_id(),
+                    EdgeType::Consumes,
+                    EdgeType::Produces,
+                );
+            }
+        }
+        for f in p.derivations.values() {
+            let mut attr =
+                serde_json::to_value(DerivedFeatureDef::from(f.as_ref().to_owned())).unwrap();
+            attr["type"] = attr["featureType"].take();
+            attr["inputAnchorFeatures"] = json!([]);
+            attr["inputDerivedFeatures"] = json!([]);
+            entities.push(entity(
+                f.base.id,
+                f.base.version,
+                "feathr_derived_feature_v1",
+                attr,
+            ));
+            connect(p.id, f.base.id, EdgeType::Contains, EdgeType::BelongsTo);
+            for input in f.inputs.values() {
+                connect(f.base.id, input.id, EdgeType::Consumes, EdgeType::Produces);
+            }
+        }
+        serde_json::from_value(json!({
+            "guidEntityMap": entities
+                .into_iter()
+                .map(|e| (e["guid"].as_str().unwrap().to_string(), e))
+                .collect::<serde_json::Map<_, _>>(),
+            "relations": relations,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_shared_inputs() {
+        let shared = FeathrProject::new_detached("shared").await;
+        let s = shared
+            .hdfs_source(
+                "s1",
+                "wasbs://public@azurefeathrstorage.blob.core.windows.net/s1.csv",
+            )
+            .build()
+            .await
+            .unwrap();
+        let k1 = TypedKey::new("c1", ValueType::INT32);
+        let g1 = shared.anchor_group("g1", s).build().await.unwrap();
+        let f1 = g1
+            .anchor("f1", FeatureType::INT32)
+            .unwrap()
+            .transform("x")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+
+        let proj = FeathrProject::new_detached("p1").await;
+        // Features of other projects are looked up in the registry
+        assert!(matches!(
+            proj.derived_feature("d1", FeatureType::INT32)
+                .add_shared_input("shared__f1")
+                .transform("shared__f1 + 1")
+                .build()
+                .await,
+            Err(Error::InvalidArgument(_))
+        ));
+        let d1 = proj
+            .derived_feature("d1", FeatureType::INT32)
+            .add_input(&f1)
+            .transform("f1 + 1")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+
+        // The shared input is in the lineage of the project but doesn't belong to it
+        let mut lineage = to_lineage(&proj).await;
+        let shared_lineage = to_lineage(&shared).await;
+        lineage.guid_entity_map.insert(
+            f1.get_id(),
+            shared_lineage.guid_entity_map[&f1.get_id()].clone(),
+        );
+        let reloaded: FeathrProjectImpl = lineage.try_into().unwrap();
+        assert!(reloaded.anchor_features.is_empty());
+        let inputs = &reloaded.derivations[&d1.get_name()].inputs;
+        assert_eq!(inputs.keys().collect::<Vec<_>>(), vec!["shared__f1"]);
+        assert_eq!(inputs["shared__f1"].id, f1.get_id());
+        assert!(inputs["shared__f1"].is_anchor_feature);
+    }
+
+    #[tokio::test]
+    async fn test_reload_project() {
+        let proj = FeathrProject::new_detached("p1").await;
+        let hdfs = proj
+            .hdfs_source("nycTaxiBatchSource", "wasbs://public@azurefeathrstorage.blob.core.windows.net/sample_data/green_tripdata_2020-04.csv")
+            .time_window("lpep_dropoff_datetime", "yyyy-MM-dd HH:mm:ss")
+            .preprocessing("add_new_dropoff_and_fare_amount_column")
+            .build()
+            .await
+            .unwrap();
+        let jdbc = proj
+            .jdbc_source(
+                "h1",
+                "jdbc:sqlserver://bet-test.database.windows.net:1433;database=bet-test",
+            )
+            .auth(JdbcSourceAuth::Userpass)
+            .dbtable("AzureRegions")
+            .build()
+            .await
+            .unwrap();
+        let cosmos = proj
+            .generic_source("cosmos", "cosmos.oltp")
+            .mode("APPEND")
+            .option(
+                "spark.cosmos.accountEndpoint",
+                "https://feathr.documents.azure.com:443/",
+            )
+            .option("spark.cosmos.accountKey", "${cosmos_KEY}")
+            .option("spark.cosmos.database", "feathr")
+            .option("spark.cosmos.container", "zones")
+            .build()
+            .await
+            .unwrap();
+        proj.kafka_source("kafka")
+            .broker("feathrazureci.servicebus.windows.net:9093")
+            .topic("feathrcieventhub")
+            .avro_json(&r#"{"type": "record", "name": "DriverTrips", "fields": []}"#)
+            .build()
+            .await
+            .unwrap();
+
+        let location_id = TypedKey::new("DOLocationID", ValueType::INT64)
+            .full_name("nyc_taxi.location_id")
+            .key_column_alias("location_id");
+        let g1 = proj
+            .anchor_group("agg_features", hdfs.clone())
+            .preprocessing("lambda df: df.dropna()")
+            .default_window(Duration::days(90))
+            .add_registry_tag("team", "taxi")
+            .build()
+            .await
+            .unwrap();
+        let g4 = proj
+            .anchor_group("enriched_trips", hdfs)
+            .join_source(cosmos)
+            .join_keys(&["DOLocationID"])
+            .build()
+            .await
+            .unwrap();
+        g4.anchor("f_location_zone", FeatureType::STRING)
+            .unwrap()
+            .transform("zone")
+            .keys(&[&location_id])
+            .build()
+            .await
+            .unwrap();
+        let f1 = g1
+            .anchor("f_location_avg_fare", FeatureType::FLOAT)
+            .unwrap()
+            .transform(
+                Transformation::window_agg(
+                    "cast_float(fare_amount)",
+                    Aggregation::AVG,
+                    Duration::days(90),
+                )
+                .unwrap(),
+            )
+            .keys(&[&location_id])
+            .add_tag("pii", "false")
+            .build()
+            .await
+            .unwrap();
+        g1.anchor("f_location_max_fare", FeatureType::FLOAT)
+            .unwrap()
+            .transform(
+                Transformation::window_agg_inherited("cast_float(fare_amount)", Aggregation::MAX)
+                    .unwrap(),
+            )
+            .keys(&[&location_id])
+            .build()
+            .await
+            .unwrap();
+        let g2 = proj
+            .anchor_group("request_features", proj.INPUT_CONTEXT().await)
+            .build()
+            .await
+            .unwrap();
+        let f2 = g2
+            .anchor("f_trip_distance", FeatureType::FLOAT)
+            .unwrap()
+            .transform("trip_distance")
+            .add_tag("unit", "mile")
+            .build()
+            .await
+            .unwrap();
+        let g3 = proj.anchor_group("regions", jdbc).build().await.unwrap();
+        g3.anchor("f_region", FeatureType::STRING)
+            .unwrap()
+            .transform("region")
+            .keys(&[&TypedKey::new("RegionID", ValueType::INT32)])
+            .build()
+            .await
+            .unwrap();
+        // No window to inherit, only NOP is allowed
+        assert!(matches!(
+            g3.anchor("f_region_count", FeatureType::INT64)
+                .unwrap()
+                .transform(Transformation::window_agg_inherited("1", Aggregation::SUM).unwrap())
+                .keys(&[&TypedKey::new("RegionID", ValueType::INT32)])
+                .build()
+                .await,
+            Err(Error::InvalidArgument(_))
+        ));
+        g3.anchor("f_region_name", FeatureType::STRING)
+            .unwrap()
+            .transform(Transformation::window_agg_inherited("name", Aggregation::NOP).unwrap())
+            .keys(&[&TypedKey::new("RegionID", ValueType::INT32)])
+            .build()
+            .await
+            .unwrap();
+        proj.derived_feature("f_avg_fare_per_mile", FeatureType::FLOAT)
+            .add_input(&f1)
+            .add_input(&f2)
+            .keys(&[&location_id])
+            .transform("f_location_avg_fare / f_trip_distance")
+            .add_tag("derived", "true")
+            .build()
+            .await
+            .unwrap();
+
+        let reloaded: FeathrProjectImpl = to_lineage(&proj).await.try_into().unwrap();
+        let p = proj.inner.read().await;
+        assert_eq!(reloaded.id, p.id);
+        assert_eq!(reloaded.name, p.name);
+        assert_eq!(reloaded.sources, p.sources);
+        assert_eq!(reloaded.anchor_features, p.anchor_features);
+        assert_eq!(reloaded.derivations, p.derivations);
+        assert_eq!(
+            reloaded.anchor_groups.keys().collect::<HashSet<_>>(),
+            p.anchor_groups.keys().collect::<HashSet<_>>()
+        );
+        for (name, g) in &p.anchor_groups {
+            let r = &reloaded.anchor_groups[name];
+            assert_eq!((r.id, r.version), (g.id, g.version));
+            assert_eq!(r.registry_tags, g.registry_tags);
+            assert_eq!(r.source, g.source);
+            assert_eq!(r.joined_sources, g.joined_sources);
+            assert_eq!(r.join_keys, g.join_keys);
+            assert_eq!(r.preprocessing, g.preprocessing);
+            assert_eq!(r.default_window, g.default_window);
+            let mut features = p.anchor_map[name].clone();
+            features.sort();
+            assert_eq!(reloaded.anchor_map[name], features);
+        }
+        assert_eq!(
+            reloaded.get_user_functions(&["f_location_avg_fare".to_string()]),
+            p.get_user_functions(&["f_location_avg_fare".to_string()])
+        );
+        // The feature config has the inherited window
+        let config = serde_json::to_value(&*p).unwrap();
+        assert_eq!(
+            config["anchors"]["agg_features"]["features"]["f_location_max_fare"]["window"],
+            "90d"
+        );
+        let functions = p.get_user_functions(&["f_location_avg_fare".to_string()]);
+        assert_eq!(
+            functions["f_location_avg_fare"],
+            "lambda df: (lambda df: df.dropna())((add_new_dropoff_and_fare_amount_column)(df))"
+        );
+        let functions = p.get_user_functions(&["f_location_zone".to_string()]);
+        assert_eq!(
+            functions["f_location_zone"],
+            r#"lambda df: join_sources((add_new_dropoff_and_fare_amount_column)(df), ["DOLocationID"], [load_source(r"""{"format":"cosmos.oltp","options":{"spark.cosmos.accountEndpoint":"https://feathr.documents.azure.com:443/","spark.cosmos.accountKey":"${cosmos_KEY}","spark.cosmos.container":"zones","spark.cosmos.database":"feathr"}}""")])"#
+        );
+    }
+
+    #[tokio::test]
+    async fn lint_project() {
+        let proj = FeathrProject::new_detached("p1").await;
+        let no_timestamp = proj
+            .hdfs_source("no_timestamp", "abfss://data@account/a.csv")
+            .build()
+            .await
+            .unwrap();
+        proj.hdfs_source("unused", "abfss://data@account/b.csv")
+            .build()
+            .await
+            .unwrap();
+        let g1 = proj.anchor_group("g1", no_timestamp).build().await.unwrap();
+        let k1 = TypedKey::new("c1", ValueType::INT32).key_column_alias("k");
+        let k2 = TypedKey::new("c2", ValueType::INT32).key_column_alias("k");
+        let f1 = g1
+            .anchor("f1", FeatureType::FLOAT)
+            .unwrap()
+            .transform(
+                Transformation::window_agg("x", Aggregation::SUM, Duration::days(1)).unwrap(),
+            )
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+        g1.anchor("f2", FeatureType::INT32)
+            .unwrap()
+            .transform("y")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+        proj.derived_feature("d1", FeatureType::FLOAT)
+            .add_input(&f1)
+            .transform("f1")
+            .keys(&[&k1, &k2])
+            .build()
+            .await
+            .unwrap();
+
+        let report = proj.lint(&[&FeatureQuery::by_name(&["d1"])]).await;
+        let found: HashSet<_> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.kind, d.entity.as_str()))
+            .collect();
+        assert!(report.has_errors());
+        assert_eq!(
+            found,
+            [
+                (LintKind::UnusedSource, "unused"),
+                (LintKind::MissingTimestampColumn, "f1"),
+                (LintKind::DuplicateKeyAlias, "d1"),
+                (LintKind::UnreferencedFeature, "f2"),
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(report.diagnostics[0].severity, Severity::Error);
+
+        // Without queries every feature counts as used
+        let report = proj.lint::<FeatureQuery>(&[]).await;
+        assert!(report
+            .diagnostics
+            .iter()
+            .all(|d| d.kind != LintKind::UnreferencedFeature));
+    }
+
+    #[tokio::test]
+    async fn test_coercion_warnings() {
+        let proj = FeathrProject::new_detached("p1").await;
+        let s = proj
+            .hdfs_source("s1", "abfss://data@account/a.csv")
+            .time_window("ts", "epoch")
+            .build()
+            .await
+            .unwrap();
+        let g1 = proj.anchor_group("g1", s).build().await.unwrap();
+        let k1 = TypedKey::new("c1", ValueType::INT32);
+        let k1_str = TypedKey::new("c1", ValueType::STRING);
+        g1.anchor("f1", FeatureType::INT32)
+            .unwrap()
+            .transform(
+                Transformation::window_agg("x", Aggregation::AVG, Duration::days(1)).unwrap(),
+            )
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+        let f2 = g1
+            .anchor("f2", FeatureType::DOUBLE)
+            .unwrap()
+            .transform("y")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+        g1.anchor("f3", FeatureType::STRING)
+            .unwrap()
+            .transform("z")
+            .keys(&[&k1_str])
+            .build()
+            .await
+            .unwrap();
+        proj.derived_feature("d1", FeatureType::INT32)
+            .add_input(&f2)
+            .transform("f2")
+            .keys(&[&k1])
+            .build()
+            .await
+            .unwrap();
+
+        let warnings = proj
+            .coercion_warnings(&[&FeatureQuery::new(&["f1", "f3", "d1"], &[&k1])])
+            .await
+            .unwrap();
+        let found: Vec<_> = warnings
+            .iter()
+            .map(|w| (w.feature.as_str(), w.context.as_str(), w.coercion))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("d1", "input feature f2", Coercion::Narrowing),
+                ("f1", "aggregation AVG", Coercion::Narrowing),
+                ("f3", "key c1 shared with d1", Coercion::Cast),
+            ]
+        );
+        assert_eq!(
+            ValueType::INT32.coercion_to(ValueType::INT64),
+            Coercion::Widening
+        );
+        assert_eq!(
+            ValueType::BYTES.coercion_to(ValueType::INT32),
+            Coercion::Incompatible
+        );
     }
 }
@@ -0,0 +1,312 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use chrono::Duration;
+use log::warn;
+use serde::Serialize;
+
+use crate::{ClusterSize, Error, Transformation};
+
+/**
+ * Registry tag annotating the computation cost of a feature, the value is `low`, `medium`, `high`
+ * or a number of cost units
+ */
+pub const COST_TAG: &str = "feathr_cost";
+
+/**
+ * A feature join with a total cost above this is predicted to be expensive
+ */
+pub const EXPENSIVE_QUERY_COST: u64 = 64;
+
+/// Cost units one executor of the suggested cluster is sized for
+const COST_PER_EXECUTOR: u64 = 16;
+const MAX_SUGGESTED_EXECUTORS: u64 = 32;
+/// Window aggregations over longer windows are `high` if not annotated
+const LONG_WINDOW_DAYS: i64 = 7;
+
+/**
+ * Relative computation cost of a feature, e.g. a simple projection is `low` and a window
+ * aggregation over months of data is `high`
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct FeatureCost(pub u64);
+
+impl FeatureCost {
+    pub const LOW: FeatureCost = FeatureCost(1);
+    pub const MEDIUM: FeatureCost = FeatureCost(4);
+    pub const HIGH: FeatureCost = FeatureCost(16);
+
+    /**
+     * The cost of a feature not annotated with `feathr_cost`, projections are `low`, UDFs and
+     * window aggregations are `medium`, window aggregations over more than 7 days or with
+     * `group_by` are `high`
+     */
+    pub fn infer(transform: &Transformation) -> Self {
+        match transform {
+            Transformation::Expression { .. } => Self::LOW,
+            Transformation::Udf { .. } => Self::MEDIUM,
+            Transformation::WindowAgg {
+                window, group_by, ..
+            } => {
+                if group_by.is_some()
+                    || window.map_or(false, |w| w > Duration::days(LONG_WINDOW_DAYS))
+                {
+                    Self::HIGH
+                } else {
+                    Self::MEDIUM
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for FeatureCost {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(Self::LOW),
+            "medium" => Ok(Self::MEDIUM),
+            "high" => Ok(Self::HIGH),
+            s => s
+                .parse::<u64>()
+                .ok()
+                .filter(|&units| units > 0)
+                .map(FeatureCost)
+                .ok_or_else(|| Error::InvalidOption(COST_TAG.to_string(), s.to_string())),
+        }
+    }
+}
+
+impl Display for FeatureCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::LOW => write!(f, "low"),
+            Self::MEDIUM => write!(f, "medium"),
+            Self::HIGH => write!(f, "high"),
+            Self(units) => write!(f, "{}", units),
+        }
+    }
+}
+
+/**
+ * Cost of a feature computed in a feature join
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureCostEstimate {
+    pub feature: String,
+    pub cost: FeatureCost,
+    /// The cost is taken from the `feathr_cost` tag instead of inferred from the transformation
+    pub annotated: bool,
+}
+
+impl FeatureCostEstimate {
+    /**
+     * The annotated cost if the tag is valid, otherwise the inferred one
+     */
+    pub(crate) fn new(
+        feature: &str,
+        tags: &HashMap<String, String>,
+        transform: &Transformation,
+    ) -> Self {
+        let annotated = tags.get(COST_TAG).and_then(|tag| match tag.parse() {
+            Ok(cost) => Some(cost),
+            Err(e) => {
+                warn!("Ignored the cost annotation of feature {}, {}", feature, e);
+                None
+            }
+        });
+        Self {
+            feature: feature.to_string(),
+            cost: annotated.unwrap_or_else(|| FeatureCost::infer(transform)),
+            annotated: annotated.is_some(),
+        }
+    }
+}
+
+/**
+ * Spark resources suggested for an expensive feature join
+ */
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SparkSizingHint {
+    pub cluster_size: ClusterSize,
+    pub shuffle_partitions: u64,
+}
+
+impl SparkSizingHint {
+    fn for_cost(total: u64, high: bool) -> Self {
+        let num_executors =
+            ((total + COST_PER_EXECUTOR - 1) / COST_PER_EXECUTOR).clamp(2, MAX_SUGGESTED_EXECUTORS);
+        // Window aggregations keep the whole window of each key in memory
+        let memory = if high { "8g" } else { "4g" };
+        let cluster_size = ClusterSize {
+            driver_cores: 4,
+            driver_memory: memory.to_string(),
+            executor_cores: 4,
+            executor_memory: memory.to_string(),
+            num_executors,
+        };
+        Self {
+            shuffle_partitions: num_executors * cluster_size.executor_cores * 2,
+            cluster_size,
+        }
+    }
+
+    /**
+     * The hint as Spark configurations
+     */
+    pub fn spark_conf(&self) -> Vec<(String, String)> {
+        vec![
+            (
+                "spark.driver.cores".to_string(),
+                self.cluster_size.driver_cores.to_string(),
+            ),
+            (
+                "spark.driver.memory".to_string(),
+                self.cluster_size.driver_memory.clone(),
+            ),
+            (
+                "spark.executor.cores".to_string(),
+                self.cluster_size.executor_cores.to_string(),
+            ),
+            (
+                "spark.executor.memory".to_string(),
+                self.cluster_size.executor_memory.clone(),
+            ),
+            (
+                "spark.executor.instances".to_string(),
+                self.cluster_size.num_executors.to_string(),
+            ),
+            (
+                "spark.sql.shuffle.partitions".to_string(),
+                self.shuffle_partitions.to_string(),
+            ),
+        ]
+    }
+}
+
+impl Display for SparkSizingHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} executors with {} cores and {} memory, {} shuffle partitions",
+            self.cluster_size.num_executors,
+            self.cluster_size.executor_cores,
+            self.cluster_size.executor_memory,
+            self.shuffle_partitions
+        )
+    }
+}
+
+/**
+ * Estimated cost of a feature join, the sum of the costs of the joined features and the inputs of
+ * the derived features, each feature is counted once
+ */
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryCost {
+    /// Sorted by cost, the most expensive first
+    pub features: Vec<FeatureCostEstimate>,
+    pub total: u64,
+    /// Set if the join is predicted to be expensive
+    pub sizing_hint: Option<SparkSizingHint>,
+}
+
+impl QueryCost {
+    pub(crate) fn new(mut features: Vec<FeatureCostEstimate>) -> Self {
+        features.sort_by(|a, b| b.cost.cmp(&a.cost).then_with(|| a.feature.cmp(&b.feature)));
+        let total = features.iter().map(|f| f.cost.0).sum();
+        let sizing_hint = (total > EXPENSIVE_QUERY_COST).then(|| {
+            SparkSizingHint::for_cost(total, features.iter().any(|f| f.cost >= FeatureCost::HIGH))
+        });
+        Self {
+            features,
+            total,
+            sizing_hint,
+        }
+    }
+
+    pub fn is_expensive(&self) -> bool {
+        self.sizing_hint.is_some()
+    }
+
+    /**
+     * Warnings of an expensive join, naming the most expensive features and the suggested
+     * resources, empty if the join is not expensive
+     */
+    pub fn warnings(&self) -> Vec<String> {
+        match &self.sizing_hint {
+            Some(hint) => {
+                let top = self
+                    .features
+                    .iter()
+                    .take(3)
+                    .map(|f| format!("{} ({})", f.feature, f.cost))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                vec![
+                    format!(
+                        "The feature join is predicted to be expensive, estimated cost {} exceeds {}, the most expensive features are {}",
+                        self.total, EXPENSIVE_QUERY_COST, top
+                    ),
+                    format!("Suggested Spark sizing: {}", hint),
+                ]
+            }
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Aggregation;
+
+    use super::*;
+
+    #[test]
+    fn test_query_cost() {
+        let agg = |days| {
+            Transformation::window_agg("amount", Aggregation::SUM, Duration::days(days)).unwrap()
+        };
+        assert_eq!(FeatureCost::infer(&agg(1)), FeatureCost::MEDIUM);
+        assert_eq!(FeatureCost::infer(&agg(30)), FeatureCost::HIGH);
+        assert_eq!(
+            FeatureCost::infer(&Transformation::Udf {
+                name: "f".to_string()
+            }),
+            FeatureCost::MEDIUM
+        );
+        assert_eq!("High".parse::<FeatureCost>().unwrap(), FeatureCost::HIGH);
+        assert_eq!("7".parse::<FeatureCost>().unwrap(), FeatureCost(7));
+        assert!("0".parse::<FeatureCost>().is_err());
+        assert!("huge".parse::<FeatureCost>().is_err());
+        let invalid: HashMap<String, String> = [(COST_TAG.to_string(), "huge".to_string())]
+            .into_iter()
+            .collect();
+        assert!(!FeatureCostEstimate::new("f", &invalid, &agg(1)).annotated);
+
+        let tags: HashMap<String, String> = [(COST_TAG.to_string(), "low".to_string())]
+            .into_iter()
+            .collect();
+        let annotated = FeatureCostEstimate::new("cheap", &tags, &agg(30));
+        assert_eq!(annotated.cost, FeatureCost::LOW);
+        assert!(annotated.annotated);
+
+        let cheap = QueryCost::new(vec![annotated.clone()]);
+        assert!(!cheap.is_expensive());
+        assert!(cheap.warnings().is_empty());
+
+        let features = (0..5)
+            .map(|i| FeatureCostEstimate::new(&format!("f{}", i), &Default::default(), &agg(30)))
+            .collect::<Vec<_>>();
+        let cost = QueryCost::new(features.into_iter().chain([annotated]).collect());
+        assert_eq!(cost.total, 81);
+        assert_eq!(cost.features.last().unwrap().feature, "cheap");
+        let hint = cost.sizing_hint.as_ref().unwrap();
+        assert_eq!(hint.cluster_size.num_executors, 6);
+        assert_eq!(hint.cluster_size.executor_memory, "8g");
+        assert_eq!(hint.shuffle_partitions, 48);
+        assert_eq!(cost.warnings().len(), 2);
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{feature::FeatureBase, SourceImpl, Transformation};
+use crate::{feature::FeatureBase, Ownership, SourceImpl, Transformation};
 
 use super::{EntityRef, FeatureTransformation, FeatureType, TypedKey};
 
@@ -83,7 +84,7 @@ impl From<crate::ValueType> for ValueType {
             crate::ValueType::UNSPECIFIED => Self::UNSPECIFIED,
             crate::ValueType::BOOL => Self::BOOL,
             crate::ValueType::INT32 => Self::INT32,
-            crate::ValueType::INT64 => Self::INT32,
+            crate::ValueType::INT64 => Self::INT64,
             crate::ValueType::FLOAT => Self::FLOAT,
             crate::ValueType::DOUBLE => Self::DOUBLE,
             crate::ValueType::STRING => Self::STRING,
@@ -117,6 +118,10 @@ pub struct ProjectAttributes {
     pub anchor_features: Vec<EntityRef>,
     pub derived_features: Vec<EntityRef>,
     pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub ownership: Ownership,
+    #[serde(default)]
+    pub default_tags: HashMap<String, String>,
 }
 
 impl TryInto<crate::project::FeathrProjectImpl> for (Uuid, u64, ProjectAttributes) {
@@ -135,6 +140,8 @@ impl TryInto<crate::project::FeathrProjectImpl> for (Uuid, u64, ProjectAttribute
             anchor_map: Default::default(),
             sources: Default::default(),
             registry_tags: self.2.tags,
+            default_tags: self.2.default_tags,
+            ownership: self.2.ownership,
         })
     }
 }
@@ -152,6 +159,8 @@ pub struct SourceAttributes {
     pub event_timestamp_column: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamp_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_verified: Option<DateTime<Utc>>,
     #[serde(rename = "type")]
     pub type_: String,
     pub tags: HashMap<String, String>,
@@ -161,15 +170,16 @@ impl TryInto<crate::source::SourceImpl> for (Uuid, u64, SourceAttributes) {
     type Error = crate::Error;
 
     fn try_into(self) -> Result<crate::source::SourceImpl, Self::Error> {
-        Ok(if self.2.name == "PASSTHROUGH" {
+        Ok(if self.2.name == "PASSTHROUGH" || self.2.type_ == "PASSTHROUGH" {
             SourceImpl {
                 id: self.0,
-                version: 1,
+                version: self.1,
                 name: self.2.name,
                 location: crate::DataLocation::InputContext,
                 time_window_parameters: None,
-                preprocessing: None,
-                registry_tags: Default::default(),
+                time_partition: None,
+                preprocessing: self.2.preprocessing,
+                registry_tags: self.2.tags,
             }
         } else {
             match self.2.type_.to_lowercase().as_str() {
@@ -186,24 +196,25 @@ impl TryInto<crate::source::SourceImpl> for (Uuid, u64, SourceAttributes) {
                             .to_owned(),
                         dbtable: self.2.options.get("dbtable").cloned(),
                         query: self.2.options.get("query").cloned(),
-                        auth: match self.2.options.get("auth") {
-                            Some(auth) => match auth.as_str().to_lowercase().as_str() {
-                                "userpass" => crate::JdbcAuth::Userpass {
-                                    user: format!("${{{}_USER}}", self.2.name),
-                                    password: format!("${{{}_PASSWORD}}", self.2.name),
-                                },
-                                "token" => crate::JdbcAuth::Token {
-                                    token: format!("${{{}_TOKEN}}", self.2.name),
+                        auth: crate::JdbcAuth::from_env_prefix(
+                            match self.2.options.get("auth") {
+                                Some(auth) => match auth.as_str().to_lowercase().as_str() {
+                                    "userpass" => crate::JdbcSourceAuth::Userpass,
+                                    "token" => crate::JdbcSourceAuth::Token,
+                                    _ => {
+                                        return Err(crate::Error::InvalidOption(
+                                            "auth".to_string(),
+                                            auth.to_owned(),
+                                        ))
+                                    }
                                 },
-                                _ => {
-                                    return Err(crate::Error::InvalidOption(
-                                        "auth".to_string(),
-                                        auth.to_owned(),
-                                    ))
-                                }
+                                None => crate::JdbcSourceAuth::Anonymous,
                             },
-                            None => crate::JdbcAuth::Anonymous,
-                        },
+                            self.2
+                                .options
+                                .get("credential_prefix")
+                                .unwrap_or(&self.2.name),
+                        ),
                     },
                     time_window_parameters: self.2.event_timestamp_column.map(|c| {
                         crate::TimeWindowParameters {
@@ -211,6 +222,7 @@ impl TryInto<crate::source::SourceImpl> for (Uuid, u64, SourceAttributes) {
                             timestamp_column_format: self.2.timestamp_format.unwrap_or_default(),
                         }
                     }),
+                    time_partition: None,
                     preprocessing: self.2.preprocessing,
                     registry_tags: self.2.tags,
                 },
@@ -227,7 +239,14 @@ impl TryInto<crate::source::SourceImpl> for (Uuid, u64, SourceAttributes) {
                             .ok_or(crate::Error::MissingOption("format".to_string()))?
                             .to_owned(),
                         mode: self.2.options.get("mode").cloned(),
-                        options: self.2.options.clone(),
+                        // `format` and `mode` are stored along with other options in the registry
+                        options: self
+                            .2
+                            .options
+                            .iter()
+                            .filter(|(k, _)| k.as_str() != "format" && k.as_str() != "mode")
+                            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                            .collect(),
                     },
                     time_window_parameters: self.2.event_timestamp_column.map(|c| {
                         crate::TimeWindowParameters {
@@ -235,6 +254,7 @@ impl TryInto<crate::source::SourceImpl> for (Uuid, u64, SourceAttributes) {
                             timestamp_column_format: self.2.timestamp_format.unwrap_or_default(),
                         }
                     }),
+                    time_partition: None,
                     preprocessing: self.2.preprocessing,
                     registry_tags: self.2.tags,
                 },
@@ -256,6 +276,72 @@ impl TryInto<crate::source::SourceImpl> for (Uuid, u64, SourceAttributes) {
                             timestamp_column_format: self.2.timestamp_format.unwrap_or_default(),
                         }
                     }),
+                    time_partition: self
+                        .2
+                        .options
+                        .get("time_partition_pattern")
+                        .map(|pattern| crate::TimePartition {
+                            pattern: pattern.to_owned(),
+                            start: self.2.options.get("time_partition_start").cloned(),
+                            end: self.2.options.get("time_partition_end").cloned(),
+                        }),
+                    preprocessing: self.2.preprocessing,
+                    registry_tags: self.2.tags,
+                },
+                "kafka" => SourceImpl {
+                    id: self.0,
+                    version: self.1,
+                    name: self.2.name,
+                    location: crate::DataLocation::Kafka {
+                        brokers: split_list(
+                            self.2
+                                .options
+                                .get("brokers")
+                                .ok_or(crate::Error::MissingOption("brokers".to_string()))?,
+                        ),
+                        topics: split_list(
+                            self.2
+                                .options
+                                .get("topics")
+                                .ok_or(crate::Error::MissingOption("topics".to_string()))?,
+                        ),
+                        schema: crate::KafkaSchema {
+                            type_: "KAFKA".to_string(),
+                            avro_json: self
+                                .2
+                                .options
+                                .get("avro_json")
+                                .cloned()
+                                .unwrap_or_default(),
+                        },
+                        consumer_group: self.2.options.get("consumer_group").cloned(),
+                        sasl: match (
+                            self.2.options.get("security_protocol"),
+                            self.2.options.get("sasl_mechanism"),
+                            self.2.options.get("sasl_jaas_config"),
+                        ) {
+                            (Some(protocol), Some(mechanism), Some(jaas_config)) => {
+                                Some(crate::KafkaSasl {
+                                    security_protocol: protocol.to_owned(),
+                                    mechanism: mechanism.to_owned(),
+                                    jaas_config: jaas_config.to_owned(),
+                                    token_endpoint_url: self
+                                        .2
+                                        .options
+                                        .get("sasl_token_endpoint_url")
+                                        .cloned(),
+                                    login_callback_handler: self
+                                        .2
+                                        .options
+                                        .get("sasl_login_callback_handler")
+                                        .cloned(),
+                                })
+                            }
+                            _ => None,
+                        },
+                    },
+                    time_window_parameters: None,
+                    time_partition: None,
                     preprocessing: self.2.preprocessing,
                     registry_tags: self.2.tags,
                 },
@@ -270,6 +356,13 @@ impl TryInto<crate::source::SourceImpl> for (Uuid, u64, SourceAttributes) {
     }
 }
 
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AnchorAttributes {
@@ -278,6 +371,14 @@ pub struct AnchorAttributes {
     pub features: Vec<EntityRef>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<EntityRef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub joined_sources: Vec<EntityRef>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub join_keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_window: Option<String>,
     pub tags: HashMap<String, String>,
 }
 
@@ -286,11 +387,21 @@ impl TryInto<crate::project::AnchorGroupImpl> for (Uuid, u64, AnchorAttributes)
 
     fn try_into(self) -> Result<crate::project::AnchorGroupImpl, Self::Error> {
         // Generated AnchorGroupImpl only contains base attributes, without contained features.
+        // Sources are resolved later with the project lineage.
         Ok(crate::project::AnchorGroupImpl {
             id: self.0,
             version: self.1,
             name: self.2.name,
             source: Default::default(),
+            joined_sources: Default::default(),
+            join_keys: self.2.join_keys,
+            preprocessing: self.2.preprocessing,
+            default_window: self
+                .2
+                .default_window
+                .as_deref()
+                .map(crate::utils::str_to_dur)
+                .transpose()?,
             registry_tags: self.2.tags,
         })
     }
@@ -306,6 +417,8 @@ pub struct AnchorFeatureAttributes {
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
     pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub ownership: Ownership,
 }
 
 impl TryInto<crate::feature::AnchorFeatureImpl> for (Uuid, u64, AnchorFeatureAttributes) {
@@ -313,15 +426,19 @@ impl TryInto<crate::feature::AnchorFeatureImpl> for (Uuid, u64, AnchorFeatureAtt
 
     fn try_into(self) -> Result<crate::feature::AnchorFeatureImpl, Self::Error> {
         let key: Vec<crate::TypedKey> = self.2.key.into_iter().map(|k| k.into()).collect();
-        let key_alias = key
-            .iter()
-            .map(|k| {
-                k.key_column_alias
-                    .as_ref()
-                    .unwrap_or(&k.key_column)
-                    .to_owned()
-            })
-            .collect();
+        // Anchor features without explicit keys are registered with the dummy key but have no key alias
+        let key_alias = if key == vec![crate::TypedKey::DUMMY_KEY()] {
+            vec![]
+        } else {
+            key.iter()
+                .map(|k| {
+                    k.key_column_alias
+                        .as_ref()
+                        .unwrap_or(&k.key_column)
+                        .to_owned()
+                })
+                .collect()
+        };
         Ok(crate::feature::AnchorFeatureImpl {
             base: FeatureBase {
                 id: self.0,
@@ -331,6 +448,7 @@ impl TryInto<crate::feature::AnchorFeatureImpl> for (Uuid, u64, AnchorFeatureAtt
                 key,
                 feature_alias: self.2.name,
                 registry_tags: self.2.tags,
+                ownership: self.2.ownership,
             },
             key_alias,
             transform: self.2.transformation.try_into()?,
@@ -350,6 +468,8 @@ pub struct DerivedFeatureAttributes {
     pub input_anchor_features: Vec<EntityRef>,
     pub input_derived_features: Vec<EntityRef>,
     pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub ownership: Ownership,
 }
 
 impl TryInto<crate::feature::DerivedFeatureImpl> for (Uuid, u64, DerivedFeatureAttributes) {
@@ -376,6 +496,7 @@ impl TryInto<crate::feature::DerivedFeatureImpl> for (Uuid, u64, DerivedFeatureA
                 key,
                 feature_alias: self.2.name,
                 registry_tags: self.2.tags,
+                ownership: self.2.ownership,
             },
             key_alias,
             transform: t.into(),
@@ -384,6 +505,30 @@ impl TryInto<crate::feature::DerivedFeatureImpl> for (Uuid, u64, DerivedFeatureA
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializationRecordAttributes {
+    pub qualified_name: String,
+    pub name: String,
+    pub sink: String,
+    pub features: Vec<EntityRef>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub job_id: String,
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetAttributes {
+    pub qualified_name: String,
+    pub name: String,
+    pub path: String,
+    pub features: Vec<EntityRef>,
+    pub job_id: String,
+    pub tags: HashMap<String, String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "typeName", content = "attributes")]
 pub enum EntityAttributes {
@@ -397,6 +542,10 @@ pub enum EntityAttributes {
     AnchorFeature(AnchorFeatureAttributes),
     #[serde(rename = "feathr_derived_feature_v1")]
     DerivedFeature(DerivedFeatureAttributes),
+    #[serde(rename = "feathr_materialization_record_v1")]
+    MaterializationRecord(MaterializationRecordAttributes),
+    #[serde(rename = "feathr_dataset_v1")]
+    Dataset(DatasetAttributes),
 }
 
 impl EntityAttributes {
@@ -407,6 +556,8 @@ impl EntityAttributes {
             EntityAttributes::Anchor(a) => a.name.clone(),
             EntityAttributes::AnchorFeature(a) => a.name.clone(),
             EntityAttributes::DerivedFeature(d) => d.name.clone(),
+            EntityAttributes::MaterializationRecord(m) => m.name.clone(),
+            EntityAttributes::Dataset(d) => d.name.clone(),
         }
     }
 
@@ -417,6 +568,8 @@ impl EntityAttributes {
             EntityAttributes::Anchor(a) => a.qualified_name.clone(),
             EntityAttributes::AnchorFeature(a) => a.qualified_name.clone(),
             EntityAttributes::DerivedFeature(d) => d.qualified_name.clone(),
+            EntityAttributes::MaterializationRecord(m) => m.qualified_name.clone(),
+            EntityAttributes::Dataset(d) => d.qualified_name.clone(),
         }
     }
 }
@@ -7,6 +7,9 @@ pub enum EdgeType {
     Contains,
     Consumes,
     Produces,
+    ConsumedBy,
+    DependsOn,
+    SimilarTo,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
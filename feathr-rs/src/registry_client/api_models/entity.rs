@@ -5,7 +5,7 @@ use uuid::Uuid;
 
 use crate::Error;
 
-use super::{EntityAttributes, Relationship};
+use super::{EdgeType, EntityAttributes, Relationship};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EntityType {
@@ -22,6 +22,10 @@ pub enum EntityType {
     AnchorFeature,
     #[serde(rename = "feathr_derived_feature_v1")]
     DerivedFeature,
+    #[serde(rename = "feathr_materialization_record_v1")]
+    MaterializationRecord,
+    #[serde(rename = "feathr_dataset_v1")]
+    Dataset,
 }
 
 fn default_version() -> u64 {
@@ -55,6 +59,8 @@ impl Entity {
             EntityAttributes::Anchor(_) => EntityType::Anchor,
             EntityAttributes::AnchorFeature(_) => EntityType::AnchorFeature,
             EntityAttributes::DerivedFeature(_) => EntityType::DerivedFeature,
+            EntityAttributes::MaterializationRecord(_) => EntityType::MaterializationRecord,
+            EntityAttributes::Dataset(_) => EntityType::Dataset,
         }
     }
 
@@ -175,6 +181,12 @@ pub struct EntityRef {
     unique_attributes: UniqueAttributes,
 }
 
+impl EntityRef {
+    pub fn get_id(&self) -> Uuid {
+        self.guid
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EntityLineage {
@@ -182,3 +194,21 @@ pub struct EntityLineage {
     pub guid_entity_map: HashMap<Uuid, Entity>,
     pub relations: Vec<Relationship>,
 }
+
+impl EntityLineage {
+    /**
+     * Get entities of `target_type` that the entity `from` connects to with `edge_type`
+     */
+    pub fn get_targets(
+        &self,
+        from: Uuid,
+        edge_type: EdgeType,
+        target_type: EntityType,
+    ) -> impl Iterator<Item = (Uuid, &Entity)> + '_ {
+        self.relations
+            .iter()
+            .filter(move |r| r.edge_type == edge_type && r.from == from)
+            .filter_map(|r| self.guid_entity_map.get(&r.to).map(|e| (r.to, e)))
+            .filter(move |(_, e)| e.get_entity_type() == target_type)
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/**
+ * Code of an error returned by the registry, codes added by newer registries are `Unknown`
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RegistryErrorCode {
+    EntityNotFound,
+    WrongEntityType,
+    EntityExists,
+    EntityInUse,
+    InvalidDefinition,
+    InvalidEdge,
+    BadRequest,
+    Conflict,
+    CredentialNotFound,
+    PermissionDenied,
+    SemanticSearchDisabled,
+    SearchError,
+    EmbeddingError,
+    StorageError,
+    InternalError,
+    #[serde(other)]
+    Unknown,
+}
+
+/**
+ * JSON body of the error responses of the registry
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: RegistryErrorCode,
+    pub message: String,
+}
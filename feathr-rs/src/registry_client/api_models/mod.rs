@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -7,16 +8,18 @@ use crate::{
     feature::{AnchorFeatureImpl, DerivedFeatureImpl},
     project::AnchorGroupImpl,
     utils::{dur_to_string, str_to_dur},
-    Error, SourceImpl,
+    Error, Ownership, SourceImpl,
 };
 
 mod attributes;
 mod edge;
 mod entity;
+mod error;
 
 pub use attributes::*;
 pub use edge::*;
 pub use entity::*;
+pub use error::*;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,6 +27,10 @@ pub struct ProjectDef {
     pub name: String,
     #[serde(default)]
     pub tags: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Ownership::is_empty")]
+    pub ownership: Ownership,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub default_tags: HashMap<String, String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -53,6 +60,15 @@ impl From<SourceImpl> for SourceDef {
             crate::DataLocation::Hdfs { path } => ("hdfs", {
                 let mut options = HashMap::new();
                 options.insert("path".to_string(), path);
+                if let Some(partition) = s.time_partition {
+                    options.insert("time_partition_pattern".to_string(), partition.pattern);
+                    if let Some(start) = partition.start {
+                        options.insert("time_partition_start".to_string(), start);
+                    }
+                    if let Some(end) = partition.end {
+                        options.insert("time_partition_end".to_string(), end);
+                    }
+                }
                 options
             }),
             crate::DataLocation::Jdbc {
@@ -70,6 +86,12 @@ impl From<SourceImpl> for SourceDef {
                 if let Some(query) = query {
                     options.insert("query".to_string(), query);
                 }
+                // Only kept if the credentials are not the ones named after the source
+                if let Some(prefix) = auth.env_prefix() {
+                    if prefix != crate::source::env_var_name(&s.name) {
+                        options.insert("credential_prefix".to_string(), prefix);
+                    }
+                }
                 match auth {
                     crate::JdbcAuth::Userpass { .. } => {
                         options.insert("auth".to_string(), "userpass".to_string());
@@ -93,7 +115,33 @@ impl From<SourceImpl> for SourceDef {
                 }
                 ("generic", options)
             }
-            _ => todo!(),
+            crate::DataLocation::Kafka {
+                brokers,
+                topics,
+                schema,
+                consumer_group,
+                sasl,
+            } => {
+                let mut options = HashMap::new();
+                options.insert("brokers".to_string(), brokers.join(","));
+                options.insert("topics".to_string(), topics.join(","));
+                options.insert("avro_json".to_string(), schema.avro_json);
+                if let Some(group) = consumer_group {
+                    options.insert("consumer_group".to_string(), group);
+                }
+                if let Some(sasl) = sasl {
+                    options.insert("security_protocol".to_string(), sasl.security_protocol);
+                    options.insert("sasl_mechanism".to_string(), sasl.mechanism);
+                    options.insert("sasl_jaas_config".to_string(), sasl.jaas_config);
+                    if let Some(url) = sasl.token_endpoint_url {
+                        options.insert("sasl_token_endpoint_url".to_string(), url);
+                    }
+                    if let Some(handler) = sasl.login_callback_handler {
+                        options.insert("sasl_login_callback_handler".to_string(), handler);
+                    }
+                }
+                ("kafka", options)
+            }
         };
         Self {
             name: s.name,
@@ -112,6 +160,14 @@ impl From<SourceImpl> for SourceDef {
 pub struct AnchorDef {
     pub name: String,
     pub source_id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub join_keys: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preprocessing: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_window: Option<String>,
     #[serde(default)]
     pub tags: HashMap<String, String>,
 }
@@ -121,6 +177,14 @@ impl From<AnchorGroupImpl> for AnchorDef {
         Self {
             name: g.name,
             source_id: g.source.inner.id.to_string(),
+            source_ids: g
+                .joined_sources
+                .iter()
+                .map(|s| s.inner.id.to_string())
+                .collect(),
+            join_keys: g.join_keys,
+            preprocessing: g.preprocessing,
+            default_window: g.default_window.map(dur_to_string),
             tags: g.registry_tags,
         }
     }
@@ -349,6 +413,8 @@ pub struct AnchorFeatureDef {
     pub key: Vec<TypedKey>,
     #[serde(default)]
     pub tags: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Ownership::is_empty")]
+    pub ownership: Ownership,
 }
 
 impl From<AnchorFeatureImpl> for AnchorFeatureDef {
@@ -359,6 +425,7 @@ impl From<AnchorFeatureImpl> for AnchorFeatureDef {
             transformation: f.transform.into(),
             key: f.base.key.into_iter().map(Into::into).collect(),
             tags: f.base.registry_tags,
+            ownership: f.base.ownership,
         }
     }
 }
@@ -374,6 +441,8 @@ pub struct DerivedFeatureDef {
     pub input_derived_features: Vec<Uuid>,
     #[serde(default)]
     pub tags: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Ownership::is_empty")]
+    pub ownership: Ownership,
 }
 
 impl From<DerivedFeatureImpl> for DerivedFeatureDef {
@@ -396,10 +465,35 @@ impl From<DerivedFeatureImpl> for DerivedFeatureDef {
                 .map(|(_, f)| f.id)
                 .collect(),
             tags: f.base.registry_tags,
+            ownership: f.base.ownership,
         }
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializationRecordDef {
+    pub name: String,
+    pub sink: String,
+    pub features: Vec<Uuid>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub job_id: String,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetDef {
+    pub name: String,
+    pub path: String,
+    pub features: Vec<Uuid>,
+    pub job_id: String,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+}
+
 fn default_version() -> u64 {
     1
 }
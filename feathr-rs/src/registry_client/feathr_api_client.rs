@@ -3,17 +3,19 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use azure_core::auth::TokenCredential;
 use azure_identity::{DefaultAzureCredential, DefaultAzureCredentialBuilder};
-use log::debug;
-use reqwest::RequestBuilder;
+use log::{debug, warn};
+use reqwest::{Method, RequestBuilder, Response};
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{Error, FeatureRegistry, VarSource};
 
 use super::api_models::{self, CreationResponse};
+use super::routing::{is_unavailable, EndpointRouter, OPT_SEQ_HEADER};
 
 #[derive(Clone)]
 pub struct FeathrApiClient {
-    registry_endpoint: String,
+    router: Arc<EndpointRouter>,
     client: reqwest::Client,
     version: usize,
     credential: Option<Arc<DefaultAzureCredential>>,
@@ -22,7 +24,7 @@ pub struct FeathrApiClient {
 impl std::fmt::Debug for FeathrApiClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FeathrApiClient")
-            .field("registry_endpoint", &self.registry_endpoint)
+            .field("registry_endpoints", &self.router.endpoints())
             .field("client", &self.client)
             .field("version", &self.version)
             .finish()
@@ -31,8 +33,24 @@ impl std::fmt::Debug for FeathrApiClient {
 
 impl FeathrApiClient {
     pub fn new(registry_url: &str, version: usize, auth: bool) -> Self {
+        Self::new_cluster(&[registry_url], None, version, auth)
+    }
+
+    /**
+     * Create Api Client of a registry cluster, `management_code` is needed to discover the leader
+     * if the cluster is protected by one
+     */
+    pub fn new_cluster(
+        registry_urls: &[&str],
+        management_code: Option<&str>,
+        version: usize,
+        auth: bool,
+    ) -> Self {
         Self {
-            registry_endpoint: registry_url.to_string(),
+            router: Arc::new(EndpointRouter::new(
+                registry_urls,
+                management_code.map(ToString::to_string),
+            )),
             client: Default::default(),
             version,
             credential: if auth {
@@ -46,8 +64,10 @@ impl FeathrApiClient {
             },
         }
     }
+
     /**
-     * Create Api Client from a VarSource
+     * Create Api Client from a VarSource, `feature_registry.api_endpoints` lists all nodes of a
+     * registry cluster and takes precedence over `feature_registry.api_endpoint`
      */
     pub async fn from_var_source(
         var_source: Arc<dyn VarSource + Send + Sync>,
@@ -58,27 +78,40 @@ impl FeathrApiClient {
             .unwrap_or("true".to_string())
             .parse()
             .map_err(|e| crate::Error::InvalidConfig(format!("Invalid api_version, {}", e)))?;
-        Ok(Self {
-            registry_endpoint: var_source
-                .get_environment_variable(&["feature_registry", "api_endpoint"])
-                .await?,
-            client: Default::default(),
-            version: var_source
-                .get_environment_variable(&["feature_registry", "api_version"])
-                .await
-                .unwrap_or("1".to_string())
-                .parse()
-                .map_err(|e| crate::Error::InvalidConfig(format!("Invalid api_version, {}", e)))?,
-            credential: if auth {
-                Some(Arc::new(
-                    DefaultAzureCredentialBuilder::new()
-                        .exclude_managed_identity_credential()
-                        .build(),
-                ))
-            } else {
-                None
-            },
-        })
+        let endpoints: Vec<String> = match var_source
+            .get_environment_variable(&["feature_registry", "api_endpoints"])
+            .await
+        {
+            // Environment variables are comma separated
+            Ok(s) => serde_yaml::from_str(&s)
+                .unwrap_or_else(|_| s.split(',').map(|e| e.trim().to_string()).collect()),
+            Err(_) => vec![
+                var_source
+                    .get_environment_variable(&["feature_registry", "api_endpoint"])
+                    .await?,
+            ],
+        };
+        if endpoints.iter().all(|e| e.trim().is_empty()) {
+            return Err(crate::Error::MissingOption(
+                "feature_registry.api_endpoint".to_string(),
+            ));
+        }
+        let management_code = var_source
+            .get_environment_variable(&["feature_registry", "management_code"])
+            .await
+            .ok();
+        let version = var_source
+            .get_environment_variable(&["feature_registry", "api_version"])
+            .await
+            .unwrap_or("1".to_string())
+            .parse()
+            .map_err(|e| crate::Error::InvalidConfig(format!("Invalid api_version, {}", e)))?;
+        Ok(Self::new_cluster(
+            &endpoints.iter().map(String::as_str).collect::<Vec<_>>(),
+            management_code.as_deref(),
+            version,
+            auth,
+        ))
     }
 
     async fn auth(&self, builder: RequestBuilder) -> Result<RequestBuilder, Error> {
@@ -98,143 +131,317 @@ impl FeathrApiClient {
             builder
         })
     }
+
+    /**
+     * Send the request to the nodes picked by the router until one of them is available, reads
+     * carry the latest sequence seen by this client so they're not served from a stale follower
+     */
+    async fn send<F>(&self, method: Method, path: &str, build: F) -> Result<Response, Error>
+    where
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        let write = method != Method::GET;
+        if write && !self.router.is_leader_known() {
+            self.router.discover_leader(&self.client).await;
+        }
+        let mut last_error = None;
+        for idx in self.router.candidates(write) {
+            let url = format!("{}{}", self.router.endpoint(idx), path);
+            debug!("URL: {}", url);
+            let mut builder = self.auth(self.client.request(method.clone(), url)).await?;
+            if let (false, Some(seq)) = (write, self.router.opt_seq()) {
+                builder = builder.header(OPT_SEQ_HEADER, seq);
+            }
+            match build(builder).send().await {
+                Ok(resp) if !is_unavailable(write, resp.status()) => {
+                    self.router.observe(&resp);
+                    return registry_error_for_status(resp).await;
+                }
+                Ok(resp) => {
+                    warn!(
+                        "Registry node {} is unavailable, status: {}",
+                        self.router.endpoint(idx),
+                        resp.status()
+                    );
+                    last_error = resp.error_for_status().err();
+                }
+                // Writes may have been applied if the request timed out, don't send them again
+                Err(e) if e.is_connect() || (!write && e.is_timeout()) => {
+                    warn!(
+                        "Registry node {} is unreachable, {}",
+                        self.router.endpoint(idx),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+            if write {
+                self.router.reset_leader();
+            }
+        }
+        Err(last_error
+            .map(Error::from)
+            .unwrap_or_else(|| Error::MissingOption("feature_registry.api_endpoint".to_string())))
+    }
+}
+
+/**
+ * Turn error responses into `Error::RegistryApiError` with the error code in the response body,
+ * registries not returning JSON error bodies get `Error::RegistryHttpError`
+ */
+async fn registry_error_for_status(resp: Response) -> Result<Response, Error> {
+    if resp.status().is_client_error() || resp.status().is_server_error() {
+        let url = resp.url().to_string();
+        let status = resp.status().to_string();
+        let text = resp.text().await?;
+        Err(match serde_json::from_str::<api_models::ErrorBody>(&text) {
+            Ok(body) => Error::RegistryApiError(body.code, body.message),
+            Err(_) => Error::RegistryHttpError(url, status, text),
+        })
+    } else {
+        Ok(resp)
+    }
 }
 
 #[allow(unused_variables)]
 #[async_trait]
 impl FeatureRegistry for FeathrApiClient {
-    async fn load_project(&self, name: &str) -> Result<api_models::EntityLineage, Error> {
-        let url = match self.version {
-            1 => format!("{}/projects/{}", self.registry_endpoint, name),
-            2 => format!("{}/projects/{}/lineage", self.registry_endpoint, name),
+    #[instrument(skip_all, fields(registry.request = "load_project", project = name))]
+    async fn load_project(
+        &self,
+        name: &str,
+        release: Option<&str>,
+    ) -> Result<api_models::EntityLineage, Error> {
+        let path = match (self.version, release) {
+            (1, None) => format!("/projects/{}", name),
+            (1, Some(_)) => Err(crate::Error::InvalidArgument(
+                "Releases are only supported by api_version 2".to_string(),
+            ))?,
+            (2, _) => format!("/projects/{}/lineage", name),
             _ => Err(crate::Error::InvalidConfig(format!(
                 "Unsupported api_version {}",
                 self.version
             )))?,
         };
-        debug!("URL: {}", url);
         Ok(self
-            .auth(self.client.get(url))
+            .send(Method::GET, &path, |b| match release {
+                Some(release) => b.query(&[("release", release)]),
+                None => b,
+            })
             .await?
-            .send()
-            .await?
-            .error_for_status()?
             .json()
             .await?)
     }
 
+    #[instrument(skip_all, fields(registry.request = "get_project_codegen", project = name))]
+    async fn get_project_codegen(&self, name: &str, lang: &str) -> Result<String, Error> {
+        if self.version != 2 {
+            return Err(crate::Error::InvalidArgument(
+                "Code generation is only supported by api_version 2".to_string(),
+            ));
+        }
+        let path = format!("/projects/{}/codegen", name);
+        Ok(self
+            .send(Method::GET, &path, |b| b.query(&[("lang", lang)]))
+            .await?
+            .text()
+            .await?)
+    }
+
+    #[instrument(skip_all, fields(registry.request = "get_feature", feature = id_or_name))]
+    async fn get_feature(&self, id_or_name: &str) -> Result<api_models::Entity, Error> {
+        let path = format!("/features/{}", id_or_name);
+        Ok(self.send(Method::GET, &path, |b| b).await?.json().await?)
+    }
+
+    #[instrument(skip_all, fields(registry.request = "new_project", project = %definition.name))]
     async fn new_project(&self, definition: api_models::ProjectDef) -> Result<(Uuid, u64), Error> {
-        let url = format!("{}/projects", self.registry_endpoint);
         debug!(
             "ProjectDef: {}",
             serde_json::to_string(&definition).unwrap()
         );
         let r: CreationResponse = self
-            .auth(self.client.post(url))
-            .await?
-            .json(&definition)
-            .send()
+            .send(Method::POST, "/projects", |b| b.json(&definition))
             .await?
-            .error_for_status()?
             .json()
             .await?;
         debug!("Entity created, id: {}", r.guid);
         Ok((r.guid, r.version))
     }
 
+    #[instrument(skip_all, fields(registry.request = "new_source", project.id = %project_id))]
     async fn new_source(
         &self,
         project_id: Uuid,
         definition: api_models::SourceDef,
     ) -> Result<(Uuid, u64), Error> {
-        let url = format!(
-            "{}/projects/{}/datasources",
-            self.registry_endpoint, project_id
-        );
+        let path = format!("/projects/{}/datasources", project_id);
         debug!("SourceDef: {}", serde_json::to_string(&definition).unwrap());
         let r: CreationResponse = self
-            .auth(self.client.post(url))
+            .send(Method::POST, &path, |b| b.json(&definition))
             .await?
-            .json(&definition)
-            .send()
-            .await?
-            .error_for_status()?
             .json()
             .await?;
         debug!("Entity created, id: {}", r.guid);
         Ok((r.guid, r.version))
     }
 
+    #[instrument(skip_all, fields(registry.request = "new_anchor", project.id = %project_id))]
     async fn new_anchor(
         &self,
         project_id: Uuid,
         definition: api_models::AnchorDef,
     ) -> Result<(Uuid, u64), Error> {
-        let url = format!("{}/projects/{}/anchors", self.registry_endpoint, project_id);
+        let path = format!("/projects/{}/anchors", project_id);
         debug!("AnchorDef: {}", serde_json::to_string(&definition).unwrap());
         let r: CreationResponse = self
-            .auth(self.client.post(url))
-            .await?
-            .json(&definition)
-            .send()
+            .send(Method::POST, &path, |b| b.json(&definition))
             .await?
-            .error_for_status()?
             .json()
             .await?;
         debug!("Entity created, id: {}", r.guid);
         Ok((r.guid, r.version))
     }
 
+    #[instrument(skip_all, fields(registry.request = "new_anchor_feature", project.id = %project_id))]
     async fn new_anchor_feature(
         &self,
         project_id: Uuid,
         anchor_id: Uuid,
         definition: api_models::AnchorFeatureDef,
     ) -> Result<(Uuid, u64), Error> {
-        let url = format!(
-            "{}/projects/{}/anchors/{}/features",
-            self.registry_endpoint, project_id, anchor_id
-        );
+        let path = format!("/projects/{}/anchors/{}/features", project_id, anchor_id);
         debug!(
             "AnchorFeatureDef: {}",
             serde_json::to_string(&definition).unwrap()
         );
         let r: CreationResponse = self
-            .auth(self.client.post(url))
-            .await?
-            .json(&definition)
-            .send()
+            .send(Method::POST, &path, |b| b.json(&definition))
             .await?
-            .error_for_status()?
             .json()
             .await?;
         debug!("Entity created, id: {}", r.guid);
         Ok((r.guid, r.version))
     }
 
+    #[instrument(skip_all, fields(registry.request = "new_derived_feature", project.id = %project_id))]
     async fn new_derived_feature(
         &self,
         project_id: Uuid,
         definition: api_models::DerivedFeatureDef,
     ) -> Result<(Uuid, u64), Error> {
-        let url = format!(
-            "{}/projects/{}/derivedfeatures",
-            self.registry_endpoint, project_id
-        );
+        let path = format!("/projects/{}/derivedfeatures", project_id);
         debug!(
             "DerivedFeatureDef: {}",
             serde_json::to_string(&definition).unwrap()
         );
         let r: CreationResponse = self
-            .auth(self.client.post(url))
+            .send(Method::POST, &path, |b| b.json(&definition))
             .await?
-            .json(&definition)
-            .send()
+            .json()
+            .await?;
+        debug!("Entity created, id: {}", r.guid);
+        Ok((r.guid, r.version))
+    }
+
+    #[instrument(skip_all, fields(registry.request = "new_materialization_record", project.id = %project_id))]
+    async fn new_materialization_record(
+        &self,
+        project_id: Uuid,
+        definition: api_models::MaterializationRecordDef,
+    ) -> Result<(Uuid, u64), Error> {
+        let path = format!("/projects/{}/materializations", project_id);
+        debug!(
+            "MaterializationRecordDef: {}",
+            serde_json::to_string(&definition).unwrap()
+        );
+        let r: CreationResponse = self
+            .send(Method::POST, &path, |b| b.json(&definition))
             .await?
-            .error_for_status()?
             .json()
             .await?;
         debug!("Entity created, id: {}", r.guid);
         Ok((r.guid, r.version))
     }
+
+    #[instrument(skip_all, fields(registry.request = "new_dataset", project.id = %project_id))]
+    async fn new_dataset(
+        &self,
+        project_id: Uuid,
+        definition: api_models::DatasetDef,
+    ) -> Result<(Uuid, u64), Error> {
+        let path = format!("/projects/{}/datasets", project_id);
+        debug!(
+            "DatasetDef: {}",
+            serde_json::to_string(&definition).unwrap()
+        );
+        let r: CreationResponse = self
+            .send(Method::POST, &path, |b| b.json(&definition))
+            .await?
+            .json()
+            .await?;
+        debug!("Entity created, id: {}", r.guid);
+        Ok((r.guid, r.version))
+    }
+
+    #[instrument(skip_all, fields(registry.request = "set_project_ownership", project.id = %project_id))]
+    async fn set_project_ownership(
+        &self,
+        project_id: Uuid,
+        ownership: crate::Ownership,
+    ) -> Result<(), Error> {
+        let path = format!("/projects/{}/ownership", project_id);
+        debug!("Ownership: {}", serde_json::to_string(&ownership).unwrap());
+        self.send(Method::PUT, &path, |b| b.json(&ownership))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(registry.request = "set_feature_ownership", feature.id = %feature_id))]
+    async fn set_feature_ownership(
+        &self,
+        feature_id: Uuid,
+        ownership: crate::Ownership,
+    ) -> Result<(), Error> {
+        let path = format!("/features/{}/ownership", feature_id);
+        debug!("Ownership: {}", serde_json::to_string(&ownership).unwrap());
+        self.send(Method::PUT, &path, |b| b.json(&ownership))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(registry.request = "set_source_verified", project.id = %project_id))]
+    async fn set_source_verified(&self, project_id: Uuid, source_id: Uuid) -> Result<(), Error> {
+        let path = format!(
+            "/projects/{}/datasources/{}/verified",
+            project_id, source_id
+        );
+        self.send(Method::PUT, &path, |b| b).await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(registry.request = "get_entity_docs", entity.id = %entity_id))]
+    async fn get_entity_docs(&self, entity_id: Uuid) -> Result<String, Error> {
+        let path = format!("/entities/{}/docs", entity_id);
+        Ok(self.send(Method::GET, &path, |b| b).await?.text().await?)
+    }
+
+    #[instrument(skip_all, fields(registry.request = "set_entity_docs", entity.id = %entity_id))]
+    async fn set_entity_docs(&self, entity_id: Uuid, docs: &str) -> Result<(), Error> {
+        let path = format!("/entities/{}/docs", entity_id);
+        self.send(Method::PUT, &path, |b| {
+            b.header(reqwest::header::CONTENT_TYPE, "text/plain; charset=utf-8")
+                .body(docs.to_string())
+        })
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(registry.request = "delete_entity", entity.id = %entity_id))]
+    async fn delete_entity(&self, entity_id: Uuid) -> Result<(), Error> {
+        let path = format!("/entities/{}", entity_id);
+        self.send(Method::DELETE, &path, |b| b).await?;
+        Ok(())
+    }
 }
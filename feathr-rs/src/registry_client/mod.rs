@@ -5,13 +5,27 @@ use crate::Error;
 
 pub mod api_models;
 mod feathr_api_client;
+mod routing;
 
+pub use api_models::RegistryErrorCode;
 pub use feathr_api_client::FeathrApiClient;
 
 // TODO:
 #[async_trait]
 pub trait FeatureRegistry: Send + Sync {
-    async fn load_project(&self, name: &str) -> Result<api_models::EntityLineage, Error>;
+    async fn load_project(
+        &self,
+        name: &str,
+        release: Option<&str>,
+    ) -> Result<api_models::EntityLineage, Error>;
+    /**
+     * Get an anchor or derived feature of any project by the id or qualified name
+     */
+    async fn get_feature(&self, id_or_name: &str) -> Result<api_models::Entity, Error>;
+    /**
+     * Snippets in the language getting the offline features of the project and materializing them
+     */
+    async fn get_project_codegen(&self, name: &str, lang: &str) -> Result<String, Error>;
     async fn new_project(&self, definition: api_models::ProjectDef) -> Result<(Uuid, u64), Error>;
     async fn new_source(
         &self,
@@ -34,4 +48,28 @@ pub trait FeatureRegistry: Send + Sync {
         project_id: Uuid,
         definition: api_models::DerivedFeatureDef,
     ) -> Result<(Uuid, u64), Error>;
+    async fn new_materialization_record(
+        &self,
+        project_id: Uuid,
+        definition: api_models::MaterializationRecordDef,
+    ) -> Result<(Uuid, u64), Error>;
+    async fn new_dataset(
+        &self,
+        project_id: Uuid,
+        definition: api_models::DatasetDef,
+    ) -> Result<(Uuid, u64), Error>;
+    async fn set_project_ownership(
+        &self,
+        project_id: Uuid,
+        ownership: crate::Ownership,
+    ) -> Result<(), Error>;
+    async fn set_feature_ownership(
+        &self,
+        feature_id: Uuid,
+        ownership: crate::Ownership,
+    ) -> Result<(), Error>;
+    async fn set_source_verified(&self, project_id: Uuid, source_id: Uuid) -> Result<(), Error>;
+    async fn get_entity_docs(&self, entity_id: Uuid) -> Result<String, Error>;
+    async fn set_entity_docs(&self, entity_id: Uuid, docs: &str) -> Result<(), Error>;
+    async fn delete_entity(&self, entity_id: Uuid) -> Result<(), Error>;
 }
@@ -0,0 +1,221 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use log::{debug, warn};
+use reqwest::{Response, StatusCode, Url};
+use serde_json::Value;
+
+pub(crate) const OPT_SEQ_HEADER: &str = "x-registry-opt-seq";
+const MANAGEMENT_CODE_HEADER: &str = "x-registry-management-code";
+
+/**
+ * Picks the nodes of a Raft registry cluster to send requests to.
+ * Writes go to the leader, which is discovered via the management API, so they're not forwarded by
+ * followers. Reads go to every node in turns along with the sequence of the latest response, a
+ * follower serves them locally once it has applied the writes seen by this client.
+ */
+#[derive(Debug)]
+pub(crate) struct EndpointRouter {
+    endpoints: Vec<String>,
+    management_code: Option<String>,
+    leader: AtomicUsize,
+    leader_known: AtomicBool,
+    next_read: AtomicUsize,
+    /// 0 if no response has carried the sequence yet
+    opt_seq: AtomicU64,
+}
+
+impl EndpointRouter {
+    pub(crate) fn new<T>(endpoints: &[T], management_code: Option<String>) -> Self
+    where
+        T: AsRef<str>,
+    {
+        Self {
+            endpoints: endpoints
+                .iter()
+                .map(|e| e.as_ref().trim().trim_end_matches('/').to_string())
+                .filter(|e| !e.is_empty())
+                .collect(),
+            management_code,
+            leader: AtomicUsize::new(0),
+            // Nothing to discover with only one node
+            leader_known: AtomicBool::new(endpoints.len() <= 1),
+            next_read: AtomicUsize::new(0),
+            opt_seq: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    pub(crate) fn endpoint(&self, idx: usize) -> &str {
+        &self.endpoints[idx]
+    }
+
+    /**
+     * Indices of the nodes to try in order, writes start from the known leader and reads start from
+     * the next node in turns
+     */
+    pub(crate) fn candidates(&self, write: bool) -> Vec<usize> {
+        let n = self.endpoints.len();
+        if n == 0 {
+            return vec![];
+        }
+        let start = if write {
+            self.leader.load(Ordering::Relaxed)
+        } else {
+            self.next_read.fetch_add(1, Ordering::Relaxed)
+        } % n;
+        (0..n).map(|i| (start + i) % n).collect()
+    }
+
+    pub(crate) fn is_leader_known(&self) -> bool {
+        self.leader_known.load(Ordering::Relaxed)
+    }
+
+    /**
+     * The leader is discovered again before the next write
+     */
+    pub(crate) fn reset_leader(&self) {
+        if self.endpoints.len() > 1 {
+            self.leader_known.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn opt_seq(&self) -> Option<u64> {
+        Some(self.opt_seq.load(Ordering::Relaxed)).filter(|&s| s > 0)
+    }
+
+    /**
+     * Keep the highest sequence returned by any node
+     */
+    pub(crate) fn observe(&self, resp: &Response) {
+        if let Some(seq) = resp
+            .headers()
+            .get(OPT_SEQ_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            self.opt_seq.fetch_max(seq, Ordering::Relaxed);
+        }
+    }
+
+    /**
+     * Ask every node for its Raft metrics and remember the one that reports itself as the leader,
+     * the first node is used if none of them does
+     */
+    pub(crate) async fn discover_leader(&self, client: &reqwest::Client) {
+        for (idx, endpoint) in self.endpoints.iter().enumerate() {
+            let url = match management_url(endpoint, "/metrics") {
+                Some(url) => url,
+                None => continue,
+            };
+            let mut builder = client.get(url);
+            if let Some(code) = &self.management_code {
+                builder = builder.header(MANAGEMENT_CODE_HEADER, code);
+            }
+            let metrics = match builder.send().await.and_then(|r| r.error_for_status()) {
+                Ok(resp) => resp.json::<Value>().await,
+                Err(e) => Err(e),
+            };
+            match metrics {
+                Ok(metrics) if is_leader(&metrics) => {
+                    debug!("Registry leader is {}", endpoint);
+                    self.leader.store(idx, Ordering::Relaxed);
+                    self.leader_known.store(true, Ordering::Relaxed);
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Failed to get metrics of {}, {}", endpoint, e),
+            }
+        }
+        warn!("Registry leader not found, sending writes to the first available node");
+        self.leader.store(0, Ordering::Relaxed);
+        self.leader_known.store(true, Ordering::Relaxed);
+    }
+}
+
+/**
+ * Management API is served at the root of the node, e.g. `http://node1:8000/metrics` for
+ * `http://node1:8000/api/v2`
+ */
+fn management_url(endpoint: &str, path: &str) -> Option<String> {
+    let mut url = Url::parse(endpoint).ok()?;
+    url.set_path(path);
+    url.set_query(None);
+    Some(url.to_string())
+}
+
+/**
+ * Metrics are returned as `{"Ok": {"id": 1, "current_leader": 1, ...}}`
+ */
+fn is_leader(metrics: &Value) -> bool {
+    let m = metrics.get("Ok").unwrap_or(metrics);
+    match (m.get("id"), m.get("current_leader")) {
+        (Some(id), Some(leader)) => !id.is_null() && id == leader,
+        _ => false,
+    }
+}
+
+/**
+ * Whether the request should be sent to the next node.
+ * Writes are only retried if the node rejected them without applying, reads are also retried on
+ * gateway errors
+ */
+pub(crate) fn is_unavailable(write: bool, status: StatusCode) -> bool {
+    match status {
+        StatusCode::SERVICE_UNAVAILABLE => true,
+        StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => !write,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let router = EndpointRouter::new(
+            &[
+                "http://node1:8000/api/v2/",
+                "http://node2:8000/api/v2",
+                "http://node3:8000/api/v2",
+            ],
+            None,
+        );
+        assert_eq!(router.endpoint(0), "http://node1:8000/api/v2");
+        assert!(!router.is_leader_known());
+
+        router.leader.store(1, Ordering::Relaxed);
+        assert_eq!(router.candidates(true), vec![1, 2, 0]);
+        // Reads start from the next node each time
+        assert_eq!(router.candidates(false), vec![0, 1, 2]);
+        assert_eq!(router.candidates(false), vec![1, 2, 0]);
+        assert_eq!(router.candidates(false), vec![2, 0, 1]);
+
+        // A single node never needs discovery
+        let router = EndpointRouter::new(&["http://localhost:8000/api/v1"], None);
+        assert!(router.is_leader_known());
+        router.reset_leader();
+        assert!(router.is_leader_known());
+    }
+
+    #[test]
+    fn test_leader_metrics() {
+        assert_eq!(
+            management_url("https://node1:8000/api/v2?x=1", "/metrics").unwrap(),
+            "https://node1:8000/metrics"
+        );
+        assert!(is_leader(&json!({"Ok": {"id": 2, "current_leader": 2}})));
+        assert!(!is_leader(&json!({"Ok": {"id": 2, "current_leader": 1}})));
+        assert!(!is_leader(
+            &json!({"Ok": {"id": 2, "current_leader": null}})
+        ));
+        assert!(is_unavailable(false, StatusCode::BAD_GATEWAY));
+        assert!(!is_unavailable(true, StatusCode::BAD_GATEWAY));
+        assert!(!is_unavailable(false, StatusCode::NOT_FOUND));
+    }
+}
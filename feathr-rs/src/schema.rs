@@ -0,0 +1,202 @@
+use std::fmt::Display;
+
+use bytes::Bytes;
+use log::debug;
+use parquet::file::footer::parse_metadata;
+
+use crate::{DataLocation, Error, JobClient};
+
+/// Max number of CSV rows used to infer column types
+const CSV_SAMPLE_ROWS: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    /// Parquet logical or physical type, or one of `long`, `double`, `boolean` and `string` for CSV
+    pub data_type: String,
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.data_type)
+    }
+}
+
+/**
+ * Columns of a source, nested Parquet columns are named by the dotted path
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceSchema {
+    pub columns: Vec<Column>,
+}
+
+impl SourceSchema {
+    /**
+     * Column names are case insensitive in Spark by default
+     */
+    pub fn get_column(&self, name: &str) -> Option<&Column> {
+        self.columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/**
+ * Read the schema from the Parquet footer or the CSV header of a HDFS source, without running
+ * any Spark job.
+ * The path must point to a single file on the storage of the job client.
+ */
+pub(crate) async fn infer_schema<T>(
+    job_client: &T,
+    location: &DataLocation,
+) -> Result<SourceSchema, Error>
+where
+    T: JobClient + Sync,
+{
+    let path = match location {
+        DataLocation::Hdfs { path } => path,
+        _ => {
+            return Err(Error::InvalidArgument(format!(
+                "Schema inference of `{}` source is not supported",
+                location.get_type()
+            )))
+        }
+    };
+    let lower = path.to_lowercase();
+    if lower.ends_with(".parquet") {
+        debug!("Reading Parquet footer of {}", path);
+        parse_parquet_schema(job_client.read_remote_file(path).await?)
+    } else if lower.ends_with(".csv") {
+        debug!("Reading CSV header of {}", path);
+        let content = job_client.read_remote_file(path).await?;
+        parse_csv_schema(&String::from_utf8_lossy(&content))
+    } else {
+        Err(Error::InvalidArgument(format!(
+            "Cannot infer schema of {}, only `.parquet` and `.csv` files are supported",
+            path
+        )))
+    }
+}
+
+fn parse_parquet_schema(content: Bytes) -> Result<SourceSchema, Error> {
+    let metadata = parse_metadata(&content)?;
+    Ok(SourceSchema {
+        columns: metadata
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|c| Column {
+                name: c.path().string(),
+                data_type: match c.logical_type() {
+                    Some(t) => format!("{:?}", t),
+                    None => c.physical_type().to_string(),
+                },
+            })
+            .collect(),
+    })
+}
+
+fn parse_csv_schema(content: &str) -> Result<SourceSchema, Error> {
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+    let header = lines
+        .next()
+        .ok_or_else(|| Error::InvalidArgument("CSV file is empty".to_string()))?;
+    let mut columns: Vec<(String, Option<&'static str>)> = split_csv_line(header)
+        .into_iter()
+        .map(|name| (name, None))
+        .collect();
+    for line in lines.take(CSV_SAMPLE_ROWS) {
+        for ((_, data_type), value) in columns.iter_mut().zip(split_csv_line(line)) {
+            if value.is_empty() {
+                continue;
+            }
+            *data_type = Some(widen(*data_type, infer_csv_type(&value)));
+        }
+    }
+    Ok(SourceSchema {
+        columns: columns
+            .into_iter()
+            .map(|(name, data_type)| Column {
+                name,
+                data_type: data_type.unwrap_or("string").to_string(),
+            })
+            .collect(),
+    })
+}
+
+fn infer_csv_type(value: &str) -> &'static str {
+    if value.parse::<i64>().is_ok() {
+        "long"
+    } else if value.parse::<f64>().is_ok() {
+        "double"
+    } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+        "boolean"
+    } else {
+        "string"
+    }
+}
+
+/**
+ * The narrowest type fits values of both types
+ */
+fn widen(current: Option<&'static str>, value: &'static str) -> &'static str {
+    match (current, value) {
+        (None, v) => v,
+        (Some(c), v) if c == v => c,
+        (Some("long"), "double") | (Some("double"), "long") => "double",
+        _ => "string",
+    }
+}
+
+/**
+ * Split a CSV line, fields can be quoted with `"` and quotes are escaped by doubling
+ */
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_schema() {
+        let schema = parse_csv_schema(
+            "id,\"name, full\",score,active,note\n1,\"a, \"\"b\"\"\",1,true,\n2,c,2.5,false,x\n",
+        )
+        .unwrap();
+        assert_eq!(
+            schema
+                .columns
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "id: long",
+                "name, full: string",
+                "score: double",
+                "active: boolean",
+                "note: string"
+            ]
+        );
+        assert!(schema.get_column("ID").is_some());
+        assert!(parse_csv_schema("").is_err());
+    }
+}
@@ -1,13 +1,15 @@
 use std::{collections::HashMap, str::FromStr, sync::Arc};
 
+use chrono::{DateTime, Utc};
 use serde::{ser::SerializeStruct, Deserialize, Serialize};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::{
+    materialization::{format_partition, validate_partition_pattern},
     project::{FeathrProjectImpl, FeathrProjectModifier},
-    utils::parse_secret,
-    Error, GetSecretKeys,
+    utils::{parse_secret, parse_secrets},
+    ConnectionTestResult, Error, FeathrClient, GetSecretKeys, SourceSchema,
 };
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
@@ -45,12 +47,85 @@ impl Serialize for JdbcAuth {
     }
 }
 
+impl JdbcAuth {
+    /**
+     * Credentials read from `{PREFIX}_USER` and `{PREFIX}_PASSWORD`, or from `{PREFIX}_TOKEN`, which
+     * are resolved by the `VarSource` of the client when a job is submitted
+     */
+    pub(crate) fn from_env_prefix(auth: JdbcSourceAuth, prefix: &str) -> Self {
+        let prefix = env_var_name(prefix);
+        let var = |suffix: &str| format!("${{{}_{}}}", prefix, suffix);
+        match auth {
+            JdbcSourceAuth::Anonymous => JdbcAuth::Anonymous,
+            JdbcSourceAuth::Userpass => JdbcAuth::Userpass {
+                user: var("USER"),
+                password: var("PASSWORD"),
+            },
+            JdbcSourceAuth::Token => JdbcAuth::Token {
+                token: var("TOKEN"),
+            },
+        }
+    }
+
+    /**
+     * Prefix of the credential variables, `None` if the credentials are not read from variables
+     */
+    pub(crate) fn env_prefix(&self) -> Option<String> {
+        let (var, suffix) = match self {
+            JdbcAuth::Userpass { user, .. } => (user, "_USER"),
+            JdbcAuth::Token { token } => (token, "_TOKEN"),
+            JdbcAuth::Anonymous => return None,
+        };
+        parse_secret(var).and_then(|s| s.strip_suffix(suffix).map(ToString::to_string))
+    }
+}
+
+/**
+ * Characters not allowed in environment variable names are replaced with `_`, e.g. `my-db` becomes
+ * `my_db`
+ */
+pub(crate) fn env_var_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KafkaSchema {
     #[serde(rename = "type")]
-    type_: String,
+    pub(crate) type_: String,
     #[serde(rename = "avroJson")]
-    avro_json: String,
+    pub(crate) avro_json: String,
+}
+
+/**
+ * SASL settings of a Kafka source, placeholders like `${EH_CLIENT_SECRET}` in the JAAS config and
+ * the token endpoint are resolved by the `VarSource` of the client when a job is submitted
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KafkaSasl {
+    pub(crate) security_protocol: String,
+    pub(crate) mechanism: String,
+    pub(crate) jaas_config: String,
+    /// OAuth token endpoint of `OAUTHBEARER`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) token_endpoint_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) login_callback_handler: Option<String>,
+}
+
+impl KafkaSasl {
+    pub(crate) fn get_secret_keys(&self) -> Vec<String> {
+        parse_secrets(&self.jaas_config)
+            .into_iter()
+            .chain(
+                self.token_endpoint_url
+                    .iter()
+                    .flat_map(|url| parse_secrets(url)),
+            )
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
@@ -73,6 +148,10 @@ pub enum DataLocation {
         brokers: Vec<String>,
         topics: Vec<String>,
         schema: KafkaSchema,
+        #[serde(rename = "consumerGroup", default)]
+        consumer_group: Option<String>,
+        #[serde(default)]
+        sasl: Option<KafkaSasl>,
     },
     Generic {
         #[serde(rename = "type", default, skip_serializing)]
@@ -139,12 +218,23 @@ impl Serialize for DataLocation {
                 brokers,
                 topics,
                 schema,
+                consumer_group,
+                sasl,
             } => {
-                let mut state = serializer.serialize_struct("DataLocation", 4)?;
+                let len = 4 + consumer_group.is_some() as usize + sasl.is_some() as usize;
+                let mut state = serializer.serialize_struct("DataLocation", len)?;
                 state.serialize_field("type", "kafka")?;
                 state.serialize_field("brokers", brokers)?;
                 state.serialize_field("topics", topics)?;
                 state.serialize_field("schema", schema)?;
+                match consumer_group {
+                    Some(group) => state.serialize_field("consumerGroup", group)?,
+                    None => state.skip_field("consumerGroup")?,
+                }
+                match sasl {
+                    Some(sasl) => state.serialize_field("sasl", sasl)?,
+                    None => state.skip_field("sasl")?,
+                }
                 state.end()
             }
             DataLocation::Generic {
@@ -228,6 +318,183 @@ impl DataLocation {
             DataLocation::InputContext => "INPUT_CONTEXT".to_string(),
         }
     }
+
+    /**
+     * Check the options of a generic location against the schema of its format, all unknown and
+     * missing options are listed in the error. Formats without a schema are not checked
+     */
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            DataLocation::Generic {
+                format, options, ..
+            } => validate_generic_options(format, options.keys().map(String::as_str)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/**
+ * Options of a format of the generic location, names are the Spark option names, which are
+ * case-insensitive
+ */
+struct GenericOptionSchema {
+    format: &'static str,
+    required: &'static [&'static str],
+    /// At least one option of each group is required, e.g. the table or the query of JDBC
+    one_of: &'static [&'static [&'static str]],
+    optional: &'static [&'static str],
+}
+
+const GENERIC_OPTION_SCHEMAS: &[GenericOptionSchema] = &[
+    GenericOptionSchema {
+        format: "cosmos.oltp",
+        required: &[
+            "spark.cosmos.accountEndpoint",
+            "spark.cosmos.accountKey",
+            "spark.cosmos.database",
+            "spark.cosmos.container",
+        ],
+        one_of: &[],
+        optional: &[
+            "spark.cosmos.applicationName",
+            "spark.cosmos.preferredRegionsList",
+            "spark.cosmos.useGatewayMode",
+            "spark.cosmos.read.customQuery",
+            "spark.cosmos.read.inferSchema.enabled",
+            "spark.cosmos.read.inferSchema.samplingSize",
+            "spark.cosmos.read.maxItemCount",
+            "spark.cosmos.read.partitioning.strategy",
+            "spark.cosmos.write.strategy",
+            "spark.cosmos.write.bulk.enabled",
+            "spark.cosmos.write.maxRetryCount",
+        ],
+    },
+    GenericOptionSchema {
+        format: "kafka",
+        required: &["kafka.bootstrap.servers"],
+        // Sources subscribe to topics, sinks write to `topic`
+        one_of: &[&["subscribe", "subscribePattern", "assign", "topic"]],
+        optional: &[
+            "kafka.group.id",
+            "kafka.security.protocol",
+            "kafka.sasl.mechanism",
+            "kafka.sasl.jaas.config",
+            "groupIdPrefix",
+            "startingOffsets",
+            "endingOffsets",
+            "startingTimestamp",
+            "endingTimestamp",
+            "failOnDataLoss",
+            "includeHeaders",
+            "maxOffsetsPerTrigger",
+            "minPartitions",
+        ],
+    },
+    GenericOptionSchema {
+        format: "delta",
+        required: &["path"],
+        one_of: &[],
+        optional: &[
+            "versionAsOf",
+            "timestampAsOf",
+            "readChangeFeed",
+            "startingVersion",
+            "startingTimestamp",
+            "maxFilesPerTrigger",
+            "ignoreDeletes",
+            "ignoreChanges",
+            "mergeSchema",
+            "overwriteSchema",
+            "replaceWhere",
+            "partitionOverwriteMode",
+            "userMetadata",
+        ],
+    },
+    GenericOptionSchema {
+        format: "jdbc",
+        required: &["url"],
+        one_of: &[&["dbtable", "query"]],
+        optional: &[
+            "user",
+            "password",
+            "driver",
+            "fetchsize",
+            "batchsize",
+            "numPartitions",
+            "partitionColumn",
+            "lowerBound",
+            "upperBound",
+            "queryTimeout",
+            "isolationLevel",
+            "truncate",
+            "customSchema",
+            "createTableOptions",
+            "createTableColumnTypes",
+            "sessionInitStatement",
+            "pushDownPredicate",
+        ],
+    },
+];
+
+/**
+ * Option keys are stored with `.` replaced by `__`, e.g. `spark__cosmos__accountKey`
+ */
+fn validate_generic_options<'a, I>(format: &str, keys: I) -> Result<(), Error>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let schema = match GENERIC_OPTION_SCHEMAS
+        .iter()
+        .find(|s| s.format.eq_ignore_ascii_case(format))
+    {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+    let keys: Vec<String> = keys.into_iter().map(|k| k.replace("__", ".")).collect();
+    let has = |name: &str| keys.iter().any(|k| k.eq_ignore_ascii_case(name));
+    let mut unknown: Vec<&str> = keys
+        .iter()
+        .filter(|k| {
+            !schema
+                .required
+                .iter()
+                .chain(schema.one_of.iter().flat_map(|g| g.iter()))
+                .chain(schema.optional.iter())
+                .any(|name| k.eq_ignore_ascii_case(name))
+        })
+        .map(String::as_str)
+        .collect();
+    unknown.sort_unstable();
+    let mut problems: Vec<String> = vec![];
+    if !unknown.is_empty() {
+        problems.push(format!("unknown options `{}`", unknown.join("`, `")));
+    }
+    let missing: Vec<&str> = schema
+        .required
+        .iter()
+        .copied()
+        .filter(|name| !has(name))
+        .collect();
+    if !missing.is_empty() {
+        problems.push(format!(
+            "missing required options `{}`",
+            missing.join("`, `")
+        ));
+    }
+    for group in schema.one_of {
+        if !group.iter().any(|name| has(name)) {
+            problems.push(format!("one of `{}` is required", group.join("`, `")));
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidArgument(format!(
+            "Invalid options of format `{}`: {}",
+            schema.format,
+            problems.join("; ")
+        )))
+    }
 }
 
 impl GetSecretKeys for DataLocation {
@@ -257,6 +524,9 @@ impl GetSecretKeys for DataLocation {
                     }
                 }
             }
+            DataLocation::Kafka {
+                sasl: Some(sasl), ..
+            } => secrets.extend(sasl.get_secret_keys()),
             _ => (),
         }
         secrets
@@ -270,6 +540,28 @@ pub(crate) struct TimeWindowParameters {
     pub(crate) timestamp_column_format: String,
 }
 
+/**
+ * Files of each partition are stored under the source path followed by the pattern, e.g.
+ * `{yyyy}/{MM}/{dd}`, the range is formatted with the pattern and both ends are inclusive
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TimePartition {
+    #[serde(rename = "timePartitionPattern")]
+    pub(crate) pattern: String,
+    #[serde(
+        rename = "timePartitionStart",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) start: Option<String>,
+    #[serde(
+        rename = "timePartitionEnd",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) end: Option<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct SourceImpl {
@@ -282,6 +574,8 @@ pub(crate) struct SourceImpl {
     pub(crate) location: DataLocation,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) time_window_parameters: Option<TimeWindowParameters>,
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub(crate) time_partition: Option<TimePartition>,
     #[serde(skip)]
     pub(crate) preprocessing: Option<String>,
     #[serde(skip)]
@@ -303,6 +597,7 @@ impl SourceImpl {
             name: "PASSTHROUGH".to_string(),
             location: DataLocation::InputContext,
             time_window_parameters: None,
+            time_partition: None,
             preprocessing: None,
             registry_tags: Default::default(),
         }
@@ -314,14 +609,10 @@ impl SourceImpl {
 
     pub(crate) fn get_secret_keys(&self) -> Vec<String> {
         match &self.location {
-            DataLocation::Jdbc { auth, .. } => match auth {
-                JdbcAuth::Userpass { .. } => vec![
-                    format!("{}_USER", self.name),
-                    format!("{}_PASSWORD", self.name),
-                ],
-                JdbcAuth::Token { .. } => vec![format!("{}_TOKEN", self.name)],
-                _ => vec![],
-            },
+            // Credentials may be shared with other sources, so the names come from the placeholders
+            DataLocation::Jdbc { .. } | DataLocation::Kafka { .. } => {
+                self.location.get_secret_keys()
+            }
             DataLocation::Generic { options, .. } => options
                 .keys()
                 .filter_map(|k| {
@@ -404,6 +695,27 @@ impl Source {
         self.inner.preprocessing.clone()
     }
 
+    pub fn get_time_partition_pattern(&self) -> Option<String> {
+        self.inner
+            .time_partition
+            .as_ref()
+            .map(|p| p.pattern.clone())
+    }
+
+    /**
+     * Check if the source is reachable before submitting any job with it
+     */
+    pub async fn test_connection(&self, client: &FeathrClient) -> ConnectionTestResult {
+        client.test_source_connection(self).await
+    }
+
+    /**
+     * Read column names and types from the Parquet or CSV file, without running any Spark job
+     */
+    pub async fn infer_schema(&self, client: &FeathrClient) -> Result<SourceSchema, Error> {
+        client.infer_source_schema(self).await
+    }
+
     #[allow(non_snake_case)]
     pub fn INPUT_CONTEXT() -> Self {
         Self {
@@ -417,6 +729,7 @@ pub struct HdfsSourceBuilder {
     name: String,
     path: String,
     time_window_parameters: Option<TimeWindowParameters>,
+    time_partition: Option<(String, Option<DateTime<Utc>>, Option<DateTime<Utc>>)>,
     preprocessing: Option<String>,
 }
 
@@ -427,6 +740,7 @@ impl HdfsSourceBuilder {
             name: name.to_string(),
             path: path.to_string(),
             time_window_parameters: None,
+            time_partition: None,
             preprocessing: None,
         }
     }
@@ -448,6 +762,48 @@ impl HdfsSourceBuilder {
         self
     }
 
+    /**
+     * The source is partitioned by time, files of each partition are stored under the path
+     * followed by the pattern, which can contain placeholders `{yyyy}`, `{MM}`, `{dd}` and `{HH}`.
+     * Only the partitions between `start` and `end` are read if they're set
+     */
+    pub fn time_partition(
+        &mut self,
+        pattern: &str,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> &mut Self {
+        self.time_partition = Some((pattern.to_string(), start, end));
+        self
+    }
+
+    fn build_time_partition(&self) -> Result<Option<TimePartition>, Error> {
+        let (pattern, start, end) = match &self.time_partition {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        validate_partition_pattern(pattern)?;
+        if !pattern.contains('{') {
+            return Err(Error::InvalidArgument(format!(
+                "Time partition pattern `{}` of source {} has no placeholder",
+                pattern, self.name
+            )));
+        }
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > end {
+                return Err(Error::InvalidArgument(format!(
+                    "Time partition range of source {} starts after it ends",
+                    self.name
+                )));
+            }
+        }
+        Ok(Some(TimePartition {
+            pattern: pattern.to_owned(),
+            start: start.map(|t| format_partition(pattern, t)),
+            end: end.map(|t| format_partition(pattern, t)),
+        }))
+    }
+
     pub async fn build(&self) -> Result<Source, Error> {
         let imp = SourceImpl {
             id: Uuid::new_v4(),
@@ -457,8 +813,9 @@ impl HdfsSourceBuilder {
                 path: self.path.clone(),
             },
             time_window_parameters: self.time_window_parameters.clone(),
+            time_partition: self.build_time_partition()?,
             preprocessing: self.preprocessing.clone(),
-            registry_tags: Default::default(),
+            registry_tags: self.owner.read().await.default_tags.clone(),
         };
         self.owner.insert_source(imp).await
     }
@@ -469,7 +826,8 @@ pub struct JdbcSourceBuilder {
     url: String,
     dbtable: Option<String>,
     query: Option<String>,
-    auth: Option<JdbcAuth>,
+    auth: Option<JdbcSourceAuth>,
+    credential_prefix: Option<String>,
     time_window_parameters: Option<TimeWindowParameters>,
     preprocessing: Option<String>,
 }
@@ -490,6 +848,7 @@ impl JdbcSourceBuilder {
             dbtable: None,
             query: None,
             auth: None,
+            credential_prefix: None,
             time_window_parameters: None,
             preprocessing: None,
         }
@@ -505,21 +864,22 @@ impl JdbcSourceBuilder {
         self
     }
 
+    /**
+     * Credentials are read from `{SOURCE_NAME}_USER` and `{SOURCE_NAME}_PASSWORD`, or from
+     * `{SOURCE_NAME}_TOKEN`, via the environment variables, the config file or the Key Vault.
+     * The global `JDBC_USER`, `JDBC_PASSWORD` and `JDBC_TOKEN` are used if they're not set.
+     */
     pub fn auth(&mut self, auth: JdbcSourceAuth) -> &mut Self {
-        match auth {
-            JdbcSourceAuth::Anonymous => self.auth = Some(JdbcAuth::Anonymous),
-            JdbcSourceAuth::Userpass => {
-                self.auth = Some(JdbcAuth::Userpass {
-                    user: format!("${{{}_USER}}", self.name),
-                    password: format!("${{{}_PASSWORD}}", self.name),
-                })
-            }
-            JdbcSourceAuth::Token => {
-                self.auth = Some(JdbcAuth::Token {
-                    token: format!("${{{}_TOKEN}}", self.name),
-                })
-            }
-        }
+        self.auth = Some(auth);
+        self
+    }
+
+    /**
+     * Read the credentials from `{prefix}_USER`, `{prefix}_PASSWORD` or `{prefix}_TOKEN` instead,
+     * so sources connecting to the same database can share them
+     */
+    pub fn credential_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.credential_prefix = Some(prefix.to_string());
         self
     }
 
@@ -541,7 +901,10 @@ impl JdbcSourceBuilder {
     }
 
     pub async fn build(&self) -> Result<Source, Error> {
-        let auth = self.auth.clone().unwrap_or(JdbcAuth::Anonymous);
+        let auth = JdbcAuth::from_env_prefix(
+            self.auth.unwrap_or(JdbcSourceAuth::Anonymous),
+            self.credential_prefix.as_deref().unwrap_or(&self.name),
+        );
         let imp = SourceImpl {
             id: Uuid::new_v4(),
             version: 1,
@@ -553,8 +916,9 @@ impl JdbcSourceBuilder {
                 auth,
             },
             time_window_parameters: self.time_window_parameters.clone(),
+            time_partition: None,
             preprocessing: self.preprocessing.clone(),
-            registry_tags: Default::default(),
+            registry_tags: self.owner.read().await.default_tags.clone(),
         };
         self.owner.insert_source(imp).await
     }
@@ -640,10 +1004,173 @@ impl KafkaSourceBuilder {
                     type_: "KAFKA".to_string(),
                     avro_json: self.avro_json.clone(),
                 },
+                consumer_group: None,
+                sasl: None,
             },
             time_window_parameters: None,
+            time_partition: None,
             preprocessing: None,
-            registry_tags: Default::default(),
+            registry_tags: self.owner.read().await.default_tags.clone(),
+        };
+        self.owner.insert_source(imp).await
+    }
+}
+
+/**
+ * How an Event Hubs source authenticates through the Kafka endpoint
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EventHubAuth {
+    /// Service principal from `{PREFIX}_CLIENT_ID`, `{PREFIX}_CLIENT_SECRET` and `{PREFIX}_TENANT_ID`
+    #[default]
+    Aad,
+    /// Shared access connection string from `{PREFIX}_CONNECTION_STRING`
+    ConnectionString,
+}
+
+pub struct EventHubSourceBuilder {
+    owner: Arc<RwLock<FeathrProjectImpl>>,
+    name: String,
+    namespace: String,
+    event_hub: String,
+    consumer_group: Option<String>,
+    auth: EventHubAuth,
+    credential_prefix: Option<String>,
+    avro_json: String,
+}
+
+impl EventHubSourceBuilder {
+    pub(crate) fn new(
+        owner: Arc<RwLock<FeathrProjectImpl>>,
+        name: &str,
+        namespace: &str,
+        event_hub: &str,
+    ) -> Self {
+        Self {
+            owner,
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            event_hub: event_hub.to_string(),
+            consumer_group: None,
+            auth: Default::default(),
+            credential_prefix: None,
+            avro_json: Default::default(),
+        }
+    }
+
+    /**
+     * Consumer group the streaming job reads with, the Kafka client assigns one if it's not set
+     */
+    pub fn consumer_group(&mut self, group: &str) -> &mut Self {
+        self.consumer_group = Some(group.to_string());
+        self
+    }
+
+    pub fn auth(&mut self, auth: EventHubAuth) -> &mut Self {
+        self.auth = auth;
+        self
+    }
+
+    /**
+     * Read the credentials from `{prefix}_*` instead of `{SOURCE_NAME}_*`, so sources in the same
+     * namespace can share them
+     */
+    pub fn credential_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.credential_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn avro_schema<T>(&mut self, schema: &T) -> &mut Self
+    where
+        T: Serialize,
+    {
+        self.avro_json = serde_json::to_string_pretty(schema).unwrap();
+        self
+    }
+
+    pub fn avro_json<T>(&mut self, json: &T) -> &mut Self
+    where
+        T: ToString,
+    {
+        self.avro_json = json.to_string();
+        self
+    }
+
+    /**
+     * Fully qualified host of the namespace, e.g. `my-ns` becomes `my-ns.servicebus.windows.net`
+     */
+    fn host(&self) -> String {
+        if self.namespace.contains('.') {
+            self.namespace.clone()
+        } else {
+            format!("{}.servicebus.windows.net", self.namespace)
+        }
+    }
+
+    fn sasl(&self, host: &str) -> KafkaSasl {
+        let prefix = env_var_name(self.credential_prefix.as_deref().unwrap_or(&self.name));
+        let var = |suffix: &str| format!("${{{}_{}}}", prefix, suffix);
+        match self.auth {
+            EventHubAuth::Aad => KafkaSasl {
+                security_protocol: "SASL_SSL".to_string(),
+                mechanism: "OAUTHBEARER".to_string(),
+                jaas_config: format!(
+                    r#"org.apache.kafka.common.security.oauthbearer.OAuthBearerLoginModule required clientId="{}" clientSecret="{}" scope="https://{}/.default";"#,
+                    var("CLIENT_ID"),
+                    var("CLIENT_SECRET"),
+                    host
+                ),
+                token_endpoint_url: Some(format!(
+                    "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
+                    var("TENANT_ID")
+                )),
+                login_callback_handler: Some(
+                    "org.apache.kafka.common.security.oauthbearer.secured.OAuthBearerLoginCallbackHandler"
+                        .to_string(),
+                ),
+            },
+            EventHubAuth::ConnectionString => KafkaSasl {
+                security_protocol: "SASL_SSL".to_string(),
+                mechanism: "PLAIN".to_string(),
+                jaas_config: format!(
+                    r#"org.apache.kafka.common.security.plain.PlainLoginModule required username="$ConnectionString" password="{}";"#,
+                    var("CONNECTION_STRING")
+                ),
+                token_endpoint_url: None,
+                login_callback_handler: None,
+            },
+        }
+    }
+
+    /**
+     * The event hub is read as a Kafka topic from the Kafka endpoint of the namespace on port 9093
+     */
+    pub async fn build(&self) -> Result<Source, Error> {
+        if self.namespace.is_empty() || self.event_hub.is_empty() {
+            return Err(Error::InvalidArgument(format!(
+                "Namespace and event hub of source {} must not be empty",
+                self.name
+            )));
+        }
+        let host = self.host();
+        let imp = SourceImpl {
+            id: Uuid::new_v4(),
+            version: 1,
+            name: self.name.to_string(),
+            location: DataLocation::Kafka {
+                brokers: vec![format!("{}:9093", host)],
+                topics: vec![self.event_hub.clone()],
+                schema: KafkaSchema {
+                    type_: "KAFKA".to_string(),
+                    avro_json: self.avro_json.clone(),
+                },
+                consumer_group: self.consumer_group.clone(),
+                sasl: Some(self.sasl(&host)),
+            },
+            time_window_parameters: None,
+            time_partition: None,
+            preprocessing: None,
+            registry_tags: self.owner.read().await.default_tags.clone(),
         };
         self.owner.insert_source(imp).await
     }
@@ -723,20 +1250,27 @@ impl GenericSourceBuilder {
         self
     }
 
+    /**
+     * Options of `cosmos.oltp`, `kafka`, `delta` and `jdbc` are checked against the known Spark
+     * options of the format, other formats are not checked
+     */
     pub async fn build(&self) -> Result<Source, Error> {
+        let location = DataLocation::Generic {
+            _type: "generic".to_string(),
+            format: self.format.clone(),
+            mode: self.mode.clone(),
+            options: self.options.clone(),
+        };
+        location.validate()?;
         let imp = SourceImpl {
             id: Uuid::new_v4(),
             version: 1,
             name: self.name.to_string(),
-            location: DataLocation::Generic {
-                _type: "generic".to_string(),
-                format: self.format.clone(),
-                mode: self.mode.clone(),
-                options: self.options.clone(),
-            },
+            location,
             time_window_parameters: self.time_window_parameters.clone(),
+            time_partition: None,
             preprocessing: self.preprocessing.clone(),
-            registry_tags: Default::default(),
+            registry_tags: self.owner.read().await.default_tags.clone(),
         };
         self.owner.insert_source(imp).await
     }
@@ -746,7 +1280,11 @@ impl GenericSourceBuilder {
 mod tests {
     use std::{collections::HashMap, str::FromStr};
 
-    use crate::DataLocation;
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        DataLocation, EventHubAuth, FeathrProject, GetSecretKeys, JdbcAuth, JdbcSourceAuth,
+    };
 
     #[test]
     fn data_location() {
@@ -792,4 +1330,160 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn generic_options() {
+        let location = |format: &str, keys: &[&str]| DataLocation::Generic {
+            _type: "generic".to_string(),
+            format: format.to_string(),
+            mode: None,
+            options: keys
+                .iter()
+                .map(|k| (k.replace('.', "__"), "v".to_string()))
+                .collect(),
+        };
+        assert!(location(
+            "cosmos.oltp",
+            &[
+                "spark.cosmos.accountEndpoint",
+                "spark.cosmos.accountKey",
+                "spark.cosmos.database",
+                "spark.cosmos.container",
+            ]
+        )
+        .validate()
+        .is_ok());
+        assert!(location("jdbc", &["url", "DBTABLE"]).validate().is_ok());
+        assert!(location("parquet", &["anything"]).validate().is_ok());
+
+        let err = location("kafka", &["kafka.bootstrap.server", "subscribe"])
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown options `kafka.bootstrap.server`"));
+        assert!(err.contains("missing required options `kafka.bootstrap.servers`"));
+        let err = location("jdbc", &["url"])
+            .validate()
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("one of `dbtable`, `query` is required"));
+    }
+
+    #[test]
+    fn jdbc_credentials() {
+        let auth = JdbcAuth::from_env_prefix(JdbcSourceAuth::Userpass, "nyc-taxi");
+        assert_eq!(
+            auth,
+            JdbcAuth::Userpass {
+                user: "${nyc_taxi_USER}".to_string(),
+                password: "${nyc_taxi_PASSWORD}".to_string(),
+            }
+        );
+        assert_eq!(auth.env_prefix().as_deref(), Some("nyc_taxi"));
+        let loc = DataLocation::Jdbc {
+            url: "jdbc:sqlserver://localhost:1433".to_string(),
+            dbtable: Some("table1".to_string()),
+            query: None,
+            auth: JdbcAuth::from_env_prefix(JdbcSourceAuth::Token, "shared"),
+        };
+        assert_eq!(loc.get_secret_keys(), vec!["shared_TOKEN".to_string()]);
+        assert_eq!(
+            JdbcAuth::from_env_prefix(JdbcSourceAuth::Anonymous, "x").env_prefix(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn time_partition() {
+        let proj = FeathrProject::new_detached("p1").await;
+        let source = proj
+            .hdfs_source("trips", "wasbs://data@account.blob.core.windows.net/trips")
+            .time_partition(
+                "{yyyy}/{MM}/{dd}",
+                Some(Utc.ymd(2022, 5, 1).and_hms(0, 0, 0)),
+                None,
+            )
+            .build()
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&source).unwrap();
+        assert_eq!(json["timePartitionPattern"], "{yyyy}/{MM}/{dd}");
+        assert_eq!(json["timePartitionStart"], "2022/05/01");
+        assert!(json.get("timePartitionEnd").is_none());
+        assert_eq!(
+            source.get_time_partition_pattern().as_deref(),
+            Some("{yyyy}/{MM}/{dd}")
+        );
+
+        for pattern in ["{yyyy}/{mm}", "/{yyyy}", "daily"] {
+            assert!(proj
+                .hdfs_source("s", "wasbs://data/s")
+                .time_partition(pattern, None, None)
+                .build()
+                .await
+                .is_err());
+        }
+        assert!(proj
+            .hdfs_source("s", "wasbs://data/s")
+            .time_partition(
+                "{yyyy}/{MM}/{dd}",
+                Some(Utc.ymd(2022, 5, 2).and_hms(0, 0, 0)),
+                Some(Utc.ymd(2022, 5, 1).and_hms(0, 0, 0)),
+            )
+            .build()
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn eventhub_source() {
+        let proj = FeathrProject::new_detached("p1").await;
+        let source = proj
+            .eventhub_source("trips", "feathr-ns", "trips")
+            .consumer_group("feathr")
+            .credential_prefix("eh")
+            .build()
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&source).unwrap();
+        assert_eq!(json["location"]["type"], "kafka");
+        assert_eq!(
+            json["location"]["brokers"][0],
+            "feathr-ns.servicebus.windows.net:9093"
+        );
+        assert_eq!(json["location"]["consumerGroup"], "feathr");
+        assert_eq!(json["location"]["sasl"]["mechanism"], "OAUTHBEARER");
+        assert_eq!(
+            source.get_secret_keys(),
+            vec!["eh_CLIENT_ID", "eh_CLIENT_SECRET", "eh_TENANT_ID"]
+        );
+        // Serialized location reads back the same
+        let location: DataLocation = serde_json::from_value(json["location"].clone()).unwrap();
+        assert_eq!(location, source.get_location());
+
+        let source = proj
+            .eventhub_source("trips2", "feathr-ns.servicebus.windows.net", "trips")
+            .auth(EventHubAuth::ConnectionString)
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(source.get_secret_keys(), vec!["trips2_CONNECTION_STRING"]);
+        match source.get_location() {
+            DataLocation::Kafka {
+                consumer_group,
+                sasl: Some(sasl),
+                ..
+            } => {
+                assert_eq!(consumer_group, None);
+                assert_eq!(sasl.mechanism, "PLAIN");
+                assert!(sasl.token_endpoint_url.is_none());
+            }
+            _ => panic!("Kafka location expected"),
+        }
+        assert!(proj
+            .eventhub_source("s", "", "trips")
+            .build()
+            .await
+            .is_err());
+    }
 }
@@ -85,7 +85,7 @@ pub(crate) fn str_to_dur(s: &str) -> Result<Duration, Error> {
             "s" | "second" | "seconds" => Ok(Duration::seconds(num)),
             "m" | "minute" | "minutes" => Ok(Duration::seconds(num * 60)),
             "h" | "hour" | "hours" => Ok(Duration::seconds(num * 3600)),
-            "d" | "day" | "dasys" => Ok(Duration::seconds(num * 86400)),
+            "d" | "day" | "days" => Ok(Duration::seconds(num * 86400)),
             _ => Err(Error::DurationError(s.to_owned())),
         }
     } else {
@@ -122,13 +122,31 @@ pub fn parse_secret(s: &str) -> Option<String> {
     None
 }
 
+/**
+ * Names of all `${VAR}` placeholders in the string, e.g. the client id and secret in a JAAS config
+ */
+pub fn parse_secrets(s: &str) -> Vec<String> {
+    let mut secrets = vec![];
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        match rest[start + 2..].find('}') {
+            Some(end) => {
+                secrets.push(rest[start + 2..start + 2 + end].to_string());
+                rest = &rest[start + 2 + end + 1..];
+            }
+            None => break,
+        }
+    }
+    secrets
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::Duration;
 
     use crate::utils::str_to_dur;
 
-    use super::dur_to_string;
+    use super::{dur_to_string, parse_secrets};
 
     #[test]
     fn test_str_to_dur() {
@@ -144,6 +162,15 @@ mod tests {
         assert!(str_to_dur("xyz999").is_err());
     }
 
+    #[test]
+    fn test_parse_secrets() {
+        assert_eq!(
+            parse_secrets(r#"clientId="${EH_CLIENT_ID}" clientSecret="${EH_CLIENT_SECRET}";"#),
+            vec!["EH_CLIENT_ID", "EH_CLIENT_SECRET"]
+        );
+        assert_eq!(parse_secrets("$ConnectionString ${UNCLOSED"), Vec::<String>::new());
+    }
+
     #[test]
     fn test_dur_to_str() {
         assert_eq!(dur_to_string(Duration::nanoseconds(1001)), "1001ns");
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -7,47 +8,663 @@ use async_trait::async_trait;
 use azure_identity::DefaultAzureCredential;
 use azure_security_keyvault::KeyClient;
 use log::{debug, warn};
+use regex::{Captures, Regex};
+use serde_yaml::Value;
 
 use crate::Logged;
 
 #[async_trait]
 pub trait VarSource: Sync + Send + std::fmt::Debug {
     async fn get_environment_variable(&self, name: &[&str]) -> Result<String, crate::Error>;
+
+    /**
+     * The effective config with secrets masked, `Null` if the source cannot enumerate its values
+     */
+    fn dump_config(&self) -> Value {
+        Value::Null
+    }
 }
 
+/**
+ * Prefix of the environment variables overriding the config, e.g. `FEATHR__SPARK_CONFIG__SPARK_CLUSTER`
+ */
+const ENV_PREFIX: &str = "FEATHR__";
+
+const MASK: &str = "******";
+
+/**
+ * Environment variable selecting the profile when the client doesn't name one
+ */
+pub const PROFILE_ENV: &str = "FEATHR_PROFILE";
+
+/**
+ * Named profiles in the config, each one is merged over the settings outside `profiles`
+ */
+const PROFILES_KEY: &str = "profiles";
+
+/**
+ * A profile inherits the settings of the profile named by this key before applying its own
+ */
+const EXTENDS_KEY: &str = "extends";
+
+/**
+ * Values used when a key is set in none of the layers
+ */
+const DEFAULTS: &[(&[&str], &str)] = &[
+    (&["spark_config", "spark_result_output_parts"], "1"),
+    (&["feature_registry", "api_version"], "1"),
+    (&["feature_registry", "auth"], "true"),
+];
+
 #[derive(Debug, Clone)]
 struct EnvVarSource;
 
+impl EnvVarSource {
+    /**
+     * `FEATHR__SECTION__KEY` takes precedence over the unprefixed `SECTION__KEY`, which is kept
+     * for existing deployments
+     */
+    fn get(name: &[&str]) -> Result<String, std::env::VarError> {
+        let name = name.join("__");
+        std::env::var(format!("{}{}", ENV_PREFIX, name.to_uppercase()))
+            .or_else(|_| std::env::var(&name))
+            .or_else(|_| std::env::var(name.to_uppercase()))
+    }
+}
+
 #[async_trait]
 impl VarSource for EnvVarSource {
     async fn get_environment_variable(&self, name: &[&str]) -> Result<String, crate::Error> {
-        let name: Vec<&str> = name.into_iter().map(|s| s.as_ref()).collect();
-        Ok(
-            std::env::var(name.join("__"))
-                .or_else(|_| std::env::var(name.join("__").to_uppercase()))?,
-        )
+        Ok(Self::get(name)?)
+    }
+}
+
+/**
+ * Expected structure of the config file, used to report missing sections and misspelled keys
+ */
+enum ConfigNode {
+    /// Scalar, or a structure that is not validated, e.g. `config_template`
+    Any,
+    Section(&'static [(&'static str, ConfigNode)]),
+}
+
+use ConfigNode::{Any, Section};
+
+const REQUIRED_SECTIONS: &[&str] = &["spark_config", "online_store", "feature_registry"];
+
+/**
+ * Keys used by this client, plus the ones only used by the Python client so configs can be shared
+ */
+const CONFIG_SCHEMA: &[(&str, ConfigNode)] = &[
+    ("api_version", Any),
+    (
+        "project_config",
+        Section(&[
+            ("project_name", Any),
+            ("required_environment_variables", Any),
+            ("optional_environment_variables", Any),
+        ]),
+    ),
+    (
+        "offline_store",
+        Section(&[
+            ("adls", Section(&[("adls_enabled", Any)])),
+            ("wasb", Section(&[("wasb_enabled", Any)])),
+            ("s3", Section(&[("s3_enabled", Any), ("s3_endpoint", Any)])),
+            (
+                "jdbc",
+                Section(&[
+                    ("jdbc_enabled", Any),
+                    ("jdbc_database", Any),
+                    ("jdbc_table", Any),
+                ]),
+            ),
+            (
+                "snowflake",
+                Section(&[("url", Any), ("user", Any), ("role", Any)]),
+            ),
+        ]),
+    ),
+    ("streaming_source", Any),
+    (
+        "spark_config",
+        Section(&[
+            ("spark_cluster", Any),
+            ("spark_result_output_parts", Any),
+            ("maven_artifact", Any),
+            ("spark_runtime", Any),
+            ("max_concurrent_jobs", Any),
+            ("max_feature_config_size", Any),
+            ("template_snippets", Any),
+            ("template_variables", Any),
+            ("cost", Any),
+            (
+                "retry",
+                Section(&[
+                    ("max_retries", Any),
+                    ("retry_on", Any),
+                    ("backoff", Any),
+                    ("max_backoff", Any),
+                ]),
+            ),
+            (
+                "azure_synapse",
+                Section(&[
+                    ("dev_url", Any),
+                    ("pool_name", Any),
+                    ("workspace_dir", Any),
+                    ("executor_size", Any),
+                    ("executor_num", Any),
+                    ("feathr_runtime_location", Any),
+                    ("max_concurrent_jobs", Any),
+                ]),
+            ),
+            (
+                "databricks",
+                Section(&[
+                    ("workspace_instance_url", Any),
+                    ("workspace_token_value", Any),
+                    ("auth", Any),
+                    ("config_template", Any),
+                    ("work_dir", Any),
+                    ("feathr_runtime_location", Any),
+                    ("submission_mode", Any),
+                    ("notebook_dir", Any),
+                    ("max_concurrent_jobs", Any),
+                ]),
+            ),
+            (
+                "generic_livy",
+                Section(&[
+                    ("livy_url", Any),
+                    ("auth", Any),
+                    ("username", Any),
+                    ("workspace_dir", Any),
+                    ("storage", Any),
+                    ("webhdfs_url", Any),
+                    ("webhdfs_user", Any),
+                    ("feathr_runtime_location", Any),
+                    ("max_concurrent_jobs", Any),
+                ]),
+            ),
+            (
+                "mock",
+                Section(&[
+                    ("workspace_dir", Any),
+                    ("statuses", Any),
+                    ("max_concurrent_jobs", Any),
+                ]),
+            ),
+        ]),
+    ),
+    (
+        "online_store",
+        Section(&[(
+            "redis",
+            Section(&[("host", Any), ("port", Any), ("ssl_enabled", Any)]),
+        )]),
+    ),
+    (
+        "feature_registry",
+        Section(&[
+            ("api_endpoint", Any),
+            ("api_endpoints", Any),
+            ("api_version", Any),
+            ("auth", Any),
+            ("management_code", Any),
+            ("purview", Any),
+        ]),
+    ),
+    ("secrets", Any),
+    ("monitoring", Any),
+    ("open_lineage", Section(&[("url", Any), ("namespace", Any)])),
+    (
+        "observability",
+        Section(&[
+            ("enabled", Any),
+            ("otlp_endpoint", Any),
+            ("service_name", Any),
+            ("sample_ratio", Any),
+        ]),
+    ),
+];
+
+/**
+ * Replace `${VAR}` and `${VAR:-default}` in string values with environment variables
+ */
+fn interpolate_env_vars(node: &mut Value, path: &mut Vec<String>, errors: &mut Vec<String>) {
+    match node {
+        Value::String(s) => {
+            let pattern = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(:-([^}]*))?\}").unwrap();
+            let replaced = pattern.replace_all(s.as_str(), |c: &Captures| {
+                match (std::env::var(&c[1]), c.get(3)) {
+                    (Ok(v), _) => v,
+                    (Err(_), Some(default)) => default.as_str().to_string(),
+                    (Err(_), None) => {
+                        errors.push(format!(
+                            "`{}` references environment variable `{}` which is not set",
+                            path.join("."),
+                            &c[1]
+                        ));
+                        Default::default()
+                    }
+                }
+            });
+            if let std::borrow::Cow::Owned(v) = replaced {
+                *s = v;
+            }
+        }
+        Value::Mapping(m) => {
+            for (k, v) in m.iter_mut() {
+                path.push(k.as_str().map(|k| k.to_string()).unwrap_or_default());
+                interpolate_env_vars(v, path, errors);
+                path.pop();
+            }
+        }
+        Value::Sequence(seq) => {
+            for (i, v) in seq.iter_mut().enumerate() {
+                path.push(i.to_string());
+                interpolate_env_vars(v, path, errors);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn profile_names(profiles: &serde_yaml::Mapping) -> String {
+    profiles
+        .keys()
+        .filter_map(|k| k.as_str())
+        .map(|k| format!("`{}`", k))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/**
+ * The settings of the profile with the inherited ones, `chain` is the profiles being resolved to
+ * detect circular inheritance
+ */
+fn resolve_profile(
+    profiles: &serde_yaml::Mapping,
+    name: &str,
+    chain: &mut Vec<String>,
+) -> Result<Value, crate::Error> {
+    if chain.iter().any(|p| p == name) {
+        chain.push(name.to_string());
+        return Err(crate::Error::InvalidConfig(format!(
+            "Circular profile inheritance: {}",
+            chain.join(" -> ")
+        )));
+    }
+    chain.push(name.to_string());
+    let mut profile = match profiles.get(&Value::String(name.to_string())) {
+        Some(p @ Value::Mapping(_)) => p.to_owned(),
+        Some(Value::Null) => Value::Mapping(Default::default()),
+        Some(_) => {
+            return Err(crate::Error::InvalidConfig(format!(
+                "Profile `{}` must be a mapping",
+                name
+            )))
+        }
+        None => {
+            return Err(crate::Error::InvalidConfig(format!(
+                "Profile `{}` is not defined, available profiles are {}",
+                name,
+                profile_names(profiles)
+            )))
+        }
+    };
+    let parent = profile
+        .as_mapping_mut()
+        .and_then(|m| m.remove(&Value::String(EXTENDS_KEY.to_string())));
+    let resolved = match parent {
+        Some(Value::String(parent)) => {
+            let mut resolved = resolve_profile(profiles, &parent, chain)?;
+            merge(&mut resolved, &profile);
+            resolved
+        }
+        Some(_) => {
+            return Err(crate::Error::InvalidConfig(format!(
+                "`{}.{}.{}` must be a profile name",
+                PROFILES_KEY, name, EXTENDS_KEY
+            )))
+        }
+        None => profile,
+    };
+    chain.pop();
+    Ok(resolved)
+}
+
+/**
+ * Remove `profiles` from the config and merge the selected profile over the common settings.
+ * The profile is `profile` if set, otherwise `FEATHR_PROFILE`, only the common settings are used
+ * if neither is set.
+ */
+fn apply_profile(root: &mut Value, profile: Option<&str>) -> Result<(), crate::Error> {
+    let profiles = root
+        .as_mapping_mut()
+        .and_then(|m| m.remove(&Value::String(PROFILES_KEY.to_string())));
+    let env_profile = std::env::var(PROFILE_ENV).ok().filter(|p| !p.is_empty());
+    let name = match (profile, &env_profile) {
+        (Some(p), _) => p,
+        (None, Some(p)) => p.as_str(),
+        (None, None) => {
+            if profiles.is_some() {
+                debug!("No profile selected, using the common settings");
+            }
+            return Ok(());
+        }
+    };
+    let profiles = match profiles {
+        Some(Value::Mapping(m)) => m,
+        Some(Value::Null) | None if profile.is_none() => {
+            // The variable may be set for other config files
+            warn!(
+                "`{}` is set to `{}` but the config has no profiles, using the common settings",
+                PROFILE_ENV, name
+            );
+            return Ok(());
+        }
+        Some(Value::Null) | None => {
+            return Err(crate::Error::InvalidConfig(format!(
+                "Profile `{}` is not defined, the config has no profiles",
+                name
+            )))
+        }
+        Some(_) => {
+            return Err(crate::Error::InvalidConfig(format!(
+                "`{}` must be a mapping",
+                PROFILES_KEY
+            )))
+        }
+    };
+    let resolved = resolve_profile(&profiles, name, &mut vec![])?;
+    merge(root, &resolved);
+    debug!("Using Feathr config profile `{}`", name);
+    Ok(())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            cur[j + 1] = (prev[j] + (ca != *cb) as usize)
+                .min(prev[j + 1] + 1)
+                .min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn suggest<'a>(key: &str, candidates: &[(&'a str, ConfigNode)]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|(c, _)| (edit_distance(key, c), *c))
+        .filter(|(d, c)| *d <= 2.max(c.len() / 3))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+fn check_section(
+    node: &Value,
+    schema: &[(&str, ConfigNode)],
+    path: &mut Vec<String>,
+    errors: &mut Vec<String>,
+    unknown: &mut Vec<String>,
+) {
+    let mapping = match node {
+        Value::Mapping(m) => m,
+        // Empty section, e.g. `online_store:` with all values set via environment variables
+        Value::Null => return,
+        _ => {
+            errors.push(format!("`{}` must be a mapping", path.join(".")));
+            return;
+        }
+    };
+    for (k, v) in mapping {
+        let key = match k.as_str() {
+            Some(k) => k,
+            None => {
+                errors.push(format!("`{}` has a non-string key", path.join(".")));
+                continue;
+            }
+        };
+        path.push(key.to_string());
+        match schema.iter().find(|(name, _)| *name == key) {
+            Some((_, Section(children))) => check_section(v, children, path, errors, unknown),
+            Some((_, Any)) => {}
+            None => unknown.push(match suggest(key, schema) {
+                Some(s) => format!("Unknown key `{}`, did you mean `{}`?", path.join("."), s),
+                None => format!("Unknown key `{}`", path.join(".")),
+            }),
+        }
+        path.pop();
+    }
+}
+
+/**
+ * Validate the config against the schema, unknown keys are only reported as warnings as the
+ * config file may be shared with other tools.
+ * A required section can be omitted if it's fully provided by environment variables or overrides.
+ */
+fn validate_config(root: &Value, overrides: &HashMap<String, String>) -> Result<(), crate::Error> {
+    let mut errors = vec![];
+    let mut unknown = vec![];
+    match root {
+        Value::Mapping(m) => {
+            for section in REQUIRED_SECTIONS {
+                let env_prefix = format!("{}__", section.to_uppercase());
+                let override_prefix = format!("{}.", section);
+                if !m.contains_key(&Value::String(section.to_string()))
+                    && !std::env::vars().any(|(k, _)| {
+                        let k = k.to_uppercase();
+                        k.starts_with(&env_prefix)
+                            || k.strip_prefix(ENV_PREFIX)
+                                .map(|k| k.starts_with(&env_prefix))
+                                .unwrap_or_default()
+                    })
+                    && !overrides.keys().any(|k| k.starts_with(&override_prefix))
+                {
+                    errors.push(format!("Required section `{}` is missing", section));
+                }
+            }
+            check_section(root, CONFIG_SCHEMA, &mut vec![], &mut errors, &mut unknown);
+        }
+        _ => errors.push("The config must be a mapping".to_string()),
+    }
+    for u in unknown {
+        warn!("{}", u);
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(crate::Error::InvalidConfig(format!(
+            "Invalid Feathr config: {}",
+            errors.join("; ")
+        )))
+    }
+}
+
+/**
+ * Parse the config, apply the profile, interpolate environment variables and validate the result
+ */
+fn parse_config(
+    content: &str,
+    profile: Option<&str>,
+    overrides: &HashMap<String, String>,
+) -> Result<Value, crate::Error> {
+    let mut root: Value = serde_yaml::from_str(content).map_err(|e| {
+        crate::Error::InvalidConfig(format!(
+            "Feathr config is not a valid YAML, {}",
+            match e.location() {
+                Some(l) => format!("line {} column {}: {}", l.line(), l.column(), e),
+                None => e.to_string(),
+            }
+        ))
+    })?;
+    apply_profile(&mut root, profile)?;
+    let mut errors = vec![];
+    interpolate_env_vars(&mut root, &mut vec![], &mut errors);
+    if !errors.is_empty() {
+        return Err(crate::Error::InvalidConfig(format!(
+            "Invalid Feathr config: {}",
+            errors.join("; ")
+        )));
+    }
+    validate_config(&root, overrides)?;
+    Ok(root)
+}
+
+/**
+ * Keys of the overrides are either dotted paths like `spark_config.spark_cluster` or the flat
+ * names of secrets like `ADLS_KEY`, matched case-insensitively
+ */
+fn normalize_key(key: &str) -> String {
+    key.replace("__", ".").to_lowercase()
+}
+
+/**
+ * Config path of an environment variable, unprefixed variables are only included if they start
+ * with a known section, as they cannot be told apart from unrelated variables otherwise
+ */
+fn env_config_path(name: &str) -> Option<Vec<String>> {
+    let (name, prefixed) = match name.strip_prefix(ENV_PREFIX) {
+        Some(n) => (n, true),
+        None => (name, false),
+    };
+    let path: Vec<String> = name.split("__").map(|s| s.to_lowercase()).collect();
+    if prefixed || (path.len() > 1 && CONFIG_SCHEMA.iter().any(|(k, _)| *k == path[0])) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn set_path<T>(node: &mut Value, path: &[T], value: Value)
+where
+    T: AsRef<str>,
+{
+    if path.is_empty() {
+        *node = value;
+        return;
+    }
+    if !node.is_mapping() {
+        *node = Value::Mapping(Default::default());
+    }
+    if let Value::Mapping(m) = node {
+        let key = Value::String(path[0].as_ref().to_string());
+        if !m.contains_key(&key) {
+            m.insert(key.clone(), Value::Null);
+        }
+        set_path(m.get_mut(&key).unwrap(), &path[1..], value);
     }
 }
 
+fn merge(dst: &mut Value, src: &Value) {
+    match (dst, src) {
+        (_, Value::Null) => {}
+        (Value::Mapping(d), Value::Mapping(s)) => {
+            for (k, v) in s {
+                match d.get_mut(k) {
+                    Some(existing) => merge(existing, v),
+                    None => {
+                        d.insert(k.to_owned(), v.to_owned());
+                    }
+                }
+            }
+        }
+        (d, s) => *d = s.to_owned(),
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["password", "secret", "token", "key", "jaas", "credential"]
+        .iter()
+        .any(|s| key.contains(s))
+}
+
+fn mask_secrets(node: &mut Value, masked: bool) {
+    match node {
+        Value::Mapping(m) => {
+            for (k, v) in m.iter_mut() {
+                let secret = masked || k.as_str().map(is_secret_key).unwrap_or_default();
+                mask_secrets(v, secret);
+            }
+        }
+        Value::Sequence(seq) => seq.iter_mut().for_each(|v| mask_secrets(v, masked)),
+        Value::Null => {}
+        v if masked => *v = Value::String(MASK.to_string()),
+        _ => {}
+    }
+}
+
+/**
+ * Layered config, a key is resolved from the first layer having it:
+ * 1. Explicit overrides passed to `FeathrClient::load_with_overrides`
+ * 2. Environment variables, `FEATHR__SECTION__KEY` first, then `SECTION__KEY`
+ * 3. Azure KeyVault, if `KEY_VAULT_NAME` is set
+ * 4. The YAML config, with the settings of the selected profile merged over the common ones
+ * 5. Built-in defaults
+ */
 #[derive(Debug, Clone)]
-struct YamlSource {
+struct LayeredSource {
     root: serde_yaml::Value,
     overlay: EnvVarSource,
     kv_overlay: Option<KeyVaultSource>,
+    overrides: HashMap<String, String>,
 }
 
-impl YamlSource {
-    fn load<T>(config_path: T) -> Result<Self, crate::Error>
-    where
-        T: AsRef<Path>,
-    {
-        let f = std::fs::File::open(config_path)?;
-        let root = serde_yaml::from_reader(f)?;
-        Ok(Self {
+impl LayeredSource {
+    fn new(root: Value, overrides: HashMap<String, String>) -> Self {
+        Self {
             root,
             overlay: EnvVarSource,
             kv_overlay: KeyVaultSource::from_env().ok(),
-        })
+            overrides,
+        }
+    }
+
+    fn load<T>(
+        config_path: T,
+        profile: Option<&str>,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, crate::Error>
+    where
+        T: AsRef<Path>,
+    {
+        let content = std::fs::read_to_string(config_path)?;
+        Self::parse(&content, profile, overrides)
+    }
+
+    fn parse(
+        content: &str,
+        profile: Option<&str>,
+        overrides: HashMap<String, String>,
+    ) -> Result<Self, crate::Error> {
+        let overrides: HashMap<String, String> = overrides
+            .into_iter()
+            .map(|(k, v)| (normalize_key(&k), v))
+            .collect();
+        let root = parse_config(content, profile, &overrides)?;
+        Ok(Self::new(root, overrides))
+    }
+
+    /**
+     * Used when there is no config file
+     */
+    fn without_config(overrides: HashMap<String, String>) -> Self {
+        Self::new(
+            Value::Null,
+            overrides
+                .into_iter()
+                .map(|(k, v)| (normalize_key(&k), v))
+                .collect(),
+        )
     }
 
     fn get_value_by_path<T>(
@@ -81,35 +698,69 @@ impl YamlSource {
             })?;
         self.get_value_by_path(child, &name[1..name.len()])
     }
+
+    fn get_default(name: &[&str]) -> Option<String> {
+        DEFAULTS
+            .iter()
+            .find(|(path, _)| *path == name)
+            .map(|(_, v)| v.to_string())
+    }
 }
 
-impl FromStr for YamlSource {
+impl FromStr for LayeredSource {
     type Err = crate::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let root = serde_yaml::from_slice(s.as_bytes())?;
-        Ok(Self {
-            root,
-            overlay: EnvVarSource,
-            kv_overlay: KeyVaultSource::from_env().ok(),
-        })
+        Self::parse(s, None, Default::default())
     }
 }
 
 #[async_trait]
-impl VarSource for YamlSource {
+impl VarSource for LayeredSource {
     async fn get_environment_variable(&self, name: &[&str]) -> Result<String, crate::Error> {
-        match self.overlay.get_environment_variable(name).await {
+        if let Some(v) = self.overrides.get(&normalize_key(&name.join("."))) {
+            return Ok(v.to_owned());
+        }
+        if let Ok(v) = self.overlay.get_environment_variable(name).await {
+            return Ok(v);
+        }
+        if let Some(kv) = &self.kv_overlay {
+            if let Ok(v) = kv.get_environment_variable(name).await {
+                return Ok(v);
+            }
+        }
+        match self.get_value_by_path(&self.root, name) {
             Ok(v) => Ok(v),
-            Err(_) => match &self.kv_overlay {
-                Some(kv) => match kv.get_environment_variable(name).await {
-                    Ok(v) => Ok(v),
-                    Err(_) => self.get_value_by_path(&self.root, name),
-                },
-                None => self.get_value_by_path(&self.root, name),
-            },
+            Err(e) => Self::get_default(name).ok_or(e),
         }
     }
+
+    /**
+     * Values only stored in KeyVault are not included, as they cannot be listed without reading them
+     */
+    fn dump_config(&self) -> Value {
+        let mut root = Value::Mapping(Default::default());
+        for (path, value) in DEFAULTS {
+            set_path(&mut root, path, Value::String(value.to_string()));
+        }
+        merge(&mut root, &self.root);
+        let mut env: Vec<(String, String)> = std::env::vars()
+            .filter(|(k, _)| env_config_path(k).is_some())
+            .collect();
+        // Prefixed variables are applied last so they win over the unprefixed ones
+        env.sort_by_key(|(k, _)| k.starts_with(ENV_PREFIX));
+        for (k, v) in env {
+            if let Some(path) = env_config_path(&k) {
+                set_path(&mut root, &path, Value::String(v));
+            }
+        }
+        for (k, v) in &self.overrides {
+            let path: Vec<&str> = k.split('.').collect();
+            set_path(&mut root, &path, Value::String(v.to_owned()));
+        }
+        mask_secrets(&mut root, false);
+        root
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -153,15 +804,45 @@ pub fn new_var_source<T>(content: T) -> Arc<dyn VarSource + Send + Sync>
 where
     T: AsRef<str>,
 {
-    match YamlSource::from_str(content.as_ref()) {
-        Ok(src) => Arc::new(src),
-        Err(_) => {
-            warn!("Failed read Feathr config, using environment variables.");
-            Arc::new(EnvVarSource)
+    match try_new_var_source(content) {
+        Ok(src) => src,
+        Err(e) => {
+            warn!(
+                "Failed read Feathr config, using environment variables. {}",
+                e
+            );
+            Arc::new(LayeredSource::without_config(Default::default()))
         }
     }
 }
 
+/**
+ * Same as `new_var_source`, but returns the error instead of falling back to environment variables
+ */
+pub fn try_new_var_source<T>(content: T) -> Result<Arc<dyn VarSource + Send + Sync>, crate::Error>
+where
+    T: AsRef<str>,
+{
+    Ok(Arc::new(LayeredSource::from_str(content.as_ref())?))
+}
+
+/**
+ * Same as `try_new_var_source`, values in `overrides` take precedence over all other layers
+ */
+pub fn try_new_var_source_with_overrides<T>(
+    content: T,
+    overrides: HashMap<String, String>,
+) -> Result<Arc<dyn VarSource + Send + Sync>, crate::Error>
+where
+    T: AsRef<str>,
+{
+    Ok(Arc::new(LayeredSource::parse(
+        content.as_ref(),
+        None,
+        overrides,
+    )?))
+}
+
 pub fn load_var_source<T>(conf_file: T) -> Arc<dyn VarSource + Send + Sync>
 where
     T: AsRef<Path>,
@@ -170,7 +851,7 @@ where
         "Loading Feathr config file `{}`",
         conf_file.as_ref().display()
     );
-    match YamlSource::load(conf_file.as_ref()) {
+    match LayeredSource::load(conf_file.as_ref(), None, Default::default()) {
         Ok(src) => {
             debug!(
                 "Feathr config file `{}` loaded",
@@ -178,16 +859,82 @@ where
             );
             Arc::new(src)
         }
-        Err(_) => {
+        Err(e) => {
             warn!(
-                "Failed load Feathr config file `{}`, using environment variables.",
-                conf_file.as_ref().display()
+                "Failed load Feathr config file `{}`, using environment variables. {}",
+                conf_file.as_ref().display(),
+                e
             );
-            Arc::new(EnvVarSource)
+            Arc::new(LayeredSource::without_config(Default::default()))
         }
     }
 }
 
+/**
+ * Load the config file, falls back to environment variables only if the file doesn't exist,
+ * any other error, e.g. malformed YAML or missing required sections, is returned.
+ */
+pub fn try_load_var_source<T>(
+    conf_file: T,
+) -> Result<Arc<dyn VarSource + Send + Sync>, crate::Error>
+where
+    T: AsRef<Path>,
+{
+    try_load_var_source_with_overrides(conf_file, Default::default())
+}
+
+/**
+ * Same as `try_load_var_source_with_overrides`, with the settings of the profile merged over the
+ * common ones, `profile` takes precedence over `FEATHR_PROFILE`
+ */
+pub fn try_load_var_source_with_profile<T>(
+    conf_file: T,
+    profile: Option<&str>,
+    overrides: HashMap<String, String>,
+) -> Result<Arc<dyn VarSource + Send + Sync>, crate::Error>
+where
+    T: AsRef<Path>,
+{
+    if !conf_file.as_ref().exists() {
+        if let Some(profile) = profile {
+            return Err(crate::Error::InvalidConfig(format!(
+                "Profile `{}` is requested but Feathr config file `{}` doesn't exist",
+                profile,
+                conf_file.as_ref().display()
+            )));
+        }
+        warn!(
+            "Feathr config file `{}` doesn't exist, using environment variables.",
+            conf_file.as_ref().display()
+        );
+        return Ok(Arc::new(LayeredSource::without_config(overrides)));
+    }
+    debug!(
+        "Loading Feathr config file `{}`",
+        conf_file.as_ref().display()
+    );
+    let src = LayeredSource::load(conf_file.as_ref(), profile, overrides)?;
+    debug!(
+        "Feathr config file `{}` loaded",
+        conf_file.as_ref().display()
+    );
+    Ok(Arc::new(src))
+}
+
+/**
+ * Same as `try_load_var_source`, values in `overrides` take precedence over all other layers,
+ * keys are dotted paths like `spark_config.spark_cluster` or secret names like `ADLS_KEY`
+ */
+pub fn try_load_var_source_with_overrides<T>(
+    conf_file: T,
+    overrides: HashMap<String, String>,
+) -> Result<Arc<dyn VarSource + Send + Sync>, crate::Error>
+where
+    T: AsRef<Path>,
+{
+    try_load_var_source_with_profile(conf_file, None, overrides)
+}
+
 pub fn default_var_source() -> Arc<dyn VarSource> {
     let conf_file: PathBuf = std::env::var("FEATHR_CONFIG")
         .ok()
@@ -195,17 +942,18 @@ pub fn default_var_source() -> Arc<dyn VarSource> {
         .into();
     debug!("Loading Feathr config file `{}`", conf_file.display());
 
-    match YamlSource::load(&conf_file) {
+    match LayeredSource::load(&conf_file, None, Default::default()) {
         Ok(src) => {
             debug!("Feathr config file `{}` loaded", conf_file.display());
             Arc::new(src)
         }
-        Err(_) => {
+        Err(e) => {
             warn!(
-                "Failed load Feathr config file `{}`, using environment variables.",
-                conf_file.display()
+                "Failed load Feathr config file `{}`, using environment variables. {}",
+                conf_file.display(),
+                e
             );
-            Arc::new(EnvVarSource)
+            Arc::new(LayeredSource::without_config(Default::default()))
         }
     }
 }
@@ -217,7 +965,8 @@ mod tests {
     #[tokio::test]
     async fn it_works() {
         crate::tests::init_logger();
-        let y = YamlSource::load("test-script/feathr_config.yaml").unwrap();
+        let y = LayeredSource::load("test-script/feathr_config.yaml", None, Default::default())
+            .unwrap();
         assert_eq!(
             y.get_environment_variable(&["project_config", "project_name"])
                 .await
@@ -225,4 +974,184 @@ mod tests {
             "project_feathr_integration_test"
         );
     }
+
+    #[test]
+    fn test_interpolate_env_vars() {
+        std::env::set_var(
+            "FEATHR_TEST_INTERPOLATE_HOST",
+            "myredis.redis.cache.windows.net",
+        );
+        let y: LayeredSource = r#"
+spark_config:
+  spark_cluster: local
+online_store:
+  redis:
+    host: ${FEATHR_TEST_INTERPOLATE_HOST}
+    port: ${FEATHR_TEST_INTERPOLATE_PORT:-6380}
+feature_registry:
+  api_endpoint: "https://${FEATHR_TEST_INTERPOLATE_HOST}/api"
+"#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            y.get_value_by_path(&y.root, &["online_store", "redis", "host"])
+                .unwrap(),
+            "myredis.redis.cache.windows.net"
+        );
+        assert_eq!(
+            y.get_value_by_path(&y.root, &["online_store", "redis", "port"])
+                .unwrap(),
+            "6380"
+        );
+        assert_eq!(
+            y.get_value_by_path(&y.root, &["feature_registry", "api_endpoint"])
+                .unwrap(),
+            "https://myredis.redis.cache.windows.net/api"
+        );
+
+        let e = LayeredSource::from_str(
+            "spark_config: {}\nonline_store: {}\nfeature_registry:\n  api_endpoint: ${FEATHR_TEST_NOT_SET}\n",
+        )
+        .unwrap_err();
+        assert!(e.to_string().contains("feature_registry.api_endpoint"));
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let e = LayeredSource::from_str("spark_config:\n  spark_cluster: local\n").unwrap_err();
+        assert!(e.to_string().contains("`online_store`"));
+        assert!(e.to_string().contains("`feature_registry`"));
+
+        let e = LayeredSource::from_str(
+            "spark_config: local\nonline_store: {}\nfeature_registry: {}\n",
+        )
+        .unwrap_err();
+        assert!(e.to_string().contains("`spark_config` must be a mapping"));
+
+        assert!(LayeredSource::from_str("spark_config: [\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_layers() {
+        // Only keys nobody else reads, as the environment is shared by all tests
+        std::env::set_var("FEATHR__SPARK_CONFIG__LAYER_TEST", "prefixed");
+        std::env::set_var("SPARK_CONFIG__LAYER_TEST", "unprefixed");
+        std::env::set_var("FEATHR__SPARK_CONFIG__LAYER_TEST_OVERRIDDEN", "env");
+        let y = LayeredSource::parse(
+            r#"
+spark_config:
+  layer_test: yaml
+  layer_test_overridden: yaml
+online_store:
+  redis:
+    host: localhost
+feature_registry:
+  api_endpoint: http://yaml/api
+"#,
+            None,
+            [
+                ("spark_config.layer_test_overridden", "override"),
+                ("REDIS_PASSWORD", "hunter2"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        )
+        .unwrap();
+        let cases: &[(&[&str], &str)] = &[
+            (&["spark_config", "layer_test"], "prefixed"),
+            (&["spark_config", "layer_test_overridden"], "override"),
+            (&["online_store", "redis", "host"], "localhost"),
+            (&["REDIS_PASSWORD"], "hunter2"),
+            (&["spark_config", "spark_result_output_parts"], "1"),
+        ];
+        for (name, value) in cases {
+            assert_eq!(y.get_environment_variable(name).await.unwrap(), *value);
+        }
+
+        let dump = y.dump_config();
+        let cases: &[(&[&str], &str)] = &[
+            (&["spark_config", "layer_test"], "prefixed"),
+            (&["spark_config", "layer_test_overridden"], "override"),
+            (&["feature_registry", "api_endpoint"], "http://yaml/api"),
+            (&["redis_password"], MASK),
+        ];
+        for (name, value) in cases {
+            assert_eq!(y.get_value_by_path(&dump, *name).unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn test_profiles() {
+        let content = r#"
+spark_config:
+  spark_cluster: local
+  spark_result_output_parts: "4"
+online_store:
+  redis:
+    host: localhost
+feature_registry:
+  api_endpoint: http://localhost/api
+profiles:
+  dev:
+    spark_config:
+      spark_cluster: databricks
+    feature_registry:
+      api_endpoint: http://dev/api
+  prod:
+    extends: dev
+    online_store:
+      redis:
+        host: prod.redis.cache.windows.net
+  loop1:
+    extends: loop2
+  loop2:
+    extends: loop1
+"#;
+        let get = |y: &LayeredSource, path: &[&str]| y.get_value_by_path(&y.root, path).unwrap();
+
+        let dev = LayeredSource::parse(content, Some("dev"), Default::default()).unwrap();
+        assert_eq!(get(&dev, &["spark_config", "spark_cluster"]), "databricks");
+        assert_eq!(get(&dev, &["online_store", "redis", "host"]), "localhost");
+
+        let prod = LayeredSource::parse(content, Some("prod"), Default::default()).unwrap();
+        assert_eq!(get(&prod, &["spark_config", "spark_cluster"]), "databricks");
+        assert_eq!(
+            get(&prod, &["spark_config", "spark_result_output_parts"]),
+            "4"
+        );
+        assert_eq!(
+            get(&prod, &["feature_registry", "api_endpoint"]),
+            "http://dev/api"
+        );
+        assert_eq!(
+            get(&prod, &["online_store", "redis", "host"]),
+            "prod.redis.cache.windows.net"
+        );
+        assert!(prod.get_value_by_path(&prod.root, &[PROFILES_KEY]).is_err());
+
+        let e = LayeredSource::parse(content, Some("test"), Default::default()).unwrap_err();
+        assert!(e.to_string().contains("`dev`, `prod`"));
+        let e = LayeredSource::parse(content, Some("loop1"), Default::default()).unwrap_err();
+        assert!(e.to_string().contains("loop1 -> loop2 -> loop1"));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let schema = CONFIG_SCHEMA;
+        assert_eq!(suggest("spark_confg", schema), Some("spark_config"));
+        assert_eq!(suggest("onlinestore", schema), Some("online_store"));
+        assert_eq!(suggest("something_else", schema), None);
+
+        let mut errors = vec![];
+        let mut unknown = vec![];
+        let root: Value =
+            serde_yaml::from_str("online_store:\n  redis:\n    hots: localhost\n").unwrap();
+        check_section(&root, schema, &mut vec![], &mut errors, &mut unknown);
+        assert!(errors.is_empty());
+        assert_eq!(
+            unknown,
+            vec!["Unknown key `online_store.redis.hots`, did you mean `host`?".to_string()]
+        );
+    }
 }
@@ -0,0 +1,631 @@
+# Generated from src/lib.rs by stubgen, DO NOT EDIT.
+from datetime import datetime
+from typing import Any, Awaitable, ClassVar, Dict, List, Optional, Tuple
+
+
+class ValueType:
+    """Type of a feature value or a key column"""
+    UNSPECIFIED: ClassVar[ValueType]
+    BOOL: ClassVar[ValueType]
+    INT32: ClassVar[ValueType]
+    INT64: ClassVar[ValueType]
+    FLOAT: ClassVar[ValueType]
+    DOUBLE: ClassVar[ValueType]
+    STRING: ClassVar[ValueType]
+    BYTES: ClassVar[ValueType]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class VectorType:
+    """Container type of a feature value, only tensor is supported"""
+    TENSOR: ClassVar[VectorType]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class TensorCategory:
+    """Whether a tensor feature is stored densely or sparsely"""
+    DENSE: ClassVar[TensorCategory]
+    SPARSE: ClassVar[TensorCategory]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class FeatureType:
+    """Type of a feature, use the class attributes like `FeatureType.FLOAT` for common types"""
+    @property
+    def tensor_category(self) -> TensorCategory: ...
+    @property
+    def dimension_type(self) -> List[ValueType]: ...
+    @property
+    def val_type(self) -> ValueType: ...
+    BOOLEAN: ClassVar[FeatureType]
+    INT32: ClassVar[FeatureType]
+    INT64: ClassVar[FeatureType]
+    FLOAT: ClassVar[FeatureType]
+    DOUBLE: ClassVar[FeatureType]
+    STRING: ClassVar[FeatureType]
+    BYTES: ClassVar[FeatureType]
+    def __init__(self, val_type: ValueType, tensor_category: TensorCategory = TensorCategory.DENSE, dimension_type: List[ValueType] = []) -> None:
+        """Create a feature type, a scalar value unless `dimension_type` is not empty"""
+        ...
+    INT32_VECTOR: ClassVar[FeatureType]
+    INT64_VECTOR: ClassVar[FeatureType]
+    FLOAT_VECTOR: ClassVar[FeatureType]
+    DOUBLE_VECTOR: ClassVar[FeatureType]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class TypedKey:
+    """A key column used to join features with the observation data"""
+    @property
+    def key_column(self) -> str: ...
+    @property
+    def key_column_type(self) -> ValueType: ...
+    @property
+    def full_name(self) -> Optional[str]: ...
+    @property
+    def description(self) -> Optional[str]: ...
+    @property
+    def key_column_alias(self) -> Optional[str]: ...
+    def __init__(self, key_column: str, key_column_type: ValueType, full_name: Optional[str] = None, description: Optional[str] = None, key_column_alias: Optional[str] = None) -> None:
+        """Create a typed key, `key_column_alias` defaults to `key_column`"""
+        ...
+    DUMMY_KEY: ClassVar[TypedKey]
+    def as_key(self, key_column_alias: str) -> TypedKey:
+        """Copy of the key with another alias"""
+        ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class Aggregation:
+    """Aggregation function of a window aggregation transformation"""
+    NOP: ClassVar[Aggregation]
+    AVG: ClassVar[Aggregation]
+    MAX: ClassVar[Aggregation]
+    MIN: ClassVar[Aggregation]
+    SUM: ClassVar[Aggregation]
+    UNION: ClassVar[Aggregation]
+    ELEMENTWISE_AVG: ClassVar[Aggregation]
+    ELEMENTWISE_MIN: ClassVar[Aggregation]
+    ELEMENTWISE_MAX: ClassVar[Aggregation]
+    ELEMENTWISE_SUM: ClassVar[Aggregation]
+    LATEST: ClassVar[Aggregation]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class Transformation:
+    """How a feature is computed from its source or input features"""
+    def __init__(self, s: str) -> None:
+        """Spark SQL expression transformation"""
+        ...
+    @staticmethod
+    def window_agg(def_expr: str, agg_func: Aggregation, window: Optional[str] = None, group_by: Optional[str] = None, filter: Optional[str] = None, limit: Optional[int] = None) -> Transformation:
+        """Sliding window aggregation, `window` is a duration like "90d" or "3h", the default window of
+        the anchor group is used if not set"""
+        ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class DataLocation:
+    """Where data is read from or written to, parsed from a URL or a dict"""
+    def __init__(self, value: Any) -> None:
+        """Parse the location from a URL string like "abfss://..." or "jdbc:...", or a dict of options.
+        Options of the `cosmos.oltp`, `kafka`, `delta` and `jdbc` formats are validated, unknown and
+        missing options are listed in the `ValueError`"""
+        ...
+    @property
+    def type(self) -> str: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class Source:
+    """A data source registered in the project, created with `FeathrProject.*_source`"""
+    @property
+    def id(self) -> str: ...
+    @property
+    def version(self) -> int: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def type(self) -> str: ...
+    @property
+    def location(self) -> DataLocation: ...
+    @property
+    def secret_keys(self) -> List[str]: ...
+    @property
+    def preprocessing(self) -> Optional[str]: ...
+    @property
+    def time_partition_pattern(self) -> Optional[str]: ...
+    def test_connection(self, client: FeathrClient) -> ConnectionTestResult:
+        """Check if the source is reachable before submitting any job with it"""
+        ...
+    def infer_schema(self, client: FeathrClient) -> List[Tuple[str, str]]:
+        """Read the column names and types from the Parquet footer or the CSV header of the source file"""
+        ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class ConnectionTestResult:
+    """Result of `Source.test_connection`, `status` is one of `Succeeded`, `Reachable`, `Failed` and `Unsupported`.
+    JDBC and Kafka sources are only tested for reachability, their credentials are not verified"""
+    @property
+    def succeeded(self) -> bool:
+        """The source is fully verified, a reachability-only test is not a success"""
+        ...
+    @property
+    def reachable(self) -> bool:
+        """The endpoint accepts connections, whether or not the credentials are verified"""
+        ...
+    @property
+    def status(self) -> str: ...
+    @property
+    def message(self) -> str: ...
+    @property
+    def elapsed(self) -> float: ...
+    @property
+    def tested_at(self) -> str: ...
+    def __repr__(self) -> str: ...
+
+
+class Diagnostic:
+    """A problem found by `FeathrProject.lint`, `severity` is one of `Error`, `Warning` and `Info`"""
+    @property
+    def severity(self) -> str: ...
+    @property
+    def kind(self) -> str: ...
+    @property
+    def entity(self) -> str: ...
+    @property
+    def message(self) -> str: ...
+    def __repr__(self) -> str: ...
+
+
+class CoercionWarning:
+    """A lossy or failing type conversion found by `FeathrProject.coercion_warnings`, `coercion` is one
+    of `Narrowing`, `Cast` and `Incompatible`"""
+    @property
+    def feature(self) -> str: ...
+    @property
+    def context(self) -> str: ...
+    @property
+    def from_type(self) -> ValueType: ...
+    @property
+    def to_type(self) -> ValueType: ...
+    @property
+    def coercion(self) -> str: ...
+    @property
+    def message(self) -> str: ...
+    def __repr__(self) -> str: ...
+
+
+class QueryCost:
+    """Estimated computation cost of a feature join, found by `FeathrProject.estimate_query_cost`"""
+    @property
+    def total(self) -> int: ...
+    @property
+    def features(self) -> Dict[str, int]:
+        """Cost of each computed feature, from the `feathr_cost` tag or inferred from the transformation"""
+        ...
+    @property
+    def expensive(self) -> bool: ...
+    @property
+    def warnings(self) -> List[str]: ...
+    @property
+    def sizing_hint(self) -> Dict[str, str]:
+        """Suggested Spark configurations if the join is expensive, empty otherwise"""
+        ...
+    def __repr__(self) -> str: ...
+
+
+class RenderedConfig:
+    """A config as it is submitted with the jobs, `raw` is the rendered string and `parsed` is the dict"""
+    @property
+    def raw(self) -> str: ...
+    @property
+    def parsed(self) -> Any: ...
+    def __str__(self) -> str: ...
+    def __repr__(self) -> str: ...
+
+
+class JdbcSourceAuth:
+    """How a JDBC source authenticates, credentials are read from the config or env vars"""
+    Anonymous: ClassVar[JdbcSourceAuth]
+    Userpass: ClassVar[JdbcSourceAuth]
+    Token: ClassVar[JdbcSourceAuth]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class EventHubAuth:
+    """How an Event Hubs source authenticates, `Aad` reads `<prefix>_CLIENT_ID`, `<prefix>_CLIENT_SECRET`
+    and `<prefix>_TENANT_ID`, `ConnectionString` reads `<prefix>_CONNECTION_STRING`"""
+    Aad: ClassVar[EventHubAuth]
+    ConnectionString: ClassVar[EventHubAuth]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class DateTimeResolution:
+    """Time step of a materialization job, one job is submitted per step"""
+    Daily: ClassVar[DateTimeResolution]
+    Hourly: ClassVar[DateTimeResolution]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class RedisSink:
+    """Materialize features into a Redis table"""
+    def __init__(self, table_name: str, streaming: bool = False, streaming_timeout: Optional[int] = None, key_transform: str = 'none', key_secret: Optional[str] = None) -> None:
+        """`streaming_timeout` is in seconds and only used by streaming jobs
+        `key_transform` is "none", "sha256" or "hmac", the HMAC secret is read from the variable named by `key_secret`"""
+        ...
+    @property
+    def table_name(self) -> str: ...
+    @property
+    def streaming(self) -> bool: ...
+    @property
+    def streaming_timeout(self) -> Optional[int]: ...
+    def transform_key(self, key: str, secret: Optional[str] = None) -> str:
+        """Transform a key value the same way as the materialization job, for online lookups
+        `secret` is the value of the `key_secret` variable, only used by "hmac\""""
+        ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class CosmosDbSink:
+    """Materialize features into a CosmosDB container, the account key is read from `<name>_KEY`"""
+    def __init__(self, name: str, endpoint: str, database: str, collection: str, streaming: bool = False, streaming_timeout: Optional[int] = None) -> None:
+        """`streaming_timeout` is in seconds and only used by streaming jobs"""
+        ...
+    @property
+    def location(self) -> DataLocation: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class SinkSelection:
+    """A sink receiving only `features`, with the key columns renamed by `key_mapping`, all materialized
+    features are written to the sink if `features` is not set"""
+    def __init__(self, sink: Any, features: Optional[List[str]] = None, key_mapping: Optional[Dict[str, str]] = None) -> None: ...
+    @property
+    def features(self) -> Optional[List[str]]: ...
+    @property
+    def key_mapping(self) -> Dict[str, str]: ...
+    def __repr__(self) -> str: ...
+
+
+class ObservationSettings:
+    """The observation data features are joined to"""
+    def __init__(self, observation_path: str, timestamp_column: Optional[str] = None, format: str = 'epoch') -> None:
+        """Features are joined by event time if `timestamp_column` is set, `format` is "epoch",
+        "epoch_millis" or a Java `SimpleDateFormat` pattern like "yyyy-MM-dd HH:mm:ss",
+        raises `ValueError` if the pattern is invalid"""
+        ...
+    @property
+    def observation_path(self) -> DataLocation: ...
+    @property
+    def timestamp_column(self) -> Optional[str]: ...
+    @property
+    def timestamp_format(self) -> Optional[str]: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class FeatureQuery:
+    """Features to join with the observation data and the keys to join on"""
+    def __init__(self, names: List[Any], keys: Optional[List[TypedKey]] = None) -> None:
+        """`names` are feature names or feature objects, features without keys are joined by the dummy key"""
+        ...
+    @staticmethod
+    def by_name(names: List[str]) -> FeatureQuery:
+        """Query features without keys, e.g. request features"""
+        ...
+    @property
+    def feature_list(self) -> List[str]: ...
+    @property
+    def key(self) -> List[str]: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class JobStatus:
+    """Status of a submitted Spark job"""
+    Starting: ClassVar[JobStatus]
+    Running: ClassVar[JobStatus]
+    Success: ClassVar[JobStatus]
+    Failed: ClassVar[JobStatus]
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+
+class AnchorFeature:
+    """A feature computed from the source of its anchor group"""
+    @property
+    def id(self) -> str: ...
+    @property
+    def version(self) -> int: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def type(self) -> FeatureType: ...
+    @property
+    def key(self) -> List[TypedKey]: ...
+    @property
+    def transformation(self) -> Transformation: ...
+    @property
+    def key_alias(self) -> List[str]: ...
+    @property
+    def registry_tags(self) -> Dict[str, str]: ...
+    def with_key(self, group: str, key_alias: List[str]) -> AnchorFeature: ...
+    def as_feature(self, group: str, feature_alias: str) -> AnchorFeature: ...
+    def __repr__(self) -> str: ...
+
+
+class DerivedFeature:
+    """A feature computed from other features"""
+    @property
+    def id(self) -> str: ...
+    @property
+    def version(self) -> int: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def type(self) -> FeatureType: ...
+    @property
+    def key(self) -> List[TypedKey]: ...
+    @property
+    def transformation(self) -> Transformation: ...
+    @property
+    def key_alias(self) -> List[str]: ...
+    @property
+    def registry_tags(self) -> Dict[str, str]: ...
+    def with_key(self, key_alias: List[str]) -> DerivedFeature: ...
+    def as_feature(self, feature_alias: str) -> DerivedFeature: ...
+    def __repr__(self) -> str: ...
+
+
+class AnchorGroup:
+    """A group of anchor features sharing the same source"""
+    @property
+    def id(self) -> str: ...
+    @property
+    def version(self) -> int: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def anchor_features(self) -> List[str]: ...
+    def anchor_feature(self, name: str, feature_type: FeatureType, transform: Any, keys: Optional[List[TypedKey]] = None, registry_tags: Optional[Dict[str, str]] = None) -> AnchorFeature:
+        """Add an anchor feature to the group, `transform` is a Spark SQL expression or a `Transformation`"""
+        ...
+    def anchor_features_from_template(self, name_pattern: str, feature_type: FeatureType, transform: Any, params: List[Dict[str, Any]], keys: Optional[List[TypedKey]] = None, registry_tags: Optional[Dict[str, str]] = None) -> List[AnchorFeature]:
+        """Add a batch of anchor features generated from a template, `{param}` placeholders in the
+        name, the transformation and the tag values are replaced with the values of each dict in
+        `params`, a window aggregation also takes its window from the `window` parameter"""
+        ...
+    def __getitem__(self, key: str) -> AnchorFeature: ...
+    def __repr__(self) -> str: ...
+
+
+class FeathrProject:
+    """A feathr project, created or loaded with `FeathrClient`"""
+    @property
+    def id(self) -> str: ...
+    @property
+    def version(self) -> int: ...
+    @property
+    def name(self) -> str: ...
+    @property
+    def default_tags(self) -> Dict[str, str]: ...
+    @property
+    def input_context(self) -> Source: ...
+    @property
+    def sources(self) -> Dict[str, Source]: ...
+    def get_source(self, name: str) -> Source: ...
+    def test_source_connection(self, name: str) -> ConnectionTestResult: ...
+    def get_docs(self, feature: Optional[str] = None) -> str:
+        """Markdown documentation of the project, or of the feature if `feature` is given"""
+        ...
+    def set_docs(self, docs: str, feature: Optional[str] = None) -> None:
+        """Replace the markdown documentation of the project, or of the feature if `feature` is given"""
+        ...
+    def delete_source(self, name: str) -> None:
+        """Delete the source, it must not be used by any anchor group"""
+        ...
+    def delete_anchor_group(self, name: str) -> None:
+        """Delete the anchor group, it must not contain any feature"""
+        ...
+    def delete_feature(self, name: str) -> None:
+        """Delete the anchor or derived feature, it must not be used by any derived feature"""
+        ...
+    def lint(self, feature_query: Optional[List[Any]] = None) -> List[Diagnostic]:
+        """Check the feature definitions before submitting jobs, features not used by `feature_query` are
+        reported if it is given"""
+        ...
+    def coercion_warnings(self, feature_query: List[Any]) -> List[CoercionWarning]:
+        """Lossy or failing type conversions in the feature join of `feature_query`, e.g. an INT32 feature
+        aggregated with AVG, they're also logged when the join job is submitted"""
+        ...
+    def estimate_query_cost(self, feature_query: List[Any]) -> QueryCost:
+        """Estimated computation cost of the feature join of `feature_query`, features are annotated with
+        the `feathr_cost` registry tag, `low`, `medium`, `high` or a number, or inferred from the transformation"""
+        ...
+    def validate_columns(self) -> List[Diagnostic]:
+        """Check the columns used by anchor features against the schemas of the sources on the storage"""
+        ...
+    def codegen(self, lang: str = 'python') -> str:
+        """Generate a ready-to-run script getting the offline features of the project and materializing them, the project must be registered"""
+        ...
+    def get_feature_config(self) -> RenderedConfig:
+        """The feature config submitted with the jobs of the project"""
+        ...
+    def get_join_config(self, observation: Any, feature_query: List[Any], output: Optional[str] = None) -> RenderedConfig:
+        """The join config `get_offline_features` would submit, the output path is empty if `output` is not set"""
+        ...
+    def get_gen_config(self, features: List[Any], start: datetime, end: datetime, step: DateTimeResolution = DateTimeResolution.Daily, sink: Optional[Any] = None, tz: Optional[str] = None, alignment: Optional[str] = None, end_inclusive: bool = False) -> List[RenderedConfig]:
+        """The generation configs `materialize_features` would submit, one per step and batch"""
+        ...
+    @property
+    def anchor_groups(self) -> Dict[str, AnchorGroup]: ...
+    @property
+    def anchor_features(self) -> List[str]: ...
+    @property
+    def derived_features(self) -> Dict[str, DerivedFeature]: ...
+    def get_anchor_group(self, name: str) -> AnchorGroup: ...
+    def get_derived_feature(self, name: str) -> DerivedFeature: ...
+    def anchor_group(self, name: str, source: Source, registry_tags: Optional[Dict[str, str]] = None, joined_sources: Optional[List[Source]] = None, join_keys: Optional[List[str]] = None, preprocessing: Optional[str] = None, default_window: Optional[str] = None) -> AnchorGroup:
+        """Add an anchor group, sources in `joined_sources` are left joined to `source` on `join_keys`.
+        `preprocessing` is only applied to this group, after the preprocessing of the sources.
+        Window aggregation features without a window use `default_window`"""
+        ...
+    def derived_feature(self, name: str, feature_type: FeatureType, transform: Any, inputs: Optional[List[Any]] = None, keys: Optional[List[TypedKey]] = None, registry_tags: Optional[Dict[str, str]] = None) -> DerivedFeature:
+        """Add a derived feature computed from `inputs`, which are anchor or derived features, or
+        qualified names of features in other projects, e.g. "shared__anchor1__f1".
+        Inputs are detected from the features named in the transform expression if not set"""
+        ...
+    def hdfs_source(self, name: str, path: str, timestamp_column: Optional[str] = None, timestamp_column_format: Optional[str] = None, preprocessing: Optional[str] = None, time_partition_pattern: Optional[str] = None, time_partition_start: Optional[datetime] = None, time_partition_end: Optional[datetime] = None) -> Source:
+        """Add a source of files in HDFS compatible storage, e.g. "abfss://..." or "wasbs://..."
+        `time_partition_pattern` is the layout of time-partitioned files under `path`, e.g. "{yyyy}/{MM}/{dd}",
+        only the partitions between `time_partition_start` and `time_partition_end` are read if they're set"""
+        ...
+    def jdbc_source(self, name: str, url: str, dbtable: Optional[str] = None, query: Optional[str] = None, auth: Optional[JdbcSourceAuth] = None, timestamp_column: Optional[str] = None, timestamp_column_format: Optional[str] = None, preprocessing: Optional[str] = None, credential_prefix: Optional[str] = None) -> Source:
+        """Add a JDBC source reading `dbtable` or the result of `query`
+        Credentials are read from `<name>_USER`, `<name>_PASSWORD` or `<name>_TOKEN`, or from the ones
+        prefixed with `credential_prefix`, and fall back to the global `JDBC_*` ones"""
+        ...
+    def cosmosdb_source(self, name: str, endpoint: str, database: str, collection: str, mode: Optional[str] = None, timestamp_column: Optional[str] = None, timestamp_column_format: Optional[str] = None, preprocessing: Optional[str] = None) -> Source:
+        """Add a CosmosDB source, the account key is read from `<name>_KEY`"""
+        ...
+    def eventhub_source(self, name: str, namespace: str, event_hub: str, consumer_group: Optional[str] = None, auth: Optional[EventHubAuth] = None, credential_prefix: Optional[str] = None, avro_json: Optional[str] = None) -> Source:
+        """Add a streaming source reading `event_hub` through the Kafka endpoint of the Event Hubs `namespace`
+        Credentials are read from `<name>_*` or `<credential_prefix>_*` according to `auth`, AAD by default"""
+        ...
+    def get_offline_features(self, observation: Any, feature_query: List[Any], output: Any, track_consumption: bool = False, spark_pool: Optional[str] = None) -> int:
+        """Submit a feature join job and return the job id, `output` is a URL or a `DataLocation`
+        With `track_consumption`, the output is registered as a dataset consuming the features once the job succeeded
+        `spark_pool` overrides the configured Azure Synapse Spark pool for this job"""
+        ...
+    def get_offline_features_async(self, observation: Any, feature_query: List[Any], output: Any, track_consumption: bool = False, spark_pool: Optional[str] = None) -> Awaitable[int]: ...
+    def materialize_features(self, features: List[Any], start: datetime, end: datetime, step: DateTimeResolution = DateTimeResolution.Daily, sink: Any = ..., spark_pool: Optional[str] = None, tz: Optional[str] = None, alignment: Optional[str] = None, end_inclusive: bool = False) -> List[int]:
+        """Submit feature generation jobs, one per `step` between `start` and `end`, and return the job ids
+        `sink` is a sink, a `SinkSelection` or a list of them, plain sinks get all features
+        `spark_pool` overrides the configured Azure Synapse Spark pool for these jobs
+        `tz` is a UTC offset like `+08:00`, `start` and `end` are read and the windows are aligned in it
+        `alignment` is `none`, `strict` or `expand`, `end_inclusive` also materializes the window containing `end`"""
+        ...
+    def materialize_features_async(self, features: List[Any], start: datetime, end: datetime, step: DateTimeResolution = DateTimeResolution.Daily, sink: Any = ..., spark_pool: Optional[str] = None, tz: Optional[str] = None, alignment: Optional[str] = None, end_inclusive: bool = False) -> Awaitable[List[int]]: ...
+    @property
+    def INPUT_CONTEXT(self) -> Source: ...
+    def __repr__(self) -> str: ...
+
+
+class FeathrClient:
+    """Client connecting to the Spark cluster and the feature registry"""
+    def __init__(self, config_file: str, overrides: Optional[Dict[str, str]] = None, profile: Optional[str] = None) -> None:
+        """Load the client with the config file, `overrides` take precedence over environment variables and the file.
+        `profile` selects the named profile in the file, `FEATHR_PROFILE` is used if it's not set"""
+        ...
+    @staticmethod
+    def load_async(config_file: str, profile: Optional[str] = None) -> Awaitable[FeathrClient]: ...
+    @staticmethod
+    def loads(content: str, backend: Optional[str] = None) -> FeathrClient:
+        """Load the client with the config content, `backend` overrides `spark_config.spark_cluster`, e.g. `mock` to run no job in tests"""
+        ...
+    @staticmethod
+    def loads_async(content: str, backend: Optional[str] = None) -> Awaitable[FeathrClient]: ...
+    def dump_config(self) -> str:
+        """The effective config in YAML after applying defaults, the file, environment variables and overrides, secrets are masked"""
+        ...
+    def load_project(self, name: str, release: Optional[str] = None) -> FeathrProject:
+        """Load the entity versions pinned by `release` if set, otherwise the latest ones"""
+        ...
+    def new_project(self, name: str, tags: Optional[Dict[str, str]] = None, default_tags: Optional[Dict[str, str]] = None) -> FeathrProject:
+        """Create a project, its sources, anchor groups and features inherit `default_tags` unless
+        they set the same tags"""
+        ...
+    def wait_for_job(self, job_id: int, timeout: Optional[int] = None) -> str:
+        """Wait for the job to end and return its output, `timeout` is in seconds"""
+        ...
+    def wait_for_job_async(self, id: int, timeout: Optional[int] = None) -> Awaitable[str]: ...
+    def wait_for_jobs(self, job_id: List[int], timeout: Optional[int] = None) -> List[str]: ...
+    def wait_for_jobs_async(self, job_id: List[int], timeout: Optional[int] = None) -> Awaitable[List[str]]: ...
+    def save_manifest(self, job_id: int) -> str:
+        """Save the manifest of a job submitted by this client next to the job output, returns the URL of the manifest"""
+        ...
+    def save_manifest_async(self, job_id: int) -> Awaitable[str]: ...
+    def resubmit(self, manifest: str) -> int:
+        """Submit the job in the manifest saved by `save_manifest` again, returns the new job id"""
+        ...
+    def resubmit_async(self, manifest: str) -> Awaitable[int]: ...
+    def get_job_status(self, job_id: int) -> JobStatus: ...
+    def get_job_status_async(self, job_id: int) -> Awaitable[JobStatus]: ...
+    def get_job_url(self, job_id: int) -> Optional[str]: ...
+    def get_job_url_async(self, job_id: int) -> Awaitable[Optional[str]]: ...
+    def get_remote_url(self, path: str) -> str: ...
+    def close(self) -> None:
+        """Cancel the pending waits of the client and its clones, they raise `asyncio.CancelledError`.
+        Waits started after closing are cancelled immediately, submitted jobs keep running"""
+        ...
+    @property
+    def closed(self) -> bool:
+        """Whether `close` has been called"""
+        ...
+    async def __aenter__(self) -> FeathrClient: ...
+    async def __aexit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+
+
+def load(config_file: str, profile: Optional[str] = None) -> FeathrClient:
+    """Load the client with the config file, `profile` selects the named profile in the file, `FEATHR_PROFILE` is used if it's not set"""
+    ...
+
+
+def loads(content: str, backend: Optional[str] = None) -> FeathrClient:
+    """Load the client with the config content, `backend` overrides `spark_config.spark_cluster`"""
+    ...
+
+
+def features() -> List[str]:
+    """Optional components compiled into the module, e.g. `otlp`, and `abi3` if the wheel is built for the stable ABI"""
+    ...
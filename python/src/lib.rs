@@ -5,16 +5,19 @@ use std::{
     hash::{Hash, Hasher},
 };
 
-use chrono::{DateTime, Duration, TimeZone, Utc};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
 use feathr::Feature;
 use futures::future::join_all;
 use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
-use pyo3::types::{PyDateAccess, PyDateTime, PyList, PyTimeAccess, PyTuple};
+use pyo3::types::{PyList, PyTuple};
 use pyo3::{exceptions::PyTypeError, prelude::*, pyclass::CompareOp};
-use utils::{block_on, cancelable_wait, value_to_py};
+use utils::{
+    block_on, cancelable_wait, cancelable_wait_async, hash_json, value_to_py, CancelToken,
+};
 
 mod utils;
 
+/// Type of a feature value or a key column
 #[pyclass]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum ValueType {
@@ -79,6 +82,7 @@ impl Into<feathr::ValueType> for ValueType {
     }
 }
 
+/// Container type of a feature value, only tensor is supported
 #[pyclass]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum VectorType {
@@ -118,6 +122,7 @@ impl Into<feathr::VectorType> for VectorType {
     }
 }
 
+/// Whether a tensor feature is stored densely or sparsely
 #[pyclass]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 enum TensorCategory {
@@ -164,6 +169,7 @@ impl Into<feathr::TensorCategory> for TensorCategory {
     }
 }
 
+/// Type of a feature, use the class attributes like `FeatureType.FLOAT` for common types
 #[pyclass]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct FeatureType {
@@ -178,6 +184,21 @@ struct FeatureType {
 #[allow(non_snake_case)]
 #[pymethods]
 impl FeatureType {
+    /// Create a feature type, a scalar value unless `dimension_type` is not empty
+    #[new]
+    #[args(tensor_category = "TensorCategory::DENSE", dimension_type = "vec![]")]
+    fn new(
+        val_type: ValueType,
+        tensor_category: TensorCategory,
+        dimension_type: Vec<ValueType>,
+    ) -> Self {
+        Self {
+            tensor_category,
+            dimension_type,
+            val_type,
+        }
+    }
+
     #[classattr]
     pub const BOOLEAN: FeatureType = FeatureType {
         tensor_category: TensorCategory::DENSE,
@@ -293,6 +314,7 @@ impl Into<feathr::FeatureType> for FeatureType {
     }
 }
 
+/// A key column used to join features with the observation data
 #[pyclass]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct TypedKey {
@@ -311,20 +333,22 @@ struct TypedKey {
 #[allow(non_snake_case)]
 #[pymethods]
 impl TypedKey {
+    /// Create a typed key, `key_column_alias` defaults to `key_column`
     #[new]
-    #[args(full_name = "None", description = "None")]
+    #[args(full_name = "None", description = "None", key_column_alias = "None")]
     fn new(
         key_column: &str,
         key_column_type: ValueType,
         full_name: Option<String>,
         description: Option<String>,
+        key_column_alias: Option<String>,
     ) -> Self {
         Self {
             key_column: key_column.to_string(),
             key_column_type,
             full_name,
             description,
-            key_column_alias: Some(key_column.to_string()),
+            key_column_alias: key_column_alias.or_else(|| Some(key_column.to_string())),
         }
     }
 
@@ -339,6 +363,7 @@ impl TypedKey {
         }
     }
 
+    /// Copy of the key with another alias
     fn as_key(&self, key_column_alias: &str) -> Self {
         let mut ret = self.clone();
         ret.key_column_alias = Some(key_column_alias.to_string());
@@ -388,6 +413,7 @@ impl Into<feathr::TypedKey> for TypedKey {
     }
 }
 
+/// Aggregation function of a window aggregation transformation
 #[allow(non_camel_case_types)]
 #[pyclass]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -466,27 +492,52 @@ impl Into<feathr::Aggregation> for Aggregation {
     }
 }
 
+/// How a feature is computed from its source or input features
 #[pyclass]
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Transformation(feathr::Transformation);
 
 #[pymethods]
 impl Transformation {
+    /// Spark SQL expression transformation
     #[new]
     fn from_str(s: &str) -> Self {
         Self(feathr::Transformation::from(s))
     }
 
+    /// Sliding window aggregation, `window` is a duration like "90d" or "3h", the default window of
+    /// the anchor group is used if not set
     #[staticmethod]
-    fn window_agg(def_expr: &str, agg_func: Aggregation, window: &str) -> PyResult<Self> {
-        Ok(Self(
-            feathr::Transformation::window_agg(
+    #[args(window = "None", group_by = "None", filter = "None", limit = "None")]
+    fn window_agg(
+        def_expr: &str,
+        agg_func: Aggregation,
+        window: Option<&str>,
+        group_by: Option<String>,
+        filter: Option<String>,
+        limit: Option<u64>,
+    ) -> PyResult<Self> {
+        let mut t = match window {
+            Some(window) => feathr::Transformation::window_agg(
                 def_expr,
                 agg_func.into(),
                 utils::str_to_dur(window)?,
-            )
-            .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
-        ))
+            ),
+            None => feathr::Transformation::window_agg_inherited(def_expr, agg_func.into()),
+        }
+        .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+        if let feathr::Transformation::WindowAgg {
+            group_by: g,
+            filter: f,
+            limit: l,
+            ..
+        } = &mut t
+        {
+            *g = group_by;
+            *f = filter;
+            *l = limit;
+        }
+        Ok(Self(t))
     }
 
     fn __repr__(&self) -> String {
@@ -501,6 +552,10 @@ impl Transformation {
         }
     }
 
+    fn __hash__(&self) -> u64 {
+        hash_json(&self.0)
+    }
+
     #[getter]
     fn __dict__<'p>(&self, py: Python<'p>) -> PyResult<PyObject> {
         let map: serde_json::Value = serde_json::to_value(&self.0)
@@ -521,30 +576,35 @@ impl Into<feathr::Transformation> for Transformation {
     }
 }
 
+/// Where data is read from or written to, parsed from a URL or a dict
 #[pyclass]
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct DataLocation(feathr::DataLocation);
+
 #[pymethods]
 impl DataLocation {
+    /// Parse the location from a URL string like "abfss://..." or "jdbc:...", or a dict of options.
+    /// Options of the `cosmos.oltp`, `kafka`, `delta` and `jdbc` formats are validated, unknown and
+    /// missing options are listed in the `ValueError`
     #[new]
     fn new<'p>(py: Python<'p>, value: &PyAny) -> PyResult<Self> {
-        if let Ok(s) = value.extract::<String>() {
-            Ok(DataLocation(
-                s.parse()
-                    .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
-            ))
+        let location: feathr::DataLocation = if let Ok(s) = value.extract::<String>() {
+            s.parse()
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
         } else {
             let dumps: Py<PyAny> = py.import("json")?.getattr("dumps")?.into();
             let ret = dumps.call1(py, PyTuple::new(py, &[value]))?;
             if let Ok(s) = ret.extract::<String>(py) {
-                Ok(DataLocation(
-                    s.parse()
-                        .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
-                ))
+                s.parse()
+                    .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
             } else {
-                Err(PyValueError::new_err("Invalid data location"))
+                return Err(PyValueError::new_err("Invalid data location"));
             }
-        }
+        };
+        location
+            .validate()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(DataLocation(location))
     }
 
     #[getter]
@@ -570,8 +630,13 @@ impl DataLocation {
             _ => Err(PyTypeError::new_err("Unsupported")),
         }
     }
+
+    fn __hash__(&self) -> u64 {
+        hash_json(&self.0)
+    }
 }
 
+/// A data source registered in the project, created with `FeathrProject.*_source`
 #[pyclass]
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct Source(feathr::Source);
@@ -613,6 +678,31 @@ impl Source {
         self.0.get_preprocessing()
     }
 
+    #[getter]
+    pub fn get_time_partition_pattern(&self) -> Option<String> {
+        self.0.get_time_partition_pattern()
+    }
+
+    /// Check if the source is reachable before submitting any job with it
+    fn test_connection(&self, client: FeathrClient) -> ConnectionTestResult {
+        block_on(async { self.0.test_connection(&client.0).await.into() })
+    }
+
+    /// Read the column names and types from the Parquet footer or the CSV header of the source file
+    fn infer_schema(&self, client: FeathrClient) -> PyResult<Vec<(String, String)>> {
+        block_on(async {
+            Ok(self
+                .0
+                .infer_schema(&client.0)
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
+                .columns
+                .into_iter()
+                .map(|c| (c.name, c.data_type))
+                .collect())
+        })
+    }
+
     fn __repr__(&self) -> String {
         format!(
             "Source(id='{}', name='{}', version={})",
@@ -630,6 +720,10 @@ impl Source {
         }
     }
 
+    fn __hash__(&self) -> u64 {
+        hash_json(&self.0)
+    }
+
     #[getter]
     fn __dict__<'p>(&self, py: Python<'p>) -> PyResult<PyObject> {
         let map: serde_json::Value = serde_json::to_value(&self.0)
@@ -650,6 +744,237 @@ impl Into<feathr::Source> for Source {
     }
 }
 
+/// Result of `Source.test_connection`, `status` is one of `Succeeded`, `Reachable`, `Failed` and `Unsupported`.
+/// JDBC and Kafka sources are only tested for reachability, their credentials are not verified
+#[pyclass]
+#[derive(Clone, Debug)]
+struct ConnectionTestResult(feathr::ConnectionTestResult);
+
+#[pymethods]
+impl ConnectionTestResult {
+    /// The source is fully verified, a reachability-only test is not a success
+    #[getter]
+    fn get_succeeded(&self) -> bool {
+        self.0.is_succeeded()
+    }
+
+    /// The endpoint accepts connections, whether or not the credentials are verified
+    #[getter]
+    fn get_reachable(&self) -> bool {
+        self.0.is_reachable()
+    }
+
+    #[getter]
+    fn get_status(&self) -> String {
+        self.0.status.to_string()
+    }
+
+    #[getter]
+    fn get_message(&self) -> String {
+        self.0.message.clone()
+    }
+
+    #[getter]
+    fn get_elapsed(&self) -> f64 {
+        self.0.elapsed.as_secs_f64()
+    }
+
+    #[getter]
+    fn get_tested_at(&self) -> String {
+        self.0.tested_at.to_rfc3339()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ConnectionTestResult(status={}, message='{}')",
+            self.0.status, self.0.message
+        )
+    }
+}
+
+impl From<feathr::ConnectionTestResult> for ConnectionTestResult {
+    fn from(v: feathr::ConnectionTestResult) -> Self {
+        Self(v)
+    }
+}
+
+/// A problem found by `FeathrProject.lint`, `severity` is one of `Error`, `Warning` and `Info`
+#[pyclass]
+#[derive(Clone, Debug)]
+struct Diagnostic(feathr::Diagnostic);
+
+#[pymethods]
+impl Diagnostic {
+    #[getter]
+    fn get_severity(&self) -> String {
+        self.0.severity.to_string()
+    }
+
+    #[getter]
+    fn get_kind(&self) -> String {
+        self.0.kind.to_string()
+    }
+
+    #[getter]
+    fn get_entity(&self) -> String {
+        self.0.entity.clone()
+    }
+
+    #[getter]
+    fn get_message(&self) -> String {
+        self.0.message.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Diagnostic(severity={}, kind={}, entity='{}', message='{}')",
+            self.0.severity, self.0.kind, self.0.entity, self.0.message
+        )
+    }
+}
+
+impl From<feathr::Diagnostic> for Diagnostic {
+    fn from(v: feathr::Diagnostic) -> Self {
+        Self(v)
+    }
+}
+
+/// A lossy or failing type conversion found by `FeathrProject.coercion_warnings`, `coercion` is one
+/// of `Narrowing`, `Cast` and `Incompatible`
+#[pyclass]
+#[derive(Clone, Debug)]
+struct CoercionWarning(feathr::CoercionWarning);
+
+#[pymethods]
+impl CoercionWarning {
+    #[getter]
+    fn get_feature(&self) -> String {
+        self.0.feature.clone()
+    }
+
+    #[getter]
+    fn get_context(&self) -> String {
+        self.0.context.clone()
+    }
+
+    #[getter]
+    fn get_from_type(&self) -> ValueType {
+        self.0.from.into()
+    }
+
+    #[getter]
+    fn get_to_type(&self) -> ValueType {
+        self.0.to.into()
+    }
+
+    #[getter]
+    fn get_coercion(&self) -> String {
+        self.0.coercion.to_string()
+    }
+
+    #[getter]
+    fn get_message(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CoercionWarning(feature='{}', context='{}', from_type={:?}, to_type={:?}, coercion={})",
+            self.0.feature, self.0.context, self.0.from, self.0.to, self.0.coercion
+        )
+    }
+}
+
+impl From<feathr::CoercionWarning> for CoercionWarning {
+    fn from(v: feathr::CoercionWarning) -> Self {
+        Self(v)
+    }
+}
+
+/// Estimated computation cost of a feature join, found by `FeathrProject.estimate_query_cost`
+#[pyclass]
+#[derive(Clone, Debug)]
+struct QueryCost(feathr::QueryCost);
+
+#[pymethods]
+impl QueryCost {
+    #[getter]
+    fn get_total(&self) -> u64 {
+        self.0.total
+    }
+
+    /// Cost of each computed feature, from the `feathr_cost` tag or inferred from the transformation
+    #[getter]
+    fn get_features(&self) -> HashMap<String, u64> {
+        self.0
+            .features
+            .iter()
+            .map(|f| (f.feature.clone(), f.cost.0))
+            .collect()
+    }
+
+    #[getter]
+    fn get_expensive(&self) -> bool {
+        self.0.is_expensive()
+    }
+
+    #[getter]
+    fn get_warnings(&self) -> Vec<String> {
+        self.0.warnings()
+    }
+
+    /// Suggested Spark configurations if the join is expensive, empty otherwise
+    #[getter]
+    fn get_sizing_hint(&self) -> HashMap<String, String> {
+        self.0
+            .sizing_hint
+            .as_ref()
+            .map(|h| h.spark_conf().into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "QueryCost(total={}, expensive={})",
+            self.0.total,
+            self.0.is_expensive()
+        )
+    }
+}
+
+/// A config as it is submitted with the jobs, `raw` is the rendered string and `parsed` is the dict
+#[pyclass]
+#[derive(Clone, Debug)]
+struct RenderedConfig(feathr::RenderedConfig);
+
+#[pymethods]
+impl RenderedConfig {
+    #[getter]
+    fn get_raw(&self) -> String {
+        self.0.content.clone()
+    }
+
+    #[getter]
+    fn get_parsed(&self, py: Python) -> PyObject {
+        value_to_py(self.0.value.clone(), py)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.content.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("RenderedConfig(size={})", self.0.content.len())
+    }
+}
+
+impl From<feathr::RenderedConfig> for RenderedConfig {
+    fn from(v: feathr::RenderedConfig) -> Self {
+        Self(v)
+    }
+}
+
+/// How a JDBC source authenticates, credentials are read from the config or env vars
 #[pyclass]
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 enum JdbcSourceAuth {
@@ -699,8 +1024,48 @@ impl Into<feathr::JdbcSourceAuth> for JdbcSourceAuth {
     }
 }
 
+/// How an Event Hubs source authenticates, `Aad` reads `<prefix>_CLIENT_ID`, `<prefix>_CLIENT_SECRET`
+/// and `<prefix>_TENANT_ID`, `ConnectionString` reads `<prefix>_CONNECTION_STRING`
+#[pyclass]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+enum EventHubAuth {
+    Aad,
+    ConnectionString,
+}
+
+#[pymethods]
+impl EventHubAuth {
+    fn __repr__(&self) -> String {
+        format!("{:#?}", &self)
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> PyResult<bool> {
+        match op {
+            CompareOp::Eq => Ok(self == other),
+            CompareOp::Ne => Ok(self != other),
+            _ => Err(PyTypeError::new_err("Unsupported")),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Into<feathr::EventHubAuth> for EventHubAuth {
+    fn into(self) -> feathr::EventHubAuth {
+        match self {
+            EventHubAuth::Aad => feathr::EventHubAuth::Aad,
+            EventHubAuth::ConnectionString => feathr::EventHubAuth::ConnectionString,
+        }
+    }
+}
+
+/// Time step of a materialization job, one job is submitted per step
 #[pyclass]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum DateTimeResolution {
     Daily,
     Hourly,
@@ -719,6 +1084,12 @@ impl DateTimeResolution {
             _ => Err(PyTypeError::new_err("Unsupported")),
         }
     }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 impl Into<feathr::DateTimeResolution> for DateTimeResolution {
@@ -730,49 +1101,107 @@ impl Into<feathr::DateTimeResolution> for DateTimeResolution {
     }
 }
 
+/// Materialize features into a Redis table
 #[pyclass]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RedisSink(feathr::RedisSink);
 
 #[pymethods]
 impl RedisSink {
+    /// `streaming_timeout` is in seconds and only used by streaming jobs
+    /// `key_transform` is "none", "sha256" or "hmac", the HMAC secret is read from the variable named by `key_secret`
     #[new]
-    #[args(streaming = "false", streaming_timeout = "None")]
-    fn new(table_name: &str, streaming: bool, streaming_timeout: Option<i64>) -> Self {
-        Self(feathr::RedisSink {
+    #[args(
+        streaming = "false",
+        streaming_timeout = "None",
+        key_transform = "\"none\"",
+        key_secret = "None"
+    )]
+    fn new(
+        table_name: &str,
+        streaming: bool,
+        streaming_timeout: Option<i64>,
+        key_transform: &str,
+        key_secret: Option<&str>,
+    ) -> PyResult<Self> {
+        let key_transform = feathr::KeyTransform::new(key_transform, key_secret)
+            .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+        Ok(Self(feathr::RedisSink {
             table_name: table_name.to_string(),
             streaming,
             streaming_timeout: streaming_timeout.map(|i| Duration::seconds(i)),
-        })
+            key_transform,
+        }))
     }
 
-    fn __repr__(&self) -> String {
-        format!("{:#?}", &self)
+    #[getter]
+    fn get_table_name(&self) -> String {
+        self.0.table_name.clone()
     }
 
     #[getter]
-    fn __dict__<'p>(&self, py: Python<'p>) -> PyResult<PyObject> {
-        let map: serde_json::Value = serde_json::to_value(&self.0)
-            .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
-        Ok(value_to_py(map, py))
+    fn get_streaming(&self) -> bool {
+        self.0.streaming
     }
-}
 
-#[pyclass]
-#[derive(Clone, Debug)]
-pub struct CosmosDbSink(feathr::GenericSink);
+    #[getter]
+    fn get_streaming_timeout(&self) -> Option<i64> {
+        self.0.streaming_timeout.map(|d| d.num_seconds())
+    }
 
-#[pymethods]
-impl CosmosDbSink {
-    #[new]
-    #[args(streaming = "false", streaming_timeout = "None")]
-    fn new(
-        name: &str,
-        endpoint: &str,
-        database: &str,
-        collection: &str,
-        streaming: bool,
-        streaming_timeout: Option<i64>,
+    /// Transform a key value the same way as the materialization job, for online lookups
+    /// `secret` is the value of the `key_secret` variable, only used by "hmac"
+    #[args(secret = "None")]
+    fn transform_key(&self, key: &str, secret: Option<&str>) -> PyResult<String> {
+        self.0
+            .key_transform
+            .apply(key, secret)
+            .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:#?}", &self)
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> PyResult<bool> {
+        match op {
+            CompareOp::Eq => Ok(self == other),
+            CompareOp::Ne => Ok(self != other),
+            _ => Err(PyTypeError::new_err("Unsupported")),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[getter]
+    fn __dict__<'p>(&self, py: Python<'p>) -> PyResult<PyObject> {
+        let map: serde_json::Value = serde_json::to_value(&self.0)
+            .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+        Ok(value_to_py(map, py))
+    }
+}
+
+/// Materialize features into a CosmosDB container, the account key is read from `<name>_KEY`
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CosmosDbSink(feathr::GenericSink);
+
+#[pymethods]
+impl CosmosDbSink {
+    /// `streaming_timeout` is in seconds and only used by streaming jobs
+    #[new]
+    #[args(streaming = "false", streaming_timeout = "None")]
+    fn new(
+        name: &str,
+        endpoint: &str,
+        database: &str,
+        collection: &str,
+        streaming: bool,
+        streaming_timeout: Option<i64>,
     ) -> Self {
         let mut options: HashMap<String, String> = HashMap::new();
         options.insert(
@@ -795,9 +1224,9 @@ impl CosmosDbSink {
             options,
         };
         Self(feathr::GenericSink {
-            location,
             streaming,
             streaming_timeout: streaming_timeout.map(|i| Duration::seconds(i)),
+            ..feathr::GenericSink::new(location)
         })
     }
 
@@ -810,6 +1239,18 @@ impl CosmosDbSink {
         format!("{:#?}", &self)
     }
 
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> PyResult<bool> {
+        match op {
+            CompareOp::Eq => Ok(self == other),
+            CompareOp::Ne => Ok(self != other),
+            _ => Err(PyTypeError::new_err("Unsupported")),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash_json(&self.0)
+    }
+
     #[getter]
     fn __dict__<'p>(&self, py: Python<'p>) -> PyResult<PyObject> {
         let map: serde_json::Value = serde_json::to_value(&self.0)
@@ -818,31 +1259,69 @@ impl CosmosDbSink {
     }
 }
 
+/// A sink receiving only `features`, with the key columns renamed by `key_mapping`, all materialized
+/// features are written to the sink if `features` is not set
 #[pyclass]
 #[derive(Clone, Debug)]
-struct ObservationSettings(feathr::ObservationSettings);
+struct SinkSelection(feathr::SinkSelection);
 
 #[pymethods]
-impl ObservationSettings {
+impl SinkSelection {
     #[new]
-    #[args(timestamp_column = "None", format = "None")]
+    #[args(features = "None", key_mapping = "None")]
     fn new(
-        observation_path: &str,
-        timestamp_column: Option<&str>,
-        format: Option<&str>,
+        sink: &PyAny,
+        features: Option<Vec<String>>,
+        key_mapping: Option<HashMap<String, String>>,
     ) -> PyResult<Self> {
+        let mut selection = feathr::SinkSelection::new(parse_sink(sink)?);
+        if let Some(features) = features {
+            selection = selection.features(&features);
+        }
+        for (key_column, output_column) in key_mapping.into_iter().flatten() {
+            selection = selection.key_mapping(&key_column, &output_column);
+        }
+        Ok(Self(selection))
+    }
+
+    #[getter]
+    fn get_features(&self) -> Option<Vec<String>> {
+        self.0.features.clone()
+    }
+
+    #[getter]
+    fn get_key_mapping(&self) -> HashMap<String, String> {
+        self.0.key_mapping.clone().into_iter().collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SinkSelection(sink='{}', features={:?}, key_mapping={:?})",
+            self.0.sink.get_location(),
+            self.0.features,
+            self.0.key_mapping
+        )
+    }
+}
+
+/// The observation data features are joined to
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ObservationSettings(feathr::ObservationSettings);
+
+#[pymethods]
+impl ObservationSettings {
+    /// Features are joined by event time if `timestamp_column` is set, `format` is "epoch",
+    /// "epoch_millis" or a Java `SimpleDateFormat` pattern like "yyyy-MM-dd HH:mm:ss",
+    /// raises `ValueError` if the pattern is invalid
+    #[new]
+    #[args(timestamp_column = "None", format = "\"epoch\"")]
+    fn new(observation_path: &str, timestamp_column: Option<&str>, format: &str) -> PyResult<Self> {
         if let Some(timestamp_column) = timestamp_column {
-            if let Some(format) = format {
-                Ok(Self(
-                    feathr::ObservationSettings::new(observation_path, timestamp_column, format)
-                        .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
-                ))
-            } else {
-                Ok(Self(
-                    feathr::ObservationSettings::new(observation_path, timestamp_column, "epoch")
-                        .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
-                ))
-            }
+            Ok(Self(
+                feathr::ObservationSettings::new(observation_path, timestamp_column, format)
+                    .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
+            ))
         } else {
             Ok(Self(
                 feathr::ObservationSettings::from_path(observation_path)
@@ -851,10 +1330,46 @@ impl ObservationSettings {
         }
     }
 
+    #[getter]
+    fn get_observation_path(&self) -> DataLocation {
+        DataLocation(self.0.observation_path.clone())
+    }
+
+    #[getter]
+    fn get_timestamp_column(&self) -> Option<String> {
+        self.0
+            .settings
+            .as_ref()
+            .map(|s| s.join_time_settings.timestamp_column.def.clone())
+    }
+
+    #[getter]
+    fn get_timestamp_format(&self) -> Option<String> {
+        self.0.settings.as_ref().map(|s| {
+            s.join_time_settings
+                .timestamp_column
+                .format
+                .as_str()
+                .to_string()
+        })
+    }
+
     fn __repr__(&self) -> String {
         format!("{:#?}", &self)
     }
 
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> PyResult<bool> {
+        match op {
+            CompareOp::Eq => Ok(self == other),
+            CompareOp::Ne => Ok(self != other),
+            _ => Err(PyTypeError::new_err("Unsupported")),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        hash_json(&self.0)
+    }
+
     #[getter]
     fn __dict__<'p>(&self, py: Python<'p>) -> PyResult<PyObject> {
         let map: serde_json::Value = serde_json::to_value(&self.0)
@@ -863,15 +1378,20 @@ impl ObservationSettings {
     }
 }
 
+/// Features to join with the observation data and the keys to join on
 #[pyclass]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct FeatureQuery(feathr::FeatureQuery);
 
 #[pymethods]
 impl FeatureQuery {
+    /// `names` are feature names or feature objects, features without keys are joined by the dummy key
     #[new]
-    fn new(names: &PyList, keys: Vec<TypedKey>) -> Self {
-        let keys: Vec<feathr::TypedKey> = keys.into_iter().map(|k| k.into()).collect();
+    #[args(keys = "None")]
+    fn new(names: &PyList, keys: Option<Vec<TypedKey>>) -> Self {
+        let keys: Vec<feathr::TypedKey> = keys
+            .map(|keys| keys.into_iter().map(|k| k.into()).collect())
+            .unwrap_or_else(|| vec![feathr::TypedKey::DUMMY_KEY()]);
         let keys: Vec<&feathr::TypedKey> = keys.iter().map(|k| k).collect();
         let mut n: Vec<String> = vec![];
         for name in names.into_iter() {
@@ -886,14 +1406,47 @@ impl FeatureQuery {
         Self(feathr::FeatureQuery::new(&n, &keys))
     }
 
+    /// Query features without keys, e.g. request features
     #[staticmethod]
     fn by_name(names: Vec<&str>) -> Self {
         Self(feathr::FeatureQuery::by_name(&names))
     }
+
+    #[getter]
+    fn get_feature_list(&self) -> Vec<String> {
+        self.0.feature_list.clone()
+    }
+
+    #[getter]
+    fn get_key(&self) -> Vec<String> {
+        self.0.key.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "FeatureQuery(feature_list={:?}, key={:?})",
+            self.0.feature_list, self.0.key
+        )
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> PyResult<bool> {
+        match op {
+            CompareOp::Eq => Ok(self == other),
+            CompareOp::Ne => Ok(self != other),
+            _ => Err(PyTypeError::new_err("Unsupported")),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
+/// Status of a submitted Spark job
 #[pyclass]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum JobStatus {
     Starting,
     Running,
@@ -914,6 +1467,12 @@ impl JobStatus {
             _ => Err(PyTypeError::new_err("Unsupported")),
         }
     }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 impl From<feathr::JobStatus> for JobStatus {
@@ -927,6 +1486,7 @@ impl From<feathr::JobStatus> for JobStatus {
     }
 }
 
+/// A feature computed from the source of its anchor group
 #[pyclass]
 #[derive(Clone, Debug)]
 struct AnchorFeature(feathr::AnchorFeature);
@@ -1020,6 +1580,7 @@ impl Into<feathr::AnchorFeature> for AnchorFeature {
     }
 }
 
+/// A feature computed from other features
 #[pyclass]
 #[derive(Clone, Debug)]
 struct DerivedFeature(feathr::DerivedFeature);
@@ -1116,6 +1677,7 @@ impl Into<feathr::DerivedFeature> for DerivedFeature {
     }
 }
 
+/// A group of anchor features sharing the same source
 #[pyclass]
 #[derive(Clone, Debug)]
 struct AnchorGroup(feathr::AnchorGroup);
@@ -1139,6 +1701,7 @@ impl AnchorGroup {
         block_on(async { self.0.get_anchor_features().await })
     }
 
+    /// Add an anchor feature to the group, `transform` is a Spark SQL expression or a `Transformation`
     #[args(keys = "None", registry_tags = "None")]
     fn anchor_feature(
         &self,
@@ -1180,6 +1743,58 @@ impl AnchorGroup {
         })
     }
 
+    /// Add a batch of anchor features generated from a template, `{param}` placeholders in the
+    /// name, the transformation and the tag values are replaced with the values of each dict in
+    /// `params`, a window aggregation also takes its window from the `window` parameter
+    #[args(keys = "None", registry_tags = "None")]
+    fn anchor_features_from_template(
+        &self,
+        name_pattern: &str,
+        feature_type: FeatureType,
+        transform: &PyAny,
+        params: Vec<HashMap<String, &PyAny>>,
+        keys: Option<Vec<TypedKey>>,
+        registry_tags: Option<HashMap<String, String>>,
+    ) -> PyResult<Vec<AnchorFeature>> {
+        let mut template = if let Ok(transform) = transform.extract::<String>() {
+            feathr::FeatureTemplate::new(name_pattern, feature_type.into(), transform)
+        } else if let Ok(transform) = transform.extract::<Transformation>() {
+            feathr::FeatureTemplate::new(name_pattern, feature_type.into(), transform)
+        } else {
+            return Err(PyValueError::new_err(
+                "`transform` must be string or Transformation object",
+            ));
+        };
+        if let Some(keys) = keys {
+            let keys: Vec<feathr::TypedKey> = keys.into_iter().map(|k| k.into()).collect();
+            let k: Vec<&feathr::TypedKey> = keys.iter().map(|k| k).collect();
+            template.keys(&k);
+        }
+        if let Some(registry_tags) = registry_tags {
+            for (key, value) in registry_tags.into_iter() {
+                template.add_tag(&key, &value);
+            }
+        }
+        // Values can be any Python object, e.g. `{"window": "7d", "limit": 10}`
+        let params = params
+            .into_iter()
+            .map(|p| {
+                p.into_iter()
+                    .map(|(k, v)| Ok((k, v.str()?.to_string())))
+                    .collect::<PyResult<HashMap<String, String>>>()
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        block_on(async {
+            Ok(template
+                .build(&self.0, params)
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
+                .into_iter()
+                .map(|f| f.into())
+                .collect())
+        })
+    }
+
     fn __getitem__(&self, key: &str) -> PyResult<AnchorFeature> {
         block_on(async {
             Ok(self
@@ -1212,6 +1827,7 @@ impl Into<feathr::AnchorGroup> for AnchorGroup {
     }
 }
 
+/// A feathr project, created or loaded with `FeathrClient`
 #[pyclass]
 struct FeathrProject(feathr::FeathrProject, FeathrClient);
 
@@ -1230,6 +1846,10 @@ impl FeathrProject {
         block_on(async { self.0.get_name().await.to_string() })
     }
     #[getter]
+    pub fn get_default_tags(&self) -> HashMap<String, String> {
+        block_on(async { self.0.get_default_tags().await })
+    }
+    #[getter]
     pub fn get_input_context(&self) -> Source {
         block_on(async { self.0.INPUT_CONTEXT().await.into() })
     }
@@ -1249,12 +1869,230 @@ impl FeathrProject {
 
     pub fn get_source(&self, name: &str) -> PyResult<Source> {
         block_on(async {
-            Ok(self
+            Ok(self
+                .0
+                .get_source(name)
+                .await
+                .map_err(|_| PyKeyError::new_err(name.to_string()))?
+                .into())
+        })
+    }
+
+    pub fn test_source_connection(&self, name: &str) -> PyResult<ConnectionTestResult> {
+        block_on(async {
+            Ok(self
+                .0
+                .test_source_connection(name)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
+                .into())
+        })
+    }
+
+    /// Markdown documentation of the project, or of the feature if `feature` is given
+    #[args(feature = "None")]
+    pub fn get_docs(&self, feature: Option<&str>) -> PyResult<String> {
+        block_on(async {
+            match feature {
+                Some(name) => self.0.get_feature_docs(name).await,
+                None => self.0.get_docs().await,
+            }
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })
+    }
+
+    /// Replace the markdown documentation of the project, or of the feature if `feature` is given
+    #[args(feature = "None")]
+    pub fn set_docs(&self, docs: &str, feature: Option<&str>) -> PyResult<()> {
+        block_on(async {
+            match feature {
+                Some(name) => self.0.set_feature_docs(name, docs).await,
+                None => self.0.set_docs(docs).await,
+            }
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })
+    }
+
+    /// Delete the source, it must not be used by any anchor group
+    pub fn delete_source(&self, name: &str) -> PyResult<()> {
+        block_on(async { self.0.delete_source(name).await.map_err(delete_error) })
+    }
+
+    /// Delete the anchor group, it must not contain any feature
+    pub fn delete_anchor_group(&self, name: &str) -> PyResult<()> {
+        block_on(async { self.0.delete_anchor_group(name).await.map_err(delete_error) })
+    }
+
+    /// Delete the anchor or derived feature, it must not be used by any derived feature
+    pub fn delete_feature(&self, name: &str) -> PyResult<()> {
+        block_on(async { self.0.delete_feature(name).await.map_err(delete_error) })
+    }
+
+    /// Check the feature definitions before submitting jobs, features not used by `feature_query` are
+    /// reported if it is given
+    #[args(feature_query = "None")]
+    pub fn lint(&self, feature_query: Option<&PyList>) -> PyResult<Vec<Diagnostic>> {
+        let queries = parse_feature_queries(feature_query)?;
+        let queries: Vec<&feathr::FeatureQuery> = queries.iter().collect();
+        block_on(async {
+            Ok(self
+                .0
+                .lint(&queries)
+                .await
+                .diagnostics
+                .into_iter()
+                .map(Diagnostic::from)
+                .collect())
+        })
+    }
+
+    /// Lossy or failing type conversions in the feature join of `feature_query`, e.g. an INT32 feature
+    /// aggregated with AVG, they're also logged when the join job is submitted
+    pub fn coercion_warnings(&self, feature_query: &PyList) -> PyResult<Vec<CoercionWarning>> {
+        let queries = parse_feature_queries(Some(feature_query))?;
+        let queries: Vec<&feathr::FeatureQuery> = queries.iter().collect();
+        block_on(async {
+            Ok(self
+                .0
+                .coercion_warnings(&queries)
+                .await
+                .map_err(|e| PyValueError::new_err(e.to_string()))?
+                .into_iter()
+                .map(CoercionWarning::from)
+                .collect())
+        })
+    }
+
+    /// Estimated computation cost of the feature join of `feature_query`, features are annotated with
+    /// the `feathr_cost` registry tag, `low`, `medium`, `high` or a number, or inferred from the transformation
+    pub fn estimate_query_cost(&self, feature_query: &PyList) -> PyResult<QueryCost> {
+        let queries = parse_feature_queries(Some(feature_query))?;
+        let queries: Vec<&feathr::FeatureQuery> = queries.iter().collect();
+        block_on(async {
+            Ok(QueryCost(
+                self.0
+                    .estimate_query_cost(&queries)
+                    .await
+                    .map_err(|e| PyValueError::new_err(e.to_string()))?,
+            ))
+        })
+    }
+
+    /// Check the columns used by anchor features against the schemas of the sources on the storage
+    pub fn validate_columns(&self) -> PyResult<Vec<Diagnostic>> {
+        block_on(async {
+            Ok(self
+                .0
+                .validate_columns()
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
+                .diagnostics
+                .into_iter()
+                .map(Diagnostic::from)
+                .collect())
+        })
+    }
+
+    /// Generate a ready-to-run script getting the offline features of the project and materializing them, the project must be registered
+    #[args(lang = "\"python\"")]
+    pub fn codegen(&self, lang: &str) -> PyResult<String> {
+        block_on(async {
+            self.0
+                .codegen(lang)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })
+    }
+
+    /// The feature config submitted with the jobs of the project
+    pub fn get_feature_config(&self) -> PyResult<RenderedConfig> {
+        block_on(async {
+            Ok(self
+                .0
+                .render_feature_config()
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
+                .into())
+        })
+    }
+
+    /// The join config `get_offline_features` would submit, the output path is empty if `output` is not set
+    #[args(output = "None")]
+    pub fn get_join_config(
+        &self,
+        observation: &PyAny,
+        feature_query: &PyList,
+        output: Option<String>,
+    ) -> PyResult<RenderedConfig> {
+        let observation: ObservationSettings = observation.extract()?;
+        let queries = parse_feature_queries(Some(feature_query))?;
+        let queries: Vec<&feathr::FeatureQuery> = queries.iter().collect();
+        block_on(async {
+            Ok(self
+                .0
+                .render_join_config(observation.0, &queries, output.as_deref())
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
+                .into())
+        })
+    }
+
+    /// The generation configs `materialize_features` would submit, one per step and batch
+    #[args(
+        step = "DateTimeResolution::Daily",
+        sink = "None",
+        tz = "None",
+        alignment = "None",
+        end_inclusive = "false"
+    )]
+    pub fn get_gen_config(
+        &self,
+        features: &PyList,
+        start: DateTimeArg,
+        end: DateTimeArg,
+        step: DateTimeResolution,
+        sink: Option<&PyAny>,
+        tz: Option<&str>,
+        alignment: Option<&str>,
+        end_inclusive: bool,
+    ) -> PyResult<Vec<RenderedConfig>> {
+        let mut feature_names: Vec<String> = vec![];
+        for f in features.into_iter() {
+            if let Ok(f) = f.extract::<AnchorFeature>() {
+                feature_names.push(f.get_name());
+            } else if let Ok(f) = f.extract::<DerivedFeature>() {
+                feature_names.push(f.get_name());
+            } else if let Ok(f) = f.extract::<String>() {
+                feature_names.push(f);
+            }
+        }
+        let (sink, selected_sinks) = match sink {
+            Some(sink) => parse_sinks(sink)?,
+            None => Default::default(),
+        };
+        let window = parse_window_options(tz, alignment, end_inclusive)?;
+        block_on(async {
+            let mut builder = self
                 .0
-                .get_source(name)
+                .feature_gen_job(
+                    &feature_names,
+                    to_utc(start, &window),
+                    to_utc(end, &window),
+                    step.into(),
+                )
                 .await
-                .map_err(|_| PyKeyError::new_err(name.to_string()))?
-                .into())
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+            builder.window_options(window);
+            builder.sinks(&sink);
+            for selection in selected_sinks {
+                builder.selected_sink(selection);
+            }
+            Ok(builder
+                .render_job_configs()
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
+                .into_iter()
+                .map(RenderedConfig::from)
+                .collect())
         })
     }
 
@@ -1311,14 +2149,39 @@ impl FeathrProject {
         })
     }
 
-    #[args(registry_tags = "None")]
+    /// Add an anchor group, sources in `joined_sources` are left joined to `source` on `join_keys`.
+    /// `preprocessing` is only applied to this group, after the preprocessing of the sources.
+    /// Window aggregation features without a window use `default_window`
+    #[args(
+        registry_tags = "None",
+        joined_sources = "None",
+        join_keys = "None",
+        preprocessing = "None",
+        default_window = "None"
+    )]
     pub fn anchor_group(
         &self,
         name: &str,
         source: Source,
         registry_tags: Option<HashMap<String, String>>,
+        joined_sources: Option<Vec<Source>>,
+        join_keys: Option<Vec<String>>,
+        preprocessing: Option<String>,
+        default_window: Option<&str>,
     ) -> PyResult<AnchorGroup> {
         let mut builder = self.0.anchor_group(name, source.into());
+        for source in joined_sources.unwrap_or_default() {
+            builder.join_source(source.into());
+        }
+        if let Some(join_keys) = join_keys {
+            builder.join_keys(&join_keys.iter().map(|k| k.as_str()).collect::<Vec<_>>());
+        }
+        if let Some(preprocessing) = preprocessing {
+            builder.preprocessing(&preprocessing);
+        }
+        if let Some(window) = default_window {
+            builder.default_window(utils::str_to_dur(window)?);
+        }
         if let Some(registry_tags) = registry_tags {
             for (key, value) in registry_tags.into_iter() {
                 builder.add_registry_tag(&key, &value);
@@ -1333,13 +2196,16 @@ impl FeathrProject {
         })
     }
 
-    #[args(keys = "None", registry_tags = "None")]
+    /// Add a derived feature computed from `inputs`, which are anchor or derived features, or
+    /// qualified names of features in other projects, e.g. "shared__anchor1__f1".
+    /// Inputs are detected from the features named in the transform expression if not set
+    #[args(inputs = "None", keys = "None", registry_tags = "None")]
     pub fn derived_feature(
         &self,
         name: &str,
         feature_type: FeatureType,
         transform: &PyAny,
-        inputs: &PyList,
+        inputs: Option<&PyList>,
         keys: Option<Vec<TypedKey>>,
         registry_tags: Option<HashMap<String, String>>,
     ) -> PyResult<DerivedFeature> {
@@ -1358,16 +2224,18 @@ impl FeathrProject {
             let k: Vec<&feathr::TypedKey> = keys.iter().map(|k| k).collect();
             builder.keys(&k);
         }
-        for f in inputs.iter() {
+        for f in inputs.into_iter().flat_map(|i| i.iter()) {
             if let Ok(f) = f.extract::<AnchorFeature>() {
                 let f: feathr::AnchorFeature = f.to_owned().into();
                 builder.add_input(&f);
             } else if let Ok(f) = f.extract::<DerivedFeature>() {
                 let f: feathr::DerivedFeature = f.to_owned().into();
                 builder.add_input(&f);
+            } else if let Ok(name) = f.extract::<String>() {
+                builder.add_shared_input(&name);
             } else {
                 return Err(PyTypeError::new_err(
-                    "Inputs must be list of AnchorFeature or DerivedFeature",
+                    "Inputs must be list of AnchorFeature, DerivedFeature or qualified feature name",
                 ));
             }
         }
@@ -1388,8 +2256,14 @@ impl FeathrProject {
     #[args(
         timestamp_column = "None",
         timestamp_column_format = "None",
-        preprocessing = "None"
+        preprocessing = "None",
+        time_partition_pattern = "None",
+        time_partition_start = "None",
+        time_partition_end = "None"
     )]
+    /// Add a source of files in HDFS compatible storage, e.g. "abfss://..." or "wasbs://..."
+    /// `time_partition_pattern` is the layout of time-partitioned files under `path`, e.g. "{yyyy}/{MM}/{dd}",
+    /// only the partitions between `time_partition_start` and `time_partition_end` are read if they're set
     pub fn hdfs_source(
         &self,
         name: &str,
@@ -1397,6 +2271,9 @@ impl FeathrProject {
         timestamp_column: Option<String>,
         timestamp_column_format: Option<String>,
         preprocessing: Option<String>, // TODO: Use PyCallable?
+        time_partition_pattern: Option<String>,
+        time_partition_start: Option<DateTimeArg>,
+        time_partition_end: Option<DateTimeArg>,
     ) -> PyResult<Source> {
         let mut builder = self.0.hdfs_source(name, path);
         if let Some(timestamp_column) = timestamp_column {
@@ -1409,6 +2286,20 @@ impl FeathrProject {
             }
         }
 
+        if let Some(pattern) = time_partition_pattern {
+            // Partitions are always in UTC
+            let utc = Default::default();
+            builder.time_partition(
+                &pattern,
+                time_partition_start.map(|dt| to_utc(dt, &utc)),
+                time_partition_end.map(|dt| to_utc(dt, &utc)),
+            );
+        } else if time_partition_start.is_some() || time_partition_end.is_some() {
+            return Err(PyValueError::new_err(
+                "time_partition_pattern must not be omitted",
+            ));
+        }
+
         if let Some(preprocessing) = preprocessing {
             builder.preprocessing(&preprocessing);
         }
@@ -1428,8 +2319,12 @@ impl FeathrProject {
         auth = "None",
         timestamp_column = "None",
         timestamp_column_format = "None",
-        preprocessing = "None"
+        preprocessing = "None",
+        credential_prefix = "None"
     )]
+    /// Add a JDBC source reading `dbtable` or the result of `query`
+    /// Credentials are read from `<name>_USER`, `<name>_PASSWORD` or `<name>_TOKEN`, or from the ones
+    /// prefixed with `credential_prefix`, and fall back to the global `JDBC_*` ones
     pub fn jdbc_source(
         &self,
         name: &str,
@@ -1440,6 +2335,7 @@ impl FeathrProject {
         timestamp_column: Option<String>,
         timestamp_column_format: Option<String>,
         preprocessing: Option<String>, // TODO: Use PyCallable?
+        credential_prefix: Option<String>,
     ) -> PyResult<Source> {
         let mut builder = self.0.jdbc_source(name, url);
 
@@ -1459,6 +2355,10 @@ impl FeathrProject {
             builder.auth(auth.into());
         }
 
+        if let Some(credential_prefix) = credential_prefix {
+            builder.credential_prefix(&credential_prefix);
+        }
+
         if let Some(timestamp_column) = timestamp_column {
             if let Some(timestamp_column_format) = timestamp_column_format {
                 builder.time_window(&timestamp_column, &timestamp_column_format);
@@ -1488,6 +2388,7 @@ impl FeathrProject {
         timestamp_column_format = "None",
         preprocessing = "None"
     )]
+    /// Add a CosmosDB source, the account key is read from `<name>_KEY`
     pub fn cosmosdb_source(
         &self,
         name: &str,
@@ -1535,11 +2436,62 @@ impl FeathrProject {
     }
     // pub fn kafka_source(&self, name: &str, brokers: &PyList, topics: &PyList, avro_json: &PyAny) {}
 
+    #[args(
+        consumer_group = "None",
+        auth = "None",
+        credential_prefix = "None",
+        avro_json = "None"
+    )]
+    /// Add a streaming source reading `event_hub` through the Kafka endpoint of the Event Hubs `namespace`
+    /// Credentials are read from `<name>_*` or `<credential_prefix>_*` according to `auth`, AAD by default
+    pub fn eventhub_source(
+        &self,
+        name: &str,
+        namespace: &str,
+        event_hub: &str,
+        consumer_group: Option<String>,
+        auth: Option<EventHubAuth>,
+        credential_prefix: Option<String>,
+        avro_json: Option<String>,
+    ) -> PyResult<Source> {
+        let mut builder = self.0.eventhub_source(name, namespace, event_hub);
+
+        if let Some(consumer_group) = consumer_group {
+            builder.consumer_group(&consumer_group);
+        }
+
+        if let Some(auth) = auth {
+            builder.auth(auth.into());
+        }
+
+        if let Some(credential_prefix) = credential_prefix {
+            builder.credential_prefix(&credential_prefix);
+        }
+
+        if let Some(avro_json) = avro_json {
+            builder.avro_json(&avro_json);
+        }
+
+        block_on(async {
+            Ok(builder
+                .build()
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
+                .into())
+        })
+    }
+
+    /// Submit a feature join job and return the job id, `output` is a URL or a `DataLocation`
+    /// With `track_consumption`, the output is registered as a dataset consuming the features once the job succeeded
+    /// `spark_pool` overrides the configured Azure Synapse Spark pool for this job
+    #[args(track_consumption = "false", spark_pool = "None")]
     fn get_offline_features(
         &self,
         observation: &PyAny,
         feature_query: &PyList,
         output: &PyAny,
+        track_consumption: bool,
+        spark_pool: Option<String>,
     ) -> PyResult<u64> {
         let observation: ObservationSettings = observation.extract()?;
         let observation = observation.0;
@@ -1570,7 +2522,7 @@ impl FeathrProject {
         };
 
         block_on(async {
-            let request = self
+            let mut builder = self
                 .0
                 .feature_join_job(
                     observation,
@@ -1580,24 +2532,35 @@ impl FeathrProject {
                         .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
                 )
                 .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?;
+            builder
                 .output_location(output)
                 .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
-                .build();
+                .track_consumption(track_consumption);
+            if let Some(pool) = &spark_pool {
+                builder.spark_pool(pool);
+            }
+            let request = builder
+                .build()
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
             let client = self.1 .0.clone();
             Ok(client
                 .submit_job(request)
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
+                .id
                 .0)
         })
     }
 
+    #[args(track_consumption = "false", spark_pool = "None")]
     fn get_offline_features_async<'p>(
         &'p self,
         observation: &PyAny,
         feature_query: &PyList,
         output: &PyAny,
+        track_consumption: bool,
+        spark_pool: Option<String>,
         py: Python<'p>,
     ) -> PyResult<&'p PyAny> {
         let observation: ObservationSettings = observation.extract()?;
@@ -1631,7 +2594,7 @@ impl FeathrProject {
 
         pyo3_asyncio::tokio::future_into_py(py, async move {
             let queries: Vec<&feathr::FeatureQuery> = queries.iter().map(|q| q).collect();
-            let request = project
+            let mut builder = project
                 .feature_join_job(
                     observation,
                     &queries,
@@ -1640,26 +2603,49 @@ impl FeathrProject {
                         .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?,
                 )
                 .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?;
+            builder
                 .output_location(output)
                 .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?
-                .build();
+                .track_consumption(track_consumption);
+            if let Some(pool) = &spark_pool {
+                builder.spark_pool(pool);
+            }
+            let request = builder
+                .build()
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
             Ok(client
                 .submit_job(request)
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
+                .id
                 .0)
         })
     }
 
-    #[args(step = "DateTimeResolution::Daily")]
+    /// Submit feature generation jobs, one per `step` between `start` and `end`, and return the job ids
+    /// `sink` is a sink, a `SinkSelection` or a list of them, plain sinks get all features
+    /// `spark_pool` overrides the configured Azure Synapse Spark pool for these jobs
+    /// `tz` is a UTC offset like `+08:00`, `start` and `end` are read and the windows are aligned in it
+    /// `alignment` is `none`, `strict` or `expand`, `end_inclusive` also materializes the window containing `end`
+    #[args(
+        step = "DateTimeResolution::Daily",
+        spark_pool = "None",
+        tz = "None",
+        alignment = "None",
+        end_inclusive = "false"
+    )]
     fn materialize_features(
         &self,
         features: &PyList,
-        start: &PyDateTime,
-        end: &PyDateTime,
+        start: DateTimeArg,
+        end: DateTimeArg,
         step: DateTimeResolution,
         sink: &PyAny,
+        spark_pool: Option<String>,
+        tz: Option<&str>,
+        alignment: Option<&str>,
+        end_inclusive: bool,
     ) -> PyResult<Vec<u64>> {
         let mut feature_names: Vec<String> = vec![];
         for f in features.into_iter() {
@@ -1672,49 +2658,10 @@ impl FeathrProject {
             }
         }
 
-        let start: DateTime<Utc> = Utc
-            .ymd(
-                start.get_year(),
-                start.get_month() as u32,
-                start.get_day() as u32,
-            )
-            .and_hms(
-                start.get_hour() as u32,
-                start.get_minute() as u32,
-                start.get_second() as u32,
-            );
-        let end: DateTime<Utc> = Utc
-            .ymd(end.get_year(), end.get_month() as u32, end.get_day() as u32)
-            .and_hms(
-                end.get_hour() as u32,
-                end.get_minute() as u32,
-                end.get_second() as u32,
-            );
-        let sink: Vec<feathr::OutputSink> = if sink.is_none() {
-            vec![]
-        } else if let Ok(sink) = sink.extract::<RedisSink>() {
-            vec![feathr::OutputSink::Redis(sink.0)]
-        } else if let Ok(sink) = sink.extract::<CosmosDbSink>() {
-            vec![feathr::OutputSink::Hdfs(sink.0)]
-        } else if let Ok(sink) = sink.extract::<Vec<&PyAny>>() {
-            let mut sinks: Vec<feathr::OutputSink> = vec![];
-            for s in sink.into_iter() {
-                if let Ok(sink) = s.extract::<RedisSink>() {
-                    sinks.push(feathr::OutputSink::Redis(sink.0));
-                } else if let Ok(sink) = s.extract::<CosmosDbSink>() {
-                    sinks.push(feathr::OutputSink::Hdfs(sink.0));
-                } else {
-                    return Err(PyValueError::new_err(format!(
-                        "sink must be RedisSink or CosmosDbSink"
-                    )));
-                }
-            }
-            sinks
-        } else {
-            return Err(PyTypeError::new_err(format!(
-                "sink must be None or RedisSink or CosmosDbSink"
-            )));
-        };
+        let window = parse_window_options(tz, alignment, end_inclusive)?;
+        let start = to_utc(start, &window);
+        let end = to_utc(end, &window);
+        let (sink, selected_sinks) = parse_sinks(sink)?;
 
         block_on(async {
             let mut builder = self
@@ -1722,31 +2669,45 @@ impl FeathrProject {
                 .feature_gen_job(&feature_names, start, end, step.into())
                 .await
                 .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+            builder.window_options(window);
             builder.sinks(&sink);
+            for selection in selected_sinks {
+                builder.selected_sink(selection);
+            }
+            if let Some(pool) = &spark_pool {
+                builder.spark_pool(pool);
+            }
 
             let request = builder
                 .build()
                 .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
             let client = self.1 .0.clone();
-            let jobs_ids: Vec<u64> = client
+            let results = client
                 .submit_jobs(request)
                 .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
-                .into_iter()
-                .map(|job_id| job_id.0)
-                .collect();
-            Ok(jobs_ids)
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?;
+            submitted_job_ids(results)
         })
     }
 
-    #[args(step = "DateTimeResolution::Daily")]
+    #[args(
+        step = "DateTimeResolution::Daily",
+        spark_pool = "None",
+        tz = "None",
+        alignment = "None",
+        end_inclusive = "false"
+    )]
     fn materialize_features_async<'p>(
         &'p self,
         features: &PyList,
-        start: &PyDateTime,
-        end: &PyDateTime,
+        start: DateTimeArg,
+        end: DateTimeArg,
         step: DateTimeResolution,
         sink: &PyAny,
+        spark_pool: Option<String>,
+        tz: Option<&str>,
+        alignment: Option<&str>,
+        end_inclusive: bool,
         py: Python<'p>,
     ) -> PyResult<&'p PyAny> {
         let mut feature_names: Vec<String> = vec![];
@@ -1759,70 +2720,35 @@ impl FeathrProject {
                 feature_names.push(f);
             }
         }
-        let start: DateTime<Utc> = Utc
-            .ymd(
-                start.get_year(),
-                start.get_month() as u32,
-                start.get_day() as u32,
-            )
-            .and_hms(
-                start.get_hour() as u32,
-                start.get_minute() as u32,
-                start.get_second() as u32,
-            );
-        let end: DateTime<Utc> = Utc
-            .ymd(end.get_year(), end.get_month() as u32, end.get_day() as u32)
-            .and_hms(
-                end.get_hour() as u32,
-                end.get_minute() as u32,
-                end.get_second() as u32,
-            );
+        let window = parse_window_options(tz, alignment, end_inclusive)?;
+        let start = to_utc(start, &window);
+        let end = to_utc(end, &window);
         let client = self.1 .0.clone();
         let project = self.0.clone();
-        let sink: Vec<feathr::OutputSink> = if sink.is_none() {
-            vec![]
-        } else if let Ok(sink) = sink.extract::<RedisSink>() {
-            vec![feathr::OutputSink::Redis(sink.0)]
-        } else if let Ok(sink) = sink.extract::<CosmosDbSink>() {
-            vec![feathr::OutputSink::Hdfs(sink.0)]
-        } else if let Ok(sink) = sink.extract::<Vec<&PyAny>>() {
-            let mut sinks: Vec<feathr::OutputSink> = vec![];
-            for s in sink.into_iter() {
-                if let Ok(sink) = s.extract::<RedisSink>() {
-                    sinks.push(feathr::OutputSink::Redis(sink.0));
-                } else if let Ok(sink) = s.extract::<CosmosDbSink>() {
-                    sinks.push(feathr::OutputSink::Hdfs(sink.0));
-                } else {
-                    return Err(PyValueError::new_err(format!(
-                        "sink must be RedisSink or CosmosDbSink"
-                    )));
-                }
-            }
-            sinks
-        } else {
-            return Err(PyTypeError::new_err(format!(
-                "sink must be None or RedisSink or CosmosDbSink"
-            )));
-        };
+        let (sink, selected_sinks) = parse_sinks(sink)?;
 
         pyo3_asyncio::tokio::future_into_py(py, async move {
             let mut builder = project
                 .feature_gen_job(&feature_names, start, end, step.into())
                 .await
                 .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+            builder.window_options(window);
             builder.sinks(&sink);
+            for selection in selected_sinks {
+                builder.selected_sink(selection);
+            }
+            if let Some(pool) = &spark_pool {
+                builder.spark_pool(pool);
+            }
 
             let request = builder
                 .build()
                 .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
-            let jobs_ids: Vec<u64> = client
+            let results = client
                 .submit_jobs(request)
                 .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
-                .into_iter()
-                .map(|job_id| job_id.0)
-                .collect();
-            Ok(jobs_ids)
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?;
+            submitted_job_ids(results)
         })
     }
 
@@ -1848,74 +2774,139 @@ impl Into<feathr::FeathrProject> for FeathrProject {
     }
 }
 
+/// Client connecting to the Spark cluster and the feature registry
 #[pyclass]
 #[derive(Clone)]
-struct FeathrClient(feathr::FeathrClient);
+struct FeathrClient(feathr::FeathrClient, CancelToken);
+
+impl From<feathr::FeathrClient> for FeathrClient {
+    fn from(c: feathr::FeathrClient) -> Self {
+        Self(c, Default::default())
+    }
+}
 
 #[pymethods]
 impl FeathrClient {
+    /// Load the client with the config file, `overrides` take precedence over environment variables and the file.
+    /// `profile` selects the named profile in the file, `FEATHR_PROFILE` is used if it's not set
     #[new]
-    fn load(config_file: String) -> PyResult<Self> {
+    #[args(overrides = "None", profile = "None")]
+    fn load(
+        config_file: String,
+        overrides: Option<HashMap<String, String>>,
+        profile: Option<String>,
+    ) -> PyResult<Self> {
         block_on(async {
-            feathr::FeathrClient::load(config_file)
-                .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
-                .map(|c| FeathrClient(c))
+            feathr::FeathrClient::load_profile_with_overrides(
+                config_file,
+                profile.as_deref(),
+                overrides.unwrap_or_default(),
+            )
+            .await
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+            .map(FeathrClient::from)
         })
     }
 
     #[staticmethod]
-    fn load_async(config_file: String, py: Python<'_>) -> PyResult<&PyAny> {
+    #[args(profile = "None")]
+    fn load_async(
+        config_file: String,
+        profile: Option<String>,
+        py: Python<'_>,
+    ) -> PyResult<&PyAny> {
         pyo3_asyncio::tokio::future_into_py(py, async move {
-            feathr::FeathrClient::load(config_file)
-                .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
-                .map(|c| FeathrClient(c))
+            feathr::FeathrClient::load_profile_with_overrides(
+                config_file,
+                profile.as_deref(),
+                Default::default(),
+            )
+            .await
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+            .map(FeathrClient::from)
         })
     }
 
+    /// Load the client with the config content, `backend` overrides `spark_config.spark_cluster`, e.g. `mock` to run no job in tests
     #[staticmethod]
-    fn loads(content: &str) -> PyResult<Self> {
+    #[args(backend = "None")]
+    fn loads(content: &str, backend: Option<String>) -> PyResult<Self> {
         let content = content.to_string();
         block_on(async move {
-            feathr::FeathrClient::from_str(&content)
+            feathr::FeathrClient::from_str_with_overrides(&content, backend_overrides(backend))
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
-                .map(|c| FeathrClient(c))
+                .map(FeathrClient::from)
         })
     }
 
     #[staticmethod]
-    fn loads_async<'p>(content: &'p str, py: Python<'p>) -> PyResult<&'p PyAny> {
+    #[args(backend = "None")]
+    fn loads_async<'p>(
+        content: &'p str,
+        backend: Option<String>,
+        py: Python<'p>,
+    ) -> PyResult<&'p PyAny> {
         let content = content.to_string();
         pyo3_asyncio::tokio::future_into_py(py, async move {
-            feathr::FeathrClient::from_str(&content)
+            feathr::FeathrClient::from_str_with_overrides(&content, backend_overrides(backend))
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
-                .map(|c| FeathrClient(c))
+                .map(FeathrClient::from)
         })
     }
 
-    fn load_project<'p>(&self, name: &str, py: Python<'p>) -> PyResult<FeathrProject> {
-        let project = block_on(cancelable_wait(py, async move {
-            self.0
-                .load_project(name)
-                .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
-        }))?;
+    /// The effective config in YAML after applying defaults, the file, environment variables and overrides, secrets are masked
+    fn dump_config(&self) -> PyResult<String> {
+        self.0
+            .dump_config()
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+    }
+
+    /// Load the entity versions pinned by `release` if set, otherwise the latest ones
+    #[args(release = "None")]
+    fn load_project<'p>(
+        &self,
+        name: &str,
+        release: Option<&str>,
+        py: Python<'p>,
+    ) -> PyResult<FeathrProject> {
+        let client = self.0.clone();
+        let project = cancelable_wait(py, &self.1, async move {
+            match release {
+                Some(release) => client.load_project_release(name, release).await,
+                None => client.load_project(name).await,
+            }
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })?;
         Ok(FeathrProject(project, self.clone()))
     }
 
-    fn new_project<'p>(&self, name: &str, py: Python<'p>) -> PyResult<FeathrProject> {
-        let project = block_on(cancelable_wait(py, async move {
-            self.0
-                .new_project(name)
+    /// Create a project, its sources, anchor groups and features inherit `default_tags` unless
+    /// they set the same tags
+    #[args(tags = "None", default_tags = "None")]
+    fn new_project<'p>(
+        &self,
+        name: &str,
+        tags: Option<HashMap<String, String>>,
+        default_tags: Option<HashMap<String, String>>,
+        py: Python<'p>,
+    ) -> PyResult<FeathrProject> {
+        let client = self.0.clone();
+        let project = cancelable_wait(py, &self.1, async move {
+            client
+                .new_project_with_default_tags(
+                    name,
+                    tags.unwrap_or_default(),
+                    default_tags.unwrap_or_default(),
+                )
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
-        }))?;
+        })?;
         Ok(FeathrProject(project, self.clone()))
     }
 
+    /// Wait for the job to end and return its output, `timeout` is in seconds
     #[args(timeout = "None")]
     fn wait_for_job<'p>(
         &self,
@@ -1925,12 +2916,12 @@ impl FeathrClient {
     ) -> PyResult<String> {
         let client = self.0.clone();
         let timeout = timeout.map(|s| Duration::seconds(s));
-        block_on(cancelable_wait(py, async {
+        cancelable_wait(py, &self.1, async move {
             Ok(client
                 .wait_for_job(feathr::JobId(job_id), timeout)
                 .await
                 .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?)
-        }))
+        })
     }
 
     #[args(timeout = "None")]
@@ -1942,11 +2933,15 @@ impl FeathrClient {
     ) -> PyResult<&'p PyAny> {
         let client = self.0.clone();
         let timeout = timeout.map(|s| Duration::seconds(s));
+        let token = self.1.clone();
         pyo3_asyncio::tokio::future_into_py(py, async move {
-            Ok(client
-                .wait_for_job(feathr::JobId(id), timeout)
-                .await
-                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?)
+            cancelable_wait_async(token, async move {
+                Ok(client
+                    .wait_for_job(feathr::JobId(id), timeout)
+                    .await
+                    .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?)
+            })
+            .await
         })
     }
 
@@ -1959,7 +2954,7 @@ impl FeathrClient {
     ) -> PyResult<Vec<String>> {
         let client = self.0.clone();
         let timeout = timeout.map(|s| Duration::seconds(s));
-        block_on(cancelable_wait(py, async {
+        cancelable_wait(py, &self.1, async move {
             let jobs = job_id
                 .into_iter()
                 .map(|job_id| client.wait_for_job(feathr::JobId(job_id), timeout));
@@ -1969,7 +2964,7 @@ impl FeathrClient {
                 .map(|r| r.unwrap_or_default())
                 .collect();
             Ok(complete)
-        }))
+        })
     }
 
     #[args(timeout = "None")]
@@ -1981,16 +2976,75 @@ impl FeathrClient {
     ) -> PyResult<&'p PyAny> {
         let client = self.0.clone();
         let timeout = timeout.map(|s| Duration::seconds(s));
+        let token = self.1.clone();
         pyo3_asyncio::tokio::future_into_py(py, async move {
-            let jobs = job_id
-                .into_iter()
-                .map(|job_id| client.wait_for_job(feathr::JobId(job_id), timeout));
-            let complete: Vec<String> = join_all(jobs)
+            cancelable_wait_async(token, async move {
+                let jobs = job_id
+                    .into_iter()
+                    .map(|job_id| client.wait_for_job(feathr::JobId(job_id), timeout));
+                let complete: Vec<String> = join_all(jobs)
+                    .await
+                    .into_iter()
+                    .map(|r| r.unwrap_or_default())
+                    .collect();
+                Ok(complete)
+            })
+            .await
+        })
+    }
+
+    /// Save the manifest of a job submitted by this client next to the job output, returns the URL of the manifest
+    pub fn save_manifest(&self, job_id: u64) -> PyResult<String> {
+        let client = self.0.clone();
+        block_on(async {
+            client
+                .save_manifest(feathr::JobId(job_id))
                 .await
-                .into_iter()
-                .map(|r| r.unwrap_or_default())
-                .collect();
-            Ok(complete)
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })
+    }
+
+    pub fn save_manifest_async<'p>(&'p self, job_id: u64, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let client = self.0.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            client
+                .save_manifest(feathr::JobId(job_id))
+                .await
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })
+    }
+
+    /// Submit the job in the manifest saved by `save_manifest` again, returns the new job id
+    pub fn resubmit(&self, manifest: &str) -> PyResult<u64> {
+        let client = self.0.clone();
+        block_on(async {
+            let manifest = client
+                .load_manifest(manifest)
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+            Ok(client
+                .resubmit(manifest)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
+                .id
+                .0)
+        })
+    }
+
+    pub fn resubmit_async<'p>(&'p self, manifest: &str, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let client = self.0.clone();
+        let manifest = manifest.to_string();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let manifest = client
+                .load_manifest(&manifest)
+                .await
+                .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+            Ok(client
+                .resubmit(manifest)
+                .await
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))?
+                .id
+                .0)
         })
     }
 
@@ -2018,19 +3072,235 @@ impl FeathrClient {
         })
     }
 
+    pub fn get_job_url(&self, job_id: u64) -> PyResult<Option<String>> {
+        let client = self.0.clone();
+        block_on(async {
+            client
+                .get_job_url(feathr::JobId(job_id))
+                .await
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })
+    }
+
+    pub fn get_job_url_async<'p>(&'p self, job_id: u64, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let client = self.0.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            client
+                .get_job_url(feathr::JobId(job_id))
+                .await
+                .map_err(|e| PyRuntimeError::new_err(format!("{:#?}", e)))
+        })
+    }
+
     pub fn get_remote_url(&self, path: &str) -> String {
         self.0.get_remote_url(path)
     }
+
+    /// Cancel the pending waits of the client and its clones, they raise `asyncio.CancelledError`.
+    /// Waits started after closing are cancelled immediately, submitted jobs keep running
+    fn close(&self) {
+        self.1.cancel()
+    }
+
+    /// Whether `close` has been called
+    #[getter]
+    fn closed(&self) -> bool {
+        self.1.is_cancelled()
+    }
+
+    fn __aenter__<'p>(slf: PyRef<'p, Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let client: Py<Self> = slf.into();
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(client) })
+    }
+
+    /// Close the client when leaving `async with`
+    fn __aexit__<'p>(
+        &self,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+        py: Python<'p>,
+    ) -> PyResult<&'p PyAny> {
+        self.close();
+        // Exceptions raised in the block are not suppressed
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(false) })
+    }
+}
+
+/// A `datetime.datetime` argument, the fields are read as attributes because the datetime C API
+/// is not part of the stable ABI
+#[derive(Clone, Copy, Debug)]
+struct DateTimeArg(NaiveDateTime);
+
+impl<'source> FromPyObject<'source> for DateTimeArg {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let expected = || PyTypeError::new_err(format!("Expected a datetime, got {}", ob));
+        let field = |name: &str| -> PyResult<i32> {
+            ob.getattr(name)
+                .and_then(|v| v.extract())
+                .map_err(|_| expected())
+        };
+        NaiveDate::from_ymd_opt(field("year")?, field("month")? as u32, field("day")? as u32)
+            .and_then(|d| {
+                d.and_hms_opt(
+                    field("hour").ok()? as u32,
+                    field("minute").ok()? as u32,
+                    field("second").ok()? as u32,
+                )
+            })
+            .map(DateTimeArg)
+            .ok_or_else(expected)
+    }
+}
+
+/// `tzinfo` is ignored, the date and time fields are read in the timezone of the windows, UTC by default
+fn to_utc(dt: DateTimeArg, window: &feathr::WindowOptions) -> DateTime<Utc> {
+    window.to_utc(dt.0)
+}
+
+fn parse_window_options(
+    tz: Option<&str>,
+    alignment: Option<&str>,
+    end_inclusive: bool,
+) -> PyResult<feathr::WindowOptions> {
+    let mut options = feathr::WindowOptions {
+        end_inclusive,
+        ..Default::default()
+    };
+    if let Some(tz) = tz {
+        options.timezone = feathr::WindowOptions::parse_timezone(tz)
+            .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+    }
+    if let Some(alignment) = alignment {
+        options.alignment = alignment
+            .parse()
+            .map_err(|e| PyValueError::new_err(format!("{:#?}", e)))?;
+    }
+    Ok(options)
+}
+
+fn parse_sink(sink: &PyAny) -> PyResult<feathr::OutputSink> {
+    if let Ok(sink) = sink.extract::<RedisSink>() {
+        Ok(feathr::OutputSink::Redis(sink.0))
+    } else if let Ok(sink) = sink.extract::<CosmosDbSink>() {
+        Ok(feathr::OutputSink::Hdfs(sink.0))
+    } else {
+        Err(PyValueError::new_err(format!(
+            "sink must be RedisSink or CosmosDbSink"
+        )))
+    }
+}
+
+/// None, a sink, a `SinkSelection` or a list of them, sinks without selection get all features
+fn parse_sinks(sink: &PyAny) -> PyResult<(Vec<feathr::OutputSink>, Vec<feathr::SinkSelection>)> {
+    let mut sinks: Vec<feathr::OutputSink> = vec![];
+    let mut selected_sinks: Vec<feathr::SinkSelection> = vec![];
+    let items: Vec<&PyAny> = if sink.is_none() {
+        vec![]
+    } else if let Ok(items) = sink.extract::<Vec<&PyAny>>() {
+        items
+    } else {
+        vec![sink]
+    };
+    for s in items {
+        if let Ok(selection) = s.extract::<SinkSelection>() {
+            selected_sinks.push(selection.0);
+        } else if let Ok(sink) = parse_sink(s) {
+            sinks.push(sink);
+        } else {
+            return Err(PyTypeError::new_err(format!(
+                "sink must be None or RedisSink or CosmosDbSink or SinkSelection"
+            )));
+        }
+    }
+    Ok((sinks, selected_sinks))
+}
+
+/// Ids of the submitted jobs, fails with the ids of the submitted ones if any submission failed
+fn submitted_job_ids(results: Vec<Result<feathr::JobHandle, feathr::Error>>) -> PyResult<Vec<u64>> {
+    let (submitted, failed): (Vec<_>, Vec<_>) = results.into_iter().partition(|r| r.is_ok());
+    let ids: Vec<u64> = submitted
+        .into_iter()
+        .filter_map(|r| r.ok())
+        .map(|handle| handle.id.0)
+        .collect();
+    if failed.is_empty() {
+        return Ok(ids);
+    }
+    let errors: Vec<String> = failed
+        .into_iter()
+        .filter_map(|r| r.err())
+        .map(|e| format!("{:#?}", e))
+        .collect();
+    Err(PyRuntimeError::new_err(format!(
+        "{} jobs failed to submit, submitted jobs are {:?}: {}",
+        errors.len(),
+        ids,
+        errors.join("\n")
+    )))
+}
+
+/// Feature names or `FeatureQuery` objects
+fn parse_feature_queries(feature_query: Option<&PyList>) -> PyResult<Vec<feathr::FeatureQuery>> {
+    let mut queries: Vec<feathr::FeatureQuery> = vec![];
+    for f in feature_query.into_iter().flatten() {
+        let q = if let Ok(s) = f.extract::<String>() {
+            feathr::FeatureQuery::by_name(&[&s])
+        } else if let Ok(f) = f.extract::<FeatureQuery>() {
+            f.0
+        } else {
+            return Err(PyValueError::new_err(format!(
+                "feature_query must be list of strings or FeatureQuery objects"
+            )));
+        };
+        queries.push(q);
+    }
+    Ok(queries)
 }
 
+/// Missing entities are reported as `KeyError` and entities still in use as `ValueError`
+fn delete_error(e: feathr::Error) -> PyErr {
+    match e {
+        feathr::Error::SourceGroupNotFound(name)
+        | feathr::Error::AnchorGroupNotFound(name)
+        | feathr::Error::FeatureNotFound(name) => PyKeyError::new_err(name),
+        feathr::Error::EntityInUse(..) => PyValueError::new_err(e.to_string()),
+        _ => PyRuntimeError::new_err(format!("{:#?}", e)),
+    }
+}
+
+/// Load the client with the config file, `profile` selects the named profile in the file, `FEATHR_PROFILE` is used if it's not set
+#[pyfunction]
+#[args(profile = "None")]
+fn load(config_file: String, profile: Option<String>) -> PyResult<FeathrClient> {
+    FeathrClient::load(config_file, None, profile)
+}
+
+/// Load the client with the config content, `backend` overrides `spark_config.spark_cluster`
 #[pyfunction]
-fn load(config_file: String) -> PyResult<FeathrClient> {
-    FeathrClient::load(config_file)
+#[args(backend = "None")]
+fn loads(content: &str, backend: Option<String>) -> PyResult<FeathrClient> {
+    FeathrClient::loads(content, backend)
 }
 
+/// Optional components compiled into the module, e.g. `otlp`, and `abi3` if the wheel is built for the stable ABI
 #[pyfunction]
-fn loads(content: &str) -> PyResult<FeathrClient> {
-    FeathrClient::loads(content)
+fn features() -> Vec<String> {
+    let mut features: Vec<String> = feathr::enabled_features()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    if cfg!(feature = "abi3") {
+        features.push("abi3".to_string());
+    }
+    features
+}
+
+fn backend_overrides(backend: Option<String>) -> HashMap<String, String> {
+    backend
+        .map(|b| ("spark_config.spark_cluster".to_string(), b))
+        .into_iter()
+        .collect()
 }
 
 /// A Python module implemented in Rust.
@@ -2046,7 +3316,13 @@ fn feathrs(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<Transformation>()?;
     m.add_class::<DataLocation>()?;
     m.add_class::<Source>()?;
+    m.add_class::<ConnectionTestResult>()?;
+    m.add_class::<Diagnostic>()?;
+    m.add_class::<CoercionWarning>()?;
+    m.add_class::<QueryCost>()?;
+    m.add_class::<RenderedConfig>()?;
     m.add_class::<JdbcSourceAuth>()?;
+    m.add_class::<EventHubAuth>()?;
     m.add_class::<AnchorFeature>()?;
     m.add_class::<DerivedFeature>()?;
     m.add_class::<AnchorGroup>()?;
@@ -2055,10 +3331,12 @@ fn feathrs(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<DateTimeResolution>()?;
     m.add_class::<RedisSink>()?;
     m.add_class::<CosmosDbSink>()?;
+    m.add_class::<SinkSelection>()?;
     m.add_class::<JobStatus>()?;
     m.add_class::<FeathrProject>()?;
     m.add_class::<FeathrClient>()?;
     m.add_function(wrap_pyfunction!(load, m)?)?;
     m.add_function(wrap_pyfunction!(loads, m)?)?;
+    m.add_function(wrap_pyfunction!(features, m)?)?;
     Ok(())
 }
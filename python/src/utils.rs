@@ -1,32 +1,114 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
 use chrono::Duration;
-use futures::{pin_mut, Future};
+use futures::{
+    future::{select, Either},
+    pin_mut, Future,
+};
 use pyo3::{
-    exceptions::PyValueError,
+    exceptions::{asyncio::CancelledError, PyValueError},
     types::{PyDict, PyList},
     IntoPy, PyObject, PyResult, Python,
 };
 use regex::Regex;
-use tokio::runtime::Handle;
+use serde::Serialize;
+use tokio::{runtime::Handle, sync::Notify};
 
 /**
- * Check CTRL-C every second, cancel the future if pressed and return Interrupted error
+ * Interval of checking CTRL-C during blocking waits
  */
-pub(crate) async fn cancelable_wait<'p, F, T>(py: Python<'p>, f: F) -> PyResult<T>
+const SIGNAL_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/**
+ * Shared by a client and all its clones, cancelled when the client is closed
+ */
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CancelToken(Arc<CancelState>);
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub(crate) fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /**
+     * Resolves once the token is cancelled, immediately if it already is
+     */
+    async fn cancelled(&self) {
+        // Register before checking the flag so a `cancel` in between is not missed
+        let notified = self.0.notify.notified();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await
+    }
+}
+
+fn closed_error() -> pyo3::PyErr {
+    CancelledError::new_err("The client is closed")
+}
+
+/**
+ * Block on the future with the GIL released, so other Python threads keep running.
+ * CTRL-C is checked every 100ms and drops the future with `KeyboardInterrupt`, closing the client drops it
+ * with `CancelledError`.
+ * Python only runs signal handlers on the main thread, waits on other threads can only be cancelled by
+ * closing the client.
+ */
+pub(crate) fn cancelable_wait<F, T>(py: Python<'_>, token: &CancelToken, f: F) -> PyResult<T>
 where
-    F: Future<Output = PyResult<T>>,
+    F: Future<Output = PyResult<T>> + Send,
+    T: Send,
 {
-    // Future needs to be pinned then its mutable ref can be awaited multiple times.
-    pin_mut!(f);
-    loop {
-        match tokio::time::timeout(std::time::Duration::from_millis(100), &mut f).await {
-            Ok(v) => {
-                return v;
-            }
-            Err(_) => {
-                // Timeout, check if CTRL-C is pressed
-                py.check_signals()?
+    py.allow_threads(|| {
+        block_on(async {
+            pin_mut!(f);
+            loop {
+                if let Ok(v) = tokio::time::timeout(SIGNAL_CHECK_INTERVAL, &mut f).await {
+                    return v;
+                }
+                // Timeout, check if CTRL-C is pressed, the GIL is needed to run the signal handlers
+                Python::with_gil(|py| py.check_signals())?;
+                if token.is_cancelled() {
+                    return Err(closed_error());
+                }
             }
-        }
+        })
+    })
+}
+
+/**
+ * Async counterpart of `cancelable_wait`, the future is dropped with `CancelledError` once the client
+ * is closed.
+ * Cancelling the asyncio task drops the future as well, pending requests and status polls are aborted
+ * either way but the submitted jobs keep running.
+ */
+pub(crate) async fn cancelable_wait_async<F, T>(token: CancelToken, f: F) -> PyResult<T>
+where
+    F: Future<Output = PyResult<T>>,
+{
+    let cancelled = token.cancelled();
+    pin_mut!(f, cancelled);
+    match select(f, cancelled).await {
+        Either::Left((v, _)) => v,
+        Either::Right(_) => Err(closed_error()),
     }
 }
 
@@ -84,6 +166,19 @@ pub(crate) fn str_to_dur(s: &str) -> PyResult<Duration> {
     }
 }
 
+/**
+ * Hash the JSON form of the value, for types which can't derive `Hash` but their `==` is structural.
+ * Objects in `serde_json::Value` are sorted by key so the result doesn't depend on `HashMap` order
+ */
+pub(crate) fn hash_json<T: Serialize>(v: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_value(v)
+        .map(|v| v.to_string())
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
 pub(crate) fn value_to_py<'p>(v: serde_json::Value, py: Python<'p>) -> PyObject {
     match v {
         serde_json::Value::Null => py.None(),
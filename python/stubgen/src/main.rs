@@ -0,0 +1,428 @@
+//! Generates `feathrs.pyi` from the pyo3 bindings in `src/lib.rs`.
+//! maturin packs the stub into the wheel so IDEs and type checkers can see the signatures and docstrings.
+//!
+//! Usage: `stubgen [--check] [PYTHON_CRATE_DIR]`, the directory defaults to the parent of this crate.
+//! With `--check` the stub is not written, the exit code is non-zero if it's out of date.
+
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use syn::{
+    Attribute, FnArg, GenericArgument, ImplItem, ImplItemMethod, Item, Lit, Meta, NestedMeta, Pat,
+    PathArguments, ReturnType, Type,
+};
+
+const HEADER: &str = r#"# Generated from src/lib.rs by stubgen, DO NOT EDIT.
+from datetime import datetime
+from typing import Any, Awaitable, ClassVar, Dict, List, Optional, Tuple
+"#;
+
+#[derive(Default)]
+struct Class {
+    doc: Vec<String>,
+    attributes: Vec<String>,
+    methods: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ => dir = PathBuf::from(arg),
+        }
+    }
+    match run(&dir, check) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => {
+            eprintln!(
+                "feathrs.pyi is out of date, run `cargo run` in python/stubgen to regenerate it"
+            );
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Failed to generate feathrs.pyi, {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/**
+ * Generate the stub of the crate in `dir`, returns whether the existing stub is up to date
+ */
+fn run(dir: &Path, check: bool) -> Result<bool, Box<dyn Error>> {
+    let src = fs::read_to_string(dir.join("src/lib.rs"))?;
+    let stub = generate(&syn::parse_file(&src)?);
+    let out = dir.join("feathrs.pyi");
+    // Don't touch the file if nothing changed, otherwise it triggers rebuilds in some IDEs
+    let up_to_date = fs::read_to_string(&out).ok().as_deref() == Some(stub.as_str());
+    if !up_to_date && !check {
+        fs::write(&out, stub)?;
+        return Ok(true);
+    }
+    Ok(up_to_date)
+}
+
+fn generate(file: &syn::File) -> String {
+    let mut order: Vec<String> = vec![];
+    let mut classes: HashMap<String, Class> = HashMap::new();
+    let mut functions: Vec<String> = vec![];
+
+    for item in &file.items {
+        match item {
+            Item::Struct(s) if has_attr(&s.attrs, "pyclass") => {
+                let name = s.ident.to_string();
+                let mut class = Class {
+                    doc: get_doc(&s.attrs),
+                    ..Default::default()
+                };
+                for f in &s.fields {
+                    if let Some(ident) = &f.ident {
+                        if has_pyo3_get(&f.attrs) {
+                            class.attributes.push(property(
+                                &ident.to_string(),
+                                &py_type(&f.ty, &name),
+                                &get_doc(&f.attrs),
+                            ));
+                        }
+                    }
+                }
+                order.push(name.clone());
+                classes.insert(name, class);
+            }
+            Item::Enum(e) if has_attr(&e.attrs, "pyclass") => {
+                let name = e.ident.to_string();
+                let class = Class {
+                    doc: get_doc(&e.attrs),
+                    attributes: e
+                        .variants
+                        .iter()
+                        .map(|v| format!("    {}: ClassVar[{}]\n", v.ident, name))
+                        .collect(),
+                    ..Default::default()
+                };
+                order.push(name.clone());
+                classes.insert(name, class);
+            }
+            Item::Impl(i) if has_attr(&i.attrs, "pymethods") => {
+                let name = match i.self_ty.as_ref() {
+                    Type::Path(p) => p.path.segments.last().unwrap().ident.to_string(),
+                    _ => continue,
+                };
+                let methods: Vec<&ImplItemMethod> = i
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        ImplItem::Method(m) => Some(m),
+                        _ => None,
+                    })
+                    .collect();
+                let class = classes.entry(name.clone()).or_default();
+                for item in &i.items {
+                    match item {
+                        ImplItem::Const(c) if has_attr(&c.attrs, "classattr") => {
+                            class
+                                .attributes
+                                .push(format!("    {}: ClassVar[{}]\n", c.ident, name));
+                        }
+                        ImplItem::Method(m) => {
+                            if let Some(s) = method(m, &name, &methods) {
+                                class.methods.push(s);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Item::Fn(f) if has_attr(&f.attrs, "pyfunction") => {
+                let (params, ret) = signature(&f.sig, &f.attrs, "", &[]);
+                functions.push(format!(
+                    "def {}({}) -> {}:{}\n",
+                    f.sig.ident,
+                    params.join(", "),
+                    ret,
+                    body(&get_doc(&f.attrs), "    ")
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    let mut stub = HEADER.to_string();
+    for name in order {
+        let class = &classes[&name];
+        write!(stub, "\n\nclass {}:\n", name).unwrap();
+        let mut members = vec![];
+        if !class.doc.is_empty() {
+            members.push(docstring(&class.doc, "    "));
+        }
+        members.extend(class.attributes.iter().cloned());
+        members.extend(class.methods.iter().cloned());
+        if members.is_empty() {
+            members.push("    ...\n".to_string());
+        }
+        stub.push_str(&members.join(""));
+    }
+    for f in functions {
+        write!(stub, "\n\n{}", f).unwrap();
+    }
+    stub
+}
+
+fn method(m: &ImplItemMethod, class: &str, siblings: &[&ImplItemMethod]) -> Option<String> {
+    let name = m.sig.ident.to_string();
+    let doc = get_doc(&m.attrs);
+    if has_attr(&m.attrs, "classattr") {
+        return Some(format!("    {}: ClassVar[{}]\n", name, class));
+    }
+    if has_attr(&m.attrs, "getter") {
+        // pyo3 strips the `get_` prefix from getter names
+        let prop = name.strip_prefix("get_").unwrap_or(&name);
+        if prop.starts_with("__") {
+            return None;
+        }
+        return Some(property(prop, &return_type(&m.sig.output, class), &doc));
+    }
+    let (params, ret) = signature(&m.sig, &m.attrs, class, siblings);
+    let (decorator, name, ret) = if has_attr(&m.attrs, "new") {
+        ("", "__init__".to_string(), "None".to_string())
+    } else if has_attr(&m.attrs, "staticmethod") {
+        ("    @staticmethod\n", name, ret)
+    } else {
+        ("", name, ret)
+    };
+    let mut params = params;
+    if decorator.is_empty() {
+        params.insert(0, "self".to_string());
+    }
+    match name.as_str() {
+        "__richcmp__" => {
+            return Some(
+                "    def __eq__(self, other: object) -> bool: ...\n    def __ne__(self, other: object) -> bool: ...\n"
+                    .to_string(),
+            )
+        }
+        "__hash__" => return Some("    def __hash__(self) -> int: ...\n".to_string()),
+        "__repr__" => return Some("    def __repr__(self) -> str: ...\n".to_string()),
+        "__aenter__" => return Some(format!("    async def __aenter__(self) -> {}: ...\n", class)),
+        "__aexit__" => {
+            return Some(
+                "    async def __aexit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...\n"
+                    .to_string(),
+            )
+        }
+        _ => {}
+    }
+    Some(format!(
+        "{}    def {}({}) -> {}:{}\n",
+        decorator,
+        name,
+        params.join(", "),
+        ret,
+        body(&doc, "        ")
+    ))
+}
+
+/**
+ * Python parameters with defaults from `#[args(...)]`, and the return type
+ */
+fn signature(
+    sig: &syn::Signature,
+    attrs: &[Attribute],
+    class: &str,
+    siblings: &[&ImplItemMethod],
+) -> (Vec<String>, String) {
+    let defaults = get_args(attrs);
+    let mut params = vec![];
+    let mut has_default = false;
+    for arg in &sig.inputs {
+        if let FnArg::Typed(t) = arg {
+            let name = match t.pat.as_ref() {
+                Pat::Ident(i) => i.ident.to_string(),
+                _ => continue,
+            };
+            let ty = py_type(&t.ty, class);
+            // The GIL token is injected by pyo3
+            if ty == "Python" {
+                continue;
+            }
+            match defaults.get(&name) {
+                Some(default) => {
+                    has_default = true;
+                    params.push(format!("{}: {} = {}", name, ty, default));
+                }
+                // Stubs can't have required parameters after optional ones, pyo3 still requires it
+                None if has_default => params.push(format!("{}: {} = ...", name, ty)),
+                None => params.push(format!("{}: {}", name, ty)),
+            }
+        }
+    }
+    let name = sig.ident.to_string();
+    let ret = match name.strip_suffix("_async") {
+        // `*_async` methods return awaitables resolving to what the blocking version returns
+        Some(blocking) => {
+            let inner = siblings
+                .iter()
+                .find(|m| m.sig.ident == blocking)
+                .map(|m| {
+                    if has_attr(&m.attrs, "new") {
+                        class.to_string()
+                    } else {
+                        return_type(&m.sig.output, class)
+                    }
+                })
+                .unwrap_or_else(|| "Any".to_string());
+            format!("Awaitable[{}]", inner)
+        }
+        None => return_type(&sig.output, class),
+    };
+    (params, ret)
+}
+
+fn return_type(output: &ReturnType, class: &str) -> String {
+    match output {
+        ReturnType::Default => "None".to_string(),
+        ReturnType::Type(_, ty) => py_type(ty, class),
+    }
+}
+
+fn py_type(ty: &Type, class: &str) -> String {
+    match ty {
+        Type::Reference(r) => py_type(&r.elem, class),
+        Type::Tuple(t) if t.elems.is_empty() => "None".to_string(),
+        Type::Tuple(t) => format!(
+            "Tuple[{}]",
+            t.elems
+                .iter()
+                .map(|t| py_type(t, class))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Type::Path(p) => {
+            let seg = p.path.segments.last().unwrap();
+            let args: Vec<String> = match &seg.arguments {
+                PathArguments::AngleBracketed(a) => a
+                    .args
+                    .iter()
+                    .filter_map(|a| match a {
+                        GenericArgument::Type(t) => Some(py_type(t, class)),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            match seg.ident.to_string().as_str() {
+                "str" | "String" => "str".to_string(),
+                "bool" => "bool".to_string(),
+                "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize" => {
+                    "int".to_string()
+                }
+                "f32" | "f64" => "float".to_string(),
+                "Option" => format!("Optional[{}]", args[0]),
+                "Vec" => format!("List[{}]", args[0]),
+                "HashMap" => format!("Dict[{}, {}]", args[0], args[1]),
+                "PyResult" => args[0].clone(),
+                "PyList" => "List[Any]".to_string(),
+                "DateTimeArg" => "datetime".to_string(),
+                "PyAny" | "PyObject" => "Any".to_string(),
+                "Python" => "Python".to_string(),
+                "Self" => class.to_string(),
+                other => other.to_string(),
+            }
+        }
+        _ => "Any".to_string(),
+    }
+}
+
+/**
+ * Convert Rust default value expressions in `#[args(...)]` to Python
+ */
+fn py_default(expr: &str) -> String {
+    match expr {
+        "None" => "None".to_string(),
+        "true" => "True".to_string(),
+        "false" => "False".to_string(),
+        "vec![]" => "[]".to_string(),
+        s if s.starts_with('"') => format!("'{}'", s.trim_matches('"')),
+        // Enum variants, e.g. `DateTimeResolution::Daily`
+        s => s.replace("::", "."),
+    }
+}
+
+fn get_args(attrs: &[Attribute]) -> HashMap<String, String> {
+    let mut ret = HashMap::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("args")) {
+        if let Ok(Meta::List(list)) = attr.parse_meta() {
+            for nested in list.nested {
+                if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
+                    if let (Some(ident), Lit::Str(s)) = (nv.path.get_ident(), &nv.lit) {
+                        ret.insert(ident.to_string(), py_default(&s.value()));
+                    }
+                }
+            }
+        }
+    }
+    ret
+}
+
+fn get_doc(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(s) => {
+                    let line = s.value();
+                    Some(line.strip_prefix(' ').unwrap_or(&line).to_string())
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|a| a.path.is_ident(name))
+}
+
+fn has_pyo3_get(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("pyo3"))
+        .any(|a| a.tokens.to_string().contains("get"))
+}
+
+fn property(name: &str, ty: &str, doc: &[String]) -> String {
+    format!(
+        "    @property\n    def {}(self) -> {}:{}\n",
+        name,
+        ty,
+        body(doc, "        ")
+    )
+}
+
+fn body(doc: &[String], indent: &str) -> String {
+    if doc.is_empty() {
+        " ...".to_string()
+    } else {
+        format!("\n{}{}...", docstring(doc, indent), indent)
+    }
+}
+
+fn docstring(doc: &[String], indent: &str) -> String {
+    let mut text = doc.join(&format!("\n{}", indent)).replace('\\', "\\\\");
+    // A quote right before the closing quotes would end the string early
+    if text.ends_with('"') {
+        text.insert(text.len() - 1, '\\');
+    }
+    format!("{}\"\"\"{}\"\"\"\n", indent, text)
+}
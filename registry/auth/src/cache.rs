@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/**
+ * Revoked tokens whose expiration is unknown are remembered for this long, AAD access tokens
+ * don't live longer than a day
+ */
+const DEFAULT_REVOCATION_HOURS: i64 = 24;
+
+struct CachedToken {
+    claims: serde_json::Value,
+    expires: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCacheStats {
+    pub capacity: usize,
+    pub size: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub hit_rate: f64,
+    pub revoked: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevokedToken {
+    pub hash: String,
+    pub expires: DateTime<Utc>,
+}
+
+/**
+ * Hex encoded SHA-256 of the token, raw tokens are never kept in memory
+ */
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/**
+ * Claims of validated tokens keyed by token hash, entries are dropped once the token expires.
+ * Revoked tokens are rejected until they expire, even if they are still cached.
+ */
+pub struct TokenCache {
+    capacity: usize,
+    entries: HashMap<String, CachedToken>,
+    revoked: HashMap<String, DateTime<Utc>>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl TokenCache {
+    /**
+     * Capacity 0 disables the cache, revocations still work
+     */
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Default::default(),
+            revoked: Default::default(),
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn get(&mut self, hash: &str, now: DateTime<Utc>) -> Option<serde_json::Value> {
+        match self.entries.get(hash) {
+            Some(e) if e.expires > now => {
+                self.hits += 1;
+                Some(e.claims.clone())
+            }
+            Some(_) => {
+                self.entries.remove(hash);
+                self.evictions += 1;
+                self.misses += 1;
+                None
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        hash: String,
+        claims: serde_json::Value,
+        expires: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) {
+        if self.capacity == 0 || expires <= now || self.is_revoked(&hash, now) {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&hash) {
+            self.purge(now);
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&hash) {
+            // Still full, drop the token expiring first
+            if let Some(first) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.expires)
+                .map(|(h, _)| h.to_owned())
+            {
+                self.entries.remove(&first);
+                self.evictions += 1;
+            }
+        }
+        self.entries.insert(hash, CachedToken { claims, expires });
+    }
+
+    /**
+     * Reject the token until `expires`, or until it expires if the token is cached
+     */
+    pub fn revoke(
+        &mut self,
+        hash: &str,
+        expires: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> RevokedToken {
+        let expires = expires
+            .or_else(|| self.entries.get(hash).map(|e| e.expires))
+            .unwrap_or_else(|| now + Duration::hours(DEFAULT_REVOCATION_HOURS));
+        self.entries.remove(hash);
+        self.revoked.insert(hash.to_string(), expires);
+        RevokedToken {
+            hash: hash.to_string(),
+            expires,
+        }
+    }
+
+    pub fn is_revoked(&self, hash: &str, now: DateTime<Utc>) -> bool {
+        self.revoked.get(hash).map_or(false, |&e| e > now)
+    }
+
+    pub fn revoked(&self) -> Vec<RevokedToken> {
+        let mut ret: Vec<RevokedToken> = self
+            .revoked
+            .iter()
+            .map(|(hash, &expires)| RevokedToken {
+                hash: hash.to_owned(),
+                expires,
+            })
+            .collect();
+        ret.sort_by(|a, b| (a.expires, &a.hash).cmp(&(b.expires, &b.hash)));
+        ret
+    }
+
+    pub fn stats(&self) -> TokenCacheStats {
+        let total = self.hits + self.misses;
+        TokenCacheStats {
+            capacity: self.capacity,
+            size: self.entries.len(),
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            hit_rate: if total == 0 {
+                0.0
+            } else {
+                self.hits as f64 / total as f64
+            },
+            revoked: self.revoked.len(),
+        }
+    }
+
+    /**
+     * Drop expired tokens and revocations of expired tokens
+     */
+    pub fn purge(&mut self, now: DateTime<Utc>) {
+        let before = self.entries.len();
+        self.entries.retain(|_, e| e.expires > now);
+        self.evictions += (before - self.entries.len()) as u64;
+        self.revoked.retain(|_, &mut e| e > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_token_cache() {
+        let now = Utc::now();
+        let mut cache = TokenCache::new(2);
+        cache.insert(
+            "a".to_string(),
+            json!({"a": 1}),
+            now + Duration::minutes(10),
+            now,
+        );
+        cache.insert(
+            "b".to_string(),
+            json!({"b": 1}),
+            now + Duration::minutes(5),
+            now,
+        );
+        assert_eq!(cache.get("a", now), Some(json!({"a": 1})));
+        assert_eq!(cache.get("c", now), None);
+
+        // Full, `b` expires first
+        cache.insert(
+            "c".to_string(),
+            json!({"c": 1}),
+            now + Duration::minutes(20),
+            now,
+        );
+        assert_eq!(cache.get("b", now), None);
+
+        // Expired
+        assert_eq!(cache.get("a", now + Duration::minutes(15)), None);
+
+        cache.revoke("c", None, now);
+        assert!(cache.is_revoked("c", now));
+        assert_eq!(cache.get("c", now), None);
+        cache.insert(
+            "c".to_string(),
+            json!({"c": 1}),
+            now + Duration::minutes(20),
+            now,
+        );
+        assert_eq!(cache.get("c", now), None);
+        assert_eq!(
+            cache.revoked(),
+            vec![RevokedToken {
+                hash: "c".to_string(),
+                expires: now + Duration::minutes(20)
+            }]
+        );
+        assert!(!cache.is_revoked("c", now + Duration::minutes(30)));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 5, 2));
+        cache.purge(now + Duration::minutes(30));
+        assert_eq!(cache.stats().revoked, 0);
+    }
+}
@@ -1,5 +1,6 @@
 use thiserror::Error;
 
+mod cache;
 mod token;
 
 #[derive(Error, Debug)]
@@ -22,11 +23,15 @@ pub enum AuthError {
     #[error("Key('{0}') is not found.")]
     KeyNotFound(String),
 
+    #[error("Token has been revoked.")]
+    TokenRevoked,
+
     #[error("Failed to initialize auth lib")]
     InitializationError,
 }
 
-pub use token::decode_token;
+pub use cache::{token_hash, RevokedToken, TokenCache, TokenCacheStats};
+pub use token::{decode_token, revoke_token, revoke_token_hash, revoked_tokens, token_cache_stats};
 
 #[cfg(test)]
 mod tests {
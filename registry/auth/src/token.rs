@@ -1,14 +1,23 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 use chrono::{DateTime, Utc};
 use common_utils::Logged;
 use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use lazy_static::lazy_static;
 use openssl::x509::X509;
 use serde::{de::DeserializeOwned, Deserialize};
 use serde_with::TimestampSeconds;
 use tokio::sync::{OnceCell, RwLock};
 
-use crate::AuthError;
+use crate::{
+    cache::{token_hash, RevokedToken, TokenCache, TokenCacheStats},
+    AuthError,
+};
+
+const DEFAULT_TOKEN_CACHE_SIZE: usize = 10000;
 
 impl From<reqwest::Error> for AuthError {
     fn from(e: reqwest::Error) -> Self {
@@ -50,23 +59,28 @@ impl TokenDecoder {
     where
         T: DeserializeOwned,
     {
-        let now = chrono::Utc::now();
+        Ok(self
+            .decode_token_with_expiration(token, check_expiration)?
+            .0)
+    }
 
-        #[serde_with::serde_as]
-        #[derive(Clone, Debug, Deserialize)]
-        struct Claims<U> {
-            #[serde_as(as = "TimestampSeconds<i64>")]
-            nbf: DateTime<Utc>,
-            #[serde_as(as = "TimestampSeconds<i64>")]
-            exp: DateTime<Utc>,
-            #[serde(flatten)]
-            user_claims: U,
-        }
+    /**
+     * Returns the user claims and the expiration time of the token
+     */
+    pub fn decode_token_with_expiration<T>(
+        &self,
+        token: &str,
+        check_expiration: bool,
+    ) -> Result<(T, DateTime<Utc>), AuthError>
+    where
+        T: DeserializeOwned,
+    {
+        let now = chrono::Utc::now();
         let claims: Claims<T> = self.decode_token_claims_no_validation(token.trim())?;
         if check_expiration && ((claims.nbf > now) || (claims.exp < now)) {
             return Err(AuthError::InvalidTimestamp);
         }
-        Ok(claims.user_claims)
+        Ok((claims.user_claims, claims.exp))
     }
 
     fn get_decoding_key(&self, kid: &str) -> Result<&DecodingKey, AuthError> {
@@ -92,6 +106,17 @@ impl TokenDecoder {
     }
 }
 
+#[serde_with::serde_as]
+#[derive(Clone, Debug, Deserialize)]
+struct Claims<U> {
+    #[serde_as(as = "TimestampSeconds<i64>")]
+    nbf: DateTime<Utc>,
+    #[serde_as(as = "TimestampSeconds<i64>")]
+    exp: DateTime<Utc>,
+    #[serde(flatten)]
+    user_claims: U,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 struct OpenIdConfiguration {
     jwks_uri: String,
@@ -135,10 +160,16 @@ struct AadKeyConfiguration {
 
 static DECODER: OnceCell<Option<Arc<RwLock<TokenDecoder>>>> = OnceCell::const_new();
 
-pub async fn decode_token<C>(token: &str) -> Result<C, AuthError>
-where
-    C: DeserializeOwned,
-{
+lazy_static! {
+    static ref TOKEN_CACHE: Mutex<TokenCache> = Mutex::new(TokenCache::new(
+        std::env::var("TOKEN_CACHE_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_TOKEN_CACHE_SIZE)
+    ));
+}
+
+async fn get_decoder() -> Result<&'static Arc<RwLock<TokenDecoder>>, AuthError> {
     DECODER
         .get_or_init(|| async {
             let base_url = std::env::var("OPENID_BASE_URL")
@@ -150,9 +181,73 @@ where
         })
         .await
         .as_ref()
-        .ok_or_else(|| AuthError::InitializationError)?
+        .ok_or_else(|| AuthError::InitializationError)
+}
+
+fn token_cache() -> MutexGuard<'static, TokenCache> {
+    // The cache is always consistent, a panic while holding the lock doesn't matter
+    TOKEN_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/**
+ * Validated tokens are cached until they expire, so the signature is only verified once per token
+ */
+pub async fn decode_token<C>(token: &str) -> Result<C, AuthError>
+where
+    C: DeserializeOwned,
+{
+    let token = token.trim();
+    let hash = token_hash(token);
+    let now = Utc::now();
+    let cached = {
+        let mut cache = token_cache();
+        if cache.is_revoked(&hash, now) {
+            return Err(AuthError::TokenRevoked);
+        }
+        cache.get(&hash, now)
+    };
+    let claims = match cached {
+        Some(claims) => claims,
+        None => {
+            let (claims, expires): (serde_json::Value, _) = get_decoder()
+                .await?
+                .read()
+                .await
+                .decode_token_with_expiration(token, true)?;
+            token_cache().insert(hash, claims.clone(), expires, now);
+            claims
+        }
+    };
+    serde_json::from_value(claims).map_err(|e| AuthError::JwtError(e.to_string()))
+}
+
+/**
+ * Reject the token until it expires, the token must have a valid signature but may have expired
+ */
+pub async fn revoke_token(token: &str) -> Result<RevokedToken, AuthError> {
+    let token = token.trim();
+    let (_, expires): (serde_json::Value, _) = get_decoder()
+        .await?
         .read()
         .await
-        .decode_token(token, true)
-        .map_err(|e| e.into())
+        .decode_token_with_expiration(token, false)?;
+    Ok(token_cache().revoke(&token_hash(token), Some(expires), Utc::now()))
+}
+
+/**
+ * Reject the token with the hash, used when the token itself is not available.
+ * Without `expires` the revocation lasts until the cached token expires, or for a day.
+ */
+pub fn revoke_token_hash(hash: &str, expires: Option<DateTime<Utc>>) -> RevokedToken {
+    token_cache().revoke(&hash.to_lowercase(), expires, Utc::now())
+}
+
+pub fn revoked_tokens() -> Vec<RevokedToken> {
+    let mut cache = token_cache();
+    cache.purge(Utc::now());
+    cache.revoked()
+}
+
+pub fn token_cache_stats() -> TokenCacheStats {
+    token_cache().stats()
 }
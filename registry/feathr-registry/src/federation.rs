@@ -0,0 +1,388 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::RwLock,
+};
+
+use futures::future::join_all;
+use log::{debug, warn};
+use poem::{
+    http::{Method, StatusCode},
+    Endpoint, IntoResponse, Request, Response, Result,
+};
+use registry_api::ApiError;
+use serde_json::Value;
+
+/// Field added to every merged entity to indicate which registry it comes from
+pub const FEDERATION_SOURCE_FIELD: &str = "registry";
+/// Response header indicating which registry served a forwarded request
+pub const FEDERATION_SOURCE_HEADER_NAME: &str = "x-registry-federation-source";
+/// Request header to explicitly pick the downstream registry for a request
+pub const FEDERATION_TARGET_HEADER_NAME: &str = "x-registry-federation-target";
+/// Response header listing the registries failed to answer a merged listing
+pub const FEDERATION_FAILURES_HEADER_NAME: &str = "x-registry-federation-failures";
+
+/// Request headers not forwarded to members, the client doesn't decode compressed responses
+const NOT_FORWARDED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "accept-encoding",
+    FEDERATION_TARGET_HEADER_NAME,
+];
+
+/**
+ * A downstream registry, in format of `name=url` or just `url`.
+ * The url must include the API base, e.g. `team-a=http://registry-a:8000/api`.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FederationMember {
+    pub name: String,
+    pub url: String,
+}
+
+impl FromStr for FederationMember {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, url) = match s.split_once('=') {
+            Some((name, url)) => (name.trim().to_string(), url.trim().to_string()),
+            None => (s.trim().to_string(), s.trim().to_string()),
+        };
+        if name.is_empty() || url.is_empty() {
+            return Err(format!("Invalid federation member '{}'", s));
+        }
+        let url = if url.starts_with("http://") || url.starts_with("https://") {
+            url
+        } else {
+            format!("http://{}", url)
+        };
+        Ok(Self {
+            name,
+            url: url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+/**
+ * Serves the registry API by proxying a set of downstream registries.
+ *
+ * Project listings are merged from all members and every entity is annotated with the
+ * member name, requests scoped to a project are forwarded to the registry owns the project.
+ */
+pub struct FederationEndpoint {
+    members: Vec<FederationMember>,
+    client: reqwest::Client,
+    /// Project name or id to the index of the member owns it
+    owners: RwLock<HashMap<String, usize>>,
+}
+
+impl FederationEndpoint {
+    pub fn new(members: Vec<FederationMember>) -> Self {
+        Self {
+            members,
+            client: reqwest::Client::new(),
+            owners: Default::default(),
+        }
+    }
+
+    fn build_request(
+        &self,
+        member: &FederationMember,
+        method: &Method,
+        path_and_query: &str,
+        req: &Request,
+    ) -> reqwest::RequestBuilder {
+        let url = format!("{}{}", member.url, path_and_query);
+        debug!("Federation: {} {}", method, url);
+        let method = reqwest::Method::from_bytes(method.as_str().as_bytes())
+            .unwrap_or(reqwest::Method::GET);
+        req.headers()
+            .iter()
+            .filter(|(name, _)| !NOT_FORWARDED_HEADERS.contains(&name.as_str()))
+            .fold(self.client.request(method, url), |r, (name, value)| {
+                r.header(name.as_str(), value.as_bytes())
+            })
+    }
+
+    async fn get_json(
+        &self,
+        member: &FederationMember,
+        path_and_query: &str,
+        req: &Request,
+    ) -> Result<Value, ApiError> {
+        let resp = self
+            .build_request(member, &Method::GET, path_and_query, req)
+            .send()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("{}: {}", member.name, e)))?;
+        let status = resp.status();
+        if !status.is_success() {
+            let message = format!("{}: {}", member.name, status);
+            return Err(match status {
+                reqwest::StatusCode::NOT_FOUND => ApiError::NotFoundError(message),
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                    ApiError::Forbidden(message)
+                }
+                _ => ApiError::InternalError(message),
+            });
+        }
+        resp.json()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("{}: {}", member.name, e)))
+    }
+
+    /**
+     * Fan out a read-only listing request to all members and merge the results
+     */
+    async fn merged_listing(&self, path_and_query: &str, req: &Request) -> Result<Response> {
+        let results = join_all(
+            self.members
+                .iter()
+                .map(|m| async move { (m, self.get_json(m, path_and_query, req).await) }),
+        )
+        .await;
+
+        let mut names: Vec<Value> = vec![];
+        let mut seen = HashSet::new();
+        let mut entities: Vec<Value> = vec![];
+        let mut v2 = false;
+        let mut failures: Vec<&str> = vec![];
+        for (member, result) in results {
+            match result {
+                // V1 API returns list of project names
+                Ok(Value::Array(items)) => {
+                    names.extend(items.into_iter().filter(|n| seen.insert(n.to_string())))
+                }
+                // V2 API returns `{ "entities": [...] }`
+                Ok(Value::Object(mut obj)) => {
+                    v2 = true;
+                    if let Some(Value::Array(items)) = obj.remove("entities") {
+                        entities.extend(items.into_iter().map(|mut e| {
+                            if let Value::Object(o) = &mut e {
+                                o.insert(
+                                    FEDERATION_SOURCE_FIELD.to_string(),
+                                    Value::String(member.name.clone()),
+                                );
+                            }
+                            e
+                        }));
+                    }
+                }
+                Ok(_) => {
+                    warn!("Federation: unexpected response from {}", member.name);
+                    failures.push(&member.name);
+                }
+                // One unavailable member should not break the global listing, it's reported in
+                // the response header instead
+                Err(e) => {
+                    warn!("Federation: {}", e);
+                    failures.push(&member.name);
+                }
+            }
+        }
+        if !self.members.is_empty() && failures.len() == self.members.len() {
+            return Err(ApiError::InternalError(format!(
+                "All registries failed: {}",
+                failures.join(",")
+            ))
+            .into());
+        }
+        let body = if v2 {
+            serde_json::json!({ "entities": entities })
+        } else {
+            Value::Array(names)
+        };
+        let mut resp = poem::web::Json(body).into_response();
+        if !failures.is_empty() {
+            resp = resp
+                .with_header(FEDERATION_FAILURES_HEADER_NAME, failures.join(","))
+                .into_response();
+        }
+        Ok(resp)
+    }
+
+    /**
+     * Find the registry owns the project, the first member that has it wins.
+     * The owner is cached until the member stops serving the project.
+     * If no member has it, a member denying the caller or failing to answer is reported instead
+     * of not found
+     */
+    async fn find_owner(
+        &self,
+        api_version: &str,
+        project: &str,
+        req: &Request,
+    ) -> Result<&FederationMember, ApiError> {
+        let cached = self.owners.read().unwrap().get(project).copied();
+        if let Some(idx) = cached {
+            return Ok(&self.members[idx]);
+        }
+        let path = format!("/{}/projects/{}", api_version, project);
+        let results = join_all(self.members.iter().map(|m| {
+            let path = path.as_str();
+            async move { self.get_json(m, path, req).await }
+        }))
+        .await;
+        let mut error = None;
+        for (idx, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(_) => {
+                    self.owners
+                        .write()
+                        .unwrap()
+                        .insert(project.to_string(), idx);
+                    return Ok(&self.members[idx]);
+                }
+                Err(ApiError::NotFoundError(_)) => {}
+                Err(e @ ApiError::Forbidden(_)) => error = Some(e),
+                Err(e) => {
+                    warn!("Federation: {}", e);
+                    if !matches!(error, Some(ApiError::Forbidden(_))) {
+                        error = Some(e);
+                    }
+                }
+            }
+        }
+        Err(error.unwrap_or_else(|| ApiError::NotFoundError(project.to_string())))
+    }
+
+    fn forget_owner(&self, project: &str) {
+        self.owners.write().unwrap().remove(project);
+    }
+
+    fn find_target(&self, req: &Request) -> Option<&FederationMember> {
+        req.headers()
+            .get(FEDERATION_TARGET_HEADER_NAME)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|name| self.members.iter().find(|m| m.name == name))
+    }
+
+    async fn forward(&self, member: &FederationMember, req: Request) -> Result<Response> {
+        let method = req.method().clone();
+        let path_and_query = path_and_query(&req);
+        let builder = self.build_request(member, &method, &path_and_query, &req);
+        let body = req.into_body().into_bytes().await?;
+        let resp = builder
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ApiError::InternalError(format!("{}: {}", member.name, e)))?;
+        to_response(member, resp).await
+    }
+}
+
+/**
+ * Convert the response of a member, all headers other than the hop-by-hop ones are kept so
+ * ETags, opt-seq and staleness headers reach the client
+ */
+async fn to_response(member: &FederationMember, resp: reqwest::Response) -> Result<Response> {
+    let status =
+        StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut builder = Response::builder().status(status);
+    for (name, value) in resp.headers() {
+        if !HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+            builder = builder.header(name.as_str(), value.as_bytes());
+        }
+    }
+    let body = resp
+        .bytes()
+        .await
+        .map_err(|e| ApiError::InternalError(format!("{}: {}", member.name, e)))?;
+    Ok(builder
+        .header(FEDERATION_SOURCE_HEADER_NAME, member.name.as_str())
+        .body(body.to_vec()))
+}
+
+/// Headers only meaningful to the connection between the federation node and the member
+const HOP_BY_HOP_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+fn path_and_query(req: &Request) -> String {
+    match req.uri().query() {
+        Some(q) => format!("{}?{}", req.uri().path(), q),
+        None => req.uri().path().to_string(),
+    }
+}
+
+#[async_trait::async_trait]
+impl Endpoint for FederationEndpoint {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let segments: Vec<String> = req
+            .uri()
+            .path()
+            .trim_matches('/')
+            .split('/')
+            .map(|s| s.to_string())
+            .collect();
+
+        // Explicitly targeted requests go to the specified registry directly
+        if let Some(member) = self.find_target(&req) {
+            return self.forward(member, req).await;
+        }
+
+        match segments.iter().map(|s| s.as_str()).collect::<Vec<_>>().as_slice() {
+            [_, "projects"] if req.method() == Method::GET => {
+                self.merged_listing(&path_and_query(&req), &req).await
+            }
+            [version, "projects", project, ..] => {
+                let member = self.find_owner(version, project, &req).await?;
+                let resp = self.forward(member, req).await?;
+                // The project may have been deleted or moved to another member
+                if resp.status() == StatusCode::NOT_FOUND {
+                    self.forget_owner(project);
+                }
+                Ok(resp)
+            }
+            _ if req.method() == Method::GET => {
+                // Entities not scoped by project, try all registries in order and return the
+                // first successful response
+                let path_and_query = path_and_query(&req);
+                for member in self.members.iter() {
+                    match self
+                        .build_request(member, &Method::GET, &path_and_query, &req)
+                        .send()
+                        .await
+                    {
+                        Ok(resp) if resp.status().is_success() => {
+                            return to_response(member, resp).await
+                        }
+                        Ok(resp) => debug!("Federation: {} {}", member.name, resp.status()),
+                        Err(e) => warn!("Federation: {}: {}", member.name, e),
+                    }
+                }
+                Err(ApiError::NotFoundError(req.uri().path().to_string()).into())
+            }
+            _ => Err(ApiError::BadRequest(format!(
+                "Cannot determine the owning registry, use `{}` header to specify one",
+                FEDERATION_TARGET_HEADER_NAME
+            ))
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FederationMember, NOT_FORWARDED_HEADERS};
+
+    #[test]
+    fn test_parse_member() {
+        let m: FederationMember = "team-a=http://registry-a:8000/api/".parse().unwrap();
+        assert_eq!(m.name, "team-a");
+        assert_eq!(m.url, "http://registry-a:8000/api");
+        let m: FederationMember = "registry-b:8000/api".parse().unwrap();
+        assert_eq!(m.name, "registry-b:8000/api");
+        assert_eq!(m.url, "http://registry-b:8000/api");
+        assert!("=http://x".parse::<FederationMember>().is_err());
+        // Members would answer with compressed bodies the client cannot decode
+        assert!(NOT_FORWARDED_HEADERS.contains(&"accept-encoding"));
+    }
+}
@@ -1,29 +1,47 @@
 use std::{
     convert::Infallible,
-    fs::{read_dir, remove_dir_all},
+    fs::{read_dir, read_to_string, remove_dir_all},
     path::PathBuf,
     pin::Pin,
     process::exit,
+    time::Duration,
     vec,
 };
 
 use clap::Parser;
 use common_utils::Logged;
 use futures::{future::join_all, Future};
-use log::{debug, info};
+use log::{debug, info, warn};
 use poem::{
     listener::TcpListener,
-    middleware::{Cors, Tracing},
+    middleware::{Compression, Cors, Tracing},
     web::Json,
-    EndpointExt, Route, Server,
+    EndpointExt, IntoEndpoint, Route, Server,
 };
 use poem_openapi::OpenApiService;
 use raft_registry::{
-    management_routes, raft_routes, FeathrApiV1, FeathrApiV2, NodeConfig, RaftRegistryApp,
-    RaftSequencer, RbacMiddleware,
+    bootstrap_from_leader, cluster_node_ids, management_routes, raft_routes, restore_backup,
+    run_backup_task, run_duplicates_task, run_gc_task, run_membership_task, run_replica_task,
+    BackupConfig, ConditionalGet, FeathrApiV1, FeathrApiV2, GcConfig, MembershipConfig,
+    MembershipPolicy, NodeConfig, RaftRegistryApp, RaftSequencer, RbacMiddleware, ReadReplica,
+    ReplicaConfig, SampleConfig, SourceSampler,
+};
+use registry_api::{
+    api_json_schema, api_typescript_definitions, BannedSourceTypesPolicy, NamePolicy,
+    ProjectTemplate, RequiredTagsPolicy, ValidationChain, WebhookValidator,
+    DEFAULT_WEBHOOK_CONNECT_TIMEOUT,
+};
+use registry_provider::LoadPolicy;
+#[cfg(feature = "onnx")]
+use sql_provider::OnnxEmbedder;
+use sql_provider::{
+    attach_storage, set_embedder, set_fts_config, set_project_cache_size, FieldBoosts, FtsConfig,
+    HttpEmbedder, RankingConfig,
 };
-use sql_provider::attach_storage;
 
+use crate::federation::{FederationEndpoint, FederationMember};
+
+mod federation;
 mod spa_endpoint;
 
 #[derive(Parser, Clone, Debug)]
@@ -57,16 +75,147 @@ pub struct Opt {
     #[clap(long)]
     pub load_db: bool,
 
+    /// How entities loaded from the database conflicting with the existing ones are handled, `skip`, `overwrite` or `new-version`
+    #[clap(long, env = "LOAD_DB_POLICY", default_value = "skip")]
+    pub load_db_policy: LoadPolicy,
+
     /// True to write updates to the database
     #[clap(long)]
     pub write_db: bool,
 
+    /// Join the cluster as learner, overwrite the database with the replicated data and exit
+    #[clap(long)]
+    pub dump_db_and_exit: bool,
+
     /// Do not init cluster when joining failed
     #[clap(long)]
     pub no_init: bool,
 
+    /// Download the latest snapshot from the leader before joining the cluster, so only the logs after the snapshot are replicated
+    #[clap(long, env = "FAST_BOOTSTRAP")]
+    pub fast_bootstrap: bool,
+
+    /// Validate the graph invariants after the data is loaded and report the issues
+    #[clap(long)]
+    pub fsck: bool,
+
+    /// Same as `--fsck`, and also repair dangling references and missing reflection edges
+    #[clap(long)]
+    pub fsck_repair: bool,
+
+    /// Run in federation mode, proxying the comma separated list of downstream registries in format of `name=url`
+    #[clap(long, env = "FEDERATION_MEMBERS", value_delimiter = ',')]
+    pub federation_members: Vec<FederationMember>,
+
+    /// Names of all new entities must match this regex
+    #[clap(long, env = "NAME_POLICY")]
+    pub name_policy: Option<String>,
+
+    /// Comma separated list of tags all new entities must carry
+    #[clap(long, env = "REQUIRED_TAGS", value_delimiter = ',')]
+    pub required_tags: Vec<String>,
+
+    /// Comma separated list of source types cannot be registered
+    #[clap(long, env = "BANNED_SOURCE_TYPES", value_delimiter = ',')]
+    pub banned_source_types: Vec<String>,
+
+    /// Comma separated list of external validator URLs, new definitions are posted to them before being written
+    #[clap(long, env = "VALIDATION_WEBHOOKS", value_delimiter = ',')]
+    pub validation_webhooks: Vec<String>,
+
+    /// Seconds to wait for a validation webhook to respond, the definition is rejected on timeout
+    #[clap(long, env = "VALIDATION_WEBHOOK_TIMEOUT", default_value = "10")]
+    pub validation_webhook_timeout: u64,
+
+    /// OpenAI compatible embedding API URL, enables semantic search when set
+    #[clap(long, env = "EMBEDDING_API_URL")]
+    pub embedding_api_url: Option<String>,
+
+    /// Embedding model name sent to the embedding API
+    #[clap(long, env = "EMBEDDING_MODEL", default_value = "text-embedding-ada-002")]
+    pub embedding_model: String,
+
+    /// API key of the embedding API
+    #[clap(long, env = "EMBEDDING_API_KEY", hide_env_values = true)]
+    pub embedding_api_key: Option<String>,
+
+    /// Local sentence embedding model in the ONNX format, enables semantic search without an embedding API, requires the `onnx` feature
+    #[clap(long, env = "EMBEDDING_MODEL_PATH")]
+    pub embedding_model_path: Option<PathBuf>,
+
+    /// `tokenizer.json` of the local embedding model, default to the one next to the model
+    #[clap(long, env = "EMBEDDING_TOKENIZER_PATH")]
+    pub embedding_tokenizer_path: Option<PathBuf>,
+
+    /// Milliseconds between background commits of the FTS index, new entities become searchable after the commit
+    #[clap(long, env = "FTS_COMMIT_INTERVAL_MS", default_value = "1000")]
+    pub fts_commit_interval_ms: u64,
+
+    /// Memory budget of the in-memory FTS segment in MB
+    #[clap(long, env = "FTS_MEMORY_BUDGET_MB", default_value = "50")]
+    pub fts_memory_budget_mb: usize,
+
+    /// Keep the FTS index under this directory instead of in memory
+    #[clap(long, env = "FTS_INDEX_DIR")]
+    pub fts_index_dir: Option<PathBuf>,
+
+    /// Boosts of search matches in each field, comma separated list of `field=boost`, fields are `name`, `qualified_name`, `tags` and `docs`
+    #[clap(
+        long,
+        env = "FTS_FIELD_BOOSTS",
+        default_value = "name=4,qualified_name=2,tags=1.5,docs=1"
+    )]
+    pub fts_field_boosts: FieldBoosts,
+
+    /// Recently modified entities score up to `1 + boost` times in search results, 0 disables the recency boost
+    #[clap(long, env = "FTS_RECENCY_BOOST", default_value = "0.5")]
+    pub fts_recency_boost: f32,
+
+    /// Days for the recency boost to halve since the last modification
+    #[clap(long, env = "FTS_RECENCY_HALF_LIFE_DAYS", default_value = "30")]
+    pub fts_recency_half_life_days: u64,
+
+    /// Do not list entities with the name equal to the search query first
+    #[clap(long, env = "FTS_NO_EXACT_PINNING")]
+    pub fts_no_exact_pinning: bool,
+
+    /// Max number of project subgraphs cached for project level read APIs, 0 disables the cache
+    #[clap(long, env = "PROJECT_CACHE_SIZE", default_value = "64")]
+    pub project_cache_size: usize,
+
+    /// Seconds to remember the `Idempotency-Key` of creation requests, retries within the window get the same response, 0 disables the key
+    #[clap(long, env = "IDEMPOTENCY_WINDOW", default_value = "86400")]
+    pub idempotency_window: u64,
+
+    /// Serve reads from the local state when the leader is unreachable or has lost the quorum, responses are marked with `x-registry-degraded: true`
+    #[clap(long, env = "DEGRADED_READS")]
+    pub degraded_reads: bool,
+
+    /// Seconds between background checks for near-duplicate features, 0 disables the check
+    #[clap(long, env = "DUPLICATE_CHECK_INTERVAL", default_value = "3600")]
+    pub duplicate_check_interval: u64,
+
+    /// JSON file of the templates used by `POST /projects/from-template`
+    #[clap(long, env = "PROJECT_TEMPLATES")]
+    pub project_templates: Option<PathBuf>,
+
     #[clap(flatten)]
     pub node_config: NodeConfig,
+
+    #[clap(flatten)]
+    pub backup_config: BackupConfig,
+
+    #[clap(flatten)]
+    pub gc_config: GcConfig,
+
+    #[clap(flatten)]
+    pub sample_config: SampleConfig,
+
+    #[clap(flatten)]
+    pub membership_config: MembershipConfig,
+
+    #[clap(flatten)]
+    pub replica_config: ReplicaConfig,
 }
 
 /**
@@ -108,6 +257,112 @@ fn cleanup_logs(options: &Opt, node_id: u64) -> anyhow::Result<()> {
     Ok(())
 }
 
+/**
+ * Build validation chain from options, built-in policies run before webhooks
+ */
+fn build_validation_chain(options: &Opt) -> anyhow::Result<ValidationChain> {
+    let mut chain = ValidationChain::new();
+    if let Some(pattern) = &options.name_policy {
+        chain = chain.hook(NamePolicy::new(pattern)?);
+    }
+    if !options.required_tags.is_empty() {
+        chain = chain.hook(RequiredTagsPolicy::new(&options.required_tags));
+    }
+    if !options.banned_source_types.is_empty() {
+        chain = chain.hook(BannedSourceTypesPolicy::new(&options.banned_source_types));
+    }
+    for url in options.validation_webhooks.iter() {
+        chain = chain.hook(WebhookValidator::with_timeouts(
+            url,
+            DEFAULT_WEBHOOK_CONNECT_TIMEOUT,
+            Duration::from_secs(options.validation_webhook_timeout),
+        ));
+    }
+    Ok(chain)
+}
+
+fn set_embedder_from_options(options: &Opt) -> anyhow::Result<()> {
+    match (&options.embedding_model_path, &options.embedding_api_url) {
+        (Some(_), Some(_)) => {
+            anyhow::bail!(
+                "`--embedding-model-path` and `--embedding-api-url` cannot be used together"
+            )
+        }
+        #[cfg(feature = "onnx")]
+        (Some(model_path), None) => {
+            let tokenizer_path = options
+                .embedding_tokenizer_path
+                .clone()
+                .unwrap_or_else(|| model_path.with_file_name("tokenizer.json"));
+            set_embedder(OnnxEmbedder::new(model_path, tokenizer_path)?)?;
+        }
+        #[cfg(not(feature = "onnx"))]
+        (Some(model_path), None) => anyhow::bail!(
+            "Cannot load {}, local embedding models require the `onnx` feature",
+            model_path.display()
+        ),
+        (None, Some(url)) => {
+            let mut embedder = HttpEmbedder::new(url, &options.embedding_model);
+            if let Some(key) = &options.embedding_api_key {
+                embedder = embedder.api_key(key);
+            }
+            set_embedder(embedder)?;
+        }
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+fn load_project_templates(options: &Opt) -> anyhow::Result<Vec<ProjectTemplate>> {
+    match &options.project_templates {
+        Some(path) => {
+            let templates = ProjectTemplate::parse_list(&read_to_string(path)?)?;
+            info!("Loaded {} project templates", templates.len());
+            Ok(templates)
+        }
+        None => Ok(vec![]),
+    }
+}
+
+/**
+ * Serve merged read-only results from downstream registries, the node doesn't join any Raft cluster
+ */
+async fn run_federation(options: Opt) -> anyhow::Result<()> {
+    info!(
+        "Starting in federation mode with {} downstream registries",
+        options.federation_members.len()
+    );
+    let api_base = format!("/{}", options.api_base.trim_start_matches('/'));
+    let route = Route::new()
+        .nest(
+            api_base,
+            FederationEndpoint::new(options.federation_members.clone()).with(Cors::new()),
+        )
+        .nest(
+            "version",
+            poem::endpoint::make_sync(move |_| {
+                let version = option_env!("CARGO_PKG_VERSION").unwrap_or("<unknown>");
+                Result::<_, Infallible>::Ok(Json(serde_json::json!({
+                    "version": version,
+                    "federation": true,
+                    "api_versions": ["v1", "v2"],
+                })))
+            }),
+        )
+        .nest(
+            "/",
+            spa_endpoint::SpaEndpoint::new("./static-files", "index.html"),
+        )
+        .with(Tracing);
+    Server::new(TcpListener::bind(
+        options.http_addr.trim_start_matches("http://"),
+    ))
+    .run(route)
+    .await
+    .log()
+    .map_err(anyhow::Error::from)
+}
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     common_utils::init_logger();
@@ -115,6 +370,27 @@ async fn main() -> Result<(), anyhow::Error> {
     // Parse the parameters passed by arguments.
     let options = Opt::parse();
 
+    if !options.federation_members.is_empty() {
+        return run_federation(options).await;
+    }
+
+    if options.backup_config.restore_from.is_some()
+        && (!options.seeds.is_empty() || options.load_db)
+    {
+        println!("ERROR: `--restore-from` can only be used to start a new cluster without `--seeds` or `--load-db`.");
+        exit(1);
+    }
+
+    if options.dump_db_and_exit && (options.seeds.is_empty() || options.node_id.is_none()) {
+        println!("ERROR: `--dump-db-and-exit` requires `--seeds` and a `--node-id` not used by any node of the cluster.");
+        exit(1);
+    }
+
+    if options.replica_config.read_replica && options.seeds.is_empty() {
+        println!("ERROR: `--read-replica` must be used with `--seeds` to join an existing cluster.");
+        exit(1);
+    }
+
     let ext_http_addr = options
         .ext_http_addr
         .clone()
@@ -122,6 +398,22 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let node_config = options.node_config.clone();
 
+    // Must be set before the registry is created
+    set_fts_config(FtsConfig {
+        commit_interval: Duration::from_millis(options.fts_commit_interval_ms),
+        memory_budget: options.fts_memory_budget_mb * 1_000_000,
+        index_dir: options.fts_index_dir.clone(),
+        ranking: RankingConfig {
+            field_boosts: options.fts_field_boosts,
+            recency_boost: options.fts_recency_boost.max(0.0),
+            recency_half_life: Duration::from_secs(options.fts_recency_half_life_days * 24 * 3600),
+            pin_exact_match: !options.fts_no_exact_pinning,
+        },
+    })?;
+    set_project_cache_size(options.project_cache_size)?;
+    // Embeddings of the loaded entities are computed in the background once the embedder is set
+    set_embedder_from_options(&options)?;
+
     let app = if options.seeds.is_empty() {
         info!("Starting as cluster leader");
         cleanup_logs(&options, 1).ok();
@@ -129,23 +421,47 @@ async fn main() -> Result<(), anyhow::Error> {
         app.init().await.ok();
         app
     } else {
-        RaftRegistryApp::new(
-            match options.node_id {
-                Some(id) => {
-                    info!("Joining cluster with node id = {}", id);
-                    cleanup_logs(&options, id).ok();
-                    id
-                }
-                None => {
-                    println!("ERROR: Node ID must be specified.");
+        let node_id = match options.node_id {
+            Some(id) => {
+                // Joining replaces the node with the same id, which must not happen to a node
+                // that only dumps the data
+                if options.dump_db_and_exit
+                    && cluster_node_ids(&options.seeds, &node_config)
+                        .await?
+                        .contains(&id)
+                {
+                    println!("ERROR: Node {} is already in the cluster, `--dump-db-and-exit` needs an unused node id.", id);
                     exit(1);
                 }
-            },
-            ext_http_addr.clone(),
-            node_config,
-        )
-        .await
+                info!("Joining cluster with node id = {}", id);
+                cleanup_logs(&options, id).ok();
+                id
+            }
+            None => {
+                println!("ERROR: Node ID must be specified.");
+                exit(1);
+            }
+        };
+        if options.fast_bootstrap {
+            // The snapshot is installed when the node is created, falls back to replaying the whole log
+            match bootstrap_from_leader(node_id, &options.seeds, &node_config).await {
+                Ok(index) => info!(
+                    "Bootstrapped from the leader snapshot at log index {}",
+                    index
+                ),
+                Err(e) => warn!("Fast bootstrap failed, error: {}", e),
+            }
+        }
+        RaftRegistryApp::new(node_id, ext_http_addr.clone(), node_config).await
     };
+    let app = app
+        .with_validation(build_validation_chain(&options)?)
+        .with_sampler(SourceSampler::new(&options.sample_config))
+        .with_membership_policy(MembershipPolicy::new(options.membership_config.clone()))
+        .with_read_replica(ReadReplica::new(options.replica_config.clone()))
+        .with_idempotency_window(Duration::from_secs(options.idempotency_window))
+        .with_degraded_reads(options.degraded_reads)
+        .with_project_templates(load_project_templates(&options)?);
 
     let api_base = format!("/{}", options.api_base.trim_start_matches('/'));
     let http_addr = ext_http_addr
@@ -172,18 +488,43 @@ async fn main() -> Result<(), anyhow::Error> {
     let spec_v2 = api_service_v2.spec();
 
     let api_route = Route::new()
-        .nest("/v1", api_service_v1)
-        .nest("/v2", api_service_v2)
+        .nest(
+            "/v1",
+            api_service_v1
+                .into_endpoint()
+                .with(FeathrApiV1::authorization()),
+        )
+        .nest(
+            "/v2",
+            api_service_v2
+                .into_endpoint()
+                .with(FeathrApiV2::authorization()),
+        )
+        .with(ConditionalGet::new(app.store.clone()))
         .with(Tracing)
-        .with(RaftSequencer::new(app.store.clone()))
+        .with(RaftSequencer::new(app.store.clone()).replica(app.replica.clone()))
         .with(Cors::new())
-        .with(RbacMiddleware);
+        .with(RbacMiddleware)
+        // Large lineage responses compress well, the encoding is negotiated with `Accept-Encoding`
+        .with(Compression::new());
 
     let docs_route = Route::new().nest("/v1", ui_v1).nest("/v2", ui_v2);
 
+    // JSON Schema and TypeScript definitions of the API models, for the UI and other clients
+    let schema = api_json_schema();
+    let typescript = api_typescript_definitions();
+
     let spec_route = Route::new()
         .at("/v1", poem::endpoint::make_sync(move |_| spec_v1.clone()))
-        .at("/v2", poem::endpoint::make_sync(move |_| spec_v2.clone()));
+        .at("/v2", poem::endpoint::make_sync(move |_| spec_v2.clone()))
+        .at(
+            "/schema.json",
+            poem::endpoint::make_sync(move |_| Json(schema.clone())),
+        )
+        .at(
+            "/schema.d.ts",
+            poem::endpoint::make_sync(move |_| typescript.clone()),
+        );
 
     let route = management_routes(raft_routes(Route::new()))
         .nest("spec", spec_route)
@@ -217,7 +558,27 @@ async fn main() -> Result<(), anyhow::Error> {
         .map_err(anyhow::Error::from)
     };
     let raft_task = async {
-        if !options.seeds.is_empty() {
+        if options.dump_db_and_exit {
+            // Join as learner so the cluster membership is not affected after this node exits
+            debug!("Joining cluster as learner");
+            app.join_cluster(&options.seeds, false).await.log()?;
+            info!("Dumping data into db");
+            let dumped = app.dump_data().await.log();
+            // Don't leave a dead learner in the cluster, even if the dump failed
+            app.leave_cluster().await.log()?;
+            dumped?;
+            info!("Data dumped, exiting");
+            exit(0);
+        }
+        if !options.seeds.is_empty() && options.replica_config.read_replica {
+            // Replicas are tagged by the leader and never promoted
+            debug!("Joining cluster as read replica");
+            app.join_cluster(&options.seeds, false).await.log()?
+        } else if !options.seeds.is_empty() && options.learner {
+            // Learners are promoted by the leader if `--auto-promote` is enabled there
+            debug!("Joining cluster as learner");
+            app.join_cluster(&options.seeds, false).await.log()?
+        } else if !options.seeds.is_empty() {
             debug!("Joining cluster");
             app.join_or_init(&options.seeds, !options.no_init)
                 .await
@@ -226,7 +587,28 @@ async fn main() -> Result<(), anyhow::Error> {
 
         if options.load_db {
             debug!("Loading data from db");
-            app.load_data().await.log()?;
+            let report = app.load_data(options.load_db_policy).await.log()?;
+            info!(
+                "Loaded data from db, {} entities applied, {} skipped",
+                report.applied.len(),
+                report.skipped.len()
+            );
+        }
+        if let Some(url) = &options.backup_config.restore_from {
+            restore_backup(&app, url).await.log()?;
+            info!("Registry restored from backup");
+        }
+        if options.fsck || options.fsck_repair {
+            // Before attaching the storage, so repairs are not written to the database
+            let report = app.fsck(options.fsck_repair).await;
+            for issue in &report.issues {
+                warn!("fsck: {}", issue);
+            }
+            info!(
+                "fsck found {} issues, {} repaired",
+                report.issues.len(),
+                report.repaired
+            );
         }
         if options.write_db {
             // This is a writer node
@@ -234,6 +616,20 @@ async fn main() -> Result<(), anyhow::Error> {
         }
         Ok(())
     };
+    if options.backup_config.backup_url.is_some() {
+        tokio::spawn(run_backup_task(app.clone(), options.backup_config.clone()));
+    }
+    tokio::spawn(run_membership_task(app.clone()));
+    tokio::spawn(run_replica_task(app.clone()));
+    if options.duplicate_check_interval > 0 {
+        tokio::spawn(run_duplicates_task(
+            app.clone(),
+            options.duplicate_check_interval,
+        ));
+    }
+    if options.gc_config.gc_interval > 0 {
+        tokio::spawn(run_gc_task(app.clone(), options.gc_config.clone()));
+    }
     let tasks: Vec<Pin<Box<dyn Future<Output = anyhow::Result<()>>>>> =
         vec![Box::pin(svc_task), Box::pin(raft_task)];
     join_all(tasks.into_iter())
@@ -1,25 +1,34 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     sync::Arc,
 };
 
-use log::{debug, trace};
+use chrono::{Duration, Utc};
+use futures_util::stream::BoxStream;
+use log::{debug, info, trace, warn};
 use openraft::{
     error::{CheckIsLeaderError, InitializeError},
     raft::ClientWriteRequest,
     Config, EntryPayload, Node, Raft,
 };
-use poem::error::Forbidden;
 use registry_api::{
-    ApiError, FeathrApiProvider, FeathrApiRequest, FeathrApiResponse, IntoApiResult,
+    AnchorDef, ApiError, FeathrApiProvider, FeathrApiRequest, FeathrApiResponse, FeedPage,
+    IdempotencyKey, IntoApiResult, ProjectTemplate, RbacEvent, ValidationChain,
 };
-use registry_provider::{Credential, Permission, RbacError, RbacProvider};
-use sql_provider::load_content;
+use registry_provider::{
+    Credential, EntityProperty, LoadPolicy, LoadReport, Permission, RbacError,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sql_provider::{dump_content, load_content, FsckReport, Registry};
 use tokio::net::ToSocketAddrs;
+use uuid::Uuid;
 
 use crate::{
-    ManagementCode, RegistryClient, RegistryNetwork, RegistryNodeId, RegistryRaft, RegistryStore,
-    Restore,
+    network::mark_degraded, rbac_middleware::current_tenant, save_bootstrap_snapshot, FeedPosition,
+    ManagementTokens, MembershipPolicy, NodeConfig, ReadReplica, RegistryClient, RegistryNetwork,
+    RegistryNodeId, RegistryRaft, RegistryStore, Restore, RevokeManagementTokenRequest,
+    RevokedManagementToken, SourceSampler,
 };
 
 // Representation of an application state. This struct can be shared around to share
@@ -32,13 +41,38 @@ pub struct RaftRegistryApp {
     pub store: Arc<RegistryStore>,
     pub config: Arc<Config>,
     pub forwarder: RegistryClient,
+    pub validation: Arc<ValidationChain>,
+    pub sampler: Arc<SourceSampler>,
+    pub membership: Arc<MembershipPolicy>,
+    pub replica: Arc<ReadReplica>,
+    pub idempotency_window: Duration,
+    pub degraded_reads: bool,
+    pub templates: Arc<BTreeMap<String, ProjectTemplate>>,
+    pub management_tokens: Arc<ManagementTokens>,
 }
 
+/// Max length of the `Idempotency-Key` header
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+const DEFAULT_FEED_SIZE: usize = 100;
+const MAX_FEED_SIZE: usize = 1000;
+
 impl RaftRegistryApp {
     pub async fn new(node_id: RegistryNodeId, addr: String, cfg: crate::NodeConfig) -> Self {
         // Create a configuration for the raft instance.
         let config = Arc::new(cfg.raft_config.clone());
 
+        // Revocations of management tokens are kept next to the journal
+        let management_tokens = ManagementTokens::new(
+            cfg.management_code.clone(),
+            Some(
+                format!(
+                    "{}/{}-{}-revoked-management-tokens.json",
+                    cfg.journal_path, cfg.instance_prefix, node_id
+                )
+                .into(),
+            ),
+        );
+
         // Create a instance of where the Raft data will be stored.
         let es = RegistryStore::open_create(node_id, cfg.clone());
 
@@ -66,7 +100,116 @@ impl RaftRegistryApp {
             store,
             config,
             forwarder,
+            validation: Default::default(),
+            sampler: Default::default(),
+            membership: Default::default(),
+            replica: Default::default(),
+            idempotency_window: Duration::hours(24),
+            degraded_reads: false,
+            templates: Default::default(),
+            management_tokens: Arc::new(management_tokens),
+        }
+    }
+
+    /**
+     * Validate definitions with the chain before writing them into the registry
+     */
+    pub fn with_validation(mut self, validation: ValidationChain) -> Self {
+        self.validation = Arc::new(validation);
+        self
+    }
+
+    /**
+     * Preview data sources with the sampler
+     */
+    pub fn with_sampler(mut self, sampler: SourceSampler) -> Self {
+        self.sampler = Arc::new(sampler);
+        self
+    }
+
+    /**
+     * Promote and demote nodes automatically with the policy
+     */
+    pub fn with_membership_policy(mut self, policy: MembershipPolicy) -> Self {
+        self.membership = Arc::new(policy);
+        self
+    }
+
+    /**
+     * Run as a read replica, which serves reads locally even without `x-registry-opt-seq`
+     */
+    pub fn with_read_replica(mut self, replica: ReadReplica) -> Self {
+        self.replica = Arc::new(replica);
+        self
+    }
+
+    /**
+     * Serve reads from the local state machine when the leader is unreachable or has lost the
+     * quorum, instead of failing them
+     */
+    pub fn with_degraded_reads(mut self, enabled: bool) -> Self {
+        self.degraded_reads = enabled;
+        self
+    }
+
+    /**
+     * Templates new projects can be created from with `POST /projects/from-template`
+     */
+    pub fn with_project_templates(mut self, templates: Vec<ProjectTemplate>) -> Self {
+        self.templates = Arc::new(
+            templates
+                .into_iter()
+                .map(|t| (t.name.to_owned(), t))
+                .collect(),
+        );
+        self
+    }
+
+    /**
+     * Retries of creation requests with the same idempotency key within the window get the
+     * response of the first request, zero disables idempotency keys
+     */
+    pub fn with_idempotency_window(mut self, window: std::time::Duration) -> Self {
+        self.idempotency_window =
+            Duration::from_std(window).unwrap_or_else(|_| Duration::max_value());
+        self
+    }
+
+    /**
+     * Idempotency key of the creation request from the `Idempotency-Key` header, the key expires
+     * after the idempotency window. `body` is the request as sent by the client, before any default
+     * is filled in, so retries have the same fingerprint
+     */
+    pub fn idempotency_key<T: Serialize>(
+        &self,
+        key: Option<String>,
+        credential: &Credential,
+        body: &T,
+    ) -> poem::Result<Option<IdempotencyKey>> {
+        let key = match key {
+            Some(key) if self.idempotency_window > Duration::zero() => key,
+            _ => return Ok(None),
+        };
+        if key.trim().is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+            return Err(ApiError::BadRequest(format!(
+                "Idempotency key must be 1 to {} characters",
+                MAX_IDEMPOTENCY_KEY_LEN
+            ))
+            .into());
         }
+        // Map keys are sorted in `Value`, so the hash doesn't depend on the order of `HashMap`s
+        let body = serde_json::to_value(body).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        let mut hasher = Sha256::new();
+        hasher.update(credential.to_string().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(body.to_string().as_bytes());
+        let now = Utc::now();
+        Ok(Some(IdempotencyKey {
+            key,
+            fingerprint: hex::encode(hasher.finalize()),
+            received_at: now,
+            expires_at: now + self.idempotency_window,
+        }))
     }
 
     pub async fn check_permission(
@@ -83,40 +226,157 @@ impl RaftRegistryApp {
             }
         };
         if !self
-            .store
-            .state_machine
-            .read()
+            .with_registry(|registry, tenant| {
+                registry.check_permission_in_tenant(tenant, credential, &resource, permission)
+            })
             .await
-            .registry
-            .check_permission(credential, &resource, permission)
             .map_api_error()?
         {
-            return Err(Forbidden(RbacError::PermissionDenied(
+            return Err(ApiError::from(RbacError::PermissionDenied(
                 credential.to_string(),
                 resource,
                 permission,
-            )));
+            ))
+            .into());
         }
         Ok(())
     }
 
-    pub async fn check_code(&self, code: Option<ManagementCode>) -> poem::Result<()> {
-        trace!("Checking code {:?}", code);
-        match self.store.get_management_code() {
-            Some(c) => match code.map(|c| c.code().to_string()) {
-                Some(code) => {
-                    if c == code {
-                        Ok(())
-                    } else {
-                        Err(ApiError::Forbidden("forbidden".to_string()))?
+    /**
+     * Check permission on an anchor or feature under the project, e.g. `["anchor1", "feature1"]`,
+     * grants on the project and the anchor are inherited.
+     * Falls back to the closest resolvable parent if the child doesn't exist.
+     */
+    pub async fn check_child_permission(
+        &self,
+        credential: &Credential,
+        project: &str,
+        children: &[&str],
+        permission: Permission,
+    ) -> poem::Result<()> {
+        let resource = self
+            .with_registry(|registry, tenant| {
+                let mut parent = match registry.get_entity_id_in_tenant(tenant, project) {
+                    Ok(id) => id,
+                    Err(_) => return project.to_string(),
+                };
+                for child in children {
+                    // Same resolution as the request handler, child can be id, qualified name or name
+                    let id = registry
+                        .get_entity_id_in_tenant(tenant, child)
+                        .or_else(|_| {
+                            let name =
+                                registry.get_entity_qualified_name_in_tenant(tenant, parent)?;
+                            registry
+                                .get_entity_id_in_tenant(tenant, &format!("{}__{}", name, child))
+                        });
+                    match id {
+                        Ok(id) => parent = id,
+                        Err(_) => break,
                     }
                 }
-                None => Err(ApiError::Forbidden("forbidden".to_string()))?,
+                parent.to_string()
+            })
+            .await;
+        self.check_permission(credential, Some(&resource), permission)
+            .await
+    }
+
+    /**
+     * Entity changes in the current tenant applied on this node after the position.
+     * The feed is served locally, a lagging node returns fewer changes and a smaller `next_since`,
+     * so polling different nodes never skips a change.
+     */
+    pub async fn change_feed(&self, since: FeedPosition, size: Option<usize>) -> FeedPage {
+        let limit = size.unwrap_or(DEFAULT_FEED_SIZE).clamp(1, MAX_FEED_SIZE);
+        let state_machine = self.store.state_machine.read().await;
+        let latest = state_machine
+            .last_applied_log
+            .map(|l| l.index)
+            .unwrap_or_default();
+        let (changes, complete) = state_machine.feed.since(&current_tenant(), since, limit);
+        let next_since = match changes.last() {
+            // There may be more changes after the last one on the page
+            Some(last) if changes.len() >= limit => last.index,
+            _ => match since {
+                FeedPosition::Index(index) => latest.max(index),
+                FeedPosition::Time(_) => latest,
             },
-            None => Ok(()),
+        };
+        FeedPage {
+            changes,
+            next_since,
+            complete,
+        }
+    }
+
+    /**
+     * Permission changes in the current tenant applied on this node from now on, Global Admins get
+     * all changes, other users only get the changes to their own permissions
+     */
+    pub async fn rbac_events(&self, credential: &Credential) -> BoxStream<'static, RbacEvent> {
+        let user = match self
+            .check_permission(credential, Some("global"), Permission::Admin)
+            .await
+        {
+            Ok(_) => None,
+            Err(_) => Some(credential.to_string()),
+        };
+        self.store.rbac_events.stream(current_tenant(), user)
+    }
+
+    /**
+     * Run `f` with the registry and the tenant of the current request, lookups take the tenant
+     * explicitly so concurrent requests only share the read lock
+     */
+    async fn with_registry<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Registry<EntityProperty>, &str) -> R,
+    {
+        let tenant = current_tenant();
+        let state_machine = self.store.state_machine.read().await;
+        f(&state_machine.registry, &tenant)
+    }
+
+    /**
+     * Management token caches are local to each node, so the revocation is sent to every other
+     * node. Failures are logged, the revocation can be sent to the failed nodes again.
+     */
+    pub async fn propagate_management_revocation(&self, revoked: &RevokedManagementToken) {
+        let req = RevokeManagementTokenRequest {
+            token: None,
+            id: Some(revoked.id),
+            expires: Some(revoked.expires),
+        };
+        for (id, client) in self.other_nodes() {
+            if let Err(e) = client.revoke_management_token(&req).await {
+                warn!(
+                    "Failed to revoke management token on node {}, error: {}",
+                    id, e
+                );
+            }
         }
     }
 
+    /**
+     * Clients of all other nodes in the cluster, authenticated with the bootstrap credential
+     */
+    fn other_nodes(&self) -> Vec<(RegistryNodeId, RegistryClient)> {
+        self.raft
+            .metrics()
+            .borrow()
+            .membership_config
+            .get_nodes()
+            .iter()
+            .filter(|(&id, _)| id != self.id)
+            .map(|(&id, node)| {
+                let client =
+                    RegistryClient::new(id, node.addr.to_owned(), self.store.get_management_code());
+                (id, client)
+            })
+            .collect()
+    }
+
     pub async fn init(&self) -> Result<(), InitializeError<RegistryNodeId>> {
         let mut nodes = BTreeMap::new();
         nodes.insert(
@@ -129,7 +389,11 @@ impl RaftRegistryApp {
         self.raft.initialize(nodes).await
     }
 
-    pub async fn load_data(&self) -> anyhow::Result<()> {
+    /**
+     * Merge the database content into the registry, entities conflicting with the existing ones are
+     * handled according to `policy`
+     */
+    pub async fn load_data(&self, policy: LoadPolicy) -> anyhow::Result<LoadReport> {
         let (entities, edges, permission_map) = load_content().await?;
         match self
             .request(
@@ -138,16 +402,57 @@ impl RaftRegistryApp {
                     entities,
                     edges,
                     permissions: permission_map,
+                    policy,
                 },
             )
             .await
         {
             FeathrApiResponse::Error(e) => Err(e)?,
-            _ => Ok(()),
+            FeathrApiResponse::LoadReport(report) => Ok(report),
+            _ => Ok(LoadReport::new(policy)),
         }
     }
 
+    /**
+     * Overwrite the database with the registry content on this node, use when the database has
+     * drifted from the Raft state, e.g. no node was running with `--write-db`
+     */
+    pub async fn dump_data(&self) -> anyhow::Result<()> {
+        let (entities, edges, permissions) = self
+            .store
+            .state_machine
+            .read()
+            .await
+            .registry
+            .export_content()?;
+        dump_content(&entities, &edges, &permissions).await
+    }
+
+    /**
+     * Validate the graph invariants of the registry on this node, and fix the repairable issues
+     * if `repair` is true.
+     * Repairs are not replicated, every node should run it against the same content.
+     */
+    pub async fn fsck(&self, repair: bool) -> FsckReport {
+        let mut state_machine = self.store.state_machine.write().await;
+        let issues = state_machine.registry.fsck();
+        let repaired = if repair {
+            state_machine.registry.repair(&issues)
+        } else {
+            0
+        };
+        FsckReport { issues, repaired }
+    }
+
     pub async fn request(&self, opt_seq: Option<u64>, req: FeathrApiRequest) -> FeathrApiResponse {
+        let req = req.in_tenant(&current_tenant());
+        // Validate on the receiving node, forwarded requests bypass this function
+        if req.is_writing_request() {
+            if let Err(e) = self.validation.validate(&req).await {
+                debug!("Request rejected, {}", e);
+                return FeathrApiResponse::Error(e);
+            }
+        }
         let mut is_leader = true;
         let should_forward = match self.raft.is_leader().await {
             Ok(_) => {
@@ -159,6 +464,8 @@ impl RaftRegistryApp {
                 debug!("Should forward the request to node {}", node_id);
                 is_leader = false;
                 match opt_seq {
+                    // Read replicas proxy all writes to the leader
+                    _ if self.replica.is_enabled() && req.is_writing_request() => true,
                     Some(seq) => match self.store.state_machine.read().await.last_applied_log {
                         Some(l) => {
                             // Check is local log index is newer than required seq, forward if local is out dated
@@ -171,20 +478,40 @@ impl RaftRegistryApp {
                             true
                         }
                     },
+                    // Read replicas serve possibly stale reads locally
+                    None if self.replica.can_serve(&self.store).await => {
+                        trace!("Serving stale read on the replica");
+                        false
+                    }
                     // opt_seq is not set, forward to the leader for consistent read
                     None => true,
                 }
             }
+            Err(e) if self.degraded_reads && !req.is_writing_request() => {
+                warn!(
+                    "Check leader failed, serving the read without the quorum, error: {:?}",
+                    e
+                );
+                mark_degraded();
+                false
+            }
             Err(e) => {
                 trace!("Check leader failed, error is {:?}", e);
-                false
-                // return FeathrApiResponse::Error(ApiError::InternalError("Raft cluster error".to_string()));
+                return FeathrApiResponse::Error(ApiError::InternalError(format!("{:?}", e)));
             }
         };
         if should_forward {
             debug!("The request is being forwarded to the leader");
             match self.forwarder.consistent_request(&req).await {
                 Ok(v) => v,
+                Err(e) if self.degraded_reads && !req.is_writing_request() => {
+                    warn!(
+                        "Failed to forward the read to the leader, serving it locally, error: {:?}",
+                        e
+                    );
+                    mark_degraded();
+                    self.local_read(opt_seq, req).await
+                }
                 Err(e) => FeathrApiResponse::Error(ApiError::InternalError(format!("{:?}", e))),
             }
         } else {
@@ -206,15 +533,137 @@ impl RaftRegistryApp {
                     ))
                 }
             } else {
-                self.store
-                    .state_machine
-                    .write()
+                self.local_read(opt_seq, req).await
+            }
+        }
+    }
+
+    async fn local_read(&self, opt_seq: Option<u64>, req: FeathrApiRequest) -> FeathrApiResponse {
+        let mut state_machine = self.store.state_machine.write().await;
+        // FTS index is committed in the background, read-your-writes needs pending docs committed
+        if opt_seq.is_some() && req.is_search_request() {
+            if let Err(e) = state_machine.registry.commit_search_index().await {
+                return FeathrApiResponse::Error(e.into());
+            }
+        }
+        state_machine.registry.request(req).await
+    }
+
+    /**
+     * Create the sources, anchors and anchor features of the template in the project, one request
+     * per entity so all of them go through the validation chain.
+     * Entities already in the project are skipped, so an interrupted seeding can be resumed.
+     */
+    pub async fn seed_project(
+        &self,
+        project_id: Uuid,
+        template: &ProjectTemplate,
+        creator: &str,
+    ) -> poem::Result<()> {
+        let project = project_id.to_string();
+        let mut source_ids: HashMap<&str, String> = HashMap::new();
+        for source in &template.sources {
+            let existing = self
+                .find_entity(FeathrApiRequest::GetProjectDataSource {
+                    project_id_or_name: project.clone(),
+                    id_or_name: source.name.clone(),
+                })
+                .await;
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let mut definition = source.clone();
+                    definition.id = Uuid::new_v4().to_string();
+                    definition.created_by = creator.to_string();
+                    self.request(
+                        None,
+                        FeathrApiRequest::CreateProjectDataSource {
+                            project_id_or_name: project.clone(),
+                            definition,
+                        },
+                    )
                     .await
-                    .registry
-                    .request(req)
+                    .into_uuid_and_version()?
+                    .0
+                    .to_string()
+                }
+            };
+            source_ids.insert(source.name.as_str(), id);
+        }
+        for anchor in &template.anchors {
+            let source_id = source_ids
+                .get(anchor.source.as_str())
+                .cloned()
+                .ok_or_else(|| ApiError::NotFoundError(anchor.source.to_owned()))?;
+            let existing = self
+                .find_entity(FeathrApiRequest::GetProjectAnchor {
+                    project_id_or_name: project.clone(),
+                    id_or_name: anchor.name.clone(),
+                })
+                .await;
+            let anchor_id = match existing {
+                Some(id) => id,
+                None => self
+                    .request(
+                        None,
+                        FeathrApiRequest::CreateProjectAnchor {
+                            project_id_or_name: project.clone(),
+                            definition: AnchorDef {
+                                id: Uuid::new_v4().to_string(),
+                                name: anchor.name.to_owned(),
+                                qualified_name: Default::default(),
+                                source_id,
+                                source_ids: vec![],
+                                join_keys: vec![],
+                                preprocessing: None,
+                                default_window: None,
+                                tags: anchor.tags.to_owned(),
+                                created_by: creator.to_string(),
+                            },
+                        },
+                    )
                     .await
+                    .into_uuid_and_version()?
+                    .0
+                    .to_string(),
+            };
+            for feature in &anchor.features {
+                let existing = self
+                    .find_entity(FeathrApiRequest::GetAnchorFeature {
+                        project_id_or_name: project.clone(),
+                        anchor_id_or_name: anchor_id.clone(),
+                        id_or_name: feature.name.clone(),
+                    })
+                    .await;
+                if existing.is_some() {
+                    continue;
+                }
+                let mut definition = feature.clone();
+                definition.id = Uuid::new_v4().to_string();
+                definition.created_by = creator.to_string();
+                self.request(
+                    None,
+                    FeathrApiRequest::CreateAnchorFeature {
+                        project_id_or_name: project.clone(),
+                        anchor_id_or_name: anchor_id.clone(),
+                        definition,
+                    },
+                )
+                .await
+                .into_uuid_and_version()?;
             }
         }
+        Ok(())
+    }
+
+    /**
+     * Id of the entity returned by the request, `None` if it doesn't exist
+     */
+    async fn find_entity(&self, req: FeathrApiRequest) -> Option<String> {
+        match self.request(None, req).await {
+            FeathrApiResponse::Entity(e) => Some(e.guid),
+            _ => None,
+        }
     }
 
     pub async fn join_cluster(&self, seeds: &[String], promote: bool) -> anyhow::Result<()> {
@@ -249,10 +698,15 @@ impl RaftRegistryApp {
                             }
                         };
                         debug!("Adding this node into the cluster as learner");
-                        if let Ok(resp) = client.add_learner((self.id, self.addr.clone())).await {
+                        let resp = if self.replica.is_enabled() {
+                            client.add_replica((self.id, self.addr.clone())).await
+                        } else {
+                            client.add_learner((self.id, self.addr.clone())).await
+                        };
+                        if let Ok(resp) = resp {
                             trace!("Got response {:?}", resp);
                             debug!("This node has joined the cluster as learner");
-                            if promote {
+                            if promote && !self.replica.is_enabled() {
                                 debug!("Promoting this node into voter");
                                 // Fetch metrics from the leader node
                                 if let Ok(metrics) = client.metrics().await {
@@ -285,6 +739,36 @@ impl RaftRegistryApp {
         Err(anyhow::Error::msg("Failed to join the cluster"))
     }
 
+    /**
+     * Remove this node from the cluster before it exits, so it doesn't stay in the membership as a
+     * dead learner.
+     * Membership changes only remove voters, so a learner is promoted first, which doesn't risk the
+     * quorum as it has caught up with the leader and is removed right after
+     */
+    pub async fn leave_cluster(&self) -> anyhow::Result<()> {
+        let metrics = self.raft.metrics().borrow().clone();
+        let leader = metrics.current_leader.and_then(|id| {
+            metrics
+                .membership_config
+                .get_node(&id)
+                .map(|node| (id, node.addr.to_owned()))
+        });
+        let (leader_id, addr) = leader
+            .ok_or_else(|| anyhow::Error::msg("Cannot leave the cluster without a leader"))?;
+        let client = RegistryClient::new(leader_id, addr, self.store.get_management_code());
+        let mut voters: BTreeSet<RegistryNodeId> =
+            metrics.membership_config.membership.voter_ids().collect();
+        if !voters.contains(&self.id) {
+            debug!("Promoting this node into voter before leaving");
+            voters.insert(self.id);
+            client.change_membership(&voters).await?;
+            voters.remove(&self.id);
+        }
+        client.change_membership(&voters).await?;
+        info!("Node {} has left the cluster", self.id);
+        Ok(())
+    }
+
     pub async fn join_or_init(&self, seeds: &[String], init: bool) -> anyhow::Result<()> {
         match self.join_cluster(seeds, true).await {
             Err(_) if init => {
@@ -296,6 +780,84 @@ impl RaftRegistryApp {
     }
 }
 
+/**
+ * Download the latest snapshot from the leader found via the seeds and save it as the snapshot of
+ * the node, so joining the cluster only replicates the logs after the snapshot instead of the whole
+ * log. Must be called before the node is created, returns the log index of the snapshot.
+ */
+pub async fn bootstrap_from_leader(
+    node_id: RegistryNodeId,
+    seeds: &[String],
+    config: &NodeConfig,
+) -> anyhow::Result<u64> {
+    for seed in expand_seeds(seeds).await? {
+        debug!("Collecting cluster info from {}", seed);
+        let client = RegistryClient::new(1, seed.to_owned(), config.management_code.clone());
+        let metrics = match client.metrics().await {
+            Ok(metrics) => metrics,
+            Err(e) => {
+                debug!("Failed to get metrics from {}, error: {:?}", seed, e);
+                continue;
+            }
+        };
+        let leader = metrics.current_leader.and_then(|id| {
+            metrics
+                .membership_config
+                .get_node(&id)
+                .map(|node| (id, node.addr.to_owned()))
+        });
+        let (leader_id, addr) = match leader {
+            Some(leader) => leader,
+            None => continue,
+        };
+        let client = RegistryClient::new(leader_id, addr.clone(), config.management_code.clone());
+        let start = std::time::Instant::now();
+        match client.snapshot(true).await {
+            Ok((snapshot_id, data)) => {
+                let index = save_bootstrap_snapshot(config, node_id, &snapshot_id, &data).await?;
+                info!(
+                    "Downloaded snapshot {} ({} bytes) from leader node {} at '{}' in {:?}",
+                    snapshot_id,
+                    data.len(),
+                    leader_id,
+                    addr,
+                    start.elapsed()
+                );
+                return Ok(index);
+            }
+            Err(e) => warn!(
+                "Failed to download the snapshot from leader node {}, error: {}",
+                leader_id, e
+            ),
+        }
+    }
+    anyhow::bail!("Failed to download the snapshot from the leader")
+}
+
+/**
+ * Ids of the voters and learners of the cluster found via the seeds
+ */
+pub async fn cluster_node_ids(
+    seeds: &[String],
+    config: &NodeConfig,
+) -> anyhow::Result<BTreeSet<RegistryNodeId>> {
+    for seed in expand_seeds(seeds).await? {
+        let client = RegistryClient::new(1, seed.to_owned(), config.management_code.clone());
+        match client.metrics().await {
+            Ok(metrics) => {
+                return Ok(metrics
+                    .membership_config
+                    .get_nodes()
+                    .keys()
+                    .copied()
+                    .collect())
+            }
+            Err(e) => debug!("Failed to get metrics from {}, error: {:?}", seed, e),
+        }
+    }
+    anyhow::bail!("Failed to collect the cluster info from the seeds")
+}
+
 /**
  * Discover seeds via DNS, it should work with K8S internal DNS service
  * TODO: Support more discover method, e.g. K8S API, broadcasting.
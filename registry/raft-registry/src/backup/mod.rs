@@ -0,0 +1,364 @@
+mod storage;
+
+use std::io::Read;
+
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use clap::Parser;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::{debug, info, warn};
+use registry_api::{FeathrApiRequest, FeathrApiResponse};
+use registry_provider::{Edge, Entity, EntityProperty, LoadPolicy, RbacRecord};
+use serde::{Deserialize, Serialize};
+
+use crate::RaftRegistryApp;
+
+pub use storage::BackupStorage;
+
+const BACKUP_FORMAT_VERSION: u32 = 1;
+const BACKUP_NAME_PREFIX: &str = "registry-backup-";
+const BACKUP_NAME_SUFFIX: &str = ".json.gz";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Clone, Debug, Serialize, Deserialize, Parser)]
+pub struct BackupConfig {
+    /// Object storage location of the scheduled backups, e.g. `abfss://container@account.dfs.core.windows.net/registry` or `s3://bucket/registry`
+    #[clap(long, env = "BACKUP_URL")]
+    pub backup_url: Option<String>,
+
+    /// Interval between backups in seconds, a backup is skipped if nothing changed since the last one
+    #[clap(long, env = "BACKUP_INTERVAL", default_value = "3600")]
+    pub backup_interval: u64,
+
+    /// Number of backups to keep, 0 to keep all
+    #[clap(long, env = "BACKUP_RETENTION", default_value = "24")]
+    pub backup_retention: usize,
+
+    /// Delete backups older than this many days, the latest backup is always kept
+    #[clap(long, env = "BACKUP_MAX_AGE_DAYS")]
+    pub backup_max_age_days: Option<i64>,
+
+    /// Bootstrap a new cluster from the backup file, or the latest backup under the location
+    #[clap(long, env = "RESTORE_FROM")]
+    pub restore_from: Option<String>,
+}
+
+/**
+ * Registry content at the time of the backup, restored with a `BatchLoad` request so the new
+ * cluster starts with fresh Raft logs
+ */
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistryBackup {
+    version: u32,
+    created_at: DateTime<Utc>,
+    node_id: u64,
+    last_applied_index: Option<u64>,
+    entities: Vec<Entity<EntityProperty>>,
+    edges: Vec<Edge>,
+    permissions: Vec<RbacRecord>,
+}
+
+impl RegistryBackup {
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let mut buf = vec![];
+        GzDecoder::new(data).read_to_end(&mut buf)?;
+        let backup: Self = serde_json::from_slice(&buf)?;
+        if backup.version > BACKUP_FORMAT_VERSION {
+            anyhow::bail!(
+                "Backup format version {} is not supported, upgrade the registry",
+                backup.version
+            );
+        }
+        Ok(backup)
+    }
+}
+
+fn backup_name(time: DateTime<Utc>) -> String {
+    format!(
+        "{}{}{}",
+        BACKUP_NAME_PREFIX,
+        time.format(BACKUP_TIME_FORMAT),
+        BACKUP_NAME_SUFFIX
+    )
+}
+
+fn parse_backup_name(name: &str) -> Option<DateTime<Utc>> {
+    let time = name
+        .strip_prefix(BACKUP_NAME_PREFIX)?
+        .strip_suffix(BACKUP_NAME_SUFFIX)?;
+    NaiveDateTime::parse_from_str(time, BACKUP_TIME_FORMAT)
+        .ok()
+        .map(|t| Utc.from_utc_datetime(&t))
+}
+
+/**
+ * Backups to be deleted by the retention policy, newest backups are kept
+ */
+fn expired_backups(
+    names: &[String],
+    now: DateTime<Utc>,
+    retention: usize,
+    max_age: Option<Duration>,
+) -> Vec<String> {
+    let mut backups: Vec<(DateTime<Utc>, &String)> = names
+        .iter()
+        .filter_map(|n| parse_backup_name(n).map(|t| (t, n)))
+        .collect();
+    backups.sort_by(|a, b| b.cmp(a));
+    backups
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, (time, _))| {
+            *idx > 0
+                && ((retention > 0 && *idx >= retention)
+                    || max_age.map(|age| now - *time > age).unwrap_or_default())
+        })
+        .map(|(_, (_, name))| name.to_owned())
+        .collect()
+}
+
+/**
+ * Upload a backup if the state machine has changed since `last_index`, returns the backed up index
+ */
+async fn backup_once(
+    app: &RaftRegistryApp,
+    storage: &BackupStorage,
+    config: &BackupConfig,
+    last_index: Option<u64>,
+) -> anyhow::Result<Option<u64>> {
+    let backup = {
+        let state_machine = app.store.state_machine.read().await;
+        let index = state_machine.last_applied_log.map(|l| l.index);
+        if index.is_some() && index == last_index {
+            debug!("Nothing changed since the last backup, skipping");
+            return Ok(last_index);
+        }
+        let (entities, edges, permissions) = state_machine.registry.export_content()?;
+        RegistryBackup {
+            version: BACKUP_FORMAT_VERSION,
+            created_at: Utc::now(),
+            node_id: app.id,
+            last_applied_index: index,
+            entities,
+            edges,
+            permissions,
+        }
+    };
+    let name = backup_name(backup.created_at);
+    storage.put(&name, backup.encode()?).await?;
+    info!(
+        "Registry backup `{}` uploaded, {} entities",
+        name,
+        backup.entities.len()
+    );
+
+    let expired = expired_backups(
+        &storage.list().await?,
+        backup.created_at,
+        config.backup_retention,
+        config.backup_max_age_days.map(Duration::days),
+    );
+    for name in expired {
+        if let Err(e) = storage.delete(&name).await {
+            warn!("Failed to delete expired backup `{}`, error: {}", name, e);
+        }
+    }
+    Ok(backup.last_applied_index)
+}
+
+/**
+ * Back up the registry content periodically if `backup_url` is set, only the leader does the
+ * backup so there is one backup per interval in the cluster
+ */
+pub async fn run_backup_task(app: RaftRegistryApp, config: BackupConfig) {
+    let storage = match config.backup_url.as_ref().map(|u| BackupStorage::parse(u)) {
+        Some(Ok(s)) => s,
+        Some(Err(e)) => {
+            warn!("Scheduled backup is disabled, error: {}", e);
+            return;
+        }
+        None => return,
+    };
+    info!(
+        "Backing up registry to `{}` every {} seconds",
+        config.backup_url.as_deref().unwrap_or_default(),
+        config.backup_interval
+    );
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        config.backup_interval.max(1),
+    ));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // The first tick completes immediately
+    interval.tick().await;
+    let mut last_index = None;
+    loop {
+        interval.tick().await;
+        if app.raft.is_leader().await.is_err() {
+            // Another backup may be uploaded while this node is follower
+            last_index = None;
+            continue;
+        }
+        match backup_once(&app, &storage, &config, last_index).await {
+            Ok(index) => last_index = index,
+            Err(e) => warn!("Failed to back up registry, error: {}", e),
+        }
+    }
+}
+
+/**
+ * Read the content of a backup, `url` is either a backup file or a backup location, the latest
+ * backup under the location is used in the latter case
+ */
+pub async fn load_backup(
+    url: &str,
+) -> anyhow::Result<(Vec<Entity<EntityProperty>>, Vec<Edge>, Vec<RbacRecord>)> {
+    let backup = read_backup(url).await?;
+    Ok((backup.entities, backup.edges, backup.permissions))
+}
+
+async fn read_backup(url: &str) -> anyhow::Result<RegistryBackup> {
+    let (location, name) = match split_backup_url(url) {
+        Some((location, name)) => (location, Some(name)),
+        None => (url.to_string(), None),
+    };
+    let storage = BackupStorage::parse(&location)?;
+    let name = match name {
+        Some(name) => name,
+        None => storage
+            .list()
+            .await?
+            .into_iter()
+            .filter(|n| parse_backup_name(n).is_some())
+            .max()
+            .ok_or_else(|| anyhow::Error::msg(format!("No backup found in `{}`", location)))?,
+    };
+    info!("Reading registry backup `{}`", name);
+    RegistryBackup::decode(&storage.get(&name).await?)
+}
+
+/**
+ * Load the backup into a new cluster, `url` is either a backup file or a backup location, the
+ * latest backup under the location is used in the latter case
+ */
+pub async fn restore_backup(app: &RaftRegistryApp, url: &str) -> anyhow::Result<()> {
+    let backup = read_backup(url).await?;
+
+    if !app
+        .store
+        .state_machine
+        .read()
+        .await
+        .registry
+        .export_content()?
+        .0
+        .is_empty()
+    {
+        anyhow::bail!("Backup can only be restored into an empty registry");
+    }
+    info!(
+        "Loading {} entities from backup created at {} by node {}",
+        backup.entities.len(),
+        backup.created_at,
+        backup.node_id
+    );
+    match app
+        .request(
+            None,
+            FeathrApiRequest::BatchLoad {
+                entities: backup.entities,
+                edges: backup.edges,
+                permissions: backup.permissions,
+                policy: LoadPolicy::Skip,
+            },
+        )
+        .await
+    {
+        FeathrApiResponse::Error(e) => Err(e)?,
+        _ => Ok(()),
+    }
+}
+
+/**
+ * Split backup file URL into the location URL and the file name, SAS token stays in the location
+ */
+fn split_backup_url(url: &str) -> Option<(String, String)> {
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (url, None),
+    };
+    let (location, name) = path.rsplit_once('/')?;
+    parse_backup_name(name)?;
+    Some((
+        match query {
+            Some(q) => format!("{}?{}", location, q),
+            None => location.to_string(),
+        },
+        name.to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expired_backups() {
+        let now = Utc.ymd(2022, 10, 10).and_hms(0, 0, 0);
+        let names: Vec<String> = (0..5)
+            .map(|d| backup_name(now - Duration::days(d)))
+            .chain(["other-file.txt".to_string()])
+            .collect();
+        assert_eq!(
+            expired_backups(&names, now, 3, None),
+            vec![names[3].clone(), names[4].clone()]
+        );
+        assert_eq!(
+            expired_backups(&names, now, 0, Some(Duration::hours(36))),
+            vec![names[2].clone(), names[3].clone(), names[4].clone()]
+        );
+        // The latest backup is always kept
+        assert!(expired_backups(&names[..1], now, 0, Some(Duration::hours(1))).is_empty());
+        assert!(expired_backups(&names, now, 0, None).is_empty());
+    }
+
+    #[test]
+    fn test_split_backup_url() {
+        assert_eq!(
+            split_backup_url("s3://bucket/registry/registry-backup-20221010T000000Z.json.gz"),
+            Some((
+                "s3://bucket/registry".to_string(),
+                "registry-backup-20221010T000000Z.json.gz".to_string()
+            ))
+        );
+        assert_eq!(
+            split_backup_url(
+                "https://a.blob.core.windows.net/c/registry-backup-20221010T000000Z.json.gz?sv=1&sig=2"
+            ),
+            Some((
+                "https://a.blob.core.windows.net/c?sv=1&sig=2".to_string(),
+                "registry-backup-20221010T000000Z.json.gz".to_string()
+            ))
+        );
+        assert_eq!(split_backup_url("s3://bucket/registry"), None);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let backup = RegistryBackup {
+            version: BACKUP_FORMAT_VERSION,
+            created_at: Utc::now(),
+            node_id: 1,
+            last_applied_index: Some(42),
+            entities: vec![],
+            edges: vec![],
+            permissions: vec![],
+        };
+        let decoded = RegistryBackup::decode(&backup.encode().unwrap()).unwrap();
+        assert_eq!(decoded.last_applied_index, Some(42));
+    }
+}
@@ -1,9 +1,12 @@
 use std::collections::BTreeSet;
+use std::io::Read;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+use auth::RevokedToken;
 use common_utils::Appliable;
 use common_utils::Logged;
+use flate2::read::GzDecoder;
 use log::debug;
 use openraft::error::AddLearnerError;
 use openraft::error::CheckIsLeaderError;
@@ -18,6 +21,10 @@ use openraft::raft::AddLearnerResponse;
 use openraft::raft::ClientWriteResponse;
 use openraft::RaftMetrics;
 use registry_api::FeathrApiResponse;
+use registry_provider::Edge;
+use registry_provider::Entity;
+use registry_provider::EntityProperty;
+use registry_provider::RbacRecord;
 use reqwest::Client;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
@@ -26,7 +33,11 @@ use serde::Serialize;
 use crate::FeathrApiRequest;
 use crate::RegistryNodeId;
 use crate::RegistryTypeConfig;
+use crate::RevokeManagementTokenRequest;
+use crate::RevokeTokenRequest;
+use crate::RevokedManagementToken;
 use crate::MANAGEMENT_CODE_HEADER_NAME;
+use crate::SNAPSHOT_ID_HEADER_NAME;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Empty {}
@@ -105,6 +116,18 @@ impl RegistryClient {
         self.send_rpc_to_leader("add-learner", Some(&req)).await
     }
 
+    /// Add a node as read replica, which is a learner never promoted into voter.
+    pub async fn add_replica(
+        &self,
+        req: (RegistryNodeId, String),
+    ) -> Result<
+        AddLearnerResponse<RegistryNodeId>,
+        RPCError<RegistryNodeId, AddLearnerError<RegistryNodeId>>,
+    > {
+        self.send_rpc_to_leader("add-learner?replica=true", Some(&req))
+            .await
+    }
+
     /// Change membership to the specified set of nodes.
     ///
     /// All nodes in `req` have to be already added as learner with [`add_learner`],
@@ -131,6 +154,65 @@ impl RegistryClient {
         self.do_send_rpc_to_leader("metrics", None::<&()>).await
     }
 
+    /// Revoke a token on the node this client points to, the revocation is not propagated.
+    pub async fn revoke_token(
+        &self,
+        req: &RevokeTokenRequest,
+    ) -> Result<RevokedToken, RPCError<RegistryNodeId, Infallible>> {
+        self.do_send_rpc_to_leader("revoke-token?local=true", Some(req))
+            .await
+    }
+
+    /// Revoke a management token on the node this client points to, the revocation is not propagated.
+    pub async fn revoke_management_token(
+        &self,
+        req: &RevokeManagementTokenRequest,
+    ) -> Result<RevokedManagementToken, RPCError<RegistryNodeId, Infallible>> {
+        self.do_send_rpc_to_leader("management-tokens/revoke?local=true", Some(req))
+            .await
+    }
+
+    /// Export all entities, edges and permissions on the node this client points to.
+    pub async fn export(
+        &self,
+    ) -> Result<
+        (Vec<Entity<EntityProperty>>, Vec<Edge>, Vec<RbacRecord>),
+        RPCError<RegistryNodeId, Infallible>,
+    > {
+        self.do_send_rpc_to_leader("export", None::<&()>).await
+    }
+
+    /// Download the latest snapshot from the leader, returns the snapshot id and the data.
+    ///
+    /// The leader builds a new snapshot if `fresh` is true.
+    pub async fn snapshot(&self, fresh: bool) -> anyhow::Result<(String, Vec<u8>)> {
+        let url = {
+            let t = self.leader.lock().unwrap();
+            format!("http://{}/snapshot?fresh={}", t.1, fresh)
+        };
+        debug!(">>> client send request to {}", url);
+        let resp = self
+            .inner
+            .get(url)
+            .apply(|r| match &self.code {
+                Some(c) => r.header(MANAGEMENT_CODE_HEADER_NAME, c),
+                None => r,
+            })
+            .send()
+            .await?
+            .error_for_status()?;
+        let snapshot_id = resp
+            .headers()
+            .get(SNAPSHOT_ID_HEADER_NAME)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::Error::msg("Snapshot id is missing in the response"))?;
+        let body = resp.bytes().await?;
+        let mut data = vec![];
+        GzDecoder::new(body.as_ref()).read_to_end(&mut data)?;
+        Ok((snapshot_id, data))
+    }
+
     // --- Internal methods
 
     /// Send RPC to specified node.
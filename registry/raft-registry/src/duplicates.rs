@@ -0,0 +1,64 @@
+use log::{debug, warn};
+use registry_api::FeathrApiRequest;
+
+use crate::RaftRegistryApp;
+
+/**
+ * Periodically records `SimilarTo` edges between the near-duplicate features of every project,
+ * only runs on the leader
+ */
+pub async fn run_duplicates_task(app: RaftRegistryApp, interval_secs: u64) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs.max(1)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        if app.raft.is_leader().await.is_err() {
+            continue;
+        }
+        let projects = app
+            .store
+            .state_machine
+            .read()
+            .await
+            .registry
+            .get_tenant_projects();
+        for (tenant, project_id) in projects {
+            let report = app
+                .request(
+                    None,
+                    FeathrApiRequest::GetProjectDuplicates {
+                        id_or_name: project_id.to_string(),
+                    }
+                    .in_tenant(&tenant),
+                )
+                .await
+                .into_duplicate_report();
+            match report {
+                Ok(report) if report.groups.iter().any(|g| !g.linked) => {
+                    debug!(
+                        "Linking near-duplicate features in project {}",
+                        report.project
+                    );
+                    let resp = app
+                        .request(
+                            None,
+                            FeathrApiRequest::LinkSimilarFeatures {
+                                project_id_or_name: project_id.to_string(),
+                            }
+                            .in_tenant(&tenant),
+                        )
+                        .await
+                        .into_unit();
+                    if let Err(e) = resp {
+                        warn!(
+                            "Failed to link features in project {}, error: {}",
+                            project_id, e
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to check project {}, error: {}", project_id, e),
+            }
+        }
+    }
+}
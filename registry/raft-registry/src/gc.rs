@@ -0,0 +1,112 @@
+use chrono::{Duration, Utc};
+use clap::Parser;
+use log::{info, warn};
+use registry_api::{FeathrApiRequest, FeathrApiResponse};
+use registry_provider::GarbageCollectionReport;
+use serde::{Deserialize, Serialize};
+
+use crate::RaftRegistryApp;
+
+pub const DEFAULT_GC_RETENTION_DAYS: i64 = 30;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Parser)]
+pub struct GcConfig {
+    /// Seconds between scheduled garbage collections of deleted entities, 0 disables the schedule
+    #[clap(long, env = "GC_INTERVAL", default_value = "0")]
+    pub gc_interval: u64,
+
+    /// Days to keep the tombstones of deleted entities before the garbage collection removes them
+    #[clap(long, env = "GC_RETENTION_DAYS", default_value = "30")]
+    pub gc_retention_days: i64,
+}
+
+/**
+ * Outcome of a garbage collection with the space reclaimed on the node ran it
+ */
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GarbageCollectionSummary {
+    #[serde(flatten)]
+    pub report: GarbageCollectionReport,
+    /// Size of the state machine snapshot before the collection, 0 in dry runs
+    pub snapshot_bytes_before: usize,
+    /// Size of the snapshot rebuilt after the collection, 0 in dry runs
+    pub snapshot_bytes_after: usize,
+}
+
+/**
+ * Remove tombstones of entities deleted more than `retention_days` ago through the Raft log, so
+ * every node removes the same entities. The snapshot of this node is rebuilt without them, other
+ * nodes drop them at their next snapshot
+ */
+pub(crate) async fn gc_once(
+    app: &RaftRegistryApp,
+    retention_days: i64,
+    dry_run: bool,
+) -> anyhow::Result<GarbageCollectionSummary> {
+    if retention_days < 0 {
+        anyhow::bail!(
+            "Retention must not be negative, got {} days",
+            retention_days
+        );
+    }
+    let before = Utc::now() - Duration::days(retention_days);
+    let snapshot_bytes_before = if dry_run {
+        0
+    } else {
+        serde_json::to_vec(&*app.store.state_machine.read().await)?.len()
+    };
+    let report = match app
+        .request(None, FeathrApiRequest::CollectGarbage { before, dry_run })
+        .await
+    {
+        FeathrApiResponse::Error(e) => Err(e)?,
+        FeathrApiResponse::GarbageCollectionReport(report) => report,
+        _ => anyhow::bail!("Unexpected response of the garbage collection"),
+    };
+    let snapshot_bytes_after = if dry_run || report.entities.is_empty() {
+        snapshot_bytes_before
+    } else {
+        app.store.transfer_snapshot(true).await?.1.len()
+    };
+    let summary = GarbageCollectionSummary {
+        report,
+        snapshot_bytes_before,
+        snapshot_bytes_after,
+    };
+    info!(
+        "Garbage collection{} removed {} entities and {} edges deleted before {}, {} tombstones retained, snapshot {} -> {} bytes",
+        if dry_run { " (dry run)" } else { "" },
+        summary.report.entities.len(),
+        summary.report.edges,
+        before,
+        summary.report.retained,
+        summary.snapshot_bytes_before,
+        summary.snapshot_bytes_after,
+    );
+    Ok(summary)
+}
+
+/**
+ * Collect garbage periodically if `gc_interval` is set, only runs on the leader
+ */
+pub async fn run_gc_task(app: RaftRegistryApp, config: GcConfig) {
+    info!(
+        "Collecting tombstones older than {} days every {} seconds",
+        config.gc_retention_days, config.gc_interval
+    );
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(config.gc_interval.max(1)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // The first tick completes immediately
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if app.raft.is_leader().await.is_err() {
+            continue;
+        }
+        if let Err(e) = gc_once(&app, config.gc_retention_days, false).await {
+            warn!("Failed to collect garbage, error: {}", e);
+        }
+    }
+}
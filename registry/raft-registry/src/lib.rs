@@ -9,6 +9,12 @@ mod network;
 mod app;
 mod client;
 mod rbac_middleware;
+mod backup;
+mod sampling;
+mod membership;
+mod replica;
+mod duplicates;
+mod gc;
 
 pub type RegistryNodeId = u64;
 
@@ -34,3 +40,15 @@ pub use network::*;
 pub use app::*;
 pub use client::RegistryClient;
 pub use rbac_middleware::RbacMiddleware;
+pub use backup::{load_backup, restore_backup, run_backup_task, BackupConfig, BackupStorage};
+pub use sampling::{HttpJobRunner, JobRunner, SampleConfig, SourceSampler};
+pub use membership::{
+    run_membership_task, MembershipAction, MembershipConfig, MembershipPolicy, MembershipReport,
+    NodeHealth, NodeRole,
+};
+pub use duplicates::run_duplicates_task;
+pub use gc::{run_gc_task, GarbageCollectionSummary, GcConfig, DEFAULT_GC_RETENTION_DAYS};
+pub use replica::{
+    is_replica_node, run_replica_task, ReadReplica, ReplicaConfig, REPLICA_NODE_KEY,
+    STALENESS_HEADER_NAME,
+};
@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, Utc};
+use clap::Parser;
+use log::{debug, info, warn};
+use openraft::RaftMetrics;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{is_replica_node, RaftRegistryApp, RegistryNodeId, RegistryTypeConfig};
+
+#[derive(Clone, Debug, Serialize, Deserialize, Parser)]
+pub struct MembershipConfig {
+    /// Promote a learner to voter once it has stayed healthy for `--promotion-healthy-minutes`
+    #[clap(long, env = "AUTO_PROMOTE")]
+    pub auto_promote: bool,
+
+    /// Demote a voter to learner once it has stayed unhealthy for `--demotion-unhealthy-minutes`
+    #[clap(long, env = "AUTO_DEMOTE")]
+    pub auto_demote: bool,
+
+    /// A node is healthy if it lags behind the leader by at most this many log entries
+    #[clap(long, env = "MEMBERSHIP_MAX_LAG", default_value = "100")]
+    pub membership_max_lag: u64,
+
+    /// Minutes a learner must stay healthy before being promoted
+    #[clap(long, env = "PROMOTION_HEALTHY_MINUTES", default_value = "5")]
+    pub promotion_healthy_minutes: i64,
+
+    /// Minutes a voter must stay unhealthy before being demoted
+    #[clap(long, env = "DEMOTION_UNHEALTHY_MINUTES", default_value = "10")]
+    pub demotion_unhealthy_minutes: i64,
+
+    /// Voters are never demoted below this number
+    #[clap(long, env = "MIN_VOTERS", default_value = "3")]
+    pub min_voters: usize,
+
+    /// Seconds between membership health checks on the leader
+    #[clap(long, env = "MEMBERSHIP_CHECK_INTERVAL", default_value = "10")]
+    pub membership_check_interval: u64,
+}
+
+impl Default for MembershipConfig {
+    fn default() -> Self {
+        Self {
+            auto_promote: false,
+            auto_demote: false,
+            membership_max_lag: 100,
+            promotion_healthy_minutes: 5,
+            demotion_unhealthy_minutes: 10,
+            min_voters: 3,
+            membership_check_interval: 10,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeRole {
+    Leader,
+    Voter,
+    Learner,
+    /// A learner joined with `--read-replica`, never promoted
+    Replica,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MembershipAction {
+    Promote,
+    Demote,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeHealth {
+    pub id: RegistryNodeId,
+    pub addr: String,
+    pub role: NodeRole,
+    /// Log entries behind the leader, `None` if the node has not replicated anything
+    pub lag: Option<u64>,
+    pub healthy: bool,
+    /// Start of the current healthy or unhealthy streak
+    pub since: DateTime<Utc>,
+    /// The action to be taken once the streak is long enough, only set if the policy is enabled
+    pub pending_action: Option<MembershipAction>,
+}
+
+/**
+ * Membership health tracked by the leader, served by the `/membership` management endpoint
+ */
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MembershipReport {
+    pub leader: Option<RegistryNodeId>,
+    pub checked_at: Option<DateTime<Utc>>,
+    pub auto_promote: bool,
+    pub auto_demote: bool,
+    pub nodes: Vec<NodeHealth>,
+}
+
+/**
+ * Promotes learners and demotes voters based on how long they have kept up with the leader.
+ *
+ * Only the leader sees the replication progress, so the health streaks restart whenever the
+ * leadership changes.
+ */
+pub struct MembershipPolicy {
+    config: MembershipConfig,
+    report: RwLock<MembershipReport>,
+}
+
+impl MembershipPolicy {
+    pub fn new(config: MembershipConfig) -> Self {
+        Self {
+            report: RwLock::new(MembershipReport {
+                auto_promote: config.auto_promote,
+                auto_demote: config.auto_demote,
+                ..Default::default()
+            }),
+            config,
+        }
+    }
+
+    pub async fn report(&self) -> MembershipReport {
+        self.report.read().await.clone()
+    }
+
+    /**
+     * Update the health streaks with the leader metrics and return the voter set to change to,
+     * `None` if the membership should stay unchanged
+     */
+    async fn check(
+        &self,
+        metrics: &RaftMetrics<RegistryTypeConfig>,
+        now: DateTime<Utc>,
+    ) -> Option<BTreeSet<RegistryNodeId>> {
+        let voters: BTreeSet<RegistryNodeId> =
+            metrics.membership_config.membership.voter_ids().collect();
+        let nodes: BTreeMap<RegistryNodeId, (String, bool)> = metrics
+            .membership_config
+            .get_nodes()
+            .iter()
+            .map(|(id, node)| (*id, (node.addr.to_owned(), is_replica_node(node))))
+            .collect();
+        let mut report = self.report.write().await;
+        let previous: BTreeMap<RegistryNodeId, NodeHealth> = if report.leader == Some(metrics.id) {
+            report.nodes.drain(..).map(|n| (n.id, n)).collect()
+        } else {
+            Default::default()
+        };
+        let lags = replication_lags(metrics);
+        report.nodes = nodes
+            .into_iter()
+            .map(|(id, (addr, replica))| {
+                let role = if id == metrics.id {
+                    NodeRole::Leader
+                } else if voters.contains(&id) {
+                    NodeRole::Voter
+                } else if replica {
+                    NodeRole::Replica
+                } else {
+                    NodeRole::Learner
+                };
+                let lag = if id == metrics.id {
+                    Some(0)
+                } else {
+                    lags.get(&id).copied().flatten()
+                };
+                let health = NodeHealth {
+                    id,
+                    addr,
+                    role,
+                    lag,
+                    healthy: lag
+                        .map(|l| l <= self.config.membership_max_lag)
+                        .unwrap_or_default(),
+                    since: now,
+                    pending_action: None,
+                };
+                match previous.get(&id) {
+                    // The streak continues
+                    Some(p) if p.healthy == health.healthy => NodeHealth {
+                        since: p.since,
+                        ..health
+                    },
+                    _ => health,
+                }
+            })
+            .collect();
+        report.leader = Some(metrics.id);
+        report.checked_at = Some(now);
+        self.plan(&mut report.nodes, voters, now)
+    }
+
+    /**
+     * Mark pending actions on the nodes, one membership change at a time as Raft doesn't allow
+     * concurrent changes
+     */
+    fn plan(
+        &self,
+        nodes: &mut [NodeHealth],
+        mut voters: BTreeSet<RegistryNodeId>,
+        now: DateTime<Utc>,
+    ) -> Option<BTreeSet<RegistryNodeId>> {
+        let mut voter_count = voters.len();
+        for node in nodes.iter_mut() {
+            node.pending_action = match (node.role, node.healthy) {
+                (NodeRole::Learner, true) if self.config.auto_promote => {
+                    Some(MembershipAction::Promote)
+                }
+                (NodeRole::Voter, false) if self.config.auto_demote => {
+                    if voter_count > self.config.min_voters {
+                        voter_count -= 1;
+                        Some(MembershipAction::Demote)
+                    } else {
+                        warn!(
+                            "Voter {} is unhealthy but cannot be demoted, only {} voters left",
+                            node.id, voter_count
+                        );
+                        None
+                    }
+                }
+                (NodeRole::Voter, false) => {
+                    warn!("Voter {} is unhealthy, lag is {:?}", node.id, node.lag);
+                    None
+                }
+                _ => None,
+            };
+        }
+        let due = nodes.iter().find(|n| match n.pending_action {
+            Some(MembershipAction::Promote) => {
+                now - n.since >= Duration::minutes(self.config.promotion_healthy_minutes)
+            }
+            Some(MembershipAction::Demote) => {
+                now - n.since >= Duration::minutes(self.config.demotion_unhealthy_minutes)
+            }
+            None => false,
+        })?;
+        match due.pending_action {
+            Some(MembershipAction::Promote) => {
+                info!("Promoting learner {} into voter", due.id);
+                voters.insert(due.id);
+            }
+            _ => {
+                info!("Demoting voter {} into learner", due.id);
+                voters.remove(&due.id);
+            }
+        }
+        Some(voters)
+    }
+}
+
+impl Default for MembershipPolicy {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+/**
+ * Number of log entries each follower and learner is behind the leader
+ */
+fn replication_lags(
+    metrics: &RaftMetrics<RegistryTypeConfig>,
+) -> BTreeMap<RegistryNodeId, Option<u64>> {
+    let last_index = metrics.last_log_index.unwrap_or_default();
+    metrics
+        .replication
+        .as_ref()
+        .map(|r| {
+            r.data()
+                .replication
+                .iter()
+                .map(|(id, target)| {
+                    (
+                        *id,
+                        target
+                            .matched()
+                            .map(|log_id| last_index.saturating_sub(log_id.index)),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/**
+ * Check the membership health periodically, the check only runs on the leader
+ */
+pub async fn run_membership_task(app: RaftRegistryApp) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app.membership.config.membership_check_interval.max(1),
+    ));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        if app.raft.is_leader().await.is_err() {
+            // Streaks are stale once this node becomes the leader again
+            app.membership.report.write().await.leader = None;
+            continue;
+        }
+        let metrics = app.raft.metrics().borrow().clone();
+        if let Some(voters) = app.membership.check(&metrics, Utc::now()).await {
+            debug!("Changing voters to {:?}", voters);
+            // Demoted voters stay in the cluster as learners
+            if let Err(e) = app.raft.change_membership(voters, true, true).await {
+                warn!("Failed to change membership, error: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: RegistryNodeId, role: NodeRole, healthy: bool, minutes: i64) -> NodeHealth {
+        NodeHealth {
+            id,
+            addr: format!("node{}:8000", id),
+            role,
+            lag: Some(if healthy { 0 } else { 1000 }),
+            healthy,
+            since: Utc::now() - Duration::minutes(minutes),
+            pending_action: None,
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let policy = MembershipPolicy::new(MembershipConfig {
+            auto_promote: true,
+            auto_demote: true,
+            min_voters: 2,
+            ..Default::default()
+        });
+        let voters: BTreeSet<RegistryNodeId> = [1, 2, 3].into_iter().collect();
+
+        // Learner 4 hasn't been healthy for long enough
+        let mut nodes = vec![
+            node(1, NodeRole::Leader, true, 60),
+            node(2, NodeRole::Voter, true, 60),
+            node(3, NodeRole::Voter, true, 60),
+            node(4, NodeRole::Learner, true, 1),
+        ];
+        assert_eq!(policy.plan(&mut nodes, voters.clone(), Utc::now()), None);
+        assert_eq!(nodes[3].pending_action, Some(MembershipAction::Promote));
+
+        nodes[3].since = Utc::now() - Duration::minutes(6);
+        assert_eq!(
+            policy.plan(&mut nodes, voters.clone(), Utc::now()),
+            Some([1, 2, 3, 4].into_iter().collect())
+        );
+
+        // Only one voter can be demoted without going below `min_voters`
+        let mut nodes = vec![
+            node(1, NodeRole::Leader, true, 60),
+            node(2, NodeRole::Voter, false, 60),
+            node(3, NodeRole::Voter, false, 60),
+        ];
+        assert_eq!(
+            policy.plan(&mut nodes, voters, Utc::now()),
+            Some([1, 3].into_iter().collect())
+        );
+        assert_eq!(nodes[2].pending_action, None);
+    }
+}
@@ -9,12 +9,16 @@ use poem_openapi::{
     OpenApi, Tags,
 };
 use registry_api::{
-    AnchorDef, AnchorFeatureDef, CreationResponse, DerivedFeatureDef, Entity, EntityLineage,
-    FeathrApiRequest, ProjectDef, RbacResponse, SourceDef,
+    AnchorDef, AnchorFeatureDef, CreationResponse, DatasetDef, DerivedFeatureDef, Entity,
+    EntityLineage, FeathrApiRequest, MaterializationRecordDef, ProjectDef, RbacResponse, SourceDef,
 };
 use registry_provider::{Credential, Permission};
 use uuid::Uuid;
 
+use super::{
+    api_v2::grant_project_admin,
+    authorization::{authorization_rules, Authorization, Rule},
+};
 use crate::RaftRegistryApp;
 
 #[derive(Tags)]
@@ -25,10 +29,40 @@ enum ApiTags {
     AnchorFeature,
     DerivedFeature,
     Feature,
+    Materialization,
+    Dataset,
     Rbac,
 }
 pub struct FeathrApiV1;
 
+impl FeathrApiV1 {
+    /// Permission required by each route, checked by the `Authorization` middleware
+    pub const AUTHORIZATION_RULES: &'static [Rule] = authorization_rules! {
+        GET "/projects" => Any, Read;
+        POST "/projects" => Global, Write;
+        GET "/projects/{project}" => Path("project"), Read;
+        GET "/projects/{project}/features" => Path("project"), Read;
+        GET "/projects/{project}/datasources" => Path("project"), Read;
+        POST "/projects/{project}/datasources" => Path("project"), Write;
+        POST "/projects/{project}/derivedfeatures" => Path("project"), Write;
+        POST "/projects/{project}/materializations" => Path("project"), Write;
+        POST "/projects/{project}/datasets" => Path("project"), Write;
+        GET "/projects/{project}/anchors" => Path("project"), Read;
+        POST "/projects/{project}/anchors" => Path("project"), Write;
+        POST "/projects/{project}/anchors/{anchor}/features" => Path("project"), Write;
+        GET "/features/{feature}" => Path("feature"), Read;
+        GET "/features/{feature}/lineage" => Path("feature"), Read;
+        GET "/features/{feature}/project" => Path("feature"), Read;
+        GET "/userroles" => Global, Admin;
+        POST "/users/{user}/userroles/add" => Query("project"), Admin;
+        DELETE "/users/{user}/userroles/delete" => Query("project"), Admin;
+    };
+
+    pub fn authorization() -> Authorization {
+        Authorization::new(Self::AUTHORIZATION_RULES)
+    }
+}
+
 #[OpenApi]
 impl FeathrApiV1 {
     #[oai(path = "/projects", method = "get", tag = "ApiTags::Project")]
@@ -40,10 +74,16 @@ impl FeathrApiV1 {
         keyword: Query<Option<String>>,
         page: Query<Option<usize>>,
         limit: Query<Option<usize>>,
+        all: Query<Option<bool>>,
     ) -> poem::Result<Json<Vec<String>>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Read)
-            .await?;
+        // Global Admins can list all projects with `all=true`, others only see the projects they
+        // can read
+        let all = all.0.unwrap_or_default();
+        if all {
+            data.0
+                .check_permission(credential.0, Some("global"), Permission::Admin)
+                .await?;
+        }
         data.0
             .request(
                 opt_seq.0,
@@ -51,6 +91,7 @@ impl FeathrApiV1 {
                     keyword: keyword.0,
                     size: limit.0,
                     offset: page.map(|page| (page - 1) * limit.unwrap_or(10)),
+                    credential: (!all).then(|| credential.0.clone()),
                 },
             )
             .await
@@ -66,9 +107,6 @@ impl FeathrApiV1 {
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
         def: Json<ProjectDef>,
     ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Write)
-            .await?;
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -81,25 +119,8 @@ impl FeathrApiV1 {
             .request(None, FeathrApiRequest::CreateProject { definition })
             .await
             .into_uuid_and_version();
-        // Grant project admin permission to the creator of the project.
         if let Ok((uuid, _)) = &ret {
-            let ret = data
-                .0
-                .request(
-                    None,
-                    FeathrApiRequest::AddUserRole {
-                        project_id_or_name: uuid.to_string(),
-                        user: credential.0.clone(),
-                        role: Permission::Admin,
-                        requestor: credential.0.clone(),
-                        reason: "Created project".to_string(),
-                    },
-                )
-                .await;
-            match ret {
-                registry_api::FeathrApiResponse::Error(e) => return Err(e.into()),
-                _ => {}
-            }
+            grant_project_admin(data.0, &credential.0, uuid).await?;
         }
         ret.map(|v| Json(v.into()))
     }
@@ -112,14 +133,13 @@ impl FeathrApiV1 {
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
     ) -> poem::Result<Json<EntityLineage>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetProjectLineage {
                     id_or_name: project.0,
+                    release: None,
+                    credential: Some(credential.0.clone()),
                 },
             )
             .await
@@ -134,7 +154,6 @@ impl FeathrApiV1 {
     )]
     async fn get_project_features(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
@@ -142,9 +161,6 @@ impl FeathrApiV1 {
         page: Query<Option<usize>>,
         limit: Query<Option<usize>>,
     ) -> poem::Result<Json<Vec<Entity>>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
@@ -168,7 +184,6 @@ impl FeathrApiV1 {
     )]
     async fn get_project_datasources(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
@@ -176,9 +191,6 @@ impl FeathrApiV1 {
         page: Query<Option<usize>>,
         limit: Query<Option<usize>>,
     ) -> poem::Result<Json<Vec<Entity>>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
@@ -202,15 +214,11 @@ impl FeathrApiV1 {
     )]
     async fn new_datasource(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
         project: Path<String>,
         def: Json<SourceDef>,
     ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -238,15 +246,11 @@ impl FeathrApiV1 {
     )]
     async fn new_derived_feature(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
         project: Path<String>,
         def: Json<DerivedFeatureDef>,
     ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -267,6 +271,70 @@ impl FeathrApiV1 {
             .map(|v| Json(v.into()))
     }
 
+    #[oai(
+        path = "/projects/:project/materializations",
+        method = "post",
+        tag = "ApiTags::Materialization"
+    )]
+    async fn new_materialization(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        project: Path<String>,
+        def: Json<MaterializationRecordDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let mut definition = def.0;
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
+        data.0
+            .request(
+                None,
+                FeathrApiRequest::CreateMaterializationRecord {
+                    project_id_or_name: project.0,
+                    definition,
+                },
+            )
+            .await
+            .into_uuid_and_version()
+            .map(|v| Json(v.into()))
+    }
+
+    #[oai(
+        path = "/projects/:project/datasets",
+        method = "post",
+        tag = "ApiTags::Dataset"
+    )]
+    async fn new_dataset(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        project: Path<String>,
+        def: Json<DatasetDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let mut definition = def.0;
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
+        data.0
+            .request(
+                None,
+                FeathrApiRequest::CreateDataset {
+                    project_id_or_name: project.0,
+                    definition,
+                },
+            )
+            .await
+            .into_uuid_and_version()
+            .map(|v| Json(v.into()))
+    }
+
     #[oai(
         path = "/projects/:project/anchors",
         method = "get",
@@ -274,7 +342,6 @@ impl FeathrApiV1 {
     )]
     async fn get_project_anchors(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
@@ -282,9 +349,6 @@ impl FeathrApiV1 {
         page: Query<Option<usize>>,
         limit: Query<Option<usize>>,
     ) -> poem::Result<Json<Vec<Entity>>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
@@ -308,15 +372,11 @@ impl FeathrApiV1 {
     )]
     async fn new_anchor(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
         project: Path<String>,
         def: Json<AnchorDef>,
     ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -344,16 +404,12 @@ impl FeathrApiV1 {
     )]
     async fn new_anchor_feature(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
         project: Path<String>,
         anchor: Path<String>,
         def: Json<AnchorFeatureDef>,
     ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -378,14 +434,10 @@ impl FeathrApiV1 {
     #[oai(path = "/features/:feature", method = "get", tag = "ApiTags::Feature")]
     async fn get_feature(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         feature: Path<String>,
     ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
@@ -410,14 +462,12 @@ impl FeathrApiV1 {
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         feature: Path<String>,
     ) -> poem::Result<Json<EntityLineage>> {
-        data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
                 FeathrApiRequest::GetFeatureLineage {
                     id_or_name: feature.0,
+                    credential: Some(credential.0.clone()),
                 },
             )
             .await
@@ -432,14 +482,10 @@ impl FeathrApiV1 {
     )]
     async fn get_feature_project(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         feature: Path<String>,
     ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
@@ -455,13 +501,9 @@ impl FeathrApiV1 {
     #[oai(path = "/userroles", method = "get", tag = "ApiTags::Rbac")]
     async fn get_user_roles(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
     ) -> poem::Result<Json<Vec<RbacResponse>>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Admin)
-            .await?;
         data.0
             .request(opt_seq.0, FeathrApiRequest::GetUserRoles)
             .await
@@ -484,9 +526,6 @@ impl FeathrApiV1 {
         role: Query<String>,
         reason: Query<String>,
     ) -> poem::Result<Json<String>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Admin)
-            .await?;
         let resp = data
             .0
             .request(
@@ -534,9 +573,6 @@ impl FeathrApiV1 {
         role: Query<String>,
         reason: Query<String>,
     ) -> poem::Result<Json<String>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Admin)
-            .await?;
         let resp = data
             .0
             .request(
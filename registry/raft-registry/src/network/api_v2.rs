@@ -1,21 +1,34 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::Utc;
 use common_utils::StringError;
+use futures_util::stream::BoxStream;
 use poem::{
     error::{BadRequest, InternalServerError},
+    http::header,
     web::Data,
 };
 use poem_openapi::{
     param::{Header, Path, Query},
-    payload::Json,
+    payload::{EventStream, Json, PlainText, Response},
     OpenApi, Tags,
 };
 use registry_api::{
-    AnchorDef, AnchorFeatureDef, ApiError, CreationResponse, DerivedFeatureDef, Entities, Entity,
-    EntityLineage, FeathrApiRequest, ProjectDef, RbacResponse, SourceDef,
+    AnchorDef, AnchorFeatureDef, ApiError, BatchGetEntitiesRequest, BatchGetEntitiesResponse,
+    CodegenLanguage, CreationResponse, DatasetDef, DerivedFeatureDef, DuplicateReport, EdgeType,
+    Entities, Entity, EntityDiff, EntityLineage, EntityType, FeathrApiRequest, FeedPage,
+    MaterializationRecordDef, Ownership, ProjectDef, ProjectSummary, ProjectTemplate, RbacEvent,
+    RbacResponse, Release, ReleaseDef, SearchMode, SourceDef, SourceSample,
 };
 use registry_provider::{Credential, Permission};
+use sql_provider::embed_query;
 use uuid::Uuid;
 
-use crate::RaftRegistryApp;
+use super::authorization::{authorization_rules, Authorization, Rule};
+use crate::{rbac_middleware::current_tenant, FeedPosition, RaftRegistryApp};
+
+/// Seconds between keep-alive comments of the event streams, so proxies don't close idle streams
+const RBAC_EVENTS_KEEP_ALIVE_SECS: u64 = 30;
 
 #[derive(Tags)]
 enum ApiTags {
@@ -25,11 +38,90 @@ enum ApiTags {
     AnchorFeature,
     DerivedFeature,
     Feature,
+    Entity,
+    Materialization,
+    Dataset,
     Rbac,
 }
 
 pub struct FeathrApiV2;
 
+impl FeathrApiV2 {
+    /// Permission required by each route, checked by the `Authorization` middleware
+    pub const AUTHORIZATION_RULES: &'static [Rule] = authorization_rules! {
+        GET "/projects" => Any, Read;
+        POST "/projects" => Global, Write;
+        GET "/project-templates" => Any, Read;
+        POST "/projects/from-template" => Global, Write;
+        GET "/projects/{project}" => Path("project"), Read;
+        DELETE "/projects/{project}" => Path("project"), Admin;
+        GET "/projects/{project}/lineage" => Path("project"), Read;
+        GET "/projects/{project}/lineage.dot" => Path("project"), Read;
+        GET "/projects/{project}/lineage.graphml" => Path("project"), Read;
+        GET "/projects/{project}/codegen" => Path("project"), Read;
+        GET "/projects/{project}/releases" => Path("project"), Read;
+        POST "/projects/{project}/releases" => Path("project"), Write;
+        GET "/projects/{project}/releases/{release}" => Path("project"), Read;
+        DELETE "/projects/{project}/releases/{release}" => Path("project"), Write;
+        GET "/projects/{project}/summary" => Path("project"), Read;
+        GET "/projects/{project}/duplicates" => Path("project"), Read;
+        PUT "/projects/{project}/ownership" => Path("project"), Write;
+        GET "/projects/{project}/features" => Path("project"), Read;
+        GET "/projects/{project}/entities" => Path("project"), Read;
+        GET "/projects/{project}/search" => Path("project"), Read;
+        GET "/projects/{project}/datasources" => Path("project"), Read;
+        POST "/projects/{project}/datasources" => Path("project"), Write;
+        GET "/projects/{project}/datasources/{source}" => Path("project"), Read;
+        PUT "/projects/{project}/datasources/{source}/verified" => Path("project"), Write;
+        GET "/sources/{source}/sample" => Path("source"), Read;
+        GET "/sources/{source}/consumers" => Path("source"), Read;
+        GET "/projects/{project}/datasources/{source}/versions" => Path("project"), Read;
+        GET "/projects/{project}/datasources/{source}/versions/{version}" => Path("project"), Read;
+        GET "/projects/{project}/derivedfeatures" => Path("project"), Read;
+        POST "/projects/{project}/derivedfeatures" => Path("project"), Write;
+        GET "/projects/{project}/derivedfeatures/{feature}" => Child(&["feature"]), Read;
+        GET "/projects/{project}/derivedfeatures/{feature}/versions" => Child(&["feature"]), Read;
+        GET "/projects/{project}/derivedfeatures/{feature}/versions/{version}" => Child(&["feature"]), Read;
+        GET "/projects/{project}/anchors" => Path("project"), Read;
+        POST "/projects/{project}/anchors" => Path("project"), Write;
+        GET "/projects/{project}/anchors/{anchor}" => Child(&["anchor"]), Read;
+        GET "/projects/{project}/anchors/{anchor}/versions" => Child(&["anchor"]), Read;
+        GET "/projects/{project}/anchors/{anchor}/versions/{version}" => Child(&["anchor"]), Read;
+        GET "/projects/{project}/anchors/{anchor}/features" => Child(&["anchor"]), Read;
+        POST "/projects/{project}/anchors/{anchor}/features" => Child(&["anchor"]), Write;
+        GET "/projects/{project}/anchors/{anchor}/features/{feature}" => Child(&["anchor", "feature"]), Read;
+        GET "/projects/{project}/anchors/{anchor}/features/{feature}/versions" => Child(&["anchor", "feature"]), Read;
+        GET "/projects/{project}/anchors/{anchor}/features/{feature}/versions/{version}" => Child(&["anchor", "feature"]), Read;
+        GET "/features/{feature}" => Path("feature"), Read;
+        GET "/features/{feature}/versions/{from}/diff/{to}" => Path("feature"), Read;
+        GET "/features/{feature}/lineage" => Path("feature"), Read;
+        GET "/features/{feature}/project" => Path("feature"), Read;
+        PUT "/features/{feature}/ownership" => Path("feature"), Write;
+        GET "/features/{feature}/materializations" => Path("feature"), Read;
+        POST "/entities:batchGet" => Any, Read;
+        GET "/entities/{entity}/related" => Path("entity"), Read;
+        GET "/entities/{entity}/docs" => Path("entity"), Read;
+        PUT "/entities/{entity}/docs" => Path("entity"), Write;
+        DELETE "/entities/{entity}" => Path("entity"), Write;
+        PUT "/entities/{entity}/environments/{environment}" => Path("entity"), Write;
+        DELETE "/entities/{entity}/environments/{environment}" => Path("entity"), Write;
+        GET "/feed" => Global, Read;
+        GET "/projects/{project}/materializations" => Path("project"), Read;
+        POST "/projects/{project}/materializations" => Path("project"), Write;
+        GET "/features/{feature}/datasets" => Path("feature"), Read;
+        GET "/projects/{project}/datasets" => Path("project"), Read;
+        POST "/projects/{project}/datasets" => Path("project"), Write;
+        GET "/userroles" => Global, Admin;
+        GET "/userroles/events" => Any, Read;
+        POST "/users/{user}/userroles/add" => Global, Admin;
+        DELETE "/users/{user}/userroles/add" => Global, Admin;
+    };
+
+    pub fn authorization() -> Authorization {
+        Authorization::new(Self::AUTHORIZATION_RULES)
+    }
+}
+
 #[OpenApi]
 impl FeathrApiV2 {
     #[oai(path = "/projects", method = "get", tag = "ApiTags::Project")]
@@ -41,10 +133,16 @@ impl FeathrApiV2 {
         keyword: Query<Option<String>>,
         size: Query<Option<usize>>,
         offset: Query<Option<usize>>,
+        all: Query<Option<bool>>,
     ) -> poem::Result<Json<Vec<String>>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Read)
-            .await?;
+        // Global Admins can list all projects with `all=true`, others only see the projects they
+        // can read
+        let all = all.0.unwrap_or_default();
+        if all {
+            data.0
+                .check_permission(credential.0, Some("global"), Permission::Admin)
+                .await?;
+        }
         data.0
             .request(
                 opt_seq.0,
@@ -52,6 +150,7 @@ impl FeathrApiV2 {
                     keyword: keyword.0,
                     size: size.0,
                     offset: offset.0,
+                    credential: (!all).then(|| credential.0.clone()),
                 },
             )
             .await
@@ -65,11 +164,12 @@ impl FeathrApiV2 {
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
         def: Json<ProjectDef>,
     ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Write)
-            .await?;
+        let key = data
+            .0
+            .idempotency_key(idempotency_key.0, credential.0, &def.0)?;
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -79,44 +179,85 @@ impl FeathrApiV2 {
         }
         let ret = data
             .0
-            .request(None, FeathrApiRequest::CreateProject { definition })
+            .request(
+                None,
+                FeathrApiRequest::CreateProject { definition }.idempotent(key),
+            )
             .await
             .into_uuid_and_version();
-        // Grant project admin permission to the creator of the project.
         if let Ok((uuid, _)) = &ret {
-            let ret = data
-                .0
-                .request(
-                    None,
-                    FeathrApiRequest::AddUserRole {
-                        project_id_or_name: uuid.to_string(),
-                        user: credential.0.clone(),
-                        role: Permission::Admin,
-                        requestor: credential.0.clone(),
-                        reason: "Created project".to_string(),
-                    },
-                )
-                .await;
-            match ret {
-                registry_api::FeathrApiResponse::Error(e) => return Err(e.into()),
-                _ => {}
-            }
+            grant_project_admin(data.0, credential.0, uuid).await?;
         }
 
         ret.map(|v| Json(v.into()))
     }
 
+    /// Templates new projects can be created from, defined in the server config
+    #[oai(path = "/project-templates", method = "get", tag = "ApiTags::Project")]
+    async fn get_project_templates(
+        &self,
+        data: Data<&RaftRegistryApp>,
+    ) -> poem::Result<Json<Vec<ProjectTemplate>>> {
+        Ok(Json(data.0.templates.values().cloned().collect()))
+    }
+
+    /// Create a project along with the sources, anchors and features of the template, the tags of
+    /// the template are added to the project unless the definition sets them.
+    /// Retrying with the same `Idempotency-Key` resumes an interrupted seeding.
+    #[oai(
+        path = "/projects/from-template",
+        method = "post",
+        tag = "ApiTags::Project"
+    )]
+    async fn new_project_from_template(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
+        template: Query<String>,
+        def: Json<ProjectDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let key =
+            data.0
+                .idempotency_key(idempotency_key.0, credential.0, &(&template.0, &def.0))?;
+        let template = data.0.templates.get(&template.0).cloned().ok_or_else(|| {
+            ApiError::BadRequest(format!("Unknown project template `{}`", template.0))
+        })?;
+        let mut definition = def.0;
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
+        for (k, v) in &template.tags {
+            definition
+                .tags
+                .entry(k.to_owned())
+                .or_insert_with(|| v.to_owned());
+        }
+        let created_by = definition.created_by.clone();
+        let (uuid, version) = data
+            .0
+            .request(
+                None,
+                FeathrApiRequest::CreateProject { definition }.idempotent(key),
+            )
+            .await
+            .into_uuid_and_version()?;
+        grant_project_admin(data.0, credential.0, &uuid).await?;
+        data.0.seed_project(uuid, &template, &created_by).await?;
+        Ok(Json((uuid, version).into()))
+    }
+
     #[oai(path = "/projects/:project", method = "get", tag = "ApiTags::Project")]
     async fn get_project(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
     ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
@@ -129,344 +270,400 @@ impl FeathrApiV2 {
             .map(Json)
     }
 
+    /// Delete the project, with `cascade=true` all its features, anchors and sources are deleted
+    /// as well, otherwise the project must be empty.
+    /// Returns the deleted entities in the order of deletion, with `dry_run=true` the entities
+    /// that would be deleted are returned and nothing is changed.
     #[oai(
-        path = "/projects/:project/lineage",
-        method = "get",
+        path = "/projects/:project",
+        method = "delete",
         tag = "ApiTags::Project"
     )]
-    async fn get_project_lineage(
+    async fn delete_project(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-    ) -> poem::Result<Json<EntityLineage>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        cascade: Query<Option<bool>>,
+        dry_run: Query<Option<bool>>,
+    ) -> poem::Result<Json<Entities>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectLineage {
+                FeathrApiRequest::DeleteProject {
                     id_or_name: project.0,
+                    cascade: cascade.0.unwrap_or_default(),
+                    dry_run: dry_run.0.unwrap_or_default(),
+                    deleted_at: Some(Utc::now()),
                 },
             )
             .await
-            .into_lineage()
+            .into_entities()
             .map(Json)
     }
 
     #[oai(
-        path = "/projects/:project/features",
+        path = "/projects/:project/lineage",
         method = "get",
         tag = "ApiTags::Project"
     )]
-    async fn get_project_features(
+    async fn get_project_lineage(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        keyword: Query<Option<String>>,
-        size: Query<Option<usize>>,
-        offset: Query<Option<usize>>,
-    ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
-        data.0
-            .request(
-                opt_seq.0,
-                FeathrApiRequest::GetProjectFeatures {
-                    project_id_or_name: project.0,
-                    keyword: keyword.0,
-                    size: size.0,
-                    offset: offset.0,
-                },
-            )
+        /// Only the entity versions pinned by the release
+        release: Query<Option<String>>,
+    ) -> poem::Result<Json<EntityLineage>> {
+        project_lineage(data.0, credential.0, opt_seq.0, project.0, release.0)
             .await
-            .into_entities()
             .map(Json)
     }
 
+    /// Project lineage in the Graphviz DOT format, e.g. `dot -Tsvg lineage.dot`
     #[oai(
-        path = "/projects/:project/datasources",
+        path = "/projects/:project/lineage.dot",
         method = "get",
-        tag = "ApiTags::DataSource"
+        tag = "ApiTags::Project"
     )]
-    async fn get_datasources(
+    async fn get_project_lineage_dot(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        keyword: Query<Option<String>>,
-        size: Query<Option<usize>>,
-        offset: Query<Option<usize>>,
-    ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+    ) -> poem::Result<Response<PlainText<String>>> {
+        let lineage = project_lineage(data.0, credential.0, opt_seq.0, project.0, None).await?;
+        Ok(Response::new(PlainText(lineage.to_dot()))
+            .header(header::CONTENT_TYPE, "text/vnd.graphviz; charset=utf-8"))
+    }
+
+    /// Project lineage in the GraphML format, e.g. for Gephi
+    #[oai(
+        path = "/projects/:project/lineage.graphml",
+        method = "get",
+        tag = "ApiTags::Project"
+    )]
+    async fn get_project_lineage_graphml(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+    ) -> poem::Result<Response<PlainText<String>>> {
+        let lineage = project_lineage(data.0, credential.0, opt_seq.0, project.0, None).await?;
+        Ok(Response::new(PlainText(lineage.to_graphml())).header(
+            header::CONTENT_TYPE,
+            "application/graphml+xml; charset=utf-8",
+        ))
+    }
+
+    /// Ready-to-run snippets getting the offline features of the project and materializing them,
+    /// `lang` defaults to `python`
+    #[oai(
+        path = "/projects/:project/codegen",
+        method = "get",
+        tag = "ApiTags::Project"
+    )]
+    async fn get_project_codegen(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        lang: Query<Option<String>>,
+    ) -> poem::Result<Response<PlainText<String>>> {
+        let lang: CodegenLanguage = lang.0.as_deref().unwrap_or("python").parse()?;
+        let lineage = project_lineage(data.0, credential.0, opt_seq.0, project.0, None).await?;
+        let content_type = match lang {
+            CodegenLanguage::Python => "text/x-python; charset=utf-8",
+        };
+        Ok(Response::new(PlainText(lineage.to_code(lang)))
+            .header(header::CONTENT_TYPE, content_type))
+    }
+
+    #[oai(
+        path = "/projects/:project/releases",
+        method = "get",
+        tag = "ApiTags::Project"
+    )]
+    async fn get_project_releases(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+    ) -> poem::Result<Json<Vec<Release>>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDataSources {
+                FeathrApiRequest::GetProjectReleases {
                     project_id_or_name: project.0,
-                    keyword: keyword.0,
-                    size: size.0,
-                    offset: offset.0,
                 },
             )
             .await
-            .into_entities()
+            .into_releases()
             .map(Json)
     }
 
+    /// Pin the current entity versions of the project as a release, the versions labeled with
+    /// `environment` are pinned if it's set, otherwise the latest ones
     #[oai(
-        path = "/projects/:project/datasources",
+        path = "/projects/:project/releases",
         method = "post",
-        tag = "ApiTags::DataSource"
+        tag = "ApiTags::Project"
     )]
-    async fn new_datasource(
+    async fn new_project_release(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        def: Json<SourceDef>,
-    ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
+        def: Json<ReleaseDef>,
+    ) -> poem::Result<Json<Release>> {
         let mut definition = def.0;
-        if definition.id.is_empty() {
-            definition.id = Uuid::new_v4().to_string();
-        }
         if definition.created_by.is_empty() {
             definition.created_by = creator.0.unwrap_or_default();
         }
         data.0
             .request(
-                None,
-                FeathrApiRequest::CreateProjectDataSource {
+                opt_seq.0,
+                FeathrApiRequest::CreateRelease {
                     project_id_or_name: project.0,
                     definition,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .into_release()
+            .map(Json)
     }
 
     #[oai(
-        path = "/projects/:project/datasources/:source",
+        path = "/projects/:project/releases/:release",
         method = "get",
-        tag = "ApiTags::DataSource"
+        tag = "ApiTags::Project"
     )]
-    async fn get_datasource(
+    async fn get_project_release(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        source: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        release: Path<String>,
+    ) -> poem::Result<Json<Release>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDataSource {
+                FeathrApiRequest::GetProjectRelease {
                     project_id_or_name: project.0,
-                    id_or_name: source.0,
+                    name: release.0,
                 },
             )
             .await
-            .into_entity()
+            .into_release()
             .map(Json)
     }
 
     #[oai(
-        path = "/projects/:project/datasources/:source/versions",
-        method = "get",
-        tag = "ApiTags::DataSource"
+        path = "/projects/:project/releases/:release",
+        method = "delete",
+        tag = "ApiTags::Project"
     )]
-    async fn get_datasource_versions(
+    async fn delete_project_release(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        source: Path<String>,
-    ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        release: Path<String>,
+    ) -> poem::Result<Json<String>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDataSourceVersions {
+                FeathrApiRequest::DeleteRelease {
                     project_id_or_name: project.0,
-                    id_or_name: source.0,
+                    name: release.0,
                 },
             )
             .await
-            .into_entities()
-            .map(Json)
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
     }
 
     #[oai(
-        path = "/projects/:project/datasources/:source/versions/:version",
+        path = "/projects/:project/summary",
         method = "get",
-        tag = "ApiTags::DataSource"
+        tag = "ApiTags::Project"
     )]
-    async fn get_datasource_version(
+    async fn get_project_summary(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        source: Path<String>,
-        version: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+    ) -> poem::Result<Json<ProjectSummary>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDataSourceVersion {
-                    project_id_or_name: project.0,
-                    id_or_name: source.0,
-                    version: parse_version(version.0)?,
+                FeathrApiRequest::GetProjectSummary {
+                    id_or_name: project.0,
                 },
             )
             .await
-            .into_entity()
+            .into_project_summary()
             .map(Json)
     }
 
+    /// Groups of anchor features with the same sources, transformation and keys
     #[oai(
-        path = "/projects/:project/derivedfeatures",
+        path = "/projects/:project/duplicates",
         method = "get",
-        tag = "ApiTags::DerivedFeature"
+        tag = "ApiTags::Project"
     )]
-    async fn get_project_derived_features(
+    async fn get_project_duplicates(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        keyword: Query<Option<String>>,
-        size: Query<Option<usize>>,
-        offset: Query<Option<usize>>,
-    ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+    ) -> poem::Result<Json<DuplicateReport>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDerivedFeatures {
-                    project_id_or_name: project.0,
-                    keyword: keyword.0,
-                    size: size.0,
-                    offset: offset.0,
+                FeathrApiRequest::GetProjectDuplicates {
+                    id_or_name: project.0,
                 },
             )
             .await
-            .into_entities()
+            .into_duplicate_report()
             .map(Json)
     }
 
     #[oai(
-        path = "/projects/:project/derivedfeatures",
-        method = "post",
-        tag = "ApiTags::DerivedFeature"
+        path = "/projects/:project/ownership",
+        method = "put",
+        tag = "ApiTags::Project"
     )]
-    async fn new_derived_feature(
+    async fn set_project_ownership(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
-        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        def: Json<DerivedFeatureDef>,
-    ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
-        let mut definition = def.0;
-        if definition.id.is_empty() {
-            definition.id = Uuid::new_v4().to_string();
-        }
-        if definition.created_by.is_empty() {
-            definition.created_by = creator.0.unwrap_or_default();
-        }
+        ownership: Json<Ownership>,
+    ) -> poem::Result<Json<String>> {
         data.0
             .request(
-                None,
-                FeathrApiRequest::CreateProjectDerivedFeature {
-                    project_id_or_name: project.0,
-                    definition,
+                opt_seq.0,
+                FeathrApiRequest::SetEntityOwnership {
+                    id_or_name: project.0,
+                    ownership: ownership.0,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
     }
 
     #[oai(
-        path = "/projects/:project/derivedfeatures/:feature",
+        path = "/projects/:project/features",
         method = "get",
-        tag = "ApiTags::DerivedFeature"
+        tag = "ApiTags::Project"
     )]
-    async fn get_project_derived_feature(
+    async fn get_project_features(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        feature: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        keyword: Query<Option<String>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDerivedFeature {
+                FeathrApiRequest::GetProjectFeatures {
                     project_id_or_name: project.0,
-                    id_or_name: feature.0,
+                    keyword: keyword.0,
+                    size: size.0,
+                    offset: offset.0,
                 },
             )
             .await
-            .into_entity()
+            .into_entities()
             .map(Json)
     }
 
+    /**
+     * List entities in the project by the type and the tags, `tag` is either `key` matching any
+     * value or `key=value`, entities must match all of the tags
+     */
     #[oai(
-        path = "/projects/:project/derivedfeatures/:feature/versions",
+        path = "/projects/:project/entities",
         method = "get",
-        tag = "ApiTags::DerivedFeature"
+        tag = "ApiTags::Project"
     )]
-    async fn get_project_derived_feature_versions(
+    async fn get_project_entities(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        feature: Path<String>,
-    ) -> poem::Result<Json<Entities>> {
+        #[oai(name = "type")] entity_type: Query<Vec<EntityType>>,
+        tag: Query<Vec<String>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
         data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectEntities {
+                    project_id_or_name: project.0,
+                    types: entity_type.0.into_iter().map(|t| t.into()).collect(),
+                    tags: parse_tag_filters(&tag.0)?,
+                    size: size.0,
+                    offset: offset.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    /**
+     * Search entities in the project, `semantic` mode ranks conceptually similar entities higher
+     * even if they don't contain the keyword.
+     * Keyword search without `offset` returns `nextCursor` to get the next page with.
+     */
+    #[oai(
+        path = "/projects/:project/search",
+        method = "get",
+        tag = "ApiTags::Project"
+    )]
+    async fn search_project(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        keyword: Query<String>,
+        #[oai(name = "type")] entity_type: Query<Vec<EntityType>>,
+        mode: Query<Option<SearchMode>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+        cursor: Query<Option<String>>,
+    ) -> poem::Result<Json<Entities>> {
+        let mode = mode.0.unwrap_or_default();
+        // The embedder may be a remote API, it's called before the registry is locked
+        let query_vector = match mode {
+            SearchMode::Semantic => embed_query(&keyword.0).await.map_err(ApiError::from)?,
+            SearchMode::Keyword => None,
+        };
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDerivedFeatureVersions {
+                FeathrApiRequest::SearchProject {
                     project_id_or_name: project.0,
-                    id_or_name: feature.0,
+                    keyword: keyword.0,
+                    types: entity_type.0.into_iter().map(|t| t.into()).collect(),
+                    mode,
+                    size: size.0,
+                    offset: offset.0,
+                    cursor: cursor.0,
+                    query_vector,
                 },
             )
             .await
@@ -475,28 +672,222 @@ impl FeathrApiV2 {
     }
 
     #[oai(
-        path = "/projects/:project/derivedfeatures/:feature/versions/:version",
+        path = "/projects/:project/datasources",
         method = "get",
-        tag = "ApiTags::DerivedFeature"
+        tag = "ApiTags::DataSource"
     )]
-    async fn get_project_derived_feature_version(
+    async fn get_datasources(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        keyword: Query<Option<String>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDataSources {
+                    project_id_or_name: project.0,
+                    keyword: keyword.0,
+                    size: size.0,
+                    offset: offset.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/datasources",
+        method = "post",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn new_datasource(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
+        project: Path<String>,
+        def: Json<SourceDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let key = data
+            .0
+            .idempotency_key(idempotency_key.0, credential.0, &(&project.0, &def.0))?;
+        let mut definition = def.0;
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
+        data.0
+            .request(
+                None,
+                FeathrApiRequest::CreateProjectDataSource {
+                    project_id_or_name: project.0,
+                    definition,
+                }
+                .idempotent(key),
+            )
+            .await
+            .into_uuid_and_version()
+            .map(|v| Json(v.into()))
+    }
+
+    #[oai(
+        path = "/projects/:project/datasources/:source",
+        method = "get",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn get_datasource(
+        &self,
+        data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        feature: Path<String>,
-        version: Path<String>,
+        source: Path<String>,
     ) -> poem::Result<Json<Entity>> {
         data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDataSource {
+                    project_id_or_name: project.0,
+                    id_or_name: source.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/datasources/:source/verified",
+        method = "put",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn set_datasource_verified(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        source: Path<String>,
+    ) -> poem::Result<Json<String>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectDerivedFeatureVersion {
+                FeathrApiRequest::SetSourceVerified {
                     project_id_or_name: project.0,
-                    id_or_name: feature.0,
+                    id_or_name: source.0,
+                    // Timestamp is decided here so all nodes apply the same value
+                    verified_at: Utc::now(),
+                },
+            )
+            .await
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
+    }
+
+    #[oai(
+        path = "/sources/:source/sample",
+        method = "get",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn get_source_sample(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        source: Path<String>,
+        rows: Query<Option<usize>>,
+    ) -> poem::Result<Json<SourceSample>> {
+        let source = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetDataSource {
+                    id_or_name: source.0,
+                },
+            )
+            .await
+            .into_entity()?;
+        Ok(Json(data.0.sampler.sample(&source, rows.0).await?))
+    }
+
+    #[oai(
+        path = "/sources/:source/consumers",
+        method = "get",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn get_source_consumers(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        source: Path<String>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
+        let consumers = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetSourceConsumers {
+                    id_or_name: source.0,
+                },
+            )
+            .await
+            .into_entities()?;
+        Ok(Json(
+            readable_page(data.0, credential.0, consumers, size.0, offset.0).await,
+        ))
+    }
+
+    #[oai(
+        path = "/projects/:project/datasources/:source/versions",
+        method = "get",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn get_datasource_versions(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        source: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDataSourceVersions {
+                    project_id_or_name: project.0,
+                    id_or_name: source.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/datasources/:source/versions/:version",
+        method = "get",
+        tag = "ApiTags::DataSource"
+    )]
+    async fn get_datasource_version(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        source: Path<String>,
+        version: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDataSourceVersion {
+                    project_id_or_name: project.0,
+                    id_or_name: source.0,
                     version: parse_version(version.0)?,
                 },
             )
@@ -506,13 +897,12 @@ impl FeathrApiV2 {
     }
 
     #[oai(
-        path = "/projects/:project/anchors",
+        path = "/projects/:project/derivedfeatures",
         method = "get",
-        tag = "ApiTags::Anchor"
+        tag = "ApiTags::DerivedFeature"
     )]
-    async fn get_project_anchors(
+    async fn get_project_derived_features(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
@@ -520,13 +910,10 @@ impl FeathrApiV2 {
         size: Query<Option<usize>>,
         offset: Query<Option<usize>>,
     ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectAnchors {
+                FeathrApiRequest::GetProjectDerivedFeatures {
                     project_id_or_name: project.0,
                     keyword: keyword.0,
                     size: size.0,
@@ -539,155 +926,795 @@ impl FeathrApiV2 {
     }
 
     #[oai(
-        path = "/projects/:project/anchors",
+        path = "/projects/:project/derivedfeatures",
         method = "post",
-        tag = "ApiTags::Anchor"
+        tag = "ApiTags::DerivedFeature"
     )]
-    async fn new_anchor(
+    async fn new_derived_feature(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
+        project: Path<String>,
+        def: Json<DerivedFeatureDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let key = data
+            .0
+            .idempotency_key(idempotency_key.0, credential.0, &(&project.0, &def.0))?;
+        let mut definition = def.0;
+        // Inputs may come from other projects, the caller must be able to read all of them
+        for input in definition.input_refs() {
+            data.0
+                .check_permission(credential.0, Some(input), Permission::Read)
+                .await?;
+        }
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
+        data.0
+            .request(
+                None,
+                FeathrApiRequest::CreateProjectDerivedFeature {
+                    project_id_or_name: project.0,
+                    definition,
+                }
+                .idempotent(key),
+            )
+            .await
+            .into_uuid_and_version()
+            .map(|v| Json(v.into()))
+    }
+
+    #[oai(
+        path = "/projects/:project/derivedfeatures/:feature",
+        method = "get",
+        tag = "ApiTags::DerivedFeature"
+    )]
+    async fn get_project_derived_feature(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDerivedFeature {
+                    project_id_or_name: project.0,
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/derivedfeatures/:feature/versions",
+        method = "get",
+        tag = "ApiTags::DerivedFeature"
+    )]
+    async fn get_project_derived_feature_versions(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDerivedFeatureVersions {
+                    project_id_or_name: project.0,
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/derivedfeatures/:feature/versions/:version",
+        method = "get",
+        tag = "ApiTags::DerivedFeature"
+    )]
+    async fn get_project_derived_feature_version(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        feature: Path<String>,
+        version: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectDerivedFeatureVersion {
+                    project_id_or_name: project.0,
+                    id_or_name: feature.0,
+                    version: parse_version(version.0)?,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors",
+        method = "get",
+        tag = "ApiTags::Anchor"
+    )]
+    async fn get_project_anchors(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        keyword: Query<Option<String>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectAnchors {
+                    project_id_or_name: project.0,
+                    keyword: keyword.0,
+                    size: size.0,
+                    offset: offset.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors",
+        method = "post",
+        tag = "ApiTags::Anchor"
+    )]
+    async fn new_anchor(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
+        project: Path<String>,
+        def: Json<AnchorDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let key = data
+            .0
+            .idempotency_key(idempotency_key.0, credential.0, &(&project.0, &def.0))?;
+        let mut definition = def.0;
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
+        data.0
+            .request(
+                None,
+                FeathrApiRequest::CreateProjectAnchor {
+                    project_id_or_name: project.0,
+                    definition,
+                }
+                .idempotent(key),
+            )
+            .await
+            .into_uuid_and_version()
+            .map(|v| Json(v.into()))
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor",
+        method = "get",
+        tag = "ApiTags::Anchor"
+    )]
+    async fn get_anchor(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        anchor: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectAnchor {
+                    project_id_or_name: project.0,
+                    id_or_name: anchor.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor/versions",
+        method = "get",
+        tag = "ApiTags::Anchor"
+    )]
+    async fn get_anchor_versions(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        anchor: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectAnchorVersions {
+                    project_id_or_name: project.0,
+                    id_or_name: anchor.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor/versions/:version",
+        method = "get",
+        tag = "ApiTags::Anchor"
+    )]
+    async fn get_anchor_version(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        anchor: Path<String>,
+        version: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetProjectAnchorVersion {
+                    project_id_or_name: project.0,
+                    id_or_name: anchor.0,
+                    version: parse_version(version.0)?,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor/features",
+        method = "get",
+        tag = "ApiTags::AnchorFeature"
+    )]
+    async fn get_anchor_features(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        anchor: Path<String>,
+        keyword: Query<Option<String>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetAnchorFeatures {
+                    project_id_or_name: project.0,
+                    anchor_id_or_name: anchor.0,
+                    keyword: keyword.0,
+                    size: size.0,
+                    offset: offset.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor/features",
+        method = "post",
+        tag = "ApiTags::AnchorFeature"
+    )]
+    async fn new_anchor_feature(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
+        project: Path<String>,
+        anchor: Path<String>,
+        def: Json<AnchorFeatureDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let key = data.0.idempotency_key(
+            idempotency_key.0,
+            credential.0,
+            &(&project.0, &anchor.0, &def.0),
+        )?;
+        let mut definition = def.0;
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
+        data.0
+            .request(
+                None,
+                FeathrApiRequest::CreateAnchorFeature {
+                    project_id_or_name: project.0,
+                    anchor_id_or_name: anchor.0,
+                    definition,
+                }
+                .idempotent(key),
+            )
+            .await
+            .into_uuid_and_version()
+            .map(|v| Json(v.into()))
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor/features/:feature",
+        method = "get",
+        tag = "ApiTags::AnchorFeature"
+    )]
+    async fn get_project_anchor_feature(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        anchor: Path<String>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetAnchorFeature {
+                    project_id_or_name: project.0,
+                    anchor_id_or_name: anchor.0,
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor/features/:feature/versions",
+        method = "get",
+        tag = "ApiTags::AnchorFeature"
+    )]
+    async fn get_project_anchor_feature_versions(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        anchor: Path<String>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetAnchorFeatureVersions {
+                    project_id_or_name: project.0,
+                    anchor_id_or_name: anchor.0,
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/projects/:project/anchors/:anchor/features/:feature/versions/:version",
+        method = "get",
+        tag = "ApiTags::AnchorFeature"
+    )]
+    async fn get_project_anchor_feature_version(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        project: Path<String>,
+        anchor: Path<String>,
+        feature: Path<String>,
+        version: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetAnchorFeatureVersion {
+                    project_id_or_name: project.0,
+                    anchor_id_or_name: anchor.0,
+                    id_or_name: feature.0,
+                    version: parse_version(version.0)?,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(path = "/features/:feature", method = "get", tag = "ApiTags::Feature")]
+    async fn get_feature(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetFeature {
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    /// Fields changed between two versions of the feature, either version can be `latest`
+    #[oai(
+        path = "/features/:feature/versions/:from/diff/:to",
+        method = "get",
+        tag = "ApiTags::Feature"
+    )]
+    async fn get_feature_version_diff(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        feature: Path<String>,
+        from: Path<String>,
+        to: Path<String>,
+    ) -> poem::Result<Json<EntityDiff>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetFeatureVersionDiff {
+                    id_or_name: feature.0,
+                    from_version: parse_version(from.0)?,
+                    to_version: parse_version(to.0)?,
+                },
+            )
+            .await
+            .into_entity_diff()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/features/:feature/lineage",
+        method = "get",
+        tag = "ApiTags::Feature"
+    )]
+    async fn get_feature_lineage(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<EntityLineage>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetFeatureLineage {
+                    id_or_name: feature.0,
+                    credential: Some(credential.0.clone()),
+                },
+            )
+            .await
+            .into_lineage()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/features/:feature/project",
+        method = "get",
+        tag = "ApiTags::Feature"
+    )]
+    async fn get_feature_project(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entity>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetEntityProject {
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(Json)
+    }
+
+    #[oai(
+        path = "/features/:feature/ownership",
+        method = "put",
+        tag = "ApiTags::Feature"
+    )]
+    async fn set_feature_ownership(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        feature: Path<String>,
+        ownership: Json<Ownership>,
+    ) -> poem::Result<Json<String>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::SetEntityOwnership {
+                    id_or_name: feature.0,
+                    ownership: ownership.0,
+                },
+            )
+            .await
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
+    }
+
+    #[oai(
+        path = "/features/:feature/materializations",
+        method = "get",
+        tag = "ApiTags::Materialization"
+    )]
+    async fn get_feature_materializations(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        feature: Path<String>,
+    ) -> poem::Result<Json<Entities>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetFeatureMaterializationRecords {
+                    id_or_name: feature.0,
+                },
+            )
+            .await
+            .into_entities()
+            .map(Json)
+    }
+
+    /// Get up to 1000 entities by GUID in one round trip, the entities the caller can't read are
+    /// reported as not found
+    #[oai(path = "/entities:batchGet", method = "post", tag = "ApiTags::Entity")]
+    async fn batch_get_entities(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        def: Json<BatchGetEntitiesRequest>,
+    ) -> poem::Result<Json<BatchGetEntitiesResponse>> {
+        let ids = def.0.parse_ids()?;
+        let mut found: HashMap<String, Entity> = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetEntities { ids: ids.clone() },
+            )
+            .await
+            .into_entities()?
+            .entities
+            .into_iter()
+            .map(|e| (e.guid.clone(), e))
+            .collect();
+        let mut entities = vec![];
+        let mut not_found = vec![];
+        for id in ids.into_iter().map(|id| id.to_string()) {
+            // Checked per entity, the ids may span projects with different roles
+            match found.remove(&id) {
+                Some(e)
+                    if data
+                        .0
+                        .check_permission(credential.0, Some(&e.guid), Permission::Read)
+                        .await
+                        .is_ok() =>
+                {
+                    entities.push(e)
+                }
+                _ => not_found.push(id),
+            }
+        }
+        Ok(Json(BatchGetEntitiesResponse {
+            entities,
+            not_found,
+        }))
+    }
+
+    #[oai(
+        path = "/entities/:entity/related",
+        method = "get",
+        tag = "ApiTags::Entity"
+    )]
+    async fn get_related_entities(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        entity: Path<String>,
+        edge_type: Query<Option<EdgeType>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
+    ) -> poem::Result<Json<Entities>> {
+        let related = data
+            .0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetRelatedEntities {
+                    id_or_name: entity.0,
+                    edge_type: edge_type.0.map(|t| t.into()),
+                },
+            )
+            .await
+            .into_entities()?;
+        Ok(Json(
+            readable_page(data.0, credential.0, related, size.0, offset.0).await,
+        ))
+    }
+
+    #[oai(path = "/entities/:entity/docs", method = "get", tag = "ApiTags::Entity")]
+    async fn get_entity_docs(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        entity: Path<String>,
+    ) -> poem::Result<PlainText<String>> {
+        data.0
+            .request(
+                opt_seq.0,
+                FeathrApiRequest::GetEntity {
+                    id_or_name: entity.0,
+                },
+            )
+            .await
+            .into_entity()
+            .map(|e| PlainText(e.docs))
+    }
+
+    #[oai(path = "/entities/:entity/docs", method = "put", tag = "ApiTags::Entity")]
+    async fn set_entity_docs(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
-        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
-        project: Path<String>,
-        def: Json<AnchorDef>,
-    ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
-        let mut definition = def.0;
-        if definition.id.is_empty() {
-            definition.id = Uuid::new_v4().to_string();
-        }
-        if definition.created_by.is_empty() {
-            definition.created_by = creator.0.unwrap_or_default();
-        }
+        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        entity: Path<String>,
+        docs: PlainText<String>,
+    ) -> poem::Result<Json<String>> {
         data.0
             .request(
-                None,
-                FeathrApiRequest::CreateProjectAnchor {
-                    project_id_or_name: project.0,
-                    definition,
+                opt_seq.0,
+                FeathrApiRequest::SetEntityDocs {
+                    id_or_name: entity.0,
+                    docs: docs.0,
                 },
             )
             .await
-            .into_uuid_and_version()
-            .map(|v| Json(v.into()))
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
     }
 
-    #[oai(
-        path = "/projects/:project/anchors/:anchor",
-        method = "get",
-        tag = "ApiTags::Anchor"
-    )]
-    async fn get_anchor(
+    #[oai(path = "/entities/:entity", method = "delete", tag = "ApiTags::Entity")]
+    async fn delete_entity(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        project: Path<String>,
-        anchor: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        entity: Path<String>,
+    ) -> poem::Result<Json<String>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectAnchor {
-                    project_id_or_name: project.0,
-                    id_or_name: anchor.0,
+                FeathrApiRequest::DeleteEntity {
+                    id_or_name: entity.0,
+                    deleted_at: Some(Utc::now()),
                 },
             )
             .await
-            .into_entity()
-            .map(Json)
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
     }
 
+    /// Label the entity version with the environment, other versions of the entity lose the label
     #[oai(
-        path = "/projects/:project/anchors/:anchor/versions",
-        method = "get",
-        tag = "ApiTags::Anchor"
+        path = "/entities/:entity/environments/:environment",
+        method = "put",
+        tag = "ApiTags::Entity"
     )]
-    async fn get_anchor_versions(
+    async fn promote_entity(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        project: Path<String>,
-        anchor: Path<String>,
-    ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        entity: Path<String>,
+        environment: Path<String>,
+    ) -> poem::Result<Json<String>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectAnchorVersions {
-                    project_id_or_name: project.0,
-                    id_or_name: anchor.0,
+                FeathrApiRequest::PromoteEntity {
+                    id_or_name: entity.0,
+                    environment: environment.0,
                 },
             )
             .await
-            .into_entities()
-            .map(Json)
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
     }
 
     #[oai(
-        path = "/projects/:project/anchors/:anchor/versions/:version",
-        method = "get",
-        tag = "ApiTags::Anchor"
+        path = "/entities/:entity/environments/:environment",
+        method = "delete",
+        tag = "ApiTags::Entity"
     )]
-    async fn get_anchor_version(
+    async fn demote_entity(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        project: Path<String>,
-        anchor: Path<String>,
-        version: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        entity: Path<String>,
+        environment: Path<String>,
+    ) -> poem::Result<Json<String>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetProjectAnchorVersion {
-                    project_id_or_name: project.0,
-                    id_or_name: anchor.0,
-                    version: parse_version(version.0)?,
+                FeathrApiRequest::DemoteEntity {
+                    id_or_name: entity.0,
+                    environment: environment.0,
                 },
             )
             .await
-            .into_entity()
-            .map(Json)
+            .into_unit()
+            .map(|_| Json("OK".to_string()))
+    }
+
+    /**
+     * Entities created, updated or deleted after `since`, which is a Raft log index or an RFC 3339
+     * timestamp, poll again with `nextSince` to get the following changes.
+     */
+    #[oai(path = "/feed", method = "get", tag = "ApiTags::Entity")]
+    async fn get_feed(
+        &self,
+        data: Data<&RaftRegistryApp>,
+        since: Query<Option<String>>,
+        size: Query<Option<usize>>,
+    ) -> poem::Result<Json<FeedPage>> {
+        // The feed covers all projects
+        let since: FeedPosition = match since.0 {
+            Some(s) => s.parse()?,
+            None => Default::default(),
+        };
+        Ok(Json(data.0.change_feed(since, size.0).await))
     }
 
     #[oai(
-        path = "/projects/:project/anchors/:anchor/features",
+        path = "/projects/:project/materializations",
         method = "get",
-        tag = "ApiTags::AnchorFeature"
+        tag = "ApiTags::Materialization"
     )]
-    async fn get_anchor_features(
+    async fn get_project_materializations(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        anchor: Path<String>,
         keyword: Query<Option<String>>,
         size: Query<Option<usize>>,
         offset: Query<Option<usize>>,
     ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetAnchorFeatures {
+                FeathrApiRequest::GetProjectMaterializationRecords {
                     project_id_or_name: project.0,
-                    anchor_id_or_name: anchor.0,
                     keyword: keyword.0,
                     size: size.0,
                     offset: offset.0,
@@ -699,22 +1726,22 @@ impl FeathrApiV2 {
     }
 
     #[oai(
-        path = "/projects/:project/anchors/:anchor/features",
+        path = "/projects/:project/materializations",
         method = "post",
-        tag = "ApiTags::AnchorFeature"
+        tag = "ApiTags::Materialization"
     )]
-    async fn new_anchor_feature(
+    async fn new_materialization(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
         project: Path<String>,
-        anchor: Path<String>,
-        def: Json<AnchorFeatureDef>,
+        def: Json<MaterializationRecordDef>,
     ) -> poem::Result<Json<CreationResponse>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Write)
-            .await?;
+        let key = data
+            .0
+            .idempotency_key(idempotency_key.0, credential.0, &(&project.0, &def.0))?;
         let mut definition = def.0;
         if definition.id.is_empty() {
             definition.id = Uuid::new_v4().to_string();
@@ -725,11 +1752,11 @@ impl FeathrApiV2 {
         data.0
             .request(
                 None,
-                FeathrApiRequest::CreateAnchorFeature {
+                FeathrApiRequest::CreateMaterializationRecord {
                     project_id_or_name: project.0,
-                    anchor_id_or_name: anchor.0,
                     definition,
-                },
+                }
+                .idempotent(key),
             )
             .await
             .into_uuid_and_version()
@@ -737,60 +1764,50 @@ impl FeathrApiV2 {
     }
 
     #[oai(
-        path = "/projects/:project/anchors/:anchor/features/:feature",
+        path = "/features/:feature/datasets",
         method = "get",
-        tag = "ApiTags::AnchorFeature"
+        tag = "ApiTags::Dataset"
     )]
-    async fn get_project_anchor_feature(
+    async fn get_feature_datasets(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        project: Path<String>,
-        anchor: Path<String>,
         feature: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+    ) -> poem::Result<Json<Entities>> {
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetAnchorFeature {
-                    project_id_or_name: project.0,
-                    anchor_id_or_name: anchor.0,
+                FeathrApiRequest::GetFeatureDatasets {
                     id_or_name: feature.0,
                 },
             )
             .await
-            .into_entity()
+            .into_entities()
             .map(Json)
     }
 
     #[oai(
-        path = "/projects/:project/anchors/:anchor/features/:feature/versions",
+        path = "/projects/:project/datasets",
         method = "get",
-        tag = "ApiTags::AnchorFeature"
+        tag = "ApiTags::Dataset"
     )]
-    async fn get_project_anchor_feature_versions(
+    async fn get_project_datasets(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
         project: Path<String>,
-        anchor: Path<String>,
-        feature: Path<String>,
+        keyword: Query<Option<String>>,
+        size: Query<Option<usize>>,
+        offset: Query<Option<usize>>,
     ) -> poem::Result<Json<Entities>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
         data.0
             .request(
                 opt_seq.0,
-                FeathrApiRequest::GetAnchorFeatureVersions {
+                FeathrApiRequest::GetProjectDatasets {
                     project_id_or_name: project.0,
-                    anchor_id_or_name: anchor.0,
-                    id_or_name: feature.0,
+                    keyword: keyword.0,
+                    size: size.0,
+                    offset: offset.0,
                 },
             )
             .await
@@ -799,125 +1816,49 @@ impl FeathrApiV2 {
     }
 
     #[oai(
-        path = "/projects/:project/anchors/:anchor/features/:feature/versions/:version",
-        method = "get",
-        tag = "ApiTags::AnchorFeature"
+        path = "/projects/:project/datasets",
+        method = "post",
+        tag = "ApiTags::Dataset"
     )]
-    async fn get_project_anchor_feature_version(
+    async fn new_dataset(
         &self,
         credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
-        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
+        #[oai(name = "x-registry-requestor")] creator: Header<Option<String>>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
         project: Path<String>,
-        anchor: Path<String>,
-        feature: Path<String>,
-        version: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&project), Permission::Read)
-            .await?;
+        def: Json<DatasetDef>,
+    ) -> poem::Result<Json<CreationResponse>> {
+        let key = data
+            .0
+            .idempotency_key(idempotency_key.0, credential.0, &(&project.0, &def.0))?;
+        let mut definition = def.0;
+        if definition.id.is_empty() {
+            definition.id = Uuid::new_v4().to_string();
+        }
+        if definition.created_by.is_empty() {
+            definition.created_by = creator.0.unwrap_or_default();
+        }
         data.0
             .request(
-                opt_seq.0,
-                FeathrApiRequest::GetAnchorFeatureVersion {
+                None,
+                FeathrApiRequest::CreateDataset {
                     project_id_or_name: project.0,
-                    anchor_id_or_name: anchor.0,
-                    id_or_name: feature.0,
-                    version: parse_version(version.0)?,
-                },
-            )
-            .await
-            .into_entity()
-            .map(Json)
-    }
-
-    #[oai(path = "/features/:feature", method = "get", tag = "ApiTags::Feature")]
-    async fn get_feature(
-        &self,
-        credential: Data<&Credential>,
-        data: Data<&RaftRegistryApp>,
-        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        feature: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
-            .await?;
-        data.0
-            .request(
-                opt_seq.0,
-                FeathrApiRequest::GetFeature {
-                    id_or_name: feature.0,
-                },
-            )
-            .await
-            .into_entity()
-            .map(Json)
-    }
-
-    #[oai(
-        path = "/features/:feature/lineage",
-        method = "get",
-        tag = "ApiTags::Feature"
-    )]
-    async fn get_feature_lineage(
-        &self,
-        credential: Data<&Credential>,
-        data: Data<&RaftRegistryApp>,
-        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        feature: Path<String>,
-    ) -> poem::Result<Json<EntityLineage>> {
-        data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
-            .await?;
-        data.0
-            .request(
-                opt_seq.0,
-                FeathrApiRequest::GetFeatureLineage {
-                    id_or_name: feature.0,
-                },
-            )
-            .await
-            .into_lineage()
-            .map(Json)
-    }
-
-    #[oai(
-        path = "/features/:feature/project",
-        method = "get",
-        tag = "ApiTags::Feature"
-    )]
-    async fn get_feature_project(
-        &self,
-        credential: Data<&Credential>,
-        data: Data<&RaftRegistryApp>,
-        #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
-        feature: Path<String>,
-    ) -> poem::Result<Json<Entity>> {
-        data.0
-            .check_permission(credential.0, Some(&feature), Permission::Read)
-            .await?;
-        data.0
-            .request(
-                opt_seq.0,
-                FeathrApiRequest::GetEntityProject {
-                    id_or_name: feature.0,
-                },
+                    definition,
+                }
+                .idempotent(key),
             )
             .await
-            .into_entity()
-            .map(Json)
+            .into_uuid_and_version()
+            .map(|v| Json(v.into()))
     }
 
     #[oai(path = "/userroles", method = "get", tag = "ApiTags::Rbac")]
     async fn get_user_roles(
         &self,
-        credential: Data<&Credential>,
         data: Data<&RaftRegistryApp>,
         #[oai(name = "x-registry-opt-seq")] opt_seq: Header<Option<u64>>,
     ) -> poem::Result<Json<Vec<RbacResponse>>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Admin)
-            .await?;
         data.0
             .request(opt_seq.0, FeathrApiRequest::GetUserRoles)
             .await
@@ -925,6 +1866,18 @@ impl FeathrApiV2 {
             .map(Json)
     }
 
+    /// Server-sent events of permission changes applied after subscribing, Global Admins get all
+    /// changes in the tenant, other users only get the changes to their own permissions
+    #[oai(path = "/userroles/events", method = "get", tag = "ApiTags::Rbac")]
+    async fn get_user_role_events(
+        &self,
+        credential: Data<&Credential>,
+        data: Data<&RaftRegistryApp>,
+    ) -> EventStream<BoxStream<'static, RbacEvent>> {
+        EventStream::new(data.0.rbac_events(credential.0).await)
+            .keep_alive(Duration::from_secs(RBAC_EVENTS_KEEP_ALIVE_SECS))
+    }
+
     #[oai(
         path = "/users/:user/userroles/add",
         method = "post",
@@ -940,9 +1893,6 @@ impl FeathrApiV2 {
         role: Query<String>,
         reason: Query<String>,
     ) -> poem::Result<Json<String>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Admin)
-            .await?;
         let resp = data
             .0
             .request(
@@ -990,9 +1940,6 @@ impl FeathrApiV2 {
         role: Query<String>,
         reason: Query<String>,
     ) -> poem::Result<Json<String>> {
-        data.0
-            .check_permission(credential.0, Some("global"), Permission::Admin)
-            .await?;
         let resp = data
             .0
             .request(
@@ -1026,6 +1973,90 @@ impl FeathrApiV2 {
     }
 }
 
+/**
+ * Lineage of the project, shared by the JSON endpoint and the graph exports
+ */
+async fn project_lineage(
+    app: &RaftRegistryApp,
+    credential: &Credential,
+    opt_seq: Option<u64>,
+    project: String,
+    release: Option<String>,
+) -> poem::Result<EntityLineage> {
+    app.request(
+        opt_seq,
+        FeathrApiRequest::GetProjectLineage {
+            id_or_name: project,
+            release,
+            credential: Some(credential.clone()),
+        },
+    )
+    .await
+    .into_lineage()
+}
+
+/**
+ * Grant project admin permission to the creator of the project.
+ * Tenants have no Global Admins, the grant in a tenant is issued by the registry so the project
+ * can be administrated, the default tenant keeps the creator as the requestor.
+ */
+pub(super) async fn grant_project_admin(
+    app: &RaftRegistryApp,
+    credential: &Credential,
+    uuid: &Uuid,
+) -> poem::Result<()> {
+    let ret = app
+        .request(
+            None,
+            FeathrApiRequest::AddUserRole {
+                project_id_or_name: uuid.to_string(),
+                user: credential.clone(),
+                role: Permission::Admin,
+                requestor: if current_tenant().is_empty() {
+                    credential.clone()
+                } else {
+                    Credential::RbacDisabled
+                },
+                reason: "Created project".to_string(),
+            },
+        )
+        .await;
+    match ret {
+        registry_api::FeathrApiResponse::Error(e) => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/**
+ * Related entities may belong to other projects, drop the ones the caller can't read before paging
+ */
+async fn readable_page(
+    app: &RaftRegistryApp,
+    credential: &Credential,
+    entities: Entities,
+    size: Option<usize>,
+    offset: Option<usize>,
+) -> Entities {
+    let mut readable = vec![];
+    for e in entities.entities {
+        if app
+            .check_permission(credential, Some(&e.guid), Permission::Read)
+            .await
+            .is_ok()
+        {
+            readable.push(e);
+        }
+    }
+    Entities {
+        entities: readable
+            .into_iter()
+            .skip(offset.unwrap_or(0))
+            .take(size.unwrap_or(100))
+            .collect(),
+        next_cursor: None,
+    }
+}
+
 fn parse_version<T>(v: T) -> Result<Option<u64>, ApiError>
 where
     T: AsRef<str>,
@@ -1038,9 +2069,30 @@ where
     })?))
 }
 
+/**
+ * Tag filters in the format of `key` or `key=value`
+ */
+fn parse_tag_filters(tags: &[String]) -> Result<HashMap<String, Option<String>>, ApiError> {
+    tags.iter()
+        .map(|tag| {
+            let (key, value) = match tag.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim().to_string())),
+                None => (tag.trim(), None),
+            };
+            if key.is_empty() {
+                return Err(ApiError::BadRequest(format!(
+                    "Invalid tag filter `{}`",
+                    tag
+                )));
+            }
+            Ok((key.to_string(), value))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_version;
+    use super::{parse_tag_filters, parse_version};
 
     #[test]
     fn test_parse_version() {
@@ -1051,4 +2103,12 @@ mod tests {
         assert_eq!(parse_version("1").unwrap(), Some(1));
         assert_eq!(parse_version("42").unwrap(), Some(42));
     }
+
+    #[test]
+    fn test_parse_tag_filters() {
+        let tags = parse_tag_filters(&["team".to_string(), "pii = true".to_string()]).unwrap();
+        assert_eq!(tags["team"], None);
+        assert_eq!(tags["pii"].as_deref(), Some("true"));
+        assert!(parse_tag_filters(&["=x".to_string()]).is_err());
+    }
 }
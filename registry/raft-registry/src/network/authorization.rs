@@ -0,0 +1,330 @@
+use async_trait::async_trait;
+use common_utils::StringError;
+use poem::{
+    error::{BadRequest, Forbidden, NotFoundError},
+    http::Method,
+    Endpoint, Middleware, Request,
+};
+use registry_provider::{Credential, Permission};
+
+use crate::RaftRegistryApp;
+
+/**
+ * Resource a route requires the permission on
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resource {
+    /// Any identity, the handler filters the result or checks further permissions itself
+    Any,
+    /// The whole registry
+    Global,
+    /// Entity named by the path parameter, e.g. `project` or `feature`
+    Path(&'static str),
+    /// Entity named by the query parameter
+    Query(&'static str),
+    /// Anchors or features named by the path parameters under the `project` path parameter, grants
+    /// on the project and the anchor are inherited
+    Child(&'static [&'static str]),
+}
+
+/**
+ * Permission required by a route, `path` is in the OpenAPI format, e.g. `/projects/{project}`
+ */
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub method: Method,
+    pub path: &'static str,
+    pub resource: Resource,
+    pub permission: Permission,
+}
+
+impl Rule {
+    pub const fn new(
+        method: Method,
+        path: &'static str,
+        resource: Resource,
+        permission: Permission,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            resource,
+            permission,
+        }
+    }
+
+    /**
+     * Path parameters if the path matches the rule, literal segments take precedence so the number
+     * of them is returned as well
+     */
+    fn matches<'a>(&self, path: &'a str) -> Option<(usize, Vec<(&'static str, &'a str)>)> {
+        let mut literals = 0;
+        let mut params = vec![];
+        let mut segments = path.trim_end_matches('/').split('/');
+        for pattern in self.path.trim_end_matches('/').split('/') {
+            let segment = segments.next()?;
+            match pattern.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+                Some(name) if !segment.is_empty() => params.push((name, segment)),
+                Some(_) => return None,
+                None if pattern == segment => literals += 1,
+                None => return None,
+            }
+        }
+        match segments.next() {
+            Some(_) => None,
+            None => Some((literals, params)),
+        }
+    }
+}
+
+/**
+ * Table of rules, one per line, e.g. `GET "/projects/{project}" => Path("project"), Read;`
+ */
+macro_rules! authorization_rules {
+    ($($method:ident $path:literal => $resource:ident $(($($arg:tt)*))?, $permission:ident;)*) => {
+        &[$(
+            $crate::network::Rule::new(
+                poem::http::Method::$method,
+                $path,
+                $crate::network::Resource::$resource$(($($arg)*))?,
+                registry_provider::Permission::$permission,
+            ),
+        )*]
+    };
+}
+pub(crate) use authorization_rules;
+
+/**
+ * Checks the permission a route requires before the request reaches the handler, so handlers don't
+ * need to check it themselves.
+ * Requests to routes without a rule are rejected with `404 Not Found`, a route added without a rule
+ * is unreachable rather than unprotected.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Authorization {
+    rules: &'static [Rule],
+}
+
+impl Authorization {
+    pub fn new(rules: &'static [Rule]) -> Self {
+        Self { rules }
+    }
+
+    /**
+     * The rule with the most literal segments matching the request, with the path parameters
+     */
+    fn find<'a>(
+        &self,
+        method: &Method,
+        path: &'a str,
+    ) -> Option<(&'static Rule, Vec<(&'static str, &'a str)>)> {
+        self.rules
+            .iter()
+            .filter(|r| r.method == *method)
+            .filter_map(|r| {
+                r.matches(path)
+                    .map(|(literals, params)| (literals, r, params))
+            })
+            .max_by_key(|(literals, _, _)| *literals)
+            .map(|(_, r, params)| (r, params))
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Authorization {
+    type Output = AuthorizationImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        AuthorizationImpl { ep, auth: *self }
+    }
+}
+
+pub struct AuthorizationImpl<E> {
+    ep: E,
+    auth: Authorization,
+}
+
+#[async_trait]
+impl<E: Endpoint> Endpoint for AuthorizationImpl<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let (rule, params) = self
+            .auth
+            .find(req.method(), req.uri().path())
+            .ok_or(NotFoundError)?;
+        let path_param = |name: &str| {
+            params
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| percent_decode(v))
+                .unwrap_or_default()
+        };
+        // Resolved before awaiting, the request must not be borrowed across the checks
+        let (resource, children) = match rule.resource {
+            Resource::Any => (None, None),
+            Resource::Global => (Some("global".to_string()), None),
+            Resource::Path(name) => (Some(path_param(name)), None),
+            // A missing parameter would check no resource at all and let any identity through
+            Resource::Query(name) => (
+                Some(
+                    query_param(req.uri().query().unwrap_or_default(), name).ok_or_else(|| {
+                        BadRequest(StringError::new(format!(
+                            "Missing query parameter `{}`",
+                            name
+                        )))
+                    })?,
+                ),
+                None,
+            ),
+            Resource::Child(names) => (
+                Some(path_param("project")),
+                Some(names.iter().map(|n| path_param(n)).collect::<Vec<_>>()),
+            ),
+        };
+        let credential = req
+            .extensions()
+            .get::<Credential>()
+            .cloned()
+            .ok_or_else(|| Forbidden(StringError::new("Missing token")))?;
+        let app = req
+            .data::<RaftRegistryApp>()
+            .cloned()
+            .expect("Registry app is attached to the routes");
+        match (resource, children) {
+            (Some(project), Some(children)) => {
+                let children: Vec<&str> = children.iter().map(|c| c.as_str()).collect();
+                app.check_child_permission(&credential, &project, &children, rule.permission)
+                    .await?
+            }
+            (resource, _) => {
+                app.check_permission(&credential, resource.as_deref(), rule.permission)
+                    .await?
+            }
+        }
+        self.ep.call(req).await
+    }
+}
+
+/**
+ * Decode `%XX` escapes the same way the path parameters are decoded for the handlers
+ */
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i..i + 3) {
+            Some([b'%', h, l]) => std::str::from_utf8(&[*h, *l])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/**
+ * First value of the parameter in the query string, `None` if it's missing
+ */
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|kv| kv.split_once('=').or(Some((kv, ""))))
+        .find(|(k, _)| percent_decode(k) == name)
+        .map(|(_, v)| percent_decode(&v.replace('+', " ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use poem_openapi::OpenApiService;
+
+    use super::*;
+    use crate::{FeathrApiV1, FeathrApiV2};
+
+    /**
+     * Every route of the API has a rule and every rule is for an existing route
+     */
+    fn check_coverage(spec: String, rules: &[Rule]) {
+        let spec: serde_json::Value = serde_json::from_str(&spec).unwrap();
+        let routes: HashSet<(String, String)> = spec["paths"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .flat_map(|(path, ops)| {
+                ops.as_object()
+                    .unwrap()
+                    .keys()
+                    .map(|m| (m.to_uppercase(), path.to_owned()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let covered: HashSet<(String, String)> = rules
+            .iter()
+            .map(|r| (r.method.to_string(), r.path.to_string()))
+            .collect();
+        assert_eq!(
+            routes.difference(&covered).collect::<Vec<_>>(),
+            Vec::<&(String, String)>::new(),
+            "Routes without authorization rules"
+        );
+        assert_eq!(
+            covered.difference(&routes).collect::<Vec<_>>(),
+            Vec::<&(String, String)>::new(),
+            "Rules without routes"
+        );
+    }
+
+    #[test]
+    fn test_rule_coverage() {
+        check_coverage(
+            OpenApiService::new(FeathrApiV1, "", "").spec(),
+            FeathrApiV1::AUTHORIZATION_RULES,
+        );
+        check_coverage(
+            OpenApiService::new(FeathrApiV2, "", "").spec(),
+            FeathrApiV2::AUTHORIZATION_RULES,
+        );
+    }
+
+    #[test]
+    fn test_rule_matching() {
+        let auth = Authorization::new(FeathrApiV2::AUTHORIZATION_RULES);
+        let (rule, params) = auth
+            .find(&Method::GET, "/projects/p1/anchors/a1/features/f1")
+            .unwrap();
+        assert_eq!(rule.resource, Resource::Child(&["anchor", "feature"]));
+        assert_eq!(
+            params,
+            vec![("project", "p1"), ("anchor", "a1"), ("feature", "f1")]
+        );
+
+        let (rule, _) = auth.find(&Method::POST, "/projects/from-template").unwrap();
+        assert_eq!(rule.resource, Resource::Global);
+        let (rule, _) = auth.find(&Method::POST, "/entities:batchGet").unwrap();
+        assert_eq!(rule.resource, Resource::Any);
+        let (rule, _) = auth.find(&Method::GET, "/projects/p1/").unwrap();
+        assert_eq!(rule.resource, Resource::Path("project"));
+        assert!(auth.find(&Method::GET, "/projects/p1/unknown").is_none());
+        assert!(auth.find(&Method::PATCH, "/projects/p1").is_none());
+        assert!(auth.find(&Method::GET, "/features//lineage").is_none());
+
+        assert_eq!(percent_decode("a%20b%2Fc%zz"), "a b/c%zz");
+        assert_eq!(
+            query_param("role=admin&project=my%20project", "project").as_deref(),
+            Some("my project")
+        );
+        assert_eq!(query_param("role=admin", "project"), None);
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use poem::{
+    http::{header, Method, StatusCode},
+    Endpoint, IntoResponse, Middleware, Request, Response,
+};
+
+use crate::{rbac_middleware::current_tenant, RegistryStore};
+
+/**
+ * Adds weak ETags to successful GET responses and answers `304 Not Modified` if the client already
+ * has the same version.
+ * Requests under `/projects/{project}` are tagged with the log index of the last change in the
+ * project or its inputs from other projects, so writes to other projects don't invalidate them,
+ * and with the log index of the last permission change, as the content is filtered by the
+ * permissions of the caller. Other requests are tagged with the last applied log index.
+ * The endpoint is always called so permissions are still checked, only the body is saved.
+ */
+pub struct ConditionalGet {
+    store: Arc<RegistryStore>,
+}
+
+impl ConditionalGet {
+    pub fn new(store: Arc<RegistryStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ConditionalGet {
+    type Output = ConditionalGetImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ConditionalGetImpl {
+            ep,
+            store: self.store.clone(),
+        }
+    }
+}
+
+pub struct ConditionalGetImpl<E> {
+    ep: E,
+    store: Arc<RegistryStore>,
+}
+
+impl<E> ConditionalGetImpl<E> {
+    /**
+     * Read before the request is handled, a write applied in between makes the tag older than the
+     * content, which only costs the client another full response
+     */
+    async fn etag(&self, project: Option<&str>) -> Option<String> {
+        let state_machine = self.store.state_machine.read().await;
+        let tenant = current_tenant();
+        let index = project
+            .and_then(|p| state_machine.feed.project_index(&tenant, p))
+            .or_else(|| state_machine.last_applied_log.map(|l| l.index))?;
+        let permission = project.and(state_machine.feed.permission_index(&tenant));
+        Some(match permission {
+            Some(permission) => format!("W/\"{}.{}\"", index, permission),
+            None => format!("W/\"{}\"", index),
+        })
+    }
+}
+
+#[async_trait]
+impl<E: Endpoint> Endpoint for ConditionalGetImpl<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        if req.method() != Method::GET {
+            return self.ep.call(req).await.map(IntoResponse::into_response);
+        }
+        let etag = self.etag(project_of(req.uri().path())).await;
+        let matched = match (&etag, req.headers().get(header::IF_NONE_MATCH)) {
+            (Some(etag), Some(v)) => v.to_str().map_or(false, |v| matches(v, etag)),
+            _ => false,
+        };
+        let resp = self.ep.call(req).await?.into_response();
+        match etag {
+            Some(etag) if resp.status() == StatusCode::OK => {
+                if matched {
+                    let mut not_modified = Response::builder()
+                        .status(StatusCode::NOT_MODIFIED)
+                        .finish();
+                    // Keep other headers such as the opt seq, but not the ones describing the body
+                    for (name, value) in resp.headers() {
+                        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+                            not_modified.headers_mut().append(name, value.clone());
+                        }
+                    }
+                    Ok(not_modified.with_header(header::ETAG, etag).into_response())
+                } else {
+                    Ok(resp.with_header(header::ETAG, etag).into_response())
+                }
+            }
+            _ => Ok(resp),
+        }
+    }
+}
+
+/**
+ * Project name or id in paths like `/v2/projects/{project}/lineage`
+ */
+fn project_of(path: &str) -> Option<&str> {
+    let mut segments = path.split('/').skip_while(|s| *s != "projects").skip(1);
+    segments.next().filter(|s| !s.is_empty())
+}
+
+/**
+ * Weak comparison against a comma separated list of tags or `*`
+ */
+fn matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |t: &str| t.trim().trim_start_matches("W/").to_string();
+    if_none_match
+        .split(',')
+        .any(|t| t.trim() == "*" || opaque(t) == opaque(etag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etag_matching() {
+        assert_eq!(
+            project_of("/v2/projects/project1/lineage"),
+            Some("project1")
+        );
+        assert_eq!(project_of("/v1/projects/project1"), Some("project1"));
+        assert_eq!(project_of("/v2/projects"), None);
+        assert_eq!(project_of("/v2/projects/"), None);
+        assert_eq!(project_of("/v2/features/f1/project"), None);
+
+        assert!(matches("W/\"42\"", "W/\"42\""));
+        assert!(matches("\"41\", \"42\"", "W/\"42\""));
+        assert!(matches("*", "W/\"42\""));
+        assert!(!matches("W/\"41\"", "W/\"42\""));
+    }
+}
@@ -1,5 +1,13 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+use auth::RevokedToken;
+use chrono::{DateTime, Duration, Utc};
+use flate2::{write::GzEncoder, Compression};
 
+use log::info;
 use openraft::{
     error::{CheckIsLeaderError, Infallible},
     raft::ClientWriteRequest,
@@ -7,26 +15,132 @@ use openraft::{
 };
 use poem::{
     get, handler, post,
-    web::{Data, Json, TypedHeader},
-    IntoResponse, Route,
+    web::{Data, Json, Query},
+    IntoResponse, Response, Route,
 };
 use poem_openapi::payload::PlainText;
 use registry_api::{ApiError, FeathrApiProvider, FeathrApiRequest, FeathrApiResponse};
+use registry_provider::LoadPolicy;
 use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    gc::gc_once, replica::replica_node, scoped, ManagementIdentity, ManagementScope,
+    ManagementTokenClaims, RaftRegistryApp, RegistryNodeId, RegistryTypeConfig,
+    RevokedManagementToken, DEFAULT_GC_RETENTION_DAYS, DEFAULT_MANAGEMENT_TOKEN_TTL_HOURS,
+    MAX_MANAGEMENT_TOKEN_TTL_HOURS, SNAPSHOT_ID_HEADER_NAME,
+};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AddLearnerParams {
+    /// The learner is a read replica and will never be promoted
+    #[serde(default)]
+    replica: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RevokeTokenParams {
+    /// Only revoke on this node, set when the revocation is propagated from another node
+    #[serde(default)]
+    local: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SnapshotParams {
+    /// Build a new snapshot instead of sending the latest one
+    #[serde(default)]
+    fresh: bool,
+}
 
-use crate::{ManagementCode, RaftRegistryApp, RegistryNodeId, RegistryTypeConfig};
+#[derive(Debug, Deserialize)]
+pub struct CollectGarbageParams {
+    /// Tombstones of entities deleted within this many days are kept
+    #[serde(default = "default_retention_days")]
+    retention_days: i64,
+    /// Report the tombstones to be removed without removing them
+    #[serde(default)]
+    dry_run: bool,
+}
+
+fn default_retention_days() -> i64 {
+    DEFAULT_GC_RETENTION_DAYS
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoadDbParams {
+    /// How entities conflicting with the existing content are handled
+    #[serde(default)]
+    policy: LoadPolicy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevokeTokenRequest {
+    /// The token to revoke
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Hex encoded SHA-256 of the token, used when the token itself is not available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueManagementTokenRequest {
+    /// Who the token is issued to, logged with every management operation
+    name: String,
+    scopes: BTreeSet<ManagementScope>,
+    #[serde(default = "default_token_ttl_hours")]
+    ttl_hours: i64,
+}
+
+fn default_token_ttl_hours() -> i64 {
+    DEFAULT_MANAGEMENT_TOKEN_TTL_HOURS
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RotateManagementTokenRequest {
+    /// The token to replace, it's revoked once the new one is issued
+    token: String,
+    /// Lifetime of the new token, defaults to the lifetime of the old one
+    #[serde(default)]
+    ttl_hours: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedManagementToken {
+    pub token: String,
+    #[serde(flatten)]
+    pub claims: ManagementTokenClaims,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevokeManagementTokenRequest {
+    /// The token to revoke
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Id of the token, used when the token itself is not available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+}
 
 #[handler]
 pub async fn add_learner(
     app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
+    params: Query<AddLearnerParams>,
     req: Json<(RegistryNodeId, String)>,
 ) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
     let node_id = req.0 .0;
-    let node = Node {
-        addr: req.0 .1.clone(),
-        ..Default::default()
+    let node = if params.replica {
+        replica_node(req.0 .1.clone())
+    } else {
+        Node {
+            addr: req.0 .1.clone(),
+            ..Default::default()
+        }
     };
     let res = app.raft.add_learner(node_id, Some(node), true).await;
     Ok(Json(res))
@@ -36,21 +150,15 @@ pub async fn add_learner(
 #[handler]
 pub async fn change_membership(
     app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
     req: Json<BTreeSet<RegistryNodeId>>,
 ) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
     let res = app.raft.change_membership(req.0, true, false).await;
     Ok(Json(res))
 }
 
 /// Initialize a single-node cluster.
 #[handler]
-pub async fn init(
-    app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
-) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
+pub async fn init(app: Data<&RaftRegistryApp>) -> poem::Result<impl IntoResponse> {
     let mut nodes = BTreeMap::new();
     nodes.insert(
         app.id,
@@ -65,28 +173,177 @@ pub async fn init(
 
 /// Get the latest metrics of the cluster
 #[handler]
-pub async fn metrics(
-    app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
-) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
+pub async fn metrics(app: Data<&RaftRegistryApp>) -> poem::Result<impl IntoResponse> {
     let metrics = app.raft.metrics().borrow().clone();
 
     let res: Result<RaftMetrics<RegistryTypeConfig>, Infallible> = Ok(metrics);
     Ok(Json(res))
 }
 
+/// Get the health of each node tracked by the membership policy, only the leader has the full report
+#[handler]
+pub async fn membership(app: Data<&RaftRegistryApp>) -> poem::Result<impl IntoResponse> {
+    Ok(Json(app.membership.report().await))
+}
+
+/// Get the hit rate and the size of the validated token cache on this node
+#[handler]
+pub async fn token_cache() -> poem::Result<impl IntoResponse> {
+    Ok(Json(auth::token_cache_stats()))
+}
+
+/// List the hashes of revoked tokens that have not expired yet
+#[handler]
+pub async fn revoked_tokens() -> poem::Result<impl IntoResponse> {
+    Ok(Json(auth::revoked_tokens()))
+}
+
+/**
+ * Reject the token until it expires, the revocation is replicated to all nodes in the cluster
+ * through the Raft log
+ */
+#[handler]
+pub async fn revoke_token(
+    app: Data<&RaftRegistryApp>,
+    params: Query<RevokeTokenParams>,
+    req: Json<RevokeTokenRequest>,
+) -> poem::Result<impl IntoResponse> {
+    let revoked = match (&req.0.token, &req.0.hash) {
+        (Some(token), _) => auth::revoke_token(token)
+            .await
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?,
+        (None, Some(hash)) => auth::revoke_token_hash(hash, req.0.expires),
+        (None, None) => {
+            return Err(ApiError::BadRequest(
+                "Either `token` or `hash` must be provided".to_string(),
+            ))?
+        }
+    };
+    if !params.local {
+        app.request(
+            None,
+            FeathrApiRequest::RevokeToken {
+                hash: revoked.hash.clone(),
+                expires: revoked.expires,
+            },
+        )
+        .await
+        .into_unit()?;
+    }
+    let res: Result<RevokedToken, Infallible> = Ok(revoked);
+    Ok(Json(res))
+}
+
+/**
+ * Issue a management token with the scopes, only the bootstrap credential can issue tokens
+ */
+#[handler]
+pub async fn issue_management_token(
+    app: Data<&RaftRegistryApp>,
+    identity: Data<&ManagementIdentity>,
+    req: Json<IssueManagementTokenRequest>,
+) -> poem::Result<impl IntoResponse> {
+    let (token, claims) = app.management_tokens.issue(
+        &req.0.name,
+        req.0.scopes.clone(),
+        Duration::hours(req.0.ttl_hours),
+    )?;
+    info!(
+        "Management token '{}' ({}) with scopes {:?} issued by {}, expires at {}",
+        claims.name, claims.id, claims.scopes, identity.0, claims.expires
+    );
+    let res: Result<_, Infallible> = Ok(IssuedManagementToken { token, claims });
+    Ok(Json(res))
+}
+
+/**
+ * Issue a new token with the same name and scopes, and revoke the old one on all nodes
+ */
+#[handler]
+pub async fn rotate_management_token(
+    app: Data<&RaftRegistryApp>,
+    identity: Data<&ManagementIdentity>,
+    req: Json<RotateManagementTokenRequest>,
+) -> poem::Result<impl IntoResponse> {
+    let old = match app.management_tokens.verify(Some(&req.0.token))? {
+        ManagementIdentity::Token(claims) => claims,
+        _ => Err(ApiError::BadRequest(
+            "Only management tokens can be rotated".to_string(),
+        ))?,
+    };
+    let ttl = req
+        .0
+        .ttl_hours
+        .map(Duration::hours)
+        .unwrap_or_else(|| old.expires - old.issued_at);
+    let (token, claims) = app
+        .management_tokens
+        .issue(&old.name, old.scopes.clone(), ttl)?;
+    let revoked = app.management_tokens.revoke(old.id, old.expires);
+    app.propagate_management_revocation(&revoked).await;
+    info!(
+        "Management token '{}' ({}) rotated into {} by {}, expires at {}",
+        old.name, old.id, claims.id, identity.0, claims.expires
+    );
+    let res: Result<_, Infallible> = Ok(IssuedManagementToken { token, claims });
+    Ok(Json(res))
+}
+
+/**
+ * Reject the management token until it expires, the revocation is propagated to all other nodes in
+ * the cluster
+ */
+#[handler]
+pub async fn revoke_management_token(
+    app: Data<&RaftRegistryApp>,
+    identity: Data<&ManagementIdentity>,
+    params: Query<RevokeTokenParams>,
+    req: Json<RevokeManagementTokenRequest>,
+) -> poem::Result<impl IntoResponse> {
+    let (id, expires) = match (&req.0.token, req.0.id) {
+        (Some(token), _) => {
+            let claims = app
+                .management_tokens
+                .decode(token)
+                .ok_or_else(|| ApiError::BadRequest("Invalid management token".to_string()))?;
+            (claims.id, claims.expires)
+        }
+        // The expiration is unknown without the token, the revocation is kept for the max lifetime
+        (None, Some(id)) => (
+            id,
+            req.0
+                .expires
+                .unwrap_or_else(|| Utc::now() + Duration::hours(MAX_MANAGEMENT_TOKEN_TTL_HOURS)),
+        ),
+        (None, None) => Err(ApiError::BadRequest(
+            "Either `token` or `id` must be provided".to_string(),
+        ))?,
+    };
+    let revoked = app.management_tokens.revoke(id, expires);
+    info!("Management token {} revoked by {}", id, identity.0);
+    if !params.local {
+        app.propagate_management_revocation(&revoked).await;
+    }
+    let res: Result<RevokedManagementToken, Infallible> = Ok(revoked);
+    Ok(Json(res))
+}
+
+/// List the ids of revoked management tokens that have not expired yet
+#[handler]
+pub async fn revoked_management_tokens(
+    app: Data<&RaftRegistryApp>,
+) -> poem::Result<impl IntoResponse> {
+    Ok(Json(app.management_tokens.revoked()))
+}
+
 /**
  * Handle request locally, may get stale response
  */
 #[handler]
 pub async fn handle_request(
     app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
     req: Json<FeathrApiRequest>,
 ) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
-
     if req.0.is_writing_request() {
         return Err(ApiError::BadRequest(
             "Updating requests must be submitted to the Raft leader".to_string(),
@@ -111,11 +368,8 @@ pub async fn handle_request(
 #[handler]
 pub async fn handle_leader_request(
     app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
     req: Json<FeathrApiRequest>,
 ) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
-
     let ret = app.raft.is_leader().await;
     match ret {
         Ok(_) => {
@@ -128,13 +382,16 @@ pub async fn handle_leader_request(
                     .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?
                     .data
             } else {
-                app.store
-                    .state_machine
-                    .write()
-                    .await
-                    .registry
-                    .request(req.0)
-                    .await
+                let mut state_machine = app.store.state_machine.write().await;
+                // Forwarded reads are consistent reads, searches must see all committed entities
+                if req.0.is_search_request() {
+                    state_machine
+                        .registry
+                        .commit_search_index()
+                        .await
+                        .map_err(ApiError::from)?;
+                }
+                state_machine.registry.request(req.0).await
             };
             let res: Result<FeathrApiResponse, CheckIsLeaderError<RegistryNodeId>> = Ok(value);
             Ok(Json(res))
@@ -143,6 +400,96 @@ pub async fn handle_leader_request(
     }
 }
 
+/**
+ * Replace the database content with the registry data on this node
+ */
+#[handler]
+pub async fn dump_db(app: Data<&RaftRegistryApp>) -> poem::Result<impl IntoResponse> {
+    app.dump_data()
+        .await
+        .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+    Ok(PlainText("OK"))
+}
+
+/**
+ * Merge the database content into the registry and report the entities applied and skipped
+ */
+#[handler]
+pub async fn load_db(
+    app: Data<&RaftRegistryApp>,
+    params: Query<LoadDbParams>,
+) -> poem::Result<impl IntoResponse> {
+    let report = app
+        .load_data(params.policy)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+    let res: Result<_, Infallible> = Ok(report);
+    Ok(Json(res))
+}
+
+/**
+ * Permanently remove the tombstones of entities deleted before the retention period, and report
+ * the removed entities and the reclaimed snapshot space
+ */
+#[handler]
+pub async fn collect_garbage(
+    app: Data<&RaftRegistryApp>,
+    params: Query<CollectGarbageParams>,
+) -> poem::Result<impl IntoResponse> {
+    let summary = gc_once(&app, params.retention_days, params.dry_run)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+    let res: Result<_, Infallible> = Ok(summary);
+    Ok(Json(res))
+}
+
+/**
+ * Export all entities, edges and permissions on this node, e.g. to compare with another registry
+ */
+#[handler]
+pub async fn export(app: Data<&RaftRegistryApp>) -> poem::Result<impl IntoResponse> {
+    let content = app
+        .store
+        .state_machine
+        .read()
+        .await
+        .registry
+        .export_content()
+        .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+    let res: Result<_, Infallible> = Ok(content);
+    Ok(Json(res))
+}
+
+/**
+ * Download the gzipped snapshot of the leader, a new node installs it before joining the cluster so
+ * only the logs after the snapshot are replicated
+ */
+#[handler]
+pub async fn snapshot(
+    app: Data<&RaftRegistryApp>,
+    params: Query<SnapshotParams>,
+) -> poem::Result<impl IntoResponse> {
+    if app.raft.is_leader().await.is_err() {
+        return Err(ApiError::BadRequest(
+            "Snapshots must be downloaded from the Raft leader".to_string(),
+        ))?;
+    }
+    let (meta, data) = app
+        .store
+        .transfer_snapshot(params.fresh)
+        .await
+        .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let body = encoder
+        .write_all(&data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| ApiError::InternalError(format!("{:?}", e)))?;
+    Ok(Response::builder()
+        .header(SNAPSHOT_ID_HEADER_NAME, meta.snapshot_id)
+        .content_type("application/gzip")
+        .body(body))
+}
+
 /**
  * Check if the program is still alive
  */
@@ -170,13 +517,48 @@ pub async fn readiness(app: Data<&RaftRegistryApp>) -> poem::Result<impl IntoRes
 }
 
 pub fn management_routes(route: Route) -> Route {
+    use ManagementScope::*;
     route
-        .at("/add-learner", post(add_learner))
-        .at("/change-membership", post(change_membership))
-        .at("/init", post(init))
-        .at("/metrics", get(metrics))
-        .at("/handle-request", post(handle_request))
-        .at("/handle-leader-request", post(handle_leader_request))
+        .at("/add-learner", scoped(Cluster, post(add_learner)))
+        .at(
+            "/change-membership",
+            scoped(Cluster, post(change_membership)),
+        )
+        .at("/init", scoped(Cluster, post(init)))
+        .at("/metrics", scoped(Monitor, get(metrics)))
+        .at("/membership", scoped(Monitor, get(membership)))
+        .at("/token-cache", scoped(Monitor, get(token_cache)))
+        .at("/revoked-tokens", scoped(Monitor, get(revoked_tokens)))
+        .at("/revoke-token", scoped(Tokens, post(revoke_token)))
+        .at(
+            "/management-tokens",
+            scoped(Admin, post(issue_management_token)),
+        )
+        .at(
+            "/management-tokens/rotate",
+            scoped(Admin, post(rotate_management_token)),
+        )
+        .at(
+            "/management-tokens/revoke",
+            scoped(Admin, post(revoke_management_token)),
+        )
+        .at(
+            "/management-tokens/revoked",
+            scoped(Admin, get(revoked_management_tokens)),
+        )
+        .at("/handle-request", scoped(Internal, post(handle_request)))
+        .at(
+            "/handle-leader-request",
+            scoped(Internal, post(handle_leader_request)),
+        )
+        .at("/dump-db", scoped(Maintenance, post(dump_db)))
+        .at("/load-db", scoped(Maintenance, post(load_db)))
+        .at(
+            "/collect-garbage",
+            scoped(Maintenance, post(collect_garbage)),
+        )
+        .at("/export", scoped(Maintenance, get(export)))
+        .at("/snapshot", scoped(Maintenance, get(snapshot)))
         .at("/ping", get(liveness))
         .at("/ready", get(readiness))
 }
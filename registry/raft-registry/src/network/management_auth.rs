@@ -0,0 +1,429 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::PathBuf,
+    sync::RwLock,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
+use poem::{Endpoint, EndpointExt, Middleware, Request};
+use registry_api::ApiError;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{RaftRegistryApp, MANAGEMENT_CODE_HEADER_NAME};
+
+const TOKEN_PREFIX: &str = "mgmt.";
+pub const DEFAULT_MANAGEMENT_TOKEN_TTL_HOURS: i64 = 24;
+pub const MAX_MANAGEMENT_TOKEN_TTL_HOURS: i64 = 24 * 90;
+
+/**
+ * Group of management functions a token is allowed to call
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManagementScope {
+    /// Read-only status, `/metrics`, `/membership`, `/token-cache` and `/revoked-tokens`
+    Monitor,
+    /// Membership changes, `/init`, `/add-learner` and `/change-membership`
+    Cluster,
+    /// Data maintenance, `/dump-db`, `/load-db`, `/collect-garbage`, `/export` and `/snapshot`
+    Maintenance,
+    /// Revoking user tokens with `/revoke-token`
+    Tokens,
+    /// Traffic between nodes, i.e. Raft RPCs and forwarded requests
+    Internal,
+    /// Issuing, rotating and revoking management tokens, only the bootstrap credential has it
+    Admin,
+}
+
+impl Display for ManagementScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManagementScope::Monitor => write!(f, "monitor"),
+            ManagementScope::Cluster => write!(f, "cluster"),
+            ManagementScope::Maintenance => write!(f, "maintenance"),
+            ManagementScope::Tokens => write!(f, "tokens"),
+            ManagementScope::Internal => write!(f, "internal"),
+            ManagementScope::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/**
+ * Content of a management token, signed with the bootstrap credential
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManagementTokenClaims {
+    pub id: Uuid,
+    /// Who the token is issued to, logged with every management operation
+    pub name: String,
+    pub scopes: BTreeSet<ManagementScope>,
+    pub issued_at: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+}
+
+/**
+ * Identity of a management request, attached to the request by `ManagementAuth`
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManagementIdentity {
+    /// No management code is configured, management functions are unprotected
+    Anonymous,
+    /// The bootstrap credential, i.e. `RAFT_MANAGEMENT_CODE`, has all scopes
+    Bootstrap,
+    Token(ManagementTokenClaims),
+}
+
+impl ManagementIdentity {
+    pub fn has_scope(&self, scope: ManagementScope) -> bool {
+        match self {
+            ManagementIdentity::Anonymous | ManagementIdentity::Bootstrap => true,
+            ManagementIdentity::Token(claims) => claims.scopes.contains(&scope),
+        }
+    }
+}
+
+impl Display for ManagementIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManagementIdentity::Anonymous => write!(f, "anonymous"),
+            ManagementIdentity::Bootstrap => write!(f, "bootstrap credential"),
+            ManagementIdentity::Token(claims) => {
+                write!(f, "token '{}' ({})", claims.name, claims.id)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevokedManagementToken {
+    pub id: Uuid,
+    /// The revocation is dropped after the token expires
+    pub expires: DateTime<Utc>,
+}
+
+/**
+ * Issues and verifies management tokens.
+ * Tokens are signed with the bootstrap credential so every node of the cluster can verify them
+ * without sharing any state, changing the bootstrap credential invalidates all issued tokens.
+ * Revocations are kept on each node and saved to `path`, so they survive restarts.
+ */
+#[derive(Debug, Default)]
+pub struct ManagementTokens {
+    secret: Option<String>,
+    revoked: RwLock<BTreeMap<Uuid, DateTime<Utc>>>,
+    path: Option<PathBuf>,
+}
+
+impl ManagementTokens {
+    pub fn new(secret: Option<String>, path: Option<PathBuf>) -> Self {
+        let revoked = path
+            .as_ref()
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|content| {
+                serde_json::from_slice::<Vec<RevokedManagementToken>>(&content).ok()
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| (r.id, r.expires))
+            .collect();
+        Self {
+            secret,
+            revoked: RwLock::new(revoked),
+            path,
+        }
+    }
+
+    fn mac(&self) -> Option<Hmac<Sha256>> {
+        self.secret.as_ref().map(|secret| {
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length")
+        })
+    }
+
+    /**
+     * Issue a token with the scopes, valid for `ttl`
+     */
+    pub fn issue(
+        &self,
+        name: &str,
+        scopes: BTreeSet<ManagementScope>,
+        ttl: Duration,
+    ) -> Result<(String, ManagementTokenClaims), ApiError> {
+        let mut mac = self.mac().ok_or_else(|| {
+            ApiError::BadRequest(
+                "Management tokens can only be issued if the management code is set".to_string(),
+            )
+        })?;
+        if name.trim().is_empty() {
+            return Err(ApiError::BadRequest(
+                "Management token name must not be empty".to_string(),
+            ));
+        }
+        if scopes.is_empty() || scopes.contains(&ManagementScope::Admin) {
+            return Err(ApiError::BadRequest(
+                "Management token scopes must not be empty or contain `admin`".to_string(),
+            ));
+        }
+        if ttl <= Duration::zero() || ttl > Duration::hours(MAX_MANAGEMENT_TOKEN_TTL_HOURS) {
+            return Err(ApiError::BadRequest(format!(
+                "Management token lifetime must be between 1 and {} hours",
+                MAX_MANAGEMENT_TOKEN_TTL_HOURS
+            )));
+        }
+        let issued_at = Utc::now();
+        let claims = ManagementTokenClaims {
+            id: Uuid::new_v4(),
+            name: name.trim().to_string(),
+            scopes,
+            issued_at,
+            expires: issued_at + ttl,
+        };
+        let payload = hex::encode(
+            serde_json::to_vec(&claims).map_err(|e| ApiError::InternalError(e.to_string()))?,
+        );
+        mac.update(payload.as_bytes());
+        let signature = hex::encode(mac.finalize().into_bytes());
+        Ok((format!("{}{}.{}", TOKEN_PREFIX, payload, signature), claims))
+    }
+
+    /**
+     * Claims of the token if it's signed with the bootstrap credential, the expiration and the
+     * revocation are not checked
+     */
+    pub fn decode(&self, token: &str) -> Option<ManagementTokenClaims> {
+        let (payload, signature) = token.strip_prefix(TOKEN_PREFIX)?.split_once('.')?;
+        let mut mac = self.mac()?;
+        mac.update(payload.as_bytes());
+        mac.verify_slice(&hex::decode(signature).ok()?).ok()?;
+        serde_json::from_slice(&hex::decode(payload).ok()?).ok()
+    }
+
+    /**
+     * Identity of the code in the management code header
+     */
+    pub fn verify(&self, code: Option<&str>) -> Result<ManagementIdentity, ApiError> {
+        let secret = match &self.secret {
+            Some(secret) => secret,
+            None => return Ok(ManagementIdentity::Anonymous),
+        };
+        let code =
+            code.ok_or_else(|| ApiError::Forbidden("Missing management code".to_string()))?;
+        if constant_time_eq(code.as_bytes(), secret.as_bytes()) {
+            return Ok(ManagementIdentity::Bootstrap);
+        }
+        let claims = self
+            .decode(code)
+            .ok_or_else(|| ApiError::Forbidden("Invalid management code".to_string()))?;
+        if claims.expires <= Utc::now() {
+            return Err(ApiError::Forbidden(format!(
+                "Management token {} has expired",
+                claims.id
+            )));
+        }
+        if self.revoked.read().unwrap().contains_key(&claims.id) {
+            return Err(ApiError::Forbidden(format!(
+                "Management token {} has been revoked",
+                claims.id
+            )));
+        }
+        Ok(ManagementIdentity::Token(claims))
+    }
+
+    /**
+     * Reject the token on this node until it expires
+     */
+    pub fn revoke(&self, id: Uuid, expires: DateTime<Utc>) -> RevokedManagementToken {
+        let mut revoked = self.revoked.write().unwrap();
+        let now = Utc::now();
+        revoked.retain(|_, e| *e > now);
+        revoked.insert(id, expires);
+        if let Some(path) = &self.path {
+            let content = revoked
+                .iter()
+                .map(|(&id, &expires)| RevokedManagementToken { id, expires })
+                .collect::<Vec<_>>();
+            if let Err(e) = serde_json::to_vec(&content)
+                .map_err(std::io::Error::from)
+                .and_then(|content| std::fs::write(path, content))
+            {
+                warn!(
+                    "Failed to save revoked management tokens to {}, error: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        RevokedManagementToken { id, expires }
+    }
+
+    /**
+     * Revoked tokens that have not expired yet
+     */
+    pub fn revoked(&self) -> Vec<RevokedManagementToken> {
+        let now = Utc::now();
+        self.revoked
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, &expires)| expires > now)
+            .map(|(&id, &expires)| RevokedManagementToken { id, expires })
+            .collect()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/**
+ * Verifies the management code header before the request reaches a management function, and logs
+ * every operation with the identity of the caller.
+ * The code is either the bootstrap credential or a management token with the scope.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct ManagementAuth {
+    scope: ManagementScope,
+}
+
+impl ManagementAuth {
+    pub fn new(scope: ManagementScope) -> Self {
+        Self { scope }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ManagementAuth {
+    type Output = ManagementAuthImpl<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ManagementAuthImpl {
+            ep,
+            scope: self.scope,
+        }
+    }
+}
+
+pub struct ManagementAuthImpl<E> {
+    ep: E,
+    scope: ManagementScope,
+}
+
+#[async_trait]
+impl<E: Endpoint> Endpoint for ManagementAuthImpl<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> poem::Result<Self::Output> {
+        let operation = format!("{} {}", req.method(), req.uri().path());
+        let code = req
+            .headers()
+            .get(MANAGEMENT_CODE_HEADER_NAME)
+            .map(|v| String::from_utf8_lossy(v.as_bytes()).to_string());
+        let identity = req
+            .data::<RaftRegistryApp>()
+            .expect("Registry app is attached to the routes")
+            .management_tokens
+            .verify(code.as_deref())
+            .map_err(|e| {
+                warn!("Rejected management operation {}, {}", operation, e);
+                e
+            })?;
+        if !identity.has_scope(self.scope) {
+            warn!(
+                "Rejected management operation {} by {}, missing scope `{}`",
+                operation, identity, self.scope
+            );
+            return Err(ApiError::Forbidden(format!(
+                "Management token doesn't have the `{}` scope",
+                self.scope
+            )))?;
+        }
+        // Raft RPCs are too frequent to be logged at info level
+        if self.scope == ManagementScope::Internal {
+            debug!("Management operation {} by {}", operation, identity);
+        } else {
+            info!("Management operation {} by {}", operation, identity);
+        }
+        req.extensions_mut().insert(identity);
+        self.ep.call(req).await
+    }
+}
+
+/**
+ * The endpoint requiring the scope
+ */
+pub fn scoped<E: Endpoint>(scope: ManagementScope, ep: E) -> ManagementAuthImpl<E> {
+    ep.with(ManagementAuth::new(scope))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_management_tokens() {
+        let tokens = ManagementTokens::new(Some("bootstrap".to_string()), None);
+        assert_eq!(
+            tokens.verify(Some("bootstrap")).unwrap(),
+            ManagementIdentity::Bootstrap
+        );
+        assert!(tokens.verify(None).is_err());
+        assert!(tokens.verify(Some("bootstrap2")).is_err());
+
+        let (token, claims) = tokens
+            .issue(
+                "ops",
+                BTreeSet::from([ManagementScope::Monitor]),
+                Duration::hours(1),
+            )
+            .unwrap();
+        let identity = tokens.verify(Some(&token)).unwrap();
+        assert_eq!(identity, ManagementIdentity::Token(claims.clone()));
+        assert!(identity.has_scope(ManagementScope::Monitor));
+        assert!(!identity.has_scope(ManagementScope::Maintenance));
+
+        // Signed by another bootstrap credential or tampered
+        let other = ManagementTokens::new(Some("other".to_string()), None);
+        assert!(other.verify(Some(&token)).is_err());
+        let tampered = format!("{}0", token);
+        assert!(tokens.verify(Some(&tampered)).is_err());
+
+        tokens.revoke(claims.id, claims.expires);
+        assert!(tokens.verify(Some(&token)).is_err());
+        assert_eq!(tokens.revoked().len(), 1);
+
+        assert!(tokens
+            .issue(
+                "ops",
+                BTreeSet::from([ManagementScope::Admin]),
+                Duration::hours(1)
+            )
+            .is_err());
+        assert!(tokens
+            .issue("ops", BTreeSet::new(), Duration::hours(1))
+            .is_err());
+        assert!(tokens
+            .issue(
+                "ops",
+                BTreeSet::from([ManagementScope::Maintenance]),
+                Duration::hours(MAX_MANAGEMENT_TOKEN_TTL_HOURS + 1)
+            )
+            .is_err());
+
+        let unprotected = ManagementTokens::new(None, None);
+        assert_eq!(
+            unprotected.verify(None).unwrap(),
+            ManagementIdentity::Anonymous
+        );
+        assert!(unprotected
+            .issue(
+                "ops",
+                BTreeSet::from([ManagementScope::Maintenance]),
+                Duration::hours(1)
+            )
+            .is_err());
+    }
+}
@@ -1,21 +1,19 @@
 use openraft::raft::{AppendEntriesRequest, InstallSnapshotRequest, VoteRequest};
-use poem::web::TypedHeader;
 use poem::{
     handler, post,
     web::{Data, Json},
     IntoResponse, Route,
 };
 
-use crate::{ManagementCode, RaftRegistryApp, RegistryNodeId, RegistryTypeConfig};
+use crate::{
+    scoped, ManagementScope::Internal, RaftRegistryApp, RegistryNodeId, RegistryTypeConfig,
+};
 
 #[handler]
 pub async fn vote(
     app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
     req: Json<VoteRequest<RegistryNodeId>>,
 ) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
-
     let res = app.raft.vote(req.0).await;
     Ok(Json(res))
 }
@@ -23,11 +21,8 @@ pub async fn vote(
 #[handler]
 pub async fn append(
     app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
     req: Json<AppendEntriesRequest<RegistryTypeConfig>>,
 ) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
-
     let res = app.raft.append_entries(req.0).await;
     Ok(Json(res))
 }
@@ -35,18 +30,15 @@ pub async fn append(
 #[handler]
 pub async fn snapshot(
     app: Data<&RaftRegistryApp>,
-    code: Option<TypedHeader<ManagementCode>>,
     req: Json<InstallSnapshotRequest<RegistryTypeConfig>>,
 ) -> poem::Result<impl IntoResponse> {
-    app.check_code(code.map(|c| c.0)).await?;
-
     let res = app.raft.install_snapshot(req.0).await;
     Ok(Json(res))
 }
 
 pub fn raft_routes(route: Route) -> Route {
     route
-        .at("/raft-vote", post(vote))
-        .at("/raft-append", post(append))
-        .at("/raft-snapshot", post(snapshot))
+        .at("/raft-vote", scoped(Internal, post(vote)))
+        .at("/raft-append", scoped(Internal, post(append)))
+        .at("/raft-snapshot", scoped(Internal, post(snapshot)))
 }
@@ -1,17 +1,45 @@
-use std::sync::Arc;
+use std::{cell::Cell, sync::Arc};
 
 use async_trait::async_trait;
 use poem::{Endpoint, IntoResponse, Middleware, Request, Response};
 
-use crate::{RegistryStore, OPT_SEQ_HEADER_NAME};
+use crate::{
+    ReadReplica, RegistryStore, DEGRADED_HEADER_NAME, OPT_SEQ_HEADER_NAME, STALENESS_HEADER_NAME,
+};
+
+tokio::task_local! {
+    static DEGRADED: Cell<bool>;
+}
+
+/**
+ * Mark the response of the request being handled as served from the local state without the
+ * confirmation of the Raft quorum, no-op outside of any request
+ */
+pub(crate) fn mark_degraded() {
+    let _ = DEGRADED.try_with(|d| d.set(true));
+}
 
 pub struct RaftSequencer {
     store: Arc<RegistryStore>,
+    replica: Option<Arc<ReadReplica>>,
 }
 
 impl RaftSequencer {
     pub fn new(store: Arc<RegistryStore>) -> Self {
-        Self { store }
+        Self {
+            store,
+            replica: None,
+        }
+    }
+
+    /**
+     * Report the staleness of the read replica in responses
+     */
+    pub fn replica(mut self, replica: Arc<ReadReplica>) -> Self {
+        if replica.is_enabled() {
+            self.replica = Some(replica);
+        }
+        self
     }
 }
 
@@ -22,6 +50,7 @@ impl<E: Endpoint> Middleware<E> for RaftSequencer {
         RaftSequencerImpl {
             ep,
             store: self.store.clone(),
+            replica: self.replica.clone(),
         }
     }
 }
@@ -29,6 +58,7 @@ impl<E: Endpoint> Middleware<E> for RaftSequencer {
 pub struct RaftSequencerImpl<E> {
     ep: E,
     store: Arc<RegistryStore>,
+    replica: Option<Arc<ReadReplica>>,
 }
 
 #[async_trait]
@@ -36,7 +66,12 @@ impl<E: Endpoint> Endpoint for RaftSequencerImpl<E> {
     type Output = Response;
 
     async fn call(&self, req: Request) -> poem::Result<Self::Output> {
-        let res = self.ep.call(req).await;
+        let (res, degraded) = DEGRADED
+            .scope(Cell::new(false), async {
+                let res = self.ep.call(req).await;
+                (res, DEGRADED.with(Cell::get))
+            })
+            .await;
         let opt_seq = self
             .store
             .state_machine
@@ -47,12 +82,29 @@ impl<E: Endpoint> Endpoint for RaftSequencerImpl<E> {
 
         match res {
             Ok(resp) => {
-                let resp = match opt_seq {
+                let mut resp = match opt_seq {
                     Some(v) => resp.with_header(OPT_SEQ_HEADER_NAME, v).into_response(),
                     None => resp.into_response(),
                 };
+                if let Some(replica) = &self.replica {
+                    // Number of log entries not applied on this replica yet
+                    let staleness = replica.staleness(&self.store).await;
+                    resp = resp
+                        .with_header(STALENESS_HEADER_NAME, staleness)
+                        .into_response();
+                }
+                if degraded {
+                    resp = resp
+                        .with_header(DEGRADED_HEADER_NAME, "true")
+                        .into_response();
+                }
                 Ok(resp)
             }
+            // Not found etc. may be caused by the stale local state as well
+            Err(err) if degraded => Ok(err
+                .into_response()
+                .with_header(DEGRADED_HEADER_NAME, "true")
+                .into_response()),
             Err(err) => Err(err),
         }
     }
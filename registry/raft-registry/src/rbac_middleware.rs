@@ -1,10 +1,10 @@
 use std::str::FromStr;
 
-use auth::decode_token;
+use auth::{decode_token, AuthError};
 use common_utils::StringError;
 use log::warn;
 use poem::{
-    error::{BadRequest, Forbidden},
+    error::{BadRequest, Forbidden, Unauthorized},
     Endpoint, Middleware, Request, Result,
 };
 use registry_provider::Credential;
@@ -28,6 +28,44 @@ pub struct RbacMiddlewareImpl<E> {
 
 const TOKEN_HEADER: &str = "Authorization";
 const DEBUG_TOKEN_HEADER: &str = "x-feathr-debug-token";
+const DEBUG_TENANT_HEADER: &str = "x-feathr-debug-tenant";
+
+tokio::task_local! {
+    static TENANT: String;
+}
+
+/**
+ * Tenant of the request being handled, empty for the default tenant or outside of any request
+ */
+pub fn current_tenant() -> String {
+    TENANT.try_with(|t| t.to_owned()).unwrap_or_default()
+}
+
+/**
+ * Requests are scoped to the tenant of the token when `ENABLE_MULTI_TENANCY` is set,
+ * tokens issued by `FEATHR_HOME_TENANT` use the default tenant so existing data stays reachable
+ */
+fn to_tenant(tid: Option<&str>) -> String {
+    if std::env::var("ENABLE_MULTI_TENANCY").is_err() {
+        return Default::default();
+    }
+    match tid {
+        Some(tid) if std::env::var("FEATHR_HOME_TENANT").unwrap_or_default() != tid => {
+            tid.to_string()
+        }
+        _ => Default::default(),
+    }
+}
+
+/**
+ * Revoked tokens are well-formed but no longer accepted
+ */
+fn token_error(e: AuthError) -> poem::Error {
+    match e {
+        AuthError::TokenRevoked => Unauthorized(e),
+        e => BadRequest(e),
+    }
+}
 
 #[derive(Default, Deserialize)]
 #[serde(default)]
@@ -37,6 +75,7 @@ struct Claims {
     email: Option<String>,
     upn: Option<String>,
     unique_name: Option<String>,
+    tid: Option<String>,
 }
 
 impl Claims {
@@ -64,6 +103,7 @@ impl<E: Endpoint> Endpoint for RbacMiddlewareImpl<E> {
     type Output = E::Output;
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let mut tenant = String::new();
         if std::env::var("ENABLE_RBAC").is_err() {
             req.extensions_mut().insert(Credential::RbacDisabled);
         } else if std::env::var("FEATHR_ENABLE_RBAC_DEBUG_MUST_NOT_USE_IN_PROD").unwrap_or_default()
@@ -82,13 +122,19 @@ impl<E: Endpoint> Endpoint for RbacMiddlewareImpl<E> {
                     );
                     req.extensions_mut().insert(credential);
                 }
+                tenant = to_tenant(
+                    req.headers()
+                        .get(DEBUG_TENANT_HEADER)
+                        .and_then(|value| value.to_str().ok()),
+                );
             } else if let Some(value) = req
                 .headers()
                 .get(TOKEN_HEADER)
                 .and_then(|value| value.to_str().ok())
             {
                 let value = value.trim_start_matches("Bearer");
-                let claims: Claims = decode_token(value).await.map_err(|e| BadRequest(e))?;
+                let claims: Claims = decode_token(value).await.map_err(token_error)?;
+                tenant = to_tenant(claims.tid.as_deref());
                 req.extensions_mut().insert(claims.get_credential()?);
             }
         } else if let Some(value) = req
@@ -97,13 +143,14 @@ impl<E: Endpoint> Endpoint for RbacMiddlewareImpl<E> {
             .and_then(|value| value.to_str().ok())
         {
             let value = value.trim_start_matches("Bearer");
-            let claims: Claims = decode_token(value).await.map_err(|e| BadRequest(e))?;
+            let claims: Claims = decode_token(value).await.map_err(token_error)?;
+            tenant = to_tenant(claims.tid.as_deref());
             req.extensions_mut().insert(claims.get_credential()?);
         } else {
             return Err(Forbidden(StringError::new("Missing token")));
         }
 
-        // call the next endpoint.
-        self.ep.call(req).await
+        // call the next endpoint in the tenant scope.
+        TENANT.scope(tenant, self.ep.call(req)).await
     }
 }
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use clap::Parser;
+use log::{debug, warn};
+use openraft::Node;
+use serde::{Deserialize, Serialize};
+
+use crate::{RaftRegistryApp, RegistryClient, RegistryStore};
+
+pub const STALENESS_HEADER_NAME: &str = "x-registry-staleness";
+
+/**
+ * Key in the node data marking the node as a read replica, replicas are never promoted into voters
+ */
+pub const REPLICA_NODE_KEY: &str = "read-replica";
+
+#[derive(Clone, Debug, Serialize, Deserialize, Parser)]
+pub struct ReplicaConfig {
+    /// Join the cluster via `--seeds` as a non-voting read replica, reads are served locally and writes are proxied to the leader
+    #[clap(long, env = "READ_REPLICA")]
+    pub read_replica: bool,
+
+    /// Seconds between polling the leader for its last log index
+    #[clap(long, env = "REPLICA_POLL_INTERVAL", default_value = "5")]
+    pub replica_poll_interval: u64,
+
+    /// Forward reads to the leader if the replica lags behind by more than this many log entries, 0 to never forward
+    #[clap(long, env = "REPLICA_MAX_STALENESS", default_value = "0")]
+    pub replica_max_staleness: u64,
+}
+
+impl Default for ReplicaConfig {
+    fn default() -> Self {
+        Self {
+            read_replica: false,
+            replica_poll_interval: 5,
+            replica_max_staleness: 0,
+        }
+    }
+}
+
+/**
+ * Tracks how far the local state machine is behind the leader
+ */
+pub struct ReadReplica {
+    config: ReplicaConfig,
+    // Highest log index known to exist in the cluster
+    known_index: AtomicU64,
+}
+
+impl ReadReplica {
+    pub fn new(config: ReplicaConfig) -> Self {
+        Self {
+            config,
+            known_index: Default::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.read_replica
+    }
+
+    /**
+     * Number of log entries the local state machine has not applied yet
+     */
+    pub async fn staleness(&self, store: &RegistryStore) -> u64 {
+        let applied = store
+            .state_machine
+            .read()
+            .await
+            .last_applied_log
+            .map(|l| l.index)
+            .unwrap_or_default();
+        self.known_index
+            .load(Ordering::Relaxed)
+            .saturating_sub(applied)
+    }
+
+    /**
+     * True if a read without `x-registry-opt-seq` can be served by this replica
+     */
+    pub async fn can_serve(&self, store: &RegistryStore) -> bool {
+        self.is_enabled()
+            && (self.config.replica_max_staleness == 0
+                || self.staleness(store).await <= self.config.replica_max_staleness)
+    }
+
+    fn observe(&self, index: Option<u64>) {
+        if let Some(index) = index {
+            self.known_index.fetch_max(index, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for ReadReplica {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+pub fn replica_node(addr: String) -> Node {
+    Node {
+        addr,
+        data: [(REPLICA_NODE_KEY.to_string(), "true".to_string())]
+            .into_iter()
+            .collect(),
+    }
+}
+
+pub fn is_replica_node(node: &Node) -> bool {
+    node.data.contains_key(REPLICA_NODE_KEY)
+}
+
+/**
+ * Keep track of the leader log index on a read replica
+ */
+pub async fn run_replica_task(app: RaftRegistryApp) {
+    if !app.replica.is_enabled() {
+        return;
+    }
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        app.replica.config.replica_poll_interval.max(1),
+    ));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        let metrics = app.raft.metrics().borrow().clone();
+        // Entries received but not applied yet
+        app.replica.observe(metrics.last_log_index);
+        let leader = metrics.current_leader.and_then(|id| {
+            metrics
+                .membership_config
+                .get_node(&id)
+                .map(|node| (id, node.addr.to_owned()))
+        });
+        let (leader_id, leader_addr) = match leader {
+            Some(leader) => leader,
+            None => {
+                warn!("Leader is unknown, staleness cannot be measured");
+                continue;
+            }
+        };
+        let client = RegistryClient::new(leader_id, leader_addr, app.store.get_management_code());
+        match client.metrics().await {
+            Ok(metrics) => {
+                debug!(
+                    "Leader {} last log index is {:?}",
+                    leader_id, metrics.last_log_index
+                );
+                app.replica.observe(metrics.last_log_index);
+            }
+            Err(e) => warn!("Failed to fetch leader metrics, error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use clap::Parser;
+use log::{debug, warn};
+use registry_api::{ApiError, Entity, EntityAttributes, SourceAttributes, SourceSample};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sql_provider::sample_jdbc_source;
+use tokio::sync::RwLock;
+
+const DEFAULT_SAMPLE_ROWS: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize, Parser)]
+pub struct SampleConfig {
+    /// URL of the job runner service, which samples non-SQL sources with a bounded Spark job
+    #[clap(long, env = "SAMPLE_JOB_RUNNER_URL")]
+    pub sample_job_runner_url: Option<String>,
+
+    /// Seconds to cache the sample of a source
+    #[clap(long, env = "SAMPLE_CACHE_TTL", default_value = "600")]
+    pub sample_cache_ttl: u64,
+
+    /// Max number of rows of a sample
+    #[clap(long, env = "SAMPLE_MAX_ROWS", default_value = "100")]
+    pub sample_max_rows: usize,
+
+    /// Seconds to wait for the sampling job or the database query
+    #[clap(long, env = "SAMPLE_TIMEOUT", default_value = "300")]
+    pub sample_timeout: u64,
+}
+
+impl Default for SampleConfig {
+    fn default() -> Self {
+        Self {
+            sample_job_runner_url: None,
+            sample_cache_ttl: 600,
+            sample_max_rows: 100,
+            sample_timeout: 300,
+        }
+    }
+}
+
+/**
+ * Runs a bounded job reading the first rows of a source
+ */
+#[async_trait]
+pub trait JobRunner: Send + Sync {
+    async fn sample(&self, source: &Entity, rows: usize) -> anyhow::Result<Vec<Value>>;
+}
+
+#[derive(Debug, Serialize)]
+struct SampleJobRequest<'a> {
+    source: &'a Entity,
+    rows: usize,
+}
+
+/**
+ * Delegate the sampling to an external HTTP service.
+ *
+ * The source entity and the row count are posted to the url as JSON, the service submits the
+ * sampling job and responds with the rows as a JSON array after the job finishes.
+ */
+#[derive(Clone, Debug)]
+pub struct HttpJobRunner {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpJobRunner {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl JobRunner for HttpJobRunner {
+    async fn sample(&self, source: &Entity, rows: usize) -> anyhow::Result<Vec<Value>> {
+        debug!(
+            "Sampling source `{}` via {}",
+            source.qualified_name, self.url
+        );
+        Ok(self
+            .client
+            .post(&self.url)
+            .json(&SampleJobRequest { source, rows })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+/**
+ * Secrets are stored as `${NAME}` in source options, the value is read from the environment variable
+ */
+fn resolve_secret(value: Option<&String>) -> Option<String> {
+    value.map(
+        |v| match v.strip_prefix("${").and_then(|name| name.strip_suffix('}')) {
+            Some(name) => std::env::var(name).unwrap_or_else(|_| {
+                warn!("Environment variable `{}` is not set", name);
+                Default::default()
+            }),
+            None => v.to_owned(),
+        },
+    )
+}
+
+fn is_sql_source(attr: &SourceAttributes) -> bool {
+    attr.type_.eq_ignore_ascii_case("jdbc")
+        && attr
+            .options
+            .get("url")
+            .map(|url| {
+                ["jdbc:sqlserver:", "jdbc:postgresql:", "jdbc:mysql:"]
+                    .iter()
+                    .any(|prefix| url.starts_with(prefix))
+            })
+            .unwrap_or_default()
+}
+
+/**
+ * Reads SQL sources directly, other sources are sampled by the job runner.
+ * Samples are cached per source version, so the UI can preview a source repeatedly without
+ * dispatching a job every time.
+ */
+pub struct SourceSampler {
+    runner: Option<Arc<dyn JobRunner>>,
+    cache: RwLock<HashMap<(String, usize), SourceSample>>,
+    ttl: chrono::Duration,
+    max_rows: usize,
+    timeout: Duration,
+}
+
+impl SourceSampler {
+    pub fn new(config: &SampleConfig) -> Self {
+        let sampler = Self {
+            runner: None,
+            cache: Default::default(),
+            ttl: chrono::Duration::seconds(config.sample_cache_ttl as i64),
+            max_rows: config.sample_max_rows.max(1),
+            timeout: Duration::from_secs(config.sample_timeout),
+        };
+        match &config.sample_job_runner_url {
+            Some(url) => sampler.runner(HttpJobRunner::new(url)),
+            None => sampler,
+        }
+    }
+
+    /**
+     * Replace the job runner
+     */
+    pub fn runner<T>(mut self, runner: T) -> Self
+    where
+        T: JobRunner + 'static,
+    {
+        self.runner = Some(Arc::new(runner));
+        self
+    }
+
+    pub async fn sample(
+        &self,
+        source: &Entity,
+        rows: Option<usize>,
+    ) -> Result<SourceSample, ApiError> {
+        let attr = match &source.attributes {
+            EntityAttributes::Source(attr) => attr,
+            _ => return Err(ApiError::NotFoundError(source.guid.to_owned())),
+        };
+        let rows = rows.unwrap_or(DEFAULT_SAMPLE_ROWS).clamp(1, self.max_rows);
+        let key = (source.guid.to_owned(), rows);
+        if let Some(sample) = self.cache.read().await.get(&key) {
+            if Utc::now() - sample.sampled_at < self.ttl {
+                debug!("Sample of source `{}` is cached", source.qualified_name);
+                return Ok(sample.to_owned());
+            }
+        }
+
+        let data = tokio::time::timeout(self.timeout, self.read(source, attr, rows))
+            .await
+            .map_err(|_| ApiError::InternalError("Sampling timed out".to_string()))??;
+        let sample = SourceSample {
+            guid: source.guid.to_owned(),
+            rows: data,
+            sampled_at: Utc::now(),
+        };
+
+        let mut cache = self.cache.write().await;
+        let now = Utc::now();
+        cache.retain(|_, s| now - s.sampled_at < self.ttl);
+        cache.insert(key, sample.clone());
+        Ok(sample)
+    }
+
+    async fn read(
+        &self,
+        source: &Entity,
+        attr: &SourceAttributes,
+        rows: usize,
+    ) -> Result<Vec<Value>, ApiError> {
+        if is_sql_source(attr) {
+            debug!("Sampling source `{}` directly", source.qualified_name);
+            return sample_jdbc_source(
+                attr.options
+                    .get("url")
+                    .map(|s| s.as_str())
+                    .unwrap_or_default(),
+                attr.options.get("dbtable").map(|s| s.as_str()),
+                attr.options.get("query").map(|s| s.as_str()),
+                resolve_secret(attr.options.get("user")).as_deref(),
+                resolve_secret(attr.options.get("password")).as_deref(),
+                rows,
+            )
+            .await
+            .map(|rows| rows.into_iter().map(Value::Object).collect())
+            .map_err(|e| ApiError::InternalError(format!("Failed to read source, {}", e)));
+        }
+        match &self.runner {
+            Some(runner) => runner
+                .sample(source, rows)
+                .await
+                // The job may return more rows than requested
+                .map(|mut data| {
+                    data.truncate(rows);
+                    data
+                })
+                .map_err(|e| ApiError::InternalError(format!("Sampling job failed, {}", e))),
+            None => Err(ApiError::BadRequest(format!(
+                "Source type `{}` can only be sampled by a job runner, which is not configured",
+                attr.type_
+            ))),
+        }
+    }
+}
+
+impl Default for SourceSampler {
+    fn default() -> Self {
+        Self::new(&Default::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_secret() {
+        std::env::set_var("SAMPLE_TEST_PASSWORD", "secret");
+        assert_eq!(
+            resolve_secret(Some(&"${SAMPLE_TEST_PASSWORD}".to_string())),
+            Some("secret".to_string())
+        );
+        assert_eq!(
+            resolve_secret(Some(&"plain".to_string())),
+            Some("plain".to_string())
+        );
+        assert_eq!(resolve_secret(None), None);
+    }
+}
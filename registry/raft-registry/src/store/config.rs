@@ -2,6 +2,8 @@ use clap::Parser;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::JournalBackend;
+
 #[derive(Clone, Debug, Serialize, Deserialize, Parser)]
 pub struct NodeConfig {
     #[clap(
@@ -28,7 +30,12 @@ pub struct NodeConfig {
     )]
     pub journal_path: String,
 
-    /// The secret to protect Raft management functions
+    /// The storage engine of the Raft journal, `sled` or `rocksdb`, use `registry-cli migrate-journal` to move an existing journal to another engine
+    #[clap(long, env = "RAFT_JOURNAL_STORAGE", default_value = "sled")]
+    #[serde(default)]
+    pub journal_storage: JournalBackend,
+
+    /// The bootstrap credential of Raft management functions, it has all scopes and signs the management tokens
     #[clap(long, hide = true, env = "RAFT_MANAGEMENT_CODE")]
     pub management_code: Option<String>,
 
@@ -0,0 +1,452 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Utc};
+use registry_api::{ApiError, ChangeType, FeathrApiRequest, FeathrApiResponse, FeedEntry};
+use registry_provider::{EdgeType, Entity, EntityProperty, EntityType, RegistryProvider};
+use serde::{Deserialize, Serialize};
+use sql_provider::Registry;
+
+/// Max number of changes kept in the feed, older changes are dropped first
+const FEED_CAPACITY: usize = 10_000;
+
+/**
+ * Start point of a feed query, either a Raft log index or a timestamp
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedPosition {
+    Index(u64),
+    Time(DateTime<Utc>),
+}
+
+impl Default for FeedPosition {
+    fn default() -> Self {
+        Self::Index(0)
+    }
+}
+
+impl FromStr for FeedPosition {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(index) = s.parse::<u64>() {
+            return Ok(Self::Index(index));
+        }
+        DateTime::parse_from_rfc3339(s)
+            .map(|t| Self::Time(t.with_timezone(&Utc)))
+            .map_err(|_| {
+                ApiError::BadRequest(format!(
+                    "`since` must be a log index or an RFC 3339 timestamp, got `{}`",
+                    s
+                ))
+            })
+    }
+}
+
+/**
+ * Entity changed by a request, updated and deleted entities are looked up before the request is
+ * applied, created entities come from the response
+ */
+pub(crate) enum PendingChange {
+    Created,
+    Updated(Entity<EntityProperty>),
+    Deleted(Entity<EntityProperty>),
+    /// A project deleted along with its children, in the order of deletion
+    DeletedProject(Vec<Entity<EntityProperty>>),
+    /// A role granted or revoked, changes what callers can see in every project of the tenant
+    Permission,
+    /// Content of the projects changed without creating or deleting entities one by one, e.g.
+    /// edges added or data loaded in bulk, only the project indices are updated
+    Projects {
+        /// Qualified names of the projects
+        projects: BTreeSet<String>,
+        permission: bool,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct FeedRecord {
+    tenant: String,
+    #[serde(flatten)]
+    entry: FeedEntry,
+}
+
+/**
+ * Recent entity changes in the order of the Raft log.
+ * It's a part of the state machine, so every node has the same changes with the same log indices
+ * and the changes survive snapshots and log compaction.
+ */
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ChangeFeed {
+    records: VecDeque<FeedRecord>,
+    /// Log index of the last dropped change, 0 if nothing has been dropped
+    dropped_index: u64,
+    /// Time of the last dropped change
+    dropped_time: Option<DateTime<Utc>>,
+    /// Log index of the last change in each project by tenant, keyed by both the project name and
+    /// id, never dropped
+    #[serde(default)]
+    projects: HashMap<String, HashMap<String, u64>>,
+    /// Log index of the last permission change by tenant
+    #[serde(default)]
+    permissions: HashMap<String, u64>,
+}
+
+impl ChangeFeed {
+    /**
+     * Tenant and the actual request of a wrapped request
+     */
+    pub(crate) fn unwrap_request(req: &FeathrApiRequest) -> (&str, &FeathrApiRequest) {
+        match req {
+            FeathrApiRequest::InTenant { tenant, request } => {
+                (tenant.as_str(), Self::unwrap_request(request).1)
+            }
+            FeathrApiRequest::Idempotent { request, .. } => Self::unwrap_request(request),
+            _ => ("", req),
+        }
+    }
+
+    /**
+     * The entity the request is going to change, the registry must be switched to the tenant of
+     * the request
+     */
+    pub(crate) fn pending(
+        registry: &Registry<EntityProperty>,
+        req: &FeathrApiRequest,
+    ) -> Option<PendingChange> {
+        match req {
+            FeathrApiRequest::CreateProject { .. }
+            | FeathrApiRequest::CreateProjectDataSource { .. }
+            | FeathrApiRequest::CreateProjectAnchor { .. }
+            | FeathrApiRequest::CreateAnchorFeature { .. }
+            | FeathrApiRequest::CreateProjectDerivedFeature { .. }
+            | FeathrApiRequest::CreateMaterializationRecord { .. }
+            | FeathrApiRequest::CreateDataset { .. } => Some(PendingChange::Created),
+            FeathrApiRequest::SetEntityOwnership { id_or_name, .. }
+            | FeathrApiRequest::SetEntityDocs { id_or_name, .. }
+            | FeathrApiRequest::PromoteEntity { id_or_name, .. }
+            | FeathrApiRequest::DemoteEntity { id_or_name, .. }
+            | FeathrApiRequest::CreateRelease {
+                project_id_or_name: id_or_name,
+                ..
+            }
+            | FeathrApiRequest::DeleteRelease {
+                project_id_or_name: id_or_name,
+                ..
+            } => registry
+                .get_entity_by_id_or_qualified_name(id_or_name)
+                .ok()
+                .map(PendingChange::Updated),
+            FeathrApiRequest::SetSourceVerified {
+                project_id_or_name,
+                id_or_name,
+                ..
+            } => registry
+                .get_entity_by_id_or_qualified_name(id_or_name)
+                .or_else(|_| {
+                    let project =
+                        registry.get_entity_by_id_or_qualified_name(project_id_or_name)?;
+                    registry.get_entity_by_qualified_name(&format!(
+                        "{}__{}",
+                        project.qualified_name, id_or_name
+                    ))
+                })
+                .ok()
+                .map(PendingChange::Updated),
+            FeathrApiRequest::DeleteEntity { id_or_name, .. } => registry
+                .get_entity_by_id_or_qualified_name(id_or_name)
+                .ok()
+                .map(PendingChange::Deleted),
+            FeathrApiRequest::DeleteProject {
+                id_or_name,
+                dry_run: false,
+                ..
+            } => registry
+                .get_entity_by_id_or_qualified_name(id_or_name)
+                .and_then(|p| registry.get_project_deletion_order(p.id))
+                .ok()
+                .map(PendingChange::DeletedProject),
+            FeathrApiRequest::AddUserRole { .. } | FeathrApiRequest::DeleteUserRole { .. } => {
+                Some(PendingChange::Permission)
+            }
+            FeathrApiRequest::LinkSimilarFeatures { project_id_or_name } => registry
+                .get_entity_by_id_or_qualified_name(project_id_or_name)
+                .ok()
+                .map(|p| PendingChange::Projects {
+                    projects: [p.qualified_name].into_iter().collect(),
+                    permission: false,
+                }),
+            FeathrApiRequest::BatchLoad {
+                entities,
+                edges,
+                permissions,
+                ..
+            } => {
+                let loaded: HashMap<_, _> = entities
+                    .iter()
+                    .map(|e| (e.id, project_of(&e.qualified_name)))
+                    .collect();
+                let mut projects: BTreeSet<String> =
+                    loaded.values().map(|p| p.to_string()).collect();
+                // Edges may connect loaded entities to the existing ones in other projects
+                for id in edges.iter().flat_map(|e| [e.from, e.to]) {
+                    if loaded.contains_key(&id) {
+                        continue;
+                    }
+                    if let Ok(e) = registry.get_entity(id) {
+                        projects.insert(project_of(&e.qualified_name).to_string());
+                    }
+                }
+                Some(PendingChange::Projects {
+                    projects,
+                    permission: !permissions.is_empty(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /**
+     * Record the change if the request succeeded
+     */
+    pub(crate) fn record(
+        &mut self,
+        index: u64,
+        tenant: &str,
+        pending: PendingChange,
+        response: &FeathrApiResponse,
+        registry: &Registry<EntityProperty>,
+    ) {
+        let (change_type, entities) = match (pending, response) {
+            (_, FeathrApiResponse::Error(_)) => return,
+            (PendingChange::Created, FeathrApiResponse::UuidAndVersion(id, version)) => {
+                match registry.get_entity(*id) {
+                    // Creating an existing name adds a new version
+                    Ok(e) if *version > 1 => (ChangeType::Updated, vec![e]),
+                    Ok(e) => (ChangeType::Created, vec![e]),
+                    Err(_) => return,
+                }
+            }
+            (PendingChange::Created, _) => return,
+            (PendingChange::Updated(e), _) => (ChangeType::Updated, vec![e]),
+            (PendingChange::Deleted(e), _) => (ChangeType::Deleted, vec![e]),
+            (PendingChange::DeletedProject(entities), _) => (ChangeType::Deleted, entities),
+            (PendingChange::Permission, _) => {
+                self.permissions.insert(tenant.to_string(), index);
+                return;
+            }
+            (
+                PendingChange::Projects {
+                    projects,
+                    permission,
+                },
+                _,
+            ) => {
+                for project in projects {
+                    self.touch_project(index, tenant, project, registry);
+                }
+                if permission {
+                    self.permissions.insert(tenant.to_string(), index);
+                }
+                return;
+            }
+        };
+        for entity in entities {
+            self.record_entity(index, tenant, change_type, entity, registry);
+        }
+    }
+
+    fn record_entity(
+        &mut self,
+        index: u64,
+        tenant: &str,
+        change_type: ChangeType,
+        entity: Entity<EntityProperty>,
+        registry: &Registry<EntityProperty>,
+    ) {
+        let project = project_of(&entity.qualified_name).to_string();
+        let project_id = if entity.entity_type == EntityType::Project {
+            Some(entity.id)
+        } else {
+            registry
+                .get_entity_by_qualified_name(&project)
+                .ok()
+                .map(|p| p.id)
+        };
+        // Projects consuming the entity show it as an input in their lineage
+        let consumers: Vec<String> = registry
+            .get_neighbors(entity.id, EdgeType::Produces)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| project_of(&e.qualified_name).to_string())
+            .filter(|p| *p != project)
+            .collect();
+        let indices = self.projects.entry(tenant.to_string()).or_default();
+        if let Some(id) = project_id {
+            indices.insert(id.to_string(), index);
+        }
+        indices.insert(project, index);
+        for consumer in consumers {
+            self.touch_project(index, tenant, consumer, registry);
+        }
+        self.push(FeedRecord {
+            tenant: tenant.to_string(),
+            entry: FeedEntry {
+                index,
+                time: Utc::now(),
+                change_type,
+                guid: entity.id.to_string(),
+                entity_type: entity.entity_type.into(),
+                qualified_name: entity.qualified_name,
+                version: entity.version,
+            },
+        });
+    }
+
+    /**
+     * Update the index of the project by both the name and the id
+     */
+    fn touch_project(
+        &mut self,
+        index: u64,
+        tenant: &str,
+        project: String,
+        registry: &Registry<EntityProperty>,
+    ) {
+        let indices = self.projects.entry(tenant.to_string()).or_default();
+        if let Ok(p) = registry.get_entity_by_qualified_name(&project) {
+            indices.insert(p.id.to_string(), index);
+        }
+        indices.insert(project, index);
+    }
+
+    fn push(&mut self, record: FeedRecord) {
+        self.records.push_back(record);
+        while self.records.len() > FEED_CAPACITY {
+            if let Some(dropped) = self.records.pop_front() {
+                self.dropped_index = dropped.entry.index;
+                self.dropped_time = Some(dropped.entry.time);
+            }
+        }
+    }
+
+    /**
+     * At most `limit` changes in the tenant after the position, and whether no change after the
+     * position has been dropped
+     */
+    pub fn since(&self, tenant: &str, since: FeedPosition, limit: usize) -> (Vec<FeedEntry>, bool) {
+        let complete = match since {
+            FeedPosition::Index(index) => index >= self.dropped_index,
+            FeedPosition::Time(time) => self.dropped_time.map_or(true, |t| time >= t),
+        };
+        let changes = self
+            .records
+            .iter()
+            .filter(|r| match since {
+                FeedPosition::Index(index) => r.entry.index > index,
+                FeedPosition::Time(time) => r.entry.time > time,
+            })
+            .filter(|r| r.tenant == tenant)
+            .take(limit)
+            .map(|r| r.entry.clone())
+            .collect();
+        (changes, complete)
+    }
+
+    /**
+     * Log index of the last change in the project, `project` is either the name or the id.
+     * `None` if the project hasn't changed since the feed started tracking projects
+     */
+    pub fn project_index(&self, tenant: &str, project: &str) -> Option<u64> {
+        self.projects.get(tenant)?.get(project).copied()
+    }
+
+    /**
+     * Log index of the last permission change in the tenant, `None` if no permission has changed
+     * since the feed started tracking permissions
+     */
+    pub fn permission_index(&self, tenant: &str) -> Option<u64> {
+        self.permissions.get(tenant).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+fn project_of(qualified_name: &str) -> &str {
+    qualified_name.split("__").next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use registry_api::EntityType;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn record(index: u64, tenant: &str, time: DateTime<Utc>) -> FeedRecord {
+        FeedRecord {
+            tenant: tenant.to_string(),
+            entry: FeedEntry {
+                index,
+                time,
+                change_type: ChangeType::Created,
+                guid: Uuid::new_v4().to_string(),
+                entity_type: EntityType::Project,
+                qualified_name: format!("project{}", index),
+                version: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_change_feed() {
+        assert_eq!(
+            "42".parse::<FeedPosition>().unwrap(),
+            FeedPosition::Index(42)
+        );
+        assert!(matches!(
+            "2022-10-01T00:00:00Z".parse::<FeedPosition>().unwrap(),
+            FeedPosition::Time(_)
+        ));
+        assert!("yesterday".parse::<FeedPosition>().is_err());
+
+        let start = Utc::now();
+        let mut feed = ChangeFeed::default();
+        for i in 1..=(FEED_CAPACITY as u64 + 10) {
+            let tenant = if i % 2 == 0 { "" } else { "tenant1" };
+            feed.push(record(i, tenant, start + Duration::seconds(i as i64)));
+        }
+        assert_eq!(feed.len(), FEED_CAPACITY);
+
+        let (changes, complete) = feed.since("", FeedPosition::Index(9_000), 3);
+        assert!(complete);
+        assert_eq!(
+            changes.iter().map(|c| c.index).collect::<Vec<_>>(),
+            vec![9_002, 9_004, 9_006]
+        );
+        let (changes, complete) = feed.since(
+            "tenant1",
+            FeedPosition::Time(start + Duration::seconds(9_000)),
+            2,
+        );
+        assert!(complete);
+        assert_eq!(
+            changes.iter().map(|c| c.index).collect::<Vec<_>>(),
+            vec![9_001, 9_003]
+        );
+        // The first 10 changes have been dropped
+        let (changes, complete) = feed.since("", FeedPosition::default(), 1);
+        assert!(!complete);
+        assert_eq!(changes[0].index, 12);
+        assert!(feed.since("", FeedPosition::Index(10), 1).1);
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use registry_api::{ApiError, FeathrApiResponse, IdempotencyKey};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct IdempotencyRecord {
+    id: Uuid,
+    version: u64,
+    #[serde(default)]
+    fingerprint: String,
+    expires_at: DateTime<Utc>,
+}
+
+/**
+ * Results of recent idempotent creation requests, keyed by tenant and the idempotency key.
+ * It's a part of the state machine, so the records are replicated and saved in snapshots.
+ */
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct IdempotencyRecords {
+    records: BTreeMap<String, BTreeMap<String, IdempotencyRecord>>,
+}
+
+impl IdempotencyRecords {
+    /**
+     * Response of the previous request with the same key, if it was received before the record
+     * expired. The key can't be reused by a different request or another identity
+     */
+    pub fn get(&self, tenant: &str, key: &IdempotencyKey) -> Option<FeathrApiResponse> {
+        self.records
+            .get(tenant)
+            .and_then(|records| records.get(&key.key))
+            .filter(|r| key.received_at < r.expires_at)
+            .map(|r| {
+                if r.fingerprint == key.fingerprint {
+                    FeathrApiResponse::UuidAndVersion(r.id, r.version)
+                } else {
+                    FeathrApiResponse::Error(ApiError::Conflict(format!(
+                        "Idempotency key `{}` has been used by a different request",
+                        key.key
+                    )))
+                }
+            })
+    }
+
+    /**
+     * Only successful creations are recorded, failed requests can be retried with the same key
+     */
+    pub fn insert(&mut self, tenant: &str, key: &IdempotencyKey, response: &FeathrApiResponse) {
+        if let FeathrApiResponse::UuidAndVersion(id, version) = response {
+            self.records.entry(tenant.to_string()).or_default().insert(
+                key.key.to_owned(),
+                IdempotencyRecord {
+                    id: *id,
+                    version: *version,
+                    fingerprint: key.fingerprint.to_owned(),
+                    expires_at: key.expires_at,
+                },
+            );
+        }
+    }
+
+    /**
+     * Remove records expired at `now`, which is the receiving time of the request being applied
+     * instead of the local clock, so every node removes the same records
+     */
+    pub fn purge(&mut self, now: DateTime<Utc>) {
+        self.records.retain(|_, records| {
+            records.retain(|_, r| r.expires_at > now);
+            !records.is_empty()
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.values().map(|r| r.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use registry_api::{ApiError, FeathrApiResponse, IdempotencyKey};
+    use uuid::Uuid;
+
+    use super::IdempotencyRecords;
+
+    #[test]
+    fn test_idempotency_records() {
+        let now = Utc::now();
+        let key = |k: &str, received_at| IdempotencyKey {
+            key: k.to_string(),
+            fingerprint: "f1".to_string(),
+            received_at,
+            expires_at: received_at + Duration::hours(1),
+        };
+        let id = Uuid::new_v4();
+        let mut records = IdempotencyRecords::default();
+
+        // Failures are not recorded
+        records.insert(
+            "",
+            &key("k1", now),
+            &FeathrApiResponse::Error(ApiError::InternalError("error".to_string())),
+        );
+        assert!(records.get("", &key("k1", now)).is_none());
+
+        records.insert(
+            "",
+            &key("k1", now),
+            &FeathrApiResponse::UuidAndVersion(id, 1),
+        );
+        assert!(matches!(
+            records.get("", &key("k1", now + Duration::minutes(30))),
+            Some(FeathrApiResponse::UuidAndVersion(i, 1)) if i == id
+        ));
+        // Keys are scoped by tenant
+        assert!(records.get("tenant1", &key("k1", now)).is_none());
+        // Reused by a different request
+        let other = IdempotencyKey {
+            fingerprint: "f2".to_string(),
+            ..key("k1", now)
+        };
+        assert!(matches!(
+            records.get("", &other),
+            Some(FeathrApiResponse::Error(ApiError::Conflict(_)))
+        ));
+        // Expired
+        assert!(records
+            .get("", &key("k1", now + Duration::hours(2)))
+            .is_none());
+
+        records.purge(now + Duration::minutes(30));
+        assert_eq!(records.len(), 1);
+        records.purge(now + Duration::hours(1));
+        assert!(records.is_empty());
+    }
+}
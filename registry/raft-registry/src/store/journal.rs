@@ -0,0 +1,457 @@
+use std::{
+    fmt::{Debug, Display},
+    io::{Error, ErrorKind, Result},
+    ops::{Bound, RangeBounds},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use sled::IVec;
+
+use crate::RegistryNodeId;
+
+/// Number of entries copied in one batch by the journal migration
+const MIGRATION_BATCH_SIZE: u64 = 1000;
+
+/**
+ * Storage of the Raft journal, log entries are keyed by their log index, entries and the vote are
+ * stored as opaque bytes
+ */
+pub trait Journal: Debug + Send + Sync {
+    fn read_vote(&self) -> Result<Option<Vec<u8>>>;
+
+    fn save_vote(&self, vote: &[u8]) -> Result<()>;
+
+    /**
+     * The entry with the greatest log index
+     */
+    fn last_entry(&self) -> Result<Option<(u64, Vec<u8>)>>;
+
+    /**
+     * Entries in the log index range, in the order of log index
+     */
+    fn entries(&self, range: (Bound<u64>, Bound<u64>)) -> Result<Vec<(u64, Vec<u8>)>>;
+
+    fn append(&self, entries: Vec<(u64, Vec<u8>)>) -> Result<()>;
+
+    fn remove(&self, range: (Bound<u64>, Bound<u64>)) -> Result<()>;
+
+    fn flush(&self) -> Result<()>;
+}
+
+pub(crate) fn to_bounds<RB: RangeBounds<u64>>(range: &RB) -> (Bound<u64>, Bound<u64>) {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+fn decode_index(key: &[u8]) -> Result<u64> {
+    key.try_into()
+        .map(u64::from_be_bytes)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "Malformed journal key"))
+}
+
+/**
+ * The storage engine of the Raft journal
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalBackend {
+    Sled,
+    RocksDb,
+}
+
+impl Default for JournalBackend {
+    fn default() -> Self {
+        Self::Sled
+    }
+}
+
+impl FromStr for JournalBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sled" => Ok(Self::Sled),
+            "rocksdb" => Ok(Self::RocksDb),
+            _ => anyhow::bail!(
+                "Unknown journal storage `{}`, must be `sled` or `rocksdb`",
+                s
+            ),
+        }
+    }
+}
+
+impl Display for JournalBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalBackend::Sled => f.write_str("sled"),
+            JournalBackend::RocksDb => f.write_str("rocksdb"),
+        }
+    }
+}
+
+impl JournalBackend {
+    /**
+     * Sled keeps the journal in `<prefix>-<node_id>.binlog`, RocksDB in `<prefix>-<node_id>.rocksdb`,
+     * so both can live under the same journal path
+     */
+    pub fn journal_file(
+        &self,
+        journal_path: &str,
+        instance_prefix: &str,
+        node_id: RegistryNodeId,
+    ) -> String {
+        let ext = match self {
+            JournalBackend::Sled => "binlog",
+            JournalBackend::RocksDb => "rocksdb",
+        };
+        format!("{}/{}-{}.{}", journal_path, instance_prefix, node_id, ext)
+    }
+
+    pub fn open(
+        &self,
+        journal_path: &str,
+        instance_prefix: &str,
+        node_id: RegistryNodeId,
+    ) -> anyhow::Result<Box<dyn Journal>> {
+        let path = self.journal_file(journal_path, instance_prefix, node_id);
+        tracing::debug!("Opening {} journal at: {:?}", self, path);
+        Ok(match self {
+            JournalBackend::Sled => Box::new(SledJournal::open(&path, node_id)?),
+            #[cfg(feature = "rocksdb")]
+            JournalBackend::RocksDb => Box::new(RocksDbJournal::open(&path, node_id)?),
+            #[cfg(not(feature = "rocksdb"))]
+            JournalBackend::RocksDb => {
+                anyhow::bail!("RocksDB journal storage is not enabled in this build")
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SledJournal {
+    db: sled::Db,
+    log: sled::Tree,
+    vote: sled::Tree,
+}
+
+impl SledJournal {
+    pub fn open(path: &str, node_id: RegistryNodeId) -> Result<Self> {
+        let db = sled::open(path)?;
+        let log = db.open_tree(format!("journal_entities_{}", node_id))?;
+        let vote = db.open_tree(format!("votes_{}", node_id))?;
+        Ok(Self { db, log, vote })
+    }
+}
+
+fn serialize_bound(v: Bound<u64>) -> Bound<IVec> {
+    match v {
+        Bound::Included(v) => Bound::Included(IVec::from(&v.to_be_bytes())),
+        Bound::Excluded(v) => Bound::Excluded(IVec::from(&v.to_be_bytes())),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl Journal for SledJournal {
+    fn read_vote(&self) -> Result<Option<Vec<u8>>> {
+        Ok(self.vote.get(b"vote")?.map(|v| v.to_vec()))
+    }
+
+    fn save_vote(&self, vote: &[u8]) -> Result<()> {
+        self.vote.insert(b"vote", vote)?;
+        Ok(())
+    }
+
+    fn last_entry(&self) -> Result<Option<(u64, Vec<u8>)>> {
+        match self.log.last()? {
+            Some((k, v)) => Ok(Some((decode_index(&k)?, v.to_vec()))),
+            None => Ok(None),
+        }
+    }
+
+    fn entries(&self, range: (Bound<u64>, Bound<u64>)) -> Result<Vec<(u64, Vec<u8>)>> {
+        self.log
+            .range((serialize_bound(range.0), serialize_bound(range.1)))
+            .map(|res| {
+                let (k, v) = res?;
+                Ok((decode_index(&k)?, v.to_vec()))
+            })
+            .collect()
+    }
+
+    fn append(&self, entries: Vec<(u64, Vec<u8>)>) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for (index, entry) in entries {
+            batch.insert(IVec::from(&index.to_be_bytes()), entry);
+        }
+        self.log.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn remove(&self, range: (Bound<u64>, Bound<u64>)) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for res in self
+            .log
+            .range((serialize_bound(range.0), serialize_bound(range.1)))
+        {
+            let (k, _) = res?;
+            batch.remove(k);
+        }
+        self.log.apply_batch(batch)?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+pub use rocks::RocksDbJournal;
+
+#[cfg(feature = "rocksdb")]
+mod rocks {
+    use std::{
+        io::{Error, ErrorKind, Result},
+        ops::Bound,
+    };
+
+    use rocksdb::{ColumnFamily, Direction, IteratorMode, Options, WriteBatch, DB};
+
+    use super::{decode_index, Journal};
+    use crate::RegistryNodeId;
+
+    fn io_err(e: rocksdb::Error) -> Error {
+        Error::new(ErrorKind::Other, e)
+    }
+
+    /**
+     * Log entries and the vote are kept in separate column families, keys of the entries are the
+     * big endian log indices so they're iterated in the order of log index
+     */
+    pub struct RocksDbJournal {
+        db: DB,
+        log: String,
+        vote: String,
+    }
+
+    impl std::fmt::Debug for RocksDbJournal {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RocksDbJournal")
+                .field("path", &self.db.path())
+                .finish()
+        }
+    }
+
+    impl RocksDbJournal {
+        pub fn open(path: &str, node_id: RegistryNodeId) -> Result<Self> {
+            let log = format!("journal_entities_{}", node_id);
+            let vote = format!("votes_{}", node_id);
+            let mut opts = Options::default();
+            opts.create_if_missing(true);
+            opts.create_missing_column_families(true);
+            let db = DB::open_cf(&opts, path, [&log, &vote]).map_err(io_err)?;
+            Ok(Self { db, log, vote })
+        }
+
+        fn cf(&self, name: &str) -> Result<&ColumnFamily> {
+            self.db.cf_handle(name).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("Column family `{}` not found", name),
+                )
+            })
+        }
+
+        /**
+         * Entries in the range, RocksDB iterators only have a start position so the end bound is
+         * checked while iterating
+         */
+        fn scan(&self, range: (Bound<u64>, Bound<u64>)) -> Result<Vec<(u64, Vec<u8>)>> {
+            let start = match range.0 {
+                Bound::Included(s) => Some(s),
+                Bound::Excluded(s) => match s.checked_add(1) {
+                    Some(s) => Some(s),
+                    None => return Ok(vec![]),
+                },
+                Bound::Unbounded => None,
+            };
+            let start_key = start.map(u64::to_be_bytes);
+            let mode = match &start_key {
+                Some(key) => IteratorMode::From(key, Direction::Forward),
+                None => IteratorMode::Start,
+            };
+            let mut ret = vec![];
+            for item in self.db.iterator_cf(self.cf(&self.log)?, mode) {
+                let (k, v) = item.map_err(io_err)?;
+                let index = decode_index(&k)?;
+                let in_range = match range.1 {
+                    Bound::Included(e) => index <= e,
+                    Bound::Excluded(e) => index < e,
+                    Bound::Unbounded => true,
+                };
+                if !in_range {
+                    break;
+                }
+                ret.push((index, v.into_vec()));
+            }
+            Ok(ret)
+        }
+    }
+
+    impl Journal for RocksDbJournal {
+        fn read_vote(&self) -> Result<Option<Vec<u8>>> {
+            self.db
+                .get_cf(self.cf(&self.vote)?, b"vote")
+                .map_err(io_err)
+        }
+
+        fn save_vote(&self, vote: &[u8]) -> Result<()> {
+            let mut opts = rocksdb::WriteOptions::default();
+            opts.set_sync(true);
+            self.db
+                .put_cf_opt(self.cf(&self.vote)?, b"vote", vote, &opts)
+                .map_err(io_err)
+        }
+
+        fn last_entry(&self) -> Result<Option<(u64, Vec<u8>)>> {
+            match self
+                .db
+                .iterator_cf(self.cf(&self.log)?, IteratorMode::End)
+                .next()
+            {
+                Some(item) => {
+                    let (k, v) = item.map_err(io_err)?;
+                    Ok(Some((decode_index(&k)?, v.into_vec())))
+                }
+                None => Ok(None),
+            }
+        }
+
+        fn entries(&self, range: (Bound<u64>, Bound<u64>)) -> Result<Vec<(u64, Vec<u8>)>> {
+            self.scan(range)
+        }
+
+        fn append(&self, entries: Vec<(u64, Vec<u8>)>) -> Result<()> {
+            let cf = self.cf(&self.log)?;
+            let mut batch = WriteBatch::default();
+            for (index, entry) in entries {
+                batch.put_cf(cf, index.to_be_bytes(), entry);
+            }
+            self.db.write(batch).map_err(io_err)
+        }
+
+        fn remove(&self, range: (Bound<u64>, Bound<u64>)) -> Result<()> {
+            let cf = self.cf(&self.log)?;
+            let mut batch = WriteBatch::default();
+            for (index, _) in self.scan(range)? {
+                batch.delete_cf(cf, index.to_be_bytes());
+            }
+            self.db.write(batch).map_err(io_err)
+        }
+
+        fn flush(&self) -> Result<()> {
+            self.db.flush_cf(self.cf(&self.log)?).map_err(io_err)?;
+            self.db.flush_cf(self.cf(&self.vote)?).map_err(io_err)
+        }
+    }
+}
+
+/**
+ * Copy the journal of the node from one storage to another, the node must be stopped.
+ * The target journal must be empty, the source journal is left untouched so it can be used to
+ * roll back. Returns the number of copied entries.
+ */
+pub fn migrate_journal(
+    journal_path: &str,
+    instance_prefix: &str,
+    node_id: RegistryNodeId,
+    from: JournalBackend,
+    to: JournalBackend,
+) -> anyhow::Result<usize> {
+    if from == to {
+        anyhow::bail!(
+            "The source and the target journal storage are both `{}`",
+            from
+        );
+    }
+    let source = from.open(journal_path, instance_prefix, node_id)?;
+    let target = to.open(journal_path, instance_prefix, node_id)?;
+    if target.last_entry()?.is_some() || target.read_vote()?.is_some() {
+        anyhow::bail!(
+            "The target journal {} is not empty",
+            to.journal_file(journal_path, instance_prefix, node_id)
+        );
+    }
+
+    if let Some(vote) = source.read_vote()? {
+        target.save_vote(&vote)?;
+    }
+    let last = match source.last_entry()? {
+        Some((index, _)) => index,
+        None => 0,
+    };
+    let mut count = 0;
+    let mut start = 0;
+    while start <= last {
+        let end = start.saturating_add(MIGRATION_BATCH_SIZE);
+        let entries = source.entries((Bound::Included(start), Bound::Excluded(end)))?;
+        count += entries.len();
+        target.append(entries)?;
+        tracing::debug!("migrate_journal: copied {} entries", count);
+        start = end;
+    }
+    target.flush()?;
+
+    // Verify the last entries are the same
+    if source.last_entry()? != target.last_entry()? {
+        anyhow::bail!("The last entries of the source and the target journal don't match");
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sled_journal() {
+        let dir = std::env::temp_dir().join(format!("journal-{}", uuid::Uuid::new_v4()));
+        let path = dir.to_string_lossy().to_string();
+        let journal = JournalBackend::Sled.open(&path, "test", 1).unwrap();
+        journal
+            .append((1..=5).map(|i| (i, vec![i as u8])).collect())
+            .unwrap();
+        journal.save_vote(b"v1").unwrap();
+        assert_eq!(journal.read_vote().unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(journal.last_entry().unwrap(), Some((5, vec![5])));
+        let indices = |r| -> Vec<u64> {
+            journal
+                .entries(r)
+                .unwrap()
+                .into_iter()
+                .map(|(i, _)| i)
+                .collect()
+        };
+        assert_eq!(indices(to_bounds(&(2..4))), vec![2, 3]);
+        assert_eq!(indices(to_bounds(&(4..))), vec![4, 5]);
+        journal.remove(to_bounds(&(..=2))).unwrap();
+        journal.remove(to_bounds(&(5..))).unwrap();
+        assert_eq!(indices(to_bounds(&(..))), vec![3, 4]);
+        drop(journal);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_backend_from_str() {
+        assert_eq!(
+            "RocksDB".parse::<JournalBackend>().unwrap(),
+            JournalBackend::RocksDb
+        );
+        assert_eq!(
+            JournalBackend::Sled.journal_file("./journal", "feathr-registry", 1),
+            "./journal/feathr-registry-1.binlog"
+        );
+        assert!("leveldb".parse::<JournalBackend>().is_err());
+    }
+}
@@ -1,31 +1,37 @@
 mod config;
+mod feed;
+mod idempotency;
+mod journal;
+mod rbac_events;
 mod store;
 
-use std::{
-    fmt::Debug,
-    io::Cursor,
-    ops::{Bound, RangeBounds},
-    sync::Arc,
-};
+use std::{collections::BTreeMap, fmt::Debug, io::Cursor, ops::RangeBounds, sync::Arc};
 
 use async_trait::async_trait;
-use log::debug;
+use chrono::{DateTime, Utc};
+use log::{debug, trace};
 use openraft::{
     storage::{LogState, Snapshot},
     AnyError, EffectiveMembership, Entry, EntryPayload, ErrorSubject, ErrorVerb, LogId,
     RaftLogReader, RaftSnapshotBuilder, RaftStorage, SnapshotMeta, StateMachineChanges,
     StorageError, StorageIOError, Vote,
 };
-use registry_api::{FeathrApiProvider, FeathrApiResponse};
-use registry_provider::EntityProperty;
+use registry_api::{FeathrApiProvider, FeathrApiRequest, FeathrApiResponse};
+use registry_provider::{EntityProperty, RegistryProvider};
 use serde::{Deserialize, Serialize};
-use sled::{Db, IVec};
 use sql_provider::Registry;
 use tokio::sync::{Mutex, RwLock};
 
 use crate::{RegistryNodeId, RegistryTypeConfig};
 
 pub use config::NodeConfig;
+pub use feed::{ChangeFeed, FeedPosition};
+pub use idempotency::IdempotencyRecords;
+#[cfg(feature = "rocksdb")]
+pub use journal::RocksDbJournal;
+pub use journal::{migrate_journal, Journal, JournalBackend, SledJournal};
+pub use rbac_events::RbacEvents;
+pub use store::save_bootstrap_snapshot;
 
 #[derive(Debug)]
 pub struct RegistrySnapshot {
@@ -42,21 +48,108 @@ pub struct RegistryStateMachine {
     pub last_membership: EffectiveMembership<RegistryNodeId>,
 
     pub registry: Registry<EntityProperty>,
+
+    #[serde(default)]
+    pub idempotency: IdempotencyRecords,
+
+    #[serde(default)]
+    pub feed: ChangeFeed,
+
+    /// Revoked user tokens by hash with their expiration time, kept in the state machine so they
+    /// survive log compaction and reach nodes joining later
+    #[serde(default)]
+    pub revoked_tokens: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl RegistryStateMachine {
+    /**
+     * Apply the revocations to the token cache of this node, after the state machine has been
+     * replaced by a snapshot
+     */
+    fn restore_revoked_tokens(&self) {
+        let now = Utc::now();
+        for (hash, &expires) in self.revoked_tokens.iter() {
+            if expires > now {
+                auth::revoke_token_hash(hash, Some(expires));
+            }
+        }
+    }
+
+    fn revoke_token(&mut self, hash: &str, expires: DateTime<Utc>) -> FeathrApiResponse {
+        let hash = hash.to_lowercase();
+        // Expired tokens are rejected anyway
+        let now = Utc::now();
+        self.revoked_tokens.retain(|_, e| *e > now);
+        auth::revoke_token_hash(&hash, Some(expires));
+        self.revoked_tokens.insert(hash, expires);
+        FeathrApiResponse::Unit
+    }
+
+    /**
+     * Apply the request at the log index and record the entity it changed in the feed
+     */
+    async fn apply_entry(&mut self, index: u64, req: &FeathrApiRequest) -> FeathrApiResponse {
+        let (tenant, request) = ChangeFeed::unwrap_request(req);
+        if let FeathrApiRequest::RevokeToken { hash, expires } = request {
+            return self.revoke_token(hash, *expires);
+        }
+        // Replayed responses of idempotency keys have been recorded by the first request
+        let replayed = req.idempotency_key().map_or(false, |(tenant, key)| {
+            self.idempotency.get(tenant, key).is_some()
+        });
+        let pending = if replayed {
+            None
+        } else {
+            let previous = self.registry.switch_tenant(tenant);
+            let pending = ChangeFeed::pending(&self.registry, request);
+            self.registry.switch_tenant(&previous);
+            pending
+        };
+        let resp = self.apply_request(req).await;
+        if let Some(pending) = pending {
+            let previous = self.registry.switch_tenant(tenant);
+            self.feed
+                .record(index, tenant, pending, &resp, &self.registry);
+            self.registry.switch_tenant(&previous);
+        }
+        resp
+    }
+
+    /**
+     * Handle the request with the registry, requests with a recorded idempotency key get the
+     * recorded response without touching the registry
+     */
+    async fn apply_request(&mut self, req: &FeathrApiRequest) -> FeathrApiResponse {
+        match req.idempotency_key() {
+            Some((tenant, key)) => {
+                self.idempotency.purge(key.received_at);
+                match self.idempotency.get(tenant, key) {
+                    Some(resp) => {
+                        trace!("Replaying response of idempotency key {}", key.key);
+                        resp
+                    }
+                    None => {
+                        let resp = self.registry.request(req.to_owned()).await;
+                        self.idempotency.insert(tenant, key, &resp);
+                        resp
+                    }
+                }
+            }
+            None => self.registry.request(req.to_owned()).await,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct RegistryStore {
     last_purged_log_id: RwLock<Option<LogId<RegistryNodeId>>>,
 
-    /// The Raft log.
-    pub log: sled::Tree, //RwLock<BTreeMap<u64, Entry<StorageRaftTypeConfig>>>,
+    /// The Raft log and the current granted vote.
+    journal: Box<dyn Journal>,
 
     /// The Raft state machine.
     pub state_machine: RwLock<RegistryStateMachine>,
 
-    /// The current granted vote.
-    vote: sled::Tree,
-
     snapshot_idx: Arc<Mutex<u64>>,
 
     current_snapshot: RwLock<Option<RegistrySnapshot>>,
@@ -64,41 +157,31 @@ pub struct RegistryStore {
     config: NodeConfig,
 
     pub node_id: RegistryNodeId,
-}
 
-fn get_sled_db(config: NodeConfig, node_id: RegistryNodeId) -> Db {
-    let db_path = format!(
-        "{}/{}-{}.binlog",
-        config.journal_path, config.instance_prefix, node_id
-    );
-    let db = sled::open(db_path.clone()).unwrap();
-    tracing::debug!("get_sled_db: created log at: {:?}", db_path);
-    db
+    /// Permission changes applied to the state machine on this node
+    pub rbac_events: RbacEvents,
 }
 
 impl RegistryStore {
     pub fn open_create(node_id: RegistryNodeId, config: NodeConfig) -> RegistryStore {
         tracing::info!("open_create, node_id: {}", node_id);
 
-        let db = get_sled_db(config.clone(), node_id);
-
-        let log = db
-            .open_tree(format!("journal_entities_{}", node_id))
+        let journal = config
+            .journal_storage
+            .open(&config.journal_path, &config.instance_prefix, node_id)
             .unwrap();
 
-        let vote = db.open_tree(format!("votes_{}", node_id)).unwrap();
-
         let current_snapshot = RwLock::new(None);
 
         RegistryStore {
             last_purged_log_id: Default::default(),
             config,
             node_id,
-            log,
+            journal,
             state_machine: Default::default(),
-            vote,
             snapshot_idx: Arc::new(Mutex::new(0)),
             current_snapshot,
+            rbac_events: Default::default(),
         }
     }
 
@@ -118,18 +201,11 @@ impl Restore for Arc<RegistryStore> {
     #[tracing::instrument(level = "trace", skip(self))]
     async fn restore(&mut self) {
         tracing::debug!("restore");
-        let log = &self.log;
-
-        let first = log
-            .iter()
-            .rev()
-            .next()
-            .map(|res| res.unwrap())
-            .map(|(_, val)| {
-                serde_json::from_slice::<Entry<RegistryTypeConfig>>(&*val)
-                    .unwrap()
-                    .log_id
-            });
+        let first = self
+            .journal
+            .last_entry()
+            .unwrap()
+            .map(|(_, val)| decode_entry(&val).unwrap().log_id);
 
         match first {
             Some(x) => {
@@ -157,17 +233,14 @@ impl RaftLogReader<RegistryTypeConfig> for Arc<RegistryStore> {
     async fn get_log_state(
         &mut self,
     ) -> Result<LogState<RegistryTypeConfig>, StorageError<RegistryNodeId>> {
-        let log = &self.log;
-        let last = log
-            .iter()
-            .rev()
-            .next()
-            .map(|res| res.unwrap())
-            .map(|(_, val)| {
-                serde_json::from_slice::<Entry<RegistryTypeConfig>>(&*val)
-                    .unwrap()
-                    .log_id
-            });
+        let last = match self
+            .journal
+            .last_entry()
+            .map_err(|e| log_io_error(ErrorVerb::Read, e))?
+        {
+            Some((_, val)) => Some(decode_entry(&val)?.log_id),
+            None => None,
+        };
 
         let last_purged = *self.last_purged_log_id.read().await;
 
@@ -190,41 +263,24 @@ impl RaftLogReader<RegistryTypeConfig> for Arc<RegistryStore> {
         &mut self,
         range: RB,
     ) -> Result<Vec<Entry<RegistryTypeConfig>>, StorageError<RegistryNodeId>> {
-        let log = &self.log;
-        let response = log
-            .range(transform_range_bound(range))
-            .map(|res| res.unwrap())
-            .map(|(_, val)| {
-                serde_json::from_slice::<Entry<RegistryTypeConfig>>(&*val)
-                    .map_err(|e| {
-                        let v = val.clone().to_vec();
-                        let s = String::from_utf8_lossy(&v);
-                        debug!("val: '{}'", s);
-                        e
-                    })
-                    .unwrap()
-            })
-            .collect();
-
-        Ok(response)
+        self.journal
+            .entries(journal::to_bounds(&range))
+            .map_err(|e| log_io_error(ErrorVerb::Read, e))?
+            .into_iter()
+            .map(|(_, val)| decode_entry(&val))
+            .collect()
     }
 }
 
-fn transform_range_bound<RB: RangeBounds<u64> + Clone + Debug + Send + Sync>(
-    range: RB,
-) -> (Bound<IVec>, Bound<IVec>) {
-    (
-        serialize_bound(&range.start_bound()),
-        serialize_bound(&range.end_bound()),
-    )
+fn log_io_error(verb: ErrorVerb, e: std::io::Error) -> StorageError<RegistryNodeId> {
+    StorageIOError::new(ErrorSubject::Logs, verb, AnyError::new(&e)).into()
 }
 
-fn serialize_bound(v: &Bound<&u64>) -> Bound<IVec> {
-    match v {
-        Bound::Included(v) => Bound::Included(IVec::from(&v.to_be_bytes())),
-        Bound::Excluded(v) => Bound::Excluded(IVec::from(&v.to_be_bytes())),
-        Bound::Unbounded => Bound::Unbounded,
-    }
+fn decode_entry(val: &[u8]) -> Result<Entry<RegistryTypeConfig>, StorageError<RegistryNodeId>> {
+    serde_json::from_slice::<Entry<RegistryTypeConfig>>(val).map_err(|e| {
+        debug!("val: '{}'", String::from_utf8_lossy(val));
+        StorageIOError::new(ErrorSubject::Logs, ErrorVerb::Read, AnyError::new(&e)).into()
+    })
 }
 
 #[async_trait]
@@ -303,16 +359,19 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
         &mut self,
         vote: &Vote<RegistryNodeId>,
     ) -> Result<(), StorageError<RegistryNodeId>> {
-        self.vote
-            .insert(b"vote", IVec::from(serde_json::to_vec(vote).unwrap()))
-            .unwrap();
-        Ok(())
+        self.journal
+            .save_vote(&serde_json::to_vec(vote).unwrap())
+            .map_err(|e| {
+                StorageIOError::new(ErrorSubject::Vote, ErrorVerb::Write, AnyError::new(&e)).into()
+            })
     }
 
     async fn read_vote(
         &mut self,
     ) -> Result<Option<Vote<RegistryNodeId>>, StorageError<RegistryNodeId>> {
-        let value = self.vote.get(b"vote").unwrap();
+        let value = self.journal.read_vote().map_err(|e| {
+            StorageIOError::new(ErrorSubject::Vote, ErrorVerb::Read, AnyError::new(&e))
+        })?;
         match value {
             None => Ok(None),
             Some(val) => Ok(Some(
@@ -330,15 +389,13 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
         &mut self,
         entries: &[&Entry<RegistryTypeConfig>],
     ) -> Result<(), StorageError<RegistryNodeId>> {
-        let log = &self.log;
-        for entry in entries {
-            log.insert(
-                entry.log_id.index.to_be_bytes(),
-                IVec::from(serde_json::to_vec(&*entry).unwrap()),
-            )
-            .unwrap();
-        }
-        Ok(())
+        let entries = entries
+            .iter()
+            .map(|entry| (entry.log_id.index, serde_json::to_vec(&*entry).unwrap()))
+            .collect();
+        self.journal
+            .append(entries)
+            .map_err(|e| log_io_error(ErrorVerb::Write, e))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
@@ -348,16 +405,9 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
     ) -> Result<(), StorageError<RegistryNodeId>> {
         tracing::debug!("delete_log: [{:?}, +oo)", log_id);
 
-        let log = &self.log;
-        let keys = log
-            .range(transform_range_bound(log_id.index..))
-            .map(|res| res.unwrap())
-            .map(|(k, _v)| k); //TODO Why originally used collect instead of the iter.
-        for key in keys {
-            log.remove(&key).unwrap();
-        }
-
-        Ok(())
+        self.journal
+            .remove(journal::to_bounds(&(log_id.index..)))
+            .map_err(|e| log_io_error(ErrorVerb::Delete, e))
     }
 
     #[tracing::instrument(level = "debug", skip(self))]
@@ -373,19 +423,9 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
             *ld = Some(log_id);
         }
 
-        {
-            let log = &self.log;
-
-            let keys = log
-                .range(transform_range_bound(..=log_id.index))
-                .map(|res| res.unwrap())
-                .map(|(k, _)| k);
-            for key in keys {
-                log.remove(&key).unwrap();
-            }
-        }
-
-        Ok(())
+        self.journal
+            .remove(journal::to_bounds(&(..=log_id.index)))
+            .map_err(|e| log_io_error(ErrorVerb::Delete, e))
     }
 
     async fn last_applied_state(
@@ -417,11 +457,14 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
             tracing::debug!(%entry.log_id, "replicate to sm");
 
             sm.last_applied_log = Some(entry.log_id);
+            sm.registry.set_applied_index(entry.log_id.index);
 
             match entry.payload {
                 EntryPayload::Blank => res.push(FeathrApiResponse::Unit),
                 EntryPayload::Normal(ref req) => {
-                    res.push(sm.registry.request(req.to_owned()).await)
+                    let resp = sm.apply_entry(entry.log_id.index, req).await;
+                    self.rbac_events.publish(entry.log_id.index, req, &resp);
+                    res.push(resp)
                 }
                 EntryPayload::Membership(ref mem) => {
                     sm.last_membership = EffectiveMembership::new(Some(entry.log_id), mem.clone());
@@ -471,7 +514,11 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
                 })?;
             let mut state_machine = self.state_machine.write().await;
             *state_machine = updated_state_machine;
+            state_machine.restore_revoked_tokens();
         }
+        // Permissions are replaced along with the whole state machine
+        self.rbac_events
+            .reset(meta.last_log_id.map(|l| l.index).unwrap_or_default());
 
         // Update current snapshot.
         let mut current_snapshot = self.current_snapshot.write().await;
@@ -541,3 +588,74 @@ impl RaftStorage<RegistryTypeConfig> for Arc<RegistryStore> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use registry_api::ProjectDef;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replicated_revocation() {
+        let mut sm = RegistryStateMachine::default();
+        let expires = Utc::now() + Duration::hours(1);
+        let req = FeathrApiRequest::RevokeToken {
+            hash: "ABCD".to_string(),
+            expires,
+        };
+        assert!(matches!(
+            sm.apply_entry(1, &req).await,
+            FeathrApiResponse::Unit
+        ));
+        assert_eq!(sm.revoked_tokens.get("abcd"), Some(&expires));
+
+        // Nodes installing a snapshot get the revocation as well
+        let restored: RegistryStateMachine =
+            serde_json::from_slice(&serde_json::to_vec(&sm).unwrap()).unwrap();
+        assert_eq!(restored.revoked_tokens, sm.revoked_tokens);
+        restored.restore_revoked_tokens();
+        assert!(auth::revoked_tokens().iter().any(|r| r.hash == "abcd"));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_changes_update_project_index() {
+        let mut sm = RegistryStateMachine::default();
+        let req = FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Default::default(),
+                name: "p1".to_string(),
+                qualified_name: Default::default(),
+                tags: Default::default(),
+                ownership: Default::default(),
+                default_tags: Default::default(),
+                created_by: Default::default(),
+            },
+        };
+        sm.apply_entry(1, &req).await;
+        assert_eq!(sm.feed.project_index("", "p1"), Some(1));
+
+        let req = FeathrApiRequest::LinkSimilarFeatures {
+            project_id_or_name: "p1".to_string(),
+        };
+        sm.apply_entry(2, &req).await;
+        assert_eq!(sm.feed.project_index("", "p1"), Some(2));
+
+        // Loaded projects are tracked by both the name and the id
+        let project = sm.registry.get_entity_by_qualified_name("p1").unwrap();
+        let mut loaded = RegistryStateMachine::default();
+        let req = FeathrApiRequest::BatchLoad {
+            entities: vec![project.clone()],
+            edges: vec![],
+            permissions: vec![],
+            policy: Default::default(),
+        };
+        loaded.apply_entry(3, &req).await;
+        assert_eq!(loaded.feed.project_index("", "p1"), Some(3));
+        assert_eq!(
+            loaded.feed.project_index("", &project.id.to_string()),
+            Some(3)
+        );
+        assert_eq!(loaded.feed.permission_index(""), None);
+    }
+}
@@ -0,0 +1,100 @@
+use futures_util::stream::{self, BoxStream, StreamExt};
+use registry_api::{FeathrApiRequest, FeathrApiResponse, RbacChangeType, RbacEvent};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::ChangeFeed;
+
+/// Max number of events buffered for each subscriber, slow subscribers get a `Reset` event
+/// instead of the events they missed
+const RBAC_EVENTS_CAPACITY: usize = 1024;
+
+/**
+ * Permission changes emitted by the state machine as the log is applied.
+ * Events are not persisted, subscribers only get the changes applied after they subscribed.
+ */
+#[derive(Debug)]
+pub struct RbacEvents {
+    sender: broadcast::Sender<RbacEvent>,
+}
+
+impl Default for RbacEvents {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(RBAC_EVENTS_CAPACITY).0,
+        }
+    }
+}
+
+impl RbacEvents {
+    pub fn subscribe(&self) -> broadcast::Receiver<RbacEvent> {
+        self.sender.subscribe()
+    }
+
+    /**
+     * Events visible to the subscriber, see `RbacEvent::is_visible`
+     */
+    pub fn stream(&self, tenant: String, user: Option<String>) -> BoxStream<'static, RbacEvent> {
+        stream::unfold(self.subscribe(), move |mut receiver| {
+            let tenant = tenant.clone();
+            let user = user.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(e) if e.is_visible(&tenant, user.as_deref()) => {
+                            return Some((e, receiver))
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(_)) => return Some((RbacEvent::reset(0), receiver)),
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /**
+     * Emit the event if the request at the log index has changed a permission
+     */
+    pub(crate) fn publish(&self, index: u64, req: &FeathrApiRequest, resp: &FeathrApiResponse) {
+        if !matches!(resp, FeathrApiResponse::Unit) {
+            return;
+        }
+        let (tenant, request) = ChangeFeed::unwrap_request(req);
+        let event = match request {
+            FeathrApiRequest::AddUserRole {
+                project_id_or_name,
+                user,
+                role,
+                ..
+            } => RbacEvent::new(
+                index,
+                RbacChangeType::Granted,
+                tenant,
+                project_id_or_name,
+                user,
+                *role,
+            ),
+            FeathrApiRequest::DeleteUserRole {
+                project_id_or_name,
+                user,
+                role,
+                ..
+            } => RbacEvent::new(
+                index,
+                RbacChangeType::Revoked,
+                tenant,
+                project_id_or_name,
+                user,
+                *role,
+            ),
+            _ => return,
+        };
+        // Nobody is listening if it fails
+        self.sender.send(event).ok();
+    }
+
+    pub(crate) fn reset(&self, index: u64) {
+        self.sender.send(RbacEvent::reset(index)).ok();
+    }
+}
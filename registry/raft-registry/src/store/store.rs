@@ -5,12 +5,15 @@ use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
 use std::io::Cursor;
 use std::io::Error;
 use std::io::ErrorKind;
+use std::sync::Arc;
 use walkdir::WalkDir;
 
 use openraft::storage::Snapshot;
+use openraft::RaftSnapshotBuilder;
 use openraft::SnapshotMeta;
 use openraft::StorageError;
 
+use crate::store::NodeConfig;
 use crate::store::RegistryStateMachine;
 use crate::store::RegistryStore;
 use crate::RegistryNodeId;
@@ -23,20 +26,80 @@ pub struct RegistrySnapshot {
     pub data: Vec<u8>,
 }
 
+/**
+ * The file with the greatest log index in the snapshot id is installed when the node starts
+ */
+fn snapshot_file_name(config: &NodeConfig, node_id: RegistryNodeId, snapshot_id: &str) -> String {
+    format!(
+        "{}/{}+{}+{}.bin",
+        config.snapshot_path, config.instance_prefix, node_id, snapshot_id
+    )
+}
+
+/**
+ * Save the snapshot downloaded from another node as the latest snapshot of this node, it's installed
+ * when the store is opened, so this must be called before the node starts.
+ * Returns the log index of the snapshot.
+ */
+pub async fn save_bootstrap_snapshot(
+    config: &NodeConfig,
+    node_id: RegistryNodeId,
+    snapshot_id: &str,
+    data: &[u8],
+) -> anyhow::Result<u64> {
+    let content: RegistryStateMachine = serde_json::from_slice(data)?;
+    let index = match content.last_applied_log {
+        Some(log_id) => log_id.index,
+        None => anyhow::bail!("Snapshot `{}` is empty", snapshot_id),
+    };
+    tokio::fs::create_dir_all(&config.snapshot_path).await?;
+    // Written under a name not recognized as a snapshot, so a partial file is never installed
+    let part_file = format!("{}/.bootstrap-{}.part", config.snapshot_path, node_id);
+    tokio::fs::write(&part_file, data).await?;
+    tokio::fs::rename(&part_file, snapshot_file_name(config, node_id, snapshot_id)).await?;
+    Ok(index)
+}
+
 impl RegistryStore {
+    /**
+     * The latest snapshot of this node for other nodes to bootstrap from, a new one is built if
+     * there is none or `fresh` is true
+     */
+    pub async fn transfer_snapshot(
+        self: &Arc<Self>,
+        fresh: bool,
+    ) -> anyhow::Result<(SnapshotMeta<RegistryNodeId>, Vec<u8>)> {
+        let latest = if fresh {
+            None
+        } else {
+            self.load_latest_snapshot()
+                .await
+                .map_err(|e| anyhow::anyhow!("{:?}", e))?
+        };
+        let snapshot = match latest {
+            Some(snapshot) => snapshot,
+            None => {
+                if self.state_machine.read().await.last_applied_log.is_none() {
+                    anyhow::bail!("Nothing has been applied on this node");
+                }
+                let mut store = self.clone();
+                store
+                    .build_snapshot()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("{:?}", e))?
+            }
+        };
+        Ok((snapshot.meta, snapshot.snapshot.into_inner()))
+    }
+
     #[tracing::instrument(level = "debug", skip(self))]
     pub async fn write_snapshot(&self) -> io::Result<()> {
         tracing::debug!("write_snapshot: start");
 
         match &*self.current_snapshot.read().await {
             Some(snapshot) => {
-                let file_name = format!(
-                    "{}/{}+{}+{}.bin",
-                    self.config.snapshot_path,
-                    self.config.instance_prefix,
-                    self.node_id,
-                    snapshot.meta.snapshot_id
-                );
+                let file_name =
+                    snapshot_file_name(&self.config, self.node_id, &snapshot.meta.snapshot_id);
                 tracing::debug!("write_snapshot: [{:?}, +oo)", file_name);
                 let file = OpenOptions::new()
                     .write(true)
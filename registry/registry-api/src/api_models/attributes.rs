@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use poem_openapi::{Enum, Object, Union};
 use serde::{Deserialize, Serialize};
 
@@ -108,6 +109,49 @@ impl Into<registry_provider::ValueType> for ValueType {
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct Ownership {
+    #[oai(default)]
+    #[serde(default)]
+    pub owner_email: Option<String>,
+    #[oai(default)]
+    #[serde(default)]
+    pub owner_team: Option<String>,
+    #[oai(default)]
+    #[serde(default)]
+    pub support_channel: Option<String>,
+}
+
+impl From<registry_provider::Ownership> for Ownership {
+    fn from(v: registry_provider::Ownership) -> Self {
+        Self {
+            owner_email: v.owner_email,
+            owner_team: v.owner_team,
+            support_channel: v.support_channel,
+        }
+    }
+}
+
+impl From<Ownership> for registry_provider::Ownership {
+    fn from(v: Ownership) -> Self {
+        Self {
+            owner_email: v.owner_email,
+            owner_team: v.owner_team,
+            support_channel: v.support_channel,
+        }
+    }
+}
+
+fn to_ownership(v: registry_provider::Ownership) -> Option<Ownership> {
+    if v.is_empty() {
+        None
+    } else {
+        Some(v.into())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 #[oai(rename_all = "camelCase")]
 pub struct ProjectAttributes {
@@ -118,6 +162,12 @@ pub struct ProjectAttributes {
     pub anchor_features: Vec<EntityRef>,
     pub derived_features: Vec<EntityRef>,
     pub tags: HashMap<String, String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    pub ownership: Option<Ownership>,
+    /// Tags inherited by new entities of the project
+    #[oai(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub default_tags: HashMap<String, String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
@@ -133,6 +183,8 @@ pub struct SourceAttributes {
     pub event_timestamp_column: Option<String>,
     #[oai(skip_serializing_if = "Option::is_none")]
     pub timestamp_format: Option<String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    pub last_verified: Option<DateTime<Utc>>,
     #[oai(rename = "type")]
     pub type_: String,
     pub tags: HashMap<String, String>,
@@ -146,6 +198,20 @@ pub struct AnchorAttributes {
     pub features: Vec<EntityRef>,
     #[oai(skip_serializing_if = "Option::is_none")]
     pub source: Option<EntityRef>,
+    /// Sources joined with `source` on `joinKeys`, empty unless the anchor is a composite anchor
+    #[oai(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub joined_sources: Vec<EntityRef>,
+    #[oai(default, skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub join_keys: Vec<String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub preprocessing: Option<String>,
+    /// Inherited by the window aggregation features without a window
+    #[oai(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub default_window: Option<String>,
     pub tags: HashMap<String, String>,
 }
 
@@ -159,6 +225,8 @@ pub struct AnchorFeatureAttributes {
     pub transformation: FeatureTransformation,
     pub key: Vec<TypedKey>,
     pub tags: HashMap<String, String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    pub ownership: Option<Ownership>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
@@ -174,6 +242,32 @@ pub struct DerivedFeatureAttributes {
     pub input_anchor_features: Vec<EntityRef>,
     pub input_derived_features: Vec<EntityRef>,
     pub tags: HashMap<String, String>,
+    #[oai(skip_serializing_if = "Option::is_none")]
+    pub ownership: Option<Ownership>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+pub struct MaterializationRecordAttributes {
+    pub qualified_name: String,
+    pub name: String,
+    pub sink: String,
+    pub features: Vec<EntityRef>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub job_id: String,
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+pub struct DatasetAttributes {
+    pub qualified_name: String,
+    pub name: String,
+    pub path: String,
+    pub features: Vec<EntityRef>,
+    pub job_id: String,
+    pub tags: HashMap<String, String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Union)]
@@ -183,6 +277,8 @@ pub enum EntityAttributes {
     Anchor(AnchorAttributes),
     AnchorFeature(AnchorFeatureAttributes),
     DerivedFeature(DerivedFeatureAttributes),
+    MaterializationRecord(MaterializationRecordAttributes),
+    Dataset(DatasetAttributes),
 }
 
 impl From<registry_provider::EntityProperty> for EntityAttributes {
@@ -193,6 +289,7 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                     qualified_name: v.qualified_name,
                     name: v.name,
                     tags: v.tags,
+                    ownership: to_ownership(v.ownership),
                     type_: attr.type_.into(),
                     transformation: attr.transformation.into(),
                     key: attr.key.into_iter().map(|e| e.into()).collect(),
@@ -203,6 +300,7 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                     qualified_name: v.qualified_name,
                     name: v.name,
                     tags: v.tags,
+                    ownership: to_ownership(v.ownership),
                     type_: attr.type_.into(),
                     transformation: attr.transformation.into(),
                     key: attr.key.into_iter().map(|e| e.into()).collect(),
@@ -210,12 +308,19 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                     input_derived_features: Default::default(),
                 })
             }
-            registry_provider::Attributes::Anchor => Self::Anchor(AnchorAttributes {
+            registry_provider::Attributes::Anchor(attr) => Self::Anchor(AnchorAttributes {
                 qualified_name: v.qualified_name,
                 name: v.name,
                 tags: v.tags,
                 features: Default::default(),
                 source: None,
+                joined_sources: Default::default(),
+                join_keys: attr
+                    .as_ref()
+                    .map(|a| a.join_keys.clone())
+                    .unwrap_or_default(),
+                default_window: attr.as_ref().and_then(|a| a.default_window.clone()),
+                preprocessing: attr.and_then(|a| a.preprocessing),
             }),
             registry_provider::Attributes::Source(attr) => Self::Source(SourceAttributes {
                 qualified_name: v.qualified_name,
@@ -225,17 +330,40 @@ impl From<registry_provider::EntityProperty> for EntityAttributes {
                 preprocessing: attr.preprocessing,
                 event_timestamp_column: attr.event_timestamp_column,
                 timestamp_format: attr.timestamp_format,
+                last_verified: attr.last_verified,
                 type_: attr.type_,
             }),
             registry_provider::Attributes::Project => Self::Project(ProjectAttributes {
                 qualified_name: v.qualified_name,
                 name: v.name,
                 tags: v.tags,
+                ownership: to_ownership(v.ownership),
+                default_tags: v.default_tags,
                 anchors: Default::default(),
                 sources: Default::default(),
                 anchor_features: Default::default(),
                 derived_features: Default::default(),
             }),
+            registry_provider::Attributes::MaterializationRecord(attr) => {
+                Self::MaterializationRecord(MaterializationRecordAttributes {
+                    qualified_name: v.qualified_name,
+                    name: v.name,
+                    tags: v.tags,
+                    sink: attr.sink,
+                    features: Default::default(),
+                    window_start: attr.window_start,
+                    window_end: attr.window_end,
+                    job_id: attr.job_id,
+                })
+            }
+            registry_provider::Attributes::Dataset(attr) => Self::Dataset(DatasetAttributes {
+                qualified_name: v.qualified_name,
+                name: v.name,
+                tags: v.tags,
+                path: attr.path,
+                features: Default::default(),
+                job_id: attr.job_id,
+            }),
         }
     }
 }
@@ -0,0 +1,179 @@
+use std::collections::{BTreeSet, HashMap};
+
+use poem_openapi::{types::ToJSON, Object};
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiError, Entity, EntityAttributes, FeatureTransformation, FeatureType, TypedKey};
+
+/**
+ * A field changed between two versions, values are absent if the field didn't exist in the version
+ */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct FieldChange {
+    /// `transformation`, `type`, `key` or `tags.<tag>`
+    pub field: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub from: Option<serde_json::Value>,
+    #[oai(skip_serializing_if_is_none)]
+    pub to: Option<serde_json::Value>,
+}
+
+/**
+ * What changed between two versions of a feature
+ */
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct EntityDiff {
+    pub qualified_name: String,
+    pub from_version: u64,
+    pub to_version: u64,
+    /// Empty if the definitions of both versions are identical
+    pub changes: Vec<FieldChange>,
+}
+
+struct FeatureFields<'a> {
+    type_: &'a FeatureType,
+    transformation: &'a FeatureTransformation,
+    key: &'a Vec<TypedKey>,
+    tags: &'a HashMap<String, String>,
+}
+
+fn feature_fields(e: &Entity) -> Result<FeatureFields, ApiError> {
+    match &e.attributes {
+        EntityAttributes::AnchorFeature(attr) => Ok(FeatureFields {
+            type_: &attr.type_,
+            transformation: &attr.transformation,
+            key: &attr.key,
+            tags: &attr.tags,
+        }),
+        EntityAttributes::DerivedFeature(attr) => Ok(FeatureFields {
+            type_: &attr.type_,
+            transformation: &attr.transformation,
+            key: &attr.key,
+            tags: &attr.tags,
+        }),
+        _ => Err(ApiError::BadRequest(format!(
+            "Entity {} is not a feature",
+            e.qualified_name
+        ))),
+    }
+}
+
+fn diff_field<T>(changes: &mut Vec<FieldChange>, field: &str, from: &T, to: &T)
+where
+    T: PartialEq + ToJSON,
+{
+    if from != to {
+        changes.push(FieldChange {
+            field: field.to_string(),
+            from: from.to_json(),
+            to: to.to_json(),
+        });
+    }
+}
+
+impl EntityDiff {
+    /**
+     * Both entities must be versions of the same feature
+     */
+    pub fn new(from: &Entity, to: &Entity) -> Result<Self, ApiError> {
+        if from.qualified_name != to.qualified_name {
+            return Err(ApiError::BadRequest(format!(
+                "Cannot diff {} with {}",
+                from.qualified_name, to.qualified_name
+            )));
+        }
+        let old = feature_fields(from)?;
+        let new = feature_fields(to)?;
+        let mut changes = vec![];
+        diff_field(
+            &mut changes,
+            "transformation",
+            old.transformation,
+            new.transformation,
+        );
+        diff_field(&mut changes, "type", old.type_, new.type_);
+        diff_field(&mut changes, "key", old.key, new.key);
+        let tags: BTreeSet<&String> = old.tags.keys().chain(new.tags.keys()).collect();
+        for tag in tags {
+            let (old_value, new_value) = (old.tags.get(tag), new.tags.get(tag));
+            if old_value != new_value {
+                changes.push(FieldChange {
+                    field: format!("tags.{}", tag),
+                    from: old_value.map(|v| serde_json::Value::String(v.to_owned())),
+                    to: new_value.map(|v| serde_json::Value::String(v.to_owned())),
+                });
+            }
+        }
+        Ok(Self {
+            qualified_name: to.qualified_name.clone(),
+            from_version: from.version,
+            to_version: to.version,
+            changes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{AnchorFeatureAttributes, EntityType, TensorCategory, ValueType, VectorType};
+
+    fn feature(version: u64, transform: &str, tags: &[(&str, &str)]) -> Entity {
+        Entity {
+            guid: format!("guid-{}", version),
+            name: "f1".to_string(),
+            qualified_name: "p1__a1__f1".to_string(),
+            version,
+            entity_type: EntityType::AnchorFeature,
+            status: "Active".to_string(),
+            display_text: "f1".to_string(),
+            labels: vec![],
+            attributes: EntityAttributes::AnchorFeature(AnchorFeatureAttributes {
+                qualified_name: "p1__a1__f1".to_string(),
+                name: "f1".to_string(),
+                type_: FeatureType {
+                    type_: VectorType::TENSOR,
+                    tensor_category: TensorCategory::DENSE,
+                    dimension_type: vec![],
+                    val_type: ValueType::INT32,
+                },
+                transformation: registry_provider::FeatureTransformation::Expression {
+                    transform_expr: transform.to_string(),
+                }
+                .into(),
+                key: vec![],
+                tags: tags
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                ownership: None,
+            }),
+            created_by: "user".to_string(),
+            created_on: Utc::now(),
+            docs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_feature_diff() {
+        let v1 = feature(1, "fare", &[("team", "a"), ("tier", "1")]);
+        let v2 = feature(2, "fare * 2", &[("team", "b"), ("owner", "x")]);
+        let diff = EntityDiff::new(&v1, &v2).unwrap();
+        assert_eq!(diff.from_version, 1);
+        assert_eq!(diff.to_version, 2);
+        let fields: Vec<&str> = diff.changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            vec!["transformation", "tags.owner", "tags.team", "tags.tier"]
+        );
+        assert_eq!(diff.changes[1].from, None);
+        assert_eq!(diff.changes[3].to, None);
+        assert!(EntityDiff::new(&v1, &v1).unwrap().changes.is_empty());
+    }
+}
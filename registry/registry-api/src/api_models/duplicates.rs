@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use poem_openapi::Object;
+use registry_provider::{AnchorFeatureAttributes, FeatureTransformation};
+use serde::{Deserialize, Serialize};
+
+use crate::EntityRef;
+
+/**
+ * Anchor features computed from the same sources with the same transformation and keys
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// Qualified names of the sources
+    pub sources: Vec<String>,
+    /// The transformation with whitespace normalized
+    pub transformation: String,
+    /// Key columns and their types, e.g. `user_id:INT64`
+    pub key: Vec<String>,
+    /// Latest versions of the features, sorted by qualified name
+    pub features: Vec<EntityRef>,
+    /// `true` if the features are already connected with `SimilarTo` edges
+    pub linked: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct DuplicateReport {
+    pub project: String,
+    pub groups: Vec<DuplicateGroup>,
+}
+
+/**
+ * What near-duplicate anchor features have in common
+ */
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FeatureFingerprint {
+    pub sources: Vec<String>,
+    pub transformation: String,
+    pub key: Vec<String>,
+}
+
+impl FeatureFingerprint {
+    pub fn new(attr: &AnchorFeatureAttributes, mut sources: Vec<String>) -> Self {
+        sources.sort();
+        let mut key: Vec<String> = attr
+            .key
+            .iter()
+            .map(|k| format!("{}:{:?}", k.key_column, k.key_column_type))
+            .collect();
+        key.sort();
+        Self {
+            sources,
+            transformation: normalize_transformation(&attr.transformation),
+            key,
+        }
+    }
+}
+
+/**
+ * Groups of 2 or more items sharing the fingerprint, ordered by the fingerprint
+ */
+pub(crate) fn group_duplicates<T, I>(items: I) -> Vec<(FeatureFingerprint, Vec<T>)>
+where
+    I: IntoIterator<Item = (FeatureFingerprint, T)>,
+{
+    let mut groups: BTreeMap<FeatureFingerprint, Vec<T>> = BTreeMap::new();
+    for (fingerprint, item) in items {
+        groups.entry(fingerprint).or_default().push(item);
+    }
+    groups.into_iter().filter(|(_, v)| v.len() > 1).collect()
+}
+
+/**
+ * Whitespace only matters between two identifier characters, e.g. `a + b` equals `a+b`, but
+ * `x and y` doesn't equal `xandy`
+ */
+fn normalize_expression(expr: &str) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut ret = String::with_capacity(expr.len());
+    for token in expr.split_whitespace() {
+        if let (Some(last), Some(first)) = (ret.chars().last(), token.chars().next()) {
+            if is_ident(last) && is_ident(first) {
+                ret.push(' ');
+            }
+        }
+        ret.push_str(token);
+    }
+    ret
+}
+
+fn normalize_transformation(t: &FeatureTransformation) -> String {
+    let normalized = match t {
+        FeatureTransformation::Expression { transform_expr } => {
+            return normalize_expression(transform_expr)
+        }
+        FeatureTransformation::WindowAgg {
+            def_expr,
+            agg_func,
+            window,
+            group_by,
+            filter,
+            limit,
+        } => FeatureTransformation::WindowAgg {
+            def_expr: normalize_expression(def_expr),
+            agg_func: agg_func.to_owned(),
+            window: window.to_owned(),
+            group_by: group_by.as_deref().map(normalize_expression),
+            filter: filter.as_deref().map(normalize_expression),
+            limit: limit.to_owned(),
+        },
+        FeatureTransformation::Udf { .. } => t.to_owned(),
+    };
+    serde_json::to_string(&normalized).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(source: &str, expr: &str) -> FeatureFingerprint {
+        FeatureFingerprint {
+            sources: vec![source.to_string()],
+            transformation: normalize_expression(expr),
+            key: vec!["user_id:INT64".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_group_duplicates() {
+        assert_eq!(normalize_expression(" fare  *\n2 "), "fare*2");
+        assert_eq!(
+            normalize_expression("cast(x as  int) > 0"),
+            "cast(x as int)>0"
+        );
+        let groups = group_duplicates(vec![
+            (fingerprint("s1", "fare * 2"), "f1"),
+            (fingerprint("s1", "fare*2"), "f2"),
+            (fingerprint("s2", "fare*2"), "f3"),
+            (fingerprint("s1", "fare * 3"), "f4"),
+            (fingerprint("s1", "  fare *2"), "f5"),
+        ]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0.transformation, "fare*2");
+        assert_eq!(groups[0].1, vec!["f1", "f2", "f5"]);
+    }
+}
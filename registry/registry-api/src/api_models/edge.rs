@@ -8,6 +8,9 @@ pub enum EdgeType {
     Contains,
     Consumes,
     Produces,
+    ConsumedBy,
+    DependsOn,
+    SimilarTo,
 }
 
 impl From<registry_provider::EdgeType> for EdgeType {
@@ -17,6 +20,9 @@ impl From<registry_provider::EdgeType> for EdgeType {
             registry_provider::EdgeType::Contains => EdgeType::Contains,
             registry_provider::EdgeType::Consumes => EdgeType::Consumes,
             registry_provider::EdgeType::Produces => EdgeType::Produces,
+            registry_provider::EdgeType::ConsumedBy => EdgeType::ConsumedBy,
+            registry_provider::EdgeType::DependsOn => EdgeType::DependsOn,
+            registry_provider::EdgeType::SimilarTo => EdgeType::SimilarTo,
         }
     }
 }
@@ -28,6 +34,9 @@ impl Into<registry_provider::EdgeType> for EdgeType {
             EdgeType::Contains => registry_provider::EdgeType::Contains,
             EdgeType::Consumes => registry_provider::EdgeType::Consumes,
             EdgeType::Produces => registry_provider::EdgeType::Produces,
+            EdgeType::ConsumedBy => registry_provider::EdgeType::ConsumedBy,
+            EdgeType::DependsOn => registry_provider::EdgeType::DependsOn,
+            EdgeType::SimilarTo => registry_provider::EdgeType::SimilarTo,
         }
     }
 }
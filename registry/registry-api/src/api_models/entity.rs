@@ -1,14 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
 use chrono::{Utc, DateTime};
 use poem_openapi::{Enum, Object};
 use registry_provider::EntityProperty;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use super::{EntityAttributes, Relationship};
+use super::{parse_uuid, EntityAttributes, Relationship};
+use crate::error::ApiError;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Enum)]
+/**
+ * Max number of ids in one `entities:batchGet` request
+ */
+pub const MAX_BATCH_GET_SIZE: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Enum)]
 pub enum EntityType {
     #[oai(rename = "unknown")]
     Unknown,
@@ -23,6 +30,10 @@ pub enum EntityType {
     AnchorFeature,
     #[oai(rename = "feathr_derived_feature_v1")]
     DerivedFeature,
+    #[oai(rename = "feathr_materialization_record_v1")]
+    MaterializationRecord,
+    #[oai(rename = "feathr_dataset_v1")]
+    Dataset,
 }
 
 impl From<registry_provider::EntityType> for EntityType {
@@ -34,10 +45,42 @@ impl From<registry_provider::EntityType> for EntityType {
             registry_provider::EntityType::Anchor => EntityType::Anchor,
             registry_provider::EntityType::AnchorFeature => EntityType::AnchorFeature,
             registry_provider::EntityType::DerivedFeature => EntityType::DerivedFeature,
+            registry_provider::EntityType::MaterializationRecord => {
+                EntityType::MaterializationRecord
+            }
+            registry_provider::EntityType::Dataset => EntityType::Dataset,
         }
     }
 }
 
+impl From<EntityType> for registry_provider::EntityType {
+    fn from(v: EntityType) -> Self {
+        match v {
+            EntityType::Unknown => registry_provider::EntityType::Unknown,
+            EntityType::Project => registry_provider::EntityType::Project,
+            EntityType::Source => registry_provider::EntityType::Source,
+            EntityType::Anchor => registry_provider::EntityType::Anchor,
+            EntityType::AnchorFeature => registry_provider::EntityType::AnchorFeature,
+            EntityType::DerivedFeature => registry_provider::EntityType::DerivedFeature,
+            EntityType::MaterializationRecord => {
+                registry_provider::EntityType::MaterializationRecord
+            }
+            EntityType::Dataset => registry_provider::EntityType::Dataset,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "lowercase")]
+#[oai(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Full text search on names and doc strings
+    #[default]
+    Keyword,
+    /// Blend vector similarity of the embeddings with the keyword search score
+    Semantic,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 #[oai(rename_all = "camelCase")]
 pub struct Entity {
@@ -53,6 +96,10 @@ pub struct Entity {
     pub attributes: EntityAttributes,
     pub created_by: String,
     pub created_on: DateTime<Utc>,
+    /// Documentation in markdown
+    #[oai(default, skip_serializing_if = "String::is_empty")]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub docs: String,
 }
 
 impl From<registry_provider::Entity<EntityProperty>> for Entity {
@@ -68,14 +115,20 @@ impl From<registry_provider::Entity<EntityProperty>> for Entity {
             labels: v.properties.labels.clone(),
             created_by: v.properties.created_by.clone(),
             created_on: v.properties.created_on.clone(),
+            docs: v.properties.docs.clone(),
             attributes: v.properties.into(),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
 pub struct Entities {
     pub entities: Vec<Entity>,
+    /// Pass it as `cursor` to get the next page, only set by searches with more results
+    #[oai(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 impl FromIterator<registry_provider::Entity<EntityProperty>> for Entities {
@@ -84,6 +137,7 @@ impl FromIterator<registry_provider::Entity<EntityProperty>> for Entities {
     ) -> Self {
         Self {
             entities: iter.into_iter().map(|e| e.into()).collect(),
+            next_cursor: None,
         }
     }
 }
@@ -94,6 +148,46 @@ impl From<Vec<registry_provider::Entity<EntityProperty>>> for Entities {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+pub struct BatchGetEntitiesRequest {
+    /// Entity GUIDs, at most `MAX_BATCH_GET_SIZE`
+    pub ids: Vec<String>,
+}
+
+impl BatchGetEntitiesRequest {
+    /**
+     * Parsed ids in the request order with duplicates removed
+     */
+    pub fn parse_ids(&self) -> Result<Vec<Uuid>, ApiError> {
+        if self.ids.len() > MAX_BATCH_GET_SIZE {
+            return Err(ApiError::BadRequest(format!(
+                "At most {} ids can be requested at once, got {}",
+                MAX_BATCH_GET_SIZE,
+                self.ids.len()
+            )));
+        }
+        let mut seen = HashSet::new();
+        let mut ids = vec![];
+        for id in &self.ids {
+            let id = parse_uuid(id)?;
+            if seen.insert(id) {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[oai(rename_all = "camelCase")]
+pub struct BatchGetEntitiesResponse {
+    /// Entities in the request order, without lineage
+    pub entities: Vec<Entity>,
+    /// Requested ids that don't exist or the caller can't read
+    pub not_found: Vec<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
 #[oai(rename_all = "camelCase")]
 pub struct EntityUniqueAttributes {
@@ -123,6 +217,10 @@ impl EntityRef {
             },
         }
     }
+
+    pub fn guid(&self) -> &str {
+        &self.guid
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Object)]
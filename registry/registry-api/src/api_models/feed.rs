@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+
+use super::EntityType;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "lowercase")]
+#[oai(rename_all = "lowercase")]
+pub enum ChangeType {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/**
+ * One change to an entity, `index` is the Raft log index of the request made the change
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct FeedEntry {
+    pub index: u64,
+    /// Time the change was applied, it may differ slightly between nodes
+    pub time: DateTime<Utc>,
+    pub change_type: ChangeType,
+    pub guid: String,
+    pub entity_type: EntityType,
+    pub qualified_name: String,
+    pub version: u64,
+}
+
+/**
+ * Changes after `since` in the order they were applied
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct FeedPage {
+    pub changes: Vec<FeedEntry>,
+    /// Pass it as `since` in the next poll
+    pub next_since: u64,
+    /// False if some changes after `since` are no longer kept, a full export is needed to catch up
+    pub complete: bool,
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use poem_openapi::{Enum, Object};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -7,14 +8,26 @@ use uuid::Uuid;
 use crate::error::ApiError;
 
 mod attributes;
+mod diff;
+mod duplicates;
 mod edge;
 mod entity;
+mod feed;
 mod rbac;
+mod release;
+mod summary;
+mod template;
 
 pub use attributes::*;
+pub use diff::*;
+pub use duplicates::*;
 pub use edge::*;
 pub use entity::*;
+pub use feed::*;
 pub use rbac::*;
+pub use release::*;
+pub use summary::*;
+pub use template::*;
 
 fn parse_uuid(s: &str) -> Result<Uuid, ApiError> {
     Uuid::parse_str(s).map_err(|_| ApiError::BadRequest(format!("Invalid GUID `{}`", s)))
@@ -31,6 +44,14 @@ pub struct ProjectDef {
     pub qualified_name: String,
     #[oai(default)]
     pub tags: HashMap<String, String>,
+    #[oai(default)]
+    #[serde(default)]
+    pub ownership: Ownership,
+    /// Tags merged into the tags of every entity created under the project, tags set on the
+    /// entity take precedence
+    #[oai(default)]
+    #[serde(default)]
+    pub default_tags: HashMap<String, String>,
     #[oai(skip)]
     pub created_by: String,
 }
@@ -43,6 +64,8 @@ impl TryInto<registry_provider::ProjectDef> for ProjectDef {
             id: Uuid::parse_str(&self.id).map_err(|e| ApiError::BadRequest(e.to_string()))?,
             qualified_name: self.qualified_name,
             tags: self.tags,
+            ownership: self.ownership.into(),
+            default_tags: self.default_tags,
             created_by: self.created_by,
         })
     }
@@ -108,6 +131,22 @@ pub struct AnchorDef {
     #[oai(skip)]
     pub qualified_name: String,
     pub source_id: String,
+    /// Sources joined with `sourceId` on `joinKeys`, e.g. dimension tables enriching the fact table
+    #[oai(default)]
+    #[serde(default)]
+    pub source_ids: Vec<String>,
+    /// Columns all sources are joined on, required if `sourceIds` is not empty
+    #[oai(default)]
+    #[serde(default)]
+    pub join_keys: Vec<String>,
+    /// Applied after the preprocessing of the sources, so anchors sharing a source can preprocess it differently
+    #[oai(default)]
+    #[serde(default)]
+    pub preprocessing: Option<String>,
+    /// Window of the window aggregation features not setting their own, e.g. `7d`
+    #[oai(default)]
+    #[serde(default)]
+    pub default_window: Option<String>,
     #[oai(default)]
     pub tags: HashMap<String, String>,
     #[oai(skip)]
@@ -123,6 +162,14 @@ impl TryInto<registry_provider::AnchorDef> for AnchorDef {
             name: self.name,
             qualified_name: self.qualified_name,
             source_id: parse_uuid(&self.source_id)?,
+            source_ids: self
+                .source_ids
+                .iter()
+                .map(|id| parse_uuid(id))
+                .collect::<Result<_, _>>()?,
+            join_keys: self.join_keys,
+            preprocessing: self.preprocessing,
+            default_window: self.default_window,
             tags: self.tags,
             created_by: self.created_by,
         })
@@ -354,6 +401,9 @@ pub struct AnchorFeatureDef {
     pub key: Vec<TypedKey>,
     #[oai(default)]
     pub tags: HashMap<String, String>,
+    #[oai(default)]
+    #[serde(default)]
+    pub ownership: Ownership,
     #[oai(skip)]
     pub created_by: String,
 }
@@ -374,6 +424,7 @@ impl TryInto<registry_provider::AnchorFeatureDef> for AnchorFeatureDef {
                 .map(|e| e.try_into())
                 .collect::<Result<_, _>>()?,
             tags: self.tags,
+            ownership: self.ownership.into(),
             created_by: self.created_by,
         })
     }
@@ -395,12 +446,32 @@ pub struct DerivedFeatureDef {
     pub input_anchor_features: Vec<String>,
     #[oai(validator(unique_items), default)]
     pub input_derived_features: Vec<String>,
+    /// Ids or qualified names of anchor or derived features, which may belong to other projects the
+    /// caller can read, e.g. `shared_project__anchor1__feature1`
+    #[oai(validator(unique_items), default)]
+    #[serde(default)]
+    pub input_features: Vec<String>,
     #[oai(default)]
     pub tags: HashMap<String, String>,
+    #[oai(default)]
+    #[serde(default)]
+    pub ownership: Ownership,
     #[oai(skip)]
     pub created_by: String,
 }
 
+impl DerivedFeatureDef {
+    /**
+     * All input references, ids of the typed lists and ids or names of `input_features`
+     */
+    pub fn input_refs(&self) -> impl Iterator<Item = &String> {
+        self.input_anchor_features
+            .iter()
+            .chain(self.input_derived_features.iter())
+            .chain(self.input_features.iter())
+    }
+}
+
 impl TryInto<registry_provider::DerivedFeatureDef> for DerivedFeatureDef {
     type Error = ApiError;
 
@@ -427,11 +498,123 @@ impl TryInto<registry_provider::DerivedFeatureDef> for DerivedFeatureDef {
                 .map(|s| parse_uuid(&s))
                 .collect::<Result<_, _>>()?,
             tags: self.tags,
+            ownership: self.ownership.into(),
+            created_by: self.created_by,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct MaterializationRecordDef {
+    #[oai(skip)]
+    pub id: String,
+    pub name: String,
+    #[oai(skip)]
+    pub qualified_name: String,
+    pub sink: String,
+    #[oai(validator(unique_items), default)]
+    pub features: Vec<String>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub job_id: String,
+    #[oai(default)]
+    pub tags: HashMap<String, String>,
+    #[oai(skip)]
+    pub created_by: String,
+}
+
+impl TryInto<registry_provider::MaterializationRecordDef> for MaterializationRecordDef {
+    type Error = ApiError;
+
+    fn try_into(self) -> Result<registry_provider::MaterializationRecordDef, Self::Error> {
+        if self.window_start > self.window_end {
+            return Err(ApiError::BadRequest(format!(
+                "Invalid materialization window {} - {}",
+                self.window_start, self.window_end
+            )));
+        }
+        Ok(registry_provider::MaterializationRecordDef {
+            id: Uuid::parse_str(&self.id).map_err(|e| ApiError::BadRequest(e.to_string()))?,
+            name: self.name,
+            qualified_name: self.qualified_name,
+            sink: self.sink,
+            features: self
+                .features
+                .into_iter()
+                .map(|s| parse_uuid(&s))
+                .collect::<Result<_, _>>()?,
+            window_start: self.window_start,
+            window_end: self.window_end,
+            job_id: self.job_id,
+            tags: self.tags,
             created_by: self.created_by,
         })
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct DatasetDef {
+    #[oai(skip)]
+    pub id: String,
+    pub name: String,
+    #[oai(skip)]
+    pub qualified_name: String,
+    pub path: String,
+    #[oai(validator(unique_items), default)]
+    pub features: Vec<String>,
+    #[oai(default)]
+    pub job_id: String,
+    #[oai(default)]
+    pub tags: HashMap<String, String>,
+    #[oai(skip)]
+    pub created_by: String,
+}
+
+impl TryInto<registry_provider::DatasetDef> for DatasetDef {
+    type Error = ApiError;
+
+    fn try_into(self) -> Result<registry_provider::DatasetDef, Self::Error> {
+        if self.features.is_empty() {
+            return Err(ApiError::BadRequest(format!(
+                "Dataset {} doesn't consume any feature",
+                self.name
+            )));
+        }
+        Ok(registry_provider::DatasetDef {
+            id: Uuid::parse_str(&self.id).map_err(|e| ApiError::BadRequest(e.to_string()))?,
+            name: self.name,
+            qualified_name: self.qualified_name,
+            path: self.path,
+            features: self
+                .features
+                .into_iter()
+                .map(|s| parse_uuid(&s))
+                .collect::<Result<_, _>>()?,
+            job_id: self.job_id,
+            tags: self.tags,
+            created_by: self.created_by,
+        })
+    }
+}
+
+/**
+ * Preview rows of a data source
+ */
+#[derive(Clone, Debug, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct SourceSample {
+    pub guid: String,
+    /// Each row is an object keyed by the column name
+    pub rows: Vec<serde_json::Value>,
+    /// Samples are cached, this is the time the source was actually read
+    pub sampled_at: DateTime<Utc>,
+}
+
 #[derive(Clone, Debug, Serialize, Object)]
 pub struct CreationResponse {
     pub guid: String,
@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
-use poem_openapi::Object;
-use registry_provider::{Permission, RbacRecord};
+use poem_openapi::{Enum, Object};
+use registry_provider::{Credential, Permission, RbacRecord};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize, Object)]
@@ -50,3 +50,89 @@ pub fn into_user_roles(permissions: impl IntoIterator<Item = RbacRecord>) -> Vec
         })
         .collect()
 }
+
+fn role_name(permission: Permission) -> &'static str {
+    match permission {
+        Permission::Read => "consumer",
+        Permission::Write => "producer",
+        Permission::Admin => "admin",
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "lowercase")]
+#[oai(rename_all = "lowercase")]
+pub enum RbacChangeType {
+    Granted,
+    Revoked,
+    /// Permissions may have changed in any way, e.g. a snapshot has been installed or the
+    /// subscriber has missed some events, all cached permissions must be dropped
+    Reset,
+}
+
+/**
+ * A permission change, `index` is the Raft log index of the request made the change.
+ * Every node emits the events when applying the log, so subscribing to any node is enough.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct RbacEvent {
+    pub index: u64,
+    pub change_type: RbacChangeType,
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_name: Option<String>,
+    #[oai(skip_serializing_if_is_none)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role_name: Option<String>,
+}
+
+impl RbacEvent {
+    pub fn new(
+        index: u64,
+        change_type: RbacChangeType,
+        tenant: &str,
+        scope: &str,
+        user: &Credential,
+        permission: Permission,
+    ) -> Self {
+        Self {
+            index,
+            change_type,
+            tenant: Some(tenant.to_string()).filter(|t| !t.is_empty()),
+            scope: Some(scope.to_string()),
+            user_name: Some(user.to_string()),
+            role_name: Some(role_name(permission).to_string()),
+        }
+    }
+
+    pub fn reset(index: u64) -> Self {
+        Self {
+            index,
+            change_type: RbacChangeType::Reset,
+            tenant: None,
+            scope: None,
+            user_name: None,
+            role_name: None,
+        }
+    }
+
+    /**
+     * Reset events are visible to everyone, other events are visible in their tenant, to the
+     * affected user if `user` is set, or to everyone otherwise
+     */
+    pub fn is_visible(&self, tenant: &str, user: Option<&str>) -> bool {
+        if self.change_type == RbacChangeType::Reset {
+            return true;
+        }
+        self.tenant.as_deref().unwrap_or_default() == tenant
+            && user.map_or(true, |u| self.user_name.as_deref() == Some(u))
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct ReleaseDef {
+    pub name: String,
+    /// Pin the versions labeled with the environment, or the latest versions if not set
+    #[oai(default)]
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[oai(skip)]
+    pub created_by: String,
+}
+
+impl From<ReleaseDef> for registry_provider::ReleaseDef {
+    fn from(v: ReleaseDef) -> Self {
+        Self {
+            name: v.name,
+            environment: v.environment,
+            created_by: v.created_by,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct Release {
+    pub name: String,
+    #[oai(skip_serializing_if_is_none)]
+    pub environment: Option<String>,
+    /// GUIDs of the pinned entity versions
+    pub entities: Vec<String>,
+    pub created_by: String,
+    pub created_on: DateTime<Utc>,
+}
+
+impl From<registry_provider::Release> for Release {
+    fn from(v: registry_provider::Release) -> Self {
+        Self {
+            name: v.name,
+            environment: v.environment,
+            entities: v.entities.into_iter().map(|id| id.to_string()).collect(),
+            created_by: v.created_by,
+            created_on: v.created_on,
+        }
+    }
+}
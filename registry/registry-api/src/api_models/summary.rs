@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct TagCount {
+    pub key: String,
+    pub value: String,
+    pub count: usize,
+}
+
+impl From<registry_provider::TagCount> for TagCount {
+    fn from(v: registry_provider::TagCount) -> Self {
+        Self {
+            key: v.key,
+            value: v.value,
+            count: v.count,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct ProjectSummary {
+    /// Number of entities per type name, e.g. `feathr_source_v1`
+    pub entity_counts: HashMap<String, usize>,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub top_tags: Vec<TagCount>,
+    /// Number of features per value type, e.g. `FLOAT`
+    pub feature_types: HashMap<String, usize>,
+}
+
+impl From<registry_provider::ProjectSummary> for ProjectSummary {
+    fn from(v: registry_provider::ProjectSummary) -> Self {
+        Self {
+            entity_counts: v
+                .entity_counts
+                .into_iter()
+                .map(|(t, c)| (t.get_name().to_string(), c))
+                .collect(),
+            last_modified: v.last_modified,
+            top_tags: v.top_tags.into_iter().map(|t| t.into()).collect(),
+            feature_types: v.feature_types,
+        }
+    }
+}
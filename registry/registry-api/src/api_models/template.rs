@@ -0,0 +1,141 @@
+use std::collections::{HashMap, HashSet};
+
+use poem_openapi::{types::ParseFromJSON, Object};
+use serde::{Deserialize, Serialize};
+
+use crate::{AnchorFeatureDef, ApiError, SourceDef};
+
+/**
+ * Anchor created by a project template, `source` is the name of a source of the template
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct TemplateAnchor {
+    pub name: String,
+    pub source: String,
+    #[oai(default)]
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    #[oai(default)]
+    #[serde(default)]
+    pub features: Vec<AnchorFeatureDef>,
+}
+
+/**
+ * Baseline of new projects defined in the server config, e.g. the `PASSTHROUGH` source and the
+ * common request features, the entities are created along with the project
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Object)]
+#[serde(rename_all = "camelCase")]
+#[oai(rename_all = "camelCase")]
+pub struct ProjectTemplate {
+    pub name: String,
+    #[oai(default)]
+    #[serde(default)]
+    pub description: String,
+    /// Tags of the new project, the tags in the project definition take precedence
+    #[oai(default)]
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    #[oai(default)]
+    #[serde(default)]
+    pub sources: Vec<SourceDef>,
+    #[oai(default)]
+    #[serde(default)]
+    pub anchors: Vec<TemplateAnchor>,
+}
+
+impl ProjectTemplate {
+    /**
+     * Templates are a JSON array in the same format as the API, e.g. sources are in the format of
+     * the body of `POST /projects/{project}/datasources`
+     */
+    pub fn parse_list(json: &str) -> Result<Vec<Self>, ApiError> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        let templates = Vec::<Self>::parse_from_json(Some(value))
+            .map_err(|e| ApiError::BadRequest(e.into_message()))?;
+        let mut names = HashSet::new();
+        for template in &templates {
+            if !names.insert(template.name.as_str()) {
+                return Err(ApiError::BadRequest(format!(
+                    "Duplicated project template `{}`",
+                    template.name
+                )));
+            }
+            template.validate()?;
+        }
+        Ok(templates)
+    }
+
+    /**
+     * Names must be unique within their scopes, and anchors must use the sources of the template
+     */
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let invalid = |msg: String| {
+            Err(ApiError::BadRequest(format!(
+                "Invalid project template `{}`, {}",
+                self.name, msg
+            )))
+        };
+        let mut sources = HashSet::new();
+        for source in &self.sources {
+            if !sources.insert(source.name.as_str()) {
+                return invalid(format!("duplicated source `{}`", source.name));
+            }
+        }
+        let mut anchors = HashSet::new();
+        for anchor in &self.anchors {
+            if !anchors.insert(anchor.name.as_str()) {
+                return invalid(format!("duplicated anchor `{}`", anchor.name));
+            }
+            if !sources.contains(anchor.source.as_str()) {
+                return invalid(format!(
+                    "anchor `{}` uses unknown source `{}`",
+                    anchor.name, anchor.source
+                ));
+            }
+            let mut features = HashSet::new();
+            for feature in &anchor.features {
+                if !features.insert(feature.name.as_str()) {
+                    return invalid(format!("duplicated feature `{}`", feature.name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_templates() {
+        let json = r#"[{
+            "name": "standard",
+            "tags": {"team": "unknown"},
+            "sources": [{"name": "PASSTHROUGH", "type": "PASSTHROUGH"}],
+            "anchors": [{
+                "name": "request_features",
+                "source": "PASSTHROUGH",
+                "features": [{
+                    "name": "f_request_time",
+                    "featureType": {"type": "TENSOR", "tensorCategory": "DENSE", "dimensionType": [], "valType": "LONG"},
+                    "transformation": {"transformExpr": "request_time"},
+                    "key": [{"keyColumn": "NOT_NEEDED", "keyColumnType": "UNSPECIFIED"}]
+                }]
+            }]
+        }]"#;
+        let templates = ProjectTemplate::parse_list(json).unwrap();
+        assert_eq!(templates.len(), 1);
+        assert_eq!(templates[0].sources[0].source_type, "PASSTHROUGH");
+        assert_eq!(templates[0].anchors[0].features[0].name, "f_request_time");
+
+        let json = r#"[{"name": "t", "anchors": [{"name": "a", "source": "s"}]}]"#;
+        assert!(ProjectTemplate::parse_list(json).is_err());
+        let json = r#"[{"name": "t"}, {"name": "t"}]"#;
+        assert!(ProjectTemplate::parse_list(json).is_err());
+    }
+}
@@ -1,33 +1,74 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use common_utils::{set, Blank};
 use log::debug;
 use registry_provider::{
-    Credential, Edge, EdgeType, EntityProperty, EntityType, Permission, RbacProvider, RbacRecord,
+    Credential, Edge, EdgeType, EntityPropMutator, EntityProperty, EntityType,
+    GarbageCollectionReport, LoadPolicy, LoadReport, Permission, RbacProvider, RbacRecord,
     RegistryError, RegistryProvider,
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    into_user_roles, AnchorDef, AnchorFeatureDef, ApiError, DerivedFeatureDef, Entities, Entity,
-    EntityAttributes, EntityLineage, EntityRef, IntoApiResult, ProjectDef, RbacResponse, SourceDef,
+    group_duplicates, into_user_roles, AnchorDef, AnchorFeatureDef, ApiError, DatasetDef,
+    DerivedFeatureDef, DuplicateGroup, DuplicateReport, Entities, Entity, EntityAttributes,
+    EntityDiff, EntityLineage, EntityRef, FeatureFingerprint, IntoApiResult,
+    MaterializationRecordDef, Ownership, ProjectDef, RbacResponse, Release, ReleaseDef, SearchMode,
+    SourceDef,
 };
 
+/**
+ * Default and maximum number of entities in one page of search results
+ */
+const DEFAULT_SEARCH_SIZE: usize = 100;
+const MAX_SEARCH_SIZE: usize = 1000;
+
+/**
+ * Client supplied key of a creation request, retries with the same key get the response of the
+ * first request instead of creating another version.
+ * Both timestamps are set by the node received the request, so all nodes agree on the expiration.
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdempotencyKey {
+    pub key: String,
+    /// Hash of the request as sent by the client and the credential sent it, a request reusing
+    /// the key must have the same fingerprint
+    #[serde(default)]
+    pub fingerprint: String,
+    pub received_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum FeathrApiRequest {
     GetProjects {
         keyword: Option<String>,
         size: Option<usize>,
         offset: Option<usize>,
+        /// Only the projects readable by the credential are listed, all projects if `None`
+        #[serde(default)]
+        credential: Option<Credential>,
     },
     GetProject {
         id_or_name: String,
     },
     GetProjectLineage {
         id_or_name: String,
+        /// Only the entity versions pinned by the release
+        #[serde(default)]
+        release: Option<String>,
+        /// Entities of other projects the credential can't read are dropped, none if `None`
+        #[serde(default)]
+        credential: Option<Credential>,
+    },
+    GetProjectSummary {
+        id_or_name: String,
+    },
+    GetProjectDuplicates {
+        id_or_name: String,
     },
     GetProjectFeatures {
         project_id_or_name: String,
@@ -135,20 +176,163 @@ pub enum FeathrApiRequest {
         anchor_id_or_name: String,
         definition: AnchorFeatureDef,
     },
+    GetProjectEntities {
+        project_id_or_name: String,
+        types: HashSet<registry_provider::EntityType>,
+        /// Tag filters, entities must have all of the keys, and the values if they're set
+        tags: HashMap<String, Option<String>>,
+        size: Option<usize>,
+        offset: Option<usize>,
+    },
+    SearchProject {
+        project_id_or_name: String,
+        keyword: String,
+        types: HashSet<registry_provider::EntityType>,
+        mode: SearchMode,
+        size: Option<usize>,
+        offset: Option<usize>,
+        /// Keyword search pages with the cursor instead of the offset if it's not set
+        #[serde(default)]
+        cursor: Option<String>,
+        /// Embedding of the keyword in `semantic` mode, computed before the request is handled so
+        /// the registry isn't locked while calling the embedder, `None` if semantic search is
+        /// disabled
+        #[serde(default)]
+        query_vector: Option<Vec<f32>>,
+    },
     GetFeature {
         id_or_name: String,
     },
     GetFeatureLineage {
         id_or_name: String,
+        /// Entities of other projects the credential can't read are dropped, none if `None`
+        #[serde(default)]
+        credential: Option<Credential>,
+    },
+    GetFeatureVersionDiff {
+        id_or_name: String,
+        from_version: Option<u64>,
+        to_version: Option<u64>,
     },
     GetEntityProject {
         id_or_name: String,
     },
+    GetDataSource {
+        id_or_name: String,
+    },
+    GetSourceConsumers {
+        id_or_name: String,
+    },
+    GetRelatedEntities {
+        id_or_name: String,
+        edge_type: Option<registry_provider::EdgeType>,
+    },
+    GetProjectMaterializationRecords {
+        project_id_or_name: String,
+        keyword: Option<String>,
+        size: Option<usize>,
+        offset: Option<usize>,
+    },
+    GetFeatureMaterializationRecords {
+        id_or_name: String,
+    },
+    CreateMaterializationRecord {
+        project_id_or_name: String,
+        definition: MaterializationRecordDef,
+    },
+    GetProjectDatasets {
+        project_id_or_name: String,
+        keyword: Option<String>,
+        size: Option<usize>,
+        offset: Option<usize>,
+    },
+    GetFeatureDatasets {
+        id_or_name: String,
+    },
+    CreateDataset {
+        project_id_or_name: String,
+        definition: DatasetDef,
+    },
+    SetEntityOwnership {
+        id_or_name: String,
+        ownership: Ownership,
+    },
+    GetEntity {
+        id_or_name: String,
+    },
+    GetEntities {
+        ids: Vec<Uuid>,
+    },
+    SetEntityDocs {
+        id_or_name: String,
+        docs: String,
+    },
+    /// `deleted_at` is set by the node received the request so all nodes expire the tombstone at
+    /// the same time, it's missing in the log entries written by older versions
+    DeleteEntity {
+        id_or_name: String,
+        #[serde(default)]
+        deleted_at: Option<DateTime<Utc>>,
+    },
+    DeleteProject {
+        id_or_name: String,
+        cascade: bool,
+        dry_run: bool,
+        #[serde(default)]
+        deleted_at: Option<DateTime<Utc>>,
+    },
+    SetSourceVerified {
+        project_id_or_name: String,
+        id_or_name: String,
+        verified_at: DateTime<Utc>,
+    },
+    PromoteEntity {
+        id_or_name: String,
+        environment: String,
+    },
+    DemoteEntity {
+        id_or_name: String,
+        environment: String,
+    },
+    GetProjectReleases {
+        project_id_or_name: String,
+    },
+    GetProjectRelease {
+        project_id_or_name: String,
+        name: String,
+    },
+    CreateRelease {
+        project_id_or_name: String,
+        definition: ReleaseDef,
+    },
+    DeleteRelease {
+        project_id_or_name: String,
+        name: String,
+    },
+    /// Record `SimilarTo` edges between the near-duplicate features of the project
+    LinkSimilarFeatures {
+        project_id_or_name: String,
+    },
     // Raft specific
     BatchLoad {
         entities: Vec<registry_provider::Entity<EntityProperty>>,
         edges: Vec<Edge>,
         permissions: Vec<RbacRecord>,
+        /// How entities conflicting with the existing content are handled
+        #[serde(default)]
+        policy: LoadPolicy,
+    },
+    /// Remove tombstones of entities deleted before `before`, which is set by the node received
+    /// the request so all nodes remove the same entities
+    CollectGarbage {
+        before: DateTime<Utc>,
+        dry_run: bool,
+    },
+    /// Reject the user token with the hash until it expires, applied to the token cache of every
+    /// node by the Raft state machine
+    RevokeToken {
+        hash: String,
+        expires: DateTime<Utc>,
     },
     // RBAC
     GetUserRoles,
@@ -166,10 +350,67 @@ pub enum FeathrApiRequest {
         requestor: Credential,
         reason: String,
     },
+    // Multi-tenancy
+    InTenant {
+        tenant: String,
+        request: Box<FeathrApiRequest>,
+    },
+    // Idempotent creation, the key is handled by the Raft state machine
+    Idempotent {
+        key: IdempotencyKey,
+        request: Box<FeathrApiRequest>,
+    },
 }
 
 impl FeathrApiRequest {
+    /**
+     * Scope the request to the tenant, requests in the default tenant are left unchanged
+     */
+    pub fn in_tenant(self, tenant: &str) -> Self {
+        match self {
+            Self::InTenant { .. } => self,
+            _ if tenant.is_empty() => self,
+            _ => Self::InTenant {
+                tenant: tenant.to_string(),
+                request: Box::new(self),
+            },
+        }
+    }
+
+    /**
+     * Attach the idempotency key to the request, if any
+     */
+    pub fn idempotent(self, key: Option<IdempotencyKey>) -> Self {
+        match key {
+            Some(key) => Self::Idempotent {
+                key,
+                request: Box::new(self),
+            },
+            None => self,
+        }
+    }
+
+    /**
+     * The tenant and the idempotency key of the request, if any
+     */
+    pub fn idempotency_key(&self) -> Option<(&str, &IdempotencyKey)> {
+        match self {
+            Self::InTenant { tenant, request } => request
+                .idempotency_key()
+                .map(|(_, key)| (tenant.as_str(), key)),
+            Self::Idempotent { key, .. } => Some(("", key)),
+            _ => None,
+        }
+    }
+
     pub fn is_writing_request(&self) -> bool {
+        if let Self::InTenant { request, .. } | Self::Idempotent { request, .. } = self {
+            return request.is_writing_request();
+        }
+        if let Self::DeleteProject { dry_run, .. } | Self::CollectGarbage { dry_run, .. } = self {
+            // Dry runs only read the registry
+            return !dry_run;
+        }
         matches!(
             &self,
             Self::CreateProject { .. }
@@ -177,11 +418,44 @@ impl FeathrApiRequest {
                 | Self::CreateProjectAnchor { .. }
                 | Self::CreateAnchorFeature { .. }
                 | Self::CreateProjectDerivedFeature { .. }
+                | Self::CreateMaterializationRecord { .. }
+                | Self::CreateDataset { .. }
+                | Self::SetEntityOwnership { .. }
+                | Self::SetEntityDocs { .. }
+                | Self::DeleteEntity { .. }
+                | Self::SetSourceVerified { .. }
+                | Self::PromoteEntity { .. }
+                | Self::DemoteEntity { .. }
+                | Self::CreateRelease { .. }
+                | Self::DeleteRelease { .. }
+                | Self::LinkSimilarFeatures { .. }
                 | Self::BatchLoad { .. }
                 | Self::AddUserRole { .. }
                 | Self::DeleteUserRole { .. }
+                | Self::RevokeToken { .. }
         )
     }
+
+    /**
+     * Requests answered by the FTS index, which is committed asynchronously
+     */
+    pub fn is_search_request(&self) -> bool {
+        match self {
+            Self::InTenant { request, .. } | Self::Idempotent { request, .. } => {
+                request.is_search_request()
+            }
+            Self::SearchProject { .. } => true,
+            Self::GetProjects { keyword, .. }
+            | Self::GetProjectFeatures { keyword, .. }
+            | Self::GetProjectDataSources { keyword, .. }
+            | Self::GetProjectAnchors { keyword, .. }
+            | Self::GetProjectDerivedFeatures { keyword, .. }
+            | Self::GetAnchorFeatures { keyword, .. }
+            | Self::GetProjectMaterializationRecords { keyword, .. }
+            | Self::GetProjectDatasets { keyword, .. } => !keyword.is_blank(),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -193,11 +467,26 @@ pub enum FeathrApiResponse {
     EntityNames(Vec<String>),
     Entity(Entity),
     Entities(Entities),
+    EntityDiff(EntityDiff),
     EntityLineage(EntityLineage),
+    ProjectSummary(ProjectSummary),
     UserRoles(Vec<RbacResponse>),
+    Release(Release),
+    Releases(Vec<Release>),
+    LoadReport(LoadReport),
+    DuplicateReport(DuplicateReport),
+    GarbageCollectionReport(GarbageCollectionReport),
 }
 
 impl FeathrApiResponse {
+    pub fn into_unit(self) -> poem::Result<()> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::Unit => Ok(()),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
     pub fn into_uuid_and_version(self) -> poem::Result<(Uuid, u64)> {
         match self {
             FeathrApiResponse::Error(e) => Err(e.into()),
@@ -229,6 +518,15 @@ impl FeathrApiResponse {
             _ => panic!("Shouldn't reach here"),
         }
     }
+
+    pub fn into_entity_diff(self) -> poem::Result<EntityDiff> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::EntityDiff(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
     pub fn into_lineage(self) -> poem::Result<EntityLineage> {
         match self {
             FeathrApiResponse::Error(e) => Err(e.into()),
@@ -237,6 +535,14 @@ impl FeathrApiResponse {
         }
     }
 
+    pub fn into_project_summary(self) -> poem::Result<ProjectSummary> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::ProjectSummary(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
     pub fn into_user_roles(self) -> poem::Result<Vec<RbacResponse>> {
         match self {
             FeathrApiResponse::Error(e) => Err(e.into()),
@@ -244,6 +550,46 @@ impl FeathrApiResponse {
             _ => panic!("Shouldn't reach here"),
         }
     }
+
+    pub fn into_release(self) -> poem::Result<Release> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::Release(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_releases(self) -> poem::Result<Vec<Release>> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::Releases(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_load_report(self) -> poem::Result<LoadReport> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::LoadReport(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_duplicate_report(self) -> poem::Result<DuplicateReport> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::DuplicateReport(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
+
+    pub fn into_garbage_collection_report(self) -> poem::Result<GarbageCollectionReport> {
+        match self {
+            FeathrApiResponse::Error(e) => Err(e.into()),
+            FeathrApiResponse::GarbageCollectionReport(v) => Ok(v),
+            _ => panic!("Shouldn't reach here"),
+        }
+    }
 }
 
 impl From<RegistryError> for FeathrApiResponse {
@@ -278,7 +624,16 @@ impl From<Entity> for FeathrApiResponse {
 
 impl From<Vec<Entity>> for FeathrApiResponse {
     fn from(v: Vec<Entity>) -> Self {
-        Self::Entities(Entities { entities: v })
+        Self::Entities(Entities {
+            entities: v,
+            next_cursor: None,
+        })
+    }
+}
+
+impl From<Entities> for FeathrApiResponse {
+    fn from(v: Entities) -> Self {
+        Self::Entities(v)
     }
 }
 
@@ -306,18 +661,60 @@ impl From<(Vec<Entity>, Vec<Edge>)> for FeathrApiResponse {
     }
 }
 
+impl From<EntityDiff> for FeathrApiResponse {
+    fn from(v: EntityDiff) -> Self {
+        Self::EntityDiff(v)
+    }
+}
+
 impl From<EntityLineage> for FeathrApiResponse {
     fn from(v: EntityLineage) -> Self {
         Self::EntityLineage(v)
     }
 }
 
+impl From<registry_provider::ProjectSummary> for FeathrApiResponse {
+    fn from(v: registry_provider::ProjectSummary) -> Self {
+        Self::ProjectSummary(v.into())
+    }
+}
+
 impl From<Vec<RbacRecord>> for FeathrApiResponse {
     fn from(v: Vec<RbacRecord>) -> Self {
         Self::UserRoles(into_user_roles(v))
     }
 }
 
+impl From<registry_provider::Release> for FeathrApiResponse {
+    fn from(v: registry_provider::Release) -> Self {
+        Self::Release(v.into())
+    }
+}
+
+impl From<Vec<registry_provider::Release>> for FeathrApiResponse {
+    fn from(v: Vec<registry_provider::Release>) -> Self {
+        Self::Releases(v.into_iter().map(|r| r.into()).collect())
+    }
+}
+
+impl From<LoadReport> for FeathrApiResponse {
+    fn from(v: LoadReport) -> Self {
+        Self::LoadReport(v)
+    }
+}
+
+impl From<DuplicateReport> for FeathrApiResponse {
+    fn from(v: DuplicateReport) -> Self {
+        Self::DuplicateReport(v)
+    }
+}
+
+impl From<GarbageCollectionReport> for FeathrApiResponse {
+    fn from(v: GarbageCollectionReport) -> Self {
+        Self::GarbageCollectionReport(v)
+    }
+}
+
 impl<T, E> From<Result<T, E>> for FeathrApiResponse
 where
     FeathrApiResponse: From<T> + From<E>,
@@ -330,6 +727,14 @@ where
     }
 }
 
+/**
+ * Large pages are cut to `MAX_SEARCH_SIZE`, clients follow `next_cursor` for the rest
+ */
+fn search_size(size: Option<usize>) -> usize {
+    size.unwrap_or(DEFAULT_SEARCH_SIZE)
+        .clamp(1, MAX_SEARCH_SIZE)
+}
+
 #[async_trait]
 pub trait FeathrApiProvider: Sync + Send {
     async fn request(&mut self, request: FeathrApiRequest) -> FeathrApiResponse;
@@ -394,12 +799,71 @@ where
                 &keyword.unwrap_or_default(),
                 types,
                 scope,
-                size.unwrap_or(100),
+                search_size(size),
                 offset.unwrap_or(0),
             )
             .map(|es| es.into_iter().map(|e| fill_entity(t, e)).collect())
         }
 
+        /**
+         * Projects matching the keyword and accepted by `visible`, the search results are fetched
+         * in chunks until the page after the filtering is full
+         */
+        fn search_visible_projects<T, F>(
+            t: &T,
+            keyword: &str,
+            size: Option<usize>,
+            offset: Option<usize>,
+            visible: F,
+        ) -> Result<Vec<Entity>, RegistryError>
+        where
+            T: RegistryProvider<EntityProperty>,
+            F: Fn(&registry_provider::Entity<EntityProperty>) -> bool,
+        {
+            let (size, offset) = (search_size(size), offset.unwrap_or(0));
+            let mut found = vec![];
+            let mut from = 0;
+            while found.len() < offset + size {
+                let chunk = t.search_entity(
+                    keyword,
+                    set![registry_provider::EntityType::Project],
+                    None,
+                    MAX_SEARCH_SIZE,
+                    from,
+                )?;
+                if chunk.is_empty() {
+                    break;
+                }
+                from += MAX_SEARCH_SIZE;
+                found.extend(chunk.into_iter().filter(|e| visible(e)));
+            }
+            Ok(found
+                .into_iter()
+                .skip(offset)
+                .take(size)
+                .map(|e| fill_entity(t, e))
+                .collect())
+        }
+
+        fn search_entities_after<T>(
+            t: &T,
+            keyword: &str,
+            size: Option<usize>,
+            cursor: Option<&str>,
+            types: HashSet<registry_provider::EntityType>,
+            scope: Option<Uuid>,
+        ) -> Result<Entities, RegistryError>
+        where
+            T: RegistryProvider<EntityProperty>,
+        {
+            let (entities, next_cursor) =
+                t.search_entity_page(keyword, types, scope, search_size(size), cursor)?;
+            Ok(Entities {
+                entities: entities.into_iter().map(|e| fill_entity(t, e)).collect(),
+                next_cursor,
+            })
+        }
+
         fn search_children<T>(
             t: &T,
             id_or_name: String,
@@ -427,6 +891,77 @@ where
             }
         }
 
+        /**
+         * Direct neighbors following any of the edge types, sorted by the qualified name
+         */
+        fn get_related<T>(
+            t: &T,
+            id: Uuid,
+            edge_types: &[registry_provider::EdgeType],
+        ) -> Result<Vec<Entity>, RegistryError>
+        where
+            T: RegistryProvider<EntityProperty>,
+        {
+            let mut seen = HashSet::new();
+            let mut entities = vec![];
+            for edge_type in edge_types {
+                for e in t.get_neighbors(id, *edge_type)? {
+                    if seen.insert(e.id) {
+                        entities.push(fill_entity(t, e));
+                    }
+                }
+            }
+            entities.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+            Ok(entities)
+        }
+
+        /**
+         * Near-duplicate anchor features of the project, only the latest versions are compared
+         */
+        fn find_duplicates<T>(
+            t: &T,
+            project_id: Uuid,
+        ) -> Result<
+            Vec<(
+                FeatureFingerprint,
+                Vec<registry_provider::Entity<EntityProperty>>,
+            )>,
+            RegistryError,
+        >
+        where
+            T: RegistryProvider<EntityProperty>,
+        {
+            let mut latest: HashMap<String, registry_provider::Entity<EntityProperty>> =
+                HashMap::new();
+            for f in t.get_children(project_id, set![EntityType::AnchorFeature])? {
+                if latest
+                    .get(&f.qualified_name)
+                    .map_or(true, |e| e.version < f.version)
+                {
+                    latest.insert(f.qualified_name.clone(), f);
+                }
+            }
+            let mut features: Vec<_> = latest.into_values().collect();
+            features.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+            let mut items = vec![];
+            for f in features {
+                let sources = t
+                    .get_neighbors(f.id, EdgeType::Consumes)?
+                    .into_iter()
+                    .filter(|e| e.entity_type == EntityType::Source)
+                    .map(|e| e.qualified_name)
+                    .collect();
+                let fingerprint = match &f.properties.attributes {
+                    registry_provider::Attributes::AnchorFeature(attr) => {
+                        FeatureFingerprint::new(attr, sources)
+                    }
+                    _ => continue,
+                };
+                items.push((fingerprint, f));
+            }
+            Ok(group_duplicates(items))
+        }
+
         fn fill_entity<T>(this: &T, mut e: registry_provider::Entity<EntityProperty>) -> Entity
         where
             T: RegistryProvider<EntityProperty>,
@@ -470,15 +1005,23 @@ where
                     };
                     project
                 }
-                registry_provider::Attributes::Anchor => {
+                registry_provider::Attributes::Anchor(_) => {
                     let anchor_id = e.id;
+                    let (joined_ids, _) = e.properties.get_source_joins();
                     let mut anchor: Entity = e.into();
-                    // Source
-                    let source = this
+                    // Sources, the primary one is not in the joined list
+                    let (joined, mut primary): (Vec<_>, Vec<_>) = this
                         .get_neighbors(anchor_id, EdgeType::Consumes)
                         .expect("Data inconsistency detected")
-                        .pop()
-                        .expect("Data inconsistency detected");
+                        .into_iter()
+                        .partition(|s| joined_ids.contains(&s.id));
+                    let source = primary.pop().expect("Data inconsistency detected");
+                    // Keep the join order in the definition
+                    let joined_sources: Vec<EntityRef> = joined_ids
+                        .iter()
+                        .filter_map(|id| joined.iter().find(|s| s.id == *id))
+                        .map(EntityRef::new)
+                        .collect();
                     // Features
                     let features: Vec<EntityRef> = this
                         .get_neighbors(anchor_id, EdgeType::Contains)
@@ -489,6 +1032,7 @@ where
                     match &mut anchor.attributes {
                         EntityAttributes::Anchor(attr) => {
                             attr.source = Some(EntityRef::new(&source));
+                            attr.joined_sources = joined_sources;
                             attr.features = features;
                         }
                         _ => panic!("Data inconsistency detected"),
@@ -524,6 +1068,34 @@ where
 
                     feature
                 }
+                registry_provider::Attributes::MaterializationRecord(_) => {
+                    let record_id = e.id;
+                    let mut record: Entity = e.into();
+                    let features = this
+                        .get_neighbors(record_id, EdgeType::Consumes)
+                        .expect("Data inconsistency detected");
+                    match &mut record.attributes {
+                        EntityAttributes::MaterializationRecord(attr) => {
+                            attr.features = features.iter().map(EntityRef::new).collect();
+                        }
+                        _ => panic!("Data inconsistency detected"),
+                    };
+                    record
+                }
+                registry_provider::Attributes::Dataset(_) => {
+                    let dataset_id = e.id;
+                    let mut dataset: Entity = e.into();
+                    let features = this
+                        .get_neighbors(dataset_id, EdgeType::DependsOn)
+                        .expect("Data inconsistency detected");
+                    match &mut dataset.attributes {
+                        EntityAttributes::Dataset(attr) => {
+                            attr.features = features.iter().map(EntityRef::new).collect();
+                        }
+                        _ => panic!("Data inconsistency detected"),
+                    };
+                    dataset
+                }
                 _ => e.into(),
             }
         }
@@ -540,56 +1112,130 @@ where
                     keyword,
                     size,
                     offset,
-                } => if keyword.is_blank() {
-                    let r = this.get_entry_points();
-                    match r {
-                        Ok(entities) => {
-                            let mut es: Vec<Entity> = vec![];
-                            for e in entities {
-                                es.push(fill_entity(this, e))
+                    credential,
+                } => {
+                    // Projects the caller can't read are dropped before paging
+                    let visible: Option<HashSet<String>> = match credential {
+                        Some(credential) => {
+                            Some(this.visible_projects(&credential)?.into_iter().collect())
+                        }
+                        None => None,
+                    };
+                    let is_visible = |e: &registry_provider::Entity<EntityProperty>| {
+                        visible
+                            .as_ref()
+                            .map_or(true, |v| v.contains(&e.qualified_name))
+                    };
+                    let projects = if keyword.is_blank() {
+                        let r = this.get_entry_points();
+                        match r {
+                            Ok(entities) => {
+                                let mut es: Vec<Entity> = vec![];
+                                for e in entities.into_iter().filter(is_visible) {
+                                    es.push(fill_entity(this, e))
+                                }
+                                es.sort_by_key(|e| e.name.clone());
+                                Ok(es)
                             }
-                            es.sort_by_key(|e| e.name.clone());
-                            Ok(es)
+                            Err(e) => Err(e),
                         }
-                        Err(e) => Err(e),
-                    }
-                } else {
-                    search_entities(
-                        this,
-                        keyword,
-                        size,
-                        offset,
-                        set![registry_provider::EntityType::Project],
-                        None,
-                    )
+                    } else if visible.is_none() {
+                        search_entities(
+                            this,
+                            keyword,
+                            size,
+                            offset,
+                            set![registry_provider::EntityType::Project],
+                            None,
+                        )
+                    } else {
+                        search_visible_projects(
+                            this,
+                            &keyword.unwrap_or_default(),
+                            size,
+                            offset,
+                            is_visible,
+                        )
+                    };
+                    projects
+                        .map(|r| {
+                            r.into_iter()
+                                .map(|e| e.qualified_name)
+                                .collect::<Vec<String>>()
+                        })
+                        .into()
                 }
-                .map(|r| {
-                    r.into_iter()
-                        .map(|e| e.qualified_name)
-                        .collect::<Vec<String>>()
-                })
-                .into(),
                 FeathrApiRequest::GetProject { id_or_name } => {
                     match this.get_entity_by_id_or_qualified_name(&id_or_name) {
                         Ok(e) => fill_entity(this, e).into(),
                         Err(e) => e.into(),
                     }
                 }
-                FeathrApiRequest::GetProjectLineage { id_or_name } => {
+                FeathrApiRequest::GetProjectLineage {
+                    id_or_name,
+                    release,
+                    credential,
+                } => {
                     debug!("Project name: {}", id_or_name);
 
-                    this.get_project(&id_or_name)
-                        .map(|(entities, edges)| {
-                            (
-                                entities
-                                    .into_iter()
-                                    .map(|e| fill_entity(this, e))
-                                    .collect::<Vec<_>>(),
-                                edges,
-                            )
-                        })
+                    let id = get_id(this, id_or_name.clone())?;
+                    let (entities, edges) = match release {
+                        Some(name) => this.get_release_lineage(id, &name)?,
+                        None => this.get_project(&id_or_name)?,
+                    };
+                    let (entities, edges) = match credential {
+                        Some(credential) => this.readable_lineage(&credential, id, entities, edges),
+                        None => (entities, edges),
+                    };
+                    (
+                        entities
+                            .into_iter()
+                            .map(|e| fill_entity(this, e))
+                            .collect::<Vec<_>>(),
+                        edges,
+                    )
                         .into()
                 }
+                FeathrApiRequest::GetProjectSummary { id_or_name } => {
+                    this.get_project_summary(&id_or_name).into()
+                }
+                FeathrApiRequest::GetProjectDuplicates { id_or_name } => {
+                    let project_id = get_id(this, id_or_name)?;
+                    let mut groups = vec![];
+                    for (fingerprint, features) in find_duplicates(this, project_id)? {
+                        let mut linked = true;
+                        for f in &features {
+                            let similar: HashSet<Uuid> = this
+                                .get_neighbors(f.id, EdgeType::SimilarTo)?
+                                .into_iter()
+                                .map(|e| e.id)
+                                .collect();
+                            linked &= features
+                                .iter()
+                                .all(|other| other.id == f.id || similar.contains(&other.id));
+                        }
+                        groups.push(DuplicateGroup {
+                            sources: fingerprint.sources,
+                            transformation: fingerprint.transformation,
+                            key: fingerprint.key,
+                            features: features.iter().map(EntityRef::new).collect(),
+                            linked,
+                        });
+                    }
+                    DuplicateReport {
+                        project: get_name(this, project_id)?,
+                        groups,
+                    }
+                    .into()
+                }
+                FeathrApiRequest::LinkSimilarFeatures { project_id_or_name } => {
+                    let project_id = get_id(this, project_id_or_name)?;
+                    for (_, features) in find_duplicates(this, project_id)? {
+                        let ids: Vec<Uuid> = features.iter().map(|f| f.id).collect();
+                        this.link_similar_features(&ids).await?;
+                    }
+                    ().into()
+                }
                 FeathrApiRequest::GetProjectFeatures {
                     project_id_or_name,
                     keyword,
@@ -610,6 +1256,72 @@ where
                     )
                     .into()
                 }
+                FeathrApiRequest::GetProjectEntities {
+                    project_id_or_name,
+                    types,
+                    tags,
+                    size,
+                    offset,
+                } => {
+                    let scope_id = get_id(this, project_id_or_name)?;
+                    this.get_entities_by_attributes(types, tags, Some(scope_id))
+                        .map(|es| {
+                            es.into_iter()
+                                .skip(offset.unwrap_or(0))
+                                .take(size.unwrap_or(usize::MAX))
+                                .map(|e| fill_entity(this, e))
+                                .collect::<Vec<_>>()
+                        })
+                        .into()
+                }
+                FeathrApiRequest::SearchProject {
+                    project_id_or_name,
+                    keyword,
+                    types,
+                    mode,
+                    size,
+                    offset,
+                    cursor,
+                    query_vector,
+                } => {
+                    let scope_id = get_id(this, project_id_or_name)?;
+                    match mode {
+                        // Offset paging is kept for existing clients
+                        SearchMode::Keyword if offset.is_none() => search_entities_after(
+                            this,
+                            &keyword,
+                            size,
+                            cursor.as_deref(),
+                            types,
+                            Some(scope_id),
+                        )
+                        .into(),
+                        SearchMode::Keyword => search_entities(
+                            this,
+                            Some(keyword),
+                            size,
+                            offset,
+                            types,
+                            Some(scope_id),
+                        )
+                        .into(),
+                        SearchMode::Semantic => this
+                            .semantic_search_entity(
+                                &keyword,
+                                &query_vector.ok_or(RegistryError::SemanticSearchDisabled)?,
+                                types,
+                                Some(scope_id),
+                                search_size(size),
+                                offset.unwrap_or(0),
+                            )
+                            .map(|es| {
+                                es.into_iter()
+                                    .map(|e| fill_entity(this, e))
+                                    .collect::<Vec<_>>()
+                            })
+                            .into(),
+                    }
+                }
                 FeathrApiRequest::CreateProject { mut definition } => {
                     definition.qualified_name = definition.name.clone();
                     this.new_project(&definition.try_into()?).await.into()
@@ -782,6 +1494,25 @@ where
                     let project_id = get_id(this, project_id_or_name)?;
                     let project_name = get_name(this, project_id)?;
                     definition.qualified_name = format!("{}__{}", project_name, definition.name);
+                    // Untyped references are sorted into the typed lists by the entity type
+                    for input in std::mem::take(&mut definition.input_features) {
+                        let input = this
+                            .get_entity_by_id_or_qualified_name(&input)
+                            .map_api_error()?;
+                        let inputs = match input.entity_type {
+                            registry_provider::EntityType::AnchorFeature => {
+                                &mut definition.input_anchor_features
+                            }
+                            registry_provider::EntityType::DerivedFeature => {
+                                &mut definition.input_derived_features
+                            }
+                            t => return Err(RegistryError::WrongEntityType(input.id, t).into()),
+                        };
+                        let id = input.id.to_string();
+                        if !inputs.contains(&id) {
+                            inputs.push(id);
+                        }
+                    }
                     this.new_derived_feature(project_id, &definition.try_into()?)
                         .await
                         .into()
@@ -853,7 +1584,72 @@ where
                     .get_entity_by_id_or_qualified_name(&id_or_name)
                     .map(|e| fill_entity(this, e))
                     .into(),
-                FeathrApiRequest::GetFeatureLineage { id_or_name } => {
+                FeathrApiRequest::GetDataSource { id_or_name } => {
+                    let source = this
+                        .get_entity_by_id_or_qualified_name(&id_or_name)
+                        .map_api_error()?;
+                    if source.entity_type != registry_provider::EntityType::Source {
+                        return Err(RegistryError::WrongEntityType(
+                            source.id,
+                            registry_provider::EntityType::Source,
+                        )
+                        .into());
+                    }
+                    fill_entity(this, source).into()
+                }
+                FeathrApiRequest::GetSourceConsumers { id_or_name } => {
+                    let source = this
+                        .get_entity_by_id_or_qualified_name(&id_or_name)
+                        .map_api_error()?;
+                    if source.entity_type != registry_provider::EntityType::Source {
+                        return Err(RegistryError::WrongEntityType(
+                            source.id,
+                            registry_provider::EntityType::Source,
+                        )
+                        .into());
+                    }
+                    // Anchors and anchor features consuming the source, regardless of the project
+                    get_related(this, source.id, &[registry_provider::EdgeType::Produces]).into()
+                }
+                FeathrApiRequest::GetRelatedEntities {
+                    id_or_name,
+                    edge_type,
+                } => {
+                    let id = get_id(this, id_or_name)?;
+                    match edge_type {
+                        Some(edge_type) => get_related(this, id, &[edge_type]),
+                        None => get_related(
+                            this,
+                            id,
+                            &[
+                                registry_provider::EdgeType::BelongsTo,
+                                registry_provider::EdgeType::Contains,
+                                registry_provider::EdgeType::Consumes,
+                                registry_provider::EdgeType::Produces,
+                                registry_provider::EdgeType::ConsumedBy,
+                                registry_provider::EdgeType::DependsOn,
+                                registry_provider::EdgeType::SimilarTo,
+                            ],
+                        ),
+                    }
+                    .into()
+                }
+                FeathrApiRequest::GetFeatureVersionDiff {
+                    id_or_name,
+                    from_version,
+                    to_version,
+                } => {
+                    let f = this
+                        .get_entity_by_id_or_qualified_name(&id_or_name)
+                        .map_api_error()?;
+                    let from = this.get_entity_version(&f.qualified_name, from_version)?;
+                    let to = this.get_entity_version(&f.qualified_name, to_version)?;
+                    EntityDiff::new(&from.into(), &to.into())?.into()
+                }
+                FeathrApiRequest::GetFeatureLineage {
+                    id_or_name,
+                    credential,
+                } => {
                     debug!("Feature name: {}", id_or_name);
                     let id = get_id(this, id_or_name)?;
                     let (up_entities, up_edges) = this
@@ -862,24 +1658,200 @@ where
                     let (down_entities, down_edges) = this
                         .bfs(id, registry_provider::EdgeType::Produces, None)
                         .map_api_error()?;
+                    let entities: Vec<_> = up_entities.into_iter().chain(down_entities).collect();
+                    let edges: Vec<_> = up_edges.into_iter().chain(down_edges).collect();
+                    // Inputs and consumers may belong to other projects
+                    let (entities, edges) = match credential {
+                        Some(credential) => this.readable_lineage(&credential, id, entities, edges),
+                        None => (entities, edges),
+                    };
                     (
-                        up_entities
+                        entities
                             .into_iter()
-                            .chain(down_entities.into_iter())
                             .map(|e| fill_entity(this, e))
                             .collect::<Vec<_>>(),
-                        up_edges
-                            .into_iter()
-                            .chain(down_edges.into_iter())
-                            .collect::<Vec<_>>(),
+                        edges,
+                    )
+                        .into()
+                }
+                FeathrApiRequest::GetProjectMaterializationRecords {
+                    project_id_or_name,
+                    keyword,
+                    size,
+                    offset,
+                } => {
+                    debug!("Project name: {}", project_id_or_name);
+                    search_children(
+                        this,
+                        project_id_or_name,
+                        keyword,
+                        size,
+                        offset,
+                        set![registry_provider::EntityType::MaterializationRecord],
+                    )
+                    .into()
+                }
+                FeathrApiRequest::GetFeatureMaterializationRecords { id_or_name } => {
+                    debug!("Feature name: {}", id_or_name);
+                    let id = get_id(this, id_or_name)?;
+                    let mut records: Vec<Entity> = this
+                        .get_neighbors(id, EdgeType::Produces)?
+                        .into_iter()
+                        .filter(|e| e.entity_type == EntityType::MaterializationRecord)
+                        .map(|e| fill_entity(this, e))
+                        .collect();
+                    records.sort_by_key(|e| e.name.clone());
+                    records.into()
+                }
+                FeathrApiRequest::CreateMaterializationRecord {
+                    project_id_or_name,
+                    mut definition,
+                } => {
+                    let project_id = get_id(this, project_id_or_name)?;
+                    let project_name = get_name(this, project_id)?;
+                    definition.qualified_name = format!("{}__{}", project_name, definition.name);
+                    this.new_materialization_record(project_id, &definition.try_into()?)
+                        .await
+                        .into()
+                }
+                FeathrApiRequest::GetProjectDatasets {
+                    project_id_or_name,
+                    keyword,
+                    size,
+                    offset,
+                } => {
+                    debug!("Project name: {}", project_id_or_name);
+                    search_children(
+                        this,
+                        project_id_or_name,
+                        keyword,
+                        size,
+                        offset,
+                        set![registry_provider::EntityType::Dataset],
                     )
+                    .into()
+                }
+                FeathrApiRequest::GetFeatureDatasets { id_or_name } => {
+                    debug!("Feature name: {}", id_or_name);
+                    let id = get_id(this, id_or_name)?;
+                    let mut datasets: Vec<Entity> = this
+                        .get_neighbors(id, EdgeType::ConsumedBy)?
+                        .into_iter()
+                        .filter(|e| e.entity_type == EntityType::Dataset)
+                        .map(|e| fill_entity(this, e))
+                        .collect();
+                    datasets.sort_by_key(|e| e.name.clone());
+                    datasets.into()
+                }
+                FeathrApiRequest::CreateDataset {
+                    project_id_or_name,
+                    mut definition,
+                } => {
+                    let project_id = get_id(this, project_id_or_name)?;
+                    let project_name = get_name(this, project_id)?;
+                    definition.qualified_name = format!("{}__{}", project_name, definition.name);
+                    this.new_dataset(project_id, &definition.try_into()?)
+                        .await
+                        .into()
+                }
+                FeathrApiRequest::SetEntityOwnership {
+                    id_or_name,
+                    ownership,
+                } => {
+                    let id = get_id(this, id_or_name)?;
+                    this.set_entity_ownership(id, &ownership.into())
+                        .await
+                        .into()
+                }
+                FeathrApiRequest::GetEntity { id_or_name } => {
+                    this.get_entity_by_id_or_qualified_name(&id_or_name).into()
+                }
+                FeathrApiRequest::GetEntities { ids } => {
+                    this.get_entities(ids.into_iter().collect()).into()
+                }
+                FeathrApiRequest::SetEntityDocs { id_or_name, docs } => {
+                    let id = get_id(this, id_or_name)?;
+                    this.set_entity_docs(id, &docs).await.into()
+                }
+                FeathrApiRequest::DeleteEntity {
+                    id_or_name,
+                    deleted_at,
+                } => {
+                    let id = get_id(this, id_or_name)?;
+                    this.delete_entity(id, deleted_at).await.into()
+                }
+                FeathrApiRequest::DeleteProject {
+                    id_or_name,
+                    cascade,
+                    dry_run,
+                    deleted_at,
+                } => {
+                    let id = get_id(this, id_or_name)?;
+                    this.delete_project(id, cascade, dry_run, deleted_at)
+                        .await
+                        .into()
+                }
+                FeathrApiRequest::SetSourceVerified {
+                    project_id_or_name,
+                    id_or_name,
+                    verified_at,
+                } => {
+                    let (_, id) = get_child_id(this, project_id_or_name, id_or_name)?;
+                    this.set_source_verified(id, verified_at).await.into()
+                }
+                FeathrApiRequest::PromoteEntity {
+                    id_or_name,
+                    environment,
+                } => {
+                    let id = get_id(this, id_or_name)?;
+                    this.promote_entity(id, &environment).await.into()
+                }
+                FeathrApiRequest::DemoteEntity {
+                    id_or_name,
+                    environment,
+                } => {
+                    let id = get_id(this, id_or_name)?;
+                    this.demote_entity(id, &environment).await.into()
+                }
+                FeathrApiRequest::GetProjectReleases { project_id_or_name } => {
+                    let project_id = get_id(this, project_id_or_name)?;
+                    this.get_releases(project_id).into()
+                }
+                FeathrApiRequest::GetProjectRelease {
+                    project_id_or_name,
+                    name,
+                } => {
+                    let project_id = get_id(this, project_id_or_name)?;
+                    this.get_release(project_id, &name).into()
+                }
+                FeathrApiRequest::CreateRelease {
+                    project_id_or_name,
+                    definition,
+                } => {
+                    let project_id = get_id(this, project_id_or_name)?;
+                    this.create_release(project_id, &definition.into())
+                        .await
                         .into()
                 }
+                FeathrApiRequest::DeleteRelease {
+                    project_id_or_name,
+                    name,
+                } => {
+                    let project_id = get_id(this, project_id_or_name)?;
+                    this.delete_release(project_id, &name).await.into()
+                }
                 FeathrApiRequest::BatchLoad {
                     entities,
                     edges,
                     permissions,
-                } => this.load_data(entities, edges, permissions).await.into(),
+                    policy,
+                } => this
+                    .load_data(entities, edges, permissions, policy)
+                    .await
+                    .into(),
+                FeathrApiRequest::CollectGarbage { before, dry_run } => {
+                    this.collect_garbage(before, dry_run).await.into()
+                }
                 FeathrApiRequest::GetEntityProject { id_or_name } => {
                     let entity = this.get_entity_by_id_or_qualified_name(&id_or_name)?;
                     if entity.entity_type == EntityType::Project {
@@ -936,12 +1908,39 @@ where
                     };
                     this.revoke_permission(&revoke).await.into()
                 }
+                FeathrApiRequest::RevokeToken { .. } => {
+                    return Err(ApiError::BadRequest(
+                        "Token revocations are handled by the Raft state machine".to_string(),
+                    ))
+                }
+                FeathrApiRequest::InTenant { .. } => {
+                    return Err(ApiError::BadRequest(
+                        "Tenant scopes cannot be nested".to_string(),
+                    ))
+                }
+                FeathrApiRequest::Idempotent { .. } => {
+                    return Err(ApiError::BadRequest(
+                        "Idempotency keys cannot be nested".to_string(),
+                    ))
+                }
             })
         }
 
-        match handle_request(self, request).await {
+        let (tenant, request) = match request {
+            FeathrApiRequest::InTenant { tenant, request } => (tenant, *request),
+            request => (String::new(), request),
+        };
+        // Responses of duplicated keys are replayed by the caller, the request is handled as usual here
+        let request = match request {
+            FeathrApiRequest::Idempotent { request, .. } => *request,
+            request => request,
+        };
+        let previous = self.switch_tenant(&tenant);
+        let ret = match handle_request(self, request).await {
             Ok(v) => v,
             Err(e) => FeathrApiResponse::Error(e),
-        }
+        };
+        self.switch_tenant(&previous);
+        ret
     }
 }
@@ -0,0 +1,32 @@
+/**
+ * Generate the JSON Schema or the TypeScript definitions of the API models, so clients don't
+ * need to maintain them by hand
+ *
+ * Usage: gen-api-schema [--typescript] [OUTPUT_FILE]
+ */
+fn main() -> std::io::Result<()> {
+    let mut typescript = false;
+    let mut output: Option<String> = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--typescript" | "-t" => typescript = true,
+            "--help" | "-h" => {
+                println!("Usage: gen-api-schema [--typescript] [OUTPUT_FILE]");
+                return Ok(());
+            }
+            _ => output = Some(arg),
+        }
+    }
+    let content = if typescript {
+        registry_api::api_typescript_definitions()
+    } else {
+        serde_json::to_string_pretty(&registry_api::api_json_schema())?
+    };
+    match output {
+        Some(path) => std::fs::write(path, content),
+        None => {
+            println!("{}", content);
+            Ok(())
+        }
+    }
+}
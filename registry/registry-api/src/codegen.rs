@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::{ApiError, Entity, EntityAttributes, EntityLineage, EntityType, TypedKey};
+
+/**
+ * Languages the snippets can be generated in
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodegenLanguage {
+    Python,
+}
+
+impl FromStr for CodegenLanguage {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "python" | "py" => Ok(Self::Python),
+            _ => Err(ApiError::BadRequest(format!(
+                "Unsupported language `{}`, only `python` is supported",
+                s
+            ))),
+        }
+    }
+}
+
+/**
+ * Python string literal
+ */
+fn py_str(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len() + 2);
+    ret.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => ret.push_str("\\\\"),
+            '"' => ret.push_str("\\\""),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\t' => ret.push_str("\\t"),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+fn py_list<I, T>(items: I) -> String
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<str>,
+{
+    format!(
+        "[{}]",
+        items
+            .into_iter()
+            .map(|s| s.as_ref().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/**
+ * Python variable name of a key, e.g. `DOLocationID` becomes `dolocationid_key`
+ */
+fn key_var(key: &TypedKey) -> String {
+    let name = key.key_column_alias.as_deref().unwrap_or(&key.key_column);
+    let mut var: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    var = var.trim_matches('_').to_string();
+    if var.starts_with(|c: char| c.is_ascii_digit()) {
+        var.insert(0, '_');
+    }
+    format!("{}_key", var)
+}
+
+fn is_keyless(keys: &[TypedKey]) -> bool {
+    keys.iter().all(|k| k.key_column == "NOT_NEEDED")
+}
+
+impl EntityLineage {
+    fn entities_of_type(&self, entity_type: EntityType) -> Vec<&Entity> {
+        let mut entities: Vec<&Entity> = self
+            .guid_entity_map
+            .values()
+            .filter(|e| e.entity_type == entity_type)
+            .collect();
+        entities.sort_by(|a, b| a.name.cmp(&b.name));
+        entities
+    }
+
+    /**
+     * Snippets in the language getting the offline features of the project and materializing its
+     * anchor features, the lineage must be the one of the whole project
+     */
+    pub fn to_code(&self, lang: CodegenLanguage) -> String {
+        match lang {
+            CodegenLanguage::Python => self.to_python(),
+        }
+    }
+
+    /**
+     * Python snippets using the `feathrs` package, features are queried in groups sharing the same
+     * keys and each anchor group with keys is materialized into its own Redis table
+     */
+    pub fn to_python(&self) -> String {
+        let project = self
+            .entities_of_type(EntityType::Project)
+            .first()
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+
+        // Features grouped by their keys, keyless features can be queried with any keys
+        let mut keys: BTreeMap<String, TypedKey> = BTreeMap::new();
+        let mut groups: BTreeMap<Vec<String>, Vec<String>> = BTreeMap::new();
+        let mut keyless: Vec<String> = vec![];
+        let features = self
+            .entities_of_type(EntityType::AnchorFeature)
+            .into_iter()
+            .chain(self.entities_of_type(EntityType::DerivedFeature));
+        for f in features {
+            let key = match &f.attributes {
+                EntityAttributes::AnchorFeature(attr) => &attr.key,
+                EntityAttributes::DerivedFeature(attr) => &attr.key,
+                _ => continue,
+            };
+            if is_keyless(key) {
+                keyless.push(f.name.clone());
+                continue;
+            }
+            let vars: Vec<String> = key.iter().map(key_var).collect();
+            for (var, k) in vars.iter().zip(key.iter()) {
+                keys.entry(var.clone()).or_insert_with(|| k.clone());
+            }
+            groups.entry(vars).or_default().push(f.name.clone());
+        }
+        if groups.is_empty() && !keyless.is_empty() {
+            groups.insert(vec![], keyless);
+        } else if let Some(names) = groups.values_mut().next() {
+            names.extend(keyless);
+        }
+
+        let mut s = String::new();
+        writeln!(s, "# Generated from the registry, project {}", project).unwrap();
+        s.push_str("from datetime import datetime, timedelta\n\n");
+        s.push_str("from feathrs import *\n\n");
+        s.push_str("client = FeathrClient(\"feathr_config.yaml\")\n");
+        writeln!(s, "project = client.load_project({})", py_str(&project)).unwrap();
+
+        if !keys.is_empty() {
+            s.push_str("\n# Keys\n");
+        }
+        for (var, k) in &keys {
+            let mut args = vec![
+                py_str(&k.key_column),
+                format!("ValueType.{:?}", k.key_column_type),
+            ];
+            if let Some(full_name) = k.full_name.as_deref().filter(|n| !n.is_empty()) {
+                args.push(format!("full_name={}", py_str(full_name)));
+            }
+            if let Some(description) = k.description.as_deref().filter(|d| !d.is_empty()) {
+                args.push(format!("description={}", py_str(description)));
+            }
+            if let Some(alias) = &k.key_column_alias {
+                args.push(format!("key_column_alias={}", py_str(alias)));
+            }
+            writeln!(s, "{} = TypedKey({})", var, args.join(", ")).unwrap();
+        }
+
+        if !groups.is_empty() {
+            s.push_str("\n# Features sharing the same keys are queried together\n");
+            s.push_str("queries = [\n");
+            for (vars, names) in &groups {
+                let names = py_list(names.iter().map(|n| py_str(n)));
+                if vars.is_empty() {
+                    writeln!(s, "    FeatureQuery({}),", names).unwrap();
+                } else {
+                    writeln!(s, "    FeatureQuery({}, {}),", names, py_list(vars)).unwrap();
+                }
+            }
+            s.push_str("]\n\n");
+            self.write_python_observation(&mut s);
+            writeln!(
+                s,
+                "output = client.get_remote_url({})",
+                py_str(&format!("{}_features.avro", project))
+            )
+            .unwrap();
+            s.push_str(
+                "job_id = project.get_offline_features(observation, feature_query=queries, output=output)\n",
+            );
+            s.push_str("print(client.wait_for_job(job_id))\n");
+        }
+
+        let materializations: Vec<(&str, Vec<&str>)> = self
+            .entities_of_type(EntityType::Anchor)
+            .into_iter()
+            .filter_map(|a| {
+                let attr = match &a.attributes {
+                    EntityAttributes::Anchor(attr) => attr,
+                    _ => return None,
+                };
+                if is_input_context(self.anchor_source(a)?) {
+                    return None;
+                }
+                let mut names: Vec<&str> = attr
+                    .features
+                    .iter()
+                    .filter_map(|r| self.guid_entity_map.get(r.guid()))
+                    .filter(|f| match &f.attributes {
+                        EntityAttributes::AnchorFeature(attr) => !is_keyless(&attr.key),
+                        _ => false,
+                    })
+                    .map(|f| f.name.as_str())
+                    .collect();
+                names.sort_unstable();
+                (!names.is_empty()).then(|| (a.name.as_str(), names))
+            })
+            .collect();
+        if !materializations.is_empty() {
+            s.push_str(
+                "\n# Materialize the features of each anchor group into Redis for the last day\n",
+            );
+            s.push_str(
+                "end = datetime.utcnow().replace(hour=0, minute=0, second=0, microsecond=0)\n",
+            );
+            s.push_str("start = end - timedelta(days=1)\n");
+            s.push_str("job_ids = []\n");
+            for (anchor, names) in materializations {
+                writeln!(
+                    s,
+                    "job_ids += project.materialize_features({}, start, end, DateTimeResolution.Daily, RedisSink({}))",
+                    py_list(names.into_iter().map(py_str)),
+                    py_str(&format!("{}_{}", project, anchor))
+                )
+                .unwrap();
+            }
+            s.push_str("print(client.wait_for_jobs(job_ids))\n");
+        }
+        s
+    }
+
+    /**
+     * The observation data defaults to the first file source with a timestamp column, which has
+     * the key columns of the features anchored to it
+     */
+    fn write_python_observation(&self, s: &mut String) {
+        let source = self
+            .entities_of_type(EntityType::Source)
+            .into_iter()
+            .find_map(|e| match &e.attributes {
+                EntityAttributes::Source(attr) if attr.event_timestamp_column.is_some() => {
+                    Some((attr.options.get("path")?, attr))
+                }
+                _ => None,
+            });
+        match source {
+            Some((path, attr)) => {
+                let mut args = vec![py_str(path)];
+                args.extend(attr.event_timestamp_column.as_deref().map(py_str));
+                args.extend(attr.timestamp_format.as_deref().map(py_str));
+                s.push_str(
+                    "# Observation data must have the key columns and the timestamp column\n",
+                );
+                writeln!(s, "observation = ObservationSettings({})", args.join(", ")).unwrap();
+            }
+            None => {
+                s.push_str(
+                    "# TODO: Observation data with the key columns and the timestamp column\n",
+                );
+                s.push_str("observation = ObservationSettings(\"<observation path>\")\n");
+            }
+        }
+    }
+
+    fn anchor_source(&self, anchor: &Entity) -> Option<&Entity> {
+        match &anchor.attributes {
+            EntityAttributes::Anchor(attr) => {
+                self.guid_entity_map.get(attr.source.as_ref()?.guid())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_input_context(source: &Entity) -> bool {
+    match &source.attributes {
+        EntityAttributes::Source(attr) => {
+            attr.name == "PASSTHROUGH" || attr.type_.eq_ignore_ascii_case("PASSTHROUGH")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_helpers() {
+        assert_eq!(py_str("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+        assert_eq!(py_list(["\"a\"", "\"b\""]), "[\"a\", \"b\"]");
+        let key = TypedKey {
+            key_column: "DOLocationID".to_string(),
+            key_column_type: crate::ValueType::INT32,
+            full_name: None,
+            description: None,
+            key_column_alias: None,
+        };
+        assert_eq!(key_var(&key), "dolocationid_key");
+        let key = TypedKey {
+            key_column_alias: Some("2nd user".to_string()),
+            ..key
+        };
+        assert_eq!(key_var(&key), "_2nd_user_key");
+        assert!(is_keyless(&[]));
+        assert_eq!(
+            "Python".parse::<CodegenLanguage>().unwrap(),
+            CodegenLanguage::Python
+        );
+        assert!("scala".parse::<CodegenLanguage>().is_err());
+    }
+}
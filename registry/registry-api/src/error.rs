@@ -1,6 +1,6 @@
 use common_utils::Logged;
-use poem::{error::ResponseError, http::StatusCode};
-use registry_provider::RegistryError;
+use poem::{error::ResponseError, http::StatusCode, Response};
+use registry_provider::{ErrorCode, RbacError, RegistryError};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use thiserror::Error;
@@ -21,38 +21,65 @@ pub enum ApiError {
 
     #[error("{0}")]
     InternalError(String),
+
+    /// Error with a specific code, e.g. converted from a `RegistryError`
+    #[error("{1}")]
+    Coded(ErrorCode, String),
+}
+
+impl ApiError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::NotFoundError(_) => ErrorCode::EntityNotFound,
+            ApiError::Conflict(_) => ErrorCode::Conflict,
+            ApiError::BadRequest(_) => ErrorCode::BadRequest,
+            ApiError::Forbidden(_) => ErrorCode::PermissionDenied,
+            ApiError::InternalError(_) => ErrorCode::InternalError,
+            ApiError::Coded(code, _) => *code,
+        }
+    }
+}
+
+/**
+ * JSON body of error responses
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl From<&ApiError> for ErrorBody {
+    fn from(e: &ApiError) -> Self {
+        Self {
+            code: e.code(),
+            message: e.to_string(),
+        }
+    }
 }
 
 impl ResponseError for ApiError {
     fn status(&self) -> poem::http::StatusCode {
-        match &self {
-            ApiError::NotFoundError(_) => StatusCode::NOT_FOUND,
-            ApiError::Conflict(_) => StatusCode::CONFLICT,
-            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
-            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
-            ApiError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        }
+        StatusCode::from_u16(self.code().http_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn as_response(&self) -> Response {
+        Response::builder()
+            .status(self.status())
+            .content_type("application/json")
+            .body(serde_json::to_string(&ErrorBody::from(self)).unwrap_or_default())
     }
 }
 
 impl From<RegistryError> for ApiError {
     fn from(e: RegistryError) -> Self {
-        match e {
-            RegistryError::WrongEntityType(id, _) => ApiError::NotFoundError(id.to_string()),
-            RegistryError::EntityNotFound(e) => ApiError::NotFoundError(e),
-            RegistryError::InvalidEntity(id) => ApiError::NotFoundError(id.to_string()),
-            RegistryError::InvalidEdge(_, _) => ApiError::InternalError(format!("{:?}", e)),
-            RegistryError::EntityNameExists(_) => ApiError::Conflict(format!("{:?}", e)),
-            RegistryError::EntityIdExists(_) => ApiError::Conflict(format!("{:?}", e)),
-            RegistryError::DeleteInUsed(_) => ApiError::BadRequest(format!("{:?}", e)),
-            RegistryError::FtsError(_) => ApiError::InternalError(format!("{:?}", e)),
-            RegistryError::ExternalStorageError(_) => ApiError::InternalError(format!("{:?}", e)),
-            RegistryError::RbacError(e) => match e {
-                registry_provider::RbacError::CredentialNotFound(_) => ApiError::BadRequest(format!("{:?}", e)),
-                registry_provider::RbacError::ResourceNotFound(e) => ApiError::NotFoundError(e),
-                registry_provider::RbacError::PermissionDenied(_, _, _) => ApiError::Forbidden(format!("{:?}", e)),
-            }
-        }
+        ApiError::Coded(e.code(), e.to_string())
+    }
+}
+
+impl From<RbacError> for ApiError {
+    fn from(e: RbacError) -> Self {
+        ApiError::Coded(e.code(), e.to_string())
     }
 }
 
@@ -65,3 +92,27 @@ impl<T> IntoApiResult<T> for Result<T, RegistryError> {
         self.log().map_err(|e| e.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn error_body() {
+        let e: ApiError = RegistryError::DeleteInUsed(Uuid::nil()).into();
+        assert_eq!(e.status(), StatusCode::CONFLICT);
+        let body: ErrorBody =
+            serde_json::from_str(&serde_json::to_string(&ErrorBody::from(&e)).unwrap()).unwrap();
+        assert_eq!(body.code, ErrorCode::EntityInUse);
+        assert_eq!(
+            serde_json::to_value(&body).unwrap()["code"],
+            "ENTITY_IN_USE"
+        );
+
+        let e = ApiError::BadRequest("Invalid GUID".to_string());
+        assert_eq!(e.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(e.code(), ErrorCode::BadRequest);
+    }
+}
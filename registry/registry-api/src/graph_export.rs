@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+
+use petgraph::{
+    dot::{Config, Dot},
+    graph::{DiGraph, NodeIndex},
+    visit::EdgeRef,
+};
+
+use crate::{EdgeType, Entity, EntityLineage, EntityType};
+
+/**
+ * Entity in the exported graph, displayed by its name
+ */
+struct LineageNode<'a>(&'a Entity);
+
+impl<'a> Display for LineageNode<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.name)
+    }
+}
+
+struct LineageEdge(EdgeType);
+
+impl Display for LineageEdge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/**
+ * Graphviz shape and fill color of each entity type
+ */
+fn node_style(entity_type: EntityType) -> (&'static str, &'static str) {
+    match entity_type {
+        EntityType::Project => ("folder", "#e6e6e6"),
+        EntityType::Source => ("cylinder", "#cfe2f3"),
+        EntityType::Anchor => ("box", "#fff2cc"),
+        EntityType::AnchorFeature => ("ellipse", "#d9ead3"),
+        EntityType::DerivedFeature => ("ellipse", "#f4cccc"),
+        EntityType::MaterializationRecord | EntityType::Dataset => ("note", "#ead1dc"),
+        EntityType::Unknown => ("plaintext", "#ffffff"),
+    }
+}
+
+fn type_order(entity_type: EntityType) -> u8 {
+    match entity_type {
+        EntityType::Project => 0,
+        EntityType::Source => 1,
+        EntityType::Anchor => 2,
+        EntityType::AnchorFeature => 3,
+        EntityType::DerivedFeature => 4,
+        EntityType::MaterializationRecord => 5,
+        EntityType::Dataset => 6,
+        EntityType::Unknown => 7,
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl EntityLineage {
+    /**
+     * The lineage as a directed graph following the data flow, i.e. sources produce anchors and
+     * features, features produce derived features, and anchors contain their features.
+     * Reverse edges and the edges from the project to every entity are left out, nodes are sorted
+     * by type and name so the output is stable.
+     */
+    fn to_graph(&self) -> DiGraph<LineageNode<'_>, LineageEdge> {
+        let mut entities: Vec<&Entity> = self
+            .guid_entity_map
+            .values()
+            .filter(|e| !matches!(e.entity_type, EntityType::Project | EntityType::Unknown))
+            .collect();
+        entities.sort_by_key(|e| (type_order(e.entity_type), e.name.clone(), e.version));
+
+        let mut graph = DiGraph::new();
+        let index: HashMap<&str, NodeIndex> = entities
+            .into_iter()
+            .map(|e| (e.guid.as_str(), graph.add_node(LineageNode(e))))
+            .collect();
+        let mut relations: Vec<_> = self
+            .relations
+            .iter()
+            .filter(|r| match r.edge_type {
+                EdgeType::Produces => true,
+                EdgeType::Contains => self
+                    .guid_entity_map
+                    .get(&r.from)
+                    .map_or(false, |e| e.entity_type != EntityType::Project),
+                _ => false,
+            })
+            .filter_map(|r| {
+                Some((
+                    *index.get(r.from.as_str())?,
+                    *index.get(r.to.as_str())?,
+                    r.edge_type.clone(),
+                ))
+            })
+            .collect();
+        relations.sort_by_key(|(from, to, _)| (*from, *to));
+        relations.dedup_by_key(|(from, to, _)| (*from, *to));
+        for (from, to, edge_type) in relations {
+            graph.add_edge(from, to, LineageEdge(edge_type));
+        }
+        graph
+    }
+
+    /**
+     * Render the lineage in the Graphviz DOT format, nodes are styled by entity type
+     */
+    pub fn to_dot(&self) -> String {
+        let graph = self.to_graph();
+        let dot = Dot::with_attr_getters(
+            &graph,
+            &[Config::EdgeNoLabel],
+            &|_, e| match e.weight().0 {
+                EdgeType::Contains => "style = dashed".to_string(),
+                _ => String::new(),
+            },
+            &|_, (_, n)| {
+                let (shape, color) = node_style(n.0.entity_type);
+                format!(
+                    "shape = {} style = filled fillcolor = \"{}\" tooltip = \"{}\" ",
+                    shape,
+                    color,
+                    n.0.qualified_name
+                        .replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                )
+            },
+        );
+        format!("{}", dot)
+    }
+
+    /**
+     * Render the lineage in the GraphML format, e.g. for Gephi, entity attributes are exported as
+     * node data
+     */
+    pub fn to_graphml(&self) -> String {
+        let graph = self.to_graph();
+        let mut s = String::new();
+        s.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        s.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        for (id, target, type_) in [
+            ("name", "node", "string"),
+            ("qualifiedName", "node", "string"),
+            ("typeName", "node", "string"),
+            ("version", "node", "long"),
+            ("color", "node", "string"),
+            ("relationshipType", "edge", "string"),
+        ] {
+            writeln!(
+                s,
+                "  <key id=\"{id}\" for=\"{target}\" attr.name=\"{id}\" attr.type=\"{type_}\"/>"
+            )
+            .unwrap();
+        }
+        s.push_str("  <graph id=\"lineage\" edgedefault=\"directed\">\n");
+        for n in graph.node_weights() {
+            let e = n.0;
+            let type_name = registry_provider::EntityType::from(e.entity_type).get_name();
+            writeln!(s, "    <node id=\"{}\">", escape_xml(&e.guid)).unwrap();
+            for (key, value) in [
+                ("name", escape_xml(&e.name)),
+                ("qualifiedName", escape_xml(&e.qualified_name)),
+                ("typeName", type_name.to_string()),
+                ("version", e.version.to_string()),
+                ("color", node_style(e.entity_type).1.to_string()),
+            ] {
+                writeln!(s, "      <data key=\"{}\">{}</data>", key, value).unwrap();
+            }
+            s.push_str("    </node>\n");
+        }
+        for edge in graph.edge_references() {
+            writeln!(
+                s,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"relationshipType\">{}</data></edge>",
+                escape_xml(&graph[edge.source()].0.guid),
+                escape_xml(&graph[edge.target()].0.guid),
+                edge.weight()
+            )
+            .unwrap();
+        }
+        s.push_str("  </graph>\n</graphml>\n");
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{EntityAttributes, ProjectAttributes, Relationship};
+
+    fn entity(guid: &str, name: &str, entity_type: EntityType) -> Entity {
+        Entity {
+            guid: guid.to_string(),
+            name: name.to_string(),
+            qualified_name: format!("p1__{}", name),
+            version: 1,
+            entity_type,
+            status: "Active".to_string(),
+            display_text: name.to_string(),
+            labels: vec![],
+            attributes: EntityAttributes::Project(ProjectAttributes {
+                qualified_name: Default::default(),
+                name: Default::default(),
+                sources: vec![],
+                anchors: vec![],
+                anchor_features: vec![],
+                derived_features: vec![],
+                tags: Default::default(),
+                ownership: Default::default(),
+                default_tags: Default::default(),
+            }),
+            created_by: Default::default(),
+            created_on: Utc::now(),
+            docs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_graph_export() {
+        let relation = |edge_type, from: &str, to: &str| Relationship {
+            edge_type,
+            from: from.to_string(),
+            to: to.to_string(),
+        };
+        let lineage = EntityLineage {
+            guid_entity_map: [
+                entity("p", "p1", EntityType::Project),
+                entity("s", "trips", EntityType::Source),
+                entity("a", "trip_features", EntityType::Anchor),
+                entity("f", "f_fare", EntityType::AnchorFeature),
+                entity("d", "f_fare_<usd>", EntityType::DerivedFeature),
+            ]
+            .into_iter()
+            .map(|e| (e.guid.clone(), e))
+            .collect(),
+            relations: vec![
+                relation(EdgeType::Contains, "p", "f"),
+                relation(EdgeType::BelongsTo, "f", "p"),
+                relation(EdgeType::Produces, "s", "a"),
+                relation(EdgeType::Consumes, "a", "s"),
+                relation(EdgeType::Contains, "a", "f"),
+                relation(EdgeType::Produces, "f", "d"),
+                relation(EdgeType::Produces, "f", "d"),
+            ],
+        };
+
+        let graph = lineage.to_graph();
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+
+        let dot = lineage.to_dot();
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("\"trips\""));
+        assert!(dot.contains("shape = cylinder"));
+        assert!(dot.contains("0 -> 1"));
+        assert!(!dot.contains("shape = folder"));
+
+        let graphml = lineage.to_graphml();
+        assert!(graphml.contains("<data key=\"name\">f_fare_&lt;usd&gt;</data>"));
+        assert!(graphml.contains(
+            "<edge source=\"s\" target=\"a\"><data key=\"relationshipType\">Produces</data></edge>"
+        ));
+    }
+}
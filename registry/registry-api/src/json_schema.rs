@@ -0,0 +1,281 @@
+use poem_openapi::{registry::Registry, types::Type};
+use serde_json::{json, Map, Value};
+
+use crate::*;
+
+const OPENAPI_REF_PREFIX: &str = "#/components/schemas/";
+const JSON_SCHEMA_REF_PREFIX: &str = "#/definitions/";
+
+/**
+ * Register all API models, types referenced by them are registered recursively
+ */
+fn register_api_models(registry: &mut Registry) {
+    ValueType::register(registry);
+    VectorType::register(registry);
+    TensorCategory::register(registry);
+    Ownership::register(registry);
+    ProjectAttributes::register(registry);
+    SourceAttributes::register(registry);
+    AnchorAttributes::register(registry);
+    AnchorFeatureAttributes::register(registry);
+    DerivedFeatureAttributes::register(registry);
+    MaterializationRecordAttributes::register(registry);
+    DatasetAttributes::register(registry);
+    EntityAttributes::register(registry);
+    EdgeType::register(registry);
+    Relationship::register(registry);
+    EntityType::register(registry);
+    SearchMode::register(registry);
+    Entity::register(registry);
+    Entities::register(registry);
+    EntityUniqueAttributes::register(registry);
+    EntityRef::register(registry);
+    EntityLineage::register(registry);
+    ProjectDef::register(registry);
+    SourceDef::register(registry);
+    AnchorDef::register(registry);
+    FeatureType::register(registry);
+    TypedKey::register(registry);
+    Aggregation::register(registry);
+    FeatureTransformation::register(registry);
+    AnchorFeatureDef::register(registry);
+    DerivedFeatureDef::register(registry);
+    MaterializationRecordDef::register(registry);
+    DatasetDef::register(registry);
+    SourceSample::register(registry);
+    CreationResponse::register(registry);
+    RbacResponse::register(registry);
+    TagCount::register(registry);
+    ProjectSummary::register(registry);
+}
+
+/**
+ * JSON Schema of all API models, the same schemas as the `components` section of the OpenAPI spec
+ */
+pub fn api_json_schema() -> Value {
+    let mut registry = Registry::new();
+    register_api_models(&mut registry);
+    let schemas = serde_json::to_value(&registry.schemas).unwrap_or_default();
+    to_json_schema(schemas)
+}
+
+/**
+ * TypeScript definitions of all API models
+ */
+pub fn api_typescript_definitions() -> String {
+    typescript_definitions(&api_json_schema())
+}
+
+/**
+ * Wrap OpenAPI component schemas into a JSON Schema document
+ */
+fn to_json_schema(mut schemas: Value) -> Value {
+    convert_openapi_schema(&mut schemas);
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "Feathr Registry API Models",
+        "definitions": schemas,
+    })
+}
+
+/**
+ * OpenAPI schemas are mostly JSON Schema, except for the location of references and `nullable`
+ */
+fn convert_openapi_schema(node: &mut Value) {
+    match node {
+        Value::Object(m) => {
+            if let Some(Value::String(r)) = m.get_mut("$ref") {
+                if let Some(name) = r.strip_prefix(OPENAPI_REF_PREFIX) {
+                    *r = format!("{}{}", JSON_SCHEMA_REF_PREFIX, name);
+                }
+            }
+            if m.remove("nullable") == Some(Value::Bool(true)) {
+                if let Some(Value::String(t)) = m.get("type") {
+                    let t = t.to_owned();
+                    m.insert("type".to_string(), json!([t, "null"]));
+                }
+            }
+            // OpenAPI only, but don't touch a property named `discriminator`
+            if matches!(m.get("discriminator"), Some(d) if d.get("propertyName").is_some()) {
+                m.remove("discriminator");
+            }
+            m.values_mut().for_each(convert_openapi_schema);
+        }
+        Value::Array(a) => a.iter_mut().for_each(convert_openapi_schema),
+        _ => {}
+    }
+}
+
+/**
+ * Generate TypeScript definitions from the JSON Schema generated by `api_json_schema`
+ */
+pub fn typescript_definitions(schema: &Value) -> String {
+    let mut ret = String::from("// Generated from the registry API models, do not edit.\n");
+    if let Some(Value::Object(definitions)) = schema.get("definitions") {
+        for (name, def) in definitions {
+            ret.push('\n');
+            if let Some(Value::String(desc)) = def.get("description") {
+                ret.push_str(&format!("/** {} */\n", desc));
+            }
+            match def.get("properties") {
+                Some(Value::Object(properties)) => {
+                    ret.push_str(&format!(
+                        "export interface {} {}\n",
+                        ts_name(name),
+                        ts_object(properties, def, 0)
+                    ));
+                }
+                _ => {
+                    ret.push_str(&format!(
+                        "export type {} = {};\n",
+                        ts_name(name),
+                        ts_type(def, 0)
+                    ));
+                }
+            }
+        }
+    }
+    ret
+}
+
+/**
+ * Schema names can contain characters not allowed in TypeScript identifiers, e.g. `Vec<String>`
+ */
+fn ts_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn ts_object(properties: &Map<String, Value>, def: &Value, indent: usize) -> String {
+    let required: Vec<&str> = def
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let pad = "  ".repeat(indent + 1);
+    let mut ret = String::from("{\n");
+    for (field, prop) in properties {
+        if let Some(Value::String(desc)) = prop.get("description") {
+            ret.push_str(&format!("{}/** {} */\n", pad, desc));
+        }
+        ret.push_str(&format!(
+            "{}{}{}: {};\n",
+            pad,
+            serde_json::to_string(field).unwrap_or_default(),
+            if required.contains(&field.as_str()) {
+                ""
+            } else {
+                "?"
+            },
+            ts_type(prop, indent + 1)
+        ));
+    }
+    ret.push_str(&"  ".repeat(indent));
+    ret.push('}');
+    ret
+}
+
+fn ts_union(schemas: &Value, separator: &str, indent: usize) -> Option<String> {
+    schemas.as_array().map(|a| {
+        a.iter()
+            .map(|s| ts_type(s, indent))
+            .collect::<Vec<_>>()
+            .join(separator)
+    })
+}
+
+fn ts_type(schema: &Value, indent: usize) -> String {
+    if let Some(Value::String(r)) = schema.get("$ref") {
+        return ts_name(r.rsplit('/').next().unwrap_or_default());
+    }
+    if let Some(u) = schema.get("oneOf").or_else(|| schema.get("anyOf")) {
+        return ts_union(u, " | ", indent).unwrap_or_else(|| "unknown".to_string());
+    }
+    if let Some(u) = schema.get("allOf") {
+        return ts_union(u, " & ", indent).unwrap_or_else(|| "unknown".to_string());
+    }
+    if let Some(Value::Array(items)) = schema.get("enum") {
+        return items
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(" | ");
+    }
+    match schema.get("type") {
+        Some(Value::Array(types)) => types
+            .iter()
+            .map(|t| {
+                let mut s = schema.clone();
+                s["type"] = t.clone();
+                ts_type(&s, indent)
+            })
+            .collect::<Vec<_>>()
+            .join(" | "),
+        Some(Value::String(t)) => match t.as_str() {
+            "string" => "string".to_string(),
+            "integer" | "number" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => format!(
+                "Array<{}>",
+                schema
+                    .get("items")
+                    .map(|i| ts_type(i, indent))
+                    .unwrap_or_else(|| "unknown".to_string())
+            ),
+            "object" => match (schema.get("properties"), schema.get("additionalProperties")) {
+                (Some(Value::Object(properties)), _) => ts_object(properties, schema, indent),
+                (_, Some(v @ Value::Object(_))) => {
+                    format!("Record<string, {}>", ts_type(v, indent))
+                }
+                _ => "Record<string, unknown>".to_string(),
+            },
+            _ => "unknown".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_typescript_definitions() {
+        let schema = to_json_schema(json!({
+            "EdgeType": {
+                "type": "string",
+                "enum": ["BelongsTo", "Contains"],
+            },
+            "Relationship": {
+                "type": "object",
+                "required": ["relationshipType", "fromEntityId"],
+                "properties": {
+                    "relationshipType": {"$ref": "#/components/schemas/EdgeType"},
+                    "fromEntityId": {"type": "string", "format": "uuid"},
+                    "tags": {"type": "object", "additionalProperties": {"type": "string"}},
+                    "version": {"type": "integer", "nullable": true},
+                    "keys": {"type": "array", "items": {"type": "string"}},
+                },
+            },
+        }));
+        assert_eq!(
+            schema["definitions"]["Relationship"]["properties"]["relationshipType"]["$ref"],
+            "#/definitions/EdgeType"
+        );
+        assert_eq!(
+            schema["definitions"]["Relationship"]["properties"]["version"]["type"],
+            json!(["integer", "null"])
+        );
+        let ts = typescript_definitions(&schema);
+        assert!(ts.contains(r#"export type EdgeType = "BelongsTo" | "Contains";"#));
+        assert!(ts.contains(r#"  "relationshipType": EdgeType;"#));
+        assert!(ts.contains(r#"  "fromEntityId": string;"#));
+        assert!(ts.contains(r#"  "tags"?: Record<string, string>;"#));
+        assert!(ts.contains(r#"  "version"?: number | null;"#));
+        assert!(ts.contains(r#"  "keys"?: Array<string>;"#));
+    }
+}
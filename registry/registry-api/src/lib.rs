@@ -1,7 +1,14 @@
 mod api_provider;
 mod api_models;
+mod codegen;
 mod error;
+mod graph_export;
+mod json_schema;
+mod validation;
 
 pub use api_provider::*;
 pub use api_models::*;
+pub use codegen::CodegenLanguage;
 pub use error::*;
+pub use json_schema::*;
+pub use validation::*;
@@ -0,0 +1,514 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use regex::Regex;
+use registry_provider::ErrorCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{ApiError, EntityType, FeathrApiRequest};
+
+/**
+ * The definition being created, extracted from a writing request
+ */
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationTarget {
+    pub entity_type: EntityType,
+    /// Project id or name, empty when creating a project
+    pub project: String,
+    pub name: String,
+    pub tags: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<String>,
+    pub definition: Value,
+}
+
+impl ValidationTarget {
+    pub fn from_request(request: &FeathrApiRequest) -> Option<Self> {
+        fn to_value<T: Serialize>(t: &T) -> Value {
+            serde_json::to_value(t).unwrap_or_default()
+        }
+        Some(match request {
+            FeathrApiRequest::CreateProject { definition } => Self {
+                entity_type: EntityType::Project,
+                project: Default::default(),
+                name: definition.name.clone(),
+                tags: definition.tags.clone(),
+                source_type: None,
+                definition: to_value(definition),
+            },
+            FeathrApiRequest::CreateProjectDataSource {
+                project_id_or_name,
+                definition,
+            } => Self {
+                entity_type: EntityType::Source,
+                project: project_id_or_name.clone(),
+                name: definition.name.clone(),
+                tags: definition.tags.clone(),
+                source_type: Some(definition.source_type.clone()),
+                definition: to_value(definition),
+            },
+            FeathrApiRequest::CreateProjectAnchor {
+                project_id_or_name,
+                definition,
+            } => Self {
+                entity_type: EntityType::Anchor,
+                project: project_id_or_name.clone(),
+                name: definition.name.clone(),
+                tags: definition.tags.clone(),
+                source_type: None,
+                definition: to_value(definition),
+            },
+            FeathrApiRequest::CreateAnchorFeature {
+                project_id_or_name,
+                definition,
+                ..
+            } => Self {
+                entity_type: EntityType::AnchorFeature,
+                project: project_id_or_name.clone(),
+                name: definition.name.clone(),
+                tags: definition.tags.clone(),
+                source_type: None,
+                definition: to_value(definition),
+            },
+            FeathrApiRequest::CreateProjectDerivedFeature {
+                project_id_or_name,
+                definition,
+            } => Self {
+                entity_type: EntityType::DerivedFeature,
+                project: project_id_or_name.clone(),
+                name: definition.name.clone(),
+                tags: definition.tags.clone(),
+                source_type: None,
+                definition: to_value(definition),
+            },
+            FeathrApiRequest::CreateMaterializationRecord {
+                project_id_or_name,
+                definition,
+            } => Self {
+                entity_type: EntityType::MaterializationRecord,
+                project: project_id_or_name.clone(),
+                name: definition.name.clone(),
+                tags: definition.tags.clone(),
+                source_type: None,
+                definition: to_value(definition),
+            },
+            FeathrApiRequest::CreateDataset {
+                project_id_or_name,
+                definition,
+            } => Self {
+                entity_type: EntityType::Dataset,
+                project: project_id_or_name.clone(),
+                name: definition.name.clone(),
+                tags: definition.tags.clone(),
+                source_type: None,
+                definition: to_value(definition),
+            },
+            FeathrApiRequest::InTenant { request, .. }
+            | FeathrApiRequest::Idempotent { request, .. } => return Self::from_request(request),
+            _ => return None,
+        })
+    }
+}
+
+/**
+ * The reason why a definition is rejected
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    /// Name of the policy that rejected the definition
+    pub policy: String,
+    pub message: String,
+    /// Optional link to the policy document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+}
+
+impl From<PolicyViolation> for ApiError {
+    fn from(v: PolicyViolation) -> Self {
+        let message = match v.reference {
+            Some(reference) => format!(
+                "Rejected by policy `{}`: {}, see {}",
+                v.policy, v.message, reference
+            ),
+            None => format!("Rejected by policy `{}`: {}", v.policy, v.message),
+        };
+        ApiError::Coded(ErrorCode::InvalidDefinition, message)
+    }
+}
+
+#[async_trait]
+pub trait ValidationHook: Debug + Send + Sync {
+    /**
+     * Name of the policy, returned to the client when the definition is rejected
+     */
+    fn policy(&self) -> String;
+
+    /**
+     * Check the definition before it's written into the registry
+     */
+    async fn validate(&self, target: &ValidationTarget) -> Result<(), PolicyViolation>;
+}
+
+/**
+ * Entity names must match the pattern
+ */
+#[derive(Clone, Debug)]
+pub struct NamePolicy {
+    pattern: Regex,
+    entity_types: HashSet<EntityType>,
+}
+
+impl NamePolicy {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            entity_types: Default::default(),
+        })
+    }
+
+    /**
+     * Only apply the policy on specified entity type, applies to all types if not set
+     */
+    pub fn entity_type(mut self, entity_type: EntityType) -> Self {
+        self.entity_types.insert(entity_type);
+        self
+    }
+}
+
+#[async_trait]
+impl ValidationHook for NamePolicy {
+    fn policy(&self) -> String {
+        format!("name-pattern:{}", self.pattern.as_str())
+    }
+
+    async fn validate(&self, target: &ValidationTarget) -> Result<(), PolicyViolation> {
+        if !self.entity_types.is_empty() && !self.entity_types.contains(&target.entity_type) {
+            return Ok(());
+        }
+        if self.pattern.is_match(&target.name) {
+            Ok(())
+        } else {
+            Err(PolicyViolation {
+                policy: self.policy(),
+                message: format!(
+                    "Name `{}` doesn't match pattern `{}`",
+                    target.name,
+                    self.pattern.as_str()
+                ),
+                reference: None,
+            })
+        }
+    }
+}
+
+/**
+ * Definitions must carry all specified tags
+ */
+#[derive(Clone, Debug)]
+pub struct RequiredTagsPolicy {
+    tags: Vec<String>,
+}
+
+impl RequiredTagsPolicy {
+    pub fn new<T>(tags: &[T]) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl ValidationHook for RequiredTagsPolicy {
+    fn policy(&self) -> String {
+        "required-tags".to_string()
+    }
+
+    async fn validate(&self, target: &ValidationTarget) -> Result<(), PolicyViolation> {
+        let missing: Vec<&str> = self
+            .tags
+            .iter()
+            .filter(|t| !target.tags.contains_key(t.as_str()))
+            .map(|t| t.as_str())
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(PolicyViolation {
+                policy: self.policy(),
+                message: format!("Missing mandatory tags: {}", missing.join(", ")),
+                reference: None,
+            })
+        }
+    }
+}
+
+/**
+ * Sources with specified types cannot be created
+ */
+#[derive(Clone, Debug)]
+pub struct BannedSourceTypesPolicy {
+    types: HashSet<String>,
+}
+
+impl BannedSourceTypesPolicy {
+    pub fn new<T>(types: &[T]) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            types: types.iter().map(|t| t.to_string().to_lowercase()).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl ValidationHook for BannedSourceTypesPolicy {
+    fn policy(&self) -> String {
+        "banned-source-types".to_string()
+    }
+
+    async fn validate(&self, target: &ValidationTarget) -> Result<(), PolicyViolation> {
+        match &target.source_type {
+            Some(t) if self.types.contains(&t.to_lowercase()) => Err(PolicyViolation {
+                policy: self.policy(),
+                message: format!("Source type `{}` is not allowed", t),
+                reference: None,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Time to wait for the validation service to accept the connection
+pub const DEFAULT_WEBHOOK_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time to wait for the validation service to respond, including connecting
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/**
+ * Delegate the validation to an external HTTP service.
+ *
+ * The `ValidationTarget` is posted to the url as JSON, the definition is accepted if the service
+ * returns 2xx, otherwise the response body is parsed as `PolicyViolation` to explain the rejection.
+ * The definition is rejected if the service cannot be reached or doesn't respond in time.
+ */
+#[derive(Clone, Debug)]
+pub struct WebhookValidator {
+    url: String,
+    client: reqwest::Client,
+    timeout: Duration,
+}
+
+impl WebhookValidator {
+    pub fn new(url: &str) -> Self {
+        Self::with_timeouts(
+            url,
+            DEFAULT_WEBHOOK_CONNECT_TIMEOUT,
+            DEFAULT_WEBHOOK_TIMEOUT,
+        )
+    }
+
+    pub fn with_timeouts(url: &str, connect_timeout: Duration, timeout: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            // Building the client only fails if the TLS backend cannot be initialized, the default
+            // client has the same problem
+            client: reqwest::Client::builder()
+                .connect_timeout(connect_timeout)
+                .build()
+                .unwrap_or_default(),
+            timeout,
+        }
+    }
+}
+
+#[async_trait]
+impl ValidationHook for WebhookValidator {
+    fn policy(&self) -> String {
+        format!("webhook:{}", self.url)
+    }
+
+    async fn validate(&self, target: &ValidationTarget) -> Result<(), PolicyViolation> {
+        debug!("Validating {:?} `{}` via {}", target.entity_type, target.name, self.url);
+        let resp = self
+            .client
+            .post(&self.url)
+            .timeout(self.timeout)
+            .json(target)
+            .send()
+            .await
+            .map_err(|e| {
+                // Reject the definition if the validator is unavailable, otherwise the policy can be bypassed
+                warn!("Validation webhook {} failed, error: {}", self.url, e);
+                PolicyViolation {
+                    policy: self.policy(),
+                    message: if e.is_timeout() {
+                        "Validation service timed out".to_string()
+                    } else {
+                        "Validation service is unavailable".to_string()
+                    },
+                    reference: None,
+                }
+            })?;
+        if resp.status().is_success() {
+            return Ok(());
+        }
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        Err(
+            serde_json::from_str(&body).unwrap_or_else(|_| PolicyViolation {
+                policy: self.policy(),
+                message: if body.is_empty() {
+                    status.to_string()
+                } else {
+                    body
+                },
+                reference: None,
+            }),
+        )
+    }
+}
+
+/**
+ * Runs all hooks in order, stops at the first violation
+ */
+#[derive(Clone, Debug, Default)]
+pub struct ValidationChain {
+    hooks: Vec<Arc<dyn ValidationHook>>,
+}
+
+impl ValidationChain {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn hook<T>(mut self, hook: T) -> Self
+    where
+        T: ValidationHook + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub async fn validate(&self, request: &FeathrApiRequest) -> Result<(), ApiError> {
+        if let Some(target) = ValidationTarget::from_request(request) {
+            for hook in self.hooks.iter() {
+                hook.validate(&target).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProjectDef, SourceDef};
+
+    fn source(name: &str, source_type: &str, tags: &[(&str, &str)]) -> FeathrApiRequest {
+        FeathrApiRequest::CreateProjectDataSource {
+            project_id_or_name: "p1".to_string(),
+            definition: SourceDef {
+                id: Default::default(),
+                name: name.to_string(),
+                qualified_name: Default::default(),
+                source_type: source_type.to_string(),
+                options: Default::default(),
+                event_timestamp_column: None,
+                timestamp_format: None,
+                preprocessing: None,
+                tags: tags
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect::<HashMap<_, _>>(),
+                created_by: Default::default(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain() {
+        let chain = ValidationChain::new()
+            .hook(
+                NamePolicy::new("^[a-z][a-z0-9_]*$")
+                    .unwrap()
+                    .entity_type(EntityType::Source),
+            )
+            .hook(RequiredTagsPolicy::new(&["owner"]))
+            .hook(BannedSourceTypesPolicy::new(&["JDBC"]));
+
+        assert!(chain
+            .validate(&source("src_1", "hdfs", &[("owner", "me")]))
+            .await
+            .is_ok());
+        assert!(chain
+            .validate(&source("Src-1", "hdfs", &[("owner", "me")]))
+            .await
+            .is_err());
+        assert!(chain.validate(&source("src_1", "hdfs", &[])).await.is_err());
+        assert!(chain
+            .validate(&source("src_1", "jdbc", &[("owner", "me")]))
+            .await
+            .is_err());
+
+        // Name policy only applies to sources
+        let project = FeathrApiRequest::CreateProject {
+            definition: ProjectDef {
+                id: Default::default(),
+                name: "Project-1".to_string(),
+                qualified_name: Default::default(),
+                tags: [("owner".to_string(), "me".to_string())].into_iter().collect(),
+                ownership: Default::default(),
+                default_tags: Default::default(),
+                created_by: Default::default(),
+            },
+        };
+        assert!(chain.validate(&project).await.is_ok());
+
+        // Reading requests are not validated
+        assert!(chain
+            .validate(&FeathrApiRequest::GetProject {
+                id_or_name: "Project-1".to_string()
+            })
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_timeout() {
+        // Accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut conns = vec![];
+            while let Ok((conn, _)) = listener.accept().await {
+                conns.push(conn);
+            }
+        });
+
+        let validator = WebhookValidator::with_timeouts(
+            &format!("http://{}/validate", addr),
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+        );
+        let target = ValidationTarget::from_request(&source("src_1", "hdfs", &[])).unwrap();
+        let start = std::time::Instant::now();
+        let violation = validator.validate(&target).await.unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(violation.message, "Validation service timed out");
+    }
+}
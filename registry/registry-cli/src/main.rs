@@ -1,3 +1,121 @@
-fn main() {
-    println!("Hello, world!");
+use std::process::exit;
+
+use clap::{Parser, Subcommand};
+use raft_registry::{load_backup, migrate_journal, JournalBackend, RegistryClient};
+use registry_provider::{Edge, Entity, EntityProperty};
+use sql_provider::diff_content;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Opt {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two registries or backups, exits with code 1 if they differ
+    Diff {
+        /// The old side, either a registry node address `http://host:port` or a backup location
+        old: String,
+
+        /// The new side, either a registry node address `http://host:port` or a backup location
+        new: String,
+
+        /// Management code or a management token with the `maintenance` scope to export content from live registries
+        #[clap(long, env = "MANAGEMENT_CODE")]
+        management_code: Option<String>,
+
+        /// Print the differences in JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Copy the Raft journal of a stopped node to another storage engine, the source journal is kept for rollback
+    MigrateJournal {
+        /// Raft Node ID
+        #[clap(long, env = "NODE_ID")]
+        node_id: u64,
+
+        /// The storage engine to copy from
+        #[clap(long, default_value = "sled")]
+        from: JournalBackend,
+
+        /// The storage engine to copy to
+        #[clap(long, default_value = "rocksdb")]
+        to: JournalBackend,
+
+        #[clap(long, env = "RAFT_JOURNAL_PATH", default_value = "./journal")]
+        journal_path: String,
+
+        #[clap(long, env = "RAFT_INSTANCE_PREFIX", default_value = "feathr-registry")]
+        instance_prefix: String,
+    },
+}
+
+/**
+ * Load entities and edges from a live registry node or a backup
+ */
+async fn load_content(
+    source: &str,
+    management_code: &Option<String>,
+) -> anyhow::Result<(Vec<Entity<EntityProperty>>, Vec<Edge>)> {
+    match source.strip_prefix("http://") {
+        Some(addr) => {
+            let client = RegistryClient::new(
+                0,
+                addr.trim_end_matches('/').to_string(),
+                management_code.to_owned(),
+            );
+            let (entities, edges, _) = client
+                .export()
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to export from {}, {}", source, e))?;
+            Ok((entities, edges))
+        }
+        None => {
+            let (entities, edges, _) = load_backup(source).await?;
+            Ok((entities, edges))
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let opt = Opt::parse();
+    match opt.command {
+        Command::Diff {
+            old,
+            new,
+            management_code,
+            json,
+        } => {
+            let (old_entities, old_edges) = load_content(&old, &management_code).await?;
+            let (new_entities, new_edges) = load_content(&new, &management_code).await?;
+            let diff = diff_content(&old_entities, &old_edges, &new_entities, &new_edges);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else {
+                println!("{}", diff);
+            }
+            if !diff.is_empty() {
+                exit(1);
+            }
+        }
+        Command::MigrateJournal {
+            node_id,
+            from,
+            to,
+            journal_path,
+            instance_prefix,
+        } => {
+            let count = migrate_journal(&journal_path, &instance_prefix, node_id, from, to)?;
+            println!(
+                "Copied {} entries to {}, start the node with `--journal-storage {}`",
+                count,
+                to.journal_file(&journal_path, &instance_prefix, node_id),
+                to
+            );
+        }
+    }
+    Ok(())
 }
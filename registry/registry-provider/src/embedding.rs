@@ -0,0 +1,24 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+
+use crate::RegistryError;
+
+/**
+ * Convert entity doc strings and search queries into vectors for semantic search.
+ *
+ * Vectors of the same embedder are compared with cosine similarity, so they must have the same
+ * dimension, but don't need to be normalized.
+ */
+#[async_trait]
+pub trait Embedder: Debug + Send + Sync {
+    /**
+     * Name of the embedding model, for logging purposes
+     */
+    fn model(&self) -> String;
+
+    /**
+     * Returns one vector for each text, in the same order
+     */
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, RegistryError>;
+}
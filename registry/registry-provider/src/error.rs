@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
@@ -26,6 +26,9 @@ pub enum RegistryError {
     #[error("Invalid edge from [{0:?}] to [{1:?}]")]
     InvalidEdge(EntityType, EntityType),
 
+    #[error("{0}")]
+    InvalidDefinition(String),
+
     #[error("Cannot delete [{0}] when it still has dependents")]
     DeleteInUsed(Uuid),
 
@@ -35,7 +38,99 @@ pub enum RegistryError {
     #[error("{0}")]
     ExternalStorageError(String),
 
+    #[error("Semantic search is not enabled")]
+    SemanticSearchDisabled,
+
+    #[error("{0}")]
+    EmbeddingError(String),
+
     #[error(transparent)]
     RbacError(#[from] RbacError),
 }
 
+impl RegistryError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RegistryError::WrongEntityType(_, _) => ErrorCode::WrongEntityType,
+            RegistryError::EntityNotFound(_) | RegistryError::InvalidEntity(_) => {
+                ErrorCode::EntityNotFound
+            }
+            RegistryError::EntityNameExists(_) | RegistryError::EntityIdExists(_) => {
+                ErrorCode::EntityExists
+            }
+            RegistryError::InvalidEdge(_, _) => ErrorCode::InvalidEdge,
+            RegistryError::InvalidDefinition(_) => ErrorCode::InvalidDefinition,
+            RegistryError::DeleteInUsed(_) => ErrorCode::EntityInUse,
+            RegistryError::FtsError(_) => ErrorCode::SearchError,
+            RegistryError::ExternalStorageError(_) => ErrorCode::StorageError,
+            RegistryError::SemanticSearchDisabled => ErrorCode::SemanticSearchDisabled,
+            RegistryError::EmbeddingError(_) => ErrorCode::EmbeddingError,
+            RegistryError::RbacError(e) => e.code(),
+        }
+    }
+}
+
+/**
+ * Stable machine-readable code of an error, returned in the `code` field of JSON error bodies.
+ * Codes are never renamed or removed, the message may change between releases.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The entity or resource doesn't exist, or isn't visible to the tenant
+    EntityNotFound,
+    /// The entity exists but has a different type than the one requested
+    WrongEntityType,
+    /// An entity with the same name or id already exists
+    EntityExists,
+    /// The entity cannot be deleted as other entities still depend on it
+    EntityInUse,
+    /// The definition is rejected, either malformed or violating a policy
+    InvalidDefinition,
+    /// The relationship is not allowed between the entity types
+    InvalidEdge,
+    /// The request is malformed, e.g. an invalid id or parameter
+    BadRequest,
+    /// The request conflicts with the current state of the registry
+    Conflict,
+    CredentialNotFound,
+    PermissionDenied,
+    SemanticSearchDisabled,
+    SearchError,
+    EmbeddingError,
+    /// The database or another external storage failed
+    StorageError,
+    InternalError,
+}
+
+impl ErrorCode {
+    /**
+     * HTTP status of the error responses with this code
+     */
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrorCode::EntityNotFound | ErrorCode::WrongEntityType => 404,
+            ErrorCode::EntityExists | ErrorCode::EntityInUse | ErrorCode::Conflict => 409,
+            ErrorCode::InvalidDefinition
+            | ErrorCode::InvalidEdge
+            | ErrorCode::BadRequest
+            | ErrorCode::CredentialNotFound
+            | ErrorCode::SemanticSearchDisabled => 400,
+            ErrorCode::PermissionDenied => 403,
+            ErrorCode::SearchError
+            | ErrorCode::EmbeddingError
+            | ErrorCode::StorageError
+            | ErrorCode::InternalError => 500,
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Same as the serialized form, e.g. `ENTITY_NOT_FOUND`
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(s)) => f.write_str(&s),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
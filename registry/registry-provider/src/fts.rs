@@ -1,5 +1,7 @@
 use std::fmt::Debug;
 
+use chrono::{DateTime, Utc};
+
 use crate::{models::EntityProperty, Entity};
 
 /**
@@ -10,6 +12,31 @@ pub trait ToDoc {
     fn get_id(&self) -> String;
     fn get_type(&self) -> String;
     fn get_body(&self) -> String;
+
+    /**
+     * Searched with a lower boost than the name
+     */
+    fn get_qualified_name(&self) -> String {
+        Default::default()
+    }
+
+    /**
+     * Labels and tags, searched with a lower boost than the names
+     */
+    fn get_tags(&self) -> String {
+        Default::default()
+    }
+
+    fn get_version(&self) -> u64 {
+        0
+    }
+
+    /**
+     * Recently modified entities are ranked higher
+     */
+    fn get_modified(&self) -> Option<DateTime<Utc>> {
+        None
+    }
 }
 
 /**
@@ -17,6 +44,14 @@ pub trait ToDoc {
  */
 pub trait ToDocString {
     fn to_doc_string(&self) -> String;
+
+    fn to_tags_doc_string(&self) -> String {
+        Default::default()
+    }
+
+    fn get_modified(&self) -> Option<DateTime<Utc>> {
+        None
+    }
 }
 
 impl<T> ToDoc for Entity<T>
@@ -24,7 +59,7 @@ where
     T: ToDocString + Clone + Debug + PartialEq + Eq,
 {
     fn get_name(&self) -> String {
-        vec![process_name(&self.name), process_name(&self.qualified_name)].join("\n")
+        process_name(&self.name)
     }
 
     fn get_id(&self) -> String {
@@ -38,6 +73,22 @@ where
     fn get_body(&self) -> String {
         self.properties.to_doc_string()
     }
+
+    fn get_qualified_name(&self) -> String {
+        process_name(&self.qualified_name)
+    }
+
+    fn get_tags(&self) -> String {
+        self.properties.to_tags_doc_string()
+    }
+
+    fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    fn get_modified(&self) -> Option<DateTime<Utc>> {
+        self.properties.get_modified()
+    }
 }
 
 impl ToDocString for EntityProperty {
@@ -48,8 +99,43 @@ impl ToDocString for EntityProperty {
             self.display_text.to_owned(),
         ];
         v.extend(self.labels.iter().cloned());
+        // Make entities searchable by owner and team
+        v.extend(
+            [
+                &self.ownership.owner_email,
+                &self.ownership.owner_team,
+                &self.ownership.support_channel,
+            ]
+            .into_iter()
+            .flatten()
+            .cloned(),
+        );
+        if !self.docs.is_empty() {
+            v.push(self.docs.to_owned());
+        }
         v.join("\n")
     }
+
+    fn to_tags_doc_string(&self) -> String {
+        self.labels
+            .iter()
+            .cloned()
+            .chain(
+                self.tags
+                    .iter()
+                    .map(|(k, v)| format!("{}\n{}", process_name(k), v)),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /**
+     * Every change creates a new version, so the creation time of the version is the last
+     * modification
+     */
+    fn get_modified(&self) -> Option<DateTime<Utc>> {
+        Some(self.created_on)
+    }
 }
 
 /**
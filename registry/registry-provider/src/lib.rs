@@ -1,10 +1,12 @@
+mod embedding;
 mod error;
 mod fts;
 mod models;
 mod registry;
 mod rbac_provider;
 
-pub use error::RegistryError;
+pub use embedding::Embedder;
+pub use error::{ErrorCode, RegistryError};
 pub use fts::*;
 pub use models::*;
 pub use registry::*;
@@ -1,7 +1,9 @@
 use std::{fmt::Debug, collections::HashMap};
 use std::hash::Hash;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -136,6 +138,27 @@ pub struct DerivedFeatureAttributes {
     pub key: Vec<TypedKey>,
 }
 
+/**
+ * Sources joined with the primary source of a composite anchor, the primary source is the one
+ * consumed by the anchor but not listed here, the preprocessing and the default aggregation window
+ * of the anchor
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchorAttributes {
+    #[serde(default)]
+    pub joined_source_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub join_keys: Vec<String>,
+    /// Applied to the source, or to the joined sources of a composite anchor, after the
+    /// preprocessing of the sources
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub preprocessing: Option<String>,
+    /// Inherited by the window aggregation features of the anchor not setting their own window
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default_window: Option<String>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SourceAttributes {
@@ -147,10 +170,33 @@ pub struct SourceAttributes {
     pub event_timestamp_column: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub timestamp_format: Option<String>,
+    /// Last time the connectivity test of the source passed
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_verified: Option<DateTime<Utc>>,
     #[serde(flatten, default)]
     pub options: HashMap<String, String>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializationRecordAttributes {
+    pub sink: String,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub job_id: String,
+}
+
+/**
+ * Output of a job consuming features, e.g. a training dataset
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetAttributes {
+    pub path: String,
+    /// The job first wrote the dataset
+    pub job_id: String,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "typeName", content = "attributes")]
 pub enum Attributes {
@@ -158,10 +204,36 @@ pub enum Attributes {
     AnchorFeature(AnchorFeatureAttributes),
     #[serde(rename = "feathr_derived_feature_v1")]
     DerivedFeature(DerivedFeatureAttributes),
+    /// `None` for anchors with single source
     #[serde(rename = "feathr_anchor_v1")]
-    Anchor,
+    Anchor(Option<AnchorAttributes>),
     #[serde(rename = "feathr_source_v1")]
     Source(SourceAttributes),
     #[serde(rename = "feathr_workspace_v1")]
     Project,
+    #[serde(rename = "feathr_materialization_record_v1")]
+    MaterializationRecord(MaterializationRecordAttributes),
+    #[serde(rename = "feathr_dataset_v1")]
+    Dataset(DatasetAttributes),
+}
+
+/**
+ * Who owns the entity and how to reach them
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ownership {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_team: Option<String>,
+    /// Where to ask questions, e.g. a Teams or Slack channel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub support_channel: Option<String>,
+}
+
+impl Ownership {
+    pub fn is_empty(&self) -> bool {
+        self.owner_email.is_none() && self.owner_team.is_none() && self.support_channel.is_none()
+    }
 }
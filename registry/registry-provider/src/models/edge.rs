@@ -14,8 +14,18 @@ pub enum EdgeType {
 
     // AnchorGroup uses Source, DerivedFeature used Anchor/DerivedFeatures
     Consumes,
+    // MaterializationRecord uses Anchor/DerivedFeatures
     // Source used by AnchorGroup, Anchor/DerivedFeatures derives DerivedFeature
     Produces,
+
+    // Anchor/DerivedFeatures used by Dataset, e.g. a training dataset written by a feature join job
+    ConsumedBy,
+    // Dataset uses Anchor/DerivedFeatures
+    DependsOn,
+
+    // Soft edge between near-duplicate AnchorFeatures, recorded by the duplicate analysis, it
+    // neither blocks deletion nor shows up in lineage traversals
+    SimilarTo,
 }
 
 impl Default for EdgeType {
@@ -31,15 +41,24 @@ impl EdgeType {
             EdgeType::Contains => EdgeType::BelongsTo,
             EdgeType::Consumes => EdgeType::Produces,
             EdgeType::Produces => EdgeType::Consumes,
+            EdgeType::ConsumedBy => EdgeType::DependsOn,
+            EdgeType::DependsOn => EdgeType::ConsumedBy,
+            EdgeType::SimilarTo => EdgeType::SimilarTo,
         }
     }
 
     pub fn is_downstream(self) -> bool {
-        matches!(self, EdgeType::Contains | EdgeType::Produces)
+        matches!(
+            self,
+            EdgeType::Contains | EdgeType::Produces | EdgeType::ConsumedBy
+        )
     }
 
     pub fn is_upstream(self) -> bool {
-        matches!(self, EdgeType::BelongsTo | EdgeType::Consumes)
+        matches!(
+            self,
+            EdgeType::BelongsTo | EdgeType::Consumes | EdgeType::DependsOn
+        )
     }
 
     pub fn validate(&self, from: EntityType, to: EntityType) -> bool {
@@ -111,6 +130,67 @@ impl EdgeType {
                     EntityType::DerivedFeature,
                     EdgeType::Consumes
                 )
+                | (
+                    EntityType::Project,
+                    EntityType::MaterializationRecord,
+                    EdgeType::Contains
+                )
+                | (
+                    EntityType::MaterializationRecord,
+                    EntityType::Project,
+                    EdgeType::BelongsTo
+                )
+                | (
+                    EntityType::MaterializationRecord,
+                    EntityType::AnchorFeature,
+                    EdgeType::Consumes
+                )
+                | (
+                    EntityType::MaterializationRecord,
+                    EntityType::DerivedFeature,
+                    EdgeType::Consumes
+                )
+                | (
+                    EntityType::AnchorFeature,
+                    EntityType::MaterializationRecord,
+                    EdgeType::Produces
+                )
+                | (
+                    EntityType::DerivedFeature,
+                    EntityType::MaterializationRecord,
+                    EdgeType::Produces
+                )
+                | (EntityType::Project, EntityType::Dataset, EdgeType::Contains)
+                | (
+                    EntityType::Dataset,
+                    EntityType::Project,
+                    EdgeType::BelongsTo
+                )
+                | (
+                    EntityType::Dataset,
+                    EntityType::AnchorFeature,
+                    EdgeType::DependsOn
+                )
+                | (
+                    EntityType::Dataset,
+                    EntityType::DerivedFeature,
+                    EdgeType::DependsOn
+                )
+                | (
+                    EntityType::AnchorFeature,
+                    EntityType::Dataset,
+                    EdgeType::ConsumedBy
+                )
+                | (
+                    EntityType::DerivedFeature,
+                    EntityType::Dataset,
+                    EdgeType::ConsumedBy
+                )
+                | (
+                    EntityType::AnchorFeature,
+                    EntityType::AnchorFeature,
+                    EdgeType::SimilarTo
+                )
         )
     }
 }
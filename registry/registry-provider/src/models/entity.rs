@@ -1,14 +1,18 @@
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::fmt::Debug;
 
+use chrono::{DateTime, Utc};
+
 use serde::de::{self, MapAccess, SeqAccess, Visitor};
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    AnchorDef, AnchorFeatureDef, DerivedFeatureDef, ProjectDef, RegistryError, SourceDef,
+    AnchorDef, AnchorFeatureDef, DatasetDef, DerivedFeatureDef, MaterializationRecordDef,
+    Ownership, ProjectDef, RegistryError, Release, SourceDef,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -20,6 +24,8 @@ pub enum EntityType {
     Anchor,
     AnchorFeature,
     DerivedFeature,
+    MaterializationRecord,
+    Dataset,
 }
 
 impl EntityType {
@@ -30,6 +36,8 @@ impl EntityType {
             EntityType::Anchor => "feathr_anchor_v1",
             EntityType::AnchorFeature => "feathr_anchor_feature_v1",
             EntityType::DerivedFeature => "feathr_derived_feature_v1",
+            EntityType::MaterializationRecord => "feathr_materialization_record_v1",
+            EntityType::Dataset => "feathr_dataset_v1",
             EntityType::Unknown => panic!("Unknown Entity Type"),
         }
     }
@@ -257,6 +265,128 @@ where
     fn new_anchor(definition: &AnchorDef) -> Result<Self, RegistryError>;
     fn new_anchor_feature(definition: &AnchorFeatureDef) -> Result<Self, RegistryError>;
     fn new_derived_feature(definition: &DerivedFeatureDef) -> Result<Self, RegistryError>;
+    fn new_materialization_record(
+        definition: &MaterializationRecordDef,
+    ) -> Result<Self, RegistryError>;
+    fn new_dataset(definition: &DatasetDef) -> Result<Self, RegistryError>;
     fn get_version(&self) -> u64;
     fn set_version(&mut self, version: u64);
+
+    /**
+     * Replace the id kept in the properties, ignored if the entity doesn't keep it
+     */
+    fn set_guid(&mut self, _guid: Uuid) {}
+
+    /**
+     * Tags of the entity, used to build the project summary
+     */
+    fn get_tags(&self) -> HashMap<String, String> {
+        Default::default()
+    }
+
+    /**
+     * Tags inherited by new entities of the project, empty if the entity is not a project
+     */
+    fn get_default_tags(&self) -> HashMap<String, String> {
+        Default::default()
+    }
+
+    /**
+     * Add the default tags of the project to the entity, tags already set on the entity take
+     * precedence, ignored if the entity doesn't support tags
+     */
+    fn inherit_tags(&mut self, _defaults: &HashMap<String, String>) {}
+
+    /**
+     * Creation time of the entity, used to build the project summary
+     */
+    fn get_created_on(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    /**
+     * Value type of the feature, `None` if the entity is not a feature
+     */
+    fn get_feature_type(&self) -> Option<String> {
+        None
+    }
+
+    /**
+     * Sources joined with the primary source and the join keys, empty if the entity is not a composite anchor
+     */
+    fn get_source_joins(&self) -> (Vec<Uuid>, Vec<String>) {
+        Default::default()
+    }
+
+    /**
+     * Preprocessing of the anchor, applied after the preprocessing of its sources
+     */
+    fn get_anchor_preprocessing(&self) -> Option<String> {
+        None
+    }
+
+    /**
+     * Aggregation window inherited by the window aggregation features of the anchor
+     */
+    fn get_anchor_default_window(&self) -> Option<String> {
+        None
+    }
+
+    /**
+     * Replace the owner and contact info of the entity, ignored if the entity doesn't support it
+     */
+    fn set_ownership(&mut self, _ownership: &Ownership) {}
+
+    /**
+     * Markdown documentation of the entity, empty if not set
+     */
+    fn get_docs(&self) -> &str {
+        ""
+    }
+
+    /**
+     * Replace the markdown documentation of the entity, ignored if the entity doesn't support it
+     */
+    fn set_docs(&mut self, _docs: &str) {}
+
+    /**
+     * Record the time the source passed the connectivity test, ignored if the entity is not a source
+     */
+    fn set_last_verified(&mut self, _time: DateTime<Utc>) {}
+
+    /**
+     * Labels of the entity version, e.g. the environments it has been promoted to
+     */
+    fn get_labels(&self) -> Vec<String> {
+        Default::default()
+    }
+
+    /**
+     * Replace the labels of the entity version, ignored if the entity doesn't support it
+     */
+    fn set_labels(&mut self, _labels: Vec<String>) {}
+
+    /**
+     * Releases of the project, empty if the entity is not a project
+     */
+    fn get_releases(&self) -> Vec<Release> {
+        Default::default()
+    }
+
+    /**
+     * Replace the releases of the project, ignored if the entity is not a project
+     */
+    fn set_releases(&mut self, _releases: Vec<Release>) {}
+
+    /**
+     * Tenant owning the entity, empty for the default tenant
+     */
+    fn get_tenant(&self) -> &str {
+        ""
+    }
+
+    /**
+     * Move the entity into the tenant, ignored if the entity doesn't support multi-tenancy
+     */
+    fn set_tenant(&mut self, _tenant: &str) {}
 }
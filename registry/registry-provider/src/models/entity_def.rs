@@ -1,9 +1,10 @@
 use std::collections::{HashMap, HashSet};
 
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
-use crate::{FeatureType, FeatureTransformation, TypedKey};
+use crate::{FeatureType, FeatureTransformation, Ownership, TypedKey};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,6 +13,12 @@ pub struct ProjectDef {
     pub qualified_name: String,
     pub created_by: String,
     pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub ownership: Ownership,
+    /// Tags merged into the tags of every entity created under the project, tags set on the
+    /// entity take precedence
+    #[serde(default)]
+    pub default_tags: HashMap<String, String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -38,6 +45,17 @@ pub struct AnchorDef {
     pub name: String,
     pub qualified_name: String,
     pub source_id: Uuid,
+    /// Sources joined with `source_id` on `join_keys`, non-empty makes a composite anchor
+    #[serde(default)]
+    pub source_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub join_keys: Vec<String>,
+    /// Anchor-level preprocessing, applied after the preprocessing of the sources
+    #[serde(default)]
+    pub preprocessing: Option<String>,
+    /// Aggregation window of the window aggregation features not setting their own, e.g. `7d`
+    #[serde(default)]
+    pub default_window: Option<String>,
     pub created_by: String,
     pub tags: HashMap<String, String>,
 }
@@ -53,6 +71,8 @@ pub struct AnchorFeatureDef {
     pub key: Vec<TypedKey>,
     pub created_by: String,
     pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub ownership: Ownership,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -68,4 +88,34 @@ pub struct DerivedFeatureDef {
     pub input_derived_features: HashSet<Uuid>,
     pub created_by: String,
     pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub ownership: Ownership,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterializationRecordDef {
+    pub id: Uuid,
+    pub name: String,
+    pub qualified_name: String,
+    pub sink: String,
+    pub features: HashSet<Uuid>,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub job_id: String,
+    pub created_by: String,
+    pub tags: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetDef {
+    pub id: Uuid,
+    pub name: String,
+    pub qualified_name: String,
+    pub path: String,
+    pub features: HashSet<Uuid>,
+    pub job_id: String,
+    pub created_by: String,
+    pub tags: HashMap<String, String>,
 }
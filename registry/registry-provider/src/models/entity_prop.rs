@@ -5,9 +5,10 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    AnchorDef, AnchorFeatureAttributes, AnchorFeatureDef, Attributes, DerivedFeatureAttributes,
-    DerivedFeatureDef, Entity, EntityPropMutator, EntityType, ProjectDef, RegistryError,
-    SourceAttributes, SourceDef,
+    AnchorAttributes, AnchorDef, AnchorFeatureAttributes, AnchorFeatureDef, Attributes,
+    DatasetAttributes, DatasetDef, DerivedFeatureAttributes, DerivedFeatureDef, Entity,
+    EntityPropMutator, EntityType, MaterializationRecordAttributes, MaterializationRecordDef,
+    Ownership, ProjectDef, RegistryError, Release, SourceAttributes, SourceDef,
 };
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -42,6 +43,19 @@ pub struct EntityProperty {
     pub created_by: String,
     #[serde(default = "default_created_on")]
     pub created_on: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Ownership::is_empty")]
+    pub ownership: Ownership,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tenant: String,
+    /// Free-form documentation in markdown
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub docs: String,
+    /// Releases of the project, empty for other entity types
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub releases: Vec<Release>,
+    /// Tags inherited by new entities of the project, empty for other entity types
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub default_tags: HashMap<String, String>,
     #[serde(flatten)]
     pub attributes: Attributes,
 }
@@ -66,6 +80,11 @@ impl EntityPropMutator for EntityProperty {
             version: 0,
             created_by: definition.created_by.to_owned(),
             created_on: Utc::now(),
+            ownership: definition.ownership.to_owned(),
+            tenant: Default::default(),
+            docs: Default::default(),
+            releases: Default::default(),
+            default_tags: definition.default_tags.to_owned(),
         })
     }
     fn new_source(definition: &SourceDef) -> Result<Self, RegistryError> {
@@ -82,11 +101,17 @@ impl EntityPropMutator for EntityProperty {
                 preprocessing: definition.preprocessing.to_owned(),
                 event_timestamp_column: definition.event_timestamp_column.to_owned(),
                 timestamp_format: definition.timestamp_format.to_owned(),
+                last_verified: None,
                 type_: definition.source_type.to_owned(),
             }),
             version: 0,
             created_by: definition.created_by.to_owned(),
             created_on: Utc::now(),
+            ownership: Default::default(),
+            tenant: Default::default(),
+            docs: Default::default(),
+            releases: Default::default(),
+            default_tags: Default::default(),
         })
     }
     fn new_anchor(definition: &AnchorDef) -> Result<Self, RegistryError> {
@@ -98,10 +123,29 @@ impl EntityPropMutator for EntityProperty {
             status: EntityStatus::Active,
             display_text: definition.name.to_owned(),
             labels: Default::default(),
-            attributes: Attributes::Anchor,
+            attributes: Attributes::Anchor(
+                if definition.source_ids.is_empty()
+                    && definition.preprocessing.is_none()
+                    && definition.default_window.is_none()
+                {
+                    None
+                } else {
+                    Some(AnchorAttributes {
+                        joined_source_ids: definition.source_ids.to_owned(),
+                        join_keys: definition.join_keys.to_owned(),
+                        preprocessing: definition.preprocessing.to_owned(),
+                        default_window: definition.default_window.to_owned(),
+                    })
+                },
+            ),
             version: 0,
             created_by: definition.created_by.to_owned(),
             created_on: Utc::now(),
+            ownership: Default::default(),
+            tenant: Default::default(),
+            docs: Default::default(),
+            releases: Default::default(),
+            default_tags: Default::default(),
         })
     }
     fn new_anchor_feature(definition: &AnchorFeatureDef) -> Result<Self, RegistryError> {
@@ -121,6 +165,11 @@ impl EntityPropMutator for EntityProperty {
             version: 0,
             created_by: definition.created_by.to_owned(),
             created_on: Utc::now(),
+            ownership: definition.ownership.to_owned(),
+            tenant: Default::default(),
+            docs: Default::default(),
+            releases: Default::default(),
+            default_tags: Default::default(),
         })
     }
     fn new_derived_feature(definition: &DerivedFeatureDef) -> Result<Self, RegistryError> {
@@ -140,6 +189,61 @@ impl EntityPropMutator for EntityProperty {
             version: 0,
             created_by: definition.created_by.to_owned(),
             created_on: Utc::now(),
+            ownership: definition.ownership.to_owned(),
+            tenant: Default::default(),
+            docs: Default::default(),
+            releases: Default::default(),
+            default_tags: Default::default(),
+        })
+    }
+    fn new_materialization_record(
+        definition: &MaterializationRecordDef,
+    ) -> Result<Self, RegistryError> {
+        Ok(EntityProperty {
+            guid: definition.id,
+            qualified_name: definition.qualified_name.to_owned(),
+            name: definition.name.to_owned(),
+            tags: definition.tags.to_owned(),
+            status: EntityStatus::Active,
+            display_text: definition.name.to_owned(),
+            labels: Default::default(),
+            attributes: Attributes::MaterializationRecord(MaterializationRecordAttributes {
+                sink: definition.sink.to_owned(),
+                window_start: definition.window_start,
+                window_end: definition.window_end,
+                job_id: definition.job_id.to_owned(),
+            }),
+            version: 0,
+            created_by: definition.created_by.to_owned(),
+            created_on: Utc::now(),
+            ownership: Default::default(),
+            tenant: Default::default(),
+            docs: Default::default(),
+            releases: Default::default(),
+            default_tags: Default::default(),
+        })
+    }
+    fn new_dataset(definition: &DatasetDef) -> Result<Self, RegistryError> {
+        Ok(EntityProperty {
+            guid: definition.id,
+            qualified_name: definition.qualified_name.to_owned(),
+            name: definition.name.to_owned(),
+            tags: definition.tags.to_owned(),
+            status: EntityStatus::Active,
+            display_text: definition.name.to_owned(),
+            labels: Default::default(),
+            attributes: Attributes::Dataset(DatasetAttributes {
+                path: definition.path.to_owned(),
+                job_id: definition.job_id.to_owned(),
+            }),
+            version: 0,
+            created_by: definition.created_by.to_owned(),
+            created_on: Utc::now(),
+            ownership: Default::default(),
+            tenant: Default::default(),
+            docs: Default::default(),
+            releases: Default::default(),
+            default_tags: Default::default(),
         })
     }
     fn get_version(&self) -> u64 {
@@ -148,6 +252,87 @@ impl EntityPropMutator for EntityProperty {
     fn set_version(&mut self, version: u64) {
         self.version = version;
     }
+    fn set_guid(&mut self, guid: Uuid) {
+        self.guid = guid;
+    }
+    fn set_ownership(&mut self, ownership: &Ownership) {
+        self.ownership = ownership.to_owned();
+    }
+    fn get_docs(&self) -> &str {
+        &self.docs
+    }
+    fn set_docs(&mut self, docs: &str) {
+        self.docs = docs.to_string();
+    }
+    fn set_last_verified(&mut self, time: DateTime<Utc>) {
+        if let Attributes::Source(attr) = &mut self.attributes {
+            attr.last_verified = Some(time);
+        }
+    }
+    fn get_labels(&self) -> Vec<String> {
+        self.labels.to_owned()
+    }
+    fn set_labels(&mut self, labels: Vec<String>) {
+        self.labels = labels;
+    }
+    fn get_releases(&self) -> Vec<Release> {
+        self.releases.to_owned()
+    }
+    fn set_releases(&mut self, releases: Vec<Release>) {
+        if let Attributes::Project = self.attributes {
+            self.releases = releases;
+        }
+    }
+    fn get_tags(&self) -> HashMap<String, String> {
+        self.tags.to_owned()
+    }
+    fn get_default_tags(&self) -> HashMap<String, String> {
+        self.default_tags.to_owned()
+    }
+    fn inherit_tags(&mut self, defaults: &HashMap<String, String>) {
+        for (k, v) in defaults {
+            self.tags
+                .entry(k.to_owned())
+                .or_insert_with(|| v.to_owned());
+        }
+    }
+    fn get_created_on(&self) -> Option<DateTime<Utc>> {
+        Some(self.created_on)
+    }
+    fn get_feature_type(&self) -> Option<String> {
+        let t = match &self.attributes {
+            Attributes::AnchorFeature(a) => &a.type_,
+            Attributes::DerivedFeature(a) => &a.type_,
+            _ => return None,
+        };
+        serde_json::to_value(&t.val_type)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+    }
+    fn get_source_joins(&self) -> (Vec<Uuid>, Vec<String>) {
+        match &self.attributes {
+            Attributes::Anchor(Some(a)) => (a.joined_source_ids.clone(), a.join_keys.clone()),
+            _ => Default::default(),
+        }
+    }
+    fn get_anchor_preprocessing(&self) -> Option<String> {
+        match &self.attributes {
+            Attributes::Anchor(Some(a)) => a.preprocessing.clone(),
+            _ => None,
+        }
+    }
+    fn get_anchor_default_window(&self) -> Option<String> {
+        match &self.attributes {
+            Attributes::Anchor(Some(a)) => a.default_window.clone(),
+            _ => None,
+        }
+    }
+    fn get_tenant(&self) -> &str {
+        &self.tenant
+    }
+    fn set_tenant(&mut self, tenant: &str) {
+        self.tenant = tenant.to_string();
+    }
 }
 
 impl From<EntityProperty> for Entity<EntityProperty> {
@@ -157,9 +342,11 @@ impl From<EntityProperty> for Entity<EntityProperty> {
             entity_type: match v.attributes {
                 Attributes::AnchorFeature(_) => EntityType::AnchorFeature,
                 Attributes::DerivedFeature(_) => EntityType::DerivedFeature,
-                Attributes::Anchor => EntityType::Anchor,
+                Attributes::Anchor(_) => EntityType::Anchor,
                 Attributes::Source(_) => EntityType::Source,
                 Attributes::Project => EntityType::Project,
+                Attributes::MaterializationRecord(_) => EntityType::MaterializationRecord,
+                Attributes::Dataset(_) => EntityType::Dataset,
             },
             name: v.name.to_owned(),
             qualified_name: v.qualified_name.to_owned(),
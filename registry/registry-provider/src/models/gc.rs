@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/**
+ * Tombstones of deleted entities removed by a garbage collection, or to be removed in a dry run
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GarbageCollectionReport {
+    /// Entities deleted before this time are removed
+    pub before: DateTime<Utc>,
+    pub dry_run: bool,
+    /// Ids of the removed entities
+    pub entities: Vec<Uuid>,
+    /// Edges still connecting the removed entities, they're normally removed by the deletion
+    pub edges: usize,
+    /// Tombstones kept as they're deleted within the retention period
+    pub retained: usize,
+}
+
+impl GarbageCollectionReport {
+    pub fn new(before: DateTime<Utc>, dry_run: bool) -> Self {
+        Self {
+            before,
+            dry_run,
+            entities: Default::default(),
+            edges: 0,
+            retained: 0,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{EntityType, RegistryError};
+
+/**
+ * How a batch load handles entities conflicting with the existing content, i.e. with the same id or
+ * the same qualified name and version.
+ * Entities identical to an existing version are never loaded again whatever the policy is.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadPolicy {
+    /// Keep the existing entity
+    #[default]
+    Skip,
+    /// Replace the existing entity with the loaded one, the id and the version are kept
+    Overwrite,
+    /// Add the loaded entity as the next version of its qualified name
+    NewVersion,
+}
+
+impl FromStr for LoadPolicy {
+    type Err = RegistryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "new-version" => Ok(Self::NewVersion),
+            _ => Err(RegistryError::InvalidDefinition(format!(
+                "Unknown load policy `{}`, must be one of `skip`, `overwrite` and `new-version`",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LoadAction {
+    Inserted,
+    Overwritten,
+    NewVersion,
+    /// Identical to an existing version
+    Unchanged,
+    /// Conflicting with an existing entity and kept out by the policy
+    Skipped,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadEntry {
+    /// Id of the entity in the loaded content
+    pub id: Uuid,
+    /// Id of the entity in the registry after loading, differs from `id` if the entity is merged
+    /// into an existing one or loaded as a new version
+    pub registry_id: Uuid,
+    pub entity_type: EntityType,
+    pub qualified_name: String,
+    /// Version in the registry after loading
+    pub version: u64,
+    pub action: LoadAction,
+}
+
+/**
+ * Outcome of a batch load, entities failed to load are reported as skipped
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadReport {
+    pub policy: LoadPolicy,
+    pub applied: Vec<LoadEntry>,
+    pub skipped: Vec<LoadEntry>,
+}
+
+impl LoadReport {
+    pub fn new(policy: LoadPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, entry: LoadEntry) {
+        match entry.action {
+            LoadAction::Unchanged | LoadAction::Skipped => self.skipped.push(entry),
+            _ => self.applied.push(entry),
+        }
+    }
+}
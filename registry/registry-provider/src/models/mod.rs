@@ -3,18 +3,28 @@ mod edge;
 mod attributes;
 mod entity_prop;
 mod entity_def;
+mod summary;
+mod release;
+mod load;
+mod gc;
 
 pub use entity::*;
 pub use edge::*;
 pub use attributes::*;
 pub use entity_prop::*;
 pub use entity_def::*;
+pub use summary::*;
+pub use release::*;
+pub use load::*;
+pub use gc::*;
 
 pub const PROJECT_TYPE: &str = "feathr_workspace_v1";
 pub const ANCHOR_TYPE: &str = "feathr_anchor_v1";
 pub const ANCHOR_FEATURE_TYPE: &str = "feathr_anchor_feature_v1";
 pub const DERIVED_FEATURE_TYPE: &str = "feathr_derived_feature_v1";
 pub const SOURCE_TYPE: &str = "feathr_source_v1";
+pub const MATERIALIZATION_RECORD_TYPE: &str = "feathr_materialization_record_v1";
+pub const DATASET_TYPE: &str = "feathr_dataset_v1";
 
 
 #[cfg(test)]
@@ -124,4 +134,46 @@ mod tests {
         let e: Entity<EntityProperty> = e.into();
         println!("{:#?}", e);
     }
+
+    #[test]
+    fn des_anchor() {
+        // Anchors created before composite anchors have no join info
+        let s = r#"{
+            "typeName": "feathr_anchor_v1",
+            "guid": "a4cfbc03-c65d-4f32-be3d-1d11247c9cdd",
+            "name": "request_features",
+            "qualifiedName": "project__request_features",
+            "status": "ACTIVE",
+            "displayText": "request_features",
+            "labels": []
+        }"#;
+        let e: EntityProperty = serde_json::from_str(s).unwrap();
+        assert_eq!(e.attributes, Attributes::Anchor(None));
+        assert_eq!(e.get_source_joins(), (vec![], vec![]));
+
+        let source_id = uuid::Uuid::new_v4();
+        let e = EntityProperty::new_anchor(&AnchorDef {
+            id: uuid::Uuid::new_v4(),
+            name: "trips".to_string(),
+            qualified_name: "project__trips".to_string(),
+            source_id: uuid::Uuid::new_v4(),
+            source_ids: vec![source_id],
+            join_keys: vec!["DOLocationID".to_string()],
+            preprocessing: Some("lambda df: df.dropna()".to_string()),
+            default_window: Some("7d".to_string()),
+            tags: Default::default(),
+            created_by: Default::default(),
+        })
+        .unwrap();
+        let e: EntityProperty = serde_json::from_str(&serde_json::to_string(&e).unwrap()).unwrap();
+        assert_eq!(
+            e.get_source_joins(),
+            (vec![source_id], vec!["DOLocationID".to_string()])
+        );
+        assert_eq!(
+            e.get_anchor_preprocessing(),
+            Some("lambda df: df.dropna()".to_string())
+        );
+        assert_eq!(e.get_anchor_default_window(), Some("7d".to_string()));
+    }
 }
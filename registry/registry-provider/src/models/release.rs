@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/**
+ * Named set of entity versions in a project, loading the project with the release gets exactly
+ * these versions no matter how the project changes afterwards
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub name: String,
+    /// The environment label the versions were picked by, `None` if the latest versions were picked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// Ids of the pinned entity versions, the project itself is not included
+    pub entities: Vec<Uuid>,
+    #[serde(default)]
+    pub created_by: String,
+    pub created_on: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseDef {
+    pub name: String,
+    pub environment: Option<String>,
+    pub created_by: String,
+}
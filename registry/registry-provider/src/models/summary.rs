@@ -0,0 +1,26 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::EntityType;
+
+/**
+ * Statistics of a project, so clients can render an overview without fetching all entities
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectSummary {
+    pub entity_counts: HashMap<EntityType, usize>,
+    pub last_modified: Option<DateTime<Utc>>,
+    /// Most used tags, in descending order of count
+    pub top_tags: Vec<TagCount>,
+    /// Number of features per value type
+    pub feature_types: HashMap<String, usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagCount {
+    pub key: String,
+    pub value: String,
+    pub count: usize,
+}
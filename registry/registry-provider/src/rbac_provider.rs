@@ -3,9 +3,13 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::RegistryError;
+use crate::{Edge, Entity, ErrorCode, RegistryError};
 
-use std::str::FromStr;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    str::FromStr,
+};
 
 use uuid::Uuid;
 
@@ -59,7 +63,7 @@ impl ToString for Permission {
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum Resource {
     Global,
-    // So far only project is used
+    // Project, anchor or feature
     NamedEntity(String),
     Entity(Uuid),
 }
@@ -100,6 +104,16 @@ pub enum RbacError {
     PermissionDenied(String, Resource, Permission),
 }
 
+impl RbacError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RbacError::CredentialNotFound(_) => ErrorCode::CredentialNotFound,
+            RbacError::ResourceNotFound(_) => ErrorCode::EntityNotFound,
+            RbacError::PermissionDenied(_, _, _) => ErrorCode::PermissionDenied,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct RbacRecord {
     pub credential: Credential,
@@ -119,6 +133,72 @@ pub trait RbacProvider: Send + Sync {
         permission: Permission,
     ) -> Result<bool, RegistryError>;
 
+    /**
+     * Returns the resource and all resources it inherits permissions from, most specific first,
+     * e.g. a grant on the project applies to all anchors and features in it
+     */
+    fn get_permission_scopes(&self, resource: &Resource) -> Result<Vec<Resource>, RegistryError>;
+
+    /**
+     * Qualified names of the projects the credential has read access to
+     */
+    fn visible_projects(&self, credential: &Credential) -> Result<Vec<String>, RegistryError>;
+
+    /**
+     * Drop the entities the credential can't read from the lineage of `root`, along with the edges
+     * to them and the entities only connected to `root` through them, e.g. features of other
+     * projects consumed by the derived features
+     */
+    fn readable_lineage<Prop>(
+        &self,
+        credential: &Credential,
+        root: Uuid,
+        entities: Vec<Entity<Prop>>,
+        edges: Vec<Edge>,
+    ) -> (Vec<Entity<Prop>>, Vec<Edge>)
+    where
+        Prop: Clone + Debug + PartialEq + Eq,
+    {
+        let can_read = |id: Uuid| {
+            matches!(
+                self.check_permission(credential, &Resource::Entity(id), Permission::Read),
+                Ok(true)
+            )
+        };
+        let readable: HashSet<Uuid> = entities
+            .iter()
+            .map(|e| e.id)
+            .filter(|&id| id == root || can_read(id))
+            .collect();
+        let mut neighbors: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for e in edges
+            .iter()
+            .filter(|e| readable.contains(&e.from) && readable.contains(&e.to))
+        {
+            neighbors.entry(e.from).or_default().push(e.to);
+            neighbors.entry(e.to).or_default().push(e.from);
+        }
+        let mut connected = HashSet::from([root]);
+        let mut queue = vec![root];
+        while let Some(id) = queue.pop() {
+            for &n in neighbors.get(&id).into_iter().flatten() {
+                if connected.insert(n) {
+                    queue.push(n);
+                }
+            }
+        }
+        (
+            entities
+                .into_iter()
+                .filter(|e| connected.contains(&e.id))
+                .collect(),
+            edges
+                .into_iter()
+                .filter(|e| connected.contains(&e.from) && connected.contains(&e.to))
+                .collect(),
+        )
+    }
+
     fn load_permissions<RI>(&mut self, permissions: RI) -> Result<(), RegistryError>
     where
         RI: Iterator<Item = RbacRecord>;
@@ -1,13 +1,37 @@
-use std::{collections::HashSet, fmt::Debug};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 use crate::{
-    AnchorDef, AnchorFeatureDef, DerivedFeatureDef, Edge, EdgeType, Entity, EntityPropMutator,
-    EntityType, ProjectDef, RbacRecord, RegistryError, SourceDef, ToDocString,
+    AnchorDef, AnchorFeatureDef, DatasetDef, DerivedFeatureDef, Edge, EdgeType, Entity,
+    EntityPropMutator, EntityType, GarbageCollectionReport, LoadPolicy, LoadReport,
+    MaterializationRecordDef, Ownership, ProjectDef, ProjectSummary, RbacRecord, RegistryError,
+    Release, ReleaseDef, SourceDef, ToDocString,
 };
 
+/**
+ * Environment labels and release names are short identifiers, e.g. `prod` or `2023-01`
+ */
+fn validate_label(kind: &str, label: &str) -> Result<(), RegistryError> {
+    if label.is_empty()
+        || label.len() > 64
+        || !label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err(RegistryError::InvalidDefinition(format!(
+            "Invalid {} `{}`, it must be 1 to 64 letters, digits, `-`, `_` or `.`",
+            kind, label
+        )));
+    }
+    Ok(())
+}
+
 pub fn extract_version(name: &str) -> (&str, Option<u64>) {
     match name.rfind(':') {
         Some(pos) => match name[pos + 1..name.len()].parse() {
@@ -31,14 +55,22 @@ where
     EntityProp: Clone + Debug + PartialEq + Eq + EntityPropMutator + ToDocString + Send + Sync,
 {
     /**
-     * Batch load entities and edges
+     * Batch load entities and edges, entities conflicting with the existing content are handled by
+     * the policy
      */
     async fn load_data(
         &mut self,
         entities: Vec<Entity<EntityProp>>,
         edges: Vec<Edge>,
         permissions: Vec<RbacRecord>,
-    ) -> Result<(), RegistryError>;
+        policy: LoadPolicy,
+    ) -> Result<LoadReport, RegistryError>;
+
+    /**
+     * Scope following calls to the tenant, returns the previous tenant so the caller can switch back.
+     * Entities of other tenants are invisible within the scope, empty string is the default tenant
+     */
+    fn switch_tenant(&mut self, tenant: &str) -> String;
 
     /**
      * Get ids of all entry points
@@ -63,6 +95,17 @@ where
      */
     fn get_entities(&self, uuids: HashSet<Uuid>) -> Result<Vec<Entity<EntityProp>>, RegistryError>;
 
+    /**
+     * Get entities with any of the types and all of the tags in the scope, sorted by the qualified
+     * name, a tag without value matches any value
+     */
+    fn get_entities_by_attributes(
+        &self,
+        types: HashSet<EntityType>,
+        tags: HashMap<String, Option<String>>,
+        scope: Option<Uuid>,
+    ) -> Result<Vec<Entity<EntityProp>>, RegistryError>;
+
     /**
      * Get entity id by its name
      */
@@ -99,6 +142,35 @@ where
         offset: usize,
     ) -> Result<Vec<Entity<EntityProp>>, RegistryError>;
 
+    /**
+     * Get one page of FTS results starting after the `cursor` returned with the previous page,
+     * the cursor of the next page is `None` if there are no more results
+     */
+    fn search_entity_page(
+        &self,
+        query: &str,
+        types: HashSet<EntityType>,
+        scope: Option<Uuid>,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> Result<(Vec<Entity<EntityProp>>, Option<String>), RegistryError>;
+
+    /**
+     * Search entities with the blended score of vector similarity and FTS relevance,
+     * `query_vector` is the embedding of the query computed by the caller.
+     * Embeddings of the entities are computed in the background, entities without one yet are
+     * ranked by FTS relevance only
+     */
+    fn semantic_search_entity(
+        &self,
+        query: &str,
+        query_vector: &[f32],
+        types: HashSet<EntityType>,
+        scope: Option<Uuid>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Entity<EntityProp>>, RegistryError>;
+
     /**
      * Get all entities and connections between them under a project
      */
@@ -107,6 +179,11 @@ where
         qualified_name: &str,
     ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError>;
 
+    /**
+     * Get entity counts, last modification time, top tags and feature type distribution of a project
+     */
+    fn get_project_summary(&self, id_or_name: &str) -> Result<ProjectSummary, RegistryError>;
+
     /**
      * Create new project
      */
@@ -149,7 +226,90 @@ where
         definition: &DerivedFeatureDef,
     ) -> Result<(Uuid, u64), RegistryError>;
 
-    async fn delete_entity(&mut self, id: Uuid) -> Result<(), RegistryError>;
+    /**
+     * Record features of the project have been materialized into a sink
+     */
+    async fn new_materialization_record(
+        &mut self,
+        project_id: Uuid,
+        definition: &MaterializationRecordDef,
+    ) -> Result<(Uuid, u64), RegistryError>;
+
+    /**
+     * Record features of the project have been consumed by a job writing the dataset
+     */
+    async fn new_dataset(
+        &mut self,
+        project_id: Uuid,
+        definition: &DatasetDef,
+    ) -> Result<(Uuid, u64), RegistryError>;
+
+    /**
+     * Delete the entity, the tombstone is kept until the garbage collection after `deleted_at`
+     * plus the retention, tombstones without the deletion time are never collected
+     */
+    async fn delete_entity(
+        &mut self,
+        id: Uuid,
+        deleted_at: Option<DateTime<Utc>>,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Permanently remove the tombstones of entities deleted before `before` in all tenants, along
+     * with the edges still connecting them. Nothing is removed if `dry_run` is true
+     */
+    async fn collect_garbage(
+        &mut self,
+        before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<GarbageCollectionReport, RegistryError>;
+
+    /**
+     * Replace the owner and contact info of a project or a feature
+     */
+    async fn set_entity_ownership(
+        &mut self,
+        id: Uuid,
+        ownership: &Ownership,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Replace the markdown documentation of an entity
+     */
+    async fn set_entity_docs(&mut self, id: Uuid, docs: &str) -> Result<(), RegistryError>;
+
+    /**
+     * Record the time the source passed the connectivity test
+     */
+    async fn set_source_verified(
+        &mut self,
+        id: Uuid,
+        time: DateTime<Utc>,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Replace the labels of the entity version
+     */
+    async fn set_entity_labels(
+        &mut self,
+        id: Uuid,
+        labels: Vec<String>,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Replace the releases of the project
+     */
+    async fn set_project_releases(
+        &mut self,
+        id: Uuid,
+        releases: Vec<Release>,
+    ) -> Result<(), RegistryError>;
+
+    /**
+     * Connect every pair of the anchor features with `SimilarTo` edges, pairs already connected
+     * are left unchanged
+     */
+    async fn link_similar_features(&mut self, ids: &[Uuid]) -> Result<(), RegistryError>;
 
     // Provided implementations
 
@@ -230,6 +390,302 @@ where
             .collect())
     }
 
+    /**
+     * Returns the project and all its children in the order they can be deleted, i.e. every entity
+     * comes after all entities depending on it, and the project is the last one.
+     * Fails with `DeleteInUsed` if an entity is used by anything outside of the project
+     */
+    fn get_project_deletion_order(
+        &self,
+        id: Uuid,
+    ) -> Result<Vec<Entity<EntityProp>>, RegistryError> {
+        let project = self.get_entity(id)?;
+        if project.entity_type != EntityType::Project {
+            return Err(RegistryError::WrongEntityType(id, project.entity_type));
+        }
+        let mut children: HashMap<Uuid, Entity<EntityProp>> = self
+            .get_neighbors(id, EdgeType::Contains)?
+            .into_iter()
+            .map(|e| (e.id, e))
+            .collect();
+        // Number of entities depending on each child, and the children each child depends on
+        let mut dependents: HashMap<Uuid, usize> = HashMap::new();
+        let mut upstreams: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for &child in children.keys() {
+            let mut downstreams = HashSet::new();
+            for edge_type in [EdgeType::Contains, EdgeType::Produces, EdgeType::ConsumedBy] {
+                for e in self.get_neighbors(child, edge_type)? {
+                    if !children.contains_key(&e.id) {
+                        return Err(RegistryError::DeleteInUsed(child));
+                    }
+                    downstreams.insert(e.id);
+                }
+            }
+            dependents.insert(child, downstreams.len());
+            for downstream in downstreams {
+                upstreams.entry(downstream).or_default().push(child);
+            }
+        }
+        let mut ready: Vec<Uuid> = dependents
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut ordered = vec![];
+        while let Some(child) = ready.pop() {
+            for upstream in upstreams.remove(&child).unwrap_or_default() {
+                if let Some(count) = dependents.get_mut(&upstream) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(upstream);
+                    }
+                }
+            }
+            if let Some(e) = children.remove(&child) {
+                ordered.push(e);
+            }
+        }
+        // Entities left are in a dependency cycle and cannot be deleted one by one
+        if let Some(&child) = children.keys().next() {
+            return Err(RegistryError::DeleteInUsed(child));
+        }
+        ordered.push(project);
+        Ok(ordered)
+    }
+
+    /**
+     * Delete the project along with all its children if `cascade` is true, otherwise the project
+     * must be empty. Returns the entities deleted in order, nothing is deleted if `dry_run` is true
+     */
+    async fn delete_project(
+        &mut self,
+        id: Uuid,
+        cascade: bool,
+        dry_run: bool,
+        deleted_at: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Entity<EntityProp>>, RegistryError> {
+        // Everything is checked before the first deletion so a failed call changes nothing
+        let entities = self.get_project_deletion_order(id)?;
+        if !cascade && entities.len() > 1 {
+            return Err(RegistryError::DeleteInUsed(id));
+        }
+        if !dry_run {
+            for e in &entities {
+                self.delete_entity(e.id, deleted_at).await?;
+            }
+        }
+        Ok(entities)
+    }
+
+    /**
+     * Label the entity version with the environment, the label is moved from other versions of the
+     * entity so at most one version is in each environment
+     */
+    async fn promote_entity(&mut self, id: Uuid, environment: &str) -> Result<(), RegistryError> {
+        validate_label("environment", environment)?;
+        let entity = self.get_entity(id)?;
+        for e in self.get_all_versions(&entity.qualified_name) {
+            let mut labels = e.properties.get_labels();
+            let labeled = labels.iter().any(|l| l == environment);
+            if e.id == id && !labeled {
+                labels.push(environment.to_string());
+            } else if e.id != id && labeled {
+                labels.retain(|l| l != environment);
+            } else {
+                continue;
+            }
+            self.set_entity_labels(e.id, labels).await?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Remove the environment label from the entity version
+     */
+    async fn demote_entity(&mut self, id: Uuid, environment: &str) -> Result<(), RegistryError> {
+        let mut labels = self.get_entity(id)?.properties.get_labels();
+        if labels.iter().any(|l| l == environment) {
+            labels.retain(|l| l != environment);
+            self.set_entity_labels(id, labels).await?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Entity versions a new release of the project would pin.
+     * One version of each feature is picked, the one labeled with the environment, or the latest
+     * one if no environment is specified, features never promoted to the environment are left out.
+     * Anchors and sources labeled with the environment are picked as well, then all upstream
+     * entities of the picked ones are added. Fails if two picked entities depend on different
+     * versions of the same entity
+     */
+    fn get_release_entities(
+        &self,
+        project_id: Uuid,
+        environment: Option<&str>,
+    ) -> Result<Vec<Entity<EntityProp>>, RegistryError> {
+        let project = self.get_entity(project_id)?;
+        if project.entity_type != EntityType::Project {
+            return Err(RegistryError::WrongEntityType(
+                project_id,
+                project.entity_type,
+            ));
+        }
+        let mut picked: HashMap<String, Entity<EntityProp>> = HashMap::new();
+        for e in self.get_neighbors(project_id, EdgeType::Contains)? {
+            let is_feature = matches!(
+                e.entity_type,
+                EntityType::AnchorFeature | EntityType::DerivedFeature
+            );
+            let pick = match environment {
+                Some(env) => {
+                    (is_feature || matches!(e.entity_type, EntityType::Source | EntityType::Anchor))
+                        && e.properties.get_labels().iter().any(|l| l == env)
+                }
+                None => {
+                    is_feature
+                        && picked
+                            .get(&e.qualified_name)
+                            .map_or(true, |p| p.version < e.version)
+                }
+            };
+            if pick {
+                picked.insert(e.qualified_name.clone(), e);
+            }
+        }
+        // Add upstream entities, i.e. the consumed sources and features, and the anchors of the
+        // anchor features
+        let mut pending: Vec<Entity<EntityProp>> = picked.values().cloned().collect();
+        while let Some(e) = pending.pop() {
+            let mut upstream = self.get_neighbors(e.id, EdgeType::Consumes)?;
+            if e.entity_type == EntityType::AnchorFeature {
+                upstream.extend(
+                    self.get_neighbors(e.id, EdgeType::BelongsTo)?
+                        .into_iter()
+                        .filter(|u| u.entity_type == EntityType::Anchor),
+                );
+            }
+            for u in upstream {
+                match picked.get(&u.qualified_name) {
+                    Some(p) if p.id == u.id => {}
+                    Some(p) => {
+                        return Err(RegistryError::InvalidDefinition(format!(
+                            "`{}` depends on version {} of `{}`, but version {} is picked",
+                            e.qualified_name, u.version, u.qualified_name, p.version
+                        )))
+                    }
+                    None => {
+                        picked.insert(u.qualified_name.clone(), u.clone());
+                        pending.push(u);
+                    }
+                }
+            }
+        }
+        let mut entities: Vec<Entity<EntityProp>> = picked.into_values().collect();
+        entities.sort_by(|a, b| a.qualified_name.cmp(&b.qualified_name));
+        Ok(entities)
+    }
+
+    /**
+     * Pin the entity versions picked by `get_release_entities` as a new release of the project,
+     * release names are unique in the project
+     */
+    async fn create_release(
+        &mut self,
+        project_id: Uuid,
+        definition: &ReleaseDef,
+    ) -> Result<Release, RegistryError> {
+        validate_label("release name", &definition.name)?;
+        if let Some(env) = &definition.environment {
+            validate_label("environment", env)?;
+        }
+        let mut releases = self.get_releases(project_id)?;
+        if releases.iter().any(|r| r.name == definition.name) {
+            return Err(RegistryError::EntityNameExists(format!(
+                "{}@{}",
+                self.get_entity_qualified_name(project_id)?,
+                definition.name
+            )));
+        }
+        let entities = self.get_release_entities(project_id, definition.environment.as_deref())?;
+        let release = Release {
+            name: definition.name.clone(),
+            environment: definition.environment.clone(),
+            entities: entities.into_iter().map(|e| e.id).collect(),
+            created_by: definition.created_by.clone(),
+            created_on: Utc::now(),
+        };
+        releases.push(release.clone());
+        self.set_project_releases(project_id, releases).await?;
+        Ok(release)
+    }
+
+    /**
+     * All releases of the project, in the order of creation
+     */
+    fn get_releases(&self, project_id: Uuid) -> Result<Vec<Release>, RegistryError> {
+        let project = self.get_entity(project_id)?;
+        if project.entity_type != EntityType::Project {
+            return Err(RegistryError::WrongEntityType(
+                project_id,
+                project.entity_type,
+            ));
+        }
+        Ok(project.properties.get_releases())
+    }
+
+    fn get_release(&self, project_id: Uuid, name: &str) -> Result<Release, RegistryError> {
+        self.get_releases(project_id)?
+            .into_iter()
+            .find(|r| r.name == name)
+            .ok_or_else(|| {
+                RegistryError::EntityNotFound(format!(
+                    "{}@{}",
+                    self.get_entity_qualified_name(project_id)
+                        .unwrap_or_default(),
+                    name
+                ))
+            })
+    }
+
+    async fn delete_release(&mut self, project_id: Uuid, name: &str) -> Result<(), RegistryError> {
+        self.get_release(project_id, name)?;
+        let mut releases = self.get_releases(project_id)?;
+        releases.retain(|r| r.name != name);
+        self.set_project_releases(project_id, releases).await
+    }
+
+    /**
+     * The project and the entity versions pinned by the release, with the edges between them.
+     * Fails if any pinned version has been deleted
+     */
+    fn get_release_lineage(
+        &self,
+        project_id: Uuid,
+        name: &str,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError> {
+        let release = self.get_release(project_id, name)?;
+        let mut ids: HashSet<Uuid> = release.entities.iter().copied().collect();
+        ids.insert(project_id);
+        let (entities, edges) = self.get_project(&project_id.to_string())?;
+        let entities: Vec<Entity<EntityProp>> = entities
+            .into_iter()
+            .filter(|e| ids.contains(&e.id))
+            .collect();
+        if let Some(missing) = release
+            .entities
+            .iter()
+            .find(|id| !entities.iter().any(|e| e.id == **id))
+        {
+            return Err(RegistryError::InvalidEntity(*missing));
+        }
+        let edges = edges
+            .into_iter()
+            .filter(|e| ids.contains(&e.from) && ids.contains(&e.to))
+            .collect();
+        Ok((entities, edges))
+    }
+
     fn get_entity_project_id(&self, id: Uuid) -> Result<Uuid, RegistryError> {
         if let Ok(e) = self.get_entity(id) {
             if e.entity_type == EntityType::Project {
@@ -251,8 +707,21 @@ where
         id: Uuid,
         size_limit: Option<usize>,
     ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError> {
-        let (upstream, upstream_edges) = self.bfs(id, EdgeType::Consumes, size_limit)?;
-        let (downstream, downstream_edges) = self.bfs(id, EdgeType::Produces, size_limit)?;
+        // Features used by a dataset and their upstream
+        let (mut upstream, mut upstream_edges) = self.bfs(id, EdgeType::DependsOn, size_limit)?;
+        for e in upstream.clone() {
+            let (entities, edges) = self.bfs(e.id, EdgeType::Consumes, size_limit)?;
+            upstream.extend(entities);
+            upstream_edges.extend(edges);
+        }
+        // Downstream features and the datasets consuming them
+        let (mut downstream, mut downstream_edges) =
+            self.bfs(id, EdgeType::Produces, size_limit)?;
+        for e in downstream.clone() {
+            let (entities, edges) = self.bfs(e.id, EdgeType::ConsumedBy, size_limit)?;
+            downstream.extend(entities);
+            downstream_edges.extend(edges);
+        }
         Ok((
             upstream
                 .into_iter()
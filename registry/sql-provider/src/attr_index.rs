@@ -0,0 +1,114 @@
+use std::collections::{HashMap, HashSet};
+
+use registry_provider::EntityType;
+use uuid::Uuid;
+
+/**
+ * Secondary indices of entity ids by the type and by the tag key, so filtered listings don't need to
+ * scan all node weights.
+ * Entities of all tenants are indexed, the tenant is checked on the result
+ */
+#[derive(Debug, Default)]
+pub(crate) struct AttributeIndex {
+    types: HashMap<EntityType, HashSet<Uuid>>,
+    tag_keys: HashMap<String, HashSet<Uuid>>,
+}
+
+impl AttributeIndex {
+    pub fn insert<I>(&mut self, id: Uuid, entity_type: EntityType, tag_keys: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.types.entry(entity_type).or_default().insert(id);
+        for key in tag_keys {
+            self.tag_keys.entry(key).or_default().insert(id);
+        }
+    }
+
+    pub fn remove<I>(&mut self, id: Uuid, entity_type: EntityType, tag_keys: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        if let Some(ids) = self.types.get_mut(&entity_type) {
+            ids.remove(&id);
+        }
+        for key in tag_keys {
+            if let Some(ids) = self.tag_keys.get_mut(&key) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.tag_keys.remove(&key);
+                }
+            }
+        }
+    }
+
+    /**
+     * Ids with any of the types and all of the tag keys, `None` if there is no filter at all
+     */
+    pub fn lookup(&self, types: &HashSet<EntityType>, tag_keys: &[&str]) -> Option<HashSet<Uuid>> {
+        let mut keyed = Vec::with_capacity(tag_keys.len());
+        for key in tag_keys {
+            match self.tag_keys.get(*key) {
+                Some(ids) => keyed.push(ids),
+                None => return Some(Default::default()),
+            }
+        }
+        // Start from the smallest set, the result can only be smaller
+        keyed.sort_by_key(|ids| ids.len());
+        let mut ret: HashSet<Uuid> = if types.is_empty() {
+            match keyed.first() {
+                Some(ids) => (*ids).clone(),
+                None => return None,
+            }
+        } else {
+            types
+                .iter()
+                .filter_map(|t| self.types.get(t))
+                .flatten()
+                .copied()
+                .collect()
+        };
+        for ids in keyed {
+            ret.retain(|id| ids.contains(id));
+        }
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let mut index = AttributeIndex::default();
+        let (f1, f2, s1) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let tags = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        index.insert(f1, EntityType::AnchorFeature, tags(&["team", "pii"]));
+        index.insert(f2, EntityType::DerivedFeature, tags(&["team"]));
+        index.insert(s1, EntityType::Source, tags(&["pii"]));
+
+        assert!(index.lookup(&Default::default(), &[]).is_none());
+        let features = HashSet::from([EntityType::AnchorFeature, EntityType::DerivedFeature]);
+        assert_eq!(
+            index.lookup(&features, &[]).unwrap(),
+            HashSet::from([f1, f2])
+        );
+        assert_eq!(
+            index.lookup(&features, &["pii"]).unwrap(),
+            HashSet::from([f1])
+        );
+        assert_eq!(
+            index.lookup(&Default::default(), &["pii"]).unwrap(),
+            HashSet::from([f1, s1])
+        );
+        assert!(index.lookup(&features, &["unknown"]).unwrap().is_empty());
+
+        index.remove(f1, EntityType::AnchorFeature, tags(&["team", "pii"]));
+        assert_eq!(index.lookup(&features, &["pii"]).unwrap(), HashSet::new());
+        assert_eq!(
+            index.lookup(&Default::default(), &["team"]).unwrap(),
+            HashSet::from([f2])
+        );
+    }
+}
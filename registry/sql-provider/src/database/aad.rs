@@ -0,0 +1,323 @@
+use std::{collections::HashMap, io};
+
+use async_trait::async_trait;
+use bb8::ManageConnection;
+use bb8_tiberius::ConnectionManager;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use tiberius::{AuthMethod, Config};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+const SQL_RESOURCE: &str = "https://database.windows.net/";
+const IMDS_ENDPOINT: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+const DEFAULT_AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+
+/**
+ * Tokens are refreshed this long before they expire, so a connection never logs in with a token
+ * that expires during the handshake
+ */
+const REFRESH_MARGIN_MINUTES: i64 = 5;
+
+/**
+ * How the registry authenticates to SQL Server, taken from the `Authentication` keyword of the ADO
+ * connection string, e.g.
+ * `Server=tcp:myserver.database.windows.net,1433;Database=registry;Authentication=Active Directory Managed Identity;Encrypt=true`
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum MsSqlAuth {
+    /// User and password in the connection string
+    SqlPassword,
+    /// System assigned identity, or the user assigned one with the client id in `User Id`
+    ManagedIdentity { client_id: Option<String> },
+    /// Client id and secret in `User Id` and `Password`, or in `AZURE_CLIENT_ID` and
+    /// `AZURE_CLIENT_SECRET`, the tenant is in `AZURE_TENANT_ID`
+    ServicePrincipal {
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+}
+
+impl MsSqlAuth {
+    pub(crate) fn from_ado_string(conn_str: &str) -> anyhow::Result<Self> {
+        let props = ado_properties(conn_str);
+        let get = |key: &str| props.get(key).filter(|v| !v.is_empty()).cloned();
+        let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+        let method = get("authentication")
+            .unwrap_or_default()
+            .replace(' ', "")
+            .to_lowercase();
+        Ok(match method.as_str() {
+            "" | "sqlpassword" => Self::SqlPassword,
+            "activedirectorymsi" | "activedirectorymanagedidentity" => Self::ManagedIdentity {
+                client_id: get("user id").or_else(|| env("AZURE_CLIENT_ID")),
+            },
+            "activedirectoryserviceprincipal" => Self::ServicePrincipal {
+                tenant_id: env("AZURE_TENANT_ID").ok_or_else(|| {
+                    anyhow::Error::msg("`AZURE_TENANT_ID` is required by service principal auth")
+                })?,
+                client_id: get("user id")
+                    .or_else(|| env("AZURE_CLIENT_ID"))
+                    .ok_or_else(|| {
+                        anyhow::Error::msg("Client id of the service principal is not set")
+                    })?,
+                client_secret: get("password")
+                    .or_else(|| env("AZURE_CLIENT_SECRET"))
+                    .ok_or_else(|| {
+                        anyhow::Error::msg("Client secret of the service principal is not set")
+                    })?,
+            },
+            _ => anyhow::bail!("Unsupported authentication method `{}`", method),
+        })
+    }
+}
+
+/**
+ * Keys are lowercased, quoted values are unquoted
+ */
+fn ado_properties(conn_str: &str) -> HashMap<String, String> {
+    conn_str
+        .split(';')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| {
+            let v = v.trim();
+            let v = v
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| v.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(v);
+            (k.trim().to_lowercase(), v.to_string())
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct AccessToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl AccessToken {
+    fn is_fresh(&self) -> bool {
+        self.expires_at - Duration::minutes(REFRESH_MARGIN_MINUTES) > Utc::now()
+    }
+}
+
+/**
+ * Managed identity endpoints return `expires_on` in epoch seconds, as a number or a string,
+ * AAD returns `expires_in` in seconds
+ */
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_on: Option<Value>,
+    #[serde(default)]
+    expires_in: Option<Value>,
+}
+
+fn as_seconds(v: &Option<Value>) -> Option<i64> {
+    match v {
+        Some(Value::Number(n)) => n.as_i64(),
+        Some(Value::String(s)) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+impl TokenResponse {
+    fn into_token(self) -> AccessToken {
+        let expires_at = as_seconds(&self.expires_on)
+            .and_then(|t| Utc.timestamp_opt(t, 0).single())
+            .or_else(|| as_seconds(&self.expires_in).map(|s| Utc::now() + Duration::seconds(s)))
+            // Shortest lifetime of AAD tokens
+            .unwrap_or_else(|| Utc::now() + Duration::hours(1));
+        AccessToken {
+            token: self.access_token,
+            expires_at,
+        }
+    }
+}
+
+/**
+ * Connection manager of the MSSQL pool.
+ * With Azure AD auth every new connection logs in with a cached token, which is refreshed before it
+ * expires, so a long-lived registry process keeps being able to open connections. Connections
+ * already opened are not affected by the expiration of the token they were opened with.
+ */
+pub(crate) struct MsSqlConnectionManager {
+    config: Config,
+    auth: MsSqlAuth,
+    token: Mutex<Option<AccessToken>>,
+    client: reqwest::Client,
+}
+
+impl MsSqlConnectionManager {
+    pub(crate) fn build(conn_str: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            config: Config::from_ado_string(conn_str)?,
+            auth: MsSqlAuth::from_ado_string(conn_str)?,
+            token: Default::default(),
+            client: Default::default(),
+        })
+    }
+
+    async fn access_token(&self) -> anyhow::Result<Option<String>> {
+        if self.auth == MsSqlAuth::SqlPassword {
+            return Ok(None);
+        }
+        let mut cached = self.token.lock().await;
+        match cached.as_ref() {
+            Some(t) if t.is_fresh() => {}
+            _ => {
+                let token = self.request_token().await?;
+                debug!("Azure AD token acquired, expires at {}", token.expires_at);
+                *cached = Some(token);
+            }
+        }
+        Ok(cached.as_ref().map(|t| t.token.clone()))
+    }
+
+    async fn request_token(&self) -> anyhow::Result<AccessToken> {
+        let req = match &self.auth {
+            MsSqlAuth::SqlPassword => unreachable!("SQL password auth doesn't use tokens"),
+            MsSqlAuth::ManagedIdentity { client_id } => {
+                // App Service and Container Apps expose their own endpoint, VMs and AKS use IMDS
+                let req = match (
+                    std::env::var("IDENTITY_ENDPOINT"),
+                    std::env::var("IDENTITY_HEADER"),
+                ) {
+                    (Ok(endpoint), Ok(header)) => self
+                        .client
+                        .get(endpoint)
+                        .header("X-IDENTITY-HEADER", header)
+                        .query(&[("api-version", "2019-08-01"), ("resource", SQL_RESOURCE)]),
+                    _ => self
+                        .client
+                        .get(IMDS_ENDPOINT)
+                        .header("Metadata", "true")
+                        .query(&[("api-version", "2018-02-01"), ("resource", SQL_RESOURCE)]),
+                };
+                match client_id {
+                    Some(id) => req.query(&[("client_id", id)]),
+                    None => req,
+                }
+            }
+            MsSqlAuth::ServicePrincipal {
+                tenant_id,
+                client_id,
+                client_secret,
+            } => {
+                let authority = std::env::var("AZURE_AUTHORITY_HOST")
+                    .unwrap_or_else(|_| DEFAULT_AUTHORITY_HOST.to_string());
+                let scope = format!("{}.default", SQL_RESOURCE);
+                self.client
+                    .post(format!(
+                        "{}/{}/oauth2/v2.0/token",
+                        authority.trim_end_matches('/'),
+                        tenant_id
+                    ))
+                    .form(&[
+                        ("grant_type", "client_credentials"),
+                        ("client_id", client_id),
+                        ("client_secret", client_secret),
+                        ("scope", &scope),
+                    ])
+            }
+        };
+        Ok(req
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?
+            .into_token())
+    }
+}
+
+#[async_trait]
+impl ManageConnection for MsSqlConnectionManager {
+    type Connection = <ConnectionManager as ManageConnection>::Connection;
+    type Error = bb8_tiberius::Error;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let mut config = self.config.clone();
+        if let Some(token) = self.access_token().await.map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("Failed to get Azure AD token, {}", e),
+            )
+        })? {
+            config.authentication(AuthMethod::aad_token(token));
+        }
+        ConnectionManager::new(config).connect().await
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        conn.simple_query("SELECT 1").await?.into_row().await?;
+        Ok(())
+    }
+
+    fn has_broken(&self, _: &mut Self::Connection) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_auth_method() {
+        assert_eq!(
+            MsSqlAuth::from_ado_string("Server=tcp:localhost,1433;User Id=sa;Password=pass")
+                .unwrap(),
+            MsSqlAuth::SqlPassword
+        );
+        assert_eq!(
+            MsSqlAuth::from_ado_string(
+                "Server=tcp:x.database.windows.net;Authentication=Active Directory Managed Identity"
+            )
+            .unwrap(),
+            MsSqlAuth::ManagedIdentity { client_id: None }
+        );
+        assert_eq!(
+            MsSqlAuth::from_ado_string(
+                "Server=tcp:x.database.windows.net;authentication=\"ActiveDirectoryMsi\";user id=abc"
+            )
+            .unwrap(),
+            MsSqlAuth::ManagedIdentity {
+                client_id: Some("abc".to_string())
+            }
+        );
+        assert!(
+            MsSqlAuth::from_ado_string("Server=x;Authentication=ActiveDirectoryDeviceCode")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_token_expiration() {
+        let now = Utc::now().timestamp();
+        let token = serde_json::from_value::<TokenResponse>(json!({
+            "access_token": "t1",
+            "expires_on": (now + 3600).to_string(),
+        }))
+        .unwrap()
+        .into_token();
+        assert_eq!(token.expires_at.timestamp(), now + 3600);
+        assert!(token.is_fresh());
+
+        let token = serde_json::from_value::<TokenResponse>(json!({
+            "access_token": "t2",
+            "expires_in": 120,
+        }))
+        .unwrap()
+        .into_token();
+        // Expires within the refresh margin
+        assert!(!token.is_fresh());
+    }
+}
@@ -1,7 +1,10 @@
 use registry_provider::{EntityProperty, Entity, Edge, RbacRecord};
+use serde_json::{Map, Value};
 
 use crate::Registry;
 
+#[cfg(feature = "mssql")]
+mod aad;
 #[cfg(feature = "mssql")]
 mod mssql;
 
@@ -45,3 +48,59 @@ pub async fn load_content(
     }
     anyhow::bail!("Unable to load registry")
 }
+
+/**
+ * Replace the content of the entities, edges and permissions tables with the given data.
+ *
+ * Revoked permissions are kept in the table as history, only active grants are replaced.
+ */
+pub async fn dump_content(
+    entities: &[Entity<EntityProperty>],
+    edges: &[Edge],
+    permissions: &[RbacRecord],
+) -> Result<(), anyhow::Error> {
+    #[cfg(feature = "mssql")]
+    if mssql::validate_condition() {
+        return mssql::dump_content(entities, edges, permissions).await;
+    }
+
+    #[cfg(feature = "ossdbms")]
+    if sqlx::validate_condition() {
+        return sqlx::dump_content(entities, edges, permissions).await;
+    }
+    anyhow::bail!("Unable to dump registry")
+}
+
+/**
+ * Read at most `rows` rows from a JDBC source, either the `dbtable` or the result of `query`.
+ *
+ * SQL Server, PostgreSQL and MySQL URLs are supported, `user` and `password` override the credential in the URL.
+ */
+pub async fn sample_jdbc_source(
+    url: &str,
+    dbtable: Option<&str>,
+    query: Option<&str>,
+    user: Option<&str>,
+    password: Option<&str>,
+    rows: usize,
+) -> Result<Vec<Map<String, Value>>, anyhow::Error> {
+    let from = match (dbtable, query) {
+        (_, Some(query)) => format!("({}) AS sample_source", query.trim().trim_end_matches(';')),
+        (Some(dbtable), None) => dbtable.to_string(),
+        (None, None) => anyhow::bail!("Either `dbtable` or `query` must be set"),
+    };
+
+    #[cfg(feature = "mssql")]
+    if url.starts_with("jdbc:sqlserver:") {
+        return mssql::sample_source(url, &from, user, password, rows).await;
+    }
+
+    #[cfg(feature = "ossdbms")]
+    if url.starts_with("jdbc:postgresql:") || url.starts_with("jdbc:mysql:") {
+        return sqlx::sample_source(url, &from, user, password, rows).await;
+    }
+
+    // Silence unused warnings when no database feature is enabled
+    let _ = (from, user, password, rows);
+    anyhow::bail!("Unsupported JDBC URL '{}'", url)
+}
@@ -1,11 +1,12 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use bb8::{Pool, PooledConnection};
+use bb8::{ManageConnection, Pool, PooledConnection};
 use bb8_tiberius::ConnectionManager;
 use chrono::{DateTime, Utc};
 use common_utils::{Appliable, Logged};
-use tiberius::{FromSql, Row};
+use serde_json::{json, Map, Value};
+use tiberius::{ColumnData, FromSql, Row};
 use tiberius_derive::FromRow;
 use tokio::sync::{OnceCell, RwLock};
 use tracing::{debug, warn};
@@ -17,7 +18,7 @@ use registry_provider::{
 };
 
 use crate::{
-    database::{get_entity_table, get_rbac_table},
+    database::{aad::MsSqlConnectionManager, get_entity_table, get_rbac_table},
     db_registry::ExternalStorage,
     Registry,
 };
@@ -72,7 +73,7 @@ struct RbacEntry {
 }
 
 async fn load_entities(
-    conn: &mut PooledConnection<'static, ConnectionManager>,
+    conn: &mut PooledConnection<'static, MsSqlConnectionManager>,
 ) -> Result<Vec<EntityProperty>, anyhow::Error> {
     let entities_table = get_entity_table();
     debug!("Loading entities from {}", entities_table);
@@ -89,7 +90,7 @@ async fn load_entities(
 }
 
 async fn load_edges(
-    conn: &mut PooledConnection<'static, ConnectionManager>,
+    conn: &mut PooledConnection<'static, MsSqlConnectionManager>,
 ) -> Result<Vec<Edge>, anyhow::Error> {
     let edges_table = std::env::var("EDGE_TABLE").unwrap_or_else(|_| "edges".to_string());
     debug!("Loading edges from {}", edges_table);
@@ -109,7 +110,7 @@ async fn load_edges(
 }
 
 async fn load_permissions(
-    conn: &mut PooledConnection<'static, ConnectionManager>,
+    conn: &mut PooledConnection<'static, MsSqlConnectionManager>,
 ) -> Result<Vec<RbacRecord>, anyhow::Error> {
     let permissions_table = get_rbac_table();
     {
@@ -173,18 +174,18 @@ async fn load_permissions(
         .collect()
 }
 
-static POOL: OnceCell<Option<Arc<RwLock<Pool<ConnectionManager>>>>> = OnceCell::const_new();
+static POOL: OnceCell<Option<Arc<RwLock<Pool<MsSqlConnectionManager>>>>> = OnceCell::const_new();
 
-async fn init_pool() -> anyhow::Result<Arc<RwLock<Pool<ConnectionManager>>>> {
+async fn init_pool() -> anyhow::Result<Arc<RwLock<Pool<MsSqlConnectionManager>>>> {
     debug!("Initializing MSSQL connection pool");
     let conn_str = std::env::var("CONNECTION_STR")?;
-    let mgr = bb8_tiberius::ConnectionManager::build(conn_str.as_str())?;
+    let mgr = MsSqlConnectionManager::build(conn_str.as_str())?;
     let pool = bb8::Pool::builder().max_size(5).build(mgr).await?;
     debug!("MSSQL connection pool initialized");
     Ok(Arc::new(RwLock::new(pool)))
 }
 
-async fn connect() -> Result<PooledConnection<'static, ConnectionManager>, anyhow::Error> {
+async fn connect() -> Result<PooledConnection<'static, MsSqlConnectionManager>, anyhow::Error> {
     debug!("Acquiring MSSQL connection pool");
     let pool = POOL
         .get_or_init(|| async { init_pool().await.ok() })
@@ -197,6 +198,62 @@ async fn connect() -> Result<PooledConnection<'static, ConnectionManager>, anyho
     Ok(conn)
 }
 
+fn column_to_json(data: &ColumnData<'static>) -> Value {
+    match data {
+        ColumnData::U8(v) => json!(v),
+        ColumnData::I16(v) => json!(v),
+        ColumnData::I32(v) => json!(v),
+        ColumnData::I64(v) => json!(v),
+        ColumnData::F32(v) => json!(v),
+        ColumnData::F64(v) => json!(v),
+        ColumnData::Bit(v) => json!(v),
+        ColumnData::String(v) => json!(v),
+        ColumnData::Guid(v) => json!(v),
+        ColumnData::Numeric(v) => json!(v.map(f64::from)),
+        ColumnData::Date(_) => json!(chrono::NaiveDate::from_sql(data).ok().flatten()),
+        ColumnData::Time(_) => json!(chrono::NaiveTime::from_sql(data).ok().flatten()),
+        ColumnData::DateTimeOffset(_) => {
+            json!(DateTime::<chrono::FixedOffset>::from_sql(data)
+                .ok()
+                .flatten())
+        }
+        ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => {
+            json!(chrono::NaiveDateTime::from_sql(data).ok().flatten())
+        }
+        // Binary and XML columns are not previewed
+        _ => Value::Null,
+    }
+}
+
+pub async fn sample_source(
+    url: &str,
+    from: &str,
+    user: Option<&str>,
+    password: Option<&str>,
+    rows: usize,
+) -> Result<Vec<Map<String, Value>>, anyhow::Error> {
+    let mut config = tiberius::Config::from_jdbc_string(url)?;
+    if let (Some(user), Some(password)) = (user, password) {
+        config.authentication(tiberius::AuthMethod::sql_server(user, password));
+    }
+    // One-off connection, sources are not pooled
+    let mut conn = ConnectionManager::new(config).connect().await?;
+    let sql = format!("SELECT TOP ({}) * FROM {}", rows, from);
+    debug!("Sampling source with `{}`", sql);
+    Ok(conn
+        .simple_query(sql)
+        .await?
+        .into_first_result()
+        .await?
+        .into_iter()
+        .map(|row| {
+            row.cells()
+                .map(|(c, data)| (c.name().to_string(), column_to_json(data)))
+                .collect()
+        })
+        .collect())
+}
+
 pub fn validate_condition() -> bool {
     if let Ok(conn_str) = std::env::var("CONNECTION_STR") {
         tiberius::Config::from_ado_string(&conn_str).is_ok()
@@ -224,6 +281,108 @@ pub async fn load_content(
     ))
 }
 
+pub async fn dump_content(
+    entities: &[Entity<EntityProperty>],
+    edges: &[Edge],
+    permissions: &[RbacRecord],
+) -> Result<(), anyhow::Error> {
+    debug!("Dumping registry data into database");
+    let mut conn = connect().await?;
+    let with_rbac = conn
+        .simple_query(format!("select 1 from {}", get_rbac_table()))
+        .await
+        .is_ok();
+    if !with_rbac {
+        warn!("Permissions table not found, permissions are not dumped");
+    }
+
+    conn.execute("BEGIN TRANSACTION", &[]).await?;
+    let ret = async {
+        conn.execute(format!("DELETE FROM {}", get_entity_table()), &[])
+            .await?;
+        let sql = format!(
+            "INSERT INTO {} (entity_id, entity_content) values (@P1, @P2)",
+            get_entity_table()
+        );
+        for e in entities {
+            conn.execute(
+                sql.as_str(),
+                &[
+                    &e.id.to_string(),
+                    &serde_json::to_string_pretty(&e.properties)?,
+                ],
+            )
+            .await?;
+        }
+
+        conn.execute(format!("DELETE FROM {}", get_edge_table()), &[])
+            .await?;
+        let sql = format!(
+            "INSERT INTO {} (from_id, to_id, edge_type) values (@P1, @P2, @P3)",
+            get_edge_table()
+        );
+        for e in edges {
+            conn.execute(
+                sql.as_str(),
+                &[
+                    &e.from.to_string(),
+                    &e.to.to_string(),
+                    &format!("{:?}", e.edge_type),
+                ],
+            )
+            .await?;
+        }
+
+        if with_rbac {
+            conn.execute(
+                format!("DELETE FROM {} WHERE delete_by is null", get_rbac_table()),
+                &[],
+            )
+            .await?;
+            let sql = format!(
+                "INSERT INTO {}
+                (user_name, role_name, project_name, create_by, create_reason, create_time)
+                values
+                (@P1, @P2, @P3, @P4, @P5, @P6)",
+                get_rbac_table()
+            );
+            for p in permissions {
+                conn.execute(
+                    sql.as_str(),
+                    &[
+                        &p.credential.to_string(),
+                        &p.permission.to_string(),
+                        &p.resource.to_string(),
+                        &p.requestor.to_string(),
+                        &p.reason,
+                        &p.time.naive_utc(),
+                    ],
+                )
+                .await?;
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    match ret {
+        Ok(_) => {
+            conn.execute("COMMIT TRANSACTION", &[]).await?;
+            debug!(
+                "{} entities, {} edges and {} permissions dumped",
+                entities.len(),
+                edges.len(),
+                permissions.len()
+            );
+            Ok(())
+        }
+        Err(e) => {
+            conn.execute("ROLLBACK TRANSACTION", &[]).await.log().ok();
+            Err(e)
+        }
+    }
+}
+
 pub fn attach_storage(registry: &mut Registry<EntityProperty>) {
     registry
         .external_storage
@@ -288,6 +447,33 @@ impl ExternalStorage<EntityProperty> for MsSqlStorage {
         Ok(())
     }
 
+    async fn update_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProperty>,
+    ) -> Result<(), RegistryError> {
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        conn.execute(
+            format!(
+                "UPDATE {} SET entity_content = @P2 WHERE entity_id = @P1",
+                self.entity_table
+            )
+            .apply(|s| {
+                debug!("SQL is: {}", s);
+                s
+            }),
+            &[
+                &id.to_string(),
+                &serde_json::to_string_pretty(&entity.properties).unwrap(),
+            ],
+        )
+        .await
+        .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
+
     async fn delete_entity(
         &mut self,
         id: Uuid,
@@ -308,6 +494,26 @@ impl ExternalStorage<EntityProperty> for MsSqlStorage {
         Ok(())
     }
 
+    async fn purge_entity(&mut self, id: Uuid) -> Result<(), RegistryError> {
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        conn.execute(
+            format!(
+                "DELETE {} WHERE from_id = @P1 or to_id = @P1",
+                self.edge_table
+            )
+            .apply(|s| {
+                debug!("SQL is: {}", s);
+                s
+            }),
+            &[&id.to_string()],
+        )
+        .await
+        .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
+
     async fn connect(
         &mut self,
         from_id: Uuid,
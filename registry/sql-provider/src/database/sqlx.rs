@@ -4,9 +4,11 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use log::debug;
 use serde::Deserialize;
+use serde_json::{json, Map, Value};
 use sqlx::{
-    any::AnyKind, pool::PoolConnection, Any, AnyConnection, AnyPool, ConnectOptions, Connection,
-    Executor, FromRow,
+    any::{AnyKind, AnyRow},
+    pool::PoolConnection,
+    Any, AnyConnection, AnyPool, Column, ConnectOptions, Connection, Executor, FromRow, Row,
 };
 
 use crate::{
@@ -248,6 +250,161 @@ pub async fn load_content(
     ))
 }
 
+pub async fn dump_content(
+    entities: &[Entity<EntityProperty>],
+    edges: &[Edge],
+    permissions: &[RbacRecord],
+) -> Result<(), anyhow::Error> {
+    let pool = POOL
+        .get_or_init(|| async { init_pool().await.ok() })
+        .await
+        .clone()
+        .ok_or_else(|| anyhow::Error::msg("Environment variable 'CONNECTION_STR' is not set."))?;
+    let params = |n: usize| -> String {
+        (1..=n)
+            .map(|i| match pool.any_kind() {
+                AnyKind::Postgres => format!("${}", i),
+                _ => "?".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    debug!("Dumping registry data into database");
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(&format!("DELETE FROM {}", get_entity_table()))
+        .execute(&mut tx)
+        .await?;
+    let sql = format!(
+        "INSERT INTO {} (entity_id, entity_content) values ({})",
+        get_entity_table(),
+        params(2)
+    );
+    for e in entities {
+        sqlx::query(&sql)
+            .bind(e.id.to_string())
+            .bind(serde_json::to_string_pretty(&e.properties)?)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    sqlx::query(&format!("DELETE FROM {}", get_edge_table()))
+        .execute(&mut tx)
+        .await?;
+    let sql = format!(
+        "INSERT INTO {} (from_id, to_id, edge_type) values ({})",
+        get_edge_table(),
+        params(3)
+    );
+    for e in edges {
+        sqlx::query(&sql)
+            .bind(e.from.to_string())
+            .bind(e.to.to_string())
+            .bind(format!("{:?}", e.edge_type))
+            .execute(&mut tx)
+            .await?;
+    }
+
+    sqlx::query(&format!(
+        "DELETE FROM {} WHERE delete_by is null",
+        get_rbac_table()
+    ))
+    .execute(&mut tx)
+    .await?;
+    let sql = format!(
+        "INSERT INTO {}
+        (user_name, role_name, project_name, create_by, create_reason, create_time)
+        values
+        ({})",
+        get_rbac_table(),
+        params(6)
+    );
+    for p in permissions {
+        sqlx::query(&sql)
+            .bind(p.credential.to_string())
+            .bind(p.permission.to_string())
+            .bind(p.resource.to_string())
+            .bind(p.requestor.to_string())
+            .bind(p.reason.clone())
+            .bind(p.time)
+            .execute(&mut tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    debug!(
+        "{} entities, {} edges and {} permissions dumped",
+        entities.len(),
+        edges.len(),
+        permissions.len()
+    );
+    Ok(())
+}
+
+fn column_to_json(row: &AnyRow, index: usize) -> Value {
+    // `AnyRow` doesn't expose the column type, try the types supported by all drivers one by one
+    macro_rules! try_decode {
+        ($($t:ty),*) => {
+            $(
+                if let Ok(v) = row.try_get::<Option<$t>, _>(index) {
+                    return json!(v);
+                }
+            )*
+        };
+    }
+    try_decode!(
+        i64,
+        i32,
+        i16,
+        f64,
+        f32,
+        bool,
+        String,
+        DateTime<Utc>,
+        chrono::NaiveDateTime,
+        chrono::NaiveDate,
+        chrono::NaiveTime
+    );
+    Value::Null
+}
+
+pub async fn sample_source(
+    url: &str,
+    from: &str,
+    user: Option<&str>,
+    password: Option<&str>,
+    rows: usize,
+) -> Result<Vec<Map<String, Value>>, anyhow::Error> {
+    // `jdbc:postgresql://host/db` -> `postgresql://host/db`
+    let mut url = reqwest::Url::parse(url.trim_start_matches("jdbc:"))?;
+    if let Some(user) = user {
+        url.set_username(user)
+            .map_err(|_| anyhow::Error::msg("Invalid user name"))?;
+    }
+    if let Some(password) = password {
+        url.set_password(Some(password))
+            .map_err(|_| anyhow::Error::msg("Invalid password"))?;
+    }
+    // One-off connection, sources are not pooled
+    let mut conn = AnyConnection::connect(url.as_str()).await?;
+    let sql = format!("SELECT * FROM {} LIMIT {}", from, rows);
+    debug!("Sampling source with `{}`", sql);
+    let ret = sqlx::query(&sql)
+        .fetch_all(&mut conn)
+        .await?
+        .iter()
+        .map(|row| {
+            row.columns()
+                .iter()
+                .map(|c| (c.name().to_string(), column_to_json(row, c.ordinal())))
+                .collect()
+        })
+        .collect();
+    conn.close().await.ok();
+    Ok(ret)
+}
+
 pub fn validate_condition() -> bool {
     if let Ok(conn_str) = std::env::var("CONNECTION_STR") {
         conn_str
@@ -374,6 +531,37 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
         Ok(())
     }
 
+    /**
+     * Function will be called when properties of an existing entity are changed in place
+     * ExternalStorage may need to update the entity record in database, etc
+     */
+    async fn update_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProperty>,
+    ) -> Result<(), RegistryError> {
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        let sql = match conn.kind() {
+            sqlx::any::AnyKind::Postgres => format!(
+                r#"UPDATE {} SET entity_content = $1 WHERE entity_id = $2;"#,
+                self.entity_table,
+            ),
+            _ => format!(
+                r#"UPDATE {} SET entity_content = ? WHERE entity_id = ?;"#,
+                self.entity_table,
+            ),
+        };
+        let query = sqlx::query(&sql)
+            .bind(serde_json::to_string_pretty(&entity.properties).unwrap())
+            .bind(id.to_string());
+        conn.execute(query)
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
+
     /**
      * Function will be called when an entity is deleted in the graph
      * ExternalStorage may need to remove the entity record from database, etc
@@ -394,6 +582,25 @@ impl ExternalStorage<EntityProperty> for SqlxStorage {
         Ok(())
     }
 
+    /**
+     * Function will be called when the tombstone of a deleted entity is removed by the garbage
+     * collection, removes the edge records still referring to the entity
+     */
+    async fn purge_entity(&mut self, id: Uuid) -> Result<(), RegistryError> {
+        let sql = format!(
+            r#"DELETE {} WHERE from_id = ? or to_id = ?;"#,
+            self.edge_table,
+        );
+        let query = sqlx::query(&sql).bind(id.to_string()).bind(id.to_string());
+        let mut conn = connect()
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        conn.execute(query)
+            .await
+            .map_err(|e| RegistryError::ExternalStorageError(format!("{:?}", e)))?;
+        Ok(())
+    }
+
     /**
      * Function will be called when 2 entities are connected.
      * EntityProp has already been updated accordingly.
@@ -1,8 +1,9 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Debug;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use log::debug;
 use petgraph::{
@@ -12,14 +13,129 @@ use petgraph::{
 };
 use registry_provider::*;
 use serde::Deserialize;
-use tokio::sync::RwLock;
+use tokio::sync::{OnceCell, RwLock};
 use uuid::Uuid;
 
+use crate::attr_index::AttributeIndex;
+use crate::embedding::{embedding_doc, VectorIndex};
 use crate::fts::{FtsError, FtsIndex};
 use crate::rbac_map::RbacMap;
 
 const NODE_CAPACITY: usize = 1000;
 
+const DEFAULT_PROJECT_CACHE_SIZE: usize = 64;
+
+static PROJECT_CACHE_SIZE: OnceCell<usize> = OnceCell::const_new();
+
+/**
+ * Set the max number of cached project subgraphs, 0 disables the cache
+ */
+pub fn set_project_cache_size(size: usize) -> Result<(), RegistryError> {
+    debug!("Project cache size: {}", size);
+    PROJECT_CACHE_SIZE.set(size).map_err(|_| {
+        RegistryError::InvalidDefinition("Project cache size is already set".to_string())
+    })
+}
+
+type ProjectGraph<EntityProp> = (HashSet<Entity<EntityProp>>, HashSet<Edge>);
+
+#[derive(Debug)]
+struct ProjectGraphCacheInner<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    // Value and the tick of the last access
+    entries: HashMap<(Uuid, u64), (ProjectGraph<EntityProp>, u64)>,
+    tick: u64,
+}
+
+/**
+ * LRU cache of project subgraphs, keyed by project id and the applied log index of the last
+ * change to the project, so entries of changed projects are never hit again
+ */
+#[derive(Debug)]
+pub(crate) struct ProjectGraphCache<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    capacity: usize,
+    inner: Mutex<ProjectGraphCacheInner<EntityProp>>,
+}
+
+impl<EntityProp> Default for ProjectGraphCache<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    fn default() -> Self {
+        Self {
+            capacity: PROJECT_CACHE_SIZE
+                .get()
+                .copied()
+                .unwrap_or(DEFAULT_PROJECT_CACHE_SIZE),
+            inner: Mutex::new(ProjectGraphCacheInner {
+                entries: Default::default(),
+                tick: 0,
+            }),
+        }
+    }
+}
+
+impl<EntityProp> ProjectGraphCache<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + ToDocString,
+{
+    fn get(&self, key: &(Uuid, u64)) -> Option<ProjectGraph<EntityProp>> {
+        let mut inner = self.inner.lock().ok()?;
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.entries.get_mut(key).map(|(graph, used)| {
+            *used = tick;
+            graph.clone()
+        })
+    }
+
+    fn insert(&self, key: (Uuid, u64), graph: ProjectGraph<EntityProp>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Ok(mut inner) = self.inner.lock() {
+            if inner.entries.len() >= self.capacity && !inner.entries.contains_key(&key) {
+                if let Some(lru) = inner
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, used))| *used)
+                    .map(|(k, _)| *k)
+                {
+                    inner.entries.remove(&lru);
+                }
+            }
+            inner.tick += 1;
+            let tick = inner.tick;
+            inner.entries.insert(key, (graph, tick));
+        }
+    }
+
+    fn invalidate(&self, project_id: Uuid) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.entries.retain(|(id, _), _| *id != project_id);
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.entries.clear();
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .map(|i| i.entries.len())
+            .unwrap_or_default()
+    }
+}
+
 impl From<FtsError> for RegistryError {
     fn from(e: FtsError) -> Self {
         RegistryError::FtsError(e.to_string())
@@ -45,6 +161,16 @@ where
         entity: &Entity<EntityProp>,
     ) -> Result<(), RegistryError>;
 
+    /**
+     * Function will be called when properties of an existing entity are changed in place
+     * ExternalStorage may need to update the entity record in database, etc
+     */
+    async fn update_entity(
+        &mut self,
+        id: Uuid,
+        entity: &Entity<EntityProp>,
+    ) -> Result<(), RegistryError>;
+
     /**
      * Function will be called when an entity is deleted in the graph
      * ExternalStorage may need to remove the entity record from database, etc
@@ -55,6 +181,14 @@ where
         entity: &Entity<EntityProp>,
     ) -> Result<(), RegistryError>;
 
+    /**
+     * Function will be called when the tombstone of a deleted entity is removed by the garbage
+     * collection, ExternalStorage may need to remove the records left by the deletion, e.g. edges
+     */
+    async fn purge_entity(&mut self, _id: Uuid) -> Result<(), RegistryError> {
+        Ok(())
+    }
+
     /**
      * Function will be called when 2 entities are connected.
      * EntityProp has already been updated accordingly.
@@ -98,11 +232,21 @@ where
     // Secondary index for nodes, can be used as entry points for all entity GUIDs
     pub(crate) node_id_map: HashMap<Uuid, NodeIndex>,
 
-    // Secondary index for nodes, can be used as entry points for all entity GUIDs
-    pub(crate) name_id_map: HashMap<String, BTreeMap<u64, Uuid>>,
+    // Secondary index for nodes by tenant and qualified name, names are only unique within a tenant
+    pub(crate) name_id_map: HashMap<String, HashMap<String, BTreeMap<u64, Uuid>>>,
+
+    // Secondary indices for live nodes by type and tag key, used by filtered listings
+    pub(crate) attr_index: AttributeIndex,
+
+    // Tenant scope of the current request, entities of other tenants are invisible
+    pub(crate) tenant: String,
 
     pub(crate) deleted: HashSet<Uuid>,
 
+    // Deletion time of the deleted entities recorded in the Raft log, tombstones older than the
+    // retention are removed by the garbage collection
+    pub(crate) deleted_at: HashMap<Uuid, DateTime<Utc>>,
+
     // Besides arbitrary NodeIndex, entry points can be used to start a graph traversal
     // Typical entry points include Projects, Sources are possible candidates as well
     pub(crate) entry_points: Vec<NodeIndex>,
@@ -110,8 +254,20 @@ where
     // FTS support
     pub(crate) fts_index: FtsIndex,
 
+    // Cached embeddings for semantic search
+    pub(crate) vector_index: VectorIndex,
+
     pub(crate) permission_map: RbacMap,
 
+    // Index of the last applied Raft log entry, set by the state machine
+    pub(crate) applied_index: u64,
+
+    // Applied index of the last change to each project, part of the project cache key
+    pub(crate) project_changes: HashMap<Uuid, u64>,
+
+    // Subgraphs of recently read projects
+    pub(crate) project_cache: ProjectGraphCache<EntityProp>,
+
     // TODO:
     pub external_storage: Vec<Arc<RwLock<dyn ExternalStorage<EntityProp>>>>,
 }
@@ -125,10 +281,17 @@ where
             graph: Default::default(),
             node_id_map: Default::default(),
             name_id_map: Default::default(),
+            attr_index: Default::default(),
+            tenant: Default::default(),
             deleted: Default::default(),
+            deleted_at: Default::default(),
             entry_points: Default::default(),
             fts_index: Default::default(),
+            vector_index: Default::default(),
             permission_map: Default::default(),
+            applied_index: 0,
+            project_changes: Default::default(),
+            project_cache: Default::default(),
             external_storage: Default::default(),
         }
     }
@@ -150,20 +313,30 @@ where
     pub fn from_content(
         graph: Graph<Entity<EntityProp>, Edge, Directed>,
         deleted: HashSet<Uuid>,
+        mut deleted_at: HashMap<Uuid, DateTime<Utc>>,
         permissions: Vec<RbacRecord>,
     ) -> Self {
+        // Entities deleted before the deletion time was recorded have no time and are kept, the
+        // snapshot must load into the same state on every node
+        deleted_at.retain(|id, _| deleted.contains(id));
         let fts_index = FtsIndex::new();
         let node_id_map = graph
             .node_indices()
             .filter_map(|idx| graph.node_weight(idx).map(|w| (w.id, idx)))
             .collect();
-        let name_id_map: HashMap<String, BTreeMap<u64, Uuid>> = graph
-            .node_weights()
-            .map(|w| (&w.qualified_name, (w.version, w.id)))
-            .group_by(|v| v.0.to_owned())
-            .into_iter()
-            .map(|(k, v)| (k, v.map(|v| v.1).collect()))
-            .collect();
+        let mut name_id_map: HashMap<String, HashMap<String, BTreeMap<u64, Uuid>>> = HashMap::new();
+        for w in graph.node_weights() {
+            name_id_map
+                .entry(w.properties.get_tenant().to_string())
+                .or_default()
+                .entry(w.qualified_name.to_owned())
+                .or_default()
+                .insert(w.version, w.id);
+        }
+        let mut attr_index = AttributeIndex::default();
+        for w in graph.node_weights().filter(|w| !deleted.contains(&w.id)) {
+            attr_index.insert(w.id, w.entity_type, w.properties.get_tags().into_keys());
+        }
         let entry_points = graph
             .node_indices()
             .filter(|&idx| {
@@ -177,10 +350,17 @@ where
             graph,
             node_id_map,
             name_id_map,
+            attr_index,
+            tenant: Default::default(),
             deleted,
+            deleted_at,
             entry_points,
             fts_index,
+            vector_index: Default::default(),
             permission_map: Default::default(),
+            applied_index: 0,
+            project_changes: Default::default(),
+            project_cache: Default::default(),
             external_storage: Default::default(),
         };
         let ids: Vec<_> = ret.node_id_map.keys().copied().collect();
@@ -188,7 +368,7 @@ where
         ids.into_iter().for_each(|id| {
             ret.index_entity(id.to_owned(), false).ok();
         });
-        ret.fts_index.commit().ok();
+        ret.fts_index.commit_blocking().ok();
 
         ret.load_permissions(permissions.into_iter()).ok();
         ret
@@ -205,55 +385,106 @@ where
             graph: Graph::new(),
             node_id_map: Default::default(),
             name_id_map: Default::default(),
+            attr_index: Default::default(),
+            tenant: Default::default(),
             deleted: Default::default(),
+            deleted_at: Default::default(),
             entry_points: Default::default(),
             fts_index: FtsIndex::new(),
+            vector_index: Default::default(),
             permission_map: Default::default(),
+            applied_index: 0,
+            project_changes: Default::default(),
+            project_cache: Default::default(),
             external_storage: Default::default(),
         }
     }
 
+    /**
+     * Export all live entities, edges and permissions, the result can be loaded back with `load`
+     */
+    pub fn export_content(
+        &self,
+    ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>, Vec<RbacRecord>), RegistryError> {
+        let entities = self
+            .graph
+            .node_weights()
+            .filter(|w| !self.deleted.contains(&w.id))
+            .cloned()
+            .collect();
+        // Edges connected to deleted entities have already been removed from the graph
+        let edges = self.graph.edge_weights().cloned().collect();
+        let permissions = self.export_permissions();
+        Ok((entities, edges, permissions))
+    }
+
+    /**
+     * Permissions of all tenants, grants in named tenants keep the entity id as names are not
+     * unique across tenants
+     */
+    fn export_permissions(&self) -> Vec<RbacRecord> {
+        self.permission_map
+            .iter()
+            .map(|(credential, permission, resource)| RbacRecord {
+                credential: credential.to_owned(),
+                resource: match &resource.resource {
+                    Resource::Entity(id) => self
+                        .get_idx(*id)
+                        .ok()
+                        .and_then(|idx| self.graph.node_weight(idx))
+                        .filter(|w| w.properties.get_tenant().is_empty())
+                        .map(|w| Resource::NamedEntity(w.qualified_name.to_owned()))
+                        .unwrap_or(Resource::Entity(*id)),
+                    r => r.to_owned(),
+                },
+                permission: permission.to_owned(),
+                requestor: resource.granted_by.to_owned(),
+                reason: resource.reason.to_owned(),
+                time: resource.granted_time,
+            })
+            .collect()
+    }
+
     pub(crate) async fn batch_load<NI, EI>(
         &mut self,
         entities: NI,
         edges: EI,
-    ) -> Result<(), RegistryError>
+        policy: LoadPolicy,
+    ) -> Result<LoadReport, RegistryError>
     where
         NI: Iterator<Item = Entity<EntityProp>>,
         EI: Iterator<Item = Edge>,
     {
+        let mut report = LoadReport::new(policy);
         let mut ids: HashSet<Uuid> = Default::default();
+        // Loaded entities ending up with another id, edges are connected to the new ids
+        let mut id_map: HashMap<Uuid, Uuid> = Default::default();
         self.fts_index.enable(false);
         for e in entities {
-            // Insert and ignore any error. e.g. duplicated entities
-            match self
-                .insert_entity(
-                    e.id,
-                    e.entity_type,
-                    e.name.clone(),
-                    e.qualified_name.clone(),
-                    e.properties.clone(),
-                )
-                .await
-            {
-                Ok(_) => {
-                    ids.insert(e.id);
-                }
-                Err(e) => {
-                    debug!("Ignored error '{:?}'", e);
+            let entry = self.load_entity(e, policy).await;
+            if entry.registry_id != entry.id {
+                id_map.insert(entry.id, entry.registry_id);
+            }
+            match entry.action {
+                LoadAction::Inserted | LoadAction::Overwritten | LoadAction::NewVersion => {
+                    ids.insert(entry.registry_id);
                 }
+                _ => {}
             }
+            report.add(entry);
         }
 
         for e in edges {
-            self.connect(e.from, e.to, e.edge_type).await.ok();
+            let from = id_map.get(&e.from).copied().unwrap_or(e.from);
+            let to = id_map.get(&e.to).copied().unwrap_or(e.to);
+            self.connect(from, to, e.edge_type).await.ok();
         }
 
         self.fts_index.enable(true);
         for id in ids {
             self.index_entity(id, false).ok();
         }
-        self.fts_index.commit()?;
+        self.fts_index.commit().await?;
 
         self.entry_points = self
             .graph
@@ -266,7 +497,138 @@ where
             })
             .collect();
 
-        Ok(())
+        Ok(report)
+    }
+
+    /**
+     * Load one entity of a batch, an entity conflicts with the existing one with the same id, or with
+     * the same qualified name and version.
+     * Errors are logged and the entity is reported as skipped
+     */
+    async fn load_entity(&mut self, e: Entity<EntityProp>, policy: LoadPolicy) -> LoadEntry {
+        let mut entry = LoadEntry {
+            id: e.id,
+            registry_id: e.id,
+            entity_type: e.entity_type,
+            qualified_name: e.qualified_name.clone(),
+            version: e.version,
+            action: LoadAction::Skipped,
+        };
+        let versions = self
+            .get_versions_in_tenant(e.properties.get_tenant(), &e.qualified_name)
+            .cloned()
+            .unwrap_or_default();
+        // Identical to an existing version, e.g. the same database is loaded again
+        if let Some((&version, &id)) = versions.iter().find(|(_, &id)| self.is_same_entity(id, &e))
+        {
+            entry.registry_id = id;
+            entry.version = version;
+            entry.action = LoadAction::Unchanged;
+            return entry;
+        }
+        let conflict = if self.node_id_map.contains_key(&e.id) {
+            Some(e.id)
+        } else {
+            versions.get(&e.properties.get_version()).copied()
+        };
+        let result = match (conflict, policy) {
+            (None, _) => self
+                .insert_entity(
+                    e.id,
+                    e.entity_type,
+                    e.name.clone(),
+                    e.qualified_name.clone(),
+                    e.properties.clone(),
+                )
+                .await
+                .map(|id| (id, LoadAction::Inserted)),
+            (Some(id), LoadPolicy::Skip) => Ok((id, LoadAction::Skipped)),
+            (Some(id), LoadPolicy::Overwrite) => self
+                .overwrite_entity(id, &e)
+                .await
+                .map(|_| (id, LoadAction::Overwritten)),
+            (Some(_), LoadPolicy::NewVersion) => {
+                let version = versions.keys().max().copied().unwrap_or_default() + 1;
+                let id = if self.node_id_map.contains_key(&e.id) {
+                    // The load is applied on every Raft node, so the new id can't be random
+                    Uuid::from_u128(e.id.as_u128() ^ (u128::from(version) << 64))
+                } else {
+                    e.id
+                };
+                let mut properties = e.properties.clone();
+                properties.set_guid(id);
+                properties.set_version(version);
+                self.insert_entity(
+                    id,
+                    e.entity_type,
+                    e.name.clone(),
+                    e.qualified_name.clone(),
+                    properties,
+                )
+                .await
+                .map(|id| (id, LoadAction::NewVersion))
+            }
+        };
+        match result {
+            Ok((id, action)) => {
+                entry.registry_id = id;
+                entry.action = action;
+                if let Some(w) = self
+                    .get_idx(id)
+                    .ok()
+                    .and_then(|idx| self.graph.node_weight(idx))
+                {
+                    entry.version = w.version;
+                }
+            }
+            Err(err) => {
+                debug!("Ignored error '{:?}' loading {}", err, e.qualified_name);
+            }
+        }
+        entry
+    }
+
+    /**
+     * Same type, name and properties, the id and the version are not compared
+     */
+    fn is_same_entity(&self, id: Uuid, e: &Entity<EntityProp>) -> bool {
+        match self
+            .get_idx(id)
+            .ok()
+            .and_then(|idx| self.graph.node_weight(idx))
+        {
+            Some(w) if w.entity_type == e.entity_type && w.qualified_name == e.qualified_name => {
+                let mut properties = e.properties.clone();
+                properties.set_guid(w.id);
+                properties.set_version(w.version);
+                properties == w.properties
+            }
+            _ => false,
+        }
+    }
+
+    /**
+     * Replace the properties of the existing entity with the loaded ones, keeping its id and version
+     */
+    async fn overwrite_entity(
+        &mut self,
+        id: Uuid,
+        e: &Entity<EntityProp>,
+    ) -> Result<(), RegistryError> {
+        let idx = self.get_idx(id)?;
+        let existing = self
+            .graph
+            .node_weight(idx)
+            .ok_or(RegistryError::InvalidEntity(id))?;
+        if existing.entity_type != e.entity_type || existing.qualified_name != e.qualified_name {
+            // Only the definition can be replaced, not the identity
+            return Err(RegistryError::EntityIdExists(id));
+        }
+        let mut properties = e.properties.clone();
+        properties.set_guid(id);
+        properties.set_version(existing.version);
+        self.update_properties_by_id(id, move |p| *p = properties)
+            .await
     }
 
     pub(crate) async fn load<NI, EI, RI>(
@@ -282,24 +644,77 @@ where
         let mut ret = Self {
             graph: Graph::with_capacity(NODE_CAPACITY * 10, NODE_CAPACITY),
             node_id_map: HashMap::with_capacity(NODE_CAPACITY),
-            name_id_map: HashMap::with_capacity(NODE_CAPACITY),
+            name_id_map: Default::default(),
+            attr_index: Default::default(),
+            tenant: Default::default(),
             deleted: HashSet::with_capacity(NODE_CAPACITY),
+            deleted_at: Default::default(),
             entry_points: Vec::with_capacity(NODE_CAPACITY),
             fts_index: FtsIndex::new(),
+            vector_index: Default::default(),
             permission_map: Default::default(),
+            applied_index: 0,
+            project_changes: Default::default(),
+            project_cache: Default::default(),
             external_storage: Default::default(),
         };
-        ret.batch_load(entities, edges).await?;
+        ret.batch_load(entities, edges, LoadPolicy::Skip).await?;
         ret.load_permissions(permissions)?;
 
         Ok(ret)
     }
 
+    /**
+     * Set the index of the Raft log entry being applied, changes made by the entry are recorded
+     * with this index
+     */
+    pub fn set_applied_index(&mut self, index: u64) {
+        self.applied_index = index;
+    }
+
+    /**
+     * Projects containing the entity, or the entity itself if it's a project
+     */
+    fn get_containing_projects(&self, uuid: Uuid) -> Vec<Uuid> {
+        let idx = match self.node_id_map.get(&uuid) {
+            Some(&idx) => idx,
+            None => return vec![],
+        };
+        match self.graph.node_weight(idx) {
+            Some(w) if w.entity_type == EntityType::Project => vec![uuid],
+            Some(_) => self
+                .get_neighbors_idx(idx, |e| e.edge_type == EdgeType::BelongsTo)
+                .into_iter()
+                .filter_map(|i| self.graph.node_weight(i))
+                .filter(|w| w.entity_type == EntityType::Project)
+                .map(|w| w.id)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /**
+     * Record the change to projects containing the entity, cached subgraphs of them are dropped
+     */
+    fn touch_projects(&mut self, uuid: Uuid) {
+        for project_id in self.get_containing_projects(uuid) {
+            self.project_changes.insert(project_id, self.applied_index);
+            self.project_cache.invalidate(project_id);
+        }
+    }
+
     pub(crate) fn get_project_by_id(
         &self,
         uuid: Uuid,
     ) -> Result<(HashSet<Entity<EntityProp>>, HashSet<Edge>), RegistryError> {
         let root = self.get_idx(uuid)?;
+        let key = (
+            uuid,
+            self.project_changes.get(&uuid).copied().unwrap_or_default(),
+        );
+        if let Some(graph) = self.project_cache.get(&key) {
+            return Ok(graph);
+        }
         let subgraph = self.graph.filter_map(
             |idx, node| {
                 self.graph
@@ -308,8 +723,10 @@ where
                     .map(|_| node)
             },
             |_, e| {
-                if self.has_connection_type(uuid, e.from, EdgeType::Contains)
-                    || self.has_connection_type(uuid, e.to, EdgeType::Contains)
+                // Soft edges are not part of the lineage
+                if e.edge_type != EdgeType::SimilarTo
+                    && (self.has_connection_type(uuid, e.from, EdgeType::Contains)
+                        || self.has_connection_type(uuid, e.to, EdgeType::Contains))
                 {
                     Some(e)
                 } else {
@@ -318,18 +735,51 @@ where
             },
         );
 
-        let entities = self
+        let entities: HashSet<Entity<EntityProp>> = self
             .graph
             .node_weight(root)
             .iter()
             .chain(subgraph.node_weights())
             .map(|&w| w.to_owned())
             .collect();
-        let edges = subgraph.edge_weights().map(|&w| w.to_owned()).collect();
+        let edges: HashSet<Edge> = subgraph.edge_weights().map(|&w| w.to_owned()).collect();
 
+        self.project_cache
+            .insert(key, (entities.clone(), edges.clone()));
         Ok((entities, edges))
     }
 
+    pub(crate) fn get_project_summary_by_id(
+        &self,
+        uuid: Uuid,
+    ) -> Result<ProjectSummary, RegistryError> {
+        const TOP_TAGS: usize = 10;
+
+        let (entities, _) = self.get_project_by_id(uuid)?;
+        let mut summary = ProjectSummary::default();
+        let mut tags: HashMap<(String, String), usize> = HashMap::new();
+        for e in entities.iter().filter(|e| !self.deleted.contains(&e.id)) {
+            summary.last_modified = summary.last_modified.max(e.properties.get_created_on());
+            if e.entity_type == EntityType::Project {
+                continue;
+            }
+            *summary.entity_counts.entry(e.entity_type).or_default() += 1;
+            for tag in e.properties.get_tags() {
+                *tags.entry(tag).or_default() += 1;
+            }
+            if let Some(t) = e.properties.get_feature_type() {
+                *summary.feature_types.entry(t).or_default() += 1;
+            }
+        }
+        summary.top_tags = tags
+            .into_iter()
+            .sorted_by(|(a, ca), (b, cb)| cb.cmp(ca).then_with(|| a.cmp(b)))
+            .take(TOP_TAGS)
+            .map(|((key, value), count)| TagCount { key, value, count })
+            .collect();
+        Ok(summary)
+    }
+
     pub(crate) fn has_connection_type(&self, from: Uuid, to: Uuid, edge_type: EdgeType) -> bool {
         if let Ok(from) = self.get_idx(from) {
             if let Ok(to) = self.get_idx(to) {
@@ -351,6 +801,18 @@ where
             .collect()
     }
 
+    /**
+     * Projects of all tenants along with their tenants, for tasks running outside of any request
+     */
+    pub fn get_tenant_projects(&self) -> Vec<(String, Uuid)> {
+        self.entry_points
+            .iter()
+            .filter_map(|&idx| self.graph.node_weight(idx))
+            .filter(|w| w.entity_type == EntityType::Project && !self.deleted.contains(&w.id))
+            .map(|w| (w.properties.get_tenant().to_string(), w.id))
+            .collect()
+    }
+
     pub(crate) fn get_features(&self) -> Vec<Entity<EntityProp>> {
         self.graph
             .node_indices()
@@ -416,21 +878,95 @@ where
             .collect()
     }
 
+    /**
+     * Entities of the current tenant with any of the types and all of the tags, a tag without value
+     * matches any value, and empty filters match everything.
+     * Candidates come from the attribute index, only the full listing scans all nodes
+     */
+    pub(crate) fn get_entities_by_attributes(
+        &self,
+        types: &HashSet<EntityType>,
+        tags: &HashMap<String, Option<String>>,
+        container: Option<Uuid>,
+    ) -> Vec<Entity<EntityProp>> {
+        let tag_keys: Vec<&str> = tags.keys().map(|k| k.as_str()).collect();
+        let entities: Vec<&Entity<EntityProp>> = match self.attr_index.lookup(types, &tag_keys) {
+            Some(ids) => ids
+                .into_iter()
+                .filter_map(|id| self.get_scoped_idx(id).ok())
+                .filter_map(|idx| self.graph.node_weight(idx))
+                .collect(),
+            None => self
+                .graph
+                .node_weights()
+                .filter(|w| {
+                    !self.deleted.contains(&w.id) && w.properties.get_tenant() == self.tenant
+                })
+                .collect(),
+        };
+        entities
+            .into_iter()
+            .filter(|w| {
+                tags.values().all(Option::is_none) || {
+                    let values = w.properties.get_tags();
+                    tags.iter()
+                        .all(|(k, v)| v.is_none() || values.get(k) == v.as_ref())
+                }
+            })
+            .filter(|w| match container {
+                Some(scope) => self.has_connection_type(w.id, scope, EdgeType::BelongsTo),
+                None => true,
+            })
+            .sorted_by(|a, b| {
+                a.qualified_name
+                    .cmp(&b.qualified_name)
+                    .then(a.version.cmp(&b.version))
+            })
+            .cloned()
+            .collect()
+    }
+
     pub(crate) fn get_entity_by_id(&self, uuid: Uuid) -> Option<Entity<EntityProp>> {
-        self.node_id_map
-            .get(&uuid)
-            .filter(|_| !self.deleted.contains(&uuid))
-            .and_then(|&i| self.graph.node_weight(i))
+        self.get_scoped_idx(uuid)
+            .ok()
+            .and_then(|i| self.graph.node_weight(i))
             .map(|w| w.to_owned())
     }
 
+    /**
+     * Tags inherited by new entities of the project, empty if the project doesn't exist
+     */
+    pub(crate) fn get_default_tags(&self, project_id: Uuid) -> HashMap<String, String> {
+        self.get_scoped_idx(project_id)
+            .ok()
+            .and_then(|i| self.graph.node_weight(i))
+            .map(|w| w.properties.get_default_tags())
+            .unwrap_or_default()
+    }
+
+    /**
+     * All versions of the entity in the current tenant
+     */
+    pub(crate) fn get_versions(&self, qualified_name: &str) -> Option<&BTreeMap<u64, Uuid>> {
+        self.get_versions_in_tenant(&self.tenant, qualified_name)
+    }
+
+    fn get_versions_in_tenant(
+        &self,
+        tenant: &str,
+        qualified_name: &str,
+    ) -> Option<&BTreeMap<u64, Uuid>> {
+        self.name_id_map
+            .get(tenant)
+            .and_then(|names| names.get(qualified_name))
+    }
+
     pub(crate) fn get_entity_by_name(
         &self,
         qualified_name: &str,
         version: Option<u64>,
     ) -> Option<Entity<EntityProp>> {
-        self.name_id_map
-            .get(qualified_name)
+        self.get_versions(qualified_name)
             .and_then(|ids| match version {
                 Some(v) => ids.get(&v),
                 None => ids.keys().max().and_then(|v| ids.get(v)),
@@ -554,8 +1090,7 @@ where
         }
 
         if self
-            .name_id_map
-            .get(&qualified_name.to_string())
+            .get_versions_in_tenant(properties.get_tenant(), &qualified_name.to_string())
             .map(|versions| versions.keys().any(|&v| properties.get_version() == v))
             .unwrap_or_default()
         {
@@ -574,23 +1109,90 @@ where
         Ok(uuid)
     }
 
+    /**
+     * Wait until all indexed entities are searchable, for searches requiring strict read-after-write
+     */
+    pub async fn commit_search_index(&self) -> Result<(), RegistryError> {
+        Ok(self.fts_index.commit().await?)
+    }
+
     pub fn index_entity(&mut self, id: Uuid, commit: bool) -> Result<(), RegistryError> {
-        if let Some(e) = self.get_entity_by_id(id) {
+        // Entities of all tenants are indexed, the tenant is a search filter
+        let idx = match self.get_idx(id) {
+            Ok(idx) => idx,
+            Err(_) => {
+                self.vector_index.remove(id);
+                return Ok(());
+            }
+        };
+        if let Some(e) = self.graph.node_weight(idx).cloned() {
+            // The doc may have changed, the embedding is computed again in the background
+            self.vector_index.update(id, embedding_doc(&e));
             let scopes = self
-                .get_neighbors(id, EdgeType::BelongsTo)?
-                .iter()
-                .map(|e| e.id.to_string())
+                .get_neighbors_idx(idx, |e| e.edge_type == EdgeType::BelongsTo)
+                .into_iter()
+                .filter_map(|i| self.graph.node_weight(i))
+                .map(|w| w.id.to_string())
                 .collect();
+            let tenant = e.properties.get_tenant();
             if commit {
-                self.fts_index.index(&e, scopes)?;
+                self.fts_index.index(&e, scopes, tenant)?;
             } else {
-                self.fts_index.add_doc(&e, scopes)?;
+                self.fts_index.add_doc(&e, scopes, tenant)?;
             }
         }
         Ok(())
     }
 
-    pub async fn delete_entity_by_id(&mut self, uuid: Uuid) -> Result<(), RegistryError> {
+    /**
+     * Replace the owner and contact info of an existing entity
+     */
+    pub(crate) async fn set_ownership_by_id(
+        &mut self,
+        uuid: Uuid,
+        ownership: &Ownership,
+    ) -> Result<(), RegistryError> {
+        self.update_properties_by_id(uuid, |p| p.set_ownership(ownership))
+            .await?;
+        // Owner info is searchable
+        self.index_entity(uuid, true)
+    }
+
+    /**
+     * Update properties of an existing entity in place and sync it to external storages
+     */
+    pub(crate) async fn update_properties_by_id<F>(
+        &mut self,
+        uuid: Uuid,
+        f: F,
+    ) -> Result<(), RegistryError>
+    where
+        F: FnOnce(&mut EntityProp),
+    {
+        let idx = self.get_idx(uuid)?;
+        self.touch_projects(uuid);
+        let w = self
+            .graph
+            .node_weight_mut(idx)
+            .ok_or(RegistryError::InvalidEntity(uuid))?;
+        let tag_keys = w.properties.get_tags().into_keys();
+        self.attr_index.remove(uuid, w.entity_type, tag_keys);
+        f(&mut w.properties);
+        self.attr_index
+            .insert(uuid, w.entity_type, w.properties.get_tags().into_keys());
+        let entity = w.to_owned();
+        self.vector_index.remove(uuid);
+        for es in &self.external_storage {
+            es.write().await.update_entity(uuid, &entity).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete_entity_by_id(
+        &mut self,
+        uuid: Uuid,
+        deleted_at: Option<DateTime<Utc>>,
+    ) -> Result<(), RegistryError> {
         if self
             .graph
             .edges_directed(self.get_idx(uuid)?, Direction::Outgoing)
@@ -600,6 +1202,7 @@ where
             Err(RegistryError::DeleteInUsed(uuid))
         } else {
             let idx = self.get_idx(uuid)?;
+            self.touch_projects(uuid);
             let edges: HashSet<EdgeIndex> = self
                 .get_neighbors_idx(idx, |_| true)
                 .into_iter()
@@ -632,15 +1235,90 @@ where
                 for es in &self.external_storage {
                     es.write().await.delete_entity(uuid, w).await?;
                 }
+                self.attr_index
+                    .remove(uuid, w.entity_type, w.properties.get_tags().into_keys());
             }
             self.graph.retain_edges(|_, e| !edges.contains(&e));
             // Mark deletion, we don't want to invalidate node indices as we have a reversed index
             self.deleted.insert(uuid);
+            if let Some(deleted_at) = deleted_at {
+                self.deleted_at.insert(uuid, deleted_at);
+            }
             Ok(())
         }
         // TODO: How to deal with FTS?
     }
 
+    /**
+     * Remove the tombstones of entities deleted before `before` in all tenants, FTS docs of them
+     * are left as search results are looked up in the graph
+     */
+    pub async fn collect_garbage(
+        &mut self,
+        before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<GarbageCollectionReport, RegistryError> {
+        let mut report = GarbageCollectionReport::new(before, dry_run);
+        let mut expired: Vec<(Uuid, NodeIndex)> = self
+            .deleted_at
+            .iter()
+            .filter(|(_, &t)| t < before)
+            .filter_map(|(id, _)| self.node_id_map.get(id).map(|&idx| (*id, idx)))
+            .collect();
+        report.retained = self.deleted.len() - expired.len();
+        let edges: HashSet<EdgeIndex> = expired
+            .iter()
+            .flat_map(|&(_, idx)| {
+                self.graph
+                    .edges_directed(idx, Direction::Outgoing)
+                    .chain(self.graph.edges_directed(idx, Direction::Incoming))
+                    .map(|e| e.id())
+            })
+            .collect();
+        report.edges = edges.len();
+        report.entities = expired.iter().map(|(id, _)| *id).sorted().collect();
+        if dry_run || expired.is_empty() {
+            return Ok(report);
+        }
+
+        for (id, _) in &expired {
+            for es in &self.external_storage {
+                es.write().await.purge_entity(*id).await?;
+            }
+        }
+        // Removing a node moves the last node into its index, so nodes are removed from the back
+        // and the other expired nodes keep their indices
+        expired.sort_by(|a, b| b.1.cmp(&a.1));
+        for (id, idx) in expired {
+            if let Some(w) = self.graph.remove_node(idx) {
+                let tenant = w.properties.get_tenant().to_string();
+                if let Some(names) = self.name_id_map.get_mut(&tenant) {
+                    if let Some(versions) = names.get_mut(&w.qualified_name) {
+                        versions.retain(|_, v| *v != id);
+                        if versions.is_empty() {
+                            names.remove(&w.qualified_name);
+                        }
+                    }
+                }
+            }
+            self.deleted.remove(&id);
+            self.deleted_at.remove(&id);
+            self.project_changes.remove(&id);
+            self.vector_index.remove(id);
+        }
+        self.node_id_map = self
+            .graph
+            .node_indices()
+            .filter_map(|idx| self.graph.node_weight(idx).map(|w| (w.id, idx)))
+            .collect();
+        self.entry_points = self
+            .graph
+            .node_indices()
+            .filter(|&idx| self.graph[idx].entity_type.is_entry_point())
+            .collect();
+        Ok(report)
+    }
+
     pub async fn connect(
         &mut self,
         from: Uuid,
@@ -665,6 +1343,9 @@ where
             let storage = storage.clone();
             storage.write().await.connect(from, to, edge_type).await?;
         }
+        // `from` may not belong to the project yet if this is the `Contains` edge
+        self.touch_projects(from);
+        self.touch_projects(to);
         match self
             .graph
             .edges_connecting(from_idx, to_idx)
@@ -703,6 +1384,93 @@ where
             .to_owned())
     }
 
+    /**
+     * Same as `get_idx`, but entities of other tenants are treated as non-existent
+     */
+    pub(crate) fn get_scoped_idx(&self, uuid: Uuid) -> Result<NodeIndex, RegistryError> {
+        self.get_idx_in_tenant(&self.tenant, uuid)
+    }
+
+    fn get_idx_in_tenant(&self, tenant: &str, uuid: Uuid) -> Result<NodeIndex, RegistryError> {
+        let idx = self.get_idx(uuid)?;
+        match self.graph.node_weight(idx) {
+            Some(w) if w.properties.get_tenant() == tenant => Ok(idx),
+            _ => Err(RegistryError::InvalidEntity(uuid)),
+        }
+    }
+
+    fn get_entity_in_tenant(
+        &self,
+        tenant: &str,
+        uuid: Uuid,
+    ) -> Result<&Entity<EntityProp>, RegistryError> {
+        self.graph
+            .node_weight(self.get_idx_in_tenant(tenant, uuid)?)
+            .ok_or(RegistryError::InvalidEntity(uuid))
+    }
+
+    pub(crate) fn get_entity_id_by_qualified_name_in_tenant(
+        &self,
+        tenant: &str,
+        qualified_name: &str,
+    ) -> Result<Uuid, RegistryError> {
+        let (qualified_name, version) = extract_version(qualified_name);
+        self.get_versions_in_tenant(tenant, qualified_name)
+            .and_then(|ids| match version {
+                Some(v) => ids.get(&v),
+                None => ids.keys().max().and_then(|v| ids.get(v)),
+            })
+            .ok_or_else(|| RegistryError::EntityNotFound(qualified_name.to_string()))
+            .cloned()
+    }
+
+    /**
+     * Same as `get_entity_id`, in the tenant instead of the current one.
+     * Lookups taking the tenant don't need to switch the tenant, so they work with a shared
+     * reference to the registry
+     */
+    pub fn get_entity_id_in_tenant(
+        &self,
+        tenant: &str,
+        name_or_id: &str,
+    ) -> Result<Uuid, RegistryError> {
+        match Uuid::parse_str(name_or_id) {
+            Ok(id) => Ok(self.get_entity_in_tenant(tenant, id)?.id),
+            Err(_) => self.get_entity_id_by_qualified_name_in_tenant(tenant, name_or_id),
+        }
+    }
+
+    /**
+     * Same as `get_entity_qualified_name`, in the tenant instead of the current one
+     */
+    pub fn get_entity_qualified_name_in_tenant(
+        &self,
+        tenant: &str,
+        uuid: Uuid,
+    ) -> Result<String, RegistryError> {
+        Ok(self
+            .get_entity_in_tenant(tenant, uuid)?
+            .qualified_name
+            .to_owned())
+    }
+
+    /**
+     * Entities connected to the entity by edges of the type, the entity must be in the tenant
+     */
+    fn get_neighbors_in_tenant(
+        &self,
+        tenant: &str,
+        uuid: Uuid,
+        edge_type: EdgeType,
+    ) -> Result<Vec<&Entity<EntityProp>>, RegistryError> {
+        let idx = self.get_idx_in_tenant(tenant, uuid)?;
+        Ok(self
+            .get_neighbors_idx(idx, |e| e.edge_type == edge_type)
+            .into_iter()
+            .filter_map(|idx| self.graph.node_weight(idx))
+            .collect())
+    }
+
     pub(crate) fn get_neighbors_idx<F>(&self, idx: NodeIndex, predicate: F) -> Vec<NodeIndex>
     where
         F: Fn(&Edge) -> bool,
@@ -738,7 +1506,13 @@ where
         qualified_name: String,
         properties: EntityProp,
     ) -> Result<NodeIndex, RegistryError> {
-        let version = self.get_next_version_number(&qualified_name);
+        let tenant = properties.get_tenant().to_string();
+        let version = self
+            .get_versions_in_tenant(&tenant, &qualified_name)
+            .and_then(|ids| ids.keys().max())
+            .copied()
+            .unwrap_or_default()
+            + 1;
         let mut entity = Entity {
             id,
             entity_type,
@@ -752,73 +1526,225 @@ where
             let storage = storage.clone();
             storage.write().await.add_entity(id, &entity).await?;
         }
+        self.attr_index
+            .insert(id, entity_type, entity.properties.get_tags().into_keys());
         let idx = self.graph.add_node(entity);
         self.node_id_map.insert(id, idx);
         self.name_id_map
+            .entry(tenant)
+            .or_default()
             .entry(qualified_name)
             .or_default()
             .insert(version, id);
         if entity_type.is_entry_point() {
             self.entry_points.push(idx);
         }
-        Ok(idx)
+        Ok(idx)
+    }
+
+    fn insert_edge(
+        &mut self,
+        edge_type: EdgeType,
+        from_idx: NodeIndex,
+        to_idx: NodeIndex,
+        from_uuid: Uuid,
+        to_uuid: Uuid,
+    ) -> EdgeIndex {
+        self.graph.add_edge(
+            from_idx,
+            to_idx,
+            Edge {
+                from: from_uuid,
+                to: to_uuid,
+                edge_type,
+            },
+        )
+    }
+
+    /**
+     * Grants on anchors and features are kept on the entity itself, all others apply to the project
+     */
+    fn get_grant_scope(&self, id: Uuid) -> Result<Uuid, RegistryError> {
+        match self.get_entity_type(id)? {
+            EntityType::Anchor | EntityType::AnchorFeature | EntityType::DerivedFeature => Ok(id),
+            _ => self.get_entity_project_id(id),
+        }
+    }
+
+    /**
+     * Returns ids of the entity and all entities it inherits permissions from,
+     * e.g. anchor feature -> anchor -> project
+     */
+    pub(crate) fn get_permission_scope_ids(&self, id: Uuid) -> Result<Vec<Uuid>, RegistryError> {
+        self.get_permission_scope_ids_in_tenant(&self.tenant, id)
+    }
+
+    fn get_permission_scope_ids_in_tenant(
+        &self,
+        tenant: &str,
+        id: Uuid,
+    ) -> Result<Vec<Uuid>, RegistryError> {
+        let entity = self.get_entity_in_tenant(tenant, id)?;
+        let mut ids = vec![id];
+        let add_versions = |ids: &mut Vec<Uuid>, qualified_name: &str| {
+            // A grant on any version applies to all versions of the entity
+            let (qualified_name, _) = extract_version(qualified_name);
+            for &v in self
+                .get_versions_in_tenant(tenant, qualified_name)
+                .into_iter()
+                .flat_map(|versions| versions.values())
+            {
+                if self.get_idx_in_tenant(tenant, v).is_ok() && !ids.contains(&v) {
+                    ids.push(v);
+                }
+            }
+        };
+        let belongs_to = self.get_neighbors_in_tenant(tenant, id, EdgeType::BelongsTo)?;
+        match entity.entity_type {
+            EntityType::Anchor | EntityType::DerivedFeature => {
+                add_versions(&mut ids, &entity.qualified_name);
+            }
+            EntityType::AnchorFeature => {
+                add_versions(&mut ids, &entity.qualified_name);
+                for anchor in belongs_to
+                    .iter()
+                    .filter(|e| e.entity_type == EntityType::Anchor)
+                {
+                    ids.push(anchor.id);
+                    add_versions(&mut ids, &anchor.qualified_name);
+                }
+            }
+            _ => {}
+        }
+        let proj_id = if entity.entity_type == EntityType::Project {
+            id
+        } else {
+            belongs_to
+                .iter()
+                .find(|e| e.entity_type == EntityType::Project)
+                .map(|e| e.id)
+                .ok_or(RegistryError::InvalidEntity(id))?
+        };
+        if !ids.contains(&proj_id) {
+            ids.push(proj_id);
+        }
+        Ok(ids)
+    }
+
+    pub(crate) fn get_permission_scopes_in_tenant(
+        &self,
+        tenant: &str,
+        resource: &Resource,
+    ) -> Result<Vec<Resource>, RegistryError> {
+        let id = match resource {
+            Resource::NamedEntity(name) => self.get_entity_id_in_tenant(tenant, name)?,
+            Resource::Entity(id) => *id,
+            Resource::Global => return Ok(vec![Resource::Global]),
+        };
+        Ok(self
+            .get_permission_scope_ids_in_tenant(tenant, id)?
+            .into_iter()
+            .map(Resource::Entity)
+            .collect())
+    }
+
+    /**
+     * Same as `check_permission`, in the tenant instead of the current one
+     */
+    pub fn check_permission_in_tenant(
+        &self,
+        tenant: &str,
+        credential: &Credential,
+        resource: &Resource,
+        permission: Permission,
+    ) -> Result<bool, RegistryError> {
+        if credential == &Credential::RbacDisabled {
+            return Ok(true);
+        }
+        // User must be either Global Admin, or Admin or having the permission on the resource or
+        // any scope it inherits from, e.g. anchor or project.
+        // Global Admins administrate the default tenant only, named tenants are managed by the
+        // project admins in the tenant
+        let scopes = self.get_permission_scopes_in_tenant(tenant, resource)?;
+        Ok((tenant.is_empty()
+            && self.permission_map.check_permission(
+                credential,
+                &Resource::Global,
+                Permission::Admin,
+            ))
+            || self
+                .permission_map
+                .check_scoped_permission(credential, &scopes, Permission::Admin)
+            || self
+                .permission_map
+                .check_scoped_permission(credential, &scopes, permission))
     }
 
-    fn insert_edge(
-        &mut self,
-        edge_type: EdgeType,
-        from_idx: NodeIndex,
-        to_idx: NodeIndex,
-        from_uuid: Uuid,
-        to_uuid: Uuid,
-    ) -> EdgeIndex {
-        self.graph.add_edge(
-            from_idx,
-            to_idx,
-            Edge {
-                from: from_uuid,
-                to: to_uuid,
-                edge_type,
-            },
-        )
+    /**
+     * Same as `visible_projects`, in the tenant instead of the current one
+     */
+    pub fn visible_projects_in_tenant(
+        &self,
+        tenant: &str,
+        credential: &Credential,
+    ) -> Result<Vec<String>, RegistryError> {
+        // Global readers and Global Admins see all projects, in the default tenant only
+        let all = credential == &Credential::RbacDisabled
+            || (tenant.is_empty()
+                && [Permission::Read, Permission::Admin].into_iter().any(|p| {
+                    self.permission_map
+                        .check_permission(credential, &Resource::Global, p)
+                }));
+        let mut names = vec![];
+        for project in self
+            .entry_points
+            .iter()
+            .filter_map(|&idx| self.graph.node_weight(idx))
+            .filter(|w| w.entity_type == EntityType::Project && w.properties.get_tenant() == tenant)
+        {
+            if all
+                || self.check_permission_in_tenant(
+                    tenant,
+                    credential,
+                    &Resource::Entity(project.id),
+                    Permission::Read,
+                )?
+            {
+                names.push(project.qualified_name.to_owned());
+            }
+        }
+        names.sort();
+        Ok(names)
     }
 
     pub(crate) fn to_entity_resource(&self, r: &Resource) -> Result<Resource, RegistryError> {
         Ok(match &r {
             Resource::NamedEntity(name) => {
                 let id = self.get_entity_id(name)?;
-                let proj_id = self.get_entity_project_id(id)?;
-                Resource::Entity(proj_id)
-            }
-            Resource::Entity(id) => {
-                let proj_id = self.get_entity_project_id(*id)?;
-                Resource::Entity(proj_id)
+                Resource::Entity(self.get_grant_scope(id)?)
             }
+            Resource::Entity(id) => Resource::Entity(self.get_grant_scope(*id)?),
             Resource::Global => Resource::Global,
         })
     }
 
     pub(crate) fn to_named_entity_resource(&self, r: &Resource) -> Result<Resource, RegistryError> {
-        Ok(match &r {
-            Resource::NamedEntity(name) => {
-                let id = self.get_entity_id(name)?;
-                let proj_id = self.get_entity_project_id(id)?;
-                let proj_name = self
-                    .get_entity_by_id(proj_id)
-                    .ok_or_else(|| RegistryError::EntityNotFound(proj_id.to_string()))?
-                    .name;
-                Resource::NamedEntity(proj_name)
-            }
-            Resource::Entity(id) => {
-                let proj_id = self.get_entity_project_id(*id)?;
-                let proj_name = self
-                    .get_entity_by_id(proj_id)
-                    .ok_or_else(|| RegistryError::EntityNotFound(proj_id.to_string()))?
-                    .name;
-                Resource::NamedEntity(proj_name)
+        let to_name = |id: Uuid| -> Result<Resource, RegistryError> {
+            let scope_id = self.get_grant_scope(id)?;
+            let entity = self
+                .get_entity_by_id(scope_id)
+                .ok_or_else(|| RegistryError::EntityNotFound(scope_id.to_string()))?;
+            // Names are only unique within a tenant, grants in named tenants are kept by id
+            if !entity.properties.get_tenant().is_empty() {
+                return Ok(Resource::Entity(scope_id));
             }
-            Resource::Global => Resource::Global,
-        })
+            Ok(Resource::NamedEntity(entity.qualified_name))
+        };
+        match &r {
+            Resource::NamedEntity(name) => to_name(self.get_entity_id(name)?),
+            Resource::Entity(id) => to_name(*id),
+            Resource::Global => Ok(Resource::Global),
+        }
     }
 
     pub(crate) async fn do_grant_permission(&mut self, grant: &RbacRecord) -> Result<(), RegistryError> {
@@ -891,6 +1817,16 @@ mod tests {
             Ok(DummyEntityProp)
         }
 
+        fn new_materialization_record(
+            _definition: &MaterializationRecordDef,
+        ) -> Result<Self, RegistryError> {
+            Ok(DummyEntityProp)
+        }
+
+        fn new_dataset(_definition: &DatasetDef) -> Result<Self, RegistryError> {
+            Ok(DummyEntityProp)
+        }
+
         fn get_version(&self) -> u64 {
             0
         }
@@ -912,6 +1848,15 @@ mod tests {
             Ok(())
         }
 
+        async fn update_entity(
+            &mut self,
+            _id: Uuid,
+            entity: &Entity<DummyEntityProp>,
+        ) -> Result<(), RegistryError> {
+            debug!("Updating entity {}", entity.name);
+            Ok(())
+        }
+
         async fn delete_entity(
             &mut self,
             _id: Uuid,
@@ -1249,6 +2194,122 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn summary() {
+        let r = init().await;
+        let id = r.get_entity_id("project1").unwrap();
+        let summary = r.get_project_summary_by_id(id).unwrap();
+        let features = summary
+            .entity_counts
+            .get(&EntityType::AnchorFeature)
+            .unwrap_or(&0)
+            + summary
+                .entity_counts
+                .get(&EntityType::DerivedFeature)
+                .unwrap_or(&0);
+        assert_eq!(features, r.get_features_by_project("project1").len());
+        assert_eq!(
+            summary.entity_counts.get(&EntityType::Source).copied(),
+            Some(r.get_sources_by_project("project1").len())
+        );
+        assert!(!summary.entity_counts.contains_key(&EntityType::Project));
+    }
+
+    #[tokio::test]
+    async fn cross_project_lineage() {
+        let mut r = init().await;
+        let derived = r.get_entity_id("project1__derived_feature1").unwrap();
+        let shared = r.get_entity_id("project2__anchor_feature2_1").unwrap();
+        r.connect(derived, shared, EdgeType::Consumes)
+            .await
+            .unwrap();
+
+        let (entities, edges) = r.get_project("project1").unwrap();
+        assert!(entities.iter().any(|e| e.id == shared));
+        assert!(edges.contains(&Edge {
+            edge_type: EdgeType::Consumes,
+            from: derived,
+            to: shared,
+        }));
+        assert!(edges.contains(&Edge {
+            edge_type: EdgeType::Produces,
+            from: shared,
+            to: derived,
+        }));
+        // The input still belongs to its own project only
+        assert_eq!(
+            r.get_project_summary_by_id(r.get_entity_id("project1").unwrap())
+                .unwrap()
+                .entity_counts
+                .get(&EntityType::AnchorFeature)
+                .copied(),
+            Some(4)
+        );
+        let (entities, _) = r.get_project("project2").unwrap();
+        assert!(!entities.iter().any(|e| e.id == derived));
+    }
+
+    #[tokio::test]
+    async fn cross_project_lineage_permission() {
+        let mut r = init().await;
+        let derived = r.get_entity_id("project1__derived_feature1").unwrap();
+        let shared = r.get_entity_id("project2__anchor_feature2_1").unwrap();
+        r.connect(derived, shared, EdgeType::Consumes)
+            .await
+            .unwrap();
+        let user = Credential::User("user1".to_string());
+        let grant = |resource: &str| RbacRecord {
+            credential: user.clone(),
+            resource: Resource::NamedEntity(resource.to_string()),
+            permission: Permission::Read,
+            requestor: Credential::RbacDisabled,
+            reason: Default::default(),
+            time: chrono::Utc::now(),
+        };
+        let feature_lineage = |r: &Registry<DummyEntityProp>, credential: &Credential| {
+            let (mut entities, mut edges) = r.bfs(derived, EdgeType::Consumes, None).unwrap();
+            let (down, down_edges) = r.bfs(derived, EdgeType::Produces, None).unwrap();
+            entities.extend(down);
+            edges.extend(down_edges);
+            r.readable_lineage(credential, derived, entities, edges)
+        };
+        let other_project = |entities: &[Entity<DummyEntityProp>]| {
+            entities
+                .iter()
+                .any(|e| e.qualified_name.starts_with("project2"))
+        };
+
+        r.load_permissions(vec![grant("project1")].into_iter())
+            .unwrap();
+        // Inputs of project2 are dropped from the feature lineage
+        let (entities, edges) = feature_lineage(&r, &user);
+        assert!(entities.iter().any(|e| e.id == derived));
+        assert!(entities
+            .iter()
+            .any(|e| e.qualified_name == "project1__anchor_feature1"));
+        assert!(!other_project(&entities));
+        assert!(!edges.iter().any(|e| e.from == shared || e.to == shared));
+        // And from the project lineage
+        let (entities, edges) = r.get_project("project1").unwrap();
+        let (entities, _) =
+            r.readable_lineage(&user, r.get_entity_id("project1").unwrap(), entities, edges);
+        assert!(!other_project(&entities));
+
+        // Without RBAC the input is kept
+        let (entities, _) = feature_lineage(&r, &Credential::RbacDisabled);
+        assert!(entities.iter().any(|e| e.id == shared));
+
+        r.load_permissions(vec![grant("project2")].into_iter())
+            .unwrap();
+        let (entities, edges) = feature_lineage(&r, &user);
+        assert!(entities.iter().any(|e| e.id == shared));
+        assert!(edges.contains(&Edge {
+            edge_type: EdgeType::Consumes,
+            from: derived,
+            to: shared,
+        }));
+    }
+
     #[tokio::test]
     async fn linage() {
         let r = init().await;
@@ -1411,15 +2472,400 @@ mod tests {
         // Now graph should have 3 nodes and 3 edges
 
         // This should fail as source1 is used by anchor1
-        assert!(r.delete_entity_by_id(src1).await.is_err());
+        assert!(r.delete_entity_by_id(src1, None).await.is_err());
 
         // This works
-        r.delete_entity_by_id(an1).await.unwrap();
+        r.delete_entity_by_id(an1, None).await.unwrap();
 
         // Now only edges between project1 and source1 remain
         assert_eq!(r.graph.edge_count(), 2);
     }
 
+    #[tokio::test]
+    async fn project_deletion() {
+        let mut r: Registry<DummyEntityProp> = Registry::new();
+        let prj1 = r
+            .new_entity(EntityType::Project, "project1", "project1", DummyEntityProp)
+            .await
+            .unwrap();
+        let mut children = vec![];
+        for (entity_type, name) in [
+            (EntityType::Source, "source1"),
+            (EntityType::Anchor, "anchor1"),
+            (EntityType::AnchorFeature, "feature1"),
+            (EntityType::DerivedFeature, "derived1"),
+        ] {
+            let id = r
+                .new_entity(
+                    entity_type,
+                    name,
+                    format!("project1__{}", name),
+                    DummyEntityProp,
+                )
+                .await
+                .unwrap();
+            r.connect(prj1, id, EdgeType::Contains).await.unwrap();
+            children.push(id);
+        }
+        let (src1, an1, f1, d1) = (children[0], children[1], children[2], children[3]);
+        r.connect(src1, an1, EdgeType::Produces).await.unwrap();
+        r.connect(an1, f1, EdgeType::Contains).await.unwrap();
+        r.connect(f1, d1, EdgeType::Produces).await.unwrap();
+
+        // The project is not empty
+        assert!(r.delete_project(prj1, false, false, None).await.is_err());
+
+        let ids: Vec<Uuid> = r
+            .delete_project(prj1, true, true, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![d1, f1, an1, src1, prj1]);
+        // Nothing deleted in the dry run
+        assert_eq!(r.graph.edge_count(), 7);
+
+        r.delete_project(prj1, true, false, None).await.unwrap();
+        assert_eq!(r.graph.edge_count(), 0);
+        assert!(r.get_entity(prj1).is_err());
+    }
+
+    #[tokio::test]
+    async fn garbage_collection() {
+        let mut r: Registry<DummyEntityProp> = Registry::new();
+        let prj1 = r
+            .new_entity(EntityType::Project, "project1", "project1", DummyEntityProp)
+            .await
+            .unwrap();
+        let src1 = r
+            .new_entity(
+                EntityType::Source,
+                "source1",
+                "project1__source1",
+                DummyEntityProp,
+            )
+            .await
+            .unwrap();
+        r.connect(prj1, src1, EdgeType::Contains).await.unwrap();
+        r.delete_entity_by_id(src1, Some(Utc::now())).await.unwrap();
+
+        // Deleted within the retention period
+        let report = r
+            .collect_garbage(Utc::now() - chrono::Duration::days(1), false)
+            .await
+            .unwrap();
+        assert!(report.entities.is_empty());
+        assert_eq!(report.retained, 1);
+
+        // Nothing removed in the dry run
+        let before = Utc::now() + chrono::Duration::days(1);
+        let report = r.collect_garbage(before, true).await.unwrap();
+        assert_eq!(report.entities, vec![src1]);
+        assert_eq!(r.graph.node_count(), 2);
+
+        let report = r.collect_garbage(before, false).await.unwrap();
+        assert_eq!(report.entities, vec![src1]);
+        assert_eq!(r.graph.node_count(), 1);
+        assert!(!r.node_id_map.contains_key(&src1));
+        assert!(r.deleted.is_empty());
+        // The remaining node has been moved but is still reachable
+        assert_eq!(r.get_entity(prj1).unwrap().id, prj1);
+    }
+
+    #[tokio::test]
+    async fn scoped_permission() {
+        let mut r = init().await;
+        let user = Credential::User("user1".to_string());
+        let grant = |resource: &str, permission: Permission| RbacRecord {
+            credential: user.clone(),
+            resource: Resource::NamedEntity(resource.to_string()),
+            permission,
+            requestor: Credential::RbacDisabled,
+            reason: Default::default(),
+            time: chrono::Utc::now(),
+        };
+        let check = |r: &Registry<DummyEntityProp>, resource: &str, permission: Permission| {
+            r.check_permission(
+                &user,
+                &Resource::NamedEntity(resource.to_string()),
+                permission,
+            )
+            .unwrap()
+        };
+
+        r.load_permissions(vec![grant("project1__anchor1", Permission::Read)].into_iter())
+            .unwrap();
+        // Anchor grant applies to the anchor and its features only
+        assert!(check(&r, "project1__anchor1", Permission::Read));
+        assert!(check(&r, "project1__anchor_feature1", Permission::Read));
+        assert!(!check(&r, "project1__anchor_feature1", Permission::Write));
+        assert!(!check(&r, "project1__derived_feature1", Permission::Read));
+        assert!(!check(&r, "project1", Permission::Read));
+        assert!(r.visible_projects(&user).unwrap().is_empty());
+
+        r.load_permissions(vec![grant("project1__derived_feature1", Permission::Write)].into_iter())
+            .unwrap();
+        assert!(check(&r, "project1__derived_feature1", Permission::Write));
+        assert!(!check(&r, "project1__derived_feature2", Permission::Write));
+
+        // Project grant is inherited by all anchors and features in the project
+        r.load_permissions(vec![grant("project1", Permission::Admin)].into_iter())
+            .unwrap();
+        assert!(check(&r, "project1__derived_feature2", Permission::Write));
+        assert!(check(&r, "project1__anchor_feature4", Permission::Write));
+        assert!(!check(&r, "project2__anchor_feature2_1", Permission::Read));
+        assert_eq!(
+            r.visible_projects(&user).unwrap(),
+            vec!["project1".to_string()]
+        );
+        let mut all = r.get_project_names().unwrap();
+        all.sort();
+        assert_eq!(r.visible_projects(&Credential::RbacDisabled).unwrap(), all);
+    }
+
+    #[tokio::test]
+    async fn tenant_isolation() {
+        let project = |name: &str| ProjectDef {
+            id: Uuid::new_v4(),
+            qualified_name: name.to_string(),
+            created_by: Default::default(),
+            tags: Default::default(),
+            ownership: Default::default(),
+            default_tags: Default::default(),
+        };
+        let mut r = Registry::<EntityProperty>::new();
+        let (default_id, _) = r.new_project(&project("project1")).await.unwrap();
+
+        // Same name in another tenant is a different project
+        r.switch_tenant("tenant1");
+        let (tenant_id, _) = r.new_project(&project("project1")).await.unwrap();
+        assert_ne!(default_id, tenant_id);
+        assert_eq!(r.get_entity_id("project1").unwrap(), tenant_id);
+        assert!(r.get_entity(default_id).is_err());
+        assert_eq!(r.get_project_names().unwrap(), vec!["project1".to_string()]);
+        r.commit_search_index().await.unwrap();
+        let found: Vec<Uuid> = r
+            .search_entity("project1", Default::default(), None, 10, 0)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(found, vec![tenant_id]);
+
+        r.switch_tenant("");
+        assert_eq!(r.get_entity_id("project1").unwrap(), default_id);
+        assert!(r.get_entity(tenant_id).is_err());
+
+        // Global Admins only administrate the default tenant
+        let admin = Credential::User("admin".to_string());
+        r.load_permissions(
+            vec![RbacRecord {
+                credential: admin.clone(),
+                resource: Resource::Global,
+                permission: Permission::Admin,
+                requestor: Credential::RbacDisabled,
+                reason: Default::default(),
+                time: chrono::Utc::now(),
+            }]
+            .into_iter(),
+        )
+        .unwrap();
+        assert!(r
+            .check_permission(&admin, &Resource::Entity(default_id), Permission::Write)
+            .unwrap());
+        r.switch_tenant("tenant1");
+        assert!(r
+            .check_permission(&admin, &Resource::Entity(default_id), Permission::Write)
+            .is_err());
+        assert!(!r
+            .check_permission(&admin, &Resource::Entity(tenant_id), Permission::Write)
+            .unwrap());
+
+        // Tenants are kept apart after reloading
+        let (entities, edges, permissions) = r.export_content().unwrap();
+        let mut r = Registry::<EntityProperty>::load(
+            entities.into_iter(),
+            edges.into_iter(),
+            permissions.into_iter(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(r.get_entity_id("project1").unwrap(), default_id);
+        r.switch_tenant("tenant1");
+        assert_eq!(r.get_entity_id("project1").unwrap(), tenant_id);
+    }
+
+    #[tokio::test]
+    async fn default_tags() {
+        let tags = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let mut r = Registry::<EntityProperty>::new();
+        let (project_id, _) = r
+            .new_project(&ProjectDef {
+                id: Uuid::new_v4(),
+                qualified_name: "project1".to_string(),
+                created_by: Default::default(),
+                tags: Default::default(),
+                ownership: Default::default(),
+                default_tags: tags(&[("team", "ads"), ("tier", "dev")]),
+            })
+            .await
+            .unwrap();
+        let (source_id, _) = r
+            .new_source(
+                project_id,
+                &SourceDef {
+                    id: Uuid::new_v4(),
+                    name: "source1".to_string(),
+                    qualified_name: "project1__source1".to_string(),
+                    source_type: "hdfs".to_string(),
+                    options: Default::default(),
+                    event_timestamp_column: None,
+                    timestamp_format: None,
+                    preprocessing: None,
+                    created_by: Default::default(),
+                    tags: tags(&[("tier", "prod")]),
+                },
+            )
+            .await
+            .unwrap();
+        // Tags of the entity take precedence
+        assert_eq!(
+            r.get_entity(source_id).unwrap().properties.tags,
+            tags(&[("team", "ads"), ("tier", "prod")])
+        );
+        // The project itself doesn't inherit them
+        assert!(r.get_entity(project_id).unwrap().properties.tags.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_project_cache() {
+        let mut r = load().await;
+        let project = r.get_projects()[0].id;
+        let (entities, edges) = r.get_project_by_id(project).unwrap();
+        assert_eq!(r.project_cache.len(), 1);
+        assert_eq!(r.get_project_by_id(project).unwrap(), (entities, edges));
+
+        // Changing any entity in the project invalidates the cached subgraph
+        let feature = r
+            .get_features()
+            .into_iter()
+            .find(|f| r.get_containing_projects(f.id) == vec![project])
+            .unwrap()
+            .id;
+        r.set_applied_index(42);
+        r.update_properties_by_id(feature, |_| {}).await.unwrap();
+        assert_eq!(r.project_changes.get(&project), Some(&42));
+        assert_eq!(r.project_cache.len(), 0);
+        r.get_project_by_id(project).unwrap();
+        assert_eq!(r.project_cache.len(), 1);
+
+        // Least recently used entry is evicted
+        let cache = ProjectGraphCache::<EntityProperty> {
+            capacity: 2,
+            ..Default::default()
+        };
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        cache.insert((a, 0), Default::default());
+        cache.insert((b, 0), Default::default());
+        assert!(cache.get(&(a, 0)).is_some());
+        cache.insert((c, 0), Default::default());
+        assert!(cache.get(&(a, 0)).is_some());
+        assert!(cache.get(&(b, 0)).is_none());
+        assert!(cache.get(&(c, 0)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_entity_docs() {
+        let mut r = load().await;
+        let feature = r.get_features()[0].id;
+        r.set_entity_docs(feature, "Computed from the **zebrafish** dataset")
+            .await
+            .unwrap();
+        assert_eq!(
+            r.get_entity_by_id(feature).unwrap().properties.docs,
+            "Computed from the **zebrafish** dataset"
+        );
+        // Docs are searchable
+        r.commit_search_index().await.unwrap();
+        let found = r
+            .search_entity("zebrafish", Default::default(), None, 10, 0)
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, feature);
+    }
+
+    #[tokio::test]
+    async fn test_releases() {
+        let mut r = load().await;
+        let project = r.get_projects()[0].id;
+        let def = |name: &str, environment: Option<&str>| ReleaseDef {
+            name: name.to_string(),
+            environment: environment.map(ToString::to_string),
+            created_by: Default::default(),
+        };
+
+        // The latest versions of all features and their upstream
+        let latest = r.create_release(project, &def("r1", None)).await.unwrap();
+        assert_eq!(latest.entities.len(), 13);
+        let (entities, edges) = r.get_release_lineage(project, "r1").unwrap();
+        assert_eq!(entities.len(), 14);
+        assert!(!edges.is_empty());
+        assert!(r.create_release(project, &def("r1", None)).await.is_err());
+        assert!(r
+            .create_release(project, &def("bad name", None))
+            .await
+            .is_err());
+
+        // Only the promoted feature and its upstream are in the environment
+        let feature = r
+            .get_features()
+            .into_iter()
+            .find(|f| f.entity_type == EntityType::AnchorFeature)
+            .unwrap()
+            .id;
+        r.promote_entity(feature, "prod").await.unwrap();
+        assert_eq!(
+            r.get_entity_by_id(feature).unwrap().properties.labels,
+            vec!["prod".to_string()]
+        );
+        let prod = r
+            .create_release(project, &def("r2", Some("prod")))
+            .await
+            .unwrap();
+        let types: HashSet<EntityType> = prod
+            .entities
+            .iter()
+            .map(|id| r.get_entity_by_id(*id).unwrap().entity_type)
+            .collect();
+        assert!(prod.entities.contains(&feature));
+        assert_eq!(
+            types,
+            [
+                EntityType::AnchorFeature,
+                EntityType::Anchor,
+                EntityType::Source
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        // Releases are not affected by later promotions
+        r.demote_entity(feature, "prod").await.unwrap();
+        assert!(r
+            .get_release_entities(project, Some("prod"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(r.get_release(project, "r2").unwrap(), prod);
+        r.delete_release(project, "r2").await.unwrap();
+        assert!(r.get_release(project, "r2").is_err());
+    }
+
     #[tokio::test]
     async fn test_load() {
         let r = load().await;
@@ -1440,6 +2886,178 @@ mod tests {
         println!("{:#?}\n{:#?}", f, e);
     }
 
+    #[tokio::test]
+    async fn test_attribute_index() {
+        let mut r = load().await;
+        let project = r.get_projects()[0].id;
+        let feature = r
+            .get_entities(|w| w.entity_type == EntityType::AnchorFeature)
+            .remove(0);
+        r.update_properties_by_id(feature.id, |p| {
+            p.tags.insert("index_test".to_string(), "1".to_string());
+        })
+        .await
+        .unwrap();
+        let find = |types: &[EntityType], value: Option<&str>| {
+            r.get_entities_by_attributes(
+                &types.iter().copied().collect(),
+                &HashMap::from([("index_test".to_string(), value.map(|v| v.to_string()))]),
+                Some(project),
+            )
+            .into_iter()
+            .map(|e| e.id)
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(find(&[], None), vec![feature.id]);
+        assert_eq!(
+            find(&[EntityType::AnchorFeature], Some("1")),
+            vec![feature.id]
+        );
+        assert!(find(&[EntityType::AnchorFeature], Some("2")).is_empty());
+        assert!(find(&[EntityType::Source], None).is_empty());
+
+        r.update_properties_by_id(feature.id, |p| {
+            p.tags.remove("index_test");
+        })
+        .await
+        .unwrap();
+        assert!(r
+            .attr_index
+            .lookup(&Default::default(), &["index_test"])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "too slow"]
+    async fn filtered_listing() {
+        const ENTITIES: usize = 100_000;
+        let mut r = load().await;
+        r.fts_index.enable(false);
+        let template = r
+            .get_entities(|w| w.entity_type == EntityType::AnchorFeature)
+            .remove(0);
+        let start = Instant::now();
+        for i in 0..ENTITIES {
+            let id = Uuid::new_v4();
+            let mut properties = template.properties.clone();
+            properties.set_guid(id);
+            properties.tags = HashMap::from([("team".to_string(), format!("team{}", i % 100))]);
+            // 1 in 1000 entities is tagged
+            if i % 1000 == 0 {
+                properties
+                    .tags
+                    .insert("bench_pii".to_string(), "true".to_string());
+            }
+            r.insert_entity(
+                id,
+                EntityType::AnchorFeature,
+                format!("feature{}", i),
+                format!("bench__feature{}", i),
+                properties,
+            )
+            .await
+            .unwrap();
+        }
+        println!(
+            "Inserted {} entities in {} ms",
+            ENTITIES,
+            start.elapsed().as_millis()
+        );
+
+        let types = HashSet::from([EntityType::AnchorFeature]);
+        let tags = HashMap::from([("bench_pii".to_string(), Some("true".to_string()))]);
+        let start = Instant::now();
+        let indexed = r.get_entities_by_attributes(&types, &tags, None);
+        let indexed_time = start.elapsed();
+        let start = Instant::now();
+        let scanned = r.get_entities(|w| {
+            w.entity_type == EntityType::AnchorFeature
+                && w.properties.tags.get("bench_pii").map(|v| v.as_str()) == Some("true")
+        });
+        let scanned_time = start.elapsed();
+        assert_eq!(indexed.len(), ENTITIES / 1000);
+        assert_eq!(indexed.len(), scanned.len());
+        println!(
+            "Indexed listing took {} us, full scan took {} us",
+            indexed_time.as_micros(),
+            scanned_time.as_micros()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_policy() {
+        let mut r = load().await;
+        let (entities, edges, permissions) = r.export_content().unwrap();
+        let count = r.graph.node_count();
+
+        // Loading the same content again changes nothing, whatever the policy is
+        let report = r
+            .load_data(entities.clone(), edges, permissions, LoadPolicy::NewVersion)
+            .await
+            .unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.skipped.len(), entities.len());
+        assert!(report
+            .skipped
+            .iter()
+            .all(|e| e.action == LoadAction::Unchanged));
+        assert_eq!(r.graph.node_count(), count);
+
+        let mut changed = entities
+            .into_iter()
+            .find(|e| e.entity_type == EntityType::AnchorFeature)
+            .unwrap();
+        changed
+            .properties
+            .tags
+            .insert("load_test".to_string(), "1".to_string());
+        let report = r
+            .load_data(vec![changed.clone()], vec![], vec![], LoadPolicy::Skip)
+            .await
+            .unwrap();
+        assert_eq!(report.skipped[0].action, LoadAction::Skipped);
+        assert!(!r
+            .get_entity(changed.id)
+            .unwrap()
+            .properties
+            .tags
+            .contains_key("load_test"));
+
+        let report = r
+            .load_data(vec![changed.clone()], vec![], vec![], LoadPolicy::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(report.applied[0].action, LoadAction::Overwritten);
+        assert_eq!(
+            r.get_entity(changed.id).unwrap().properties.tags["load_test"],
+            "1"
+        );
+        assert_eq!(r.graph.node_count(), count);
+
+        changed
+            .properties
+            .tags
+            .insert("load_test".to_string(), "2".to_string());
+        let report = r
+            .load_data(
+                vec![changed.clone()],
+                vec![],
+                vec![],
+                LoadPolicy::NewVersion,
+            )
+            .await
+            .unwrap();
+        let entry = &report.applied[0];
+        assert_eq!(entry.action, LoadAction::NewVersion);
+        assert_ne!(entry.registry_id, changed.id);
+        let latest = r.get_entity_version(&changed.qualified_name, None).unwrap();
+        assert_eq!(latest.id, entry.registry_id);
+        assert_eq!(latest.version, entry.version);
+        assert_eq!(latest.properties.guid, entry.registry_id);
+        assert_eq!(r.graph.node_count(), count + 1);
+    }
+
     #[tokio::test]
     async fn test_dump() {
         let r = load().await;
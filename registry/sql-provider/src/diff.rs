@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display};
+
+use registry_provider::{Edge, Entity, EntityPropMutator, EntityType, RegistryError, ToDocString};
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::Registry;
+
+/**
+ * Identity of an entity in the diff
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffEntity {
+    pub id: Uuid,
+    pub entity_type: EntityType,
+    pub qualified_name: String,
+    pub version: u64,
+}
+
+impl<EntityProp> From<&Entity<EntityProp>> for DiffEntity
+where
+    EntityProp: Clone + Debug + PartialEq + Eq,
+{
+    fn from(e: &Entity<EntityProp>) -> Self {
+        Self {
+            id: e.id,
+            entity_type: e.entity_type,
+            qualified_name: e.qualified_name.to_owned(),
+            version: e.version,
+        }
+    }
+}
+
+/**
+ * Entity with the same id on both sides but different content
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityChange {
+    #[serde(flatten)]
+    pub entity: DiffEntity,
+    /// JSON pointers to the changed fields of the entity
+    pub changed_fields: Vec<String>,
+}
+
+/**
+ * Differences between two registry contents, entities are matched by id and edges by both ends
+ * and the type
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryDiff {
+    pub added_entities: Vec<DiffEntity>,
+    pub removed_entities: Vec<DiffEntity>,
+    pub changed_entities: Vec<EntityChange>,
+    pub added_edges: Vec<Edge>,
+    pub removed_edges: Vec<Edge>,
+}
+
+impl RegistryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_entities.is_empty()
+            && self.removed_entities.is_empty()
+            && self.changed_entities.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+impl Display for RegistryDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entity = |e: &DiffEntity| {
+            format!(
+                "{:?} {} v{} ({})",
+                e.entity_type, e.qualified_name, e.version, e.id
+            )
+        };
+        for e in &self.added_entities {
+            writeln!(f, "+ {}", entity(e))?;
+        }
+        for e in &self.removed_entities {
+            writeln!(f, "- {}", entity(e))?;
+        }
+        for c in &self.changed_entities {
+            writeln!(
+                f,
+                "~ {}: {}",
+                entity(&c.entity),
+                c.changed_fields.join(", ")
+            )?;
+        }
+        for e in &self.added_edges {
+            writeln!(f, "+ {} -[{:?}]-> {}", e.from, e.edge_type, e.to)?;
+        }
+        for e in &self.removed_edges {
+            writeln!(f, "- {} -[{:?}]-> {}", e.from, e.edge_type, e.to)?;
+        }
+        write!(
+            f,
+            "{} added, {} removed, {} changed entities, {} added, {} removed edges",
+            self.added_entities.len(),
+            self.removed_entities.len(),
+            self.changed_entities.len(),
+            self.added_edges.len(),
+            self.removed_edges.len()
+        )
+    }
+}
+
+/**
+ * Compare two registry contents, e.g. exported from two registries or loaded from two backups
+ */
+pub fn diff_content<EntityProp>(
+    old_entities: &[Entity<EntityProp>],
+    old_edges: &[Edge],
+    new_entities: &[Entity<EntityProp>],
+    new_edges: &[Edge],
+) -> RegistryDiff
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + Serialize,
+{
+    let old_map: HashMap<Uuid, &Entity<EntityProp>> =
+        old_entities.iter().map(|e| (e.id, e)).collect();
+    let new_map: HashMap<Uuid, &Entity<EntityProp>> =
+        new_entities.iter().map(|e| (e.id, e)).collect();
+
+    let mut diff = RegistryDiff::default();
+    for e in new_entities {
+        match old_map.get(&e.id) {
+            None => diff.added_entities.push(e.into()),
+            Some(&old) => {
+                let mut changed_fields = vec![];
+                diff_value(
+                    "",
+                    &serde_json::to_value(old).unwrap_or_default(),
+                    &serde_json::to_value(e).unwrap_or_default(),
+                    &mut changed_fields,
+                );
+                if !changed_fields.is_empty() {
+                    diff.changed_entities.push(EntityChange {
+                        entity: e.into(),
+                        changed_fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.removed_entities = old_entities
+        .iter()
+        .filter(|e| !new_map.contains_key(&e.id))
+        .map(|e| e.into())
+        .collect();
+
+    let old_edges: HashSet<&Edge> = old_edges.iter().collect();
+    let new_edges: HashSet<&Edge> = new_edges.iter().collect();
+    diff.added_edges = new_edges
+        .difference(&old_edges)
+        .map(|&e| e.to_owned())
+        .collect();
+    diff.removed_edges = old_edges
+        .difference(&new_edges)
+        .map(|&e| e.to_owned())
+        .collect();
+
+    diff.added_entities.sort_by(compare_entity);
+    diff.removed_entities.sort_by(compare_entity);
+    diff.changed_entities
+        .sort_by(|a, b| compare_entity(&a.entity, &b.entity));
+    diff.added_edges.sort_by_key(edge_key);
+    diff.removed_edges.sort_by_key(edge_key);
+    diff
+}
+
+fn compare_entity(a: &DiffEntity, b: &DiffEntity) -> std::cmp::Ordering {
+    (&a.qualified_name, a.version, a.id).cmp(&(&b.qualified_name, b.version, b.id))
+}
+
+fn edge_key(e: &Edge) -> (Uuid, Uuid, String) {
+    (e.from, e.to, format!("{:?}", e.edge_type))
+}
+
+/**
+ * Collect JSON pointers of the differences, arrays are compared as a whole
+ */
+fn diff_value(path: &str, old: &Value, new: &Value, changed: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+                match (old.get(key), new.get(key)) {
+                    (Some(o), Some(n)) => diff_value(&path, o, n, changed),
+                    _ => changed.push(path),
+                }
+            }
+        }
+        (old, new) if old != new => changed.push(path.to_string()),
+        _ => {}
+    }
+}
+
+impl<EntityProp> Registry<EntityProp>
+where
+    EntityProp:
+        Clone + Debug + PartialEq + Eq + EntityPropMutator + ToDocString + Send + Sync + Serialize,
+{
+    /**
+     * Compare live content of this registry, as the old side, with another registry
+     */
+    pub fn diff(&self, other: &Self) -> Result<RegistryDiff, RegistryError> {
+        let (old_entities, old_edges, _) = self.export_content()?;
+        let (new_entities, new_edges, _) = other.export_content()?;
+        Ok(diff_content(
+            &old_entities,
+            &old_edges,
+            &new_entities,
+            &new_edges,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use registry_provider::EntityProperty;
+
+    use super::*;
+    use crate::mock::load;
+
+    #[tokio::test]
+    async fn test_diff() {
+        let old = load().await;
+        let (entities, edges, _) = old.export_content().unwrap();
+        assert!(diff_content(&entities, &edges, &entities, &edges).is_empty());
+
+        let mut new_entities: Vec<Entity<EntityProperty>> = entities.clone();
+        let removed = new_entities.pop().unwrap();
+        new_entities[0].name = "renamed".to_string();
+        let new_edges: Vec<Edge> = edges
+            .iter()
+            .filter(|e| e.from != removed.id && e.to != removed.id)
+            .cloned()
+            .collect();
+
+        let diff = diff_content(&entities, &edges, &new_entities, &new_edges);
+        assert!(diff.added_entities.is_empty());
+        assert_eq!(diff.removed_entities, vec![DiffEntity::from(&removed)]);
+        assert_eq!(diff.changed_entities.len(), 1);
+        assert_eq!(diff.changed_entities[0].entity.id, new_entities[0].id);
+        assert!(diff.changed_entities[0]
+            .changed_fields
+            .contains(&"/name".to_string()));
+        assert_eq!(diff.removed_edges.len(), edges.len() - new_edges.len());
+        assert!(diff
+            .removed_edges
+            .iter()
+            .all(|e| e.from == removed.id || e.to == removed.id));
+    }
+}
@@ -0,0 +1,377 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use registry_provider::{Embedder, RegistryError, ToDoc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    OnceCell,
+};
+use uuid::Uuid;
+
+/**
+ * Weight of the vector similarity in the blended score, the rest is the normalized FTS score
+ */
+pub(crate) const SEMANTIC_WEIGHT: f32 = 0.7;
+
+const EMBEDDING_BATCH_SIZE: usize = 64;
+
+/**
+ * The embedder is shared by the whole process instead of being owned by the registry,
+ * so it survives the registry being replaced when a Raft snapshot is installed
+ */
+static EMBEDDER: OnceCell<Arc<dyn Embedder>> = OnceCell::const_new();
+
+/**
+ * Docs waiting for the background worker to compute their embeddings
+ */
+static PENDING_DOCS: OnceCell<UnboundedSender<PendingDoc>> = OnceCell::const_new();
+
+/// Index to update, entity id, generation of the request and the doc
+type PendingDoc = (VectorIndex, Uuid, u64, String);
+
+/**
+ * Enable semantic search with the embedder, can only be set once.
+ * Must be called in the Tokio runtime before the registry is loaded, so the embeddings of the
+ * loaded entities are computed in the background right away.
+ */
+pub fn set_embedder<T>(embedder: T) -> Result<(), RegistryError>
+where
+    T: Embedder + 'static,
+{
+    debug!("Semantic search enabled with model {}", embedder.model());
+    let embedder: Arc<dyn Embedder> = Arc::new(embedder);
+    EMBEDDER
+        .set(embedder.clone())
+        .map_err(|_| RegistryError::EmbeddingError("Embedder is already set".to_string()))?;
+    let (sender, receiver) = unbounded_channel();
+    PENDING_DOCS.set(sender).ok();
+    tokio::spawn(embed_pending_docs(embedder, receiver));
+    Ok(())
+}
+
+/**
+ * Embedding of the search query, `None` if semantic search is disabled.
+ * Called before the registry is locked, so a slow embedder doesn't block other requests.
+ */
+pub async fn embed_query(query: &str) -> Result<Option<Vec<f32>>, RegistryError> {
+    let embedder = match EMBEDDER.get() {
+        Some(embedder) => embedder,
+        None => return Ok(None),
+    };
+    embedder
+        .embed(&[query.to_string()])
+        .await?
+        .pop()
+        .map(Some)
+        .ok_or_else(|| RegistryError::EmbeddingError("Empty embedding result".to_string()))
+}
+
+/**
+ * Compute embeddings of the pending docs in batches, failed docs are queued again by the next
+ * search missing them
+ */
+async fn embed_pending_docs(
+    embedder: Arc<dyn Embedder>,
+    mut receiver: UnboundedReceiver<PendingDoc>,
+) {
+    while let Some(doc) = receiver.recv().await {
+        let mut batch = vec![doc];
+        while batch.len() < EMBEDDING_BATCH_SIZE {
+            match receiver.try_recv() {
+                Ok(doc) => batch.push(doc),
+                Err(_) => break,
+            }
+        }
+        debug!("Computing embeddings for {} entities", batch.len());
+        let texts: Vec<String> = batch.iter().map(|(_, _, _, t)| t.to_owned()).collect();
+        match embedder.embed(&texts).await {
+            Ok(vectors) => {
+                for ((index, id, generation, _), v) in batch.into_iter().zip(vectors.into_iter()) {
+                    index.insert(id, generation, Some(v));
+                }
+            }
+            Err(e) => {
+                warn!("Failed to compute embeddings, error: {:?}", e);
+                for (index, id, generation, _) in batch {
+                    index.insert(id, generation, None);
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Text of the entity to compute the embedding from
+ */
+pub(crate) fn embedding_doc<T: ToDoc>(entity: &T) -> String {
+    format!("{}\n{}", entity.get_name(), entity.get_body())
+}
+
+/**
+ * Calls an OpenAI compatible embedding API, e.g. OpenAI, Azure OpenAI or a self-hosted model server
+ */
+#[derive(Clone, Debug)]
+pub struct HttpEmbedder {
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    input: &'a [String],
+    model: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+impl HttpEmbedder {
+    pub fn new(url: &str, model: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            model: model.to_string(),
+            api_key: None,
+            client: Default::default(),
+        }
+    }
+
+    /**
+     * The key is sent in both `Authorization` and `api-key` headers, so it works with OpenAI and Azure OpenAI
+     */
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl Embedder for HttpEmbedder {
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, RegistryError> {
+        let mut req = self.client.post(&self.url).json(&EmbeddingRequest {
+            input: texts,
+            model: &self.model,
+        });
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key).header("api-key", key);
+        }
+        let resp = req
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| RegistryError::EmbeddingError(format!("Embedding API failed, {}", e)))?;
+        let mut data = resp
+            .json::<EmbeddingResponse>()
+            .await
+            .map_err(|e| {
+                RegistryError::EmbeddingError(format!("Invalid embedding response, {}", e))
+            })?
+            .data;
+        if data.len() != texts.len() {
+            return Err(RegistryError::EmbeddingError(format!(
+                "Expect {} embeddings, got {}",
+                texts.len(),
+                data.len()
+            )));
+        }
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Vectors {
+    vectors: HashMap<Uuid, Vec<f32>>,
+    /// Generation of the latest request of each entity queued for the background worker, results
+    /// of outdated requests are dropped
+    pending: HashMap<Uuid, u64>,
+    generation: u64,
+}
+
+/**
+ * Cached embeddings of entity docs, computed by a background worker when the entity is indexed,
+ * so the embedder is never called with the registry locked.
+ * The cache is shared with the worker, entries are invalidated when the entity is re-indexed.
+ */
+#[derive(Clone, Debug, Default)]
+pub(crate) struct VectorIndex {
+    inner: Arc<RwLock<Vectors>>,
+}
+
+impl VectorIndex {
+    /**
+     * The doc of the entity has changed, compute the embedding again in the background
+     */
+    pub fn update(&self, id: Uuid, doc: String) {
+        self.remove(id);
+        self.queue(id, doc);
+    }
+
+    /**
+     * Compute the embedding in the background if it's not cached or queued yet, no-op if semantic
+     * search is disabled
+     */
+    pub fn queue(&self, id: Uuid, doc: String) {
+        let sender = match PENDING_DOCS.get() {
+            Some(sender) => sender,
+            None => return,
+        };
+        let generation = match self.inner.write() {
+            Ok(mut inner) => {
+                if inner.vectors.contains_key(&id) || inner.pending.contains_key(&id) {
+                    return;
+                }
+                inner.generation += 1;
+                let generation = inner.generation;
+                inner.pending.insert(id, generation);
+                generation
+            }
+            Err(_) => return,
+        };
+        sender.send((self.clone(), id, generation, doc)).ok();
+    }
+
+    pub fn remove(&self, id: Uuid) {
+        if let Ok(mut inner) = self.inner.write() {
+            inner.vectors.remove(&id);
+            inner.pending.remove(&id);
+        }
+    }
+
+    pub fn similarity(&self, id: Uuid, query: &[f32]) -> Option<f32> {
+        let inner = self.inner.read().ok()?;
+        inner.vectors.get(&id).map(|v| cosine(v, query))
+    }
+
+    /**
+     * Result of the background worker, `None` if the embedder failed
+     */
+    fn insert(&self, id: Uuid, generation: u64, vector: Option<Vec<f32>>) {
+        if let Ok(mut inner) = self.inner.write() {
+            // The entity has been removed or re-indexed while the embedding was being computed
+            if inner.pending.get(&id) != Some(&generation) {
+                return;
+            }
+            inner.pending.remove(&id);
+            if let Some(vector) = vector {
+                inner.vectors.insert(id, vector);
+            }
+        }
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/**
+ * Blend vector similarities with FTS scores, FTS scores are normalized by the max score.
+ * Returns ids ordered by the blended score, highest first.
+ */
+pub(crate) fn blend_scores(
+    similarities: HashMap<Uuid, f32>,
+    keyword_scores: HashMap<Uuid, f32>,
+    weight: f32,
+) -> Vec<(Uuid, f32)> {
+    let max_keyword_score = keyword_scores.values().copied().fold(0.0, f32::max);
+    let mut ret: Vec<(Uuid, f32)> = similarities
+        .into_iter()
+        .map(|(id, sim)| {
+            let keyword_score = if max_keyword_score > 0.0 {
+                keyword_scores.get(&id).copied().unwrap_or_default() / max_keyword_score
+            } else {
+                0.0
+            };
+            (id, weight * sim + (1.0 - weight) * keyword_score)
+        })
+        .collect();
+    ret.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine() {
+        assert!((cosine(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert_eq!(cosine(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+        // Vectors from different models are not comparable
+        assert_eq!(cosine(&[1.0, 0.0], &[1.0, 0.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_outdated_embeddings() {
+        let index = VectorIndex::default();
+        let id = Uuid::new_v4();
+        index.inner.write().unwrap().pending.insert(id, 1);
+        // Re-indexed while the first embedding is being computed
+        index.remove(id);
+        index.inner.write().unwrap().pending.insert(id, 2);
+        index.insert(id, 1, Some(vec![1.0, 0.0]));
+        assert_eq!(index.similarity(id, &[1.0, 0.0]), None);
+        index.insert(id, 2, Some(vec![0.0, 1.0]));
+        assert!(index.similarity(id, &[0.0, 1.0]).unwrap() > 0.99);
+        // Failed embeddings can be queued again
+        index.inner.write().unwrap().pending.insert(id, 3);
+        index.insert(id, 3, None);
+        assert!(index.inner.read().unwrap().pending.is_empty());
+    }
+
+    #[test]
+    fn test_blend_scores() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let similarities: HashMap<Uuid, f32> = [(a, 0.9), (b, 0.5), (c, 0.6)].into_iter().collect();
+        // `b` is the best keyword match
+        let keyword_scores: HashMap<Uuid, f32> = [(b, 4.0), (c, 1.0)].into_iter().collect();
+
+        let ids: Vec<Uuid> = blend_scores(similarities.clone(), keyword_scores.clone(), 1.0)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![a, c, b]);
+
+        let ids: Vec<Uuid> = blend_scores(similarities.clone(), keyword_scores.clone(), 0.5)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![b, a, c]);
+
+        // No keyword match at all
+        let ids: Vec<Uuid> = blend_scores(similarities, Default::default(), SEMANTIC_WEIGHT)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![a, c, b]);
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::HashSet;
+use std::fmt::{Debug, Display};
+
+use log::{debug, info};
+use petgraph::visit::EdgeRef;
+use registry_provider::{EdgeType, EntityPropMutator, EntityType, ToDocString};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Registry;
+
+/**
+ * A violated graph invariant found by `Registry::fsck`
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum FsckIssue {
+    /// The id map points to a node with a different id or to nothing
+    DanglingId { id: Uuid },
+    /// The node is not reachable from the id map
+    UnindexedNode { id: Uuid },
+    /// The name map points to an id not in the registry
+    DanglingName {
+        tenant: String,
+        qualified_name: String,
+        version: u64,
+        id: Uuid,
+    },
+    /// The edge connects a deleted entity, or its endpoints don't match the ids in the edge
+    DanglingEdge {
+        from: Uuid,
+        to: Uuid,
+        edge_type: EdgeType,
+    },
+    /// The reflection of an existing edge is missing, e.g. `Contains` without `BelongsTo`
+    MissingReflection {
+        from: Uuid,
+        to: Uuid,
+        edge_type: EdgeType,
+    },
+    /// An anchor must consume exactly one source
+    AnchorSourceCount { anchor: Uuid, sources: usize },
+    /// Versions of an entity must be 1, 2, 3...
+    VersionGap {
+        tenant: String,
+        qualified_name: String,
+        versions: Vec<u64>,
+    },
+}
+
+impl FsckIssue {
+    /**
+     * Issues that can be fixed without guessing the intended content
+     */
+    pub fn is_repairable(&self) -> bool {
+        !matches!(
+            self,
+            FsckIssue::AnchorSourceCount { .. } | FsckIssue::VersionGap { .. }
+        )
+    }
+}
+
+impl Display for FsckIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FsckIssue::DanglingId { id } => write!(f, "Id `{}` points to a missing node", id),
+            FsckIssue::UnindexedNode { id } => write!(f, "Entity `{}` is not in the id map", id),
+            FsckIssue::DanglingName {
+                tenant,
+                qualified_name,
+                version,
+                id,
+            } => write!(
+                f,
+                "Name `{}` version {} in tenant `{}` points to missing entity `{}`",
+                qualified_name, version, tenant, id
+            ),
+            FsckIssue::DanglingEdge {
+                from,
+                to,
+                edge_type,
+            } => write!(f, "Edge `{}` {:?} `{}` is dangling", from, edge_type, to),
+            FsckIssue::MissingReflection {
+                from,
+                to,
+                edge_type,
+            } => write!(f, "Edge `{}` {:?} `{}` is missing", from, edge_type, to),
+            FsckIssue::AnchorSourceCount { anchor, sources } => write!(
+                f,
+                "Anchor `{}` consumes {} sources instead of 1",
+                anchor, sources
+            ),
+            FsckIssue::VersionGap {
+                tenant,
+                qualified_name,
+                versions,
+            } => write!(
+                f,
+                "Versions of `{}` in tenant `{}` are not contiguous, {:?}",
+                qualified_name, tenant, versions
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsckReport {
+    pub issues: Vec<FsckIssue>,
+    pub repaired: usize,
+}
+
+impl<EntityProp> Registry<EntityProp>
+where
+    EntityProp: Clone + Debug + PartialEq + Eq + EntityPropMutator + ToDocString + Send + Sync,
+{
+    /**
+     * Validate the graph invariants, issues are returned in the order they should be repaired
+     */
+    pub fn fsck(&self) -> Vec<FsckIssue> {
+        let mut issues = vec![];
+
+        // Id map
+        for (&id, &idx) in &self.node_id_map {
+            if self.graph.node_weight(idx).map(|w| w.id) != Some(id) {
+                issues.push(FsckIssue::DanglingId { id });
+            }
+        }
+        for idx in self.graph.node_indices() {
+            let id = self.graph[idx].id;
+            if self.node_id_map.get(&id) != Some(&idx) {
+                issues.push(FsckIssue::UnindexedNode { id });
+            }
+        }
+
+        // Name map
+        let ids: HashSet<Uuid> = self.graph.node_weights().map(|w| w.id).collect();
+        for (tenant, names) in &self.name_id_map {
+            for (qualified_name, versions) in names {
+                for (&version, &id) in versions {
+                    if !ids.contains(&id) {
+                        issues.push(FsckIssue::DanglingName {
+                            tenant: tenant.to_owned(),
+                            qualified_name: qualified_name.to_owned(),
+                            version,
+                            id,
+                        });
+                    }
+                }
+                if !versions.keys().copied().eq(1..=versions.len() as u64) {
+                    issues.push(FsckIssue::VersionGap {
+                        tenant: tenant.to_owned(),
+                        qualified_name: qualified_name.to_owned(),
+                        versions: versions.keys().copied().collect(),
+                    });
+                }
+            }
+        }
+
+        // Edges
+        for e in self.graph.edge_references() {
+            let w = e.weight();
+            let (from, to) = (&self.graph[e.source()], &self.graph[e.target()]);
+            if from.id != w.from
+                || to.id != w.to
+                || self.deleted.contains(&w.from)
+                || self.deleted.contains(&w.to)
+            {
+                issues.push(FsckIssue::DanglingEdge {
+                    from: w.from,
+                    to: w.to,
+                    edge_type: w.edge_type,
+                });
+            } else if !self
+                .graph
+                .edges_connecting(e.target(), e.source())
+                .any(|r| r.weight().edge_type == w.edge_type.reflection())
+            {
+                issues.push(FsckIssue::MissingReflection {
+                    from: w.to,
+                    to: w.from,
+                    edge_type: w.edge_type.reflection(),
+                });
+            }
+        }
+
+        // Anchors
+        for idx in self.graph.node_indices() {
+            let w = &self.graph[idx];
+            if w.entity_type != EntityType::Anchor || self.deleted.contains(&w.id) {
+                continue;
+            }
+            let sources = self
+                .get_neighbors_idx(idx, |e| e.edge_type == EdgeType::Consumes)
+                .into_iter()
+                .filter(|&i| {
+                    self.graph[i].entity_type == EntityType::Source
+                        && !self.deleted.contains(&self.graph[i].id)
+                })
+                .count();
+            if sources != 1 {
+                issues.push(FsckIssue::AnchorSourceCount {
+                    anchor: w.id,
+                    sources,
+                });
+            }
+        }
+        issues
+    }
+
+    /**
+     * Fix repairable issues found by `fsck`, returns the number of issues fixed.
+     * Only the in-memory graph is changed, neither the external storage nor the FTS index.
+     */
+    pub fn repair(&mut self, issues: &[FsckIssue]) -> usize {
+        let mut repaired = 0;
+        for issue in issues.iter().filter(|i| i.is_repairable()) {
+            debug!("Repairing: {}", issue);
+            match issue {
+                FsckIssue::DanglingId { id } => {
+                    self.node_id_map.remove(id);
+                }
+                FsckIssue::UnindexedNode { id } => {
+                    if let Some(idx) = self.graph.node_indices().find(|&i| self.graph[i].id == *id)
+                    {
+                        self.node_id_map.insert(*id, idx);
+                        if self.graph[idx].entity_type.is_entry_point()
+                            && !self.entry_points.contains(&idx)
+                        {
+                            self.entry_points.push(idx);
+                        }
+                    }
+                }
+                FsckIssue::DanglingName {
+                    tenant,
+                    qualified_name,
+                    version,
+                    ..
+                } => {
+                    if let Some(names) = self.name_id_map.get_mut(tenant) {
+                        if let Some(versions) = names.get_mut(qualified_name) {
+                            versions.remove(version);
+                            if versions.is_empty() {
+                                names.remove(qualified_name);
+                            }
+                        }
+                    }
+                }
+                FsckIssue::DanglingEdge {
+                    from,
+                    to,
+                    edge_type,
+                } => {
+                    self.graph.retain_edges(|g, e| {
+                        !(g[e].from == *from && g[e].to == *to && g[e].edge_type == *edge_type)
+                    });
+                }
+                FsckIssue::MissingReflection {
+                    from,
+                    to,
+                    edge_type,
+                } => {
+                    if let (Ok(from_idx), Ok(to_idx)) = (self.get_idx(*from), self.get_idx(*to)) {
+                        if !self
+                            .graph
+                            .edges_connecting(from_idx, to_idx)
+                            .any(|e| e.weight().edge_type == *edge_type)
+                        {
+                            self.graph.add_edge(
+                                from_idx,
+                                to_idx,
+                                registry_provider::Edge {
+                                    from: *from,
+                                    to: *to,
+                                    edge_type: *edge_type,
+                                },
+                            );
+                        }
+                    }
+                }
+                _ => continue,
+            }
+            repaired += 1;
+        }
+        if repaired > 0 {
+            // Repairs bypass the change tracking, drop all cached project subgraphs
+            self.project_cache.clear();
+        }
+        info!("Repaired {} of {} issues", repaired, issues.len());
+        repaired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::load;
+
+    #[tokio::test]
+    async fn test_fsck() {
+        let mut r = load().await;
+        let baseline = r.fsck();
+        let project = r.get_projects()[0].id;
+        let project_idx = r.get_idx(project).unwrap();
+
+        // Remove one `BelongsTo` edge to the project and add a dangling name
+        let edge = r
+            .graph
+            .edges_directed(project_idx, petgraph::Direction::Incoming)
+            .find(|e| e.weight().edge_type == EdgeType::BelongsTo)
+            .map(|e| e.id())
+            .unwrap();
+        let child = r.graph[edge].from;
+        r.graph.remove_edge(edge);
+        let ghost = Uuid::new_v4();
+        r.name_id_map
+            .entry(Default::default())
+            .or_default()
+            .entry("ghost".to_string())
+            .or_default()
+            .insert(1, ghost);
+
+        let issues = r.fsck();
+        assert!(issues.contains(&FsckIssue::MissingReflection {
+            from: child,
+            to: project,
+            edge_type: EdgeType::BelongsTo,
+        }));
+        assert!(issues.contains(&FsckIssue::DanglingName {
+            tenant: Default::default(),
+            qualified_name: "ghost".to_string(),
+            version: 1,
+            id: ghost,
+        }));
+
+        r.repair(&issues);
+        assert!(r.has_connection_type(child, project, EdgeType::BelongsTo));
+        assert_eq!(
+            r.fsck().into_iter().filter(|i| i.is_repairable()).count(),
+            0
+        );
+        // Unrepairable issues in the sample data are left as is
+        assert_eq!(
+            r.fsck()
+                .into_iter()
+                .filter(|i| !i.is_repairable())
+                .collect::<HashSet<_>>(),
+            baseline
+                .into_iter()
+                .filter(|i| !i.is_repairable())
+                .collect::<HashSet<_>>()
+        );
+    }
+}
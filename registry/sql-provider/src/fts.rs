@@ -1,21 +1,187 @@
-use std::{collections::HashSet, fmt::Debug};
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::HashSet,
+    fmt::{Debug, Display},
+    ops::Bound,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
-use log::{debug, warn};
+use chrono::Utc;
+use log::{debug, error, warn};
 use regex::Regex;
 use registry_provider::*;
 use tantivy::{
-    collector::TopDocs,
+    collector::{DocSetCollector, TopDocs},
     doc,
-    query::{BooleanQuery, Query, QueryParser, TermQuery},
+    fastfield::FastFieldReader,
+    query::{BooleanQuery, Query, QueryClone, QueryParser, RangeQuery, TermQuery},
     schema::{
         Cardinality, Field, IndexRecordOption, NumericOptions, Schema, TextFieldIndexing, STRING,
         TEXT,
     },
-    Index, IndexReader, IndexWriter, ReloadPolicy, Term,
+    DocAddress, DocId, Index, IndexReader, IndexWriter, ReloadPolicy, Score, Searcher,
+    SegmentReader, Term,
 };
 use thiserror::Error;
+use tokio::sync::{oneshot, OnceCell};
 use uuid::Uuid;
 
+/**
+ * Tantivy rejects writers with tiny memory budgets
+ */
+const MIN_MEMORY_BUDGET: usize = 15_000_000;
+
+/**
+ * At most this many exact matches are pinned on top of the results
+ */
+const MAX_PINNED: usize = 10;
+
+/**
+ * Boosts of the matches in each field, e.g. a match in the name weighs 4 times of a match in the
+ * docs by default
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FieldBoosts {
+    pub name: f32,
+    pub qualified_name: f32,
+    pub tags: f32,
+    pub docs: f32,
+}
+
+impl Default for FieldBoosts {
+    fn default() -> Self {
+        Self {
+            name: 4.0,
+            qualified_name: 2.0,
+            tags: 1.5,
+            docs: 1.0,
+        }
+    }
+}
+
+/**
+ * Comma separated list of `field=boost`, e.g. `name=5,tags=2`, omitted fields keep the default
+ */
+impl FromStr for FieldBoosts {
+    type Err = RegistryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            RegistryError::InvalidDefinition(format!(
+                "Invalid field boosts `{}`, must be a list of `field=boost`, fields are `name`, `qualified_name`, `tags` and `docs`",
+                s
+            ))
+        };
+        let mut boosts = Self::default();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (field, boost) = item.split_once('=').ok_or_else(invalid)?;
+            let boost: f32 = boost
+                .trim()
+                .parse()
+                .ok()
+                .filter(|b: &f32| b.is_finite() && *b >= 0.0)
+                .ok_or_else(invalid)?;
+            match field.trim().to_lowercase().replace('-', "_").as_str() {
+                "name" => boosts.name = boost,
+                "qualified_name" => boosts.qualified_name = boost,
+                "tags" => boosts.tags = boost,
+                "docs" => boosts.docs = boost,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(boosts)
+    }
+}
+
+impl Display for FieldBoosts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "name={},qualified_name={},tags={},docs={}",
+            self.name, self.qualified_name, self.tags, self.docs
+        )
+    }
+}
+
+/**
+ * Relevance ranking of `FtsIndex::search`
+ */
+#[derive(Clone, Debug)]
+pub struct RankingConfig {
+    pub field_boosts: FieldBoosts,
+    /**
+     * A just modified entity scores up to `1 + recency_boost` times of an old one with the same
+     * relevance, 0 disables the recency boost
+     */
+    pub recency_boost: f32,
+    /**
+     * The recency boost halves every `recency_half_life` since the last modification
+     */
+    pub recency_half_life: Duration,
+    /**
+     * List entities whose name or qualified name equals the query first
+     */
+    pub pin_exact_match: bool,
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            field_boosts: Default::default(),
+            recency_boost: 0.5,
+            recency_half_life: Duration::from_secs(30 * 24 * 3600),
+            pin_exact_match: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FtsConfig {
+    /**
+     * Pending documents are committed in the background at this interval
+     */
+    pub commit_interval: Duration,
+    /**
+     * Memory budget of the in-memory segment, the writer flushes to a new segment when it's exceeded
+     */
+    pub memory_budget: usize,
+    /**
+     * Keep the index on disk under this directory instead of in memory
+     */
+    pub index_dir: Option<PathBuf>,
+    pub ranking: RankingConfig,
+}
+
+impl Default for FtsConfig {
+    fn default() -> Self {
+        Self {
+            commit_interval: Duration::from_secs(1),
+            memory_budget: 50_000_000,
+            index_dir: None,
+            ranking: Default::default(),
+        }
+    }
+}
+
+static FTS_CONFIG: OnceCell<FtsConfig> = OnceCell::const_new();
+
+/**
+ * Configure all FTS indices created afterwards, can only be set once
+ */
+pub fn set_fts_config(config: FtsConfig) -> Result<(), RegistryError> {
+    debug!("FTS index config: {:?}", config);
+    FTS_CONFIG
+        .set(config)
+        .map_err(|_| RegistryError::FtsError("FTS config is already set".to_string()))
+}
+
 /**
  * HACK: Tantivy doesn't support sorting by string field.
  * This function converts the first 12 alphanumerical characters to a number so that it can be sorted.
@@ -47,6 +213,69 @@ fn str_score(s: &str) -> u64 {
     u64::MAX - ret
 }
 
+/**
+ * Raw string fields need a non-empty term, the default tenant has an empty name
+ */
+fn tenant_text(tenant: &str) -> &str {
+    if tenant.is_empty() {
+        "-"
+    } else {
+        tenant
+    }
+}
+
+/**
+ * Position of the last document in a page of search results.
+ * Results are ordered by the name score then the id, so documents with the same name never get
+ * skipped or repeated across pages, even if the index is changed in between.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchCursor {
+    score: u64,
+    id: Uuid,
+}
+
+impl Ord for SearchCursor {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        // Higher score comes first, same as `TopDocs::order_by_u64_field`
+        other
+            .score
+            .cmp(&self.score)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for SearchCursor {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/**
+ * Cursors are opaque tokens to the clients, 16 hex digits of the score followed by the id
+ */
+impl Display for SearchCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}{}", self.score, self.id.simple())
+    }
+}
+
+impl FromStr for SearchCursor {
+    type Err = RegistryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RegistryError::InvalidDefinition(format!("Invalid search cursor `{}`", s));
+        if s.len() != 48 || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let (score, id) = s.split_at(16);
+        Ok(Self {
+            score: u64::from_str_radix(score, 16).map_err(|_| invalid())?,
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum FtsError {
     #[error(transparent)]
@@ -54,21 +283,99 @@ pub enum FtsError {
 
     #[error(transparent)]
     QueryParseError(#[from] tantivy::query::QueryParserError),
+
+    #[error("FTS index writer is gone")]
+    WriterGone,
+}
+
+/**
+ * On-disk index directory, removed with the index as the index is always rebuilt on startup
+ */
+struct IndexDir(PathBuf);
+
+impl Drop for IndexDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            warn!(
+                "Failed to remove FTS index dir {:?}, error: {:?}",
+                self.0, e
+            );
+        }
+    }
+}
+
+/**
+ * The writer and the reader are shared with the background commit thread
+ */
+#[derive(Clone)]
+struct Committer {
+    writer: Arc<Mutex<IndexWriter>>,
+    reader: IndexReader,
+    // Number of documents added since the last commit
+    pending: Arc<AtomicUsize>,
+}
+
+impl Committer {
+    fn commit(&self) -> Result<(), FtsError> {
+        if self.pending.load(Ordering::SeqCst) == 0 {
+            return Ok(());
+        }
+        let mut writer = self.writer.lock().map_err(|_| FtsError::WriterGone)?;
+        let count = self.pending.swap(0, Ordering::SeqCst);
+        writer.commit()?;
+        // Don't wait for the `OnCommit` policy, searches right after this call must see the documents
+        self.reader.reload()?;
+        debug!("Committed {} documents into FTS index", count);
+        Ok(())
+    }
+
+    fn run(self, interval: Duration, requests: Receiver<oneshot::Sender<Result<(), FtsError>>>) {
+        loop {
+            let waiter = match requests.recv_timeout(interval) {
+                Ok(waiter) => Some(waiter),
+                Err(RecvTimeoutError::Timeout) => None,
+                // The index has been dropped
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            let ret = self.commit();
+            match waiter {
+                Some(waiter) => {
+                    waiter.send(ret).ok();
+                }
+                None => {
+                    if let Err(e) = ret {
+                        error!("Failed to commit FTS index, error: {:?}", e);
+                    }
+                }
+            }
+        }
+        debug!("FTS commit thread exited");
+    }
 }
 
 pub struct FtsIndex {
     _schema: Schema,
-    reader: IndexReader,
-    writer: Option<IndexWriter>,
+    committer: Committer,
+    commit_requests: Mutex<Sender<oneshot::Sender<Result<(), FtsError>>>>,
     index: Index,
     name_field: Field,
+    qualified_name_field: Field,
     id_field: Field,
     scopes_field: Field,
     type_field: Field,
     body_field: Field,
+    tags_field: Field,
+    // Lowercased name and qualified name for exact matching
+    exact_name_field: Field,
     name_score_field: Field,
+    version_field: Field,
+    modified_field: Field,
+    tenant_field: Field,
+    ranking: RankingConfig,
     enabled: bool,
     cleaner: Regex,
+    // Must be dropped after the index
+    _dir: Option<IndexDir>,
 }
 
 impl Debug for FtsIndex {
@@ -82,7 +389,10 @@ impl Debug for FtsIndex {
             .field("type_field", &self.type_field)
             .field("body_field", &self.body_field)
             .field("name_score_field", &self.body_field)
+            .field("tenant_field", &self.tenant_field)
+            .field("ranking", &self.ranking)
             .field("enabled", &self.enabled)
+            .field("pending", &self.committer.pending)
             .finish()
     }
 }
@@ -94,45 +404,111 @@ impl FtsIndex {
             .set_index_option(IndexRecordOption::WithFreqsAndPositions);
         let mut schema_builder = Schema::builder();
         schema_builder.add_text_field("name", TEXT.set_indexing_options(indexing_option.clone()));
+        schema_builder.add_text_field(
+            "qualified_name",
+            TEXT.set_indexing_options(indexing_option.clone()),
+        );
         schema_builder.add_text_field("id", STRING.set_stored());
         schema_builder.add_text_field(
             "scopes",
             TEXT.set_indexing_options(indexing_option.clone().set_tokenizer("whitespace")),
         );
         schema_builder.add_text_field("type", STRING);
+        schema_builder.add_text_field("tags", TEXT.set_indexing_options(indexing_option.clone()));
         schema_builder.add_text_field("body", TEXT.set_indexing_options(indexing_option));
+        schema_builder.add_text_field("exact_name", STRING);
         schema_builder.add_u64_field(
             "name_score",
+            NumericOptions::default()
+                .set_fast(Cardinality::SingleValue)
+                .set_indexed(),
+        );
+        schema_builder.add_u64_field(
+            "version",
             NumericOptions::default().set_fast(Cardinality::SingleValue),
         );
+        schema_builder.add_i64_field(
+            "modified",
+            NumericOptions::default().set_fast(Cardinality::SingleValue),
+        );
+        schema_builder.add_text_field("tenant", STRING);
         let schema = schema_builder.build();
         let name_field = schema.get_field("name").unwrap();
+        let qualified_name_field = schema.get_field("qualified_name").unwrap();
         let id_field = schema.get_field("id").unwrap();
         let scopes_field = schema.get_field("scopes").unwrap();
         let type_field = schema.get_field("type").unwrap();
         let body_field = schema.get_field("body").unwrap();
+        let tags_field = schema.get_field("tags").unwrap();
+        let exact_name_field = schema.get_field("exact_name").unwrap();
         let name_score_field = schema.get_field("name_score").unwrap();
-        let index = Index::create_in_ram(schema.clone());
-        Self {
-            _schema: schema,
+        let version_field = schema.get_field("version").unwrap();
+        let modified_field = schema.get_field("modified").unwrap();
+        let tenant_field = schema.get_field("tenant").unwrap();
+        let config = FTS_CONFIG.get().cloned().unwrap_or_default();
+        let (index, dir) = match &config.index_dir {
+            Some(base) => {
+                // Each registry instance owns a separate directory
+                let path = base.join(Uuid::new_v4().to_string());
+                match std::fs::create_dir_all(&path)
+                    .map_err(tantivy::TantivyError::from)
+                    .and_then(|_| Index::create_in_dir(&path, schema.clone()))
+                {
+                    Ok(index) => (index, Some(IndexDir(path))),
+                    Err(e) => {
+                        warn!(
+                            "Failed to create FTS index in {:?}, fallback to memory, error: {:?}",
+                            path, e
+                        );
+                        (Index::create_in_ram(schema.clone()), None)
+                    }
+                }
+            }
+            None => (Index::create_in_ram(schema.clone()), None),
+        };
+        let committer = Committer {
+            writer: Arc::new(Mutex::new(
+                index
+                    .writer_with_num_threads(1, config.memory_budget.max(MIN_MEMORY_BUDGET))
+                    .unwrap(),
+            )),
             reader: index
                 .reader_builder()
                 .reload_policy(ReloadPolicy::OnCommit)
                 .try_into()
                 .unwrap(),
-            writer: None,
+            pending: Default::default(),
+        };
+        let (tx, rx) = mpsc::channel();
+        let background = committer.clone();
+        std::thread::Builder::new()
+            .name("fts-commit".to_string())
+            .spawn(move || background.run(config.commit_interval, rx))
+            .unwrap();
+        Self {
+            _schema: schema,
+            committer,
+            commit_requests: Mutex::new(tx),
             index,
             name_field,
+            qualified_name_field,
             id_field,
             scopes_field,
             type_field,
             body_field,
+            tags_field,
+            exact_name_field,
             name_score_field,
+            version_field,
+            modified_field,
+            tenant_field,
+            ranking: config.ranking,
             enabled: true,
             cleaner: Regex::new(
                 r"([:+\(\)\[\]\{\}])|(\s[aA][nN][dD]\s)|(\s[oO][rR]\s)|(\s[tT][oO]\s)",
             )
             .unwrap(),
+            _dir: dir,
         }
     }
 
@@ -141,57 +517,104 @@ impl FtsIndex {
         self.enabled = enabled;
     }
 
-    pub fn add_doc<T: ToDoc>(&mut self, d: &T, scopes: Vec<String>) -> Result<(), FtsError> {
-        if self.writer.is_none() {
-            self.writer = Some(self.index.writer(30_000_000).unwrap());
-        }
-        let doc = doc!(
+    /**
+     * Add the document into the in-memory segment, it's searchable after the next commit
+     */
+    pub fn add_doc<T: ToDoc>(
+        &mut self,
+        d: &T,
+        scopes: Vec<String>,
+        tenant: &str,
+    ) -> Result<(), FtsError> {
+        let mut doc = doc!(
             self.name_field => d.get_name(),
+            self.qualified_name_field => d.get_qualified_name(),
             self.id_field => d.get_id(),
             self.scopes_field => scopes.join(" "),
             self.type_field => d.get_type(),
             self.body_field => d.get_body(),
+            self.tags_field => d.get_tags(),
             self.name_score_field => str_score(&d.get_name()),
+            self.version_field => d.get_version(),
+            self.modified_field => d.get_modified().map(|t| t.timestamp()).unwrap_or_default(),
+            self.tenant_field => tenant_text(tenant),
         );
-        self.writer.as_ref().unwrap().add_document(doc)?;
+        // The first line is the original name, the rest are the processed ones
+        for name in [d.get_name(), d.get_qualified_name()] {
+            if let Some(name) = name.lines().next().filter(|n| !n.is_empty()) {
+                doc.add_text(self.exact_name_field, name.to_lowercase());
+            }
+        }
+        let writer = self
+            .committer
+            .writer
+            .lock()
+            .map_err(|_| FtsError::WriterGone)?;
+        writer.add_document(doc)?;
+        self.committer.pending.fetch_add(1, Ordering::SeqCst);
         Ok(())
     }
 
-    pub fn commit(&mut self) -> Result<(), FtsError> {
-        if let Some(writer) = &mut self.writer {
-            writer.commit()?;
+    /**
+     * Commit pending documents in the current thread, used after bulk loads
+     */
+    pub fn commit_blocking(&self) -> Result<(), FtsError> {
+        self.committer.commit()
+    }
+
+    /**
+     * Ask the background thread to commit pending documents and wait until they're searchable
+     */
+    pub async fn commit(&self) -> Result<(), FtsError> {
+        if self.committer.pending.load(Ordering::SeqCst) == 0 {
+            return Ok(());
         }
-        self.writer = None;
-        Ok(())
+        let (tx, rx) = oneshot::channel();
+        self.commit_requests
+            .lock()
+            .map_err(|_| FtsError::WriterGone)?
+            .send(tx)
+            .map_err(|_| FtsError::WriterGone)?;
+        rx.await.map_err(|_| FtsError::WriterGone)?
     }
 
+    /**
+     * Index the document, the background thread commits it within the commit interval
+     */
     pub fn index<T: ToDoc + Debug>(
         &mut self,
         doc: &T,
         scopes: Vec<String>,
+        tenant: &str,
     ) -> Result<(), FtsError> {
         if !self.enabled {
             return Ok(());
         }
-        self.add_doc(doc, scopes)?;
-        self.commit()?;
-        Ok(())
+        self.add_doc(doc, scopes, tenant)
     }
 
-    pub fn search(
+    fn build_query(
         &self,
         q: &str,
         types: HashSet<String>,
         scope: Option<String>,
-        limit: usize,
-        offset: usize,
-    ) -> Result<Vec<Uuid>, FtsError> {
-        //
-        let searcher = self.reader.searcher();
-        let query_parser = QueryParser::for_index(
+        tenant: &str,
+    ) -> Result<Box<dyn Query>, FtsError> {
+        let mut query_parser = QueryParser::for_index(
             &self.index,
-            vec![self.name_field, self.id_field, self.body_field],
+            vec![
+                self.name_field,
+                self.qualified_name_field,
+                self.id_field,
+                self.tags_field,
+                self.body_field,
+            ],
         );
+        let boosts = &self.ranking.field_boosts;
+        query_parser.set_field_boost(self.name_field, boosts.name);
+        query_parser.set_field_boost(self.qualified_name_field, boosts.qualified_name);
+        query_parser.set_field_boost(self.tags_field, boosts.tags);
+        query_parser.set_field_boost(self.body_field, boosts.docs);
         let parsed_q: Box<dyn Query> = match query_parser.parse_query(q) {
             Ok(q) => q,
             Err(e) => {
@@ -202,18 +625,32 @@ impl FtsIndex {
                 query_parser.parse_query(&self.cleaner.replace_all(q, " ").to_string())?
             }
         };
-        let query = if types.is_empty() {
-            match scope {
-                Some(id) => Box::new(BooleanQuery::intersection(vec![
-                    parsed_q,
-                    Box::new(TermQuery::new(
-                        Term::from_field_text(self.scopes_field, &id),
-                        IndexRecordOption::Basic,
-                    )),
-                ])),
-                None => parsed_q,
-            }
-        } else {
+        let mut queries = vec![parsed_q];
+        queries.extend(self.build_filters(types, scope, tenant));
+        Ok(Box::new(BooleanQuery::intersection(queries)))
+    }
+
+    /**
+     * Tenant, scope and type filters of a search
+     */
+    fn build_filters(
+        &self,
+        types: HashSet<String>,
+        scope: Option<String>,
+        tenant: &str,
+    ) -> Vec<Box<dyn Query>> {
+        // Documents of other tenants never show up
+        let mut queries: Vec<Box<dyn Query>> = vec![Box::new(TermQuery::new(
+            Term::from_field_text(self.tenant_field, tenant_text(tenant)),
+            IndexRecordOption::Basic,
+        ))];
+        if let Some(id) = scope {
+            queries.push(Box::new(TermQuery::new(
+                Term::from_field_text(self.scopes_field, &id),
+                IndexRecordOption::Basic,
+            )));
+        }
+        if !types.is_empty() {
             let type_queries = types
                 .into_iter()
                 .map(|t| -> Box<dyn Query> {
@@ -223,43 +660,239 @@ impl FtsIndex {
                     ))
                 })
                 .collect();
-            match scope {
-                Some(id) => Box::new(BooleanQuery::intersection(vec![
-                    parsed_q,
-                    Box::new(TermQuery::new(
-                        Term::from_field_text(self.scopes_field, &id),
-                        IndexRecordOption::Basic,
-                    )),
-                    Box::new(BooleanQuery::union(type_queries)),
-                ])),
-                None => Box::new(BooleanQuery::intersection(vec![
-                    parsed_q,
-                    Box::new(BooleanQuery::union(type_queries)),
-                ])),
-            }
+            queries.push(Box::new(BooleanQuery::union(type_queries)));
+        }
+        queries
+    }
+
+    fn get_doc_id(&self, searcher: &Searcher, addr: DocAddress) -> Option<Uuid> {
+        let doc = searcher.doc(addr).ok();
+        doc.and_then(|d| {
+            d.into_iter()
+                .find(|f| f.field == self.id_field)
+                .and_then(|f| {
+                    debug!("Found id: {}", f.value.as_text().unwrap_or_default());
+                    f.value.as_text().map(|s| Uuid::parse_str(s).ok())
+                })
+                .flatten()
+        })
+    }
+
+    /**
+     * Search ordered by relevance, matches in the names weigh more than matches in the docs and
+     * recently modified entities are boosted, exact name matches are listed first if enabled
+     */
+    pub fn search(
+        &self,
+        q: &str,
+        types: HashSet<String>,
+        scope: Option<String>,
+        tenant: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Uuid>, FtsError> {
+        let searcher = self.committer.reader.searcher();
+        let mut ids = if self.ranking.pin_exact_match {
+            self.exact_matches(&searcher, q, types.clone(), scope.clone(), tenant)?
+        } else {
+            vec![]
         };
+        let query = self.build_query(q, types, scope, tenant)?;
+        let ranked = self.ranked(&searcher, query.as_ref(), offset + limit + ids.len())?;
+        ids.extend(ranked.into_iter().map(|(id, _)| id));
+        // Pinned documents also show up in the ranked ones, and re-indexed entities may have
+        // multiple docs
+        let mut seen = HashSet::new();
+        Ok(ids
+            .into_iter()
+            .filter(|id| seen.insert(*id))
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
+
+    /**
+     * Documents with the name or the qualified name equal to the query, latest versions first
+     */
+    fn exact_matches(
+        &self,
+        searcher: &Searcher,
+        q: &str,
+        types: HashSet<String>,
+        scope: Option<String>,
+        tenant: &str,
+    ) -> Result<Vec<Uuid>, FtsError> {
+        let name = q.trim().to_lowercase();
+        if name.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut queries: Vec<Box<dyn Query>> = vec![Box::new(TermQuery::new(
+            Term::from_field_text(self.exact_name_field, &name),
+            IndexRecordOption::Basic,
+        ))];
+        queries.extend(self.build_filters(types, scope, tenant));
         let top_docs = searcher.search(
-            &query,
-            &TopDocs::with_limit(limit)
-                .and_offset(offset)
-                .order_by_u64_field(self.name_score_field),
+            &BooleanQuery::intersection(queries),
+            &TopDocs::with_limit(MAX_PINNED).order_by_u64_field(self.version_field),
         )?;
         Ok(top_docs
             .into_iter()
-            .filter_map(|(_, addr)| {
-                let doc = searcher.doc(addr).ok();
-                doc.and_then(|d| {
-                    d.into_iter()
-                        .find(|f| f.field == self.id_field)
-                        .and_then(|f| {
-                            debug!("Found id: {}", f.value.as_text().unwrap_or_default());
-                            f.value.as_text().map(|s| Uuid::parse_str(s).ok())
-                        })
-                        .flatten()
-                })
+            .filter_map(|(_, addr)| self.get_doc_id(searcher, addr))
+            .collect())
+    }
+
+    /**
+     * Matching documents ordered by the relevance multiplied by the recency boost, ties are broken
+     * by the version
+     */
+    fn ranked(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+    ) -> Result<Vec<(Uuid, f32)>, FtsError> {
+        let now = Utc::now().timestamp();
+        let weight = self.ranking.recency_boost;
+        let half_life = self.ranking.recency_half_life.as_secs().max(1) as f32;
+        let version_field = self.version_field;
+        let modified_field = self.modified_field;
+        let collector =
+            TopDocs::with_limit(limit.max(1)).tweak_score(move |segment: &SegmentReader| {
+                // Both are declared as fast fields in the schema
+                let versions = segment.fast_fields().u64(version_field).unwrap();
+                let modified = segment.fast_fields().i64(modified_field).unwrap();
+                move |doc: DocId, score: Score| {
+                    let age = (now - modified.get(doc)).max(0) as f32;
+                    let recency = weight * 0.5f32.powf(age / half_life);
+                    (score * (1.0 + recency), versions.get(doc))
+                }
+            });
+        Ok(searcher
+            .search(query, &collector)?
+            .into_iter()
+            .filter_map(|((score, _), addr)| self.get_doc_id(searcher, addr).map(|id| (id, score)))
+            .collect())
+    }
+
+    /**
+     * Ordered by the name instead of the relevance so pages stay stable while the index changes,
+     * starts after the cursor instead of skipping `offset` documents.
+     * Returns the cursor of the last document if there are more results.
+     */
+    pub fn search_after(
+        &self,
+        q: &str,
+        types: HashSet<String>,
+        scope: Option<String>,
+        tenant: &str,
+        limit: usize,
+        after: Option<SearchCursor>,
+    ) -> Result<(Vec<Uuid>, Option<SearchCursor>), FtsError> {
+        let limit = limit.max(1);
+        let searcher = self.committer.reader.searcher();
+        let query = self.build_query(q, types, scope, tenant)?;
+        let mut hits = vec![];
+        let upper = match after {
+            Some(cursor) => {
+                // Documents with the same score as the cursor are only ordered by id
+                hits.extend(
+                    self.search_score(&searcher, query.as_ref(), cursor.score)?
+                        .into_iter()
+                        .filter(|h| h > &cursor),
+                );
+                Bound::Excluded(cursor.score)
+            }
+            None => Bound::Unbounded,
+        };
+        let rest = BooleanQuery::intersection(vec![
+            query.box_clone(),
+            Box::new(RangeQuery::new_u64_bounds(
+                self.name_score_field,
+                Bound::Unbounded,
+                upper,
+            )),
+        ]);
+        let top_docs = searcher.search(
+            &rest,
+            &TopDocs::with_limit(limit + 1).order_by_u64_field(self.name_score_field),
+        )?;
+        // TopDocs cuts off documents with the lowest score in no particular order, take all of them
+        // so the last page boundary is stable
+        let boundary = if top_docs.len() > limit {
+            top_docs.last().map(|(score, _)| *score)
+        } else {
+            None
+        };
+        hits.extend(
+            top_docs
+                .into_iter()
+                .filter(|(score, _)| Some(*score) != boundary)
+                .filter_map(|(score, addr)| {
+                    self.get_doc_id(&searcher, addr)
+                        .map(|id| SearchCursor { score, id })
+                }),
+        );
+        if let Some(score) = boundary {
+            hits.extend(self.search_score(&searcher, query.as_ref(), score)?);
+        }
+        hits.sort();
+        hits.dedup();
+        let next = if hits.len() > limit {
+            hits.truncate(limit);
+            hits.last().copied()
+        } else {
+            None
+        };
+        Ok((hits.into_iter().map(|h| h.id).collect(), next))
+    }
+
+    /**
+     * All matching documents with exactly this name score
+     */
+    fn search_score(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        score: u64,
+    ) -> Result<Vec<SearchCursor>, FtsError> {
+        let query = BooleanQuery::intersection(vec![
+            query.box_clone(),
+            Box::new(RangeQuery::new_u64_bounds(
+                self.name_score_field,
+                Bound::Included(score),
+                Bound::Included(score),
+            )),
+        ]);
+        Ok(searcher
+            .search(&query, &DocSetCollector)?
+            .into_iter()
+            .filter_map(|addr| {
+                self.get_doc_id(searcher, addr)
+                    .map(|id| SearchCursor { score, id })
             })
             .collect())
     }
+
+    /**
+     * Ordered by BM25 scores with the field boosts, without the recency boost and the pinning,
+     * returns ids with the scores
+     */
+    pub fn search_with_score(
+        &self,
+        q: &str,
+        types: HashSet<String>,
+        scope: Option<String>,
+        tenant: &str,
+        limit: usize,
+    ) -> Result<Vec<(Uuid, f32)>, FtsError> {
+        let searcher = self.committer.reader.searcher();
+        let query = self.build_query(q, types, scope, tenant)?;
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+        Ok(top_docs
+            .into_iter()
+            .filter_map(|(score, addr)| self.get_doc_id(&searcher, addr).map(|id| (id, score)))
+            .collect())
+    }
 }
 
 impl Default for FtsIndex {
@@ -317,15 +950,17 @@ mod tests {
             fts.add_doc(
                 &a,
                 vec![format!("scope-{}", i % 2), format!("scope-{}", i % 5)],
+                "",
             )
             .unwrap();
         }
-        fts.commit().unwrap();
+        fts.commit_blocking().unwrap();
         let ids = fts
             .search(
                 "body",
                 set!["SomeType1".to_string()],
                 Some("scope-2".to_string()),
+                "",
                 10,
                 0,
             )
@@ -336,6 +971,203 @@ mod tests {
         }
     }
 
+    #[test]
+    fn cursor_search() {
+        init_logger();
+        let mut fts = FtsIndex::new();
+        let mut all = HashSet::new();
+        // Many documents share the same name so pages break inside ties
+        for i in 0..23 {
+            let id = Uuid::new_v4();
+            let a = A {
+                name: format!("name{}", i % 3),
+                id: id.to_string(),
+                scopes: vec![],
+                type_: "SomeType".to_string(),
+                body: format!("This is the body of name{}", i),
+            };
+            all.insert(id);
+            fts.add_doc(&a, vec![], "").unwrap();
+        }
+        fts.commit_blocking().unwrap();
+
+        let mut seen = vec![];
+        let mut cursor = None;
+        loop {
+            let (ids, next) = fts
+                .search_after("body", HashSet::new(), None, "", 5, cursor)
+                .unwrap();
+            assert!(ids.len() <= 5);
+            seen.extend(ids);
+            match next {
+                Some(c) => {
+                    let token = c.to_string();
+                    assert_eq!(token.parse::<SearchCursor>().unwrap(), c);
+                    cursor = Some(c);
+                }
+                None => break,
+            }
+        }
+        assert_eq!(seen.len(), all.len());
+        assert_eq!(seen.iter().cloned().collect::<HashSet<_>>(), all);
+
+        assert!("abc".parse::<SearchCursor>().is_err());
+    }
+
+    #[test]
+    fn tenant_search() {
+        init_logger();
+        let mut fts = FtsIndex::new();
+        let mut tenants: HashMap<Uuid, &str> = HashMap::new();
+        for (i, tenant) in ["", "tenant1", "tenant2"]
+            .into_iter()
+            .cycle()
+            .take(9)
+            .enumerate()
+        {
+            let id = Uuid::new_v4();
+            let a = A {
+                name: format!("some name{}", i),
+                id: id.to_string(),
+                scopes: vec![],
+                type_: "SomeType".to_string(),
+                body: format!("This is the body of name{}", i),
+            };
+            tenants.insert(id, tenant);
+            fts.add_doc(&a, vec![], tenant).unwrap();
+        }
+        fts.commit_blocking().unwrap();
+        for tenant in ["", "tenant1", "tenant2"] {
+            let ids = fts
+                .search("body", HashSet::new(), None, tenant, 10, 0)
+                .unwrap();
+            assert_eq!(ids.len(), 3);
+            assert!(ids.iter().all(|id| tenants[id] == tenant));
+        }
+        assert!(fts
+            .search("body", HashSet::new(), None, "tenant3", 10, 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn async_commit() {
+        init_logger();
+        let mut fts = FtsIndex::new();
+        let a = A {
+            name: "some name".to_string(),
+            id: Uuid::new_v4().to_string(),
+            scopes: vec![],
+            type_: "SomeType".to_string(),
+            body: "This is the body".to_string(),
+        };
+        fts.index(&a, vec![], "").unwrap();
+        // Not committed yet
+        assert!(fts
+            .search("body", HashSet::new(), None, "", 10, 0)
+            .unwrap()
+            .is_empty());
+        fts.commit().await.unwrap();
+        assert_eq!(
+            fts.search("body", HashSet::new(), None, "", 10, 0)
+                .unwrap()
+                .len(),
+            1
+        );
+        // Nothing pending
+        fts.commit().await.unwrap();
+    }
+
+    #[derive(Clone, Debug)]
+    struct Ranked {
+        name: &'static str,
+        id: Uuid,
+        tags: &'static str,
+        body: &'static str,
+        version: u64,
+        modified: chrono::DateTime<Utc>,
+    }
+
+    impl ToDoc for Ranked {
+        fn get_name(&self) -> String {
+            self.name.to_string()
+        }
+        fn get_id(&self) -> String {
+            self.id.to_string()
+        }
+        fn get_type(&self) -> String {
+            "SomeType".to_string()
+        }
+        fn get_body(&self) -> String {
+            self.body.to_string()
+        }
+        fn get_tags(&self) -> String {
+            self.tags.to_string()
+        }
+        fn get_version(&self) -> u64 {
+            self.version
+        }
+        fn get_modified(&self) -> Option<chrono::DateTime<Utc>> {
+            Some(self.modified)
+        }
+    }
+
+    #[test]
+    fn ranked_search() {
+        init_logger();
+        let mut fts = FtsIndex::new();
+        let now = Utc::now();
+        let old = now - chrono::Duration::days(365);
+        let docs = [
+            ("balance", "", "the age of the account", 1, old),
+            ("account", "age", "", 1, old),
+            ("user_age", "", "", 1, old),
+            ("age", "", "", 1, old),
+            ("age", "", "", 2, old),
+            ("stale_total", "", "", 1, old),
+            ("recent_total", "", "", 1, now),
+        ]
+        .map(|(name, tags, body, version, modified)| Ranked {
+            name,
+            id: Uuid::new_v4(),
+            tags,
+            body,
+            version,
+            modified,
+        });
+        for d in docs.iter() {
+            fts.add_doc(d, vec![], "").unwrap();
+        }
+        fts.commit_blocking().unwrap();
+        let names = |q: &str| {
+            fts.search(q, HashSet::new(), None, "", 10, 0)
+                .unwrap()
+                .into_iter()
+                .map(|id| {
+                    let d = docs.iter().find(|d| d.id == id).unwrap();
+                    format!("{}:{}", d.name, d.version)
+                })
+                .collect::<Vec<_>>()
+        };
+        // Exact matches first, then matches in the name, tags and docs
+        assert_eq!(
+            names("age"),
+            vec!["age:2", "age:1", "user_age:1", "account:1", "balance:1"]
+        );
+        assert_eq!(names("total"), vec!["recent_total:1", "stale_total:1"]);
+        assert_eq!(
+            fts.search("age", HashSet::new(), None, "", 2, 2).unwrap(),
+            vec![docs[2].id, docs[1].id]
+        );
+
+        let boosts: FieldBoosts = "name=5, tags=0".parse().unwrap();
+        assert_eq!(boosts.name, 5.0);
+        assert_eq!(boosts.tags, 0.0);
+        assert_eq!(boosts.docs, FieldBoosts::default().docs);
+        assert!("title=1".parse::<FieldBoosts>().is_err());
+        assert!("name=-1".parse::<FieldBoosts>().is_err());
+    }
+
     #[test]
     fn cleaner() {
         let cleaner =
@@ -1,54 +1,83 @@
+mod attr_index;
 mod database;
 mod db_registry;
+mod diff;
+mod embedding;
+mod fsck;
 mod fts;
+#[cfg(feature = "onnx")]
+mod onnx;
 mod rbac_map;
 mod serdes;
 
 #[cfg(any(mock, test))]
 mod mock;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
 use async_trait::async_trait;
-pub use database::{attach_storage, load_content};
-pub use db_registry::Registry;
+use chrono::{DateTime, Utc};
+pub use database::{attach_storage, dump_content, load_content, sample_jdbc_source};
+pub use db_registry::{set_project_cache_size, Registry};
+pub use diff::{diff_content, DiffEntity, EntityChange, RegistryDiff};
+pub use embedding::{embed_query, set_embedder, HttpEmbedder};
+pub use fsck::{FsckIssue, FsckReport};
+pub use fts::{set_fts_config, FieldBoosts, FtsConfig, RankingConfig};
 use log::{debug, warn};
+#[cfg(feature = "onnx")]
+pub use onnx::OnnxEmbedder;
 use registry_provider::{
-    extract_version, AnchorDef, AnchorFeatureDef, Credential, DerivedFeatureDef, Edge, EdgeType,
-    Entity, EntityPropMutator, EntityType, Permission, ProjectDef, RbacError, RbacProvider,
-    RbacRecord, RegistryError, RegistryProvider, Resource, SourceDef, ToDocString,
+    extract_version, AnchorDef, AnchorFeatureDef, Credential, DatasetDef, DerivedFeatureDef, Edge,
+    EdgeType, Entity, EntityPropMutator, EntityType, GarbageCollectionReport, LoadPolicy,
+    LoadReport, MaterializationRecordDef, Ownership, Permission, ProjectDef, ProjectSummary,
+    RbacError, RbacProvider, RbacRecord, RegistryError, RegistryProvider, Release, Resource,
+    SourceDef, ToDoc, ToDocString,
 };
 use uuid::Uuid;
 
+use crate::embedding::{blend_scores, embedding_doc, SEMANTIC_WEIGHT};
+use crate::fts::SearchCursor;
+
 #[async_trait]
 impl<EntityProp> RegistryProvider<EntityProp> for Registry<EntityProp>
 where
     EntityProp: Clone + Debug + PartialEq + Eq + EntityPropMutator + ToDocString + Send + Sync,
 {
     /**
-     * Replace existing content with input snapshot
+     * Merge input snapshot into existing content, conflicting entities are handled by the policy
      */
     async fn load_data(
         &mut self,
         entities: Vec<Entity<EntityProp>>,
         edges: Vec<Edge>,
         permissions: Vec<RbacRecord>,
-    ) -> Result<(), RegistryError> {
-        self.batch_load(entities.into_iter(), edges.into_iter())
+        policy: LoadPolicy,
+    ) -> Result<LoadReport, RegistryError> {
+        let report = self
+            .batch_load(entities.into_iter(), edges.into_iter(), policy)
             .await?;
         self.load_permissions(permissions.into_iter())?;
-        Ok(())
+        Ok(report)
+    }
+
+    /**
+     * Scope following calls to the tenant, returns the previous tenant
+     */
+    fn switch_tenant(&mut self, tenant: &str) -> String {
+        std::mem::replace(&mut self.tenant, tenant.to_string())
     }
 
     /**
-     * Get ids of all entry points
+     * Get ids of all entry points in the current tenant
      */
     fn get_entry_points(&self) -> Result<Vec<Entity<EntityProp>>, RegistryError> {
         Ok(self
             .entry_points
             .iter()
-            .filter_map(|&idx| self.graph.node_weight(idx).cloned())
+            .filter_map(|&idx| self.graph.node_weight(idx))
+            .filter(|w| w.properties.get_tenant() == self.tenant)
+            .cloned()
             .collect())
     }
 
@@ -57,7 +86,7 @@ where
      */
     fn get_entity(&self, uuid: Uuid) -> Result<Entity<EntityProp>, RegistryError> {
         self.graph
-            .node_weight(self.get_idx(uuid)?)
+            .node_weight(self.get_scoped_idx(uuid)?)
             .cloned()
             .ok_or(RegistryError::InvalidEntity(uuid))
     }
@@ -81,26 +110,32 @@ where
         Ok(uuids
             .into_iter()
             .filter_map(|id| {
-                self.get_idx(id)
+                self.get_scoped_idx(id)
                     .ok()
                     .and_then(|idx| self.graph.node_weight(idx).cloned())
             })
             .collect())
     }
 
+    /**
+     * Get entities by the type and tag indices
+     */
+    fn get_entities_by_attributes(
+        &self,
+        types: HashSet<EntityType>,
+        tags: HashMap<String, Option<String>>,
+        scope: Option<Uuid>,
+    ) -> Result<Vec<Entity<EntityProp>>, RegistryError> {
+        Ok(Registry::get_entities_by_attributes(
+            self, &types, &tags, scope,
+        ))
+    }
+
     /**
      * Get entity id by its name
      */
     fn get_entity_id_by_qualified_name(&self, qualified_name: &str) -> Result<Uuid, RegistryError> {
-        let (qualified_name, version) = extract_version(qualified_name);
-        self.name_id_map
-            .get(qualified_name)
-            .and_then(|ids| match version {
-                Some(v) => ids.get(&v),
-                None => ids.keys().max().and_then(|v| ids.get(v)),
-            })
-            .ok_or_else(|| RegistryError::EntityNotFound(qualified_name.to_string()))
-            .cloned()
+        self.get_entity_id_by_qualified_name_in_tenant(&self.tenant, qualified_name)
     }
 
     /**
@@ -111,7 +146,7 @@ where
         uuid: Uuid,
         edge_type: EdgeType,
     ) -> Result<Vec<Entity<EntityProp>>, RegistryError> {
-        let idx = self.get_idx(uuid)?;
+        let idx = self.get_scoped_idx(uuid)?;
         Ok(self
             .get_neighbors_idx(idx, |e| e.edge_type == edge_type)
             .into_iter()
@@ -128,6 +163,8 @@ where
         edge_type: EdgeType,
         size_limit: Option<usize>,
     ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError> {
+        // Edges never cross tenants, checking the starting point is enough
+        self.get_scoped_idx(uuid)?;
         self.bfs_traversal(uuid, size_limit, |_| true, |e| e.edge_type == edge_type)
     }
 
@@ -148,6 +185,7 @@ where
                 query,
                 types.into_iter().map(|t| format!("{:?}", t)).collect(),
                 container.map(|id| id.to_string()),
+                &self.tenant,
                 limit,
                 offset,
             )? // TODO:
@@ -157,6 +195,85 @@ where
             .collect())
     }
 
+    /**
+     * Get one page of entity ids with FTS after the cursor
+     */
+    fn search_entity_page(
+        &self,
+        query: &str,
+        types: HashSet<EntityType>,
+        container: Option<Uuid>,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> Result<(Vec<Entity<EntityProp>>, Option<String>), RegistryError> {
+        let after = cursor.map(|c| c.parse::<SearchCursor>()).transpose()?;
+        let (ids, next) = self.fts_index.search_after(
+            query,
+            types.into_iter().map(|t| format!("{:?}", t)).collect(),
+            container.map(|id| id.to_string()),
+            &self.tenant,
+            limit,
+            after,
+        )?;
+        Ok((
+            ids.into_iter()
+                .filter_map(|id| self.get_entity_by_id(id))
+                .collect(),
+            next.map(|c| c.to_string()),
+        ))
+    }
+
+    /**
+     * Search entities with the blended score of vector similarity and FTS relevance
+     */
+    fn semantic_search_entity(
+        &self,
+        query: &str,
+        query_vector: &[f32],
+        types: HashSet<EntityType>,
+        container: Option<Uuid>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Entity<EntityProp>>, RegistryError> {
+        let candidates =
+            Registry::get_entities_by_attributes(self, &types, &Default::default(), container);
+        // Entities whose embeddings are still being computed are ranked by keyword score only
+        let similarities: HashMap<Uuid, f32> = candidates
+            .iter()
+            .map(|e| match self.vector_index.similarity(e.id, query_vector) {
+                Some(s) => (e.id, s),
+                None => {
+                    self.vector_index.queue(e.id, embedding_doc(e));
+                    (e.id, 0.0)
+                }
+            })
+            .collect();
+        // Keyword matching may fail on natural language queries, it only boosts the ranking
+        let mut keyword_scores: HashMap<Uuid, f32> = HashMap::new();
+        match self.fts_index.search_with_score(
+            query,
+            types.into_iter().map(|t| format!("{:?}", t)).collect(),
+            container.map(|id| id.to_string()),
+            &self.tenant,
+            candidates.len().max(1),
+        ) {
+            Ok(scores) => {
+                for (id, score) in scores {
+                    // Re-indexed entities may have multiple docs
+                    let s = keyword_scores.entry(id).or_default();
+                    *s = s.max(score);
+                }
+            }
+            Err(e) => warn!("Keyword search failed, error: {:?}", e),
+        }
+        Ok(blend_scores(similarities, keyword_scores, SEMANTIC_WEIGHT)
+            .into_iter()
+            .skip(offset)
+            .filter_map(|(id, _)| self.get_entity_by_id(id))
+            .take(limit)
+            .collect())
+    }
+
     /**
      * Get all entities and connections between them under a project
      */
@@ -165,14 +282,40 @@ where
         qualified_name: &str,
     ) -> Result<(Vec<Entity<EntityProp>>, Vec<Edge>), RegistryError> {
         let uuid = self.get_entity_id(qualified_name)?;
-        let (entities, edges) = self.get_project_by_id(uuid)?;
+        let (mut entities, mut edges) = self.get_project_by_id(uuid)?;
+        // Derived features may consume features of other projects, keep these inputs and the edges
+        // to them so the lineage shows where the shared features come from, the API drops the ones
+        // the caller can't read
+        let derived: Vec<Uuid> = entities
+            .iter()
+            .filter(|e| e.entity_type == EntityType::DerivedFeature)
+            .map(|e| e.id)
+            .collect();
+        for id in derived {
+            for input in self.get_neighbors(id, EdgeType::Consumes)? {
+                let edge = Edge {
+                    edge_type: EdgeType::Consumes,
+                    from: id,
+                    to: input.id,
+                };
+                edges.insert(edge.reflection());
+                edges.insert(edge);
+                entities.insert(input);
+            }
+        }
         Ok((entities.into_iter().collect(), edges.into_iter().collect()))
     }
 
+    fn get_project_summary(&self, id_or_name: &str) -> Result<ProjectSummary, RegistryError> {
+        let uuid = self.get_entity_id(id_or_name)?;
+        self.get_project_summary_by_id(uuid)
+    }
+
     // Create new project
     async fn new_project(&mut self, definition: &ProjectDef) -> Result<(Uuid, u64), RegistryError> {
         // TODO: Pre-flight validation
         let mut prop = EntityProp::new_project(definition)?;
+        prop.set_tenant(&self.tenant);
         match self.get_all_versions(&definition.qualified_name).last() {
             // It makes no sense to create a new version of a project
             Some(e) => Ok((e.id, e.version)),
@@ -201,6 +344,8 @@ where
     ) -> Result<(Uuid, u64), RegistryError> {
         // TODO: Pre-flight validation
         let mut prop = EntityProp::new_source(definition)?;
+        prop.set_tenant(&self.tenant);
+        prop.inherit_tags(&self.get_default_tags(project_id));
 
         for v in self.get_all_versions(&definition.qualified_name) {
             if v.properties == prop {
@@ -235,14 +380,30 @@ where
         project_id: Uuid,
         definition: &AnchorDef,
     ) -> Result<(Uuid, u64), RegistryError> {
-        if self.get_entity_by_id(definition.source_id).is_none() {
-            debug!(
-                "Source {} not found, cannot create anchor",
-                definition.source_id
-            );
-            return Err(RegistryError::EntityNotFound(
-                definition.source_id.to_string(),
-            ));
+        let sources: HashSet<Uuid> = std::iter::once(definition.source_id)
+            .chain(definition.source_ids.iter().copied())
+            .collect();
+        if sources.len() != definition.source_ids.len() + 1 {
+            return Err(RegistryError::InvalidDefinition(format!(
+                "Anchor {} consumes the same source more than once",
+                definition.qualified_name
+            )));
+        }
+        if !definition.source_ids.is_empty() && definition.join_keys.is_empty() {
+            return Err(RegistryError::InvalidDefinition(format!(
+                "Anchor {} has multiple sources but no join keys",
+                definition.qualified_name
+            )));
+        }
+        for source_id in sources.iter() {
+            match self.get_entity_by_id(*source_id) {
+                Some(e) if e.entity_type == EntityType::Source => {}
+                Some(e) => return Err(RegistryError::WrongEntityType(e.id, e.entity_type)),
+                None => {
+                    debug!("Source {} not found, cannot create anchor", source_id);
+                    return Err(RegistryError::EntityNotFound(source_id.to_string()));
+                }
+            }
         }
 
         if let Some(e) = self
@@ -253,21 +414,28 @@ where
                     "Found existing entity {}, qualified_name '{}'",
                     e.id, e.qualified_name
                 );
-                // We only check source for conflicts as the anchor is always empty when it's just created
-                let source = self
+                // We only check sources, join keys, preprocessing and the default window for conflicts as the anchor is always empty when it's just created
+                let consumed: HashSet<Uuid> = self
                     .get_neighbors(e.id, EdgeType::Consumes)
-                    .expect("Data inconsistency detected");
-                // An anchor has exactly one source
-                assert!(source.len() == 1, "Data inconsistency detected");
-                definition.source_id == source[0].id
+                    .expect("Data inconsistency detected")
+                    .into_iter()
+                    .map(|s| s.id)
+                    .collect();
+                consumed == sources
+                    && e.properties.get_source_joins()
+                        == (definition.source_ids.clone(), definition.join_keys.clone())
+                    && e.properties.get_anchor_preprocessing() == definition.preprocessing
+                    && e.properties.get_anchor_default_window() == definition.default_window
             })
         {
-            // Found existing anchor with same name and source
+            // Found existing anchor with same name and sources
             return Ok((e.id, e.version));
         }
 
         // Create new version
         let mut prop = EntityProp::new_anchor(definition)?;
+        prop.set_tenant(&self.tenant);
+        prop.inherit_tags(&self.get_default_tags(project_id));
         let version = self.get_next_version_number(&definition.qualified_name);
         prop.set_version(version);
 
@@ -284,8 +452,10 @@ where
         self.connect(project_id, anchor_id, EdgeType::Contains)
             .await?;
 
-        self.connect(anchor_id, definition.source_id, EdgeType::Consumes)
-            .await?;
+        for source_id in sources {
+            self.connect(anchor_id, source_id, EdgeType::Consumes)
+                .await?;
+        }
 
         self.index_entity(anchor_id, true)?;
         Ok((anchor_id, version))
@@ -300,6 +470,8 @@ where
     ) -> Result<(Uuid, u64), RegistryError> {
         // TODO: Pre-flight validation
         let mut prop = EntityProp::new_anchor_feature(definition)?;
+        prop.set_tenant(&self.tenant);
+        prop.inherit_tags(&self.get_default_tags(project_id));
 
         if let Some(e) = self
             .get_all_versions(&definition.qualified_name)
@@ -370,6 +542,8 @@ where
         }
 
         let mut prop = EntityProp::new_derived_feature(definition)?;
+        prop.set_tenant(&self.tenant);
+        prop.inherit_tags(&self.get_default_tags(project_id));
 
         if let Some(e) = self
             .get_all_versions(&definition.qualified_name)
@@ -419,13 +593,232 @@ where
         Ok((feature_id, version))
     }
 
-    async fn delete_entity(&mut self, id: Uuid) -> Result<(), RegistryError> {
-        self.delete_entity_by_id(id).await
+    // Record materialized features under specified project
+    async fn new_materialization_record(
+        &mut self,
+        project_id: Uuid,
+        definition: &MaterializationRecordDef,
+    ) -> Result<(Uuid, u64), RegistryError> {
+        for id in definition.features.iter() {
+            match self.get_entity_by_id(*id) {
+                Some(e)
+                    if e.entity_type == EntityType::AnchorFeature
+                        || e.entity_type == EntityType::DerivedFeature => {}
+                Some(e) => return Err(RegistryError::WrongEntityType(e.id, e.entity_type)),
+                None => {
+                    debug!(
+                        "Feature {} not found, cannot create materialization record {}",
+                        id, definition.qualified_name
+                    );
+                    return Err(RegistryError::EntityNotFound(id.to_string()));
+                }
+            }
+        }
+
+        let mut prop = EntityProp::new_materialization_record(definition)?;
+        prop.set_tenant(&self.tenant);
+        prop.inherit_tags(&self.get_default_tags(project_id));
+
+        if let Some(e) = self
+            .get_all_versions(&definition.qualified_name)
+            .into_iter()
+            .find(|e| {
+                let features: HashSet<Uuid> = self
+                    .get_neighbors(e.id, EdgeType::Consumes)
+                    .expect("Data inconsistency detected")
+                    .into_iter()
+                    .map(|e| e.id)
+                    .collect();
+                features == definition.features && prop == e.properties
+            })
+        {
+            return Ok((e.id, e.version));
+        }
+
+        let version = self.get_next_version_number(&definition.qualified_name);
+        prop.set_version(version);
+        let record_id = self
+            .insert_entity(
+                definition.id,
+                EntityType::MaterializationRecord,
+                &definition.name,
+                &definition.qualified_name,
+                prop,
+            )
+            .await?;
+
+        self.connect(project_id, record_id, EdgeType::Contains)
+            .await?;
+
+        for &id in definition.features.iter() {
+            self.connect(record_id, id, EdgeType::Consumes).await?;
+        }
+
+        self.index_entity(record_id, true)?;
+        Ok((record_id, version))
+    }
+
+    // Record features consumed by a dataset under specified project
+    async fn new_dataset(
+        &mut self,
+        project_id: Uuid,
+        definition: &DatasetDef,
+    ) -> Result<(Uuid, u64), RegistryError> {
+        for id in definition.features.iter() {
+            match self.get_entity_by_id(*id) {
+                Some(e)
+                    if e.entity_type == EntityType::AnchorFeature
+                        || e.entity_type == EntityType::DerivedFeature => {}
+                Some(e) => return Err(RegistryError::WrongEntityType(e.id, e.entity_type)),
+                None => {
+                    debug!(
+                        "Feature {} not found, cannot create dataset {}",
+                        id, definition.qualified_name
+                    );
+                    return Err(RegistryError::EntityNotFound(id.to_string()));
+                }
+            }
+        }
+
+        // Jobs writing the same dataset are not recorded, only a different set of features
+        // creates a new version
+        if let Some(e) = self
+            .get_all_versions(&definition.qualified_name)
+            .into_iter()
+            .find(|e| {
+                let features: HashSet<Uuid> = self
+                    .get_neighbors(e.id, EdgeType::DependsOn)
+                    .expect("Data inconsistency detected")
+                    .into_iter()
+                    .map(|e| e.id)
+                    .collect();
+                features == definition.features
+            })
+        {
+            return Ok((e.id, e.version));
+        }
+
+        let mut prop = EntityProp::new_dataset(definition)?;
+        prop.set_tenant(&self.tenant);
+        prop.inherit_tags(&self.get_default_tags(project_id));
+        let version = self.get_next_version_number(&definition.qualified_name);
+        prop.set_version(version);
+        let dataset_id = self
+            .insert_entity(
+                definition.id,
+                EntityType::Dataset,
+                &definition.name,
+                &definition.qualified_name,
+                prop,
+            )
+            .await?;
+
+        self.connect(project_id, dataset_id, EdgeType::Contains)
+            .await?;
+
+        for &id in definition.features.iter() {
+            self.connect(dataset_id, id, EdgeType::DependsOn).await?;
+        }
+
+        self.index_entity(dataset_id, true)?;
+        Ok((dataset_id, version))
+    }
+
+    async fn delete_entity(
+        &mut self,
+        id: Uuid,
+        deleted_at: Option<DateTime<Utc>>,
+    ) -> Result<(), RegistryError> {
+        self.delete_entity_by_id(id, deleted_at).await
+    }
+
+    async fn collect_garbage(
+        &mut self,
+        before: DateTime<Utc>,
+        dry_run: bool,
+    ) -> Result<GarbageCollectionReport, RegistryError> {
+        Registry::collect_garbage(self, before, dry_run).await
+    }
+
+    async fn set_entity_ownership(
+        &mut self,
+        id: Uuid,
+        ownership: &Ownership,
+    ) -> Result<(), RegistryError> {
+        match self.get_entity_type(id)? {
+            EntityType::Project | EntityType::AnchorFeature | EntityType::DerivedFeature => {
+                self.set_ownership_by_id(id, ownership).await
+            }
+            t => Err(RegistryError::WrongEntityType(id, t)),
+        }
+    }
+
+    async fn set_entity_docs(&mut self, id: Uuid, docs: &str) -> Result<(), RegistryError> {
+        self.update_properties_by_id(id, |p| p.set_docs(docs))
+            .await?;
+        // Docs are searchable
+        self.index_entity(id, true)
+    }
+
+    async fn set_source_verified(
+        &mut self,
+        id: Uuid,
+        time: DateTime<Utc>,
+    ) -> Result<(), RegistryError> {
+        match self.get_entity_type(id)? {
+            EntityType::Source => {
+                self.update_properties_by_id(id, |p| p.set_last_verified(time))
+                    .await
+            }
+            t => Err(RegistryError::WrongEntityType(id, t)),
+        }
+    }
+
+    async fn set_entity_labels(
+        &mut self,
+        id: Uuid,
+        labels: Vec<String>,
+    ) -> Result<(), RegistryError> {
+        self.update_properties_by_id(id, |p| p.set_labels(labels))
+            .await?;
+        // Labels are searchable
+        self.index_entity(id, true)
+    }
+
+    async fn set_project_releases(
+        &mut self,
+        id: Uuid,
+        releases: Vec<Release>,
+    ) -> Result<(), RegistryError> {
+        match self.get_entity_type(id)? {
+            EntityType::Project => {
+                self.update_properties_by_id(id, |p| p.set_releases(releases))
+                    .await
+            }
+            t => Err(RegistryError::WrongEntityType(id, t)),
+        }
+    }
+
+    async fn link_similar_features(&mut self, ids: &[Uuid]) -> Result<(), RegistryError> {
+        for &id in ids {
+            match self.get_entity_type(id)? {
+                EntityType::AnchorFeature => {}
+                t => return Err(RegistryError::WrongEntityType(id, t)),
+            }
+        }
+        for (i, &from) in ids.iter().enumerate() {
+            for &to in &ids[i + 1..] {
+                if from != to && !self.has_connection_type(from, to, EdgeType::SimilarTo) {
+                    self.connect(from, to, EdgeType::SimilarTo).await?;
+                }
+            }
+        }
+        Ok(())
     }
 
     fn get_all_versions(&self, qualified_name: &str) -> Vec<Entity<EntityProp>> {
         let (qualified_name, _version) = extract_version(qualified_name);
-        match self.name_id_map.get(qualified_name) {
+        match self.get_versions(qualified_name) {
             Some(ids) => ids
                 .iter()
                 .filter_map(|(_version, id)| self.get_entity_by_id(*id))
@@ -436,8 +829,7 @@ where
 
     fn get_next_version_number(&self, qualified_name: &str) -> u64 {
         let (qualified_name, _version) = extract_version(qualified_name);
-        self.name_id_map
-            .get(qualified_name)
+        self.get_versions(qualified_name)
             .and_then(|ids| ids.keys().max())
             .cloned()
             .unwrap_or_default()
@@ -457,32 +849,15 @@ where
         resource: &Resource,
         permission: Permission,
     ) -> Result<bool, RegistryError> {
-        if credential == &Credential::RbacDisabled {
-            return Ok(true);
-        }
-        // Get corresponding project to the resource
-        let resource = match resource {
-            Resource::NamedEntity(name) => {
-                let id = self.get_entity_id(name)?;
-                let proj_id = self.get_entity_project_id(id)?;
-                Resource::Entity(proj_id)
-            }
-            Resource::Entity(id) => {
-                let proj_id = self.get_entity_project_id(*id)?;
-                Resource::Entity(proj_id)
-            }
-            Resource::Global => Resource::Global,
-        };
-        // User must be either Global Admin or Project Admin or having the permission on the resource
-        Ok(self
-            .permission_map
-            .check_permission(credential, &Resource::Global, Permission::Admin)
-            || self
-                .permission_map
-                .check_permission(credential, &resource, Permission::Admin)
-            || self
-                .permission_map
-                .check_permission(credential, &resource, permission))
+        self.check_permission_in_tenant(&self.tenant, credential, resource, permission)
+    }
+
+    fn get_permission_scopes(&self, resource: &Resource) -> Result<Vec<Resource>, RegistryError> {
+        self.get_permission_scopes_in_tenant(&self.tenant, resource)
+    }
+
+    fn visible_projects(&self, credential: &Credential) -> Result<Vec<String>, RegistryError> {
+        self.visible_projects_in_tenant(&self.tenant, credential)
     }
 
     fn load_permissions<RI>(&mut self, permissions: RI) -> Result<(), RegistryError>
@@ -511,8 +886,24 @@ where
     }
 
     fn get_permissions(&self) -> Result<Vec<RbacRecord>, RegistryError> {
+        let in_tenant = |r: &Resource| match r {
+            Resource::Entity(id) => self.get_scoped_idx(*id).is_ok(),
+            _ => false,
+        };
+        // Global grants are listed in named tenants only for users having grants in the tenant
+        let members: HashSet<&Credential> = self
+            .permission_map
+            .iter()
+            .filter(|(_, _, r)| in_tenant(&r.resource))
+            .map(|(c, _, _)| c)
+            .collect();
         self.permission_map
             .iter()
+            .filter(|(c, _, r)| {
+                in_tenant(&r.resource)
+                    || (r.resource == Resource::Global
+                        && (self.tenant.is_empty() || members.contains(c)))
+            })
             .map(|(credential, permission, resource)| {
                 Ok(RbacRecord {
                     credential: credential.to_owned(),
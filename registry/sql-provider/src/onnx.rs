@@ -0,0 +1,156 @@
+use std::{
+    fmt::{Debug, Display},
+    path::Path,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use ndarray::{Array2, CowArray, IxDyn};
+use ort::{
+    tensor::OrtOwnedTensor, Environment, GraphOptimizationLevel, Session, SessionBuilder, Value,
+};
+use registry_provider::{Embedder, RegistryError};
+use tokenizers::{Encoding, PaddingParams, Tokenizer, TruncationParams};
+
+/// Max number of tokens of each doc, longer docs are truncated
+const MAX_TOKENS: usize = 256;
+
+/**
+ * Runs a sentence embedding model exported to ONNX on the local node, e.g. `all-MiniLM-L6-v2`,
+ * so entity docs never leave the registry.
+ * The model takes `input_ids`, `attention_mask` and `token_type_ids`, the sentence embedding is
+ * the mean of the token embeddings in the first output.
+ */
+#[derive(Clone)]
+pub struct OnnxEmbedder {
+    model: String,
+    session: Arc<Session>,
+    tokenizer: Arc<Tokenizer>,
+}
+
+impl Debug for OnnxEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnnxEmbedder")
+            .field("model", &self.model)
+            .finish()
+    }
+}
+
+fn onnx_error<E: Display>(e: E) -> RegistryError {
+    RegistryError::EmbeddingError(format!("ONNX embedder failed, {}", e))
+}
+
+impl OnnxEmbedder {
+    /**
+     * Load the model and the `tokenizer.json` of the Hugging Face tokenizer it was trained with
+     */
+    pub fn new<P, T>(model_path: P, tokenizer_path: T) -> Result<Self, RegistryError>
+    where
+        P: AsRef<Path>,
+        T: AsRef<Path>,
+    {
+        let model_path = model_path.as_ref();
+        let environment = Arc::new(
+            Environment::builder()
+                .with_name("feathr-registry")
+                .build()
+                .map_err(onnx_error)?,
+        );
+        let session = SessionBuilder::new(&environment)
+            .and_then(|b| b.with_optimization_level(GraphOptimizationLevel::Level1))
+            .and_then(|b| b.with_model_from_file(model_path))
+            .map_err(onnx_error)?;
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path).map_err(onnx_error)?;
+        // Docs in the same batch are padded to the longest one
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer.with_truncation(Some(TruncationParams {
+            max_length: MAX_TOKENS,
+            ..Default::default()
+        }));
+        Ok(Self {
+            model: model_path.display().to_string(),
+            session: Arc::new(session),
+            tokenizer: Arc::new(tokenizer),
+        })
+    }
+
+    fn embed_blocking(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, RegistryError> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts, true)
+            .map_err(onnx_error)?;
+        let shape = (
+            encodings.len(),
+            encodings.first().map(|e| e.len()).unwrap_or_default(),
+        );
+        let input = |values: fn(&Encoding) -> &[u32]| {
+            Array2::from_shape_vec(
+                shape,
+                encodings
+                    .iter()
+                    .flat_map(|e| values(e).iter().map(|v| *v as i64))
+                    .collect(),
+            )
+            .map(|a| CowArray::from(a.into_dyn()))
+            .map_err(onnx_error)
+        };
+        let inputs: Vec<CowArray<i64, IxDyn>> = vec![
+            input(Encoding::get_ids)?,
+            input(Encoding::get_attention_mask)?,
+            input(Encoding::get_type_ids)?,
+        ];
+        let allocator = self.session.allocator();
+        let outputs = self
+            .session
+            .run(
+                inputs
+                    .iter()
+                    .map(|a| Value::from_array(allocator, a))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(onnx_error)?,
+            )
+            .map_err(onnx_error)?;
+        let tokens: OrtOwnedTensor<f32, _> = outputs
+            .first()
+            .ok_or_else(|| onnx_error("the model has no output"))?
+            .try_extract()
+            .map_err(onnx_error)?;
+        let tokens = tokens.view();
+        let dim = tokens.shape().last().copied().unwrap_or_default();
+        // Mean pooling of the tokens that are not padding
+        Ok(tokens
+            .outer_iter()
+            .zip(encodings.iter())
+            .map(|(doc, encoding)| {
+                let mut sum = vec![0.0; dim];
+                let mut count = 0.0;
+                for (token, mask) in doc.outer_iter().zip(encoding.get_attention_mask()) {
+                    if *mask == 0 {
+                        continue;
+                    }
+                    for (s, v) in sum.iter_mut().zip(token.iter()) {
+                        *s += v;
+                    }
+                    count += 1.0;
+                }
+                sum.into_iter().map(|s| s / f32::max(count, 1.0)).collect()
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Embedder for OnnxEmbedder {
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, RegistryError> {
+        let embedder = self.clone();
+        let texts = texts.to_vec();
+        // Inference is CPU bound
+        tokio::task::spawn_blocking(move || embedder.embed_blocking(texts))
+            .await
+            .map_err(onnx_error)?
+    }
+}
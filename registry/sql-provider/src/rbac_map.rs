@@ -72,6 +72,20 @@ impl RbacMap {
             .unwrap_or(false)
     }
 
+    /**
+     * Permission granted on any of the scopes applies to the resource
+     */
+    pub fn check_scoped_permission(
+        &self,
+        credential: &Credential,
+        scopes: &[Resource],
+        permission: Permission,
+    ) -> bool {
+        scopes
+            .iter()
+            .any(|scope| self.check_permission(credential, scope, permission))
+    }
+
     pub fn grant_permission(&mut self, grant: &RbacRecord) {
         self.map
             .entry(grant.credential.clone())
@@ -16,10 +16,11 @@ where
     where
         S: serde::Serializer,
     {
-        let mut entity = serializer.serialize_struct("Registry", 3)?;
+        let mut entity = serializer.serialize_struct("Registry", 4)?;
         entity.serialize_field("graph", &self.graph)?;
         entity.serialize_field("deleted", &self.deleted)?;
         entity.serialize_field("permission_map", &self.permission_map.iter().collect::<Vec<_>>())?;
+        entity.serialize_field("deleted_at", &self.deleted_at)?;
         entity.end()
     }
 }
@@ -46,6 +47,7 @@ EntityProp: Clone
             Graph,
             Deleted,
             PermissionMap,
+            DeletedAt,
         }
         struct RegistryVisitor<EntityProp> {
             _t1: std::marker::PhantomData<EntityProp>,
@@ -82,8 +84,10 @@ EntityProp: Clone
                 let permission_map = seq
                     .next_element()?
                     .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                // Snapshots taken before deletion times were recorded don't have the field
+                let deleted_at = seq.next_element()?.unwrap_or_default();
             Ok(Registry::<EntityProp>::from_content(
-                    graph, deleted, permission_map,
+                    graph, deleted, deleted_at, permission_map,
                 ))
             }
 
@@ -94,6 +98,7 @@ EntityProp: Clone
                 let mut graph = None;
                 let mut deleted = None;
                 let mut permission_map = None;
+                let mut deleted_at = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Graph => {
@@ -114,18 +119,24 @@ EntityProp: Clone
                             }
                             permission_map = Some(map.next_value()?);
                         }
+                        Field::DeletedAt => {
+                            if deleted_at.is_some() {
+                                return Err(de::Error::duplicate_field("deleted_at"));
+                            }
+                            deleted_at = Some(map.next_value()?);
+                        }
                     }
                 }
                 let graph = graph.ok_or_else(|| de::Error::missing_field("graph"))?;
                 let deleted = deleted.ok_or_else(|| de::Error::missing_field("deleted"))?;
                 let permission_map = permission_map.ok_or_else(|| de::Error::missing_field("permission_map"))?;
                 Ok(Registry::<EntityProp>::from_content(
-                    graph, deleted, permission_map,
+                    graph, deleted, deleted_at.unwrap_or_default(), permission_map,
                 ))
             }
         }
 
-        const FIELDS: &[&str] = &["graph", "deleted", "permission_map"];
+        const FIELDS: &[&str] = &["graph", "deleted", "permission_map", "deleted_at"];
         deserializer.deserialize_struct(
             "Registry",
             FIELDS,